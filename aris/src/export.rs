@@ -0,0 +1,16 @@
+/*!
+Writes proofs in formats for reading outside of Aris, such as handouts and homework write-ups.
+*/
+
+/// graph writes the dependencies between a proof's lines as a Graphviz graph
+pub mod graph;
+
+/// latex typesets a proof in LaTeX, in the Fitch style of the `logicproof` package
+pub mod latex;
+
+/// text writes a proof as a plain text or Markdown table, like the one in the web app
+pub mod text;
+
+/// submission seals an assigned proof with an instructor's key, to check that a submission of it kept its premises and goals
+#[cfg(feature = "seal")]
+pub mod submission;
@@ -0,0 +1,203 @@
+/*!
+Writes the dependencies between the lines of a proof as a graph in Graphviz's DOT language, for drawing the structure of a proof.
+
+Each line is a node labeled with its number and formula, and each subproof is a cluster around its lines.
+A step has an edge to each line it cites, and a dashed edge to each subproof it cites, which ends at the subproof's cluster.
+Formulas longer than `MAX_LABEL_CHARS` are cut short, so that the nodes stay small enough to lay out.
+
+# Example
+```
+#[macro_use] extern crate frunk_core;
+use aris::deps;
+use aris::export::graph::proof_to_dot;
+use aris::expr::Expr;
+use aris::proofs::builder::ProofBuilder;
+use aris::proofs::pooledproof::PooledProof;
+use aris::rules::RuleM;
+
+let prf: PooledProof<HList![Expr]> = ProofBuilder::new().premise("A & B").step("A", RuleM::AndElim, deps![1]).build().unwrap();
+let dot = proof_to_dot(&prf);
+assert!(dot.contains("  l1 [label=\"1: (A ∧ B)\"];\n"));
+assert!(dot.contains("  l2 -> l1;\n"));
+```
+*/
+
+use crate::proofs::tree::TreeLine;
+use crate::proofs::tree::TreeProof;
+use crate::proofs::Proof;
+
+/// The most characters of a formula that a node's label shows
+pub const MAX_LABEL_CHARS: usize = 40;
+
+/// Escape text for a double-quoted DOT string, where backslashes also start
+/// Graphviz's label escapes such as `\n`
+fn escape_dot(text: &str) -> String {
+    text.chars().fold(String::new(), |mut escaped, c| {
+        match c {
+            '"' | '\\' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            '\n' => escaped.push_str("\\n"),
+            _ => escaped.push(c),
+        }
+        escaped
+    })
+}
+
+/// Cut a formula short at `MAX_LABEL_CHARS` characters, ending it with an ellipsis
+fn truncate(formula: String) -> String {
+    if formula.chars().count() <= MAX_LABEL_CHARS {
+        return formula;
+    }
+    formula.chars().take(MAX_LABEL_CHARS - 1).chain(std::iter::once('…')).collect()
+}
+
+fn add_nodes(tree: &TreeProof, depth: usize, line: &mut usize, nodes: &mut String, edges: &mut String) {
+    let indent = "  ".repeat(depth + 1);
+    let node = |line: usize, formula: String, nodes: &mut String| *nodes += &format!("{indent}l{line} [label=\"{}\"];\n", escape_dot(&format!("{line}: {}", truncate(formula))));
+    for premise in tree.premises.iter() {
+        *line += 1;
+        node(*line, format!("{premise}"), nodes);
+    }
+    for tree_line in tree.lines.iter() {
+        match tree_line {
+            TreeLine::Step { expr, deps, sdeps, .. } => {
+                *line += 1;
+                node(*line, format!("{expr}"), nodes);
+                for dep in deps.iter() {
+                    *edges += &format!("  l{line} -> l{dep};\n");
+                }
+                for span in sdeps.iter() {
+                    *edges += &format!("  l{line} -> l{} [lhead=cluster_{}, style=dashed];\n", span.start(), span.start());
+                }
+            }
+            // An empty subproof has no lines to draw a cluster around
+            TreeLine::Subproof(sub) if sub.premises.is_empty() && sub.lines.is_empty() => {}
+            TreeLine::Subproof(sub) => {
+                *nodes += &format!("{indent}subgraph cluster_{} {{\n", *line + 1);
+                add_nodes(sub, depth + 1, line, nodes, edges);
+                *nodes += &format!("{indent}}}\n");
+            }
+        }
+    }
+}
+
+/// Write the dependency graph of a proof in the DOT language
+pub fn proof_to_dot<P: Proof>(prf: &P) -> String {
+    let (mut nodes, mut edges) = (String::new(), String::new());
+    add_nodes(&TreeProof::from_proof(prf), 0, &mut 0, &mut nodes, &mut edges);
+    format!("digraph proof {{\n  compound=true;\n  node [shape=box];\n{nodes}{edges}}}\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::deps;
+    use crate::expr::Expr;
+    use crate::proofs::builder::ProofBuilder;
+    use crate::proofs::pooledproof::PooledProof;
+    use crate::rules::RuleM;
+
+    use frunk_core::HList;
+
+    type P = PooledProof<HList![Expr]>;
+
+    /// A token of the DOT language, as far as `proof_to_dot` uses it
+    #[derive(Debug, PartialEq, Eq)]
+    enum Token {
+        Id(String),
+        Quoted(String),
+        Punct(char),
+        Arrow,
+    }
+
+    /// Split DOT into tokens, failing on a string that isn't closed or a
+    /// character that can't start a token
+    fn tokenize(dot: &str) -> Result<Vec<Token>, String> {
+        let mut tokens = vec![];
+        let mut chars = dot.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                c if c.is_whitespace() => {}
+                '{' | '}' | '[' | ']' | ';' | ',' | '=' => tokens.push(Token::Punct(c)),
+                '-' if chars.next_if_eq(&'>').is_some() => tokens.push(Token::Arrow),
+                '"' => {
+                    let mut quoted = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('\\') => quoted.push(chars.next().ok_or("unfinished escape")?),
+                            Some('"') => break,
+                            Some(c) => quoted.push(c),
+                            None => return Err("unclosed string".into()),
+                        }
+                    }
+                    tokens.push(Token::Quoted(quoted));
+                }
+                c if c.is_alphanumeric() || c == '_' => {
+                    let mut id = c.to_string();
+                    while let Some(c) = chars.next_if(|c| c.is_alphanumeric() || *c == '_') {
+                        id.push(c);
+                    }
+                    tokens.push(Token::Id(id));
+                }
+                c => return Err(format!("unexpected {c:?}")),
+            }
+        }
+        Ok(tokens)
+    }
+
+    #[test]
+    fn test_nested_subproofs() {
+        /*
+        1 | P -> Q
+          | ---
+        2 | | ~Q
+          | | ---
+        3 | | | P
+          | | | ---
+        4 | | | Q ; ImpElim [1, 3]
+        5 | | | _|_ ; ContradictionIntro [2, 4]
+        6 | | ~P ; NotIntro [3..5]
+        7 | ~Q -> ~P ; ImpIntro [2..6]
+        */
+        let prf: P = ProofBuilder::new()
+            .premise("P -> Q")
+            .subproof(|b| b.premise("~Q").subproof(|b| b.premise("P").step("Q", RuleM::ImpElim, deps![1, 3]).step("_|_", RuleM::ContradictionIntro, deps![2, 4])).step("~P", RuleM::NotIntro, deps![; 3..=5]))
+            .step("~Q -> ~P", RuleM::ImpIntro, deps![; 2..=6])
+            .build()
+            .unwrap();
+        let dot = proof_to_dot(&prf);
+        assert_eq!(dot, include_str!("../../../example-proofs/golden/nested_subproofs.dot"));
+
+        // Braces and brackets balance, and there is an edge for each citation
+        let tokens = tokenize(&dot).unwrap();
+        assert_eq!(tokens[..3], [Token::Id("digraph".into()), Token::Id("proof".into()), Token::Punct('{')]);
+        let count = |c| tokens.iter().filter(|token| **token == Token::Punct(c)).count();
+        assert_eq!((count('{'), count('[')), (count('}'), count(']')));
+        assert_eq!(tokens.iter().filter(|token| **token == Token::Arrow).count(), 6);
+
+        // An empty subproof isn't drawn
+        let mut prf = prf;
+        prf.add_subproof();
+        assert_eq!(proof_to_dot(&prf), dot);
+    }
+
+    #[test]
+    fn test_escaping() {
+        let mut prf = P::new();
+        let name = "say\"}{\\\"\\n";
+        prf.add_premise(Expr::var(name));
+        prf.add_premise(Expr::var(&"x".repeat(100)));
+        let tokens = tokenize(&proof_to_dot(&prf)).unwrap();
+        let labels = tokens.iter().filter_map(|token| if let Token::Quoted(label) = token { Some(label.clone()) } else { None }).collect::<Vec<_>>();
+        assert_eq!(labels[0], format!("1: {name}"));
+        assert_eq!(labels[1].chars().count(), "2: ".len() + MAX_LABEL_CHARS);
+        assert!(labels[1].ends_with("x…"));
+
+        // The braces in the label are inside its string, so they don't open or close anything
+        assert_eq!(tokens.iter().filter(|token| matches!(token, Token::Punct('{' | '}'))).count(), 2);
+        assert_eq!(escape_dot("a\"b\\c\nd"), "a\\\"b\\\\c\\nd");
+    }
+}
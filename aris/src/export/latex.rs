@@ -0,0 +1,232 @@
+/*!
+Typesets a proof in LaTeX, in the Fitch style of the [`logicproof`](https://ctan.org/pkg/logicproof) package.
+
+`logicproof` numbers the lines itself, in the same order as `Proof::iter_lines`, and draws a scope line beside each subproof.
+Formulas are written with `Expr::to_latex`, and the right column has each step's rule and the lines it cites, with a cited subproof written as the range of lines it spans.
+With `LatexOptions::annotations`, each step's row ends with a TeX comment saying whether it verified.
+
+# Example
+```
+#[macro_use] extern crate frunk_core;
+use aris::deps;
+use aris::export::latex::{proof_to_latex, LatexOptions};
+use aris::expr::Expr;
+use aris::proofs::builder::ProofBuilder;
+use aris::proofs::pooledproof::PooledProof;
+use aris::rules::RuleM;
+
+let prf: PooledProof<HList![Expr]> = ProofBuilder::new().premise("A").step("A", RuleM::Reit, deps![1]).build().unwrap();
+let latex = proof_to_latex(&prf, &LatexOptions { standalone: false, annotations: None });
+assert_eq!(latex, "\\begin{logicproof}{0}\n  A & Premise\\\\\n  A & Reiteration 1\n\\end{logicproof}\n");
+```
+*/
+
+use crate::proofs::tree::TreeLine;
+use crate::proofs::tree::TreeProof;
+use crate::proofs::xml_interop::Annotations;
+use crate::proofs::ExtractError;
+use crate::proofs::PjRef;
+use crate::proofs::Proof;
+use crate::rules::RuleT;
+
+use std::collections::HashMap;
+
+/// How `proof_to_latex` writes a proof
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LatexOptions {
+    /// Whether to write a complete document that compiles on its own, rather
+    /// than only the `logicproof` environment, for pasting into another one
+    pub standalone: bool,
+    /// Verification results to note beside the steps they're for, which are
+    /// left out if they aren't for the proof being typeset
+    pub annotations: Option<Annotations>,
+}
+
+impl Default for LatexOptions {
+    fn default() -> Self {
+        LatexOptions { standalone: true, annotations: None }
+    }
+}
+
+/// A row of the `logicproof` environment, at the subproof depth `depth`
+enum Row {
+    Line { depth: usize, formula: String, justification: String, annotation: Option<Result<(), String>> },
+    Open { depth: usize },
+    Close { depth: usize },
+}
+
+/// Escape text for LaTeX text mode, writing logic symbols in math mode
+fn text_to_latex(text: &str) -> String {
+    text.chars()
+        .map(|c| match c {
+            '∧' => r"$\land$".into(),
+            '∨' => r"$\lor$".into(),
+            '↔' => r"$\leftrightarrow$".into(),
+            '≡' => r"$\equiv$".into(),
+            '→' => r"$\rightarrow$".into(),
+            '¬' => r"$\lnot$".into(),
+            '⊥' => r"$\bot$".into(),
+            '⊤' => r"$\top$".into(),
+            '∀' => r"$\forall$".into(),
+            '∃' => r"$\exists$".into(),
+            '#' | '$' | '%' | '&' | '_' | '{' | '}' => format!("\\{c}"),
+            c => c.to_string(),
+        })
+        .collect()
+}
+
+fn add_rows(tree: &TreeProof, depth: usize, annotations: &HashMap<usize, Result<(), String>>, line: &mut usize, rows: &mut Vec<Row>) {
+    let kind = if depth == 0 { "Premise" } else { "Assumption" };
+    for premise in tree.premises.iter() {
+        *line += 1;
+        rows.push(Row::Line { depth, formula: premise.to_latex(), justification: kind.into(), annotation: None });
+    }
+    for line in tree.lines.iter() {
+        match line {
+            TreeLine::Step { expr, rule, deps, sdeps } => {
+                *line += 1;
+                let citations = deps.iter().map(|dep| dep.to_string()).chain(sdeps.iter().map(|span| format!("{}--{}", span.start(), span.end()))).collect::<Vec<_>>();
+                let mut justification = text_to_latex(&rule.get_name());
+                if !citations.is_empty() {
+                    justification = format!("{justification} {}", citations.join(", "));
+                }
+                rows.push(Row::Line { depth, formula: expr.to_latex(), justification, annotation: annotations.get(line).cloned() });
+            }
+            TreeLine::Subproof(sub) => {
+                rows.push(Row::Open { depth });
+                add_rows(sub, depth + 1, annotations, line, rows);
+                // A subproof without any lines isn't drawn, since logicproof can't box nothing
+                if let Some(Row::Open { .. }) = rows.last() {
+                    rows.pop();
+                } else {
+                    rows.push(Row::Close { depth });
+                }
+            }
+        }
+    }
+}
+
+/// Typeset a proof as a `logicproof` environment, or as a document containing
+/// one if `opts.standalone` is set
+pub fn proof_to_latex<P: Proof>(prf: &P, opts: &LatexOptions) -> String {
+    let mut rows = vec![];
+    let annotations = opts.annotations.as_ref().map(|annotations| annotations.by_line_number(prf)).unwrap_or_default();
+    add_rows(&TreeProof::from_proof(prf), 0, &annotations, &mut 0, &mut rows);
+    let max_depth = rows.iter().filter_map(|row| if let Row::Close { depth } = row { Some(depth + 1) } else { None }).max().unwrap_or(0);
+
+    let mut out = String::new();
+    if opts.standalone {
+        out += "\\documentclass{article}\n\\usepackage{logicproof}\n\\begin{document}\n";
+    }
+    out += &format!("\\begin{{logicproof}}{{{max_depth}}}\n");
+    for (i, row) in rows.iter().enumerate() {
+        let (depth, text) = match row {
+            Row::Line { depth, formula, justification, .. } => (depth + 1, format!("{formula} & {justification}")),
+            Row::Open { depth } => (depth + 1, "\\begin{subproof}".into()),
+            Row::Close { depth } => (depth + 1, "\\end{subproof}".into()),
+        };
+        // Rows are separated by `\\`, except before the end of a subproof or
+        // of the proof, and after the end of a subproof, unless another one
+        // starts right after it
+        let separated = matches!((row, rows.get(i + 1)), (Row::Line { .. }, Some(Row::Line { .. } | Row::Open { .. })) | (Row::Close { .. }, Some(Row::Open { .. })));
+        let comment = match row {
+            Row::Line { annotation: Some(Ok(())), .. } => " % verified".into(),
+            Row::Line { annotation: Some(Err(message)), .. } => format!(" % error: {}", message.replace('\n', " ")),
+            _ => String::new(),
+        };
+        out += &format!("{}{text}{}{comment}\n", "  ".repeat(depth), if separated { "\\\\" } else { "" });
+    }
+    out += "\\end{logicproof}\n";
+    if opts.standalone {
+        out += "\\end{document}\n";
+    }
+    out
+}
+
+/// Typeset a subproof as a proof of its own, such as to share a lemma. Like
+/// `Proof::extract_subproof`, this fails if the subproof cites lines outside
+/// of it.
+pub fn subproof_to_latex<P: Proof>(prf: &P, sr: &P::SubproofReference, opts: &LatexOptions) -> Result<String, ExtractError<PjRef<P>, P::SubproofReference>> {
+    Ok(proof_to_latex(&prf.extract_subproof(sr)?, opts))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::deps;
+    use crate::expr::Expr;
+    use crate::proofs::builder::ProofBuilder;
+    use crate::proofs::pooledproof::PooledProof;
+    use crate::rules::RuleM;
+
+    use frunk_core::HList;
+
+    type P = PooledProof<HList![Expr]>;
+
+    #[test]
+    fn test_nested_subproofs() {
+        /*
+        1 | P -> Q
+          | ---
+        2 | | ~Q
+          | | ---
+        3 | | | P
+          | | | ---
+        4 | | | Q ; ImpElim [1, 3]
+        5 | | | _|_ ; ContradictionIntro [2, 4]
+        6 | | ~P ; NotIntro [3..5]
+        7 | ~Q -> ~P ; ImpIntro [2..6]
+        */
+        let prf: P = ProofBuilder::new()
+            .premise("P -> Q")
+            .subproof(|b| b.premise("~Q").subproof(|b| b.premise("P").step("Q", RuleM::ImpElim, deps![1, 3]).step("_|_", RuleM::ContradictionIntro, deps![2, 4])).step("~P", RuleM::NotIntro, deps![; 3..=5]))
+            .step("~Q -> ~P", RuleM::ImpIntro, deps![; 2..=6])
+            .build()
+            .unwrap();
+        let expected = include_str!("../../../example-proofs/golden/nested_subproofs.tex");
+        assert_eq!(proof_to_latex(&prf, &LatexOptions::default()), expected);
+
+        // Without the document around it, it's only the environment
+        let environment = proof_to_latex(&prf, &LatexOptions { standalone: false, annotations: None });
+        assert!(environment.starts_with("\\begin{logicproof}{2}\n"));
+        assert!(expected.contains(&environment));
+
+        // An empty subproof isn't drawn
+        let mut prf = prf;
+        prf.add_subproof();
+        assert_eq!(proof_to_latex(&prf, &LatexOptions { standalone: false, annotations: None }), environment);
+    }
+
+    #[test]
+    fn test_annotations() {
+        let prf: P = ProofBuilder::new().premise("A & B").step("A", RuleM::AndElim, deps![1]).step("C", RuleM::AndElim, deps![1]).build().unwrap();
+        let annotations = Annotations::from_verification(&prf);
+        let latex = proof_to_latex(&prf, &LatexOptions { standalone: false, annotations: Some(annotations.clone()) });
+        let lines = latex.lines().collect::<Vec<_>>();
+        assert_eq!(lines[1], "  (A \\land B) & Premise\\\\");
+        assert_eq!(lines[2], "  A & $\\land$ Elimination 1\\\\ % verified");
+        assert!(lines[3].starts_with("  C & $\\land$ Elimination 1 % error: "));
+
+        // They're left out once the proof has changed
+        let mut edited = prf.clone();
+        edited.add_premise(crate::parser::parse_unwrap("D"));
+        assert!(!proof_to_latex(&edited, &LatexOptions { standalone: false, annotations: Some(annotations) }).contains('%'));
+    }
+
+    #[test]
+    fn test_escaping() {
+        let prf: P = ProofBuilder::new().premise("is_red(x_1) & y").step("is_red(x_1)", RuleM::AndElim, deps![1]).build().unwrap();
+        let latex = proof_to_latex(&prf, &LatexOptions { standalone: false, annotations: None });
+        assert_eq!(latex, "\\begin{logicproof}{0}\n  (\\mathit{is\\_red}(\\mathit{x\\_1}) \\land y) & Premise\\\\\n  \\mathit{is\\_red}(\\mathit{x\\_1}) & $\\land$ Elimination 1\n\\end{logicproof}\n");
+        assert_eq!(text_to_latex("100% A_B & {C} ∀"), "100\\% A\\_B \\& \\{C\\} $\\forall$");
+    }
+
+    #[test]
+    fn test_subproof() {
+        let prf: P = ProofBuilder::new().premise("A").subproof(|b| b.premise("B").step("B", RuleM::Reit, deps![2])).step("B -> B", RuleM::ImpIntro, deps![; 2..=3]).build().unwrap();
+        let sub = *prf.lines()[0].get::<<P as Proof>::SubproofReference, _>().unwrap();
+        let opts = LatexOptions { standalone: false, annotations: None };
+        assert_eq!(subproof_to_latex(&prf, &sub, &opts), Ok("\\begin{logicproof}{0}\n  B & Premise\\\\\n  B & Reiteration 1\n\\end{logicproof}\n".into()));
+    }
+}
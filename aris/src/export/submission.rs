@@ -0,0 +1,213 @@
+/*!
+Seals a proof handed out as an assignment, so that a submission of it can be checked to have the premises, goals, and assignment it was given.
+
+`seal` saves a proof with an HMAC-SHA256 of its assignment id, its goals, its locked lines, and how many premises it has, keyed by a secret that only the instructor has.
+The seal is kept in the proof's metadata as it's edited and saved again, and `verify_seal` checks that what it covers hasn't changed since.
+Steps, and lines that aren't locked, aren't covered by the seal, so a student's work on the proof leaves it valid.
+
+Unlike the `submission_hash`, which anyone can recompute after editing a file, the seal can only be made with the instructor's key.
+*/
+
+use crate::proofs::xml_interop::proof_from_xml;
+use crate::proofs::xml_interop::xml_from_proof_and_metadata;
+use crate::proofs::xml_interop::ProofMetaData;
+use crate::proofs::xml_interop::XmlImportError;
+use crate::proofs::PjRef;
+use crate::proofs::Proof;
+
+use std::fmt::Write as _;
+
+use base64::Engine;
+use sha2::Digest;
+use sha2::Sha256;
+
+/// Whether a proof's seal matches its assignment, goals, and locked lines
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SealStatus {
+    /// The sealed parts of the proof are as the instructor gave them
+    Valid,
+    /// A locked line, goal, premise, or the assignment id was changed, or the
+    /// proof was sealed with a different key
+    LockedContentModified,
+    /// The proof has no seal
+    MissingSeal,
+}
+
+/// HMAC-SHA256, as in RFC 2104
+fn hmac_sha256(key: &[u8], message: &[u8]) -> [u8; 32] {
+    const BLOCK_SIZE: usize = 64;
+    let mut block = [0; BLOCK_SIZE];
+    if key.len() > BLOCK_SIZE {
+        block[..32].copy_from_slice(&Sha256::digest(key));
+    } else {
+        block[..key.len()].copy_from_slice(key);
+    }
+    let pad = |byte: u8| block.map(|b| b ^ byte);
+    let inner = Sha256::new().chain_update(pad(0x36)).chain_update(message).finalize();
+    let outer = Sha256::new().chain_update(pad(0x5c)).chain_update(inner).finalize();
+    let mut mac = [0; 32];
+    mac.copy_from_slice(&outer);
+    mac
+}
+
+/// Compare two MACs in time that doesn't depend on where they differ
+fn macs_equal(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+/// Write one part of the sealed content, with its value's length so that no
+/// value can pass for the parts after it, such as an assignment id with a
+/// newline and a goal in it
+pub(crate) fn write_field(content: &mut String, name: &str, value: &str) {
+    let _ = writeln!(content, "{name} {}:{value}", value.len());
+}
+
+/// The parts of a proof that its seal covers, written out to be MACed
+fn sealed_content<P: Proof>(prf: &P, meta: &ProofMetaData) -> String {
+    let mut content = String::new();
+    write_field(&mut content, "assignment", meta.assignment.as_deref().unwrap_or(""));
+    for goal in &meta.goals {
+        write_field(&mut content, "goal", &goal.to_canonical_string());
+    }
+    // Counting the premises keeps new ones from being added to prove the goals with
+    let _ = writeln!(content, "premises {}", prf.premises().len());
+    for id in &meta.locked {
+        match prf.ref_of_stable_id(*id).and_then(|r: PjRef<P>| prf.lookup_expr(&r)) {
+            Some(expr) => write_field(&mut content, &format!("locked {}", id.0), &expr.to_canonical_string()),
+            None => {
+                let _ = writeln!(content, "locked {} missing", id.0);
+            }
+        }
+    }
+    content
+}
+
+/// The seal of a proof made with `instructor_key`, as it's written in `ProofMetaData::seal`
+fn compute_seal<P: Proof>(prf: &P, meta: &ProofMetaData, instructor_key: &[u8]) -> String {
+    base64::engine::general_purpose::STANDARD.encode(hmac_sha256(instructor_key, sealed_content(prf, meta).as_bytes()))
+}
+
+/// Save a proof as XML, sealed with `instructor_key` over its assignment id,
+/// goals, locked lines, and number of premises
+pub fn seal<P: Proof>(prf: &P, meta: &ProofMetaData, instructor_key: &[u8]) -> Vec<u8> {
+    let meta = ProofMetaData { seal: Some(compute_seal(prf, meta, instructor_key)), ..meta.clone() };
+    let mut out = vec![];
+    xml_from_proof_and_metadata(prf, &meta, &mut out).expect("writing XML to memory doesn't fail");
+    out
+}
+
+/// Check the seal of a proof saved as XML against `instructor_key`
+pub fn verify_seal<P: Proof>(data: &[u8], instructor_key: &[u8]) -> Result<SealStatus, XmlImportError> {
+    let (prf, meta, _) = proof_from_xml::<P, _>(data)?;
+    let seal = match &meta.seal {
+        Some(seal) => seal,
+        None => return Ok(SealStatus::MissingSeal),
+    };
+    let expected = hmac_sha256(instructor_key, sealed_content(&prf, &meta).as_bytes());
+    match base64::engine::general_purpose::STANDARD.decode(seal) {
+        Ok(found) if macs_equal(&found, &expected) => Ok(SealStatus::Valid),
+        _ => Ok(SealStatus::LockedContentModified),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::expr::Expr;
+    use crate::parser::parse_unwrap as p;
+    use crate::proofs::pooledproof::PooledProof;
+    use crate::proofs::Justification;
+    use crate::rules::RuleM;
+
+    use frunk_core::coproduct::Coproduct;
+    use frunk_core::HList;
+
+    type P = PooledProof<HList![Expr]>;
+
+    const KEY: &[u8] = b"instructor secret";
+
+    fn hex(bytes: &[u8]) -> String {
+        bytes.iter().map(|b| format!("{b:02x}")).collect()
+    }
+
+    #[test]
+    fn test_hmac_sha256() {
+        // Test cases 2 and 6 of RFC 4231, the second with a key longer than a block
+        assert_eq!(hex(&hmac_sha256(b"Jefe", b"what do ya want for nothing?")), "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843");
+        assert_eq!(hex(&hmac_sha256(&[0xaa; 131], b"Test Using Larger Than Block-Size Key - Hash Key First")), "60e431591ee0b67f0d8a26aacbf5b77f8e0bc6213728c5140546040f0ee37f54");
+        assert!(macs_equal(b"abc", b"abc"));
+        assert!(!macs_equal(b"abc", b"abd"));
+        assert!(!macs_equal(b"abc", b"ab"));
+    }
+
+    /// An assignment to prove `B` from the locked premises `A -> B` and `A`
+    fn sealed_assignment() -> Vec<u8> {
+        let mut prf = P::new();
+        let r1 = prf.add_premise(p("A -> B"));
+        let r2 = prf.add_premise(p("A"));
+        let mut meta = ProofMetaData { assignment: Some("hw3-problem2".into()), goals: vec![p("B")], ..Default::default() };
+        for r in [r1, r2] {
+            meta.locked.insert(prf.stable_id_of(&Coproduct::inject(r)).unwrap());
+        }
+        seal(&prf, &meta, KEY)
+    }
+
+    /// Load a proof, change it, and save it again as the web app would, keeping its metadata
+    fn edit(data: &[u8], f: impl FnOnce(&mut P, &mut ProofMetaData)) -> Vec<u8> {
+        let (mut prf, mut meta, _) = proof_from_xml::<P, _>(data).unwrap();
+        f(&mut prf, &mut meta);
+        let mut out = vec![];
+        xml_from_proof_and_metadata(&prf, &meta, &mut out).unwrap();
+        out
+    }
+
+    #[test]
+    fn test_seal() {
+        let sealed = sealed_assignment();
+        assert_eq!(verify_seal::<P>(&sealed, KEY), Ok(SealStatus::Valid));
+        assert_eq!(verify_seal::<P>(&sealed, b"another key"), Ok(SealStatus::LockedContentModified));
+
+        // A student's steps don't affect the seal, however they're changed
+        let answered = edit(&sealed, |prf, _| {
+            let premises = prf.premises();
+            prf.add_step(Justification(p("B"), RuleM::ImpElim, vec![Coproduct::inject(premises[0]), Coproduct::inject(premises[1])], vec![]));
+        });
+        assert_eq!(verify_seal::<P>(&answered, KEY), Ok(SealStatus::Valid));
+        let reworded = edit(&answered, |prf, _| {
+            let step = *prf.lines()[0].get::<<P as Proof>::JustificationReference, _>().unwrap();
+            prf.with_mut_step(&step, |j| j.0 = p("B & B"));
+        });
+        assert_eq!(verify_seal::<P>(&reworded, KEY), Ok(SealStatus::Valid));
+
+        // Its locked premises, goals, and assignment id do
+        let tampered = edit(&answered, |prf, _| {
+            let premise = prf.premises()[1];
+            prf.with_mut_premise(&premise, |e| *e = p("B"));
+        });
+        assert_eq!(verify_seal::<P>(&tampered, KEY), Ok(SealStatus::LockedContentModified));
+        let unlocked = edit(&answered, |_, meta| meta.locked.clear());
+        assert_eq!(verify_seal::<P>(&unlocked, KEY), Ok(SealStatus::LockedContentModified));
+        let added = edit(&answered, |prf, _| {
+            prf.add_premise(p("B"));
+        });
+        assert_eq!(verify_seal::<P>(&added, KEY), Ok(SealStatus::LockedContentModified));
+        let regoaled = edit(&answered, |_, meta| meta.goals = vec![p("A")]);
+        assert_eq!(verify_seal::<P>(&regoaled, KEY), Ok(SealStatus::LockedContentModified));
+        let reassigned = edit(&answered, |_, meta| meta.assignment = Some("hw3-problem1".into()));
+        assert_eq!(verify_seal::<P>(&reassigned, KEY), Ok(SealStatus::LockedContentModified));
+        let garbled = edit(&answered, |_, meta| meta.seal = Some("not base64!".into()));
+        assert_eq!(verify_seal::<P>(&garbled, KEY), Ok(SealStatus::LockedContentModified));
+
+        // An assignment id can't be made to read as a goal
+        let prf = proof_from_xml::<P, _>(&sealed).unwrap().0;
+        let disguised = ProofMetaData { assignment: Some("hw3-problem2\ngoal B".into()), ..Default::default() };
+        let genuine = ProofMetaData { assignment: Some("hw3-problem2".into()), goals: vec![p("B")], ..Default::default() };
+        assert_ne!(sealed_content(&prf, &disguised), sealed_content(&prf, &genuine));
+        assert_ne!(compute_seal(&prf, &disguised, KEY), compute_seal(&prf, &genuine, KEY));
+
+        let unsealed = edit(&answered, |_, meta| meta.seal = None);
+        assert_eq!(verify_seal::<P>(&unsealed, KEY), Ok(SealStatus::MissingSeal));
+        assert!(verify_seal::<P>(b"<bram>", KEY).is_err());
+    }
+}
@@ -0,0 +1,253 @@
+/*!
+Writes a proof as text, laid out like the proof table of the web app, for pasting into emails, forums, and LMS discussions.
+
+Each line has its number, a bar for each subproof it's in, its formula, and, for steps, the rule and the lines it cites, with a cited subproof written as the range of lines it spans.
+`TextLayout::Plain` pads the columns to line up in a monospace font and draws a rule under each proof's premises, and `TextLayout::Markdown` writes a Markdown table.
+With `TextStyle::annotations`, there's another column saying whether each step verified.
+
+# Example
+```
+#[macro_use] extern crate frunk_core;
+use aris::deps;
+use aris::export::text::{proof_to_text, TextLayout, TextStyle};
+use aris::expr::Expr;
+use aris::proofs::builder::ProofBuilder;
+use aris::proofs::pooledproof::PooledProof;
+use aris::rules::RuleM;
+
+let prf: PooledProof<HList![Expr]> = ProofBuilder::new().premise("A & B").step("A", RuleM::AndElim, deps![1]).build().unwrap();
+assert_eq!(proof_to_text(&prf, TextStyle::default()), "1 | (A ∧ B)\n  | ---\n2 | A        ∧ Elimination  1\n");
+assert_eq!(proof_to_text(&prf, TextStyle { layout: TextLayout::Plain, ascii: true, ..Default::default() }), "1 | (A & B)\n  | ---\n2 | A        ∧ Elimination  1\n");
+```
+*/
+
+use crate::expr::Expr;
+use crate::proofs::tree::TreeLine;
+use crate::proofs::tree::TreeProof;
+use crate::proofs::xml_interop::Annotations;
+use crate::proofs::ExtractError;
+use crate::proofs::PjRef;
+use crate::proofs::Proof;
+use crate::rules::RuleT;
+
+use std::collections::HashMap;
+
+/// How `proof_to_text` lays out a proof
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum TextLayout {
+    /// Columns padded with spaces, for a monospace font
+    Plain,
+    /// A Markdown table
+    Markdown,
+}
+
+/// How `proof_to_text` writes a proof
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TextStyle {
+    pub layout: TextLayout,
+    /// Whether to write formulas with `Expr::to_ascii` rather than with the
+    /// logic symbols
+    pub ascii: bool,
+    /// Verification results to write beside the steps they're for, which are
+    /// left out if they aren't for the proof being written
+    pub annotations: Option<Annotations>,
+}
+
+impl Default for TextStyle {
+    fn default() -> Self {
+        TextStyle { layout: TextLayout::Plain, ascii: false, annotations: None }
+    }
+}
+
+/// A row of the table, at the subproof depth `depth`
+enum Row {
+    /// A premise or step. `rule`, `deps`, and `status` are empty for premises.
+    Line { line: usize, depth: usize, formula: String, rule: String, deps: String, status: String },
+    /// The rule under the premises of a proof
+    PremiseRule { depth: usize },
+}
+
+fn add_rows(tree: &TreeProof, depth: usize, ascii: bool, annotations: &HashMap<usize, Result<(), String>>, line: &mut usize, rows: &mut Vec<Row>) {
+    let show = |expr: &Expr| if ascii { expr.to_ascii() } else { format!("{expr}") };
+    for premise in tree.premises.iter() {
+        *line += 1;
+        rows.push(Row::Line { line: *line, depth, formula: show(premise), rule: String::new(), deps: String::new(), status: String::new() });
+    }
+    if !tree.premises.is_empty() {
+        rows.push(Row::PremiseRule { depth });
+    }
+    for tree_line in tree.lines.iter() {
+        match tree_line {
+            TreeLine::Step { expr, rule, deps, sdeps } => {
+                *line += 1;
+                let deps = deps.iter().map(|dep| dep.to_string()).chain(sdeps.iter().map(|span| format!("{}-{}", span.start(), span.end()))).collect::<Vec<_>>().join(", ");
+                let status = match annotations.get(line) {
+                    Some(Ok(())) => "verified".into(),
+                    Some(Err(message)) => format!("error: {}", message.replace('\n', " ")),
+                    None => String::new(),
+                };
+                rows.push(Row::Line { line: *line, depth, formula: show(expr), rule: rule.get_name(), deps, status });
+            }
+            TreeLine::Subproof(sub) => add_rows(sub, depth + 1, ascii, annotations, line, rows),
+        }
+    }
+}
+
+/// Escape the characters that Markdown gives a meaning to in a table cell
+fn escape_markdown(text: &str) -> String {
+    text.chars().fold(String::new(), |mut escaped, c| {
+        if matches!(c, '\\' | '|' | '*' | '_' | '`') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+        escaped
+    })
+}
+
+/// Write a proof as text in the layout and with the formulas of `style`
+pub fn proof_to_text<P: Proof>(prf: &P, style: TextStyle) -> String {
+    let mut rows = vec![];
+    let annotations = style.annotations.as_ref().map(|annotations| annotations.by_line_number(prf)).unwrap_or_default();
+    add_rows(&TreeProof::from_proof(prf), 0, style.ascii, &annotations, &mut 0, &mut rows);
+    let mut out = String::new();
+    match style.layout {
+        TextLayout::Plain => {
+            let bars = |depth: usize| "| ".repeat(depth + 1);
+            let number_width = rows.iter().filter_map(|row| if let Row::Line { line, .. } = row { Some(line.to_string().len()) } else { None }).max().unwrap_or(0);
+            let formula_width = rows.iter().filter_map(|row| if let Row::Line { depth, formula, .. } = row { Some(bars(*depth).chars().count() + formula.chars().count()) } else { None }).max().unwrap_or(0);
+            let rule_width = rows.iter().filter_map(|row| if let Row::Line { rule, .. } = row { Some(rule.chars().count()) } else { None }).max().unwrap_or(0);
+            let deps_width = rows.iter().filter_map(|row| if let Row::Line { deps, .. } = row { Some(deps.chars().count()) } else { None }).max().unwrap_or(0);
+            for row in rows.iter() {
+                let text = match row {
+                    Row::Line { line, depth, formula, rule, deps, status } => format!("{line:>number_width$} {:<formula_width$}  {rule:<rule_width$}  {deps:<deps_width$}  {status}", format!("{}{formula}", bars(*depth))),
+                    Row::PremiseRule { depth } => format!("{:number_width$} {}---", "", bars(*depth)),
+                };
+                out += text.trim_end();
+                out += "\n";
+            }
+        }
+        TextLayout::Markdown => {
+            let annotated = !annotations.is_empty();
+            out += "| Line | Formula | Rule | Dependencies |";
+            out += if annotated { " Status |\n| ---: | --- | --- | --- | --- |\n" } else { "\n| ---: | --- | --- | --- |\n" };
+            for row in rows.iter() {
+                if let Row::Line { line, depth, formula, rule, deps, status } = row {
+                    out += &format!("| {line} | {}{} | {} | {deps} |", "\\| ".repeat(*depth), escape_markdown(formula), escape_markdown(rule));
+                    if annotated {
+                        out += &format!(" {} |", escape_markdown(status));
+                    }
+                    out += "\n";
+                }
+            }
+        }
+    }
+    out
+}
+
+/// Write a subproof as a proof of its own, such as to share a lemma. Like
+/// `Proof::extract_subproof`, this fails if the subproof cites lines outside
+/// of it.
+pub fn subproof_to_text<P: Proof>(prf: &P, sr: &P::SubproofReference, style: TextStyle) -> Result<String, ExtractError<PjRef<P>, P::SubproofReference>> {
+    Ok(proof_to_text(&prf.extract_subproof(sr)?, style))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::deps;
+    use crate::proofs::builder::ProofBuilder;
+    use crate::proofs::pooledproof::PooledProof;
+    use crate::rules::RuleM;
+
+    use frunk_core::coproduct::Coproduct;
+    use frunk_core::HList;
+
+    type P = PooledProof<HList![Expr]>;
+
+    fn three_levels() -> P {
+        /*
+        1 | A | E
+          | ---
+        2 | | B
+          | | ---
+        3 | | | C
+          | | | ---
+        4 | | | | D
+          | | | | ---
+        5 | | | | A | E ; Reit [1]
+        6 | | | D -> A | E ; ImpIntro [4..5]
+        7 | | C -> (D -> A | E) ; ImpIntro [3..6]
+        8 | B -> (C -> (D -> A | E)) ; ImpIntro [2..7]
+        */
+        ProofBuilder::new()
+            .premise("A | E")
+            .subproof(|b| {
+                b.premise("B")
+                    .subproof(|b| b.premise("C").subproof(|b| b.premise("D").step("A | E", RuleM::Reit, deps![1])).step("D -> (A | E)", RuleM::ImpIntro, deps![; 4..=5]))
+                    .step("C -> (D -> (A | E))", RuleM::ImpIntro, deps![; 3..=6])
+            })
+            .step("B -> (C -> (D -> (A | E)))", RuleM::ImpIntro, deps![; 2..=7])
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_plain_alignment() {
+        let prf = three_levels();
+        assert_eq!(proof_to_text(&prf, TextStyle::default()), include_str!("../../../example-proofs/golden/three_levels.txt"));
+
+        // Every rule starts in the same column, however deep its line is
+        let ascii = proof_to_text(&prf, TextStyle { layout: TextLayout::Plain, ascii: true, ..Default::default() });
+        let rule_columns = ascii.lines().filter_map(|line| line.find("Reiteration").or_else(|| line.find("→ Introduction"))).collect::<Vec<_>>();
+        assert_eq!(rule_columns.len(), 4);
+        assert!(rule_columns.windows(2).all(|w| w[0] == w[1]));
+        assert!(ascii.contains("8 | (B -> (C -> (D -> (A | E))))  → Introduction  2-7\n"));
+    }
+
+    #[test]
+    fn test_markdown() {
+        let prf = three_levels();
+        let markdown = proof_to_text(&prf, TextStyle { layout: TextLayout::Markdown, ..Default::default() });
+        assert_eq!(markdown.lines().nth(2), Some("| 1 | (A ∨ E) |  |  |"));
+        assert_eq!(markdown.lines().nth(6), Some("| 5 | \\| \\| \\| (A ∨ E) | Reiteration | 1 |"));
+
+        // Every `|` in a cell is escaped, so that each row has the table's five column separators
+        let ascii = proof_to_text(&prf, TextStyle { layout: TextLayout::Markdown, ascii: true, ..Default::default() });
+        assert_eq!(ascii.lines().nth(6), Some("| 5 | \\| \\| \\| (A \\| E) | Reiteration | 1 |"));
+        assert!(ascii.lines().all(|line| line.matches('|').count() - line.matches("\\|").count() == 5));
+        assert_eq!(escape_markdown("_|_ * `x` \\"), "\\_\\|\\_ \\* \\`x\\` \\\\");
+    }
+
+    #[test]
+    fn test_annotations() {
+        let prf: P = ProofBuilder::new().premise("A & B").step("A", RuleM::AndElim, deps![1]).step("C", RuleM::AndElim, deps![1]).build().unwrap();
+        let style = |layout| TextStyle { layout, annotations: Some(Annotations::from_verification(&prf)), ..Default::default() };
+        let plain = proof_to_text(&prf, style(TextLayout::Plain));
+        let lines = plain.lines().collect::<Vec<_>>();
+        assert_eq!(lines[0], "1 | (A ∧ B)");
+        assert_eq!(lines[2], "2 | A        ∧ Elimination  1  verified");
+        assert!(lines[3].starts_with("3 | C        ∧ Elimination  1  error: "));
+
+        let markdown = proof_to_text(&prf, style(TextLayout::Markdown));
+        let lines = markdown.lines().collect::<Vec<_>>();
+        assert_eq!(lines[0], "| Line | Formula | Rule | Dependencies | Status |");
+        assert_eq!(lines[2], "| 1 | (A ∧ B) |  |  |  |");
+        assert_eq!(lines[3], "| 2 | A | ∧ Elimination | 1 | verified |");
+        assert!(lines[4].starts_with("| 3 | C | ∧ Elimination | 1 | error: "));
+    }
+
+    #[test]
+    fn test_subproof() {
+        // A subproof that only cites its own lines is written as a proof of its own
+        let prf: P = ProofBuilder::new().premise("A").subproof(|b| b.premise("B").step("B", RuleM::Reit, deps![2])).step("B -> B", RuleM::ImpIntro, deps![; 2..=3]).build().unwrap();
+        let sub = *prf.lines()[0].get::<<P as Proof>::SubproofReference, _>().unwrap();
+        assert_eq!(subproof_to_text(&prf, &sub, TextStyle::default()), Ok("1 | B\n  | ---\n2 | B  Reiteration  1\n".into()));
+
+        // One that cites a line outside of it isn't
+        let prf = three_levels();
+        let premise: PjRef<P> = Coproduct::inject(prf.premises()[0]);
+        let sub = *prf.lines()[0].get::<<P as Proof>::SubproofReference, _>().unwrap();
+        assert_eq!(subproof_to_text(&prf, &sub, TextStyle::default()), Err(ExtractError::EscapingDependency { lines: vec![Coproduct::inject(premise)] }));
+    }
+}
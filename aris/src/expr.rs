@@ -65,6 +65,16 @@ pub enum QuantKind {
     Exists,
 }
 
+/// Which symbols expressions are formatted with for display, by `Expr::display_with`
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Symbols {
+    /// Logic symbols like `∧` and `→`, as in `Display`
+    #[default]
+    Unicode,
+    /// The ASCII syntax of the parser, like `&` and `->`, as in `Expr::to_ascii`
+    Ascii,
+}
+
 /// A logical expression
 #[derive(Clone, Debug, PartialEq, Eq, Hash, Ord, PartialOrd, Serialize, Deserialize)]
 #[repr(C)]
@@ -225,6 +235,41 @@ impl fmt::Display for Expr {
     }
 }
 
+impl Op {
+    /// The LaTeX math mode symbol for the operator
+    pub fn to_latex(&self) -> &'static str {
+        match self {
+            Op::And => r"\land",
+            Op::Or => r"\lor",
+            Op::Bicon => r"\leftrightarrow",
+            Op::Equiv => r"\equiv",
+            Op::Add => "+",
+            Op::Mult => r"\cdot",
+        }
+    }
+}
+
+impl QuantKind {
+    /// The LaTeX math mode symbol for the quantifier
+    pub fn to_latex(&self) -> &'static str {
+        match self {
+            QuantKind::Forall => r"\forall",
+            QuantKind::Exists => r"\exists",
+        }
+    }
+}
+
+/// Format a variable name for LaTeX math mode, escaping underscores and
+/// setting names longer than a letter in italics so they don't read as products
+fn latex_name(name: &str) -> String {
+    let escaped = name.replace('_', r"\_");
+    if name.chars().count() == 1 {
+        escaped
+    } else {
+        format!(r"\mathit{{{escaped}}}")
+    }
+}
+
 impl fmt::Display for NnfExpr {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
@@ -336,6 +381,60 @@ pub fn subst(expr: Expr, var_to_replace: &str, replacement: Expr) -> Expr {
     }
 }
 
+/// Replace every occurrence of a subexpression with another expression,
+/// returning the result and the number of occurrences replaced.
+///
+/// An occurrence is only replaced if the free variables in it are free in
+/// `expr`, so `P(a)` isn't replaced in `∀a, P(a)`. Like `subst`, this is
+/// capture-avoiding: a quantified variable is renamed if replacing something
+/// in its body would otherwise bind a free variable of `replacement`.
+///
+/// ## Parameters
+///   * `expr` - expression to search for occurrences of `target`
+///   * `target` - subexpression to be replaced
+///   * `replacement` - expression that replaces each occurrence
+pub fn replace_subexpr(expr: Expr, target: &Expr, replacement: &Expr) -> (Expr, usize) {
+    if expr == *target {
+        return (replacement.clone(), 1);
+    }
+    let mut count = 0;
+    let mut aux = |e: Expr| {
+        let (e, n) = replace_subexpr(e, target, replacement);
+        count += n;
+        e
+    };
+    let ret = match expr {
+        Expr::Contra => Expr::Contra,
+        Expr::Taut => Expr::Taut,
+        Expr::Var { name } => Expr::Var { name },
+        Expr::Apply { func, args } => Expr::Apply { func: Box::new(aux(*func)), args: args.into_iter().map(aux).collect() },
+        Expr::Not { operand } => Expr::Not { operand: Box::new(aux(*operand)) },
+        Expr::Impl { left, right } => Expr::Impl { left: Box::new(aux(*left)), right: Box::new(aux(*right)) },
+        Expr::Assoc { op, exprs } => Expr::Assoc { op, exprs: exprs.into_iter().map(aux).collect() },
+        Expr::Quant { kind, name, body } => {
+            if free_vars(target).contains(&name) {
+                // Occurrences in the body refer to this quantifier's variable,
+                // not the free one being replaced
+                return (Expr::Quant { kind, name, body }, 0);
+            }
+            let (new_body, n) = replace_subexpr((*body).clone(), target, replacement);
+            if n > 0 && free_vars(replacement).contains(&name) {
+                // Capture-avoidance behavior, rename the quantified variable
+                // before replacing so that it doesn't bind the replacement's
+                let avoid = &(&free_vars(replacement) | &free_vars(&body)) | &free_vars(target);
+                let old_name = name;
+                let name = gen_var(&old_name, &avoid);
+                let body = subst(*body, &old_name, Expr::var(&name));
+                let (body, n) = replace_subexpr(body, target, replacement);
+                return (Expr::Quant { kind, name, body: Box::new(body) }, n);
+            }
+            count += n;
+            Expr::Quant { kind, name, body: Box::new(new_body) }
+        }
+    };
+    (ret, count)
+}
+
 /// Constraints that should hold for a substitution, maintained in a set during unification
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub enum Constraint {
@@ -407,6 +506,62 @@ pub fn unify(mut c: HashSet<Constraint>) -> Option<Substitution> {
     }
 }
 
+/// Finds the terms that the variables in `vars` have to be simultaneously substituted with to turn `pattern` into `target`.
+/// Unlike `unify`, only `vars` may be substituted, and a substitution that would capture a variable bound inside `pattern` is rejected.
+/// Variables that don't occur free in `pattern` don't appear in the result.
+///
+/// On failure, the error message names the variable that couldn't be matched, if there is one.
+pub fn match_instantiation(pattern: &Expr, target: &Expr, vars: &[String]) -> Result<HashMap<String, Expr>, String> {
+    fn aux(pattern: &Expr, target: &Expr, vars: &[String], binders: &mut Vec<String>, bindings: &mut HashMap<String, Expr>) -> Result<(), Option<String>> {
+        match (pattern, target) {
+            (Expr::Var { name }, _) if vars.contains(name) && !binders.contains(name) => {
+                if free_vars(target).iter().any(|v| binders.contains(v)) {
+                    return Err(Some(format!("{name} can't be instantiated with {target}, since a variable in it would be captured by a quantifier.")));
+                }
+                match bindings.get(name) {
+                    Some(previous) if previous != target => Err(Some(format!("The variable {name} can't be matched consistently, it would have to be both {previous} and {target}."))),
+                    Some(_) => Ok(()),
+                    None => {
+                        bindings.insert(name.clone(), target.clone());
+                        Ok(())
+                    }
+                }
+            }
+            (Expr::Contra, Expr::Contra) | (Expr::Taut, Expr::Taut) => Ok(()),
+            (Expr::Var { name: n1 }, Expr::Var { name: n2 }) if n1 == n2 => Ok(()),
+            (Expr::Apply { func: f1, args: a1 }, Expr::Apply { func: f2, args: a2 }) if a1.len() == a2.len() => {
+                aux(f1, f2, vars, binders, bindings)?;
+                a1.iter().zip(a2.iter()).try_for_each(|(x, y)| aux(x, y, vars, binders, bindings))
+            }
+            (Expr::Not { operand: o1 }, Expr::Not { operand: o2 }) => aux(o1, o2, vars, binders, bindings),
+            (Expr::Impl { left: l1, right: r1 }, Expr::Impl { left: l2, right: r2 }) => {
+                aux(l1, l2, vars, binders, bindings)?;
+                aux(r1, r2, vars, binders, bindings)
+            }
+            (Expr::Assoc { op: o1, exprs: e1 }, Expr::Assoc { op: o2, exprs: e2 }) if o1 == o2 && e1.len() == e2.len() => e1.iter().zip(e2.iter()).try_for_each(|(x, y)| aux(x, y, vars, binders, bindings)),
+            (Expr::Quant { kind: k1, name: n1, body: b1 }, Expr::Quant { kind: k2, name: n2, body: b2 }) if k1 == k2 => {
+                // compare the bodies up to renaming of the binder, by substituting the same fresh variable for both binders
+                let mut avoid: HashSet<String> = free_vars(pattern).union(&free_vars(target)).cloned().collect();
+                avoid.extend(vars.iter().cloned());
+                avoid.extend(binders.iter().cloned());
+                let fresh = gen_var("__match_var", &avoid);
+                let (b1, b2) = (subst(*b1.clone(), n1, Expr::var(&fresh)), subst(*b2.clone(), n2, Expr::var(&fresh)));
+                binders.push(fresh);
+                let result = aux(&b1, &b2, vars, binders, bindings);
+                binders.pop();
+                result
+            }
+            _ => Err(None),
+        }
+    }
+    let mut bindings = HashMap::new();
+    match aux(pattern, target, vars, &mut vec![], &mut bindings) {
+        Ok(()) => Ok(bindings),
+        Err(Some(msg)) => Err(msg),
+        Err(None) => Err(format!("{target} is not an instance of {pattern}.")),
+    }
+}
+
 /*
 Note apply_non_literal
 
@@ -470,6 +625,110 @@ impl Expr {
     pub fn exists(name: &str, body: Expr) -> Expr {
         Expr::Quant { kind: QuantKind::Exists, name: name.into(), body: Box::new(body) }
     }
+    /// Format the expression in LaTeX math mode, parenthesized the same way as
+    /// its `Display`
+    ///
+    /// ```rust
+    /// use aris::parser::parse_unwrap as p;
+    ///
+    /// assert_eq!(p("~A -> (B & C)").to_latex(), r"(\lnot A \rightarrow (B \land C))");
+    /// assert_eq!(p("forall x is_red(x)").to_latex(), r"(\forall x, \mathit{is\_red}(x))");
+    /// ```
+    pub fn to_latex(&self) -> String {
+        match self {
+            Expr::Contra => r"\bot".into(),
+            Expr::Taut => r"\top".into(),
+            Expr::Var { name } => latex_name(name),
+            Expr::Apply { func, args } => format!("{}({})", func.to_latex(), args.iter().map(Expr::to_latex).join(", ")),
+            Expr::Not { operand } => format!(r"\lnot {}", operand.to_latex()),
+            Expr::Impl { left, right } => format!(r"({} \rightarrow {})", left.to_latex(), right.to_latex()),
+            Expr::Assoc { op, exprs } => format!("({})", exprs.iter().map(Expr::to_latex).join(&format!(" {} ", op.to_latex()))),
+            Expr::Quant { kind, name, body } => format!("({} {}, {})", kind.to_latex(), latex_name(name), body.to_latex()),
+        }
+    }
+    /// Format the expression in the ASCII syntax of the parser, parenthesized
+    /// the same way as its `Display`, so that it can be parsed back
+    ///
+    /// ```rust
+    /// use aris::parser::parse_unwrap as p;
+    ///
+    /// assert_eq!(p("~A -> (B | _|_)").to_ascii(), "(~A -> (B | _|_))");
+    /// assert_eq!(p(&p("forall x P(x, y)").to_ascii()), p("forall x P(x, y)"));
+    /// ```
+    pub fn to_ascii(&self) -> String {
+        match self {
+            Expr::Contra => "_|_".into(),
+            Expr::Taut => "^|^".into(),
+            Expr::Var { name } => name.clone(),
+            Expr::Apply { func, args } => format!("{}({})", func.to_ascii(), args.iter().map(Expr::to_ascii).join(", ")),
+            Expr::Not { operand } => format!("~{}", operand.to_ascii()),
+            Expr::Impl { left, right } => format!("({} -> {})", left.to_ascii(), right.to_ascii()),
+            Expr::Assoc { op, exprs } => {
+                let op = match op {
+                    Op::And => "&",
+                    Op::Or => "|",
+                    Op::Bicon => "<->",
+                    Op::Equiv => "===",
+                    Op::Add => "+",
+                    Op::Mult => "*",
+                };
+                format!("({})", exprs.iter().map(Expr::to_ascii).join(&format!(" {op} ")))
+            }
+            Expr::Quant { kind: QuantKind::Forall, name, body } => format!("(forall {name} {})", body.to_ascii()),
+            Expr::Quant { kind: QuantKind::Exists, name, body } => format!("(exists {name} {})", body.to_ascii()),
+        }
+    }
+    /// Format the expression for display with `symbols`. Either way, the
+    /// parser reads it back.
+    ///
+    /// ```rust
+    /// use aris::expr::Symbols;
+    /// use aris::parser::parse_unwrap as p;
+    ///
+    /// assert_eq!(p("A & B -> ~C").display_with(Symbols::Unicode), "((A ∧ B) → ¬C)");
+    /// assert_eq!(p("A & B -> ~C").display_with(Symbols::Ascii), "((A & B) -> ~C)");
+    /// ```
+    pub fn display_with(&self, symbols: Symbols) -> String {
+        match symbols {
+            Symbols::Unicode => self.to_string(),
+            Symbols::Ascii => self.to_ascii(),
+        }
+    }
+    /// Format the expression in the syntax that files are saved in: Unicode
+    /// symbols, with every operation other than negation and application
+    /// parenthesized. Unlike `Display`, which may change to read better, this
+    /// is fixed, since saved proofs and their hashes depend on it. The parser
+    /// reads it back, along with the ASCII syntax of older files.
+    ///
+    /// ```rust
+    /// use aris::parser::parse_unwrap as p;
+    ///
+    /// assert_eq!(p("~A -> (B | _|_)").to_canonical_string(), "(¬A → (B ∨ ⊥))");
+    /// assert_eq!(p("forall x P(x, y)").to_canonical_string(), "(∀ x P(x, y))");
+    /// ```
+    pub fn to_canonical_string(&self) -> String {
+        match self {
+            Expr::Contra => "⊥".into(),
+            Expr::Taut => "⊤".into(),
+            Expr::Var { name } => name.clone(),
+            Expr::Apply { func, args } => format!("{}({})", func.to_canonical_string(), args.iter().map(Expr::to_canonical_string).join(", ")),
+            Expr::Not { operand } => format!("¬{}", operand.to_canonical_string()),
+            Expr::Impl { left, right } => format!("({} → {})", left.to_canonical_string(), right.to_canonical_string()),
+            Expr::Assoc { op, exprs } => {
+                let op = match op {
+                    Op::And => "∧",
+                    Op::Or => "∨",
+                    Op::Bicon => "↔",
+                    Op::Equiv => "≡",
+                    Op::Add => "+",
+                    Op::Mult => "*",
+                };
+                format!("({})", exprs.iter().map(Expr::to_canonical_string).join(&format!(" {op} ")))
+            }
+            Expr::Quant { kind: QuantKind::Forall, name, body } => format!("(∀ {name} {})", body.to_canonical_string()),
+            Expr::Quant { kind: QuantKind::Exists, name, body } => format!("(∃ {name} {})", body.to_canonical_string()),
+        }
+    }
     /// Infer arities (number of arguments) for each variable that occurs free in an expression
     pub fn infer_arities(&self, arities: &mut HashMap<String, usize>) {
         match self {
@@ -779,6 +1038,38 @@ impl Expr {
         })
     }
 
+    /// Strip every pair of directly nested negations, at any depth:
+    /// ~~P -> P
+    /// Odd runs keep exactly one negation (~~~P -> ~P), so two expressions have
+    /// the same normal form iff they differ only by inserting/removing pairs of ~
+    pub fn normalize_double_negation(self) -> Expr {
+        self.transform(&|expr| match expr {
+            Expr::Not { operand } => match *operand {
+                Expr::Not { operand: inner } => (*inner, true),
+                operand => (Expr::not(operand), false),
+            },
+            _ => (expr, false),
+        })
+    }
+
+    /// Flatten a biconditional in the leftmost position of another biconditional into one chain:
+    /// (A <-> B) <-> C -> A <-> B <-> C
+    /// A chain `A <-> B <-> C` associates to the left, so rules compare biconditionals in this form
+    pub fn normalize_bicon_chains(self) -> Expr {
+        self.transform(&|expr| match expr {
+            Expr::Assoc { op: Op::Bicon, exprs } if matches!(exprs.first(), Some(Expr::Assoc { op: Op::Bicon, .. })) => {
+                let mut exprs = exprs.into_iter();
+                let mut chain = match exprs.next() {
+                    Some(Expr::Assoc { exprs: inner, .. }) => inner,
+                    _ => unreachable!(),
+                };
+                chain.extend(exprs);
+                (Expr::Assoc { op: Op::Bicon, exprs: chain }, true)
+            }
+            _ => (expr, false),
+        })
+    }
+
     /// Reduce an expression over idempotence, that is:
     /// A & A -> A
     /// A | A -> A
@@ -1300,15 +1591,44 @@ impl NnfExpr {
     /// assert_eq!(NnfExpr::var("A").into_cnf(), CnfExpr::var("A"));
     /// ```
     pub fn into_cnf(self) -> CnfExpr {
-        // Make an iterator over the CNF conversions of NNF expressions
-        fn map_cnf(exprs: Vec<NnfExpr>) -> impl Iterator<Item = CnfExpr> {
-            exprs.into_iter().map(NnfExpr::into_cnf)
+        match self.try_into_cnf(&mut |_| Ok::<(), std::convert::Infallible>(())) {
+            Ok(cnf) => cnf,
+            Err(never) => match never {},
         }
+    }
 
+    /// Like [`into_cnf`](NnfExpr::into_cnf), calling `charge` with the number
+    /// of clauses each subexpression expands to before building them, and
+    /// stopping at the first error it returns. Distributing ORs can multiply
+    /// the number of clauses, so this lets a caller give up on a conversion
+    /// that would blow up.
+    ///
+    /// ```rust
+    /// use aris::parser::parse_unwrap as p;
+    ///
+    /// let mut spent = 0;
+    /// let cnf = p("(A & B) | (C & D)").into_nnf().unwrap().try_into_cnf(&mut |cost| {
+    ///     spent += cost;
+    ///     if spent <= 8 { Ok(()) } else { Err(()) }
+    /// });
+    /// assert_eq!(cnf, Ok(p("(A & B) | (C & D)").into_cnf().unwrap()));
+    /// assert_eq!(p("(A & B) | (C & D) | (E & F)").into_nnf().unwrap().try_into_cnf(&mut |cost| if cost <= 4 { Ok(()) } else { Err(()) }), Err(()));
+    /// ```
+    pub fn try_into_cnf<E, F: FnMut(u64) -> Result<(), E>>(self, charge: &mut F) -> Result<CnfExpr, E> {
         match self {
-            NnfExpr::Lit { polarity, name } => CnfExpr::literal(polarity, name),
-            NnfExpr::And { exprs } => CnfExpr::and(map_cnf(exprs)),
-            NnfExpr::Or { exprs } => CnfExpr::or(map_cnf(exprs)),
+            NnfExpr::Lit { polarity, name } => {
+                charge(1)?;
+                Ok(CnfExpr::literal(polarity, name))
+            }
+            NnfExpr::And { exprs } => {
+                let exprs = exprs.into_iter().map(|expr| expr.try_into_cnf(&mut *charge)).collect::<Result<Vec<_>, _>>()?;
+                Ok(CnfExpr::and(exprs))
+            }
+            NnfExpr::Or { exprs } => {
+                let exprs = exprs.into_iter().map(|expr| expr.try_into_cnf(&mut *charge)).collect::<Result<Vec<_>, _>>()?;
+                charge(exprs.iter().fold(1u64, |clauses, expr| clauses.saturating_mul(expr.0.len() as u64)))?;
+                Ok(CnfExpr::or(exprs))
+            }
         }
     }
 }
@@ -1531,6 +1851,32 @@ mod tests {
         assert_eq!(gen_var("A", &hashset!["A".to_owned(), "A0".to_owned(), "A1".to_owned(), "A2".to_owned(), "A3".to_owned()]), "A4");
     }
 
+    #[test]
+    fn test_canonical_string() {
+        use crate::parser::parse_unwrap as p;
+        // Saved files and their hashes depend on these, so they mustn't change
+        let cases = [
+            ("_|_", "⊥"),
+            ("^|^", "⊤"),
+            ("f(x, g(y))", "f(x, g(y))"),
+            ("~~A", "¬¬A"),
+            ("A -> B", "(A → B)"),
+            ("A & B & C", "(A ∧ B ∧ C)"),
+            ("A | B", "(A ∨ B)"),
+            ("A <-> B", "(A ↔ B)"),
+            ("A === B", "(A ≡ B)"),
+            ("a + b", "(a + b)"),
+            ("a * b", "(a * b)"),
+            ("forall x P(x)", "(∀ x P(x))"),
+            ("exists x forall y R(x, y)", "(∃ x (∀ y R(x, y)))"),
+            ("~(A -> B) & C", "(¬(A → B) ∧ C)"),
+        ];
+        for (input, canonical) in cases {
+            assert_eq!(p(input).to_canonical_string(), canonical);
+            assert_eq!(p(canonical), p(input));
+        }
+    }
+
     #[test]
     fn test_subst() {
         use crate::parser::parse_unwrap as p;
@@ -1541,6 +1887,20 @@ mod tests {
         assert_eq!(subst(p("forall f f(x) & g(y, z)"), "g", p("f")), p("forall f0 f0(x) & f(y, z)"));
     }
 
+    #[test]
+    fn test_replace_subexpr() {
+        use crate::parser::parse_unwrap as p;
+        let r = |e, target, replacement| replace_subexpr(p(e), &p(target), &p(replacement));
+        assert_eq!(r("P(a) & forall x P(x)", "P", "Q"), (p("Q(a) & forall x Q(x)"), 2));
+        assert_eq!(r("(A -> B) | ~(A -> B)", "A -> B", "C"), (p("C | ~C"), 2));
+        assert_eq!(r("A & B", "C", "D"), (p("A & B"), 0));
+        // occurrences whose free variables are bound aren't the target
+        assert_eq!(r("P(a) & forall a P(a)", "P(a)", "Q"), (p("Q & forall a P(a)"), 1));
+        // the quantified variable is renamed rather than capturing the replacement
+        assert_eq!(r("forall y R(y, c)", "c", "y"), (p("forall y0 R(y0, y)"), 1));
+        assert_eq!(r("forall y R(y)", "c", "y"), (p("forall y R(y)"), 0));
+    }
+
     #[test]
     fn test_unify() {
         use crate::parser::parse_unwrap as p;
@@ -1580,6 +1940,27 @@ mod tests {
         f("(a & (b & c)) | (q | r)");
     }
 
+    #[test]
+    fn test_normalize_double_negation() {
+        use crate::parser::parse_unwrap as p;
+        let n = |s: &str| p(s).normalize_double_negation();
+        assert_eq!(n("~~P"), p("P"));
+        assert_eq!(n("~~~P"), p("~P"));
+        assert_eq!(n("~~~~P"), p("P"));
+        assert_eq!(n("~~A & ~(~~B | ~~~C)"), p("A & ~(B | ~C)"));
+        assert_eq!(n("forall x ~~P(x)"), p("forall x P(x)"));
+    }
+
+    #[test]
+    fn test_normalize_bicon_chains() {
+        use crate::parser::parse_unwrap as p;
+        let n = |s: &str| p(s).normalize_bicon_chains();
+        assert_eq!(n("(A <-> B) <-> C"), p("A <-> B <-> C"));
+        assert_eq!(n("((A <-> B) <-> C) <-> D"), p("A <-> B <-> C <-> D"));
+        assert_eq!(n("~((A <-> B) <-> C)"), p("~(A <-> B <-> C)"));
+        assert_eq!(n("A <-> (B <-> C)"), p("A <-> (B <-> C)"));
+    }
+
     #[test]
     fn test_expressions_for_depth() {
         use std::iter::FromIterator;
@@ -0,0 +1,23 @@
+/*!
+Reads proofs written for other proof checkers.
+*/
+
+/// carnap reads Carnap's plain-text Fitch-style proofs
+pub mod carnap;
+
+/// Something an importer couldn't carry over as it was, which the user should
+/// look over
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ImportWarning {
+    /// The step on line `line` uses `rule`, which Aris doesn't have, so it was
+    /// imported with `RuleM::EmptyRule`
+    UnsupportedRule { rule: String, line: usize },
+}
+
+impl std::fmt::Display for ImportWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            ImportWarning::UnsupportedRule { rule, line } => write!(f, "Line {line} uses the rule {rule:?}, which Aris doesn't have, so it needs a rule chosen."),
+        }
+    }
+}
@@ -0,0 +1,259 @@
+/*!
+Reads proofs in the plain-text Fitch syntax of [Carnap](https://carnap.io), which a lot of open courseware distributes its exercises in.
+
+Each line is a formula, a colon, a rule, and the lines it cites, as in `Q :->E 1,2`.
+Premises have the rule `PR`, and a subproof is indented further than the lines around it, with spaces or `|`, and starts with lines with the rule `AS`.
+A line with `AS` at the indentation of a subproof that already has steps starts the next subproof.
+Lines are numbered from 1 in the order they're written, and a subproof is cited by the range of lines it spans, as in `->I 2-4`.
+
+Formulas are read with `parser::parse`, and rules with the names in common use in Carnap's systems, such as those of forall x.
+A step using a rule that Aris doesn't have is imported with `RuleM::EmptyRule`, along with an `ImportWarning` naming the rule, so that the rest of the proof can still be used.
+
+# Example
+```
+#[macro_use] extern crate frunk_core;
+use aris::expr::Expr;
+use aris::import::carnap::proof_from_carnap;
+use aris::proofs::pooledproof::PooledProof;
+use aris::proofs::Proof;
+
+let text = "
+P->Q :PR
+    P :AS
+    Q :->E 1,2
+P->Q :->I 2-3
+";
+let (prf, warnings) = proof_from_carnap::<PooledProof<HList![Expr]>>(text).unwrap();
+assert!(warnings.is_empty());
+assert!(prf.verify_all().is_fully_verified());
+```
+*/
+
+use super::ImportWarning;
+use crate::expr::Expr;
+use crate::proofs::tree::TreeError;
+use crate::proofs::tree::TreeLine;
+use crate::proofs::tree::TreeProof;
+use crate::proofs::Proof;
+use crate::rules::Rule;
+use crate::rules::RuleM;
+
+/// Reasons a proof can't be read from Carnap's syntax
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CarnapError {
+    /// Line `line` doesn't have a colon between its formula and rule, or cites
+    /// something that isn't a line number or range
+    Syntax { line: usize },
+    /// The formula on line `line` doesn't parse
+    UnparseableFormula { text: String, line: usize },
+    /// Line `line` is indented further without starting a subproof, or back to
+    /// an indentation that no enclosing proof has
+    BadIndentation { line: usize },
+    /// Line `line` is a premise after the first step, or inside a subproof
+    MisplacedPremise { line: usize },
+    /// A step cites a line or subproof that doesn't exist
+    Dependency(TreeError),
+}
+
+impl std::fmt::Display for CarnapError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        use CarnapError::*;
+        match self {
+            Syntax { line } => write!(f, "Line {line} isn't a formula, a colon, a rule, and the lines it cites."),
+            UnparseableFormula { text, line } => write!(f, "Failed to parse {text:?} on line {line}."),
+            BadIndentation { line } => write!(f, "The indentation of line {line} doesn't match a subproof."),
+            MisplacedPremise { line } => write!(f, "Line {line} is a premise, but premises have to come before the first step, outside of any subproof."),
+            Dependency(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+/// The rule Carnap calls `name`, if Aris has it
+fn translate_rule(name: &str) -> Option<Rule> {
+    Some(match name {
+        "R" | "Reit" => RuleM::Reit,
+        "&I" | "/\\I" | "∧I" => RuleM::AndIntro,
+        "&E" | "/\\E" | "∧E" => RuleM::AndElim,
+        "vI" | "\\/I" | "∨I" => RuleM::OrIntro,
+        "vE" | "\\/E" | "∨E" => RuleM::OrElim,
+        "->I" | "→I" | "CP" => RuleM::ImpIntro,
+        "->E" | "→E" | "MP" => RuleM::ImpElim,
+        "~I" | "-I" | "¬I" => RuleM::NotIntro,
+        "~E" | "-E" | "¬E" => RuleM::ContradictionIntro,
+        "X" | "_|_E" | "⊥E" => RuleM::ContradictionElim,
+        "DNE" => RuleM::NotElim,
+        "<->I" | "↔I" => RuleM::BiconditionalIntro,
+        "<->E" | "↔E" => RuleM::BiconditionalElim,
+        "MT" => RuleM::ModusTollens,
+        "DS" => RuleM::DisjunctiveSyllogism,
+        "HS" => RuleM::HypotheticalSyllogism,
+        "DeM" => RuleM::DeMorgan,
+        "DN" => RuleM::DoubleNegation,
+        "Comm" => RuleM::Commutation,
+        "Assoc" => RuleM::Association,
+        "Dist" => RuleM::Distribution,
+        "Impl" => RuleM::Implication,
+        "Contra" => RuleM::Contraposition,
+        "Exp" => RuleM::Currying,
+        "AI" | "∀I" => RuleM::ForallIntro,
+        "AE" | "∀E" => RuleM::ForallElim,
+        "EI" | "∃I" => RuleM::ExistsIntro,
+        "EE" | "∃E" => RuleM::ExistsElim,
+        _ => return None,
+    })
+}
+
+/// A proof being read, and the indentation of its lines
+struct Open {
+    indent: usize,
+    tree: TreeProof,
+}
+
+/// Load a proof from Carnap's syntax, along with a warning for each step whose
+/// rule Aris doesn't have
+pub fn proof_from_carnap<P: Proof>(text: &str) -> Result<(P, Vec<ImportWarning>), CarnapError> {
+    use CarnapError::*;
+    let lines = text.lines().filter(|line| !line.trim().is_empty()).collect::<Vec<_>>();
+    let indent_of = |line: &str| line.chars().take_while(|c| matches!(c, ' ' | '\t' | '|')).count();
+    let base = lines.iter().map(|line| indent_of(line)).min().unwrap_or(0);
+    let mut warnings = vec![];
+    let mut stack = vec![Open { indent: base, tree: TreeProof { premises: vec![], lines: vec![] } }];
+    // Close the innermost open subproof, adding it to the proof containing it
+    let close = |stack: &mut Vec<Open>| {
+        let sub = stack.pop().expect("there's a subproof to close").tree;
+        stack.last_mut().expect("a subproof is inside another proof").tree.lines.push(TreeLine::Subproof(sub));
+    };
+
+    for (i, line) in lines.iter().enumerate() {
+        let number = i + 1;
+        let indent = indent_of(line);
+        let (formula, justification) = line.trim_start_matches([' ', '\t', '|']).rsplit_once(':').ok_or(Syntax { line: number })?;
+        let mut words = justification.split_whitespace();
+        let rule = words.next().ok_or(Syntax { line: number })?;
+        let formula = formula.trim();
+        let expr: Expr = crate::parser::parse(formula).ok_or_else(|| UnparseableFormula { text: formula.into(), line: number })?;
+
+        while indent < stack.last().map_or(0, |open| open.indent) {
+            if stack.len() == 1 {
+                return Err(BadIndentation { line: number });
+            }
+            close(&mut stack);
+        }
+        let current = stack.last().expect("the outermost proof is always open");
+        if indent > current.indent && rule != "AS" {
+            return Err(BadIndentation { line: number });
+        }
+
+        match rule {
+            "PR" => {
+                if stack.len() > 1 || !stack[0].tree.lines.is_empty() {
+                    return Err(MisplacedPremise { line: number });
+                }
+                stack[0].tree.premises.push(expr);
+            }
+            "AS" => {
+                if indent == current.indent {
+                    // Another assumption of the same subproof, or the first of the next one
+                    if stack.len() == 1 {
+                        return Err(BadIndentation { line: number });
+                    }
+                    if !current.tree.lines.is_empty() {
+                        close(&mut stack);
+                        stack.push(Open { indent, tree: TreeProof { premises: vec![], lines: vec![] } });
+                    }
+                } else {
+                    stack.push(Open { indent, tree: TreeProof { premises: vec![], lines: vec![] } });
+                }
+                stack.last_mut().expect("a subproof was just opened").tree.premises.push(expr);
+            }
+            name => {
+                let rule = translate_rule(name).unwrap_or_else(|| {
+                    warnings.push(ImportWarning::UnsupportedRule { rule: name.into(), line: number });
+                    RuleM::EmptyRule
+                });
+                let (mut deps, mut sdeps) = (vec![], vec![]);
+                for citation in words.flat_map(|word| word.split(',')).filter(|citation| !citation.is_empty()) {
+                    let number_of = |s: &str| s.trim().parse::<usize>().map_err(|_| Syntax { line: number });
+                    match citation.split_once('-') {
+                        Some((first, last)) => sdeps.push(number_of(first)?..=number_of(last)?),
+                        None => deps.push(number_of(citation)?),
+                    }
+                }
+                stack.last_mut().expect("the outermost proof is always open").tree.lines.push(TreeLine::Step { expr, rule, deps, sdeps });
+            }
+        }
+    }
+    while stack.len() > 1 {
+        close(&mut stack);
+    }
+    let tree = stack.pop().expect("the outermost proof is always open").tree;
+    Ok((tree.to_proof().map_err(Dependency)?, warnings))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::parser::parse_unwrap as p;
+    use crate::proofs::pooledproof::PooledProof;
+
+    use frunk_core::HList;
+
+    type P = PooledProof<HList![Expr]>;
+
+    #[test]
+    fn test_conditional_proof() {
+        let text = include_str!("../../../example-proofs/carnap/conditional_proof.txt");
+        let (prf, warnings) = proof_from_carnap::<P>(text).unwrap();
+        assert_eq!(warnings, vec![]);
+        assert_eq!(
+            TreeProof::from_proof(&prf),
+            TreeProof {
+                premises: vec![p("P -> Q"), p("Q -> R")],
+                lines: vec![
+                    TreeLine::Subproof(TreeProof { premises: vec![p("P")], lines: vec![TreeLine::Step { expr: p("Q"), rule: RuleM::ImpElim, deps: vec![1, 3], sdeps: vec![] }, TreeLine::Step { expr: p("R"), rule: RuleM::ImpElim, deps: vec![2, 4], sdeps: vec![] }] }),
+                    TreeLine::Step { expr: p("P -> R"), rule: RuleM::ImpIntro, deps: vec![], sdeps: vec![3..=5] },
+                ],
+            }
+        );
+        assert!(prf.verify_all().is_fully_verified());
+    }
+
+    #[test]
+    fn test_disjunction_elimination() {
+        let text = include_str!("../../../example-proofs/carnap/disjunction_elimination.txt");
+        let (prf, warnings) = proof_from_carnap::<P>(text).unwrap();
+        assert_eq!(warnings, vec![]);
+        // The second assumption at the same indentation starts a second subproof
+        let tree = TreeProof::from_proof(&prf);
+        assert_eq!(tree.lines.iter().filter(|line| matches!(line, TreeLine::Subproof(_))).count(), 2);
+        assert_eq!(tree.lines.last(), Some(&TreeLine::Step { expr: p("Q | P"), rule: RuleM::OrElim, deps: vec![1], sdeps: vec![2..=3, 4..=5] }));
+        assert!(prf.verify_all().is_fully_verified());
+    }
+
+    #[test]
+    fn test_unsupported_rule() {
+        let text = "P :PR\n~~P :DNI 1\nP :R 1\n";
+        let (prf, warnings) = proof_from_carnap::<P>(text).unwrap();
+        assert_eq!(warnings, vec![ImportWarning::UnsupportedRule { rule: "DNI".into(), line: 2 }]);
+        let tree = TreeProof::from_proof(&prf);
+        assert_eq!(tree.lines[0], TreeLine::Step { expr: p("~~P"), rule: RuleM::EmptyRule, deps: vec![1], sdeps: vec![] });
+        assert_eq!(tree.lines[1], TreeLine::Step { expr: p("P"), rule: RuleM::Reit, deps: vec![1], sdeps: vec![] });
+    }
+
+    #[test]
+    fn test_errors() {
+        let load = |text: &str| proof_from_carnap::<P>(text).map(|_| ()).unwrap_err();
+        // Indented without an assumption
+        assert_eq!(load("P :PR\n    P :R 1\n"), CarnapError::BadIndentation { line: 2 });
+        // Back to an indentation that no enclosing proof has
+        assert_eq!(load("P :PR\n    Q :AS\n  Q :R 2\n"), CarnapError::BadIndentation { line: 3 });
+        // The same goes for bars
+        assert_eq!(load("P :PR\n| Q :AS\n| | Q :R 2\n"), CarnapError::BadIndentation { line: 3 });
+        assert_eq!(load("P :PR\nP :R 1\nQ :PR\n"), CarnapError::MisplacedPremise { line: 3 });
+        assert_eq!(load("P :PR\nP R 1\n"), CarnapError::Syntax { line: 2 });
+        assert_eq!(load("P :PR\nP :R one\n"), CarnapError::Syntax { line: 2 });
+        assert_eq!(load("P :PR\nP & :R 1\n"), CarnapError::UnparseableFormula { text: "P &".into(), line: 2 });
+        assert_eq!(load("P :PR\nP :R 3\n"), CarnapError::Dependency(TreeError::NoSuchLine { line: 3, cited_by: 2 }));
+    }
+}
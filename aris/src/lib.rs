@@ -2,10 +2,13 @@
 extern crate lazy_static;
 
 mod equivs;
+pub mod export;
 pub mod expr;
+pub mod import;
 pub mod macros;
 pub mod parser;
 pub mod proofs;
 mod rewrite_rules;
 pub mod rules;
+pub mod truth_table;
 mod zipper_vec;
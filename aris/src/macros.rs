@@ -4,7 +4,8 @@
 /// The format of each row is `(symbol, macros)`.
 pub static TABLE: [(&str, &[&str]); 10] = [("⊥", &[".con", "^"]), ("⊤", &[".taut", "!"]), ("¬", &[".not", "~"]), ("∀", &["forall", "@"]), ("∃", &["exists", "?"]), ("∧", &[".and", "&", r#"/\"#]), ("∨", &[".or", "|", r#"\/"#]), ("↔", &[".bicon", "%", "<->"]), ("→", &[".impl", "$", "->"]), ("≡", &[".equiv", "==="])];
 
-/// Convert ASCII characters and macros to logic symbols.
+/// Convert ASCII characters and macros to logic symbols. Macros that are words,
+/// like `forall`, are only converted on their own, not as part of longer words.
 ///
 /// ```rust
 /// assert_eq!(
@@ -17,7 +18,90 @@ pub static TABLE: [(&str, &[&str]); 10] = [("⊥", &[".con", "^"]), ("⊤", &[".
 ///     ),
 ///     "⊥ ⊥ ⊤ ¬ ¬ ∀ ∀ ∃ ∃ ∧ ∧ ∧ ∨ ∨ ∨ ↔ ↔ ↔ → → → ≡ ≡"
 /// );
+/// assert_eq!(aris::macros::expand("forall x, existsy(x)"), "∀ x, existsy(x)");
 /// ```
 pub fn expand(s: &str) -> String {
-    TABLE.iter().fold(s.to_string(), |s, (symbol, macros)| macros.iter().fold(s, |s, macro_| s.replace(macro_, symbol)))
+    expand_at(s, usize::MAX).0
+}
+
+/// Convert ASCII characters and macros to logic symbols like `expand`, for
+/// text with a caret at `caret`, counted in characters. Returns the converted
+/// text and where the caret is in it, which is after the symbol of a macro the
+/// caret was in. A word macro right before the caret isn't converted, since
+/// the word might still be being typed.
+///
+/// ```rust
+/// assert_eq!(aris::macros::expand_at("A -> B", 4), ("A → B".to_string(), 3));
+/// assert_eq!(aris::macros::expand_at("forall", 6), ("forall".to_string(), 6));
+/// assert_eq!(aris::macros::expand_at("forall ", 7), ("∀ ".to_string(), 2));
+/// ```
+pub fn expand_at(s: &str, caret: usize) -> (String, usize) {
+    let is_word = |c: char| c.is_alphanumeric() || c == '_';
+    let chars = s.chars().collect::<Vec<char>>();
+    let mut ret = String::new();
+    let mut ret_len = 0;
+    let mut new_caret = None;
+    let mut i = 0;
+    while i < chars.len() {
+        if caret == i {
+            new_caret.get_or_insert(ret_len);
+        }
+        // The longest macro starting here, which is `<->` instead of `->`
+        let found = TABLE
+            .iter()
+            .flat_map(|(symbol, macros)| macros.iter().map(move |macro_| (*symbol, macro_.chars().collect::<Vec<char>>())))
+            .filter(|(_, macro_)| chars[i..].starts_with(macro_))
+            .filter(|(_, macro_)| {
+                let end = i + macro_.len();
+                !is_word(macro_[0]) || ((i == 0 || !is_word(chars[i - 1])) && (if end == chars.len() { caret != end } else { !is_word(chars[end]) }))
+            })
+            .max_by_key(|(_, macro_)| macro_.len());
+        let len = match found {
+            Some((symbol, macro_)) => {
+                ret.push_str(symbol);
+                ret_len += symbol.chars().count();
+                macro_.len()
+            }
+            None => {
+                ret.push(chars[i]);
+                ret_len += 1;
+                1
+            }
+        };
+        i += len;
+        if caret < i {
+            new_caret.get_or_insert(ret_len);
+        }
+    }
+    (ret, new_caret.unwrap_or(ret_len))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expand_at_caret() {
+        // Replacements before the caret move it back by how much shorter they are,
+        assert_eq!(expand_at("A -> B & C", 8), ("A → B ∧ C".to_string(), 7));
+        assert_eq!(expand_at("A <-> B", 7), ("A ↔ B".to_string(), 5));
+        // ones at the caret put it after their symbol,
+        assert_eq!(expand_at("A ->", 4), ("A →".to_string(), 3));
+        assert_eq!(expand_at("A <-> B", 3), ("A ↔ B".to_string(), 3));
+        assert_eq!(expand_at("A <-> B", 4), ("A ↔ B".to_string(), 3));
+        assert_eq!(expand_at("A <-> B", 2), ("A ↔ B".to_string(), 2));
+        // and ones after it leave it where it is
+        assert_eq!(expand_at("A B -> C", 1), ("A B → C".to_string(), 1));
+        assert_eq!(expand_at("", 0), ("".to_string(), 0));
+        assert_eq!(expand_at("¬A", 2), ("¬A".to_string(), 2));
+
+        // Word macros are only replaced on their own,
+        assert_eq!(expand_at("Pforall x", 9), ("Pforall x".to_string(), 9));
+        assert_eq!(expand_at("exists_x", 8), ("exists_x".to_string(), 8));
+        assert_eq!(expand_at("forall x", 8), ("∀ x".to_string(), 3));
+        // and not until the word is done being typed
+        assert_eq!(expand_at("A & exists", 10), ("A ∧ exists".to_string(), 10));
+        assert_eq!(expand_at("A & exists", 3), ("A ∧ ∃".to_string(), 3));
+        assert_eq!(expand_at("(forall)", 7), ("(∀)".to_string(), 2));
+    }
 }
@@ -34,7 +34,7 @@ use aris::expr::Expr;
 use aris::proofs::xml_interop::proof_from_xml;
 let data = &include_bytes!("../../example-proofs/propositional_logic_arguments_for_proofs_ii_problem_10.bram")[..];
 type P = aris::proofs::pooledproof::PooledProof<HList![Expr]>;
-let (prf, metadata) = proof_from_xml::<P, _>(data).unwrap();
+let (prf, metadata, _) = proof_from_xml::<P, _>(data).unwrap();
 ```
 
 ## Creating a proof programatically
@@ -130,12 +130,19 @@ fn should_fail_with_lifetime_error() {
 This is a similar trick to the rank-2 type of `runST` in Haskell used to prevent the phantom state from escaping.
 */
 
+use crate::expr::replace_subexpr;
 use crate::expr::Expr;
+use crate::rules::CheckContext;
 use crate::rules::ProofCheckError;
 use crate::rules::Rule;
+use crate::rules::RuleT;
 
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+use std::collections::HashMap;
 use std::collections::HashSet;
 use std::hash::Hash;
+use std::ops::RangeInclusive;
 
 use frunk_core::coproduct::Coproduct;
 use frunk_core::hlist;
@@ -174,6 +181,30 @@ pub mod lined_proof;
 /// xml_interop contains functions for loading a proof from an xml reader
 pub mod xml_interop;
 
+/// json_interop contains functions for saving a proof to JSON and loading it back
+pub mod json_interop;
+
+/// tree contains TreeProof, a plain recursive representation of a proof that cites lines by number
+pub mod tree;
+
+/// builder contains ProofBuilder, for writing proofs by hand with formulas as strings and dependencies as line numbers
+pub mod builder;
+
+/// template creates proof skeletons from a list of premises and a goal
+pub mod template;
+
+/// autocomplete fills in the rule and dependency of steps that follow trivially from an earlier line
+pub mod autocomplete;
+
+/// cache contains VerificationCache, which remembers the result of checking each line until an edit affects it
+pub mod cache;
+
+/// diff compares two versions of a proof line by line
+pub mod diff;
+
+/// history contains RecordingProof, which wraps another proof to record mutations so they can be undone and redone
+pub mod history;
+
 /// DisplayIndented gives a convention for passing around state to pretty printers
 /// it is intended that objects that implement this implement display as:
 /// `fn fmt(&self, fmt: &mut Formatter) -> std::result::Result<(), std::fmt::Error> { self.display_indented(fmt, 1, &mut 1) }`
@@ -210,6 +241,30 @@ pub trait Proof: Sized {
     fn with_mut_premise<A, F: FnOnce(&mut Expr) -> A>(&mut self, r: &Self::PremiseReference, f: F) -> Option<A>;
     fn with_mut_step<A, F: FnOnce(&mut Justification<Expr, PjRef<Self>, Self::SubproofReference>) -> A>(&mut self, r: &Self::JustificationReference, f: F) -> Option<A>;
     fn with_mut_subproof<A, F: FnOnce(&mut Self::Subproof) -> A>(&mut self, r: &Self::SubproofReference, f: F) -> Option<A>;
+    /// Like `with_mut_premise`, but says why `r` is invalid instead of returning `None`
+    fn try_with_mut_premise<A, F: FnOnce(&mut Expr) -> A>(&mut self, r: &Self::PremiseReference, f: F) -> Result<A, InvalidRef<PjsRef<Self>>> {
+        let reference = Coproduct::inject(r.clone());
+        match self.invalid_ref_reason(&reference) {
+            Some(reason) => Err(InvalidRef { reference, reason }),
+            None => self.with_mut_premise(r, f).ok_or(InvalidRef { reference, reason: InvalidRefReason::Removed }),
+        }
+    }
+    /// Like `with_mut_step`, but says why `r` is invalid instead of returning `None`
+    fn try_with_mut_step<A, F: FnOnce(&mut Justification<Expr, PjRef<Self>, Self::SubproofReference>) -> A>(&mut self, r: &Self::JustificationReference, f: F) -> Result<A, InvalidRef<PjsRef<Self>>> {
+        let reference = Coproduct::inject(r.clone());
+        match self.invalid_ref_reason(&reference) {
+            Some(reason) => Err(InvalidRef { reference, reason }),
+            None => self.with_mut_step(r, f).ok_or(InvalidRef { reference, reason: InvalidRefReason::Removed }),
+        }
+    }
+    /// Like `with_mut_subproof`, but says why `r` is invalid instead of returning `None`
+    fn try_with_mut_subproof<A, F: FnOnce(&mut Self::Subproof) -> A>(&mut self, r: &Self::SubproofReference, f: F) -> Result<A, InvalidRef<PjsRef<Self>>> {
+        let reference = Coproduct::inject(r.clone());
+        match self.invalid_ref_reason(&reference) {
+            Some(reason) => Err(InvalidRef { reference, reason }),
+            None => self.with_mut_subproof(r, f).ok_or(InvalidRef { reference, reason: InvalidRefReason::Removed }),
+        }
+    }
     fn add_premise(&mut self, e: Expr) -> Self::PremiseReference;
     fn add_subproof(&mut self) -> Self::SubproofReference;
     fn add_step(&mut self, just: Justification<Expr, PjRef<Self>, Self::SubproofReference>) -> Self::JustificationReference;
@@ -224,10 +279,414 @@ pub trait Proof: Sized {
     fn add_step_relative(&mut self, just: Justification<Expr, PjRef<Self>, Self::SubproofReference>, r: &JsRef<Self>, after: bool) -> Self::JustificationReference;
     fn remove_line(&mut self, r: &PjRef<Self>);
     fn remove_subproof(&mut self, r: &Self::SubproofReference);
+    /// Walk the proof in display order: each subproof's premises, then its
+    /// lines, with nested subproofs walked in place. Subproofs themselves are
+    /// included, just before their contents, without a line number.
+    fn iter_lines(&self) -> std::vec::IntoIter<LineInfo<Self>> {
+        fn aux<P: Proof>(top: &P, sub: &P::Subproof, line: &mut usize, depth: usize, out: &mut Vec<LineInfo<P>>) {
+            use frunk_core::coproduct::Coproduct::{Inl, Inr};
+            for pr in sub.premises() {
+                out.push(LineInfo { reference: Coproduct::inject(pr), line: Some(*line), depth });
+                *line += 1;
+            }
+            for lineref in sub.lines() {
+                match lineref {
+                    Inl(jr) => {
+                        out.push(LineInfo { reference: Coproduct::inject(jr), line: Some(*line), depth });
+                        *line += 1;
+                    }
+                    Inr(Inl(sr)) => {
+                        out.push(LineInfo { reference: Coproduct::inject(sr.clone()), line: None, depth });
+                        if let Some(sub) = top.lookup_subproof(&sr) {
+                            aux(top, &sub, line, depth + 1, out);
+                        }
+                    }
+                    Inr(Inr(void)) => match void {},
+                }
+            }
+        }
+        let mut out = Vec::new();
+        aux(self, self.top_level_proof(), &mut 1, 0, &mut out);
+        out.into_iter()
+    }
+    /// A hash of the proof's logical content: its premises, its steps'
+    /// formulas and rules, where its subproofs are, and what each step cites,
+    /// by display line number as in `TreeProof`. References and UI state don't
+    /// affect it, so it's the same after saving and reloading or compacting the
+    /// proof. It's the start of the SHA-256 of `TreeProof::canonical_string`,
+    /// so it's also the same across builds and platforms.
+    fn content_hash(&self) -> u64 {
+        use sha2::Digest;
+        let hash = sha2::Sha256::digest(tree::TreeProof::from_proof(self).canonical_string().as_bytes());
+        let mut start = [0; 8];
+        start.copy_from_slice(&hash[..8]);
+        u64::from_be_bytes(start)
+    }
+    /// Copy a subproof, including everything nested in it, as a new sibling
+    /// right before or after it. Dependencies between lines inside the subproof
+    /// point at the copies, while dependencies on lines outside it are kept.
+    fn duplicate_subproof(&mut self, sr: &Self::SubproofReference, after: bool) -> Self::SubproofReference {
+        let snapshot = Snapshot::of_subproof(self, sr);
+        let copy = self.add_subproof_relative(&Coproduct::inject(sr.clone()), after);
+        let mut remap = Remap::new(true);
+        self.with_mut_subproof(&copy, |sub| remap.replay(sub, &snapshot));
+        copy
+    }
+    /// Copy a subproof out as a standalone proof, whose premises are the
+    /// subproof's premises. Fails if anything in the subproof cites a line or
+    /// subproof outside of it.
+    fn extract_subproof(&self, sr: &Self::SubproofReference) -> Result<Self, ExtractError<PjRef<Self>, Self::SubproofReference>> {
+        if self.lookup_subproof(sr).is_none() {
+            return Err(ExtractError::SubproofDoesNotExist(sr.clone()));
+        }
+        let snapshot = Snapshot::of_subproof(self, sr);
+        let escaping = snapshot.escaping_dependencies();
+        if !escaping.is_empty() {
+            return Err(ExtractError::EscapingDependency { lines: escaping });
+        }
+        let mut prf = Self::new();
+        Remap::new(false).replay(&mut prf, &snapshot);
+        Ok(prf)
+    }
+    /// Copy only what the lines in `keep` rely on, as given by `transitive_deps`,
+    /// with the lines themselves and the subproofs enclosing them, in the same
+    /// order. With no lines given, the last step of the top-level proof is kept.
+    /// Premises of the top-level proof that nothing relies on are dropped
+    /// unless `keep_premises` is set.
+    fn minimized(&self, keep: &[PjRef<Self>], keep_premises: bool) -> Self {
+        use frunk_core::coproduct::Coproduct::{Inl, Inr};
+        let default_keep = self.direct_lines().last().map(|jr| Coproduct::inject(jr.clone())).into_iter().collect::<Vec<PjRef<Self>>>();
+        let keep = if keep.is_empty() { &default_keep[..] } else { keep };
+        let mut lines = HashSet::new();
+        let mut subproofs = HashSet::new();
+        for r in keep {
+            lines.insert(r.clone());
+            if let Inr(Inl(jr)) = r {
+                let (deps, sdeps) = self.transitive_deps(jr);
+                lines.extend(deps);
+                subproofs.extend(sdeps);
+            }
+        }
+        for r in lines.iter() {
+            let mut current = pj_to_pjs::<Self>(r.clone());
+            while let Some(parent) = self.parent_of_line(&current) {
+                subproofs.insert(parent.clone());
+                current = Coproduct::inject(parent);
+            }
+        }
+        let mut snapshot = Snapshot::of(self, self.top_level_proof());
+        snapshot.retain(&lines, &subproofs, keep_premises);
+        let mut prf = Self::new();
+        Remap::new(false).replay(&mut prf, &snapshot);
+        prf
+    }
+    /// Copy all of `other` into a new subproof right before or after `anchor`.
+    /// `other`'s premises become the subproof's premises.
+    fn import_as_subproof(&mut self, other: &Self, anchor: &JsRef<Self>, after: bool) -> Self::SubproofReference {
+        let snapshot = Snapshot::of(other, other.top_level_proof());
+        let sub = self.add_subproof_relative(anchor, after);
+        let mut remap = Remap::new(false);
+        self.with_mut_subproof(&sub, |dst| remap.replay(dst, &snapshot));
+        sub
+    }
+    /// Add copies of `other`'s premises after this proof's premises, returning
+    /// the new premises
+    fn append_premises_from(&mut self, other: &Self) -> Vec<Self::PremiseReference> {
+        other.premises().iter().filter_map(|pr| other.lookup_premise(pr)).map(|e| self.add_premise(e)).collect()
+    }
+    /// Replace every occurrence of `target` in the premises and steps within
+    /// `scope` with `replacement`, returning the number of occurrences
+    /// replaced. See `expr::replace_subexpr` for how binders are handled.
+    fn replace_expr(&mut self, target: &Expr, replacement: &Expr, scope: ReplaceScope<Self::SubproofReference>) -> usize {
+        use frunk_core::coproduct::Coproduct::{Inl, Inr};
+        let lines = match scope {
+            ReplaceScope::WholeProof => self.top_level_proof().contained_justifications(true),
+            ReplaceScope::Subproof(sr) => match self.lookup_subproof(&sr) {
+                Some(sub) => sub.contained_justifications(true),
+                None => return 0,
+            },
+        };
+        let replace = |e: &mut Expr| {
+            let (new, n) = replace_subexpr(std::mem::replace(e, Expr::Contra), target, replacement);
+            *e = new;
+            n
+        };
+        let mut count = 0;
+        for r in lines {
+            count += match r {
+                Inl(pr) => self.with_mut_premise(&pr, replace),
+                Inr(Inl(jr)) => self.with_mut_step(&jr, |Justification(e, _, _, _)| replace(e)),
+                Inr(Inr(void)) => match void {},
+            }
+            .unwrap_or(0);
+        }
+        count
+    }
+    /// Remove a line, and remove it from the dependencies of every step that
+    /// cites it. Returns the steps that cited it.
+    fn remove_line_cascade(&mut self, r: &PjRef<Self>) -> Vec<Self::JustificationReference> {
+        let affected = self.remove_dependency_everywhere(&Coproduct::inject(r.clone()));
+        self.remove_line(r);
+        affected
+    }
+    /// Check whether a line can be deleted with `Proof::delete_line`. The
+    /// top-level proof must keep at least one premise, and every other
+    /// subproof must keep at least one premise and one step.
+    fn may_delete_line(&self, r: &PjRef<Self>) -> Result<(), DeleteError> {
+        use frunk_core::coproduct::Coproduct::{Inl, Inr};
+        let line = self.lookup_pj(r).ok_or(DeleteError::StaleRef)?;
+        let remaining = match self.parent_of_line(&pj_to_pjs::<Self>(r.clone())) {
+            Some(sr) => {
+                let sub = self.lookup_subproof(&sr).ok_or(DeleteError::StaleRef)?;
+                match line {
+                    Inl(_) => sub.premises().len(),
+                    Inr(Inl(_)) => sub.direct_lines().len(),
+                    Inr(Inr(void)) => match void {},
+                }
+            }
+            None => match line {
+                Inl(_) => self.premises().len(),
+                Inr(Inl(_)) => return Ok(()),
+                Inr(Inr(void)) => match void {},
+            },
+        };
+        if remaining > 1 {
+            Ok(())
+        } else {
+            Err(DeleteError::LastLineInSubproof)
+        }
+    }
+    /// Delete a line with `Proof::remove_line_cascade`, if
+    /// `Proof::may_delete_line` allows it. Returns the steps that cited it.
+    fn delete_line(&mut self, r: &PjRef<Self>) -> Result<Vec<Self::JustificationReference>, DeleteError> {
+        self.may_delete_line(r)?;
+        Ok(self.remove_line_cascade(r))
+    }
+    /// Delete the subproof directly containing a line with
+    /// `Proof::remove_subproof_cascade`
+    fn delete_enclosing_subproof(&mut self, r: &PjRef<Self>) -> Result<RemovedSubproof<Self>, DeleteError> {
+        self.lookup_pj(r).ok_or(DeleteError::StaleRef)?;
+        let sr = self.parent_of_line(&pj_to_pjs::<Self>(r.clone())).ok_or(DeleteError::CannotDeleteRoot)?;
+        Ok(self.remove_subproof_cascade(&sr))
+    }
+    /// Subproofs without at least one premise and one step. Deleting lines
+    /// with `Proof::delete_line` never leaves one, but a loaded file can.
+    fn incomplete_subproofs(&self) -> Vec<Self::SubproofReference> {
+        use frunk_core::coproduct::Coproduct::{Inl, Inr};
+        self.iter_lines()
+            .filter_map(|info| match info.reference {
+                Inr(Inr(Inl(sr))) => Some(sr),
+                _ => None,
+            })
+            .filter(|sr| self.lookup_subproof(sr).is_some_and(|sub| sub.premises().is_empty() || sub.direct_lines().is_empty()))
+            .collect()
+    }
+    /// Remove a subproof, and remove it and everything inside it from the
+    /// dependencies of every remaining step
+    fn remove_subproof_cascade(&mut self, r: &Self::SubproofReference) -> RemovedSubproof<Self> {
+        fn aux<P: Proof>(top: &P, sr: P::SubproofReference, lines: &mut Vec<PjRef<P>>, subproofs: &mut Vec<P::SubproofReference>) {
+            use frunk_core::coproduct::Coproduct::{Inl, Inr};
+            if let Some(sub) = top.lookup_subproof(&sr) {
+                lines.extend(sub.premises().into_iter().map(Coproduct::inject));
+                for line in sub.lines() {
+                    match line {
+                        Inl(jr) => lines.push(Coproduct::inject(jr)),
+                        Inr(Inl(sr)) => aux(top, sr, lines, subproofs),
+                        Inr(Inr(void)) => match void {},
+                    }
+                }
+            }
+            subproofs.push(sr);
+        }
+        let mut lines = Vec::new();
+        let mut subproofs = Vec::new();
+        aux(self, r.clone(), &mut lines, &mut subproofs);
+        self.remove_subproof(r);
+        // Steps inside the subproof are gone by now, so only steps outside it are affected
+        let removed: Vec<Coprod!(PjRef<Self>, Self::SubproofReference)> = lines.iter().map(|line| Coproduct::inject(line.clone())).chain(subproofs.iter().map(|sr| Coproduct::inject(sr.clone()))).collect();
+        let mut affected: Vec<Self::JustificationReference> = removed.iter().flat_map(|dep| self.remove_dependency_everywhere(dep)).collect();
+        affected.sort();
+        affected.dedup();
+        RemovedSubproof { lines, subproofs, affected }
+    }
+    /// Remove `dep` from the dependencies of every step, returning the steps it
+    /// was removed from in sorted order
+    fn remove_dependency_everywhere(&mut self, dep: &Coprod!(PjRef<Self>, Self::SubproofReference)) -> Vec<Self::JustificationReference> {
+        use frunk_core::coproduct::Coproduct::{Inl, Inr};
+        let affected = match dep {
+            Inl(lr) => self.dependents_of(lr),
+            Inr(Inl(sr)) => self.dependents_of_subproof(sr),
+            Inr(Inr(void)) => match *void {},
+        };
+        for jr in affected.iter() {
+            self.with_mut_step(jr, |Justification(_, _, deps, sdeps)| match dep {
+                Inl(lr) => deps.retain(|x| x != lr),
+                Inr(Inl(sr)) => sdeps.retain(|x| x != sr),
+                Inr(Inr(void)) => match *void {},
+            });
+        }
+        affected
+    }
+    /// Steps that cite the line `r`, in sorted order. This walks the whole
+    /// proof; implementations may override it with an index.
+    fn dependents_of(&self, r: &PjRef<Self>) -> Vec<Self::JustificationReference> {
+        let mut dependents: Vec<Self::JustificationReference> = self.contained_justifications(false).into_iter().filter_map(|line| Coproduct::uninject(line).ok()).filter(|jr| self.lookup_step(jr).map(|Justification(_, _, deps, _)| deps.contains(r)).unwrap_or(false)).collect();
+        dependents.sort();
+        dependents
+    }
+    /// Steps that cite the subproof `r`, in sorted order. This walks the whole
+    /// proof; implementations may override it with an index.
+    fn dependents_of_subproof(&self, r: &Self::SubproofReference) -> Vec<Self::JustificationReference> {
+        let mut dependents: Vec<Self::JustificationReference> = self.contained_justifications(false).into_iter().filter_map(|line| Coproduct::uninject(line).ok()).filter(|jr| self.lookup_step(jr).map(|Justification(_, _, _, sdeps)| sdeps.contains(r)).unwrap_or(false)).collect();
+        dependents.sort();
+        dependents
+    }
+    /// Swap a step with its neighboring line or subproof in the same subproof
+    fn move_line(&mut self, r: &Self::JustificationReference, direction: MoveDirection) -> Result<(), MoveError<PjRef<Self>, Self::SubproofReference>>;
+    /// Swap a premise with its neighboring premise in the same subproof
+    fn move_premise(&mut self, r: &Self::PremiseReference, direction: MoveDirection) -> Result<(), MoveError<PjRef<Self>, Self::SubproofReference>>;
+    /// Move a step into the sibling subproof right before it, as its last
+    /// line, or with `MoveDirection::Down` into the one right after it, as its
+    /// first line. Returns the step's reference afterwards.
+    fn nest_line(&mut self, r: &Self::JustificationReference, direction: MoveDirection) -> Result<Self::JustificationReference, MoveError<PjRef<Self>, Self::SubproofReference>>;
+    /// Move the first line of a subproof out to right before the subproof, or
+    /// with `MoveDirection::Down` its last line out to right after it. Returns
+    /// the step's reference afterwards.
+    fn unnest_line(&mut self, r: &Self::JustificationReference, direction: MoveDirection) -> Result<Self::JustificationReference, MoveError<PjRef<Self>, Self::SubproofReference>>;
+    /// Move a step into the end of the subproof right before it
+    fn indent_line(&mut self, r: &Self::JustificationReference) -> Result<Self::JustificationReference, MoveError<PjRef<Self>, Self::SubproofReference>> {
+        self.nest_line(r, MoveDirection::Up)
+    }
+    /// Move the last line of a subproof out to right after it, or its first
+    /// line out to right before it
+    fn outdent_line(&mut self, r: &Self::JustificationReference) -> Result<Self::JustificationReference, MoveError<PjRef<Self>, Self::SubproofReference>> {
+        let line: JsRef<Self> = Coproduct::inject(r.clone());
+        let last = self.parent_of_line(&js_to_pjs::<Self>(line.clone())).and_then(|sr| self.lookup_subproof(&sr)).and_then(|sub| sub.lines().pop());
+        self.unnest_line(r, if last == Some(line) { MoveDirection::Down } else { MoveDirection::Up })
+    }
+    /// Reorder the premises of a subproof, or of the top-level proof, to
+    /// `order`, which must contain each of them exactly once. Steps cite
+    /// premises by reference, so no dependency changes, but line numbers do.
+    fn set_premise_order(&mut self, order: &[Self::PremiseReference]) -> Result<(), MoveError<PjRef<Self>, Self::SubproofReference>> {
+        if let Some(r) = order.iter().find(|r| self.lookup_premise(r).is_none()) {
+            return Err(MoveError::LineDoesNotExist(Coproduct::inject(r.clone())));
+        }
+        let parent = order.first().and_then(|r| self.parent_of_line(&Coproduct::inject(r.clone())));
+        let mut current = match &parent {
+            Some(sr) => self.lookup_subproof(sr).map(|sub| sub.premises()).unwrap_or_default(),
+            None => self.premises(),
+        };
+        if order.len() != current.len() || order.iter().collect::<HashSet<_>>() != current.iter().collect::<HashSet<_>>() {
+            return Err(MoveError::NotAPermutation);
+        }
+        for (i, r) in order.iter().enumerate() {
+            let mut j = current.iter().position(|x| x == r).expect("order is a permutation of current");
+            while j > i {
+                self.move_premise(r, MoveDirection::Up)?;
+                current.swap(j - 1, j);
+                j -= 1;
+            }
+        }
+        Ok(())
+    }
     fn premises(&self) -> Vec<Self::PremiseReference>;
     fn lines(&self) -> Vec<JsRef<Self>>;
     fn parent_of_line(&self, r: &PjsRef<Self>) -> Option<Self::SubproofReference>;
-    fn verify_line(&self, r: &PjRef<Self>) -> Result<(), ProofCheckError<PjRef<Self>, Self::SubproofReference>>;
+    /// The identifier given to a line when it was created, which is kept when
+    /// the proof is saved and loaded
+    fn stable_id_of(&self, r: &PjRef<Self>) -> Option<LineId>;
+    fn ref_of_stable_id(&self, id: LineId) -> Option<PjRef<Self>>;
+    /// Replace the stable identifier of a line, as when loading a saved proof.
+    /// Returns false without changing anything if the line doesn't exist or
+    /// another line already has the identifier.
+    fn set_stable_id(&mut self, r: &PjRef<Self>, id: LineId) -> bool;
+    /// The stable identifier the next new line will be given, which is past
+    /// every one a line of the proof has had, even if it was removed
+    fn next_stable_id(&self) -> LineId;
+    /// Give new lines stable identifiers from `next` on, if that's past what
+    /// they'd be given, as when loading a saved proof whose removed lines'
+    /// identifiers mustn't be reused
+    fn reserve_stable_ids(&mut self, next: LineId);
+    /// Why `r` doesn't refer to a premise, step, or subproof of this proof, or
+    /// `None` if it does
+    fn invalid_ref_reason(&self, r: &PjsRef<Self>) -> Option<InvalidRefReason>;
+    fn verify_line_with(&self, r: &PjRef<Self>, ctx: &CheckContext) -> Result<(), ProofCheckError<PjRef<Self>, Self::SubproofReference>>;
+
+    /// Check that every dependency of the step `r` may be cited by it, as
+    /// given by `Proof::can_reference_dep`, before its rule is checked.
+    /// Citing itself or a later line or subproof are reported as such, and
+    /// citing an earlier one that's out of scope as `ReferencesLaterLine`.
+    fn check_references(&self, r: &PjRef<Self>) -> Result<(), ProofCheckError<PjRef<Self>, Self::SubproofReference>> {
+        use frunk_core::coproduct::Coproduct::{Inl, Inr};
+        let Justification(_, _, deps, sdeps) = match r {
+            Inl(_) => return Ok(()),
+            Inr(Inl(jr)) => self.lookup_justification_or_die(jr)?,
+            Inr(Inr(void)) => match *void {},
+        };
+        let invalid = match deps.into_iter().map(Coproduct::inject).chain(sdeps.into_iter().map(Coproduct::inject)).find(|dep| !self.can_reference_dep(r, dep)) {
+            Some(dep) => dep,
+            None => return Ok(()),
+        };
+        if invalid == Coproduct::inject(r.clone()) {
+            return Err(ProofCheckError::SelfReference);
+        }
+        let position = |line: PjsRef<Self>| self.iter_lines().position(|info| info.reference == line);
+        let dep_position = match &invalid {
+            Inl(pj) => position(pj_to_pjs::<Self>(pj.clone())),
+            Inr(Inl(sr)) => position(Coproduct::inject(sr.clone())),
+            Inr(Inr(void)) => match *void {},
+        };
+        match (dep_position, position(pj_to_pjs::<Self>(r.clone())), invalid) {
+            (None, _, Inl(pj)) => Err(ProofCheckError::LineDoesNotExist(pj)),
+            (None, _, Inr(Inl(sr))) => Err(ProofCheckError::SubproofDoesNotExist(sr)),
+            (Some(d), Some(o), dep) if d > o => Err(ProofCheckError::ForwardReference { dep }),
+            (_, _, dep) => Err(ProofCheckError::ReferencesLaterLine(r.clone(), dep)),
+        }
+    }
+
+    fn verify_line(&self, r: &PjRef<Self>) -> Result<(), ProofCheckError<PjRef<Self>, Self::SubproofReference>> {
+        self.verify_line_with(r, &CheckContext::default())
+    }
+
+    /// Check every line of the proof, in display order
+    fn verify_all_with(&self, ctx: &CheckContext) -> ProofReport<Self> {
+        let lines = self
+            .iter_lines()
+            .filter_map(|info| {
+                let r: PjRef<Self> = info.reference.subset().ok()?;
+                let result = self.verify_line_with(&r, ctx);
+                Some((info.line?, r, result))
+            })
+            .collect();
+        ProofReport { lines, structural: self.dependency_violations() }
+    }
+
+    fn verify_all(&self) -> ProofReport<Self> {
+        self.verify_all_with(&CheckContext::default())
+    }
+
+    /// Check that every step only cites lines and subproofs that exist, come
+    /// before it, and are in scope for it. Proofs built from untrusted input
+    /// should be validated before anything assumes their references resolve.
+    fn validate(&self) -> Vec<StructuralError<PjRef<Self>, Self::SubproofReference>> {
+        use frunk_core::coproduct::Coproduct::{Inl, Inr};
+        let positions = self.iter_lines().enumerate().map(|(i, info)| (info.reference, i)).collect::<HashMap<PjsRef<Self>, usize>>();
+        self.dependency_violations()
+            .into_iter()
+            .map(|(dep, of)| {
+                let dep_position = match &dep {
+                    Inl(pj) => positions.get(&pj_to_pjs::<Self>(pj.clone())),
+                    Inr(Inl(sr)) => positions.get(&Coproduct::inject(sr.clone())),
+                    Inr(Inr(void)) => match *void {},
+                };
+                match (dep_position, positions.get(&pj_to_pjs::<Self>(of.clone()))) {
+                    (None, _) => StructuralError::DanglingReference { dep, of },
+                    (Some(d), Some(o)) if d >= o => StructuralError::ForwardReference { dep, of },
+                    _ => StructuralError::OutOfScope { dep, of },
+                }
+            })
+            .collect()
+    }
 
     fn lookup_expr(&self, r: &PjRef<Self>) -> Option<Expr> {
         r.clone().fold(hlist![|pr| self.lookup_premise(&pr), |jr| self.lookup_step(&jr).map(|x| x.0)])
@@ -250,6 +709,17 @@ pub trait Proof: Sized {
     fn direct_lines(&self) -> Vec<Self::JustificationReference> {
         self.lines().iter().filter_map(|x| Coproduct::uninject::<Self::JustificationReference, _>(x.clone()).ok()).collect()
     }
+    /// The last step directly in this (sub)proof, which is what rules that
+    /// cite a subproof take as what it shows. Nested subproofs after it are
+    /// skipped, since they only show something once a step cites them.
+    /// `None` if there are no direct steps.
+    fn conclusion_ref(&self) -> Option<Self::JustificationReference> {
+        self.direct_lines().pop()
+    }
+    /// The formula of `conclusion_ref`
+    fn conclusion(&self) -> Option<Expr> {
+        self.conclusion_ref().and_then(|r| self.lookup_step(&r)).map(|Justification(conclusion, _, _, _)| conclusion)
+    }
     fn exprs(&self) -> Vec<PjRef<Self>> {
         self.premises().into_iter().map(Coproduct::inject).chain(self.direct_lines().into_iter().map(Coproduct::inject)).collect()
     }
@@ -263,9 +733,15 @@ pub trait Proof: Sized {
         }
         ret
     }
+    /// The first and last line numbers of the lines in this (sub)proof, which
+    /// is how a step citing it shows it, given the number of each line. `None`
+    /// if none of its lines are numbered.
+    fn line_span(&self, line_number: impl Fn(&PjRef<Self>) -> Option<usize>) -> Option<RangeInclusive<usize>> {
+        let lines = self.contained_justifications(true).iter().filter_map(line_number).collect::<Vec<_>>();
+        Some(*lines.iter().min()?..=*lines.iter().max()?)
+    }
     fn transitive_dependencies(&self, line: PjRef<Self>) -> HashSet<PjRef<Self>> {
         use frunk_core::coproduct::Coproduct::{Inl, Inr};
-        // TODO: cycle detection
         let mut stack: Vec<PjsRef<Self>> = vec![pj_to_pjs::<Self>(line)];
         let mut result = HashSet::new();
         while let Some(r) = stack.pop() {
@@ -274,7 +750,10 @@ pub trait Proof: Sized {
                     result.insert(Coproduct::inject(pr));
                 }
                 Inr(Inl(jr)) => {
-                    result.insert(Coproduct::inject(jr.clone()));
+                    // Only expand each step once, so that cyclic dependencies in a malformed proof terminate
+                    if !result.insert(Coproduct::inject(jr.clone())) {
+                        continue;
+                    }
                     if let Some(Justification(_, _, deps, sdeps)) = self.lookup_step(&jr) {
                         stack.extend(deps.into_iter().map(pj_to_pjs::<Self>));
                         stack.extend(sdeps.into_iter().map(Coproduct::inject));
@@ -290,6 +769,80 @@ pub trait Proof: Sized {
         }
         result
     }
+    /// Everything the step `r` ultimately relies on: the lines and subproofs it
+    /// cites, what those lines cite, and every line of the subproofs it cites.
+    /// Each reference is only followed once, so this terminates even if a
+    /// malformed proof has a dependency cycle, in which case `r` itself is in
+    /// the result.
+    fn transitive_deps(&self, r: &Self::JustificationReference) -> (BTreeSet<PjRef<Self>>, BTreeSet<Self::SubproofReference>) {
+        use frunk_core::coproduct::Coproduct::{Inl, Inr};
+        let mut lines = BTreeSet::new();
+        let mut subproofs = BTreeSet::new();
+        let mut stack: Vec<PjsRef<Self>> = vec![];
+        let push_deps = |stack: &mut Vec<PjsRef<Self>>, jr: &Self::JustificationReference| {
+            if let Some(Justification(_, _, deps, sdeps)) = self.lookup_step(jr) {
+                stack.extend(deps.into_iter().map(pj_to_pjs::<Self>));
+                stack.extend(sdeps.into_iter().map(Coproduct::inject));
+            }
+        };
+        push_deps(&mut stack, r);
+        while let Some(dep) = stack.pop() {
+            match dep {
+                Inl(pr) => {
+                    lines.insert(Coproduct::inject(pr));
+                }
+                Inr(Inl(jr)) => {
+                    if lines.insert(Coproduct::inject(jr.clone())) {
+                        push_deps(&mut stack, &jr);
+                    }
+                }
+                Inr(Inr(Inl(sr))) => {
+                    if subproofs.insert(sr.clone()) {
+                        if let Some(sub) = self.lookup_subproof(&sr) {
+                            stack.extend(sub.premises().into_iter().map(Coproduct::inject));
+                            stack.extend(sub.lines().into_iter().map(js_to_pjs::<Self>));
+                        }
+                    }
+                }
+                Inr(Inr(Inr(void))) => match void {},
+            }
+        }
+        (lines, subproofs)
+    }
+    /// Counts of what the proof contains, and which premises it doesn't need
+    fn stats(&self) -> ProofStats<Self> {
+        use frunk_core::coproduct::Coproduct::{Inl, Inr};
+        let mut stats = ProofStats { lines: 0, premises: self.premises().len(), subproofs: 0, max_depth: 0, rules: vec![], unused_premises: vec![] };
+        let mut used = HashSet::new();
+        for info in self.iter_lines() {
+            match info.reference {
+                Inl(_) => {
+                    stats.lines += 1;
+                    stats.max_depth = stats.max_depth.max(info.depth);
+                }
+                Inr(Inl(jr)) => {
+                    stats.lines += 1;
+                    stats.max_depth = stats.max_depth.max(info.depth);
+                    if let Some(Justification(_, rule, _, _)) = self.lookup_step(&jr) {
+                        match stats.rules.iter_mut().find(|(other, _)| *other == rule) {
+                            Some((_, count)) => *count += 1,
+                            None => stats.rules.push((rule, 1)),
+                        }
+                    }
+                    if self.verify_line(&Coproduct::inject(jr.clone())).is_ok() {
+                        used.extend(self.transitive_deps(&jr).0);
+                    }
+                }
+                Inr(Inr(Inl(_))) => {
+                    stats.subproofs += 1;
+                    stats.max_depth = stats.max_depth.max(info.depth + 1);
+                }
+                Inr(Inr(Inr(void))) => match void {},
+            }
+        }
+        stats.unused_premises = self.premises().into_iter().filter(|pr| !used.contains(&Coproduct::inject(pr.clone()))).collect();
+        stats
+    }
     fn depth_of_line(&self, r: &PjsRef<Self>) -> usize {
         let mut result = 0;
         let mut current = r.clone();
@@ -355,8 +908,43 @@ pub trait Proof: Sized {
 
         aux(self, self.parent_of_line(&pj_to_pjs::<Self>(r.clone())), &pj_to_pjs::<Self>(r.clone()), deps, sdeps);
     }
+    /// All dependencies that are out of scope for the step citing them, paired
+    /// with that step, in proof order
+    fn dependency_violations(&self) -> Vec<(Coprod!(PjRef<Self>, Self::SubproofReference), PjRef<Self>)> {
+        fn aux<P: Proof>(top: &P, lines: Vec<JsRef<P>>, out: &mut Vec<(Coprod!(PjRef<P>, P::SubproofReference), PjRef<P>)>) {
+            use frunk_core::coproduct::Coproduct::{Inl, Inr};
+            for line in lines {
+                match line {
+                    Inl(jr) => {
+                        if let Some(Justification(_, _, deps, sdeps)) = top.lookup_step(&jr) {
+                            let of: PjRef<P> = Coproduct::inject(jr);
+                            let mut valid_deps = HashSet::new();
+                            let mut valid_sdeps = HashSet::new();
+                            top.possible_deps_for_line(&of, &mut valid_deps, &mut valid_sdeps);
+                            out.extend(deps.into_iter().filter(|dep| !valid_deps.contains(dep)).map(|dep| (Coproduct::inject(dep), of.clone())));
+                            out.extend(sdeps.into_iter().filter(|sdep| !valid_sdeps.contains(sdep)).map(|sdep| (Coproduct::inject(sdep), of.clone())));
+                        }
+                    }
+                    Inr(Inl(sr)) => {
+                        if let Some(sub) = top.lookup_subproof(&sr) {
+                            aux(top, sub.lines(), out);
+                        }
+                    }
+                    Inr(Inr(void)) => match void {},
+                }
+            }
+        }
+        let mut out = Vec::new();
+        aux(self, self.lines(), &mut out);
+        out
+    }
+    /// Whether the step `r1` may cite `r2`: it has to come before `r1`, and be
+    /// in scope for it. A step can never cite itself.
     fn can_reference_dep(&self, r1: &PjRef<Self>, r2: &Coprod!(PjRef<Self>, Self::SubproofReference)) -> bool {
         use self::Coproduct::{Inl, Inr};
+        if *r2 == Coproduct::inject(r1.clone()) {
+            return false;
+        }
         let mut valid_deps = HashSet::new();
         let mut valid_sdeps = HashSet::new();
         self.possible_deps_for_line(r1, &mut valid_deps, &mut valid_sdeps);
@@ -368,6 +956,654 @@ pub trait Proof: Sized {
     }
 }
 
+/// Direction to move a line in, with `Proof::move_line` or `Proof::move_premise`
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum MoveDirection {
+    Up,
+    Down,
+}
+
+/// Which part of a proof `Proof::replace_expr` rewrites
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ReplaceScope<S> {
+    /// Every premise and step of the proof
+    WholeProof,
+    /// The premises and steps of a subproof, including nested subproofs
+    Subproof(S),
+}
+
+/// Reasons a line can't be moved
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum MoveError<R, S> {
+    /// The line isn't in the proof
+    LineDoesNotExist(R),
+    /// The line is already first or last in its subproof, so it can only be
+    /// moved out of the subproof with `Proof::outdent_line`
+    CrossesSubproofBoundary(R),
+    /// The line is already first or last in the top-level proof
+    NoNeighbor(R),
+    /// There's no subproof next to the line to move it into
+    NoAdjacentSubproof(R),
+    /// The line isn't the first or last line of a subproof, so it can't be
+    /// moved out of one
+    NotAtSubproofEdge(R),
+    /// The premises given to `Proof::set_premise_order` aren't all the
+    /// premises of one subproof, each exactly once
+    NotAPermutation,
+    /// After the move, the justification `of` would reference `dep`, which
+    /// would no longer be in scope before it
+    WouldBreakDependency { dep: Coprod!(R, S), of: R },
+}
+
+impl<R, S> MoveError<R, S> {
+    /// The message to show the user, naming lines with `line` and subproofs
+    /// with `subproof`, such as `LineNames::line` and `LineNames::subproof`
+    pub fn describe(&self, line: impl Fn(&R) -> String, subproof: impl Fn(&S) -> String) -> String {
+        self.describe_with(&|r| Some(line(r)), &|s| Some(subproof(s)))
+    }
+
+    /// Like `describe`, calling lines without a name "the line"
+    fn describe_with(&self, line: &dyn Fn(&R) -> Option<String>, subproof: &dyn Fn(&S) -> Option<String>) -> String {
+        use frunk_core::coproduct::Coproduct::{Inl, Inr};
+        use MoveError::*;
+        let the_line = |r: &R| line(r).unwrap_or_else(|| "the line".into());
+        match self {
+            LineDoesNotExist(_) => "The line to move does not exist.".into(),
+            CrossesSubproofBoundary(r) => format!("Can't move {} out of its subproof.", the_line(r)),
+            NoNeighbor(r) => format!("Can't move {} any further.", the_line(r)),
+            NoAdjacentSubproof(r) => format!("There is no subproof next to {} to move it into.", the_line(r)),
+            NotAtSubproofEdge(r) => format!("Can't move {} out of its subproof, since it isn't the subproof's first or last line.", the_line(r)),
+            NotAPermutation => "The new order must list every premise of the subproof exactly once.".into(),
+            WouldBreakDependency { dep, of } => {
+                let dep = match dep {
+                    Inl(r) => line(r),
+                    Inr(Inl(s)) => subproof(s),
+                    Inr(Inr(void)) => match *void {},
+                };
+                match (dep, line(of)) {
+                    (Some(dep), Some(of)) => format!("Moving this line would put {dep} after {of}, which depends on it."),
+                    _ => "Moving this line would put a line after a step that depends on it.".into(),
+                }
+            }
+        }
+    }
+}
+
+impl<R, S> std::fmt::Display for MoveError<R, S> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.describe_with(&|_| None, &|_| None))
+    }
+}
+
+/// Reasons a line or subproof can't be deleted
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum DeleteError {
+    /// The line isn't in the proof
+    StaleRef,
+    /// Deleting the line would leave its subproof without a premise or a step
+    LastLineInSubproof,
+    /// The line isn't in a subproof, and the top-level proof can't be deleted
+    CannotDeleteRoot,
+}
+
+impl std::fmt::Display for DeleteError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            DeleteError::StaleRef => write!(f, "The line to delete does not exist."),
+            DeleteError::LastLineInSubproof => write!(f, "A subproof must keep at least one premise and one step."),
+            DeleteError::CannotDeleteRoot => write!(f, "The top-level proof can't be deleted."),
+        }
+    }
+}
+
+/// Why a reference passed to one of the `Proof::try_with_mut_*` methods is invalid
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum InvalidRefReason {
+    /// The reference was to something that has since been removed
+    Removed,
+    /// The reference is past the end of its pool, so it was never handed out
+    /// by this proof, or was the last one and has since been removed
+    OutOfRange,
+    /// The reference was handed out by a different proof
+    OtherProof,
+}
+
+/// A reference that doesn't refer to anything in a proof, from the
+/// `Proof::try_with_mut_*` methods
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InvalidRef<R> {
+    pub reference: R,
+    pub reason: InvalidRefReason,
+}
+
+impl<R> InvalidRef<R> {
+    /// The message to show the user, naming the reference with `name`, such
+    /// as `LineNames::line_or_subproof`
+    pub fn describe(&self, name: impl Fn(&R) -> String) -> String {
+        let name = name(&self.reference);
+        match self.reason {
+            InvalidRefReason::Removed => format!("Can't change {name}, which has been removed."),
+            InvalidRefReason::OutOfRange => format!("Can't change {name}, which does not exist."),
+            InvalidRefReason::OtherProof => format!("Can't change {name}, which belongs to a different proof."),
+        }
+    }
+}
+
+impl<R> std::fmt::Display for InvalidRef<R> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.describe(|_| "the referenced line".into()))
+    }
+}
+
+/// An identifier for a line that, unlike a reference, survives saving and
+/// loading the proof, and is never reused for another line of the same proof
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct LineId(pub usize);
+
+/// A line or subproof visited by `Proof::iter_lines`
+pub struct LineInfo<P: Proof> {
+    pub reference: PjsRef<P>,
+    /// 1-based line number as displayed in the UI, or `None` for a subproof
+    pub line: Option<usize>,
+    /// Number of subproofs enclosing the line
+    pub depth: usize,
+}
+
+/// How lines and subproofs are named in messages to the user: a line by its
+/// 1-based line number, like "line 3", and a subproof by the lines it spans,
+/// like "lines 3-5", as `Citation` shows them
+pub struct LineNames<P: Proof> {
+    lines: HashMap<PjRef<P>, usize>,
+    subproofs: HashMap<P::SubproofReference, RangeInclusive<usize>>,
+}
+
+impl<P: Proof> LineNames<P> {
+    /// The names of the lines and subproofs of `prf` as it is now
+    pub fn new(prf: &P) -> Self {
+        use frunk_core::coproduct::Coproduct::{Inl, Inr};
+        let infos = prf.iter_lines().collect::<Vec<_>>();
+        let lines = infos.iter().filter_map(|info| Some((info.reference.clone().subset().ok()?, info.line?))).collect::<HashMap<PjRef<P>, usize>>();
+        let subproofs = infos
+            .iter()
+            .filter_map(|info| match &info.reference {
+                Inr(Inr(Inl(sr))) => Some((sr.clone(), prf.lookup_subproof(sr)?.line_span(|r| lines.get(r).copied())?)),
+                _ => None,
+            })
+            .collect();
+        LineNames { lines, subproofs }
+    }
+    /// "line 3", or "a missing line" if `r` isn't in the proof
+    pub fn line(&self, r: &PjRef<P>) -> String {
+        self.lines.get(r).map_or_else(|| "a missing line".into(), |line| format!("line {line}"))
+    }
+    /// "lines 3-5", or "a missing subproof" if `s` isn't in the proof or has no lines
+    pub fn subproof(&self, s: &P::SubproofReference) -> String {
+        match self.subproofs.get(s) {
+            Some(span) if span.start() == span.end() => format!("line {}", span.start()),
+            Some(span) => format!("lines {}-{}", span.start(), span.end()),
+            None => "a missing subproof".into(),
+        }
+    }
+    /// The name of a line or subproof
+    pub fn line_or_subproof(&self, r: &PjsRef<P>) -> String {
+        use frunk_core::coproduct::Coproduct::{Inl, Inr};
+        match r {
+            Inl(pr) => self.line(&Coproduct::inject(pr.clone())),
+            Inr(Inl(jr)) => self.line(&Coproduct::inject(jr.clone())),
+            Inr(Inr(Inl(sr))) => self.subproof(sr),
+            Inr(Inr(Inr(void))) => match *void {},
+        }
+    }
+    /// The name of a dependency, which is a line or subproof
+    pub fn dependency(&self, r: &Coprod!(PjRef<P>, P::SubproofReference)) -> String {
+        use frunk_core::coproduct::Coproduct::{Inl, Inr};
+        match r {
+            Inl(r) => self.line(r),
+            Inr(Inl(sr)) => self.subproof(sr),
+            Inr(Inr(void)) => match *void {},
+        }
+    }
+}
+
+type LineResult<P> = Result<(), ProofCheckError<PjRef<P>, <P as Proof>::SubproofReference>>;
+
+/// The result of checking a whole proof with `Proof::verify_all`
+pub struct ProofReport<P: Proof> {
+    /// Each line's 1-based line number, reference, and check result, in display order
+    pub lines: Vec<(usize, PjRef<P>, LineResult<P>)>,
+    /// Dependencies that don't exist or aren't in scope for the step citing
+    /// them, paired with that step
+    pub structural: Vec<(Coprod!(PjRef<P>, P::SubproofReference), PjRef<P>)>,
+}
+
+impl<P: Proof> ProofReport<P> {
+    /// The check result for a line, if it's in the proof
+    pub fn get(&self, r: &PjRef<P>) -> Option<&LineResult<P>> {
+        self.lines.iter().find(|(_, line, _)| line == r).map(|(_, _, result)| result)
+    }
+    /// Line numbers, references, and check results, ordered by line number
+    pub fn iter(&self) -> impl Iterator<Item = (usize, &PjRef<P>, &LineResult<P>)> {
+        self.lines.iter().map(|(n, r, result)| (*n, r, result))
+    }
+    /// Only the lines that failed to check, ordered by line number
+    pub fn errors(&self) -> impl Iterator<Item = (usize, &PjRef<P>, &ProofCheckError<PjRef<P>, P::SubproofReference>)> {
+        self.lines.iter().filter_map(|(n, r, result)| result.as_ref().err().map(|err| (*n, r, err)))
+    }
+    /// The number of lines that failed to check
+    pub fn error_count(&self) -> usize {
+        self.errors().count()
+    }
+    pub fn is_fully_verified(&self) -> bool {
+        self.error_count() == 0 && self.structural.is_empty()
+    }
+}
+
+/// Summary of a proof's contents, from `Proof::stats`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ProofStats<P: Proof> {
+    /// Premises and steps, including those in subproofs
+    pub lines: usize,
+    /// Premises of the top-level proof
+    pub premises: usize,
+    /// Subproofs, including nested ones
+    pub subproofs: usize,
+    /// The most subproofs any line or subproof is nested in
+    pub max_depth: usize,
+    /// Each rule used by a step and how many steps use it, in order of first use
+    pub rules: Vec<(Rule, usize)>,
+    /// Premises of the top-level proof that no step that checks depends on, even transitively
+    pub unused_premises: Vec<P::PremiseReference>,
+}
+
+impl<P: Proof> ProofStats<P> {
+    /// How many steps use `rule`
+    pub fn uses_of(&self, rule: Rule) -> usize {
+        self.rules.iter().find(|(other, _)| *other == rule).map(|(_, count)| *count).unwrap_or(0)
+    }
+}
+
+/// Whether a goal is concluded by a top-level line of a proof
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GoalStatus<R> {
+    /// The goal is concluded by a top-level line that checks
+    Reached(R),
+    /// The goal is concluded by a top-level line, but none of the lines concluding it check
+    ReachedButUnverified(R),
+    /// No top-level line concludes the goal
+    NotReached,
+}
+
+/// Reasons an edit made through a `ProofDocument` is refused
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum EditError<R> {
+    /// The line is locked, so it can't be changed or removed
+    LockedLine(R),
+    /// The line isn't in the proof
+    InvalidRef(InvalidRef<R>),
+    /// The line can't be deleted from the proof
+    Delete(DeleteError),
+}
+
+impl<R> EditError<R> {
+    /// The message to show the user, naming lines with `name`, such as
+    /// `LineNames::line_or_subproof`
+    pub fn describe(&self, name: impl Fn(&R) -> String) -> String {
+        match self {
+            EditError::LockedLine(r) => format!("Can't change {}, which is locked.", name(r)),
+            EditError::InvalidRef(err) => err.describe(name),
+            EditError::Delete(err) => err.to_string(),
+        }
+    }
+}
+
+impl<R> std::fmt::Display for EditError<R> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.describe(|_| "the line".into()))
+    }
+}
+
+/// A proof together with the goals it's meant to prove
+pub struct ProofDocument<P: Proof> {
+    pub proof: P,
+    pub goals: Vec<Expr>,
+    /// Stable ids of the lines that can't be changed or removed, such as the
+    /// premises of an assigned problem
+    pub locked: BTreeSet<LineId>,
+    /// Notes on lines, by stable id, so that they stay with their lines as
+    /// the proof is rearranged
+    pub comments: BTreeMap<LineId, String>,
+}
+
+impl<P: Proof> ProofDocument<P> {
+    pub fn new(proof: P, goals: Vec<Expr>) -> Self {
+        ProofDocument { proof, goals, locked: BTreeSet::new(), comments: BTreeMap::new() }
+    }
+
+    /// The comment on a line, if it has one
+    pub fn comment(&self, r: &PjRef<P>) -> Option<&str> {
+        self.proof.stable_id_of(r).and_then(|id| self.comments.get(&id)).map(String::as_str)
+    }
+
+    /// Set the comment on a line, or remove it if `comment` is `None`,
+    /// returning false if the line isn't in the proof
+    pub fn set_comment(&mut self, r: &PjRef<P>, comment: Option<String>) -> bool {
+        match (self.proof.stable_id_of(r), comment) {
+            (Some(id), Some(comment)) => {
+                self.comments.insert(id, comment);
+                true
+            }
+            (Some(id), None) => {
+                self.comments.remove(&id);
+                true
+            }
+            (None, _) => false,
+        }
+    }
+
+    /// Whether the line is locked against changes
+    pub fn is_locked(&self, r: &PjRef<P>) -> bool {
+        self.proof.stable_id_of(r).map(|id| self.locked.contains(&id)).unwrap_or(false)
+    }
+
+    /// Lock a line against changes, returning false if it isn't in the proof
+    pub fn lock(&mut self, r: &PjRef<P>) -> bool {
+        self.proof.stable_id_of(r).map(|id| self.locked.insert(id)).is_some()
+    }
+
+    fn check_unlocked(&self, r: &PjRef<P>) -> Result<(), EditError<PjsRef<P>>> {
+        if self.is_locked(r) {
+            return Err(EditError::LockedLine(pj_to_pjs::<P>(r.clone())));
+        }
+        Ok(())
+    }
+
+    /// Like `check_unlocked`, for every line in a subproof, including in
+    /// nested subproofs, naming the first locked one
+    fn check_subproof_unlocked(&self, sr: &P::SubproofReference) -> Result<(), EditError<PjsRef<P>>> {
+        let contained = self.proof.lookup_subproof(sr).map(|sub| sub.contained_justifications(true)).unwrap_or_default();
+        match self.proof.iter_lines().filter_map(|info| info.reference.subset().ok()).find(|r: &PjRef<P>| contained.contains(r) && self.is_locked(r)) {
+            Some(r) => Err(EditError::LockedLine(pj_to_pjs::<P>(r))),
+            None => Ok(()),
+        }
+    }
+
+    /// Like `Proof::try_with_mut_premise`, but refuses to change a locked premise
+    pub fn with_mut_premise<A, F: FnOnce(&mut Expr) -> A>(&mut self, r: &P::PremiseReference, f: F) -> Result<A, EditError<PjsRef<P>>> {
+        self.check_unlocked(&Coproduct::inject(r.clone()))?;
+        self.proof.try_with_mut_premise(r, f).map_err(EditError::InvalidRef)
+    }
+
+    /// Like `Proof::try_with_mut_step`, but refuses to change a locked step
+    pub fn with_mut_step<A, F: FnOnce(&mut Justification<Expr, PjRef<P>, P::SubproofReference>) -> A>(&mut self, r: &P::JustificationReference, f: F) -> Result<A, EditError<PjsRef<P>>> {
+        self.check_unlocked(&Coproduct::inject(r.clone()))?;
+        self.proof.try_with_mut_step(r, f).map_err(EditError::InvalidRef)
+    }
+
+    /// Like `Proof::remove_line`, but refuses to remove a locked line, and
+    /// removes the line's comment along with it
+    pub fn remove_line(&mut self, r: &PjRef<P>) -> Result<(), EditError<PjsRef<P>>> {
+        self.check_unlocked(r)?;
+        let id = self.proof.stable_id_of(r);
+        self.proof.remove_line(r);
+        if let Some(id) = id {
+            self.comments.remove(&id);
+        }
+        Ok(())
+    }
+
+    /// Like `Proof::delete_line`, but refuses to delete a locked line, and
+    /// removes the line's comment along with it
+    pub fn delete_line(&mut self, r: &PjRef<P>) -> Result<Vec<P::JustificationReference>, EditError<PjsRef<P>>> {
+        self.check_unlocked(r)?;
+        let id = self.proof.stable_id_of(r);
+        let affected = self.proof.delete_line(r).map_err(EditError::Delete)?;
+        if let Some(id) = id {
+            self.comments.remove(&id);
+        }
+        Ok(affected)
+    }
+
+    /// Like `Proof::remove_subproof_cascade`, but refuses to remove a
+    /// subproof with a locked line anywhere in it, and removes the comments of
+    /// the lines in it along with them
+    pub fn remove_subproof(&mut self, sr: &P::SubproofReference) -> Result<RemovedSubproof<P>, EditError<PjsRef<P>>> {
+        let reference = Coproduct::inject(sr.clone());
+        if let Some(reason) = self.proof.invalid_ref_reason(&reference) {
+            return Err(EditError::InvalidRef(InvalidRef { reference, reason }));
+        }
+        self.check_subproof_unlocked(sr)?;
+        let ids = self.proof.lookup_subproof(sr).map(|sub| sub.contained_justifications(true)).unwrap_or_default().iter().filter_map(|r| self.proof.stable_id_of(r)).collect::<Vec<_>>();
+        let removed = self.proof.remove_subproof_cascade(sr);
+        for id in ids {
+            self.comments.remove(&id);
+        }
+        Ok(removed)
+    }
+
+    /// Like `Proof::delete_enclosing_subproof`, but refuses to delete a
+    /// subproof with a locked line anywhere in it, even if `r` isn't locked
+    pub fn delete_enclosing_subproof(&mut self, r: &PjRef<P>) -> Result<RemovedSubproof<P>, EditError<PjsRef<P>>> {
+        self.proof.lookup_pj(r).ok_or(EditError::Delete(DeleteError::StaleRef))?;
+        let sr = self.proof.parent_of_line(&pj_to_pjs::<P>(r.clone())).ok_or(EditError::Delete(DeleteError::CannotDeleteRoot))?;
+        self.remove_subproof(&sr)
+    }
+
+    /// The status of each goal, in the same order as `self.goals`. A line
+    /// concludes a goal if its expression is alpha-equivalent to it, and only
+    /// lines outside of any subproof count.
+    pub fn check_goals(&self) -> Vec<GoalStatus<PjRef<P>>> {
+        let report = self.proof.verify_all();
+        let top = self.proof.top_level_proof();
+        let top_level = top.premises().into_iter().map(|pr| -> PjRef<P> { Coproduct::inject(pr) }).chain(top.direct_lines().into_iter().map(|jr| -> PjRef<P> { Coproduct::inject(jr) })).collect::<Vec<_>>();
+        let verified = |r: &PjRef<P>| report.get(r).map(|result| result.is_ok()).unwrap_or(false) && !report.structural.iter().any(|(_, of)| of == r);
+        self.goals
+            .iter()
+            .map(|goal| {
+                let goal = goal.clone().replacing_bound_vars();
+                let concluding = top_level.iter().filter(|r| self.proof.lookup_expr(r).map(|e| e.replacing_bound_vars() == goal).unwrap_or(false)).collect::<Vec<_>>();
+                match (concluding.iter().find(|r| verified(r)), concluding.first()) {
+                    (Some(r), _) => GoalStatus::Reached((*r).clone()),
+                    (None, Some(r)) => GoalStatus::ReachedButUnverified((*r).clone()),
+                    (None, None) => GoalStatus::NotReached,
+                }
+            })
+            .collect()
+    }
+}
+
+/// An owned copy of the contents of a subproof, keeping the references they
+/// had where they were copied from. This lets them be written back into a proof
+/// (possibly the same one) without holding a borrow of the original.
+struct Snapshot<R, J, S> {
+    premises: Vec<(R, Expr)>,
+    lines: Vec<SnapshotLine<R, J, S>>,
+}
+
+enum SnapshotLine<R, J, S> {
+    Step(J, Justification<Expr, Coprod!(R, J), S>),
+    Subproof(S, Snapshot<R, J, S>),
+}
+
+impl<R: Clone, J: Clone, S: Clone> Snapshot<R, J, S> {
+    fn of<P: Proof<PremiseReference = R, JustificationReference = J, SubproofReference = S>>(top: &P, sub: &P::Subproof) -> Self {
+        use frunk_core::coproduct::Coproduct::{Inl, Inr};
+        let premises = sub.premises().into_iter().filter_map(|pr| Some((pr.clone(), top.lookup_premise(&pr)?))).collect();
+        let lines = sub
+            .lines()
+            .into_iter()
+            .filter_map(|line| match line {
+                Inl(jr) => Some(SnapshotLine::Step(jr.clone(), top.lookup_step(&jr)?)),
+                Inr(Inl(sr)) => Some(SnapshotLine::Subproof(sr.clone(), Snapshot::of(top, &top.lookup_subproof(&sr)?))),
+                Inr(Inr(void)) => match void {},
+            })
+            .collect();
+        Snapshot { premises, lines }
+    }
+
+    fn of_subproof<P: Proof<PremiseReference = R, JustificationReference = J, SubproofReference = S>>(top: &P, sr: &S) -> Self {
+        match top.lookup_subproof(sr) {
+            Some(sub) => Snapshot::of(top, &sub),
+            None => Snapshot { premises: vec![], lines: vec![] },
+        }
+    }
+}
+
+impl<R: Clone + Eq + Hash, J: Clone + Eq + Hash, S: Clone + Eq + Hash> Snapshot<R, J, S> {
+    /// Drop steps and subproofs that aren't in `lines` and `subproofs`. Premises
+    /// of the outermost subproof are dropped too if they aren't in `lines`,
+    /// unless `keep_premises` is set, while premises of nested ones are kept.
+    fn retain(&mut self, lines: &HashSet<Coprod!(R, J)>, subproofs: &HashSet<S>, keep_premises: bool) {
+        if !keep_premises {
+            self.premises.retain(|(pr, _)| lines.contains(&Coproduct::inject(pr.clone())));
+        }
+        self.lines.retain(|line| match line {
+            SnapshotLine::Step(jr, _) => lines.contains(&Coproduct::inject(jr.clone())),
+            SnapshotLine::Subproof(sr, _) => subproofs.contains(sr),
+        });
+        for line in self.lines.iter_mut() {
+            if let SnapshotLine::Subproof(_, sub) = line {
+                sub.retain(lines, subproofs, true);
+            }
+        }
+    }
+
+    /// Dependencies on lines and subproofs that aren't part of the snapshot,
+    /// in the order they're first cited
+    fn escaping_dependencies(&self) -> Vec<Coprod!(Coprod!(R, J), S)> {
+        fn contents<R: Clone + Eq + Hash, J: Clone + Eq + Hash, S: Clone + Eq + Hash>(snapshot: &Snapshot<R, J, S>, out: &mut HashSet<Coprod!(Coprod!(R, J), S)>) {
+            for (pr, _) in snapshot.premises.iter() {
+                let line: Coprod!(R, J) = Coproduct::inject(pr.clone());
+                out.insert(Coproduct::inject(line));
+            }
+            for line in snapshot.lines.iter() {
+                match line {
+                    SnapshotLine::Step(jr, _) => {
+                        let line: Coprod!(R, J) = Coproduct::inject(jr.clone());
+                        out.insert(Coproduct::inject(line));
+                    }
+                    SnapshotLine::Subproof(sr, sub) => {
+                        out.insert(Coproduct::inject(sr.clone()));
+                        contents(sub, out);
+                    }
+                }
+            }
+        }
+        fn cited<R: Clone + Eq + Hash, J: Clone + Eq + Hash, S: Clone + Eq + Hash>(snapshot: &Snapshot<R, J, S>, inside: &HashSet<Coprod!(Coprod!(R, J), S)>, out: &mut Vec<Coprod!(Coprod!(R, J), S)>) {
+            for line in snapshot.lines.iter() {
+                match line {
+                    SnapshotLine::Step(_, Justification(_, _, deps, sdeps)) => {
+                        let deps = deps.iter().map(|dep| -> Coprod!(Coprod!(R, J), S) { Coproduct::inject(dep.clone()) });
+                        let sdeps = sdeps.iter().map(|sdep| -> Coprod!(Coprod!(R, J), S) { Coproduct::inject(sdep.clone()) });
+                        for dep in deps.chain(sdeps) {
+                            if !inside.contains(&dep) && !out.contains(&dep) {
+                                out.push(dep);
+                            }
+                        }
+                    }
+                    SnapshotLine::Subproof(_, sub) => cited(sub, inside, out),
+                }
+            }
+        }
+        let mut inside = HashSet::new();
+        contents(self, &mut inside);
+        let mut out = Vec::new();
+        cited(self, &inside, &mut out);
+        out
+    }
+}
+
+/// Mapping from the references in a `Snapshot` to the references of the
+/// copies made from it
+struct Remap<R, J, S> {
+    lines: HashMap<Coprod!(R, J), Coprod!(R, J)>,
+    subproofs: HashMap<S, S>,
+    /// Whether dependencies on lines that weren't copied are kept as-is, or
+    /// dropped because they don't mean anything where the copies are made
+    keep_unmapped: bool,
+}
+
+impl<R: Clone + Eq + Hash, J: Clone + Eq + Hash, S: Clone + Eq + Hash> Remap<R, J, S> {
+    fn new(keep_unmapped: bool) -> Self {
+        Remap { lines: HashMap::new(), subproofs: HashMap::new(), keep_unmapped }
+    }
+
+    /// Add copies of everything in `snapshot` to the end of `dst`
+    fn replay<P: Proof<PremiseReference = R, JustificationReference = J, SubproofReference = S>>(&mut self, dst: &mut P, snapshot: &Snapshot<R, J, S>) {
+        for (pr, e) in snapshot.premises.iter() {
+            let copy = dst.add_premise(e.clone());
+            self.lines.insert(Coproduct::inject(pr.clone()), Coproduct::inject(copy));
+        }
+        for line in snapshot.lines.iter() {
+            match line {
+                SnapshotLine::Step(jr, Justification(e, rule, deps, sdeps)) => {
+                    let deps = deps.iter().filter_map(|dep| self.lines.get(dep).cloned().or_else(|| if self.keep_unmapped { Some(dep.clone()) } else { None })).collect();
+                    let sdeps = sdeps.iter().filter_map(|sdep| self.subproofs.get(sdep).cloned().or_else(|| if self.keep_unmapped { Some(sdep.clone()) } else { None })).collect();
+                    let copy = dst.add_step(Justification(e.clone(), *rule, deps, sdeps));
+                    self.lines.insert(Coproduct::inject(jr.clone()), Coproduct::inject(copy));
+                }
+                SnapshotLine::Subproof(sr, sub) => {
+                    let copy = dst.add_subproof();
+                    self.subproofs.insert(sr.clone(), copy.clone());
+                    dst.with_mut_subproof(&copy, |dst| self.replay(dst, sub));
+                }
+            }
+        }
+    }
+}
+
+/// Everything removed by `Proof::remove_subproof_cascade`
+pub struct RemovedSubproof<P: Proof> {
+    /// Premises and steps that were inside the subproof, including inside
+    /// nested subproofs, in proof order
+    pub lines: Vec<PjRef<P>>,
+    /// The removed subproof and all subproofs nested in it, innermost first
+    pub subproofs: Vec<P::SubproofReference>,
+    /// Remaining steps that cited something that was removed
+    pub affected: Vec<P::JustificationReference>,
+}
+
+/// Reasons a subproof can't be extracted with `Proof::extract_subproof`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ExtractError<R, S> {
+    SubproofDoesNotExist(S),
+    /// Lines in the subproof cite these lines or subproofs outside of it
+    EscapingDependency { lines: Vec<Coprod!(R, S)> },
+}
+
+impl<R: std::fmt::Debug, S: std::fmt::Debug> std::fmt::Display for ExtractError<R, S> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        use ExtractError::*;
+        match self {
+            SubproofDoesNotExist(s) => write!(f, "The referenced subproof {s:?} does not exist."),
+            EscapingDependency { lines } => write!(f, "The subproof depends on lines outside of it: {lines:?}."),
+        }
+    }
+}
+
+/// Problems with the shape of a proof that no rule check can catch, such as
+/// those in a malformed file
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum StructuralError<R, S> {
+    /// The step `of` cites a line or subproof that isn't in the proof
+    DanglingReference { dep: Coprod!(R, S), of: R },
+    /// The step `of` cites itself, or a line or subproof that comes after it
+    ForwardReference { dep: Coprod!(R, S), of: R },
+    /// The step `of` cites an earlier line or subproof that isn't in scope,
+    /// such as one inside a sibling subproof or a subproof containing `of`
+    OutOfScope { dep: Coprod!(R, S), of: R },
+}
+
+impl<R: std::fmt::Debug, S: std::fmt::Debug> std::fmt::Display for StructuralError<R, S> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        use StructuralError::*;
+        match self {
+            DanglingReference { dep, of } => write!(f, "{of:?} references {dep:?}, which does not exist."),
+            ForwardReference { dep, of } => write!(f, "{of:?} references {dep:?}, which does not come before it."),
+            OutOfScope { dep, of } => write!(f, "{of:?} references {dep:?}, which is not in scope."),
+        }
+    }
+}
+
 /// A Justification struct represents a step in the proof.
 /// It contains an expression, a rule indicating why that expression is justified, and references to previous lines/subproofs for validating the rule.
 #[derive(Clone, Debug, PartialEq, Eq)]
@@ -377,6 +1613,106 @@ impl<T, R, S> Justification<T, R, S> {
     fn map0<U, F: FnOnce(T) -> U>(self, f: F) -> Justification<U, R, S> {
         Justification(f(self.0), self.1, self.2, self.3)
     }
+
+    /// Remove line dependencies if the rule takes none, and likewise for
+    /// subproof dependencies. This is used after the rule of a step changes,
+    /// so that dependencies which the new rule can't use don't linger.
+    /// Returns `true` if anything was removed.
+    pub fn drop_disallowed_deps(&mut self) -> bool {
+        let mut dropped = false;
+        if self.1.num_deps() == Some(0) && !self.2.is_empty() {
+            self.2.clear();
+            dropped = true;
+        }
+        if self.1.num_subdeps() == Some(0) && !self.3.is_empty() {
+            self.3.clear();
+            dropped = true;
+        }
+        dropped
+    }
+}
+
+impl<T, R: Ord, S: Ord> Justification<T, R, S> {
+    /// Sort the dependencies in their references' order and remove
+    /// duplicates, so that steps citing the same lines and subproofs compare
+    /// equal. Proofs do this whenever a step is added or changed.
+    pub fn normalize(&mut self) {
+        self.2.sort();
+        self.2.dedup();
+        self.3.sort();
+        self.3.dedup();
+    }
+}
+
+impl<T, R: Clone + Eq + Hash, J: Clone + Eq + Hash, S> Justification<T, Coprod!(R, J), S> {
+    /// The line dependencies, sorted by their line numbers in `proof`.
+    /// Dependencies that aren't in `proof` go last.
+    pub fn deps_in_display_order<P: Proof<PremiseReference = R, JustificationReference = J, SubproofReference = S>>(&self, proof: &P) -> Vec<Coprod!(R, J)> {
+        let line_numbers: HashMap<PjRef<P>, usize> = proof.iter_lines().filter_map(|info| Some((info.reference.subset().ok()?, info.line?))).collect();
+        let mut deps = self.2.clone();
+        deps.sort_by_key(|dep| line_numbers.get(dep).copied().unwrap_or(usize::MAX));
+        deps
+    }
+
+    /// How the step cites each of its dependencies, given the number of each
+    /// line: its lines by number, then its subproofs by the lines they span,
+    /// each in order. Dependencies that can't be numbered go last.
+    pub fn citations<P: Proof<PremiseReference = R, JustificationReference = J, SubproofReference = S>>(&self, proof: &P, line_number: impl Fn(&PjRef<P>) -> Option<usize>) -> Vec<Citation<Coprod!(R, J), S>>
+    where
+        S: Clone,
+    {
+        let mut lines = self.2.iter().map(|dep| line_number(dep).map_or_else(|| Citation::MissingLine(dep.clone()), |line| Citation::Line { dep: dep.clone(), line })).collect::<Vec<_>>();
+        let mut subproofs = self
+            .3
+            .iter()
+            .map(|sdep| match proof.lookup_subproof(sdep) {
+                None => Citation::MissingSubproof(sdep.clone()),
+                Some(sub) => sub.line_span(&line_number).map_or_else(|| Citation::EmptySubproof(sdep.clone()), |span| Citation::Lines { sdep: sdep.clone(), span }),
+            })
+            .collect::<Vec<_>>();
+        lines.sort_by_key(Citation::span);
+        subproofs.sort_by_key(Citation::span);
+        lines.extend(subproofs);
+        lines
+    }
+}
+
+/// How a step cites one of its dependencies, which is written as the number of
+/// a line or the range of lines a subproof spans, or `?` if it has no lines
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Citation<R, S> {
+    /// The line `dep`, on line `line`
+    Line { dep: R, line: usize },
+    /// The subproof `sdep`, which spans `span`
+    Lines { sdep: S, span: RangeInclusive<usize> },
+    /// A line that isn't in the proof, such as one that was deleted
+    MissingLine(R),
+    /// A subproof that isn't in the proof
+    MissingSubproof(S),
+    /// A subproof without any lines
+    EmptySubproof(S),
+}
+
+impl<R, S> Citation<R, S> {
+    /// The first and last lines cited, or `usize::MAX` for both if there
+    /// aren't any, so that those sort last
+    fn span(&self) -> (usize, usize) {
+        match self {
+            Citation::Line { line, .. } => (*line, *line),
+            Citation::Lines { span, .. } => (*span.start(), *span.end()),
+            Citation::MissingLine(_) | Citation::MissingSubproof(_) | Citation::EmptySubproof(_) => (usize::MAX, usize::MAX),
+        }
+    }
+}
+
+impl<R, S> std::fmt::Display for Citation<R, S> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Citation::Line { line, .. } => write!(f, "{line}"),
+            Citation::Lines { span, .. } => write!(f, "{}-{}", span.start(), span.end()),
+            Citation::MissingLine(_) | Citation::MissingSubproof(_) | Citation::EmptySubproof(_) => write!(f, "?"),
+        }
+    }
 }
 
 pub trait JustificationExprDisplay {
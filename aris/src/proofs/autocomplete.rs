@@ -0,0 +1,118 @@
+/*!
+Small, predictable completions of steps whose formula has been written but whose rule hasn't.
+
+Only steps justified by the placeholder `EmptyRule` are filled in, and only from a single earlier line that's in scope for them:
+1. a line with the same formula, cited with Reiteration, or otherwise
+2. a conjunction with the formula as one of its conjuncts, cited with ∧ Elimination.
+
+When several lines qualify, the earliest one is used, so the result doesn't depend on how the proof stores its lines.
+
+# Example
+```
+#[macro_use] extern crate frunk_core;
+use aris::expr::Expr;
+use aris::parser::parse_unwrap as p;
+use aris::proofs::autocomplete::fill_trivial;
+use aris::proofs::pooledproof::PooledProof;
+use aris::proofs::{Justification, Proof};
+use aris::rules::RuleM;
+
+let mut prf = PooledProof::<HList![Expr]>::new();
+prf.add_premise(p("A & B"));
+let step = prf.add_step(Justification(p("B"), RuleM::EmptyRule, vec![], vec![]));
+assert_eq!(fill_trivial(&mut prf, &step), Some(RuleM::AndElim));
+assert!(prf.verify_all().is_fully_verified());
+```
+*/
+
+use super::Justification;
+use super::PjRef;
+use super::Proof;
+use crate::expr::Expr;
+use crate::expr::Op;
+use crate::rules::Rule;
+use crate::rules::RuleM;
+
+use std::collections::HashSet;
+
+/// Fill in the rule and dependency of the step `target`, if its rule is the
+/// placeholder and an earlier line in scope is the same formula or a
+/// conjunction containing it. Returns the rule that was set.
+pub fn fill_trivial<P: Proof>(prf: &mut P, target: &P::JustificationReference) -> Option<Rule> {
+    let Justification(conclusion, rule, _, _) = prf.lookup_step(target)?;
+    if rule != RuleM::EmptyRule {
+        return None;
+    }
+    let line: PjRef<P> = frunk_core::coproduct::Coproduct::inject(target.clone());
+    let mut in_scope = HashSet::new();
+    prf.possible_deps_for_line(&line, &mut in_scope, &mut HashSet::new());
+    let candidates = prf.iter_lines().filter_map(|info| info.reference.subset().ok()).filter(|r: &PjRef<P>| in_scope.contains(r)).filter_map(|r| Some((prf.lookup_expr(&r)?, r))).collect::<Vec<_>>();
+    let is_conjunct = |e: &Expr| matches!(e, Expr::Assoc { op: Op::And, exprs } if exprs.contains(&conclusion));
+    let (rule, dep) = match candidates.iter().find(|(e, _)| *e == conclusion) {
+        Some((_, dep)) => (RuleM::Reit, dep.clone()),
+        None => (RuleM::AndElim, candidates.iter().find(|(e, _)| is_conjunct(e))?.1.clone()),
+    };
+    prf.with_mut_step(target, |Justification(_, r, deps, sdeps)| {
+        *r = rule;
+        *deps = vec![dep];
+        sdeps.clear();
+    })?;
+    Some(rule)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::parser::parse_unwrap as p;
+    use crate::proofs::pooledproof::PooledProof;
+
+    use frunk_core::coproduct::Coproduct;
+    use frunk_core::HList;
+
+    type P = PooledProof<HList![Expr]>;
+
+    fn blank(e: &str) -> Justification<Expr, PjRef<P>, <P as Proof>::SubproofReference> {
+        Justification(p(e), RuleM::EmptyRule, vec![], vec![])
+    }
+
+    #[test]
+    fn test_fill_trivial() {
+        let mut prf = P::new();
+        let p1 = prf.add_premise(p("A"));
+        let p2 = prf.add_premise(p("B & C & D & E & F"));
+        let sub = prf.add_subproof();
+        let (s1, s2, s3) = prf
+            .with_mut_subproof(&sub, |sub| {
+                sub.add_premise(p("G"));
+                (sub.add_step(blank("A")), sub.add_step(blank("E")), sub.add_step(blank("H")))
+            })
+            .unwrap();
+
+        // An outer premise, with the same formula
+        assert_eq!(fill_trivial(&mut prf, &s1), Some(RuleM::Reit));
+        assert_eq!(prf.lookup_step(&s1), Some(Justification(p("A"), RuleM::Reit, vec![Coproduct::inject(p1)], vec![])));
+        // A conjunct in the middle of a five-way conjunction
+        assert_eq!(fill_trivial(&mut prf, &s2), Some(RuleM::AndElim));
+        assert_eq!(prf.lookup_step(&s2).unwrap().2, vec![Coproduct::inject(p2)]);
+        // Nothing matches, or the rule is already set
+        assert_eq!(fill_trivial(&mut prf, &s3), None);
+        assert_eq!(prf.lookup_step(&s3), Some(blank("H")));
+        assert_eq!(fill_trivial(&mut prf, &s1), None);
+        assert!(prf.verify_line(&Coproduct::inject(s1)).is_ok());
+        assert!(prf.verify_line(&Coproduct::inject(s2)).is_ok());
+
+        // With two candidates, the earliest is cited, and lines inside a closed subproof aren't candidates
+        let s4 = prf.add_step(blank("A"));
+        let s5 = prf.add_step(blank("A"));
+        assert_eq!(fill_trivial(&mut prf, &s5), Some(RuleM::Reit));
+        assert_eq!(prf.lookup_step(&s5).unwrap().2, vec![Coproduct::inject(p1)]);
+        assert_eq!(fill_trivial(&mut prf, &s4), Some(RuleM::Reit));
+        assert_eq!(prf.lookup_step(&s4).unwrap().2, vec![Coproduct::inject(p1)]);
+        let s6 = prf.add_step(blank("E"));
+        let s7 = prf.add_step(blank("G"));
+        assert_eq!(fill_trivial(&mut prf, &s6), Some(RuleM::AndElim));
+        assert_eq!(prf.lookup_step(&s6).unwrap().2, vec![Coproduct::inject(p2)]);
+        assert_eq!(fill_trivial(&mut prf, &s7), None);
+    }
+}
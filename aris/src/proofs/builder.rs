@@ -0,0 +1,257 @@
+/*!
+A fluent builder for writing proofs by hand, such as test fixtures and assignment templates.
+
+Formulas are given as strings, which are parsed when they're added, and dependencies are given as display line numbers with the `deps!` macro.
+Lines are numbered the same way as in the UI: each subproof's premises, then its lines, with nested subproofs numbered in place.
+A step can only cite lines and subproofs that come before it, so a bad line number is reported when the proof is built rather than when it's checked.
+
+# Example
+```
+#[macro_use] extern crate frunk_core;
+use aris::deps;
+use aris::expr::Expr;
+use aris::proofs::builder::ProofBuilder;
+use aris::proofs::pooledproof::PooledProof;
+use aris::proofs::Proof;
+use aris::rules::RuleM;
+
+// 1 | P -> Q
+//   | ---
+// 2 | | P
+//   | | ---
+// 3 | | Q ; ImpElim [1, 2]
+// 4 | P -> Q ; ImpIntro [2..3]
+let prf: PooledProof<HList![Expr]> = ProofBuilder::new()
+    .premise("P -> Q")
+    .subproof(|b| b.premise("P").step("Q", RuleM::ImpElim, deps![1, 2]))
+    .step("P -> Q", RuleM::ImpIntro, deps![; 2..=3])
+    .build()
+    .unwrap();
+assert!(prf.verify_all().is_fully_verified());
+```
+*/
+
+use super::tree::TreeError;
+use super::tree::TreeLine;
+use super::tree::TreeProof;
+use super::PjRef;
+use super::Proof;
+use crate::rules::Rule;
+
+use std::ops::RangeInclusive;
+
+/// The lines and subproofs a step cites, by display line number. This is
+/// usually written with the `deps!` macro.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Deps {
+    pub lines: Vec<usize>,
+    pub subproofs: Vec<RangeInclusive<usize>>,
+}
+
+/// Dependencies for `ProofBuilder::step`: line numbers, then optionally a
+/// semicolon and the line ranges of subproofs.
+///
+/// ```
+/// use aris::deps;
+/// let d = deps![1, 2; 3..=5];
+/// assert_eq!(d.lines, vec![1, 2]);
+/// assert_eq!(d.subproofs, vec![3..=5]);
+/// assert_eq!(deps![; 3..=5].lines, Vec::<usize>::new());
+/// ```
+#[macro_export]
+macro_rules! deps {
+    ($($line:expr),* $(; $($subproof:expr),*)?) => {
+        $crate::proofs::builder::Deps { lines: vec![$($line),*], subproofs: vec![$($($subproof),*)?] }
+    };
+}
+
+/// Reasons a `ProofBuilder` can't build its proof
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum BuildError {
+    /// The formula for line `line` doesn't parse
+    Parse { line: usize, input: String },
+    /// A premise was added to a subproof after one of its steps, which would
+    /// number it differently than the UI does
+    PremiseAfterStep { line: usize },
+    /// The step on line `cited_by` cites line `line`, which doesn't come before it
+    NoSuchLine { line: usize, cited_by: usize },
+    /// The step on line `cited_by` cites the subproof spanning `lines`, but no
+    /// subproof before it spans them
+    NoSuchSubproof { lines: RangeInclusive<usize>, cited_by: usize },
+}
+
+impl std::fmt::Display for BuildError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            BuildError::Parse { line, input } => write!(f, "Line {line}: failed to parse {input:?}."),
+            BuildError::PremiseAfterStep { line } => write!(f, "Line {line}: premises must come before the steps of their subproof."),
+            BuildError::NoSuchLine { line, cited_by } => write!(f, "Line {cited_by} cites line {line}, which doesn't come before it."),
+            BuildError::NoSuchSubproof { lines, cited_by } => write!(f, "Line {} cites lines {}-{}, which aren't a subproof before it.", cited_by, lines.start(), lines.end()),
+        }
+    }
+}
+
+impl From<TreeError> for BuildError {
+    fn from(e: TreeError) -> BuildError {
+        match e {
+            TreeError::NoSuchLine { line, cited_by } => BuildError::NoSuchLine { line, cited_by },
+            TreeError::NoSuchSubproof { lines, cited_by } => BuildError::NoSuchSubproof { lines, cited_by },
+        }
+    }
+}
+
+/// Builds a proof one line at a time. The first error is kept and returned
+/// by `build`, so calls can be chained without checking each one.
+#[derive(Clone, Debug)]
+pub struct ProofBuilder {
+    tree: TreeProof,
+    /// Number of lines before the next one, including those of enclosing subproofs
+    lines: usize,
+    /// Spans of the subproofs closed so far
+    subproofs: Vec<RangeInclusive<usize>>,
+    error: Option<BuildError>,
+}
+
+impl Default for ProofBuilder {
+    fn default() -> Self {
+        ProofBuilder::new()
+    }
+}
+
+impl ProofBuilder {
+    pub fn new() -> Self {
+        ProofBuilder { tree: TreeProof { premises: vec![], lines: vec![] }, lines: 0, subproofs: vec![], error: None }
+    }
+
+    fn fail(&mut self, error: BuildError) {
+        self.error.get_or_insert(error);
+    }
+
+    fn parse(&mut self, input: &str) -> Option<crate::expr::Expr> {
+        let e = crate::parser::parse(input);
+        if e.is_none() {
+            self.fail(BuildError::Parse { line: self.lines, input: input.into() });
+        }
+        e
+    }
+
+    /// Add a premise to the current subproof
+    pub fn premise(mut self, formula: &str) -> Self {
+        self.lines += 1;
+        if !self.tree.lines.is_empty() {
+            self.fail(BuildError::PremiseAfterStep { line: self.lines });
+        }
+        if let Some(e) = self.parse(formula) {
+            self.tree.premises.push(e);
+        }
+        self
+    }
+
+    /// Add a step to the current subproof
+    pub fn step(mut self, formula: &str, rule: Rule, deps: Deps) -> Self {
+        self.lines += 1;
+        let cited_by = self.lines;
+        if let Some(&line) = deps.lines.iter().find(|line| **line == 0 || **line >= cited_by) {
+            self.fail(BuildError::NoSuchLine { line, cited_by });
+        }
+        if let Some(lines) = deps.subproofs.iter().find(|span| !self.subproofs.contains(span)) {
+            self.fail(BuildError::NoSuchSubproof { lines: lines.clone(), cited_by });
+        }
+        if let Some(expr) = self.parse(formula) {
+            self.tree.lines.push(TreeLine::Step { expr, rule, deps: deps.lines, sdeps: deps.subproofs });
+        }
+        self
+    }
+
+    /// Add a subproof to the current subproof, filled in by `f`
+    pub fn subproof<F: FnOnce(ProofBuilder) -> ProofBuilder>(mut self, f: F) -> Self {
+        let first = self.lines + 1;
+        let inner = ProofBuilder { tree: TreeProof { premises: vec![], lines: vec![] }, lines: self.lines, subproofs: std::mem::take(&mut self.subproofs), error: self.error.take() };
+        let inner = f(inner);
+        self.lines = inner.lines;
+        self.subproofs = inner.subproofs;
+        self.error = inner.error;
+        if self.lines >= first {
+            self.subproofs.push(first..=self.lines);
+        }
+        self.tree.lines.push(TreeLine::Subproof(inner.tree));
+        self
+    }
+
+    /// The proof built so far, as a `TreeProof`
+    pub fn into_tree(self) -> Result<TreeProof, BuildError> {
+        match self.error {
+            Some(e) => Err(e),
+            None => Ok(self.tree),
+        }
+    }
+
+    /// Build the proof
+    pub fn build<P: Proof>(self) -> Result<P, BuildError> {
+        Ok(self.into_tree()?.to_proof()?)
+    }
+
+    /// Build the proof, along with the reference of each line, indexed by
+    /// line number - 1
+    pub fn build_with_lines<P: Proof>(self) -> Result<(P, Vec<PjRef<P>>), BuildError> {
+        let prf: P = self.build()?;
+        let lines = prf.iter_lines().filter_map(|info| info.reference.subset().ok()).collect();
+        Ok((prf, lines))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::expr::Expr;
+    use crate::parser::parse_unwrap as p;
+    use crate::proofs::pooledproof::PooledProof;
+    use crate::proofs::Justification;
+    use crate::rules::RuleM;
+
+    use frunk_core::coproduct::Coproduct;
+    use frunk_core::HList;
+
+    type P = PooledProof<HList![Expr]>;
+
+    #[test]
+    fn test_nested_numbering() {
+        /*
+        1 | A
+          | ----------
+        2 | | B
+          | | ----------
+        3 | | | C
+          | | | ----------
+        4 | | | A ; Reit [1]
+        5 | | C -> A ; ImpIntro [3..4]
+        6 | B -> (C -> A) ; ImpIntro [2..5]
+        */
+        let (prf, lines) = ProofBuilder::new()
+            .premise("A")
+            .subproof(|b| b.premise("B").subproof(|b| b.premise("C").step("A", RuleM::Reit, deps![1])).step("C -> A", RuleM::ImpIntro, deps![; 3..=4]))
+            .step("B -> (C -> A)", RuleM::ImpIntro, deps![; 2..=5])
+            .build_with_lines::<P>()
+            .unwrap();
+        assert!(prf.verify_all().is_fully_verified());
+        assert_eq!(lines.len(), 6);
+        let numbered = prf.iter_lines().filter_map(|info| Some((info.line?, prf.lookup_expr(&info.reference.subset().ok()?)?))).collect::<Vec<_>>();
+        assert_eq!(numbered, vec![(1, p("A")), (2, p("B")), (3, p("C")), (4, p("A")), (5, p("C -> A")), (6, p("B -> (C -> A)"))]);
+        let Justification(_, _, deps, _) = prf.lookup_step(&Coproduct::uninject(lines[3]).unwrap()).unwrap();
+        assert_eq!(deps, vec![lines[0]]);
+    }
+
+    #[test]
+    fn test_errors() {
+        let build = |b: ProofBuilder| b.build::<P>().unwrap_err();
+        assert_eq!(build(ProofBuilder::new().premise("A").step("A", RuleM::Reit, deps![2])), BuildError::NoSuchLine { line: 2, cited_by: 2 });
+        assert_eq!(build(ProofBuilder::new().premise("A").step("A", RuleM::Reit, deps![0])), BuildError::NoSuchLine { line: 0, cited_by: 2 });
+        assert_eq!(build(ProofBuilder::new().premise("A").step("A &", RuleM::AndIntro, deps![1])), BuildError::Parse { line: 2, input: "A &".into() });
+        assert_eq!(build(ProofBuilder::new().step("A", RuleM::Reit, deps![]).premise("A")), BuildError::PremiseAfterStep { line: 2 });
+        // a subproof can't be cited from inside itself, and the first error is the one kept
+        let b = ProofBuilder::new().subproof(|b| b.premise("A").step("A -> A", RuleM::ImpIntro, deps![; 1..=2])).step("B", RuleM::Reit, deps![5]);
+        assert_eq!(build(b), BuildError::NoSuchSubproof { lines: 1..=2, cited_by: 2 });
+        assert!(ProofBuilder::new().subproof(|b| b.premise("A")).step("A -> A", RuleM::ImpIntro, deps![; 1..=1]).build::<P>().is_ok());
+    }
+}
@@ -0,0 +1,276 @@
+/*!
+Remembers the result of checking each line, so that redrawing a proof only re-checks the lines an edit could have affected.
+
+Each result is stored with a fingerprint of everything checking the line looks at: its formula, rule, and dependencies, the formulas of the lines it cites, the contents of the subproofs it cites, the formulas of the lines outside those subproofs that they depend on (which ∀ Introduction and ∃ Elimination check for the variable they generalize), and whether it may cite them from where it is.
+A cached result is only used while the fingerprint still matches, so an invalidation that's missed costs a re-check rather than a wrong answer.
+`invalidate_for_change` drops the results an edit affects up front, using the steps that cite the edited line.
+
+# Example
+```
+#[macro_use] extern crate frunk_core;
+use aris::expr::Expr;
+use aris::parser::parse_unwrap as p;
+use aris::proofs::cache::VerificationCache;
+use aris::proofs::pooledproof::PooledProof;
+use aris::proofs::{Justification, Proof};
+use aris::rules::RuleM;
+use frunk_core::coproduct::Coproduct;
+
+let mut prf = PooledProof::<HList![Expr]>::new();
+let r1 = prf.add_premise(p("A"));
+let r2 = prf.add_step(Justification(p("A"), RuleM::Reit, vec![Coproduct::inject(r1)], vec![]));
+let mut cache = VerificationCache::new();
+assert!(cache.get_or_verify(&prf, &Coproduct::inject(r2)).is_ok());
+assert!(cache.contains(&Coproduct::inject(r2)));
+
+prf.with_mut_premise(&r1, |e| *e = p("B"));
+cache.invalidate_for_change(&Coproduct::inject(r1), &prf);
+assert!(!cache.contains(&Coproduct::inject(r2)));
+assert!(cache.get_or_verify(&prf, &Coproduct::inject(r2)).is_err());
+```
+*/
+
+use super::pj_to_pjs;
+use super::Justification;
+use super::PjRef;
+use super::Proof;
+use crate::rules::CheckContext;
+use crate::rules::ProofCheckError;
+use crate::rules::RuleT;
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::BTreeSet;
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::hash::Hash;
+use std::hash::Hasher;
+
+use frunk_core::coproduct::Coproduct;
+
+type CheckResult<P> = Result<(), ProofCheckError<PjRef<P>, <P as Proof>::SubproofReference>>;
+
+/// Results of `Proof::verify_line_with`, by line
+pub struct VerificationCache<P: Proof> {
+    entries: HashMap<PjRef<P>, (u64, CheckResult<P>)>,
+}
+
+impl<P: Proof> Default for VerificationCache<P> {
+    fn default() -> Self {
+        VerificationCache::new()
+    }
+}
+
+impl<P: Proof> VerificationCache<P> {
+    pub fn new() -> Self {
+        VerificationCache { entries: HashMap::new() }
+    }
+
+    /// The result of checking the line `r`, which is only checked again if it
+    /// or something it cites has changed since it was last checked
+    pub fn get_or_verify(&mut self, prf: &P, r: &PjRef<P>) -> CheckResult<P> {
+        self.get_or_verify_with(prf, r, &CheckContext::default())
+    }
+
+    /// Like `get_or_verify`, checking with the settings in `ctx`
+    pub fn get_or_verify_with(&mut self, prf: &P, r: &PjRef<P>, ctx: &CheckContext) -> CheckResult<P> {
+        let fingerprint = fingerprint(prf, r, ctx);
+        match self.entries.get(r) {
+            Some((cached, result)) if *cached == fingerprint => result.clone(),
+            _ => {
+                let result = prf.verify_line_with(r, ctx);
+                self.entries.insert(r.clone(), (fingerprint, result.clone()));
+                result
+            }
+        }
+    }
+
+    /// Whether a result is cached for the line `r`
+    pub fn contains(&self, r: &PjRef<P>) -> bool {
+        self.entries.contains_key(r)
+    }
+
+    /// Drop the results an edit to the line `changed` can affect: its own,
+    /// and those of the steps that cite it or a subproof containing it,
+    /// transitively. Call this after editing a line, or before deleting it.
+    pub fn invalidate_for_change(&mut self, changed: &PjRef<P>, prf: &P) {
+        let mut stack = vec![changed.clone()];
+        let mut seen = HashSet::new();
+        while let Some(r) = stack.pop() {
+            if !seen.insert(r.clone()) {
+                continue;
+            }
+            self.entries.remove(&r);
+            stack.extend(prf.dependents_of(&r).into_iter().map(Coproduct::inject));
+            let mut parent = prf.parent_of_line(&pj_to_pjs::<P>(r));
+            while let Some(sr) = parent {
+                stack.extend(prf.dependents_of_subproof(&sr).into_iter().map(Coproduct::inject));
+                parent = prf.parent_of_line(&Coproduct::inject(sr));
+            }
+        }
+    }
+
+    /// Drop every result, e.g. after the proof's references are renumbered by
+    /// `compact`
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
+
+/// A hash of everything `Proof::verify_line_with` looks at when checking `r`
+fn fingerprint<P: Proof>(prf: &P, r: &PjRef<P>, ctx: &CheckContext) -> u64 {
+    use frunk_core::coproduct::Coproduct::{Inl, Inr};
+    let mut hasher = DefaultHasher::new();
+    ctx.profile.hash(&mut hasher);
+    ctx.budget.steps.get().hash(&mut hasher);
+    match r {
+        Inl(pr) => prf.lookup_premise(pr).hash(&mut hasher),
+        Inr(Inl(jr)) => {
+            let step = prf.lookup_step(jr);
+            step.is_some().hash(&mut hasher);
+            if let Some(Justification(expr, rule, deps, sdeps)) = step {
+                (expr, rule.get_name()).hash(&mut hasher);
+                for dep in deps {
+                    (prf.lookup_expr(&dep), dep).hash(&mut hasher);
+                }
+                for sdep in sdeps {
+                    sdep.hash(&mut hasher);
+                    if let Some(sub) = prf.lookup_subproof(&sdep) {
+                        hash_subproof(&sub, &mut hasher);
+                        hash_outside_dependencies(&sub, &mut hasher);
+                    }
+                }
+                prf.check_references(r).hash(&mut hasher);
+            }
+        }
+        Inr(Inr(void)) => match *void {},
+    }
+    hasher.finish()
+}
+
+fn hash_subproof<S: Proof>(sub: &S, hasher: &mut DefaultHasher) {
+    use frunk_core::coproduct::Coproduct::{Inl, Inr};
+    for pr in sub.premises() {
+        (sub.lookup_premise(&pr), pr).hash(hasher);
+    }
+    for line in sub.lines() {
+        match line {
+            Inl(jr) => {
+                if let Some(Justification(expr, rule, deps, sdeps)) = sub.lookup_step(&jr) {
+                    (jr, expr, rule.get_name(), deps, sdeps).hash(hasher);
+                }
+            }
+            Inr(Inl(sr)) => {
+                if let Some(nested) = sub.lookup_subproof(&sr) {
+                    hash_subproof(&nested, hasher);
+                }
+                sr.hash(hasher);
+            }
+            Inr(Inr(void)) => match void {},
+        }
+    }
+}
+
+/// Hash the formulas of the lines outside `sub` that its lines depend on,
+/// transitively, in a fixed order
+fn hash_outside_dependencies<S: Proof>(sub: &S, hasher: &mut DefaultHasher) {
+    let contained = sub.contained_justifications(true);
+    let outside = contained.iter().flat_map(|r| sub.transitive_dependencies(r.clone())).filter(|r| !contained.contains(r)).collect::<BTreeSet<_>>();
+    for r in outside {
+        (sub.lookup_expr(&r), r).hash(hasher);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::deps;
+    use crate::expr::Expr;
+    use crate::parser::parse_unwrap as p;
+    use crate::proofs::builder::ProofBuilder;
+    use crate::proofs::pooledproof::PooledProof;
+    use crate::rules::RuleM;
+
+    use frunk_core::HList;
+
+    type P = PooledProof<HList![Expr]>;
+
+    #[test]
+    fn test_invalidate_for_change() {
+        /*
+        1 | A
+        2 | B
+          | ----------
+        3 | A & B ; AndIntro [1, 2]
+        4 | A ; AndElim [3]
+        5 | B ; Reit [2]
+        6 | | C
+          | | ----------
+        7 | | A ; Reit [1]
+        8 | C -> A ; ImpIntro [6..7]
+        9 | B ; Reit [5]
+        */
+        let (mut prf, lines) = ProofBuilder::new()
+            .premise("A")
+            .premise("B")
+            .step("A & B", RuleM::AndIntro, deps![1, 2])
+            .step("A", RuleM::AndElim, deps![3])
+            .step("B", RuleM::Reit, deps![2])
+            .subproof(|b| b.premise("C").step("A", RuleM::Reit, deps![1]))
+            .step("C -> A", RuleM::ImpIntro, deps![; 6..=7])
+            .step("B", RuleM::Reit, deps![5])
+            .build_with_lines::<P>()
+            .unwrap();
+        let mut cache = VerificationCache::new();
+        let fill = |cache: &mut VerificationCache<P>, prf: &P| lines.iter().all(|r| cache.get_or_verify(prf, r).is_ok());
+        let cached = |cache: &VerificationCache<P>| (1..=lines.len()).filter(|n| cache.contains(&lines[n - 1])).collect::<Vec<_>>();
+        assert!(fill(&mut cache, &prf));
+        assert_eq!(cached(&cache), vec![1, 2, 3, 4, 5, 6, 7, 8, 9]);
+
+        // Editing a premise drops the steps citing it, the steps citing those, and the steps citing a subproof that cites it
+        let p1 = prf.premises()[0];
+        prf.with_mut_premise(&p1, |e| *e = p("D"));
+        cache.invalidate_for_change(&lines[0], &prf);
+        assert_eq!(cached(&cache), vec![2, 5, 6, 9]);
+        assert!(cache.get_or_verify(&prf, &lines[2]).is_err());
+        assert!(cache.get_or_verify(&prf, &lines[4]).is_ok());
+
+        // An edit that nothing cites only drops its own line
+        prf.with_mut_premise(&p1, |e| *e = p("A"));
+        cache.invalidate_for_change(&lines[0], &prf);
+        assert!(fill(&mut cache, &prf));
+        let last: <P as Proof>::JustificationReference = Coproduct::uninject(lines[8]).unwrap();
+        prf.with_mut_step(&last, |j| j.0 = p("E"));
+        cache.invalidate_for_change(&lines[8], &prf);
+        assert_eq!(cached(&cache), vec![1, 2, 3, 4, 5, 6, 7, 8]);
+
+        // A stale entry that wasn't invalidated is checked again rather than reused
+        let p2 = prf.premises()[1];
+        prf.with_mut_premise(&p2, |e| *e = p("F"));
+        assert!(cache.get_or_verify(&prf, &lines[4]).is_err());
+    }
+
+    #[test]
+    fn test_outside_dependencies() {
+        /*
+        1 | ∀x P(x)
+          | ----------
+        2 | | P(a) ; ForallElim [1]
+        3 | ∀y P(y) ; ForallIntro [2..2]
+        */
+        let (mut prf, lines) = ProofBuilder::new()
+            .premise("forall x P(x)")
+            .subproof(|b| b.step("P(a)", RuleM::ForallElim, deps![1]))
+            .step("forall y P(y)", RuleM::ForallIntro, deps![; 2..=2])
+            .build_with_lines::<P>()
+            .unwrap();
+        let mut cache = VerificationCache::new();
+        assert!(cache.get_or_verify(&prf, &lines[2]).is_ok());
+
+        // ∀ Introduction reads the premise through the subproof, so editing it
+        // to mention a is noticed even without invalidating anything
+        let p1 = prf.premises()[0];
+        prf.with_mut_premise(&p1, |e| *e = p("forall x (P(x) & Q(a))"));
+        assert!(cache.get_or_verify(&prf, &lines[2]).is_err());
+    }
+}
@@ -0,0 +1,298 @@
+/*!
+Line-by-line comparison of two versions of a proof, e.g. a student's two submissions, or a proof before and after a transformation.
+
+Lines are matched up by stable ID, so a line that was edited is still recognized as the same line.
+Lines that aren't matched that way, e.g. ones from proof types without stable IDs, are matched by a longest common subsequence of their depth and formula.
+Lines of two proofs that were written separately can share stable IDs by coincidence, so comparing them reports most lines as modified.
+
+Dependencies are compared by which lines they refer to, so lines being renumbered by an insertion above them isn't a change.
+
+# Example
+```
+#[macro_use] extern crate frunk_core;
+use aris::expr::Expr;
+use aris::parser::parse_unwrap as p;
+use aris::proofs::diff::{diff, LineChange};
+use aris::proofs::pooledproof::PooledProof;
+use aris::proofs::Proof;
+
+let mut a = PooledProof::<HList![Expr]>::new();
+a.add_premise(p("A"));
+let mut b = PooledProof::<HList![Expr]>::new();
+b.add_premise(p("A"));
+b.add_premise(p("B"));
+let d = diff(&a, &b);
+assert_eq!(d.changes, vec![LineChange::Inserted { new_line: 2, expr: p("B") }]);
+assert_eq!(d.render_text(), "--- a\n+++ b\n+2: B\n");
+```
+*/
+
+use super::pj_to_pjs;
+use super::Justification;
+use super::PjRef;
+use super::Proof;
+use crate::expr::Expr;
+use crate::rules::Rule;
+use crate::rules::RuleT;
+
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::ops::RangeInclusive;
+
+use frunk_core::coproduct::Coproduct;
+
+/// A difference between two versions of a proof, with the display line
+/// numbers of the line in the old version and the new one
+#[derive(Clone, Debug, PartialEq)]
+pub enum LineChange {
+    Removed { old_line: usize, expr: Expr },
+    Inserted { new_line: usize, expr: Expr },
+    /// The line's formula changed from `old` to `new`
+    Modified { old_line: usize, new_line: usize, old: Expr, new: Expr },
+    RuleChanged { old_line: usize, new_line: usize, old: Rule, new: Rule },
+    /// The step cites different lines or subproofs. Each version's dependencies
+    /// are numbered as in that version, in ascending order.
+    DepsChanged { old_line: usize, new_line: usize, old_deps: Vec<usize>, new_deps: Vec<usize>, old_sdeps: Vec<RangeInclusive<usize>>, new_sdeps: Vec<RangeInclusive<usize>> },
+}
+
+/// The changes between two versions of a proof, in line order
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ProofDiff {
+    pub changes: Vec<LineChange>,
+}
+
+impl ProofDiff {
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    /// A summary in the style of a unified diff: a line with `-` for each
+    /// removed line, `+` for each inserted one, both for a changed formula,
+    /// and `~` with both line numbers for a changed rule or dependencies
+    pub fn render_text(&self) -> String {
+        fn ranges(sdeps: &[RangeInclusive<usize>]) -> String {
+            sdeps.iter().map(|span| format!("{}-{}", span.start(), span.end())).collect::<Vec<_>>().join(", ")
+        }
+        fn deps(lines: &[usize], sdeps: &[RangeInclusive<usize>]) -> String {
+            let lines = lines.iter().map(|line| line.to_string()).collect::<Vec<_>>().join(", ");
+            if sdeps.is_empty() {
+                format!("[{lines}]")
+            } else {
+                format!("[{}; {}]", lines, ranges(sdeps))
+            }
+        }
+        let mut out = String::from("--- a\n+++ b\n");
+        for change in &self.changes {
+            let line = match change {
+                LineChange::Removed { old_line, expr } => format!("-{old_line}: {expr}"),
+                LineChange::Inserted { new_line, expr } => format!("+{new_line}: {expr}"),
+                LineChange::Modified { old_line, new_line, old, new } => format!("-{old_line}: {old}\n+{new_line}: {new}"),
+                LineChange::RuleChanged { old_line, new_line, old, new } => format!("~{}/{}: rule {} -> {}", old_line, new_line, old.get_name(), new.get_name()),
+                LineChange::DepsChanged { old_line, new_line, old_deps, new_deps, old_sdeps, new_sdeps } => format!("~{}/{}: deps {} -> {}", old_line, new_line, deps(old_deps, old_sdeps), deps(new_deps, new_sdeps)),
+            };
+            out += &line;
+            out += "\n";
+        }
+        out
+    }
+}
+
+/// A numbered line of one side of the diff
+struct Line<P: Proof> {
+    r: PjRef<P>,
+    number: usize,
+    depth: usize,
+    expr: Expr,
+}
+
+fn lines_of<P: Proof>(prf: &P) -> Vec<Line<P>> {
+    prf.iter_lines()
+        .filter_map(|info| {
+            let r: PjRef<P> = info.reference.subset().ok()?;
+            Some(Line { number: info.line?, depth: info.depth, expr: prf.lookup_expr(&r)?, r })
+        })
+        .collect()
+}
+
+/// For each line of `old`, the index of the line of `new` it matches
+fn align<P: Proof>(a: &P, b: &P, old: &[Line<P>], new: &[Line<P>]) -> Vec<Option<usize>> {
+    let new_index: HashMap<PjRef<P>, usize> = new.iter().enumerate().map(|(j, line)| (line.r.clone(), j)).collect();
+    let mut matched = vec![None; old.len()];
+    let mut taken = vec![false; new.len()];
+    for (i, line) in old.iter().enumerate() {
+        if let Some(&j) = a.stable_id_of(&line.r).and_then(|id| b.ref_of_stable_id(id)).and_then(|r| new_index.get(&r)) {
+            if !taken[j] {
+                matched[i] = Some(j);
+                taken[j] = true;
+            }
+        }
+    }
+
+    // Longest common subsequence of what's left, by depth and formula
+    let rest_old = (0..old.len()).filter(|i| matched[*i].is_none()).collect::<Vec<_>>();
+    let rest_new = (0..new.len()).filter(|j| !taken[*j]).collect::<Vec<_>>();
+    let same = |i: usize, j: usize| old[rest_old[i]].depth == new[rest_new[j]].depth && old[rest_old[i]].expr == new[rest_new[j]].expr;
+    let (n, m) = (rest_old.len(), rest_new.len());
+    let mut table = vec![vec![0usize; m + 1]; n + 1];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            table[i][j] = if same(i, j) { table[i + 1][j + 1] + 1 } else { std::cmp::max(table[i + 1][j], table[i][j + 1]) };
+        }
+    }
+    let (mut i, mut j) = (0, 0);
+    while i < n && j < m {
+        if same(i, j) {
+            matched[rest_old[i]] = Some(rest_new[j]);
+            i += 1;
+            j += 1;
+        } else if table[i + 1][j] >= table[i][j + 1] {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+    matched
+}
+
+/// Compare two versions of a proof, line by line
+pub fn diff<P: Proof>(a: &P, b: &P) -> ProofDiff {
+    use frunk_core::coproduct::Coproduct::{Inl, Inr};
+    let (old, new) = (lines_of(a), lines_of(b));
+    let matched = align(a, b, &old, &new);
+    let old_index: HashMap<PjRef<P>, usize> = old.iter().enumerate().map(|(i, line)| (line.r.clone(), i)).collect();
+    let new_index: HashMap<PjRef<P>, usize> = new.iter().enumerate().map(|(j, line)| (line.r.clone(), j)).collect();
+    let new_to_old: HashMap<usize, usize> = matched.iter().enumerate().filter_map(|(i, j)| Some(((*j)?, i))).collect();
+
+    let span = |prf: &P, index: &HashMap<PjRef<P>, usize>, lines: &[Line<P>], sr: &P::SubproofReference| -> Option<RangeInclusive<usize>> {
+        let numbers = prf.lookup_subproof(sr)?.contained_justifications(true).iter().filter_map(|r| Some(lines[*index.get(r)?].number)).collect::<Vec<_>>();
+        Some(*numbers.iter().min()?..=*numbers.iter().max()?)
+    };
+    // The subproof of `b` that `sr` of `a` became: the one directly containing
+    // the line its first line was matched with
+    let map_subproof = |sr: &P::SubproofReference| -> Option<P::SubproofReference> {
+        let sub = a.lookup_subproof(sr)?;
+        let first: PjRef<P> = match sub.premises().first() {
+            Some(pr) => Coproduct::inject(pr.clone()),
+            None => Coproduct::inject(sub.direct_lines().first()?.clone()),
+        };
+        let j = matched[*old_index.get(&first)?]?;
+        b.parent_of_line(&pj_to_pjs::<P>(new[j].r.clone()))
+    };
+
+    // Each change is sorted by the old line it's at or after, then by new line,
+    // so that removed lines come before what replaced them
+    let mut changes: Vec<((usize, usize), LineChange)> = vec![];
+    for (i, line) in old.iter().enumerate() {
+        let j = match matched[i] {
+            Some(j) => j,
+            None => {
+                changes.push(((line.number, 0), LineChange::Removed { old_line: line.number, expr: line.expr.clone() }));
+                continue;
+            }
+        };
+        let (old_line, new_line) = (line.number, new[j].number);
+        let key = (old_line, new_line);
+        if line.expr != new[j].expr {
+            changes.push((key, LineChange::Modified { old_line, new_line, old: line.expr.clone(), new: new[j].expr.clone() }));
+        }
+        let (old_step, new_step) = match (&line.r, &new[j].r) {
+            (Inr(Inl(x)), Inr(Inl(y))) => match (a.lookup_step(x), b.lookup_step(y)) {
+                (Some(x), Some(y)) => (x, y),
+                _ => continue,
+            },
+            _ => continue,
+        };
+        let Justification(_, old_rule, old_deps, old_sdeps) = old_step;
+        let Justification(_, new_rule, new_deps, new_sdeps) = new_step;
+        if old_rule != new_rule {
+            changes.push((key, LineChange::RuleChanged { old_line, new_line, old: old_rule, new: new_rule }));
+        }
+        let mapped_deps = old_deps.iter().map(|dep| Some(new[matched[*old_index.get(dep)?]?].r.clone())).collect::<Option<HashSet<_>>>();
+        let mapped_sdeps = old_sdeps.iter().map(map_subproof).collect::<Option<HashSet<_>>>();
+        if mapped_deps != Some(new_deps.iter().cloned().collect()) || mapped_sdeps != Some(new_sdeps.iter().cloned().collect()) {
+            let numbers = |deps: &[PjRef<P>], index: &HashMap<PjRef<P>, usize>, lines: &[Line<P>]| {
+                let mut numbers = deps.iter().filter_map(|dep| Some(lines[*index.get(dep)?].number)).collect::<Vec<_>>();
+                numbers.sort();
+                numbers
+            };
+            let spans = |prf: &P, sdeps: &[P::SubproofReference], index: &HashMap<PjRef<P>, usize>, lines: &[Line<P>]| {
+                let mut spans = sdeps.iter().filter_map(|sr| span(prf, index, lines, sr)).collect::<Vec<_>>();
+                spans.sort_by_key(|span| (*span.start(), *span.end()));
+                spans
+            };
+            changes.push((
+                key,
+                LineChange::DepsChanged {
+                    old_line,
+                    new_line,
+                    old_deps: numbers(&old_deps, &old_index, &old),
+                    new_deps: numbers(&new_deps, &new_index, &new),
+                    old_sdeps: spans(a, &old_sdeps, &old_index, &old),
+                    new_sdeps: spans(b, &new_sdeps, &new_index, &new),
+                },
+            ));
+        }
+    }
+    // An inserted line goes after the old line of the closest matched line before it
+    let mut anchor = 0;
+    for (j, line) in new.iter().enumerate() {
+        match new_to_old.get(&j) {
+            Some(i) => anchor = old[*i].number,
+            None => changes.push(((anchor, line.number), LineChange::Inserted { new_line: line.number, expr: line.expr.clone() })),
+        }
+    }
+    changes.sort_by_key(|(key, _)| *key);
+    ProofDiff { changes: changes.into_iter().map(|(_, change)| change).collect() }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::deps;
+    use crate::parser::parse_unwrap as p;
+    use crate::proofs::builder::ProofBuilder;
+    use crate::proofs::pooledproof::PooledProof;
+    use crate::rules::RuleM;
+
+    use frunk_core::HList;
+
+    type P = PooledProof<HList![Expr]>;
+
+    #[test]
+    fn test_diff() {
+        /*
+        1 | A
+        2 | A -> B
+          | ----------
+        3 | B ; ImpElim [2, 1]
+        4 | A & B ; AndIntro [1, 3]
+        */
+        let builder = ProofBuilder::new().premise("A").premise("A -> B").step("B", RuleM::ImpElim, deps![2, 1]).step("A & B", RuleM::AndIntro, deps![1, 3]);
+        let (a, _) = builder.clone().build_with_lines::<P>().unwrap();
+        let (mut b, lines) = builder.build_with_lines::<P>().unwrap();
+        assert!(diff(&a, &b).is_empty());
+
+        // Edit line 4, and insert a subproof before it, which renumbers it without changing its dependencies
+        let last: <P as Proof>::JustificationReference = Coproduct::uninject(lines[3]).unwrap();
+        b.with_mut_step(&last, |j| j.0 = p("B & A"));
+        let sub = b.add_subproof_relative(&Coproduct::inject(last), false);
+        b.with_mut_subproof(&sub, |sub| {
+            let c = sub.add_premise(p("C"));
+            sub.add_step(Justification(p("C"), RuleM::Reit, vec![Coproduct::inject(c)], vec![]));
+        });
+        let d = diff(&a, &b);
+        assert_eq!(d.changes, vec![LineChange::Inserted { new_line: 4, expr: p("C") }, LineChange::Inserted { new_line: 5, expr: p("C") }, LineChange::Modified { old_line: 4, new_line: 6, old: p("A & B"), new: p("B & A") }]);
+        assert_eq!(d.render_text(), "--- a\n+++ b\n+4: C\n+5: C\n-4: (A ∧ B)\n+6: (B ∧ A)\n");
+
+        // Changing a step's rule and dependencies
+        let third: <P as Proof>::JustificationReference = Coproduct::uninject(lines[2]).unwrap();
+        b.with_mut_step(&third, |j| {
+            j.1 = RuleM::Reit;
+            j.2 = vec![lines[0]];
+        });
+        let d = diff(&a, &b);
+        assert_eq!(d.changes[..2], [LineChange::RuleChanged { old_line: 3, new_line: 3, old: RuleM::ImpElim, new: RuleM::Reit }, LineChange::DepsChanged { old_line: 3, new_line: 3, old_deps: vec![1, 2], new_deps: vec![1], old_sdeps: vec![], new_sdeps: vec![] }]);
+        assert!(d.render_text().contains("~3/3: deps [1, 2] -> [1]\n"));
+    }
+}
@@ -0,0 +1,854 @@
+/*!
+Undo and redo for proofs, by recording each mutation as an invertible command.
+
+`RecordingProof<P>` implements `Proof` by passing everything through to the `P` it wraps, and recording each mutation made through it.
+Lines and subproofs taken out of the proof are recorded along with where they were and which other steps cited them, so that they can be put back.
+
+# References
+Putting a line back into a `P` gives it a new reference, so a command that re-inserts something returns the renaming from old references to new ones, which is then applied to every other recorded command.
+This keeps the recorded history consistent with the proof, but references held outside of it from before an undo or redo may no longer resolve.
+
+Lines that are put back keep the stable ids they had, so anything that refers to them by `LineId` still finds them.
+
+# Grouping
+Each mutation is its own undo step, unless it's made between `start_group` and `end_group`, in which case all of those are undone and redone together.
+
+# Limitations
+Mutations made inside `with_mut_subproof` can't be observed individually, so they're recorded as replacing the whole subproof.
+*/
+
+use super::js_to_pjs;
+use super::pj_to_pjs;
+use super::InvalidRefReason;
+use super::JsRef;
+use super::JustVal;
+use super::Justification;
+use super::LineId;
+use super::MoveDirection;
+use super::MoveError;
+use super::PjRef;
+use super::PjsRef;
+use super::Proof;
+use super::Remap;
+use super::Snapshot;
+use super::SnapshotLine;
+use crate::expr::Expr;
+use crate::rules::CheckContext;
+use crate::rules::ProofCheckError;
+
+use std::collections::HashMap;
+use std::collections::HashSet;
+
+use frunk_core::coproduct::Coproduct;
+
+type SnapshotOf<P> = Snapshot<<P as Proof>::PremiseReference, <P as Proof>::JustificationReference, <P as Proof>::SubproofReference>;
+
+/// The contents of a line or subproof, enough to put it back into a proof
+enum Item<P: Proof> {
+    Premise(P::PremiseReference, Expr),
+    Step(P::JustificationReference, JustVal<P>),
+    Subproof(P::SubproofReference, SnapshotOf<P>),
+}
+
+/// Where an item is, relative to its neighbors
+enum Position<P: Proof> {
+    After(PjsRef<P>),
+    Before(PjsRef<P>),
+    /// The item has no neighbors of its kind in this subproof, or at the top level for `None`
+    Alone(Option<P::SubproofReference>),
+}
+
+enum Command<P: Proof> {
+    /// An item was inserted (`before` is `None`), removed (`after` is `None`), or changed in place
+    Replace {
+        position: Position<P>,
+        before: Option<Item<P>>,
+        after: Option<Item<P>>,
+        /// Steps outside the item that cite it or something in it, before and after the change
+        dependents_before: Vec<(P::JustificationReference, JustVal<P>)>,
+        dependents_after: Vec<(P::JustificationReference, JustVal<P>)>,
+        /// Stable ids of the lines in the item, before and after the change
+        ids_before: Vec<(PjRef<P>, LineId)>,
+        ids_after: Vec<(PjRef<P>, LineId)>,
+    },
+    Move {
+        line: PjRef<P>,
+        direction: MoveDirection,
+    },
+    /// A step was moved into a neighboring subproof (`nest`) or out of its subproof
+    Nest {
+        line: P::JustificationReference,
+        direction: MoveDirection,
+        nest: bool,
+    },
+}
+
+/// New references for lines and subproofs that were put back into a proof
+struct Renames<P: Proof>(HashMap<PjsRef<P>, PjsRef<P>>);
+
+impl<P: Proof> Renames<P> {
+    fn pjs(&self, r: &PjsRef<P>) -> PjsRef<P> {
+        self.0.get(r).cloned().unwrap_or_else(|| r.clone())
+    }
+    fn premise(&self, r: &P::PremiseReference) -> P::PremiseReference {
+        self.pjs(&Coproduct::inject(r.clone())).uninject().unwrap_or_else(|_| r.clone())
+    }
+    fn step(&self, r: &P::JustificationReference) -> P::JustificationReference {
+        self.pjs(&Coproduct::inject(r.clone())).uninject().unwrap_or_else(|_| r.clone())
+    }
+    fn subproof(&self, r: &P::SubproofReference) -> P::SubproofReference {
+        self.pjs(&Coproduct::inject(r.clone())).uninject().unwrap_or_else(|_| r.clone())
+    }
+    fn line(&self, r: &PjRef<P>) -> PjRef<P> {
+        use frunk_core::coproduct::Coproduct::{Inl, Inr};
+        match r {
+            Inl(pr) => Coproduct::inject(self.premise(pr)),
+            Inr(Inl(jr)) => Coproduct::inject(self.step(jr)),
+            Inr(Inr(void)) => match *void {},
+        }
+    }
+    fn justification(&self, just: &JustVal<P>) -> JustVal<P> {
+        let Justification(e, rule, deps, sdeps) = just;
+        Justification(e.clone(), *rule, deps.iter().map(|dep| self.line(dep)).collect(), sdeps.iter().map(|sdep| self.subproof(sdep)).collect())
+    }
+    fn snapshot(&self, snapshot: &SnapshotOf<P>) -> SnapshotOf<P> {
+        let premises = snapshot.premises.iter().map(|(pr, e)| (self.premise(pr), e.clone())).collect();
+        let lines = snapshot
+            .lines
+            .iter()
+            .map(|line| match line {
+                SnapshotLine::Step(jr, just) => SnapshotLine::Step(self.step(jr), self.justification(just)),
+                SnapshotLine::Subproof(sr, sub) => SnapshotLine::Subproof(self.subproof(sr), self.snapshot(sub)),
+            })
+            .collect();
+        Snapshot { premises, lines }
+    }
+}
+
+impl<P: Proof> Item<P> {
+    fn reference(&self) -> PjsRef<P> {
+        match self {
+            Item::Premise(pr, _) => Coproduct::inject(pr.clone()),
+            Item::Step(jr, _) => Coproduct::inject(jr.clone()),
+            Item::Subproof(sr, _) => Coproduct::inject(sr.clone()),
+        }
+    }
+    fn rename(&mut self, renames: &Renames<P>) {
+        *self = match self {
+            Item::Premise(pr, e) => Item::Premise(renames.premise(pr), e.clone()),
+            Item::Step(jr, just) => Item::Step(renames.step(jr), renames.justification(just)),
+            Item::Subproof(sr, sub) => Item::Subproof(renames.subproof(sr), renames.snapshot(sub)),
+        };
+    }
+}
+
+impl<P: Proof> Command<P> {
+    fn rename(&mut self, renames: &Renames<P>) {
+        match self {
+            Command::Replace { position, before, after, dependents_before, dependents_after, ids_before, ids_after } => {
+                *position = match position {
+                    Position::After(r) => Position::After(renames.pjs(r)),
+                    Position::Before(r) => Position::Before(renames.pjs(r)),
+                    Position::Alone(sr) => Position::Alone(sr.as_ref().map(|sr| renames.subproof(sr))),
+                };
+                for item in before.iter_mut().chain(after.iter_mut()) {
+                    item.rename(renames);
+                }
+                for (jr, just) in dependents_before.iter_mut().chain(dependents_after.iter_mut()) {
+                    *jr = renames.step(jr);
+                    *just = renames.justification(just);
+                }
+                for (line, _) in ids_before.iter_mut().chain(ids_after.iter_mut()) {
+                    *line = renames.line(line);
+                }
+            }
+            Command::Move { line, .. } => *line = renames.line(line),
+            Command::Nest { line, .. } => *line = renames.step(line),
+        }
+    }
+}
+
+/// Whether the next command joins the last undo step
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Grouping {
+    Off,
+    /// `start_group` was called, and the next command begins a new step
+    Starting,
+    /// Commands are added to the last step
+    Open,
+}
+
+/// A proof that records mutations made to it, so they can be undone and redone
+pub struct RecordingProof<P: Proof> {
+    proof: P,
+    /// Each undo step, oldest first, as the commands in it in the order they were made
+    undo_stack: Vec<Vec<Command<P>>>,
+    redo_stack: Vec<Vec<Command<P>>>,
+    grouping: Grouping,
+    /// Whether the last undo step was made by the last group, which `continue_group` can add to
+    resumable: bool,
+    /// The most steps kept to undo, if there's a limit
+    limit: Option<usize>,
+}
+
+impl<P: Proof> RecordingProof<P> {
+    /// Start recording mutations to `proof`, with no history
+    pub fn from_proof(proof: P) -> Self {
+        RecordingProof { proof, undo_stack: vec![], redo_stack: vec![], grouping: Grouping::Off, resumable: false, limit: None }
+    }
+    /// Keep at most `limit` steps to undo, forgetting the oldest ones past that
+    pub fn with_limit(mut self, limit: usize) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+    pub fn inner(&self) -> &P {
+        &self.proof
+    }
+    pub fn into_inner(self) -> P {
+        self.proof
+    }
+    pub fn can_undo(&self) -> bool {
+        !self.undo_stack.is_empty()
+    }
+    pub fn can_redo(&self) -> bool {
+        !self.redo_stack.is_empty()
+    }
+
+    /// Make the mutations from now on one undo step, until `end_group` or the next `start_group`
+    pub fn start_group(&mut self) {
+        self.grouping = Grouping::Starting;
+    }
+    /// Add the mutations from now on to the undo step of the last group, as if it hadn't ended.
+    /// If that's no longer the most recent step, because of an undo, a redo, or a mutation outside of a group, this starts a new group instead.
+    pub fn continue_group(&mut self) {
+        self.grouping = if self.resumable { Grouping::Open } else { Grouping::Starting };
+    }
+    /// Make each mutation from now on its own undo step again
+    pub fn end_group(&mut self) {
+        self.resumable = self.grouping == Grouping::Open;
+        self.grouping = Grouping::Off;
+    }
+
+    /// Change the proof without recording it, such as to renumber its references, which forgets the history since it may no longer apply
+    pub fn edit_unrecorded<A>(&mut self, edit: impl FnOnce(&mut P) -> A) -> A {
+        self.undo_stack.clear();
+        self.redo_stack.clear();
+        self.resumable = false;
+        edit(&mut self.proof)
+    }
+
+    /// Revert the most recent step that hasn't been undone. Returns whether there was one.
+    pub fn undo(&mut self) -> bool {
+        self.undo_renaming().is_some()
+    }
+
+    /// Reapply the most recently undone step. Returns whether there was one.
+    pub fn redo(&mut self) -> bool {
+        self.redo_renaming().is_some()
+    }
+
+    /// Like `undo`, but returns the new references of the lines and subproofs that were put back or moved, by their references from before
+    pub fn undo_renaming(&mut self) -> Option<HashMap<PjsRef<P>, PjsRef<P>>> {
+        let mut step = self.undo_stack.pop()?;
+        let renames = self.apply_step(&mut step, false);
+        self.redo_stack.push(step);
+        Some(renames)
+    }
+
+    /// Like `redo`, but returns the new references of the lines and subproofs that were put back or moved, by their references from before
+    pub fn redo_renaming(&mut self) -> Option<HashMap<PjsRef<P>, PjsRef<P>>> {
+        let mut step = self.redo_stack.pop()?;
+        let renames = self.apply_step(&mut step, true);
+        self.undo_stack.push(step);
+        Some(renames)
+    }
+
+    /// Apply the commands of `step` in order, or revert them in reverse order if `forward` is false
+    fn apply_step(&mut self, step: &mut [Command<P>], forward: bool) -> HashMap<PjsRef<P>, PjsRef<P>> {
+        self.resumable = false;
+        let mut all = HashMap::new();
+        for i in 0..step.len() {
+            let i = if forward { i } else { step.len() - 1 - i };
+            let renames = self.apply(&step[i], forward);
+            if renames.0.is_empty() {
+                continue;
+            }
+            for command in step.iter_mut() {
+                command.rename(&renames);
+            }
+            self.rename_all(&renames);
+            for new in all.values_mut() {
+                *new = renames.pjs(new);
+            }
+            for (old, new) in renames.0 {
+                all.entry(old).or_insert(new);
+            }
+        }
+        all
+    }
+
+    fn push(&mut self, command: Command<P>) {
+        self.redo_stack.clear();
+        if self.grouping == Grouping::Off {
+            self.resumable = false;
+        }
+        if self.grouping == Grouping::Open {
+            if let Some(step) = self.undo_stack.last_mut() {
+                step.push(command);
+                return;
+            }
+        }
+        self.undo_stack.push(vec![command]);
+        if self.grouping == Grouping::Starting {
+            self.grouping = Grouping::Open;
+        }
+        if self.limit.map_or(false, |limit| self.undo_stack.len() > limit) {
+            self.undo_stack.remove(0);
+        }
+    }
+
+    fn rename_all(&mut self, renames: &Renames<P>) {
+        if renames.0.is_empty() {
+            return;
+        }
+        for command in self.undo_stack.iter_mut().chain(self.redo_stack.iter_mut()).flatten() {
+            command.rename(renames);
+        }
+    }
+
+    /// The stable ids of the lines in `item`
+    fn ids_of(&self, item: &Item<P>) -> Vec<(PjRef<P>, LineId)> {
+        fn lines<P: Proof>(snapshot: &SnapshotOf<P>, out: &mut Vec<PjRef<P>>) {
+            out.extend(snapshot.premises.iter().map(|(pr, _)| Coproduct::inject(pr.clone())));
+            for line in snapshot.lines.iter() {
+                match line {
+                    SnapshotLine::Step(jr, _) => out.push(Coproduct::inject(jr.clone())),
+                    SnapshotLine::Subproof(_, sub) => lines::<P>(sub, out),
+                }
+            }
+        }
+        let mut refs = vec![];
+        match item {
+            Item::Premise(pr, _) => refs.push(Coproduct::inject(pr.clone())),
+            Item::Step(jr, _) => refs.push(Coproduct::inject(jr.clone())),
+            Item::Subproof(_, sub) => lines::<P>(sub, &mut refs),
+        }
+        refs.into_iter().filter_map(|line| self.proof.stable_id_of(&line).map(|id| (line, id))).collect()
+    }
+
+    fn item_of(&self, r: &PjsRef<P>) -> Option<Item<P>> {
+        use frunk_core::coproduct::Coproduct::{Inl, Inr};
+        match r {
+            Inl(pr) => self.proof.lookup_premise(pr).map(|e| Item::Premise(pr.clone(), e)),
+            Inr(Inl(jr)) => self.proof.lookup_step(jr).map(|just| Item::Step(jr.clone(), just)),
+            Inr(Inr(Inl(sr))) => self.proof.lookup_subproof(sr).map(|sub| Item::Subproof(sr.clone(), Snapshot::of(&self.proof, &sub))),
+            Inr(Inr(Inr(void))) => match *void {},
+        }
+    }
+
+    fn position_of(&self, r: &PjsRef<P>) -> Position<P> {
+        let parent = self.proof.parent_of_line(r);
+        let (premises, lines) = match &parent {
+            Some(sr) => self.proof.lookup_subproof(sr).map(|sub| (sub.premises(), sub.lines())).unwrap_or_default(),
+            None => (self.proof.premises(), self.proof.lines()),
+        };
+        let siblings = match r.get::<P::PremiseReference, _>() {
+            Some(_) => premises.into_iter().map(Coproduct::inject).collect::<Vec<PjsRef<P>>>(),
+            None => lines.into_iter().map(js_to_pjs::<P>).collect(),
+        };
+        match siblings.iter().position(|sibling| sibling == r) {
+            Some(i) if i > 0 => Position::After(siblings[i - 1].clone()),
+            Some(i) if i + 1 < siblings.len() => Position::Before(siblings[i + 1].clone()),
+            _ => Position::Alone(parent),
+        }
+    }
+
+    /// Steps outside of `item` that cite it or anything in it
+    fn dependents_of_item(&self, item: &Item<P>) -> Vec<(P::JustificationReference, JustVal<P>)> {
+        fn contents<P: Proof>(snapshot: &SnapshotOf<P>, out: &mut HashSet<PjsRef<P>>) {
+            out.extend(snapshot.premises.iter().map(|(pr, _)| Coproduct::inject(pr.clone())));
+            for line in snapshot.lines.iter() {
+                match line {
+                    SnapshotLine::Step(jr, _) => {
+                        out.insert(Coproduct::inject(jr.clone()));
+                    }
+                    SnapshotLine::Subproof(sr, sub) => {
+                        out.insert(Coproduct::inject(sr.clone()));
+                        contents::<P>(sub, out);
+                    }
+                }
+            }
+        }
+        let mut inside = HashSet::new();
+        inside.insert(item.reference());
+        if let Item::Subproof(_, sub) = item {
+            contents::<P>(sub, &mut inside);
+        }
+        let mut dependents = self
+            .proof
+            .contained_justifications(false)
+            .into_iter()
+            .filter_map(|line| Coproduct::uninject::<P::JustificationReference, _>(line).ok())
+            .filter(|jr| !inside.contains(&Coproduct::inject(jr.clone())))
+            .filter_map(|jr| self.proof.lookup_step(&jr).map(|just| (jr, just)))
+            .filter(|(_, Justification(_, _, deps, sdeps))| deps.iter().any(|dep| inside.contains(&pj_to_pjs::<P>(dep.clone()))) || sdeps.iter().any(|sdep| inside.contains(&Coproduct::inject(sdep.clone()))))
+            .collect::<Vec<_>>();
+        dependents.sort_by(|(a, _), (b, _)| a.cmp(b));
+        dependents
+    }
+
+    /// Record that `r` was just inserted
+    fn record_insert(&mut self, r: PjsRef<P>) {
+        if let Some(item) = self.item_of(&r) {
+            let position = self.position_of(&r);
+            let ids_after = self.ids_of(&item);
+            self.push(Command::Replace { position, before: None, after: Some(item), dependents_before: vec![], dependents_after: vec![], ids_before: vec![], ids_after });
+        }
+    }
+
+    /// Run `edit` on the proof, and record how it changed `r`, which it may remove
+    fn record_change<A>(&mut self, r: PjsRef<P>, edit: impl FnOnce(&mut P) -> Option<A>) -> Option<A> {
+        let before = match self.item_of(&r) {
+            Some(before) => before,
+            None => return edit(&mut self.proof),
+        };
+        let position = self.position_of(&r);
+        let dependents_before = self.dependents_of_item(&before);
+        let ids_before = self.ids_of(&before);
+        let ret = edit(&mut self.proof)?;
+        let after = self.item_of(&r);
+        let dependents_after = dependents_before.iter().filter_map(|(jr, _)| self.proof.lookup_step(jr).map(|just| (jr.clone(), just))).collect();
+        let ids_after = after.as_ref().map(|after| self.ids_of(after)).unwrap_or_default();
+        self.push(Command::Replace { position, before: Some(before), after, dependents_before, dependents_after, ids_before, ids_after });
+        Some(ret)
+    }
+
+    /// Put `item` back into the proof at `position`, returning its new references
+    fn insert_item(&mut self, item: &Item<P>, position: &Position<P>) -> Renames<P> {
+        let mut renames = HashMap::new();
+        let (anchor, after) = match position {
+            Position::After(r) => (Some(r), true),
+            Position::Before(r) => (Some(r), false),
+            Position::Alone(_) => (None, false),
+        };
+        let parent = match position {
+            Position::Alone(parent) => parent.clone(),
+            _ => None,
+        };
+        let js_anchor = anchor.and_then(|r| r.clone().subset::<JsRef<P>, _>().ok());
+        match item {
+            Item::Premise(pr, e) => {
+                let copy = match (anchor.and_then(|r| r.get::<P::PremiseReference, _>()), &parent) {
+                    (Some(anchor), _) => self.proof.add_premise_relative(e.clone(), anchor, after),
+                    (None, Some(sr)) => self.proof.with_mut_subproof(sr, |sub| sub.add_premise(e.clone())).expect("parent subproof should exist"),
+                    (None, None) => self.proof.add_premise(e.clone()),
+                };
+                renames.insert(Coproduct::inject(pr.clone()), Coproduct::inject(copy));
+            }
+            Item::Step(jr, just) => {
+                let copy = match (&js_anchor, &parent) {
+                    (Some(anchor), _) => self.proof.add_step_relative(just.clone(), anchor, after),
+                    (None, Some(sr)) => self.proof.with_mut_subproof(sr, |sub| sub.add_step(just.clone())).expect("parent subproof should exist"),
+                    (None, None) => self.proof.add_step(just.clone()),
+                };
+                renames.insert(Coproduct::inject(jr.clone()), Coproduct::inject(copy));
+            }
+            Item::Subproof(sr, snapshot) => {
+                let copy = match (&js_anchor, &parent) {
+                    (Some(anchor), _) => self.proof.add_subproof_relative(anchor, after),
+                    (None, Some(parent)) => self.proof.with_mut_subproof(parent, |sub| sub.add_subproof()).expect("parent subproof should exist"),
+                    (None, None) => self.proof.add_subproof(),
+                };
+                let mut remap = Remap::new(true);
+                self.proof.with_mut_subproof(&copy, |dst| remap.replay(dst, snapshot));
+                renames.extend(remap.lines.into_iter().map(|(old, new)| (pj_to_pjs::<P>(old), pj_to_pjs::<P>(new))));
+                renames.extend(remap.subproofs.into_iter().map(|(old, new)| (Coproduct::inject(old), Coproduct::inject(new))));
+                renames.insert(Coproduct::inject(sr.clone()), Coproduct::inject(copy));
+            }
+        }
+        Renames(renames)
+    }
+
+    fn remove_item(&mut self, item: &Item<P>) {
+        match item {
+            Item::Premise(pr, _) => self.proof.remove_line(&Coproduct::inject(pr.clone())),
+            Item::Step(jr, _) => self.proof.remove_line(&Coproduct::inject(jr.clone())),
+            Item::Subproof(sr, _) => self.proof.remove_subproof(sr),
+        }
+    }
+
+    /// Apply `command` to the proof, or revert it if `forward` is false
+    fn apply(&mut self, command: &Command<P>, forward: bool) -> Renames<P> {
+        match command {
+            Command::Replace { position, before, after, dependents_before, dependents_after, ids_before, ids_after } => {
+                let (from, to, dependents, ids) = if forward { (before, after, dependents_after, ids_after) } else { (after, before, dependents_before, ids_before) };
+                let renames = match (from, to) {
+                    (Some(Item::Premise(pr, _)), Some(Item::Premise(_, e))) => {
+                        self.proof.with_mut_premise(pr, |x| *x = e.clone());
+                        Renames(HashMap::new())
+                    }
+                    (Some(Item::Step(jr, _)), Some(Item::Step(_, just))) => {
+                        self.proof.with_mut_step(jr, |x| *x = just.clone());
+                        Renames(HashMap::new())
+                    }
+                    _ => {
+                        if let Some(from) = from {
+                            self.remove_item(from);
+                        }
+                        match to {
+                            Some(to) => self.insert_item(to, position),
+                            None => Renames(HashMap::new()),
+                        }
+                    }
+                };
+                for (jr, just) in dependents.iter() {
+                    self.proof.with_mut_step(jr, |x| *x = renames.justification(just));
+                }
+                // Lines that were put back get their old ids, which were freed when they were taken out
+                for (line, id) in ids.iter() {
+                    self.proof.set_stable_id(&renames.line(line), *id);
+                }
+                renames
+            }
+            Command::Move { line, direction } => {
+                use frunk_core::coproduct::Coproduct::{Inl, Inr};
+                let direction = match (forward, direction) {
+                    (true, direction) => *direction,
+                    (false, MoveDirection::Up) => MoveDirection::Down,
+                    (false, MoveDirection::Down) => MoveDirection::Up,
+                };
+                let _ = match line {
+                    Inl(pr) => self.proof.move_premise(pr, direction),
+                    Inr(Inl(jr)) => self.proof.move_line(jr, direction),
+                    Inr(Inr(void)) => match *void {},
+                };
+                Renames(HashMap::new())
+            }
+            Command::Nest { line, direction, nest } => {
+                // Nesting into the subproof above is undone by moving the line out of its bottom, and vice versa
+                let (nest, direction) = match (forward, direction) {
+                    (true, direction) => (*nest, *direction),
+                    (false, MoveDirection::Up) => (!nest, MoveDirection::Down),
+                    (false, MoveDirection::Down) => (!nest, MoveDirection::Up),
+                };
+                let moved = if nest { self.proof.nest_line(line, direction) } else { self.proof.unnest_line(line, direction) };
+                match moved {
+                    Ok(jr) if jr != *line => Renames(std::iter::once((Coproduct::inject(line.clone()), Coproduct::inject(jr))).collect()),
+                    _ => Renames(HashMap::new()),
+                }
+            }
+        }
+    }
+}
+
+/// Reading a `RecordingProof` reads the proof it wraps, which can't be changed through this without recording it
+impl<P: Proof> std::ops::Deref for RecordingProof<P> {
+    type Target = P;
+    fn deref(&self) -> &P {
+        &self.proof
+    }
+}
+
+impl<P: Proof> Proof for RecordingProof<P> {
+    type PremiseReference = P::PremiseReference;
+    type JustificationReference = P::JustificationReference;
+    type SubproofReference = P::SubproofReference;
+    type Subproof = P::Subproof;
+    fn new() -> Self {
+        RecordingProof::from_proof(P::new())
+    }
+    fn top_level_proof(&self) -> &Self::Subproof {
+        self.proof.top_level_proof()
+    }
+    fn lookup_premise(&self, r: &Self::PremiseReference) -> Option<Expr> {
+        self.proof.lookup_premise(r)
+    }
+    fn lookup_step(&self, r: &Self::JustificationReference) -> Option<Justification<Expr, PjRef<Self>, Self::SubproofReference>> {
+        self.proof.lookup_step(r)
+    }
+    fn lookup_subproof(&self, r: &Self::SubproofReference) -> Option<Self::Subproof> {
+        self.proof.lookup_subproof(r)
+    }
+    fn with_mut_premise<A, F: FnOnce(&mut Expr) -> A>(&mut self, r: &Self::PremiseReference, f: F) -> Option<A> {
+        self.record_change(Coproduct::inject(r.clone()), |proof| proof.with_mut_premise(r, f))
+    }
+    fn with_mut_step<A, F: FnOnce(&mut Justification<Expr, PjRef<Self>, Self::SubproofReference>) -> A>(&mut self, r: &Self::JustificationReference, f: F) -> Option<A> {
+        self.record_change(Coproduct::inject(r.clone()), |proof| proof.with_mut_step(r, f))
+    }
+    fn with_mut_subproof<A, F: FnOnce(&mut Self::Subproof) -> A>(&mut self, r: &Self::SubproofReference, f: F) -> Option<A> {
+        self.record_change(Coproduct::inject(r.clone()), |proof| proof.with_mut_subproof(r, f))
+    }
+    fn add_premise(&mut self, e: Expr) -> Self::PremiseReference {
+        let r = self.proof.add_premise(e);
+        self.record_insert(Coproduct::inject(r.clone()));
+        r
+    }
+    fn add_subproof(&mut self) -> Self::SubproofReference {
+        let r = self.proof.add_subproof();
+        self.record_insert(Coproduct::inject(r.clone()));
+        r
+    }
+    fn add_step(&mut self, just: Justification<Expr, PjRef<Self>, Self::SubproofReference>) -> Self::JustificationReference {
+        let r = self.proof.add_step(just);
+        self.record_insert(Coproduct::inject(r.clone()));
+        r
+    }
+    fn add_premise_relative(&mut self, e: Expr, r: &Self::PremiseReference, after: bool) -> Self::PremiseReference {
+        let r = self.proof.add_premise_relative(e, r, after);
+        self.record_insert(Coproduct::inject(r.clone()));
+        r
+    }
+    fn add_subproof_relative(&mut self, r: &JsRef<Self>, after: bool) -> Self::SubproofReference {
+        let r = self.proof.add_subproof_relative(r, after);
+        self.record_insert(Coproduct::inject(r.clone()));
+        r
+    }
+    fn add_step_relative(&mut self, just: Justification<Expr, PjRef<Self>, Self::SubproofReference>, r: &JsRef<Self>, after: bool) -> Self::JustificationReference {
+        let r = self.proof.add_step_relative(just, r, after);
+        self.record_insert(Coproduct::inject(r.clone()));
+        r
+    }
+    fn remove_line(&mut self, r: &PjRef<Self>) {
+        self.record_change(pj_to_pjs::<Self>(r.clone()), |proof| {
+            proof.remove_line(r);
+            Some(())
+        });
+    }
+    fn remove_subproof(&mut self, r: &Self::SubproofReference) {
+        self.record_change(Coproduct::inject(r.clone()), |proof| {
+            proof.remove_subproof(r);
+            Some(())
+        });
+    }
+    fn move_line(&mut self, r: &Self::JustificationReference, direction: MoveDirection) -> Result<(), MoveError<PjRef<Self>, Self::SubproofReference>> {
+        self.proof.move_line(r, direction)?;
+        self.push(Command::Move { line: Coproduct::inject(r.clone()), direction });
+        Ok(())
+    }
+    fn move_premise(&mut self, r: &Self::PremiseReference, direction: MoveDirection) -> Result<(), MoveError<PjRef<Self>, Self::SubproofReference>> {
+        self.proof.move_premise(r, direction)?;
+        self.push(Command::Move { line: Coproduct::inject(r.clone()), direction });
+        Ok(())
+    }
+    fn nest_line(&mut self, r: &Self::JustificationReference, direction: MoveDirection) -> Result<Self::JustificationReference, MoveError<PjRef<Self>, Self::SubproofReference>> {
+        let jr = self.proof.nest_line(r, direction)?;
+        self.push(Command::Nest { line: jr.clone(), direction, nest: true });
+        Ok(jr)
+    }
+    fn unnest_line(&mut self, r: &Self::JustificationReference, direction: MoveDirection) -> Result<Self::JustificationReference, MoveError<PjRef<Self>, Self::SubproofReference>> {
+        let jr = self.proof.unnest_line(r, direction)?;
+        self.push(Command::Nest { line: jr.clone(), direction, nest: false });
+        Ok(jr)
+    }
+    fn premises(&self) -> Vec<Self::PremiseReference> {
+        self.proof.premises()
+    }
+    fn lines(&self) -> Vec<JsRef<Self>> {
+        self.proof.lines()
+    }
+    fn parent_of_line(&self, r: &PjsRef<Self>) -> Option<Self::SubproofReference> {
+        self.proof.parent_of_line(r)
+    }
+    fn stable_id_of(&self, r: &PjRef<Self>) -> Option<LineId> {
+        self.proof.stable_id_of(r)
+    }
+    fn ref_of_stable_id(&self, id: LineId) -> Option<PjRef<Self>> {
+        self.proof.ref_of_stable_id(id)
+    }
+    fn set_stable_id(&mut self, r: &PjRef<Self>, id: LineId) -> bool {
+        self.proof.set_stable_id(r, id)
+    }
+    fn next_stable_id(&self) -> LineId {
+        self.proof.next_stable_id()
+    }
+    fn reserve_stable_ids(&mut self, next: LineId) {
+        self.proof.reserve_stable_ids(next)
+    }
+    fn invalid_ref_reason(&self, r: &PjsRef<Self>) -> Option<InvalidRefReason> {
+        self.proof.invalid_ref_reason(r)
+    }
+    fn verify_line_with(&self, r: &PjRef<Self>, ctx: &CheckContext) -> Result<(), ProofCheckError<PjRef<Self>, Self::SubproofReference>> {
+        self.proof.verify_line_with(r, ctx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::proofs::pooledproof::PooledProof;
+    use crate::rules::Rule;
+    use crate::rules::RuleM;
+
+    use frunk_core::HList;
+
+    type Shape = Vec<(usize, Option<Expr>, Option<Rule>, Vec<usize>, Vec<usize>)>;
+
+    /// Each line and subproof's depth, contents, and dependencies by position,
+    /// which stays the same when references change
+    fn shape<P: Proof>(prf: &P) -> Shape {
+        use frunk_core::coproduct::Coproduct::{Inl, Inr};
+        let lines = prf.iter_lines().collect::<Vec<_>>();
+        let index = |r: &PjsRef<P>| lines.iter().position(|info| info.reference == *r).expect("dependency should exist");
+        lines
+            .iter()
+            .map(|info| match &info.reference {
+                Inl(pr) => (info.depth, prf.lookup_premise(pr), None, vec![], vec![]),
+                Inr(Inl(jr)) => {
+                    let Justification(e, rule, deps, sdeps) = prf.lookup_step(jr).unwrap();
+                    (info.depth, Some(e), Some(rule), deps.into_iter().map(|dep| index(&pj_to_pjs::<P>(dep))).collect(), sdeps.into_iter().map(|sdep| index(&Coproduct::inject(sdep))).collect())
+                }
+                Inr(Inr(Inl(_))) => (info.depth, None, None, vec![], vec![]),
+                Inr(Inr(Inr(void))) => match *void {},
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_undo_redo() {
+        use crate::parser::parse_unwrap as p;
+        type P = PooledProof<HList![Expr]>;
+        let mut initial = P::new();
+        initial.add_premise(p("P"));
+        let original = shape(&initial);
+        let mut prf = RecordingProof::from_proof(initial);
+        assert!(!prf.undo());
+
+        let p1 = prf.add_premise(p("A -> B"));
+        let p2 = prf.add_premise_relative(p("A"), &p1, true);
+        let s1 = prf.add_step(Justification(p("B"), RuleM::ImpElim, vec![Coproduct::inject(p1), Coproduct::inject(p2)], vec![]));
+        let sub = prf.add_subproof();
+        prf.with_mut_subproof(&sub, |sub| {
+            let c = sub.add_premise(p("C"));
+            sub.add_step(Justification(p("C"), RuleM::Reit, vec![Coproduct::inject(c)], vec![]));
+        })
+        .unwrap();
+        let s2 = prf.add_step(Justification(p("C -> C"), RuleM::ImpIntro, vec![], vec![sub]));
+        prf.with_mut_premise(&p2, |e| *e = p("A & A")).unwrap();
+        prf.with_mut_step(&s1, |j| j.1 = RuleM::Reit).unwrap();
+        prf.remove_line(&Coproduct::inject(p1));
+        let s3 = prf.add_step_relative(Justification(p("B"), RuleM::Reit, vec![Coproduct::inject(s1)], vec![]), &Coproduct::inject(s2), false);
+        prf.move_line(&s3, MoveDirection::Up).unwrap();
+        prf.remove_subproof(&sub);
+        let last = shape(&prf);
+
+        // Removing p1 and the subproof took them out of the steps that cited them
+        let Justification(_, _, deps, _) = prf.lookup_step(&s1).unwrap();
+        assert_eq!(deps, vec![Coproduct::inject(p2)]);
+        let Justification(_, _, _, sdeps) = prf.lookup_step(&s2).unwrap();
+        assert!(sdeps.is_empty());
+
+        let mut undone = 0;
+        while prf.undo() {
+            undone += 1;
+        }
+        assert_eq!(undone, 12);
+        assert_eq!(shape(&prf), original);
+
+        let mut redone = 0;
+        while prf.redo() {
+            redone += 1;
+        }
+        assert_eq!(redone, 12);
+        assert_eq!(shape(&prf), last);
+
+        // Undoing the removals puts back the citations of what was removed
+        assert!(prf.undo());
+        assert!(prf.undo());
+        assert!(prf.undo());
+        assert!(prf.undo());
+        let steps = prf.lines().into_iter().filter_map(|line| line.get::<<P as Proof>::JustificationReference, _>().copied()).collect::<Vec<_>>();
+        let Justification(_, _, deps, _) = prf.lookup_step(&steps[0]).unwrap();
+        assert_eq!(deps, prf.premises()[1..].iter().map(|pr| Coproduct::inject(*pr)).collect::<Vec<_>>());
+        let Justification(_, _, _, sdeps) = prf.lookup_step(&steps[1]).unwrap();
+        assert_eq!(sdeps.len(), 1);
+        assert!(prf.can_redo());
+
+        // A new mutation discards what was undone
+        prf.add_premise(p("D"));
+        assert!(!prf.can_redo());
+        assert!(!prf.redo());
+    }
+
+    #[test]
+    fn test_undo_indent() {
+        use crate::parser::parse_unwrap as p;
+        type P = PooledProof<HList![Expr]>;
+        let mut prf = RecordingProof::from_proof(P::new());
+        let p1 = prf.add_premise(p("A"));
+        let sub = prf.add_subproof();
+        let s1 = prf.with_mut_subproof(&sub, |sub| {
+            sub.add_premise(p("B"));
+            sub.add_step(Justification(p("A"), RuleM::Reit, vec![Coproduct::inject(p1)], vec![]))
+        });
+        let s2 = prf.add_step(Justification(p("A"), RuleM::Reit, vec![Coproduct::inject(p1)], vec![]));
+        let original = shape(&prf);
+
+        let s2 = prf.indent_line(&s2).unwrap();
+        let s1 = prf.outdent_line(&s1.unwrap()).unwrap();
+        let last = shape(&prf);
+        assert_ne!(last, original);
+        assert_eq!(prf.lookup_subproof(&sub).unwrap().lines(), vec![Coproduct::inject(s2)]);
+        assert_eq!(prf.lines()[0], Coproduct::inject(s1));
+
+        assert!(prf.undo());
+        assert!(prf.undo());
+        assert_eq!(shape(&prf), original);
+        assert!(prf.redo());
+        assert!(prf.redo());
+        assert_eq!(shape(&prf), last);
+    }
+
+    #[test]
+    fn test_undo_group() {
+        use crate::parser::parse_unwrap as p;
+        type P = PooledProof<HList![Expr]>;
+        let mut prf = RecordingProof::from_proof(P::new()).with_limit(2);
+        let p1 = prf.add_premise(p("A"));
+        let id = prf.stable_id_of(&Coproduct::inject(p1)).unwrap();
+        let original = shape(&prf);
+
+        prf.start_group();
+        let s1 = prf.add_step(Justification(p("A"), RuleM::Reit, vec![Coproduct::inject(p1)], vec![]));
+        prf.add_step(Justification(p("A & A"), RuleM::AndIntro, vec![Coproduct::inject(p1)], vec![]));
+        prf.end_group();
+        prf.remove_line(&Coproduct::inject(p1));
+        let last = shape(&prf);
+
+        // The premise is put back with the id it had, and the renaming says where it went
+        let renames = prf.undo_renaming().unwrap();
+        let old: PjsRef<P> = Coproduct::inject(p1);
+        let p1 = renames[&old].get::<<P as Proof>::PremiseReference, _>().copied().unwrap();
+        assert_eq!(prf.stable_id_of(&Coproduct::inject(p1)), Some(id));
+        let Justification(_, _, deps, _) = prf.lookup_step(&s1).unwrap();
+        assert_eq!(deps, vec![Coproduct::inject(p1)]);
+
+        // Both steps of the group are undone at once, and adding the premise was forgotten past the limit
+        assert!(prf.undo());
+        assert_eq!(shape(&prf), original);
+        assert!(!prf.can_undo());
+        assert!(prf.redo());
+        assert!(prf.redo());
+        assert_eq!(shape(&prf), last);
+
+        // Continuing a group adds to the step that was last made
+        let s2 = prf.add_step(Justification(p("B"), RuleM::EmptyRule, vec![], vec![]));
+        let before = shape(&prf);
+        prf.start_group();
+        prf.with_mut_step(&s2, |j| j.0 = p("C")).unwrap();
+        prf.end_group();
+        prf.continue_group();
+        prf.with_mut_step(&s2, |j| j.0 = p("D")).unwrap();
+        prf.end_group();
+        assert!(prf.undo());
+        assert_eq!(shape(&prf), before);
+
+        // but not to a step that was undone
+        prf.continue_group();
+        prf.with_mut_step(&s2, |j| j.0 = p("E")).unwrap();
+        prf.end_group();
+        assert!(prf.undo());
+        assert_eq!(shape(&prf), before);
+        assert!(prf.can_undo());
+    }
+}
@@ -1,9 +1,14 @@
 use crate::expr::Expr;
+use crate::proofs::InvalidRefReason;
 use crate::proofs::JsRef;
 use crate::proofs::Justification;
+use crate::proofs::LineId;
+use crate::proofs::MoveDirection;
+use crate::proofs::MoveError;
 use crate::proofs::PjRef;
 use crate::proofs::PjsRef;
 use crate::proofs::Proof;
+use crate::rules::CheckContext;
 use crate::rules::ProofCheckError;
 use crate::rules::RuleM;
 use crate::rules::RuleT;
@@ -62,6 +67,18 @@ impl Proof for JavaShallowProof {
     }
     fn remove_line(&mut self, _: &PjRef<Self>) {}
     fn remove_subproof(&mut self, _: &Self::SubproofReference) {}
+    fn move_line(&mut self, _: &Self::JustificationReference, _: MoveDirection) -> Result<(), MoveError<PjRef<Self>, Self::SubproofReference>> {
+        unimplemented!()
+    }
+    fn move_premise(&mut self, _: &Self::PremiseReference, _: MoveDirection) -> Result<(), MoveError<PjRef<Self>, Self::SubproofReference>> {
+        unimplemented!()
+    }
+    fn nest_line(&mut self, _: &Self::JustificationReference, _: MoveDirection) -> Result<Self::JustificationReference, MoveError<PjRef<Self>, Self::SubproofReference>> {
+        unimplemented!()
+    }
+    fn unnest_line(&mut self, _: &Self::JustificationReference, _: MoveDirection) -> Result<Self::JustificationReference, MoveError<PjRef<Self>, Self::SubproofReference>> {
+        unimplemented!()
+    }
     fn premises(&self) -> Vec<Self::PremiseReference> {
         if !self.0.is_empty() {
             vec![self.0[0].clone()]
@@ -75,12 +92,30 @@ impl Proof for JavaShallowProof {
     fn parent_of_line(&self, _: &PjsRef<Self>) -> Option<Self::SubproofReference> {
         unimplemented!()
     }
-    fn verify_line(&self, r: &PjRef<Self>) -> Result<(), ProofCheckError<PjRef<Self>, Self::SubproofReference>> {
+    fn stable_id_of(&self, _: &PjRef<Self>) -> Option<LineId> {
+        unimplemented!()
+    }
+    fn ref_of_stable_id(&self, _: LineId) -> Option<PjRef<Self>> {
+        unimplemented!()
+    }
+    fn set_stable_id(&mut self, _: &PjRef<Self>, _: LineId) -> bool {
+        unimplemented!()
+    }
+    fn next_stable_id(&self) -> LineId {
+        unimplemented!()
+    }
+    fn reserve_stable_ids(&mut self, _: LineId) {
+        unimplemented!()
+    }
+    fn invalid_ref_reason(&self, _: &PjsRef<Self>) -> Option<InvalidRefReason> {
+        unimplemented!()
+    }
+    fn verify_line_with(&self, r: &PjRef<Self>, ctx: &CheckContext) -> Result<(), ProofCheckError<PjRef<Self>, Self::SubproofReference>> {
         use frunk_core::coproduct::Coproduct::{Inl, Inr};
         match self.lookup_pj(r) {
             None => Err(ProofCheckError::LineDoesNotExist(r.clone())),
             Some(Inl(_)) => Ok(()), // premises are always valid
-            Some(Inr(Inl(Justification(conclusion, rule, deps, sdeps)))) => rule.check(self, conclusion, deps, sdeps),
+            Some(Inr(Inl(Justification(conclusion, rule, deps, sdeps)))) => rule.check(self, conclusion, deps, sdeps, ctx),
             Some(Inr(Inr(void))) => match void {},
         }
     }
@@ -0,0 +1,310 @@
+/*!
+A JSON format for proofs, compact enough for autosaving and sharing in a URL, and easier for other tools to read than the XML one.
+
+A document is an object with the fields
+- `format`, which is always `"aris-proof"`, and `version`, which is `1`,
+- `author`, `hash`, `title`, `created`, and `modified`, which are optional, and `goals`, a list of formulas, from the `ProofMetaData`, whose `assignment`, `seal`, `raw_inputs`, `locked`, and `annotations` aren't saved,
+- `proof`, with `premises`, a list of formulas, and `lines`, a list of
+  - `{"kind": "step", "formula": ..., "rule": ..., "deps": [...], "sdeps": [[first, last], ...]}`, or
+  - `{"kind": "subproof", "premises": [...], "lines": [...]}`,
+- `comments`, which is optional, an object from line numbers to the `ProofMetaData::comments` of those lines.
+
+Formulas are written as text, rules by their serialized names, and dependencies as display line numbers, numbered as in `TreeProof`, with each subproof cited by the first and last line it spans.
+Fields that this version doesn't know about are ignored, so that later versions can add them.
+
+# Example
+```
+#[macro_use] extern crate frunk_core;
+use aris::expr::Expr;
+use aris::proofs::json_interop::{proof_from_json, proof_to_json};
+use aris::proofs::pooledproof::PooledProof;
+use aris::proofs::xml_interop::ProofMetaData;
+use aris::proofs::Proof;
+use aris::parser::parse_unwrap as p;
+
+type P = PooledProof<HList![Expr]>;
+let mut prf = P::new();
+prf.add_premise(p("A"));
+let json = proof_to_json(&prf, &ProofMetaData { goals: vec![p("A")], ..Default::default() });
+let (reloaded, metadata) = proof_from_json::<P>(&json).unwrap();
+assert_eq!(reloaded.premises().len(), 1);
+assert_eq!(metadata.goals, vec![p("A")]);
+```
+*/
+
+use super::tree::TreeError;
+use super::tree::TreeLine;
+use super::tree::TreeProof;
+use super::xml_interop::ProofMetaData;
+use super::LineId;
+use super::Proof;
+use crate::expr::Expr;
+use crate::rules::RuleM;
+
+use std::collections::BTreeMap;
+
+use serde::Deserialize;
+use serde::Serialize;
+
+/// The value of a document's `format` field
+pub const FORMAT: &str = "aris-proof";
+/// The version of the format written by `proof_to_json`, and the only one read
+/// by `proof_from_json`
+pub const VERSION: u64 = 1;
+
+/// Reasons a proof can't be loaded from JSON
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum JsonError {
+    /// The document isn't valid JSON, or doesn't have the fields it needs
+    Syntax { message: String },
+    /// The document's `format` isn't `"aris-proof"`
+    WrongFormat { format: String },
+    /// The document is for a version of the format that isn't supported
+    UnsupportedVersion { version: u64 },
+    /// The step on line `line` uses a rule that doesn't exist
+    UnknownRule { name: String, line: usize },
+    /// The formula on line `line`, or of a goal if it's `None`, doesn't parse
+    UnparseableFormula { text: String, line: Option<usize> },
+    /// A dependency doesn't refer to a line or subproof
+    Dependency(TreeError),
+}
+
+impl std::fmt::Display for JsonError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        use JsonError::*;
+        match self {
+            Syntax { message } => write!(f, "Invalid proof JSON: {message}"),
+            WrongFormat { format } => write!(f, "Expected a document with format {FORMAT:?}, not {format:?}."),
+            UnsupportedVersion { version } => write!(f, "Version {version} of the proof format isn't supported, only version {VERSION}."),
+            UnknownRule { name, line } => write!(f, "Unknown rule {name:?} on line {line}."),
+            UnparseableFormula { text, line: Some(line) } => write!(f, "Failed to parse {text:?} on line {line}."),
+            UnparseableFormula { text, line: None } => write!(f, "Failed to parse the goal {text:?}."),
+            Dependency(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+/// The fields that say how to read the rest of a document
+#[derive(Deserialize)]
+struct Header {
+    format: String,
+    version: u64,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Document {
+    format: String,
+    version: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    author: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    hash: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    title: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    created: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    modified: Option<String>,
+    #[serde(default)]
+    goals: Vec<String>,
+    proof: JsonProof,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    comments: BTreeMap<usize, String>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct JsonProof {
+    #[serde(default)]
+    premises: Vec<String>,
+    #[serde(default)]
+    lines: Vec<JsonLine>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+enum JsonLine {
+    Step {
+        formula: String,
+        rule: String,
+        #[serde(default)]
+        deps: Vec<usize>,
+        #[serde(default)]
+        sdeps: Vec<(usize, usize)>,
+    },
+    Subproof(JsonProof),
+}
+
+impl JsonProof {
+    fn from_tree(tree: &TreeProof) -> JsonProof {
+        let premises = tree.premises.iter().map(Expr::to_canonical_string).collect();
+        let lines = tree
+            .lines
+            .iter()
+            .map(|line| match line {
+                TreeLine::Step { expr, rule, deps, sdeps } => JsonLine::Step { formula: expr.to_canonical_string(), rule: RuleM::to_serialized_name(*rule).into(), deps: deps.clone(), sdeps: sdeps.iter().map(|sdep| (*sdep.start(), *sdep.end())).collect() },
+                TreeLine::Subproof(sub) => JsonLine::Subproof(JsonProof::from_tree(sub)),
+            })
+            .collect();
+        JsonProof { premises, lines }
+    }
+
+    /// Convert to a `TreeProof`, where `line` is the number of the last line
+    /// before this proof's first one
+    fn to_tree(&self, line: &mut usize) -> Result<TreeProof, JsonError> {
+        let mut premises = vec![];
+        for text in &self.premises {
+            *line += 1;
+            premises.push(parse_formula(text, Some(*line))?);
+        }
+        let mut lines = vec![];
+        for json_line in &self.lines {
+            lines.push(match json_line {
+                JsonLine::Step { formula, rule, deps, sdeps } => {
+                    *line += 1;
+                    let expr = parse_formula(formula, Some(*line))?;
+                    let rule = RuleM::from_serialized_name(rule).ok_or_else(|| JsonError::UnknownRule { name: rule.clone(), line: *line })?;
+                    TreeLine::Step { expr, rule, deps: deps.clone(), sdeps: sdeps.iter().map(|&(first, last)| first..=last).collect() }
+                }
+                JsonLine::Subproof(sub) => TreeLine::Subproof(sub.to_tree(line)?),
+            });
+        }
+        Ok(TreeProof { premises, lines })
+    }
+}
+
+fn parse_formula(text: &str, line: Option<usize>) -> Result<Expr, JsonError> {
+    crate::parser::parse(text).ok_or_else(|| JsonError::UnparseableFormula { text: text.into(), line })
+}
+
+/// The stable id of each line of a proof, by its line number
+fn line_numbers<P: Proof>(prf: &P) -> impl Iterator<Item = (usize, LineId)> + '_ {
+    prf.iter_lines().filter_map(|info| Some((info.line?, prf.stable_id_of(&info.reference.subset().ok()?)?)))
+}
+
+/// Write a proof and its metadata as JSON
+pub fn proof_to_json<P: Proof>(prf: &P, meta: &ProofMetaData) -> String {
+    let document = Document {
+        format: FORMAT.into(),
+        version: VERSION,
+        author: meta.author.clone(),
+        hash: meta.hash.clone(),
+        title: meta.title.clone(),
+        created: meta.created.clone(),
+        modified: meta.modified.clone(),
+        goals: meta.goals.iter().map(Expr::to_canonical_string).collect(),
+        proof: JsonProof::from_tree(&TreeProof::from_proof(prf)),
+        comments: line_numbers(prf).filter_map(|(line, id)| Some((line, meta.comments.get(&id)?.clone()))).collect(),
+    };
+    serde_json::to_string(&document).expect("a proof document always serializes")
+}
+
+/// Load a proof and its metadata from JSON written by `proof_to_json`
+pub fn proof_from_json<P: Proof>(json: &str) -> Result<(P, ProofMetaData), JsonError> {
+    let syntax = |err: serde_json::Error| JsonError::Syntax { message: err.to_string() };
+    let header: Header = serde_json::from_str(json).map_err(syntax)?;
+    if header.format != FORMAT {
+        return Err(JsonError::WrongFormat { format: header.format });
+    }
+    if header.version != VERSION {
+        return Err(JsonError::UnsupportedVersion { version: header.version });
+    }
+    let document: Document = serde_json::from_str(json).map_err(syntax)?;
+    let goals = document.goals.iter().map(|goal| parse_formula(goal, None)).collect::<Result<Vec<_>, _>>()?;
+    let tree = document.proof.to_tree(&mut 0)?;
+    let prf = tree.to_proof().map_err(JsonError::Dependency)?;
+    // Comments on lines that don't exist are dropped
+    let comments = line_numbers(&prf).filter_map(|(line, id)| Some((id, document.comments.get(&line)?.clone()))).collect();
+    Ok((prf, ProofMetaData { author: document.author, hash: document.hash, title: document.title, created: document.created, modified: document.modified, goals, comments, ..Default::default() }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::proofs::pooledproof::PooledProof;
+    use crate::proofs::xml_interop::proof_from_xml;
+
+    use frunk_core::HList;
+
+    type P = PooledProof<HList![Expr]>;
+
+    fn assert_roundtrips(prf: &P, metadata: &ProofMetaData) {
+        let json = proof_to_json(prf, metadata);
+        let (reloaded, reloaded_metadata) = proof_from_json::<P>(&json).unwrap();
+        assert_eq!(TreeProof::from_proof(&reloaded), TreeProof::from_proof(prf));
+        assert_eq!(reloaded_metadata.author, metadata.author);
+        assert_eq!(reloaded_metadata.hash, metadata.hash);
+        assert_eq!((&reloaded_metadata.title, &reloaded_metadata.created, &reloaded_metadata.modified), (&metadata.title, &metadata.created, &metadata.modified));
+        assert_eq!(reloaded_metadata.goals, metadata.goals);
+        assert_eq!(reloaded.verify_all().iter().map(|(_, _, result)| result.is_ok()).collect::<Vec<_>>(), prf.verify_all().iter().map(|(_, _, result)| result.is_ok()).collect::<Vec<_>>());
+        assert_eq!(proof_to_json(&reloaded, &reloaded_metadata), json);
+    }
+
+    #[test]
+    fn test_json_roundtrip() {
+        let data = &include_bytes!("../../../example-proofs/resolution_example.bram")[..];
+        let (prf, mut metadata, _) = proof_from_xml::<P, _>(data).unwrap();
+        assert_roundtrips(&prf, &metadata);
+        metadata.title = Some("Resolution".into());
+        metadata.mark_modified("2023-04-05T06:07:08Z".into());
+        assert_roundtrips(&prf, &metadata);
+
+        // Subproofs inside subproofs, cited from inside and outside
+        let data = &include_bytes!("../../../example-proofs/propositional_logic_arguments_for_proofs_ii_problem_10.bram")[..];
+        let (prf, metadata, _) = proof_from_xml::<P, _>(data).unwrap();
+        assert!(TreeProof::from_proof(&prf).lines.iter().any(|line| matches!(line, TreeLine::Subproof(sub) if sub.lines.iter().any(|line| matches!(line, TreeLine::Subproof(_))))));
+        assert_roundtrips(&prf, &metadata);
+    }
+
+    #[test]
+    fn test_json_comments() {
+        let data = &include_bytes!("../../../example-proofs/propositional_logic_arguments_for_proofs_ii_problem_10.bram")[..];
+        let (prf, mut metadata, _) = proof_from_xml::<P, _>(data).unwrap();
+        let ids = line_numbers(&prf).collect::<Vec<_>>();
+        let (second, last) = (ids[1], ids[ids.len() - 1]);
+        metadata.comments.insert(second.1, "\"quoted\" \\ <b>&amp;</b>".into());
+        metadata.comments.insert(last.1, "∀x — 証明 👍".into());
+        let json = proof_to_json(&prf, &metadata);
+        assert!(json.contains(r#""comments":{"2":"\"quoted\" \\ <b>&amp;</b>""#));
+        assert_roundtrips(&prf, &metadata);
+
+        // Comments are on the same lines after loading, whatever their stable ids
+        let (reloaded, reloaded_metadata) = proof_from_json::<P>(&json).unwrap();
+        let comments = line_numbers(&reloaded).filter_map(|(line, id)| Some((line, reloaded_metadata.comments.get(&id)?.as_str()))).collect::<Vec<_>>();
+        assert_eq!(comments, vec![(second.0, "\"quoted\" \\ <b>&amp;</b>"), (last.0, "∀x — 証明 👍")]);
+
+        // A comment on a line that doesn't exist is dropped
+        let dangling = json.replace(r#""comments":{"2""#, r#""comments":{"999""#);
+        assert_eq!(proof_from_json::<P>(&dangling).unwrap().1.comments.len(), 1);
+    }
+
+    #[test]
+    fn test_json_fields() {
+        let json = r#"{
+            "format": "aris-proof",
+            "version": 1,
+            "goals": ["A"],
+            "comment": "a field from a later version",
+            "proof": {
+                "premises": ["A"],
+                "lines": [
+                    {"kind": "subproof", "premises": ["B"], "lines": [{"kind": "step", "formula": "A", "rule": "REITERATION", "deps": [1], "color": "red"}]},
+                    {"kind": "step", "formula": "B -> A", "rule": "CONDITIONAL_PROOF", "sdeps": [[2, 3]]}
+                ]
+            }
+        }"#;
+        let (prf, metadata) = proof_from_json::<P>(json).unwrap();
+        assert_eq!(metadata.goals, vec![crate::parser::parse_unwrap("A")]);
+        assert!(prf.verify_all().is_fully_verified());
+
+        let wrong_version = json.replace("\"version\": 1", "\"version\": 2");
+        assert_eq!(proof_from_json::<P>(&wrong_version).unwrap_err(), JsonError::UnsupportedVersion { version: 2 });
+        let wrong_format = json.replace("\"aris-proof\"", "\"something-else\"");
+        assert_eq!(proof_from_json::<P>(&wrong_format).unwrap_err(), JsonError::WrongFormat { format: "something-else".into() });
+        assert!(matches!(proof_from_json::<P>(&json[..json.len() / 2]).unwrap_err(), JsonError::Syntax { .. }));
+        let unknown_rule = json.replace("\"REITERATION\"", "\"BOGUS_RULE\"");
+        assert_eq!(proof_from_json::<P>(&unknown_rule).unwrap_err(), JsonError::UnknownRule { name: "BOGUS_RULE".into(), line: 3 });
+        let dangling = json.replace("\"deps\": [1]", "\"deps\": [9]");
+        assert_eq!(proof_from_json::<P>(&dangling).unwrap_err(), JsonError::Dependency(TreeError::NoSuchLine { line: 9, cited_by: 3 }));
+    }
+}
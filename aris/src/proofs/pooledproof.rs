@@ -109,30 +109,82 @@ While the `[]`s in the `Justification`s are actually `Vec`s and will be that sim
 use crate::expr::Expr;
 use crate::proofs::js_to_pjs;
 use crate::proofs::DisplayIndented;
+use crate::proofs::InvalidRefReason;
 use crate::proofs::JsRef;
 use crate::proofs::Justification;
+use crate::proofs::LineId;
+use crate::proofs::MoveDirection;
+use crate::proofs::MoveError;
 use crate::proofs::PjRef;
 use crate::proofs::PjsRef;
 use crate::proofs::Proof;
+use crate::rules::CheckContext;
 use crate::rules::ProofCheckError;
 use crate::rules::RuleT;
 use crate::zipper_vec::ZipperVec;
 
 use std::collections::BTreeMap;
 use std::collections::HashSet;
+use std::hash::Hash;
+use std::hash::Hasher;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering as AtomicOrdering;
 
 use frunk_core::coproduct::Coproduct;
 use frunk_core::hlist::HCons;
 use frunk_core::Coprod;
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
-pub struct PremKey(usize);
+/// Which `Pools` handed out a key, so that a key from another proof can be
+/// told apart from one whose line was removed. Clones of a proof keep the id,
+/// since the same keys refer to the same lines in them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct PoolId(usize);
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
-pub struct JustKey(usize);
+impl PoolId {
+    fn fresh() -> Self {
+        static NEXT: AtomicUsize = AtomicUsize::new(0);
+        PoolId(NEXT.fetch_add(1, AtomicOrdering::Relaxed))
+    }
+}
+
+/// Define a key type, which is compared, ordered, and hashed by its index
+/// alone, so that the pool it came from only matters to `invalid_ref_reason`
+macro_rules! pool_key {
+    ($name:ident) => {
+        #[derive(Clone, Copy, Debug)]
+        pub struct $name(usize, PoolId);
+
+        impl PartialEq for $name {
+            fn eq(&self, other: &Self) -> bool {
+                self.0 == other.0
+            }
+        }
+
+        impl Eq for $name {}
+
+        impl PartialOrd for $name {
+            fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+                Some(self.cmp(other))
+            }
+        }
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
-pub struct SubKey(usize);
+        impl Ord for $name {
+            fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+                self.0.cmp(&other.0)
+            }
+        }
+
+        impl Hash for $name {
+            fn hash<H: Hasher>(&self, state: &mut H) {
+                self.0.hash(state)
+            }
+        }
+    };
+}
+
+pool_key!(PremKey);
+pool_key!(JustKey);
+pool_key!(SubKey);
 
 type PooledRef = Coprod!(PremKey, JustKey);
 type PjsKey = Coprod!(PremKey, JustKey, SubKey);
@@ -143,11 +195,14 @@ pub struct Pools<T> {
     just_map: BTreeMap<JustKey, Justification<T, PooledRef, SubKey>>,
     sub_map: BTreeMap<SubKey, PooledSubproof<T>>,
     containing_subproof: BTreeMap<PjsKey, SubKey>,
+    stable_ids: BTreeMap<PooledRef, LineId>,
+    next_stable_id: usize,
+    id: PoolId,
 }
 
 impl<T> Pools<T> {
     fn new() -> Self {
-        Pools { prem_map: BTreeMap::new(), just_map: BTreeMap::new(), sub_map: BTreeMap::new(), containing_subproof: BTreeMap::new() }
+        Pools { prem_map: BTreeMap::new(), just_map: BTreeMap::new(), sub_map: BTreeMap::new(), containing_subproof: BTreeMap::new(), stable_ids: BTreeMap::new(), next_stable_id: 0, id: PoolId::fresh() }
     }
     fn subproof_to_subkey(&self, sub: &PooledSubproof<T>) -> Option<SubKey> {
         for (k, v) in self.sub_map.iter() {
@@ -200,6 +255,7 @@ impl<T> Pools<T> {
     }
     fn remove_premise(&mut self, idx: &PremKey) {
         self.prem_map.remove(idx);
+        self.stable_ids.remove(&Coproduct::inject(*idx));
         for (_, v) in self.sub_map.iter_mut() {
             let premise_list = std::mem::replace(&mut v.premise_list, ZipperVec::new());
             v.premise_list = ZipperVec::from_vec(premise_list.iter().filter(|x| x != &idx).cloned().collect());
@@ -208,6 +264,7 @@ impl<T> Pools<T> {
     }
     fn remove_step(&mut self, idx: &JustKey) {
         self.just_map.remove(idx);
+        self.stable_ids.remove(&Coproduct::inject(*idx));
         for (_, v) in self.sub_map.iter_mut() {
             let line_list = std::mem::replace(&mut v.line_list, ZipperVec::new());
             v.line_list = ZipperVec::from_vec(line_list.iter().filter(|x| x.get() != Some(idx)).cloned().collect());
@@ -238,6 +295,58 @@ impl<T> Pools<T> {
         }
         self.remove_line_helper(&Coproduct::inject(*idx));
     }
+    fn memory_stats(&self) -> MemoryStats {
+        fn end<K, V>(map: &BTreeMap<K, V>, key: impl Fn(&K) -> usize) -> usize {
+            map.keys().next_back().map(|k| key(k) + 1).unwrap_or(0)
+        }
+        MemoryStats {
+            live_premises: self.prem_map.len(),
+            tombstoned_premises: end(&self.prem_map, |k| k.0) - self.prem_map.len(),
+            live_steps: self.just_map.len(),
+            tombstoned_steps: end(&self.just_map, |k| k.0) - self.just_map.len(),
+            live_subproofs: self.sub_map.len(),
+            tombstoned_subproofs: end(&self.sub_map, |k| k.0) - self.sub_map.len(),
+        }
+    }
+    /// Renumber the keys of each pool densely, in their existing order
+    fn compact(&mut self) -> RefRemap {
+        use frunk_core::coproduct::Coproduct::{Inl, Inr};
+        let remap = RefRemap {
+            premises: self.prem_map.keys().enumerate().map(|(i, k)| (*k, PremKey(i, self.id))).collect(),
+            steps: self.just_map.keys().enumerate().map(|(i, k)| (*k, JustKey(i, self.id))).collect(),
+            subproofs: self.sub_map.keys().enumerate().map(|(i, k)| (*k, SubKey(i, self.id))).collect(),
+        };
+        self.prem_map = std::mem::take(&mut self.prem_map).into_iter().map(|(k, v)| (remap.premises[&k], v)).collect();
+        self.just_map = std::mem::take(&mut self.just_map)
+            .into_iter()
+            .map(|(k, Justification(expr, rule, deps, sdeps))| {
+                let deps = deps.iter().filter_map(|d| remap.line(d)).collect();
+                let sdeps = sdeps.iter().filter_map(|sr| remap.subproof(sr)).collect();
+                (remap.steps[&k], Justification(expr, rule, deps, sdeps))
+            })
+            .collect();
+        self.sub_map = std::mem::take(&mut self.sub_map)
+            .into_iter()
+            .map(|(k, mut sub)| {
+                sub.remap_keys(&remap);
+                (remap.subproofs[&k], sub)
+            })
+            .collect();
+        self.containing_subproof = std::mem::take(&mut self.containing_subproof)
+            .into_iter()
+            .filter_map(|(k, parent)| {
+                let k: PjsKey = match k {
+                    Inl(pr) => Coproduct::inject(remap.premises.get(&pr).copied()?),
+                    Inr(Inl(jr)) => Coproduct::inject(remap.steps.get(&jr).copied()?),
+                    Inr(Inr(Inl(sr))) => Coproduct::inject(remap.subproof(&sr)?),
+                    Inr(Inr(Inr(void))) => match void {},
+                };
+                Some((k, remap.subproof(&parent)?))
+            })
+            .collect();
+        self.stable_ids = std::mem::take(&mut self.stable_ids).into_iter().filter_map(|(k, id)| Some((remap.line(&k)?, id))).collect();
+        remap
+    }
 }
 
 #[derive(Clone, Debug, PartialEq, Eq)]
@@ -258,23 +367,165 @@ impl<T> PooledSubproof<T> {
     fn new(p: &mut Pools<T>) -> Self {
         PooledSubproof { pools: p as _, premise_list: ZipperVec::new(), line_list: ZipperVec::new() }
     }
+    /// The lines of the subproof `sr`, or of this subproof if `sr` is `None`
+    fn line_list_mut(&mut self, sr: Option<SubKey>) -> Option<&mut ZipperVec<Coprod!(JustKey, SubKey)>> {
+        match sr {
+            Some(sr) => unsafe { &mut *self.pools }.sub_map.get_mut(&sr).map(|sub| &mut sub.line_list),
+            None => Some(&mut self.line_list),
+        }
+    }
+    fn remap_keys(&mut self, remap: &RefRemap) {
+        use frunk_core::coproduct::Coproduct::{Inl, Inr};
+        self.premise_list = ZipperVec::from_vec(self.premise_list.iter().filter_map(|pr| remap.premises.get(pr).copied()).collect());
+        self.line_list = ZipperVec::from_vec(
+            self.line_list
+                .iter()
+                .filter_map(|line| match line {
+                    Inl(jr) => remap.steps.get(jr).map(|jr| Coproduct::inject(*jr)),
+                    Inr(Inl(sr)) => remap.subproof(sr).map(Coproduct::inject),
+                    Inr(Inr(void)) => match *void {},
+                })
+                .collect(),
+        );
+    }
+}
+
+impl<T> PooledProof<T> {
+    /// Renumber the references of every line and subproof so that no keys
+    /// are left unused by lines that were removed. Returns the mapping from
+    /// old references to new ones, for anything else that holds references
+    /// into this proof.
+    pub fn compact(&mut self) -> RefRemap {
+        let remap = self.pools.compact();
+        self.proof.remap_keys(&remap);
+        remap
+    }
+    /// How many keys of each pool are in use, and how many were left unused
+    /// by lines that were removed
+    pub fn memory_stats(&self) -> MemoryStats {
+        self.pools.memory_stats()
+    }
+}
+
+/// Mapping from old references to new ones, from `PooledProof::compact`
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RefRemap {
+    pub premises: BTreeMap<PremKey, PremKey>,
+    pub steps: BTreeMap<JustKey, JustKey>,
+    pub subproofs: BTreeMap<SubKey, SubKey>,
+}
+
+impl RefRemap {
+    /// The new reference for a line, or `None` if it wasn't in the proof
+    pub fn line(&self, r: &Coprod!(PremKey, JustKey)) -> Option<Coprod!(PremKey, JustKey)> {
+        use frunk_core::coproduct::Coproduct::{Inl, Inr};
+        match r {
+            Inl(pr) => self.premises.get(pr).map(|pr| Coproduct::inject(*pr)),
+            Inr(Inl(jr)) => self.steps.get(jr).map(|jr| Coproduct::inject(*jr)),
+            Inr(Inr(void)) => match *void {},
+        }
+    }
+    /// The new reference for a subproof, or `None` if it wasn't in the proof
+    pub fn subproof(&self, r: &SubKey) -> Option<SubKey> {
+        self.subproofs.get(r).copied()
+    }
+}
+
+/// Counts of the keys of each pool of a `PooledProof`, from
+/// `PooledProof::memory_stats`. Tombstoned keys are ones below the highest key
+/// in use whose lines were removed; they aren't reused until `compact`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MemoryStats {
+    pub live_premises: usize,
+    pub tombstoned_premises: usize,
+    pub live_steps: usize,
+    pub tombstoned_steps: usize,
+    pub live_subproofs: usize,
+    pub tombstoned_subproofs: usize,
+}
+
+impl MemoryStats {
+    /// Total tombstoned keys across all pools
+    pub fn tombstones(&self) -> usize {
+        self.tombstoned_premises + self.tombstoned_steps + self.tombstoned_subproofs
+    }
 }
 
 impl<T: Clone> Pools<T> {
     /// Get next unused key in pool's premises map
     pub fn next_premkey(&self) -> PremKey {
         // Increment highest index
-        PremKey(self.prem_map.keys().next_back().map(|key| key.0 + 1).unwrap_or(0))
+        PremKey(self.prem_map.keys().next_back().map(|key| key.0 + 1).unwrap_or(0), self.id)
     }
     /// Get next unused key in pool's justifications map
     pub fn next_justkey(&self) -> JustKey {
         // Increment highest index
-        JustKey(self.just_map.keys().next_back().map(|key| key.0 + 1).unwrap_or(0))
+        JustKey(self.just_map.keys().next_back().map(|key| key.0 + 1).unwrap_or(0), self.id)
+    }
+    /// Give a new line the next stable id, which is never reused even after the line is removed
+    fn assign_stable_id(&mut self, idx: PooledRef) {
+        self.stable_ids.insert(idx, LineId(self.next_stable_id));
+        self.next_stable_id += 1;
     }
     /// Get next unused key in pool's subproofs map
     pub fn next_subkey(&self) -> SubKey {
         // Increment highest index
-        SubKey(self.sub_map.keys().next_back().map(|key| key.0 + 1).unwrap_or(0))
+        SubKey(self.sub_map.keys().next_back().map(|key| key.0 + 1).unwrap_or(0), self.id)
+    }
+    /// Keys are handed out in increasing order, so a missing key before the
+    /// next unused one must have been removed
+    fn invalid_ref_reason(&self, idx: &PjsKey) -> Option<InvalidRefReason> {
+        use frunk_core::coproduct::Coproduct::{Inl, Inr};
+        let (pool, present, in_range) = match idx {
+            Inl(pr) => (pr.1, self.prem_map.contains_key(pr), *pr < self.next_premkey()),
+            Inr(Inl(jr)) => (jr.1, self.just_map.contains_key(jr), *jr < self.next_justkey()),
+            Inr(Inr(Inl(sr))) => (sr.1, self.sub_map.contains_key(sr), *sr < self.next_subkey()),
+            Inr(Inr(Inr(void))) => match *void {},
+        };
+        match (pool == self.id, present, in_range) {
+            (false, _, _) => Some(InvalidRefReason::OtherProof),
+            (true, true, _) => None,
+            (true, false, true) => Some(InvalidRefReason::Removed),
+            (true, false, false) => Some(InvalidRefReason::OutOfRange),
+        }
+    }
+}
+
+impl<Tail: Default + Clone> PooledSubproof<HCons<Expr, Tail>> {
+    /// Move the step `r` from the lines of the subproof `from` to those of
+    /// `to`, where `None` is this subproof, with `place` inserting it. The key
+    /// stays the same. Undone if it puts a dependency out of scope that wasn't
+    /// already.
+    fn relocate_step<F: FnOnce(&mut Vec<Coprod!(JustKey, SubKey)>)>(&mut self, r: JustKey, from: Option<SubKey>, to: Option<SubKey>, place: F) -> Result<JustKey, MoveError<PjRef<Self>, SubKey>> {
+        let pools = unsafe { &mut *self.pools };
+        let line: Coprod!(JustKey, SubKey) = Coproduct::inject(r);
+        let before = self.dependency_violations();
+        let old_from = self.line_list_mut(from).cloned().unwrap_or_default();
+        let old_to = self.line_list_mut(to).cloned().unwrap_or_default();
+        if let Some(lines) = self.line_list_mut(from) {
+            *lines = ZipperVec::from_vec(old_from.iter().filter(|x| **x != line).cloned().collect());
+        }
+        if let Some(lines) = self.line_list_mut(to) {
+            let mut v = lines.iter().cloned().collect();
+            place(&mut v);
+            *lines = ZipperVec::from_vec(v);
+        }
+        let set_parent = |pools: &mut Pools<_>, parent: Option<SubKey>| match parent {
+            Some(s) => pools.containing_subproof.insert(Coproduct::inject(r), s),
+            None => pools.containing_subproof.remove(&Coproduct::inject(r)),
+        };
+        set_parent(pools, to);
+        if let Some((dep, of)) = self.dependency_violations().into_iter().find(|violation| !before.contains(violation)) {
+            if let Some(lines) = self.line_list_mut(to) {
+                *lines = old_to;
+            }
+            if let Some(lines) = self.line_list_mut(from) {
+                *lines = old_from;
+            }
+            set_parent(pools, from);
+            return Err(MoveError::WouldBreakDependency { dep, of });
+        }
+        Ok(r)
     }
 }
 
@@ -307,6 +558,7 @@ impl<Tail: Default + Clone> Proof for PooledSubproof<HCons<Expr, Tail>> {
         pools.just_map.get_mut(r).map(|j_hcons: &mut Justification<HCons<Expr, Tail>, _, _>| {
             let mut j_expr: Justification<Expr, _, _> = Justification(j_hcons.0.get().clone(), j_hcons.1, j_hcons.2.clone(), j_hcons.3.clone());
             let ret = f(&mut j_expr);
+            j_expr.normalize();
             *j_hcons.0.get_mut() = j_expr.0;
             j_hcons.1 = j_expr.1;
             j_hcons.2 = j_expr.2;
@@ -321,6 +573,7 @@ impl<Tail: Default + Clone> Proof for PooledSubproof<HCons<Expr, Tail>> {
         let pools = unsafe { &mut *self.pools };
         let idx = pools.next_premkey();
         pools.prem_map.insert(idx, HCons { head: e, tail: Tail::default() });
+        pools.assign_stable_id(Coproduct::inject(idx));
         pools.set_parent(Coproduct::inject(idx), self);
         self.premise_list.push(idx);
         idx
@@ -346,12 +599,15 @@ impl<Tail: Default + Clone> Proof for PooledSubproof<HCons<Expr, Tail>> {
         // 3 | | P ; -> Elim statement_at(1), statement_at(2)
         // 4 | Top -> P ; -> Intro subproof_beginning_at(1)
         let tps = pools.transitive_parents(Coproduct::inject(idx));
+        let mut just = just;
+        just.normalize();
         let Justification(e, r, deps, mut sdeps) = just;
         // silently remove sdeps that are invalid due to this pattern, as they show up naturally when parsing Aris's xml due to an ambiguity between deps and sdeps
         sdeps.retain(|x| !tps.contains(x));
 
         self.line_list.push(Coproduct::inject(idx));
         pools.just_map.insert(idx, Justification(HCons { head: e, tail: Tail::default() }, r, deps, sdeps));
+        pools.assign_stable_id(Coproduct::inject(idx));
 
         idx
     }
@@ -359,6 +615,7 @@ impl<Tail: Default + Clone> Proof for PooledSubproof<HCons<Expr, Tail>> {
         let pools = unsafe { &mut *self.pools };
         let idx = pools.next_premkey();
         pools.prem_map.insert(idx, HCons { head: e, tail: Tail::default() });
+        pools.assign_stable_id(Coproduct::inject(idx));
         if let Some(s) = pools.parent_of(&Coproduct::inject(*r)) {
             self.with_mut_subproof(&s, |sub| {
                 pools.set_parent(Coproduct::inject(idx), sub);
@@ -388,7 +645,10 @@ impl<Tail: Default + Clone> Proof for PooledSubproof<HCons<Expr, Tail>> {
         let pools = unsafe { &mut *self.pools };
         let idx = pools.next_justkey();
         // TODO: occurs-before check
+        let mut just = just;
+        just.normalize();
         pools.just_map.insert(idx, Justification(HCons { head: just.0, tail: Tail::default() }, just.1, just.2, just.3));
+        pools.assign_stable_id(Coproduct::inject(idx));
         if let Some(s) = pools.parent_of(&js_to_pjs::<Self>(*r)) {
             self.with_mut_subproof(&s, |sub| {
                 pools.set_parent(Coproduct::inject(idx), sub);
@@ -407,6 +667,90 @@ impl<Tail: Default + Clone> Proof for PooledSubproof<HCons<Expr, Tail>> {
         let pools = unsafe { &mut *self.pools };
         pools.remove_subproof(r);
     }
+    fn move_line(&mut self, r: &Self::JustificationReference, direction: MoveDirection) -> Result<(), MoveError<PjRef<Self>, Self::SubproofReference>> {
+        let pools = unsafe { &mut *self.pools };
+        let line: PjRef<Self> = Coproduct::inject(*r);
+        if !pools.just_map.contains_key(r) {
+            return Err(MoveError::LineDoesNotExist(line));
+        }
+        let before = self.dependency_violations();
+        let parent = pools.parent_of(&Coproduct::inject(*r));
+        let swap = |this: &mut Self, after: bool| match parent {
+            Some(s) => this.with_mut_subproof(&s, |sub| sub.line_list.swap_relative(&Coproduct::inject(*r), after)).unwrap_or(false),
+            None => this.line_list.swap_relative(&Coproduct::inject(*r), after),
+        };
+        let after = direction == MoveDirection::Down;
+        if !swap(self, after) {
+            return Err(if parent.is_some() { MoveError::CrossesSubproofBoundary(line) } else { MoveError::NoNeighbor(line) });
+        }
+        // Only report dependencies that this move broke, not ones that were already out of scope
+        if let Some((dep, of)) = self.dependency_violations().into_iter().find(|violation| !before.contains(violation)) {
+            swap(self, !after);
+            return Err(MoveError::WouldBreakDependency { dep, of });
+        }
+        Ok(())
+    }
+    fn move_premise(&mut self, r: &Self::PremiseReference, direction: MoveDirection) -> Result<(), MoveError<PjRef<Self>, Self::SubproofReference>> {
+        let pools = unsafe { &mut *self.pools };
+        let line: PjRef<Self> = Coproduct::inject(*r);
+        if !pools.prem_map.contains_key(r) {
+            return Err(MoveError::LineDoesNotExist(line));
+        }
+        // Premises always come before steps, so reordering them can't put a dependency after the step citing it
+        let parent = pools.parent_of(&Coproduct::inject(*r));
+        let after = direction == MoveDirection::Down;
+        let moved = match parent {
+            Some(s) => self.with_mut_subproof(&s, |sub| sub.premise_list.swap_relative(r, after)).unwrap_or(false),
+            None => self.premise_list.swap_relative(r, after),
+        };
+        if !moved {
+            return Err(if parent.is_some() { MoveError::CrossesSubproofBoundary(line) } else { MoveError::NoNeighbor(line) });
+        }
+        Ok(())
+    }
+    fn nest_line(&mut self, r: &Self::JustificationReference, direction: MoveDirection) -> Result<Self::JustificationReference, MoveError<PjRef<Self>, Self::SubproofReference>> {
+        let pools = unsafe { &mut *self.pools };
+        let line: PjRef<Self> = Coproduct::inject(*r);
+        if !pools.just_map.contains_key(r) {
+            return Err(MoveError::LineDoesNotExist(line));
+        }
+        let parent = pools.parent_of(&Coproduct::inject(*r));
+        let siblings = self.line_list_mut(parent).map(|lines| lines.iter().cloned().collect::<Vec<_>>()).unwrap_or_default();
+        let i = siblings.iter().position(|x| x == &Coproduct::inject(*r)).ok_or(MoveError::LineDoesNotExist(line))?;
+        let neighbor = match direction {
+            MoveDirection::Up => i.checked_sub(1),
+            MoveDirection::Down => Some(i + 1),
+        };
+        match neighbor.and_then(|j| siblings.get(j)).and_then(|x| x.get::<SubKey, _>()) {
+            Some(sr) => self.relocate_step(*r, parent, Some(*sr), |lines| match direction {
+                MoveDirection::Up => lines.push(Coproduct::inject(*r)),
+                MoveDirection::Down => lines.insert(0, Coproduct::inject(*r)),
+            }),
+            None => Err(MoveError::NoAdjacentSubproof(line)),
+        }
+    }
+    fn unnest_line(&mut self, r: &Self::JustificationReference, direction: MoveDirection) -> Result<Self::JustificationReference, MoveError<PjRef<Self>, Self::SubproofReference>> {
+        let pools = unsafe { &mut *self.pools };
+        let line: PjRef<Self> = Coproduct::inject(*r);
+        if !pools.just_map.contains_key(r) {
+            return Err(MoveError::LineDoesNotExist(line));
+        }
+        let sr = pools.parent_of(&Coproduct::inject(*r)).ok_or(MoveError::NotAtSubproofEdge(line))?;
+        let siblings = self.line_list_mut(Some(sr)).map(|lines| lines.iter().cloned().collect::<Vec<_>>()).unwrap_or_default();
+        let edge = match direction {
+            MoveDirection::Up => siblings.first(),
+            MoveDirection::Down => siblings.last(),
+        };
+        if edge != Some(&Coproduct::inject(*r)) {
+            return Err(MoveError::NotAtSubproofEdge(line));
+        }
+        let grandparent = pools.parent_of(&Coproduct::inject(sr));
+        self.relocate_step(*r, Some(sr), grandparent, |lines| {
+            let i = lines.iter().position(|x| x == &Coproduct::inject(sr)).unwrap_or(lines.len());
+            let i = if direction == MoveDirection::Down { i + 1 } else { i };
+            lines.insert(i.min(lines.len()), Coproduct::inject(*r));
+        })
+    }
     fn premises(&self) -> Vec<Self::PremiseReference> {
         self.premise_list.iter().cloned().collect()
     }
@@ -417,31 +761,42 @@ impl<Tail: Default + Clone> Proof for PooledSubproof<HCons<Expr, Tail>> {
         let pools = unsafe { &mut *self.pools };
         pools.parent_of(r)
     }
-    fn verify_line(&self, r: &PjRef<Self>) -> Result<(), ProofCheckError<PjRef<Self>, Self::SubproofReference>> {
+    fn stable_id_of(&self, r: &PjRef<Self>) -> Option<LineId> {
+        let pools = unsafe { &*self.pools };
+        pools.stable_ids.get(r).copied()
+    }
+    fn ref_of_stable_id(&self, id: LineId) -> Option<PjRef<Self>> {
+        let pools = unsafe { &*self.pools };
+        pools.stable_ids.iter().find(|(_, other)| **other == id).map(|(r, _)| *r)
+    }
+    fn set_stable_id(&mut self, r: &PjRef<Self>, id: LineId) -> bool {
+        let pools = unsafe { &mut *self.pools };
+        if !pools.stable_ids.contains_key(r) || pools.stable_ids.iter().any(|(other, other_id)| *other_id == id && other != r) {
+            return false;
+        }
+        pools.stable_ids.insert(*r, id);
+        pools.next_stable_id = pools.next_stable_id.max(id.0 + 1);
+        true
+    }
+    fn next_stable_id(&self) -> LineId {
+        LineId(unsafe { &*self.pools }.next_stable_id)
+    }
+    fn reserve_stable_ids(&mut self, next: LineId) {
+        let pools = unsafe { &mut *self.pools };
+        pools.next_stable_id = pools.next_stable_id.max(next.0);
+    }
+    fn invalid_ref_reason(&self, r: &PjsRef<Self>) -> Option<InvalidRefReason> {
+        unsafe { &*self.pools }.invalid_ref_reason(r)
+    }
+    fn verify_line_with(&self, r: &PjRef<Self>, ctx: &CheckContext) -> Result<(), ProofCheckError<PjRef<Self>, Self::SubproofReference>> {
         use self::Coproduct::{Inl, Inr};
         match self.lookup_pj(r) {
             None => Err(ProofCheckError::LineDoesNotExist(*r)),
             Some(Inl(_)) => Ok(()), // premises are always valid
             Some(Inr(Inl(Justification(conclusion, rule, deps, sdeps)))) => {
-                // TODO: efficient caching for ReferencesLaterLine check, so this isn't potentially O(n)
-                let mut valid_deps = HashSet::new();
-                let mut valid_sdeps = HashSet::new();
-                self.possible_deps_for_line(r, &mut valid_deps, &mut valid_sdeps);
-                println!("possible_deps_for_line: {r:?} {valid_deps:?} {valid_sdeps:?}");
-
-                for dep in deps.iter() {
-                    let dep_co = Coproduct::inject(*dep);
-                    if !self.can_reference_dep(r, &dep_co) {
-                        return Err(ProofCheckError::ReferencesLaterLine(*r, dep_co));
-                    }
-                }
-                for sdep in sdeps.iter() {
-                    let sdep_co = Coproduct::inject(*sdep);
-                    if !self.can_reference_dep(r, &sdep_co) {
-                        return Err(ProofCheckError::ReferencesLaterLine(*r, sdep_co));
-                    }
-                }
-                rule.check(self, conclusion, deps, sdeps)
+                // TODO: efficient caching for the reference check, so this isn't potentially O(n)
+                self.check_references(r)?;
+                rule.check(self, conclusion, deps, sdeps, ctx)
             }
             Some(Inr(Inr(void))) => match void {},
         }
@@ -509,6 +864,18 @@ impl<Tail: Default + Clone> Proof for PooledProof<HCons<Expr, Tail>> {
         self.proof.line_list = ZipperVec::from_vec(line_list.iter().filter(|x| x.get() != Some(r)).cloned().collect());
         self.proof.remove_subproof(r);
     }
+    fn move_line(&mut self, r: &Self::JustificationReference, direction: MoveDirection) -> Result<(), MoveError<PjRef<Self>, Self::SubproofReference>> {
+        self.proof.move_line(r, direction)
+    }
+    fn move_premise(&mut self, r: &Self::PremiseReference, direction: MoveDirection) -> Result<(), MoveError<PjRef<Self>, Self::SubproofReference>> {
+        self.proof.move_premise(r, direction)
+    }
+    fn nest_line(&mut self, r: &Self::JustificationReference, direction: MoveDirection) -> Result<Self::JustificationReference, MoveError<PjRef<Self>, Self::SubproofReference>> {
+        self.proof.nest_line(r, direction)
+    }
+    fn unnest_line(&mut self, r: &Self::JustificationReference, direction: MoveDirection) -> Result<Self::JustificationReference, MoveError<PjRef<Self>, Self::SubproofReference>> {
+        self.proof.unnest_line(r, direction)
+    }
     fn premises(&self) -> Vec<Self::PremiseReference> {
         self.proof.premises()
     }
@@ -518,8 +885,26 @@ impl<Tail: Default + Clone> Proof for PooledProof<HCons<Expr, Tail>> {
     fn parent_of_line(&self, r: &PjsRef<Self>) -> Option<Self::SubproofReference> {
         self.proof.parent_of_line(r)
     }
-    fn verify_line(&self, r: &PjRef<Self>) -> Result<(), ProofCheckError<PjRef<Self>, Self::SubproofReference>> {
-        self.proof.verify_line(r)
+    fn stable_id_of(&self, r: &PjRef<Self>) -> Option<LineId> {
+        self.proof.stable_id_of(r)
+    }
+    fn ref_of_stable_id(&self, id: LineId) -> Option<PjRef<Self>> {
+        self.proof.ref_of_stable_id(id)
+    }
+    fn set_stable_id(&mut self, r: &PjRef<Self>, id: LineId) -> bool {
+        self.proof.set_stable_id(r, id)
+    }
+    fn next_stable_id(&self) -> LineId {
+        self.proof.next_stable_id()
+    }
+    fn reserve_stable_ids(&mut self, next: LineId) {
+        self.proof.reserve_stable_ids(next)
+    }
+    fn invalid_ref_reason(&self, r: &PjsRef<Self>) -> Option<InvalidRefReason> {
+        self.proof.invalid_ref_reason(r)
+    }
+    fn verify_line_with(&self, r: &PjRef<Self>, ctx: &CheckContext) -> Result<(), ProofCheckError<PjRef<Self>, Self::SubproofReference>> {
+        self.proof.verify_line_with(r, ctx)
     }
 }
 
@@ -588,6 +973,953 @@ mod tests {
         println!("{prf}");
     }
 
+    #[test]
+    fn test_insert_relative_to_subproof() {
+        use crate::parser::parse_unwrap as p;
+        let step = |e| Justification(p(e), RuleM::EmptyRule, vec![], vec![]);
+        let mut prf = PooledProof::<HList![Expr]>::new();
+        prf.add_premise(p("A"));
+        let s1 = prf.add_step(step("A"));
+        let sub1 = prf.add_subproof();
+        let s2 = prf.add_step(step("B"));
+
+        // Inserting after a subproof makes the new item its next sibling
+        let s3 = prf.add_step_relative(step("C"), &Coproduct::inject(sub1), true);
+        let sub2 = prf.add_subproof_relative(&Coproduct::inject(sub1), true);
+        assert_eq!(prf.lines(), vec![Coproduct::inject(s1), Coproduct::inject(sub1), Coproduct::inject(sub2), Coproduct::inject(s3), Coproduct::inject(s2)]);
+        assert_eq!(prf.parent_of_line(&Coproduct::inject(s3)), None);
+        assert_eq!(prf.parent_of_line(&Coproduct::inject(sub2)), None);
+
+        // Inserting before the first subproof of a parent puts the new item
+        // right after the parent's premises
+        let (prem, sub3) = prf
+            .with_mut_subproof(&sub1, |sub| {
+                let prem = sub.add_premise(p("B"));
+                (prem, sub.add_subproof())
+            })
+            .unwrap();
+        let s4 = prf.add_step_relative(step("D"), &Coproduct::inject(sub3), false);
+        let sub4 = prf.add_subproof_relative(&Coproduct::inject(sub3), false);
+        let inner = prf.lookup_subproof(&sub1).unwrap();
+        assert_eq!(inner.premises(), vec![prem]);
+        assert_eq!(inner.lines(), vec![Coproduct::inject(s4), Coproduct::inject(sub4), Coproduct::inject(sub3)]);
+        assert_eq!(prf.parent_of_line(&Coproduct::inject(s4)), Some(sub1));
+        assert_eq!(prf.parent_of_line(&Coproduct::inject(sub4)), Some(sub1));
+    }
+
+    #[test]
+    fn test_move_line() {
+        use crate::parser::parse_unwrap as p;
+        use crate::proofs::LineNames;
+        let pj = |r: JustKey| -> PjRef<PooledProof<HList![Expr]>> { Coproduct::inject(r) };
+        let mut prf = PooledProof::<HList![Expr]>::new();
+        let p1 = prf.add_premise(p("A"));
+        let p2 = prf.add_premise(p("B"));
+        let s1 = prf.add_step(Justification(p("A"), RuleM::Reit, vec![Coproduct::inject(p1)], vec![]));
+        let s2 = prf.add_step(Justification(p("B"), RuleM::Reit, vec![Coproduct::inject(p2)], vec![]));
+        let s3 = prf.add_step(Justification(p("A & B"), RuleM::AndIntro, vec![Coproduct::inject(s1), Coproduct::inject(s2)], vec![]));
+        let sub = prf.add_subproof();
+        let (sp, ss1, ss2) = prf
+            .with_mut_subproof(&sub, |sub| {
+                let sp = sub.add_premise(p("C"));
+                let ss1 = sub.add_step(Justification(p("C"), RuleM::Reit, vec![Coproduct::inject(sp)], vec![]));
+                let ss2 = sub.add_step(Justification(p("C"), RuleM::Reit, vec![Coproduct::inject(ss1)], vec![]));
+                (sp, ss1, ss2)
+            })
+            .unwrap();
+        let s4 = prf.add_step(Justification(p("C -> C"), RuleM::ImpIntro, vec![], vec![sub]));
+
+        // Independent neighbors can be swapped
+        assert_eq!(prf.move_line(&s1, MoveDirection::Down), Ok(()));
+        assert_eq!(prf.lines(), vec![Coproduct::inject(s2), Coproduct::inject(s1), Coproduct::inject(s3), Coproduct::inject(sub), Coproduct::inject(s4)]);
+        assert_eq!(prf.move_line(&s1, MoveDirection::Up), Ok(()));
+        assert_eq!(prf.move_premise(&p2, MoveDirection::Up), Ok(()));
+        assert_eq!(prf.premises(), vec![p2, p1]);
+
+        // Moves that would put a step before something it cites are refused, and leave the proof unchanged
+        assert_eq!(prf.move_line(&s3, MoveDirection::Up), Err(MoveError::WouldBreakDependency { dep: Coproduct::inject(pj(s2)), of: pj(s3) }));
+        assert_eq!(prf.move_line(&s4, MoveDirection::Up), Err(MoveError::WouldBreakDependency { dep: Coproduct::inject(sub), of: pj(s4) }));
+        assert_eq!(prf.move_line(&ss1, MoveDirection::Down), Err(MoveError::WouldBreakDependency { dep: Coproduct::inject(pj(ss1)), of: pj(ss2) }));
+        assert_eq!(prf.lines(), vec![Coproduct::inject(s1), Coproduct::inject(s2), Coproduct::inject(s3), Coproduct::inject(sub), Coproduct::inject(s4)]);
+        assert_eq!(prf.lookup_subproof(&sub).unwrap().lines(), vec![Coproduct::inject(ss1), Coproduct::inject(ss2)]);
+        let names = LineNames::new(&prf);
+        let describe = |err: MoveError<PjRef<PooledProof<HList![Expr]>>, SubKey>| err.describe(|r| names.line(r), |s| names.subproof(s));
+        assert_eq!(describe(prf.move_line(&s4, MoveDirection::Up).unwrap_err()), "Moving this line would put lines 6-8 after line 9, which depends on it.");
+        assert_eq!(describe(prf.move_line(&ss1, MoveDirection::Up).unwrap_err()), "Can't move line 7 out of its subproof.");
+
+        // Lines can't leave their subproof
+        assert_eq!(prf.move_line(&ss1, MoveDirection::Up), Err(MoveError::CrossesSubproofBoundary(Coproduct::inject(ss1))));
+        assert_eq!(prf.move_line(&ss2, MoveDirection::Down), Err(MoveError::CrossesSubproofBoundary(Coproduct::inject(ss2))));
+        assert_eq!(prf.move_premise(&sp, MoveDirection::Down), Err(MoveError::CrossesSubproofBoundary(Coproduct::inject(sp))));
+        assert_eq!(prf.move_line(&s1, MoveDirection::Up), Err(MoveError::NoNeighbor(Coproduct::inject(s1))));
+        assert_eq!(prf.move_line(&s4, MoveDirection::Down), Err(MoveError::NoNeighbor(Coproduct::inject(s4))));
+    }
+
+    #[test]
+    fn test_set_premise_order() {
+        use crate::proofs::builder::ProofBuilder;
+        use std::collections::HashMap;
+        type P = PooledProof<HList![Expr]>;
+        let (mut prf, lines) = ProofBuilder::new()
+            .premise("A")
+            .premise("B")
+            .premise("C")
+            .step("A & B", RuleM::AndIntro, crate::deps![1, 2])
+            .subproof(|b| b.premise("D").premise("E").step("D & E", RuleM::AndIntro, crate::deps![5, 6]))
+            .build_with_lines::<P>()
+            .unwrap();
+        assert!(prf.verify_all().is_fully_verified());
+        let prem = |n: usize| *lines[n - 1].get::<PremKey, _>().unwrap();
+        let sub = prf.parent_of_line(&Coproduct::inject(prem(5))).unwrap();
+
+        prf.set_premise_order(&[prem(3), prem(1), prem(2)]).unwrap();
+        assert_eq!(prf.premises(), vec![prem(3), prem(1), prem(2)]);
+        prf.set_premise_order(&[prem(6), prem(5)]).unwrap();
+        assert_eq!(prf.lookup_subproof(&sub).unwrap().premises(), vec![prem(6), prem(5)]);
+        assert!(prf.verify_all().is_fully_verified());
+        let numbers: HashMap<PjRef<P>, usize> = prf.iter_lines().filter_map(|info| Some((info.reference.subset().ok()?, info.line?))).collect();
+        let renumbered = lines.iter().map(|r| numbers[r]).collect::<Vec<_>>();
+        assert_eq!(renumbered, vec![2, 3, 1, 4, 6, 5, 7]);
+
+        let not_a_permutation = Err(MoveError::NotAPermutation);
+        assert_eq!(prf.set_premise_order(&[prem(1), prem(2)]), not_a_permutation);
+        assert_eq!(prf.set_premise_order(&[prem(1), prem(1), prem(2)]), not_a_permutation);
+        assert_eq!(prf.set_premise_order(&[prem(1), prem(2), prem(5)]), not_a_permutation);
+        let missing = PremKey(100, prf.pools.id);
+        assert_eq!(prf.set_premise_order(&[prem(1), prem(2), missing]), Err(MoveError::LineDoesNotExist(Coproduct::inject(missing))));
+        assert_eq!(prf.premises(), vec![prem(3), prem(1), prem(2)]);
+    }
+
+    #[test]
+    fn test_indent_line() {
+        use crate::proofs::builder::ProofBuilder;
+        type P = PooledProof<HList![Expr]>;
+        let (mut prf, lines) = ProofBuilder::new()
+            .premise("A")
+            .subproof(|b| b.premise("B").step("A", RuleM::Reit, crate::deps![1]))
+            .step("A", RuleM::Reit, crate::deps![3])
+            .step("B -> A", RuleM::ImpIntro, crate::deps![; 2..=3])
+            .subproof(|b| b.premise("C").step("C", RuleM::Reit, crate::deps![6]))
+            .step("A", RuleM::Reit, crate::deps![1])
+            .step("A", RuleM::Reit, crate::deps![8])
+            .build_with_lines::<P>()
+            .unwrap();
+        let step = |n: usize| *lines[n - 1].get::<JustKey, _>().unwrap();
+        let pj = |n: usize| -> PjRef<P> { Coproduct::inject(step(n)) };
+        let (sub1, sub2) = (prf.parent_of_line(&Coproduct::inject(step(3))).unwrap(), prf.parent_of_line(&Coproduct::inject(step(7))).unwrap());
+        let top_lines = prf.lines();
+
+        // Line 4 cites the conclusion of the subproof above it, which is only in scope from inside it
+        assert!(prf.verify_line(&pj(4)).is_err());
+        assert_eq!(prf.indent_line(&step(4)), Ok(step(4)));
+        assert_eq!(prf.lookup_subproof(&sub1).unwrap().lines(), vec![Coproduct::inject(step(3)), Coproduct::inject(step(4))]);
+        assert_eq!(prf.parent_of_line(&Coproduct::inject(step(4))), Some(sub1));
+        assert!(prf.verify_line(&pj(4)).is_ok());
+        assert!(prf.verify_line(&pj(5)).is_ok());
+
+        // Line 5 would cite the subproof it's in, and line 9 would cite a line inside a subproof
+        assert_eq!(prf.indent_line(&step(5)), Err(MoveError::WouldBreakDependency { dep: Coproduct::inject(sub1), of: pj(5) }));
+        assert_eq!(prf.indent_line(&step(8)), Err(MoveError::WouldBreakDependency { dep: Coproduct::inject(pj(8)), of: pj(9) }));
+        assert_eq!(prf.lookup_subproof(&sub2).unwrap().lines(), vec![Coproduct::inject(step(7))]);
+        assert_eq!(prf.parent_of_line(&Coproduct::inject(step(8))), None);
+        assert_eq!(prf.indent_line(&step(9)), Err(MoveError::NoAdjacentSubproof(pj(9))));
+        assert_eq!(prf.outdent_line(&step(9)), Err(MoveError::NotAtSubproofEdge(pj(9))));
+
+        // The first line of a subproof moves out above it, and can be moved back in from above
+        assert_eq!(prf.outdent_line(&step(3)), Ok(step(3)));
+        assert_eq!(prf.lines()[0], Coproduct::inject(step(3)));
+        assert_eq!(prf.nest_line(&step(3), MoveDirection::Down), Ok(step(3)));
+
+        assert_eq!(prf.outdent_line(&step(4)), Ok(step(4)));
+        assert_eq!(prf.lines(), top_lines);
+        assert_eq!(prf.lookup_subproof(&sub1).unwrap().lines(), vec![Coproduct::inject(step(3))]);
+    }
+
+    #[test]
+    fn test_forward_and_self_references() {
+        use crate::parser::parse_unwrap as p;
+        use crate::rules::ProofCheckError;
+        let mut prf = PooledProof::<HList![Expr]>::new();
+        let p1 = prf.add_premise(p("A"));
+        let s2 = prf.add_step(Justification(p("A"), RuleM::Reit, vec![], vec![]));
+        let s3 = prf.add_step(Justification(p("A"), RuleM::Reit, vec![Coproduct::inject(p1)], vec![]));
+        let sub = prf.add_subproof();
+        let s4 = prf
+            .with_mut_subproof(&sub, |sub| {
+                let sp = sub.add_premise(p("B"));
+                sub.add_step(Justification(p("B"), RuleM::Reit, vec![Coproduct::inject(sp)], vec![]))
+            })
+            .unwrap();
+        let s5 = prf.add_step(Justification(p("A"), RuleM::Reit, vec![Coproduct::inject(s4)], vec![]));
+        let s6 = prf.add_step(Justification(p("B -> B"), RuleM::ImpIntro, vec![], vec![sub]));
+        let pj = |jr: JustKey| -> PjRef<PooledProof<HList![Expr]>> { Coproduct::inject(jr) };
+
+        prf.with_mut_step(&s2, |j| j.2 = vec![pj(s2)]);
+        assert_eq!(prf.verify_line(&pj(s2)), Err(ProofCheckError::SelfReference));
+        prf.with_mut_step(&s2, |j| j.2 = vec![pj(s3)]);
+        assert_eq!(prf.verify_line(&pj(s2)), Err(ProofCheckError::ForwardReference { dep: Coproduct::inject(pj(s3)) }));
+        prf.with_mut_step(&s2, |j| (j.2, j.3) = (vec![], vec![sub]));
+        assert_eq!(prf.verify_line(&pj(s2)), Err(ProofCheckError::ForwardReference { dep: Coproduct::inject(sub) }));
+        // Earlier, but out of scope
+        assert_eq!(prf.verify_line(&pj(s5)), Err(ProofCheckError::ReferencesLaterLine(pj(s5), Coproduct::inject(pj(s4)))));
+        let missing = JustKey(100, prf.pools.id);
+        prf.with_mut_step(&s3, |j| j.2 = vec![Coproduct::inject(missing)]);
+        assert_eq!(prf.verify_line(&pj(s3)), Err(ProofCheckError::LineDoesNotExist(pj(missing))));
+        assert!(prf.verify_line(&pj(s6)).is_ok());
+
+        let report = prf.verify_all();
+        assert_eq!(report.get(&pj(s2)), Some(&Err(ProofCheckError::ForwardReference { dep: Coproduct::inject(sub) })));
+        assert!(!prf.can_reference_dep(&pj(s6), &Coproduct::inject(pj(s6))));
+    }
+
+    #[test]
+    fn test_remove_line_cascade() {
+        use crate::parser::parse_unwrap as p;
+        use crate::rules::ProofCheckError;
+        let mut prf = PooledProof::<HList![Expr]>::new();
+        let p1 = prf.add_premise(p("A"));
+        let p2 = prf.add_premise(p("B"));
+        let s3 = prf.add_step(Justification(p("A & B"), RuleM::AndIntro, vec![Coproduct::inject(p1), Coproduct::inject(p2)], vec![]));
+        let s4 = prf.add_step(Justification(p("A"), RuleM::Reit, vec![Coproduct::inject(p1)], vec![]));
+        let s5 = prf.add_step(Justification(p("B"), RuleM::Reit, vec![Coproduct::inject(p2)], vec![]));
+
+        assert_eq!(prf.remove_line_cascade(&Coproduct::inject(p2)), vec![s3, s5]);
+        assert_eq!(prf.premises(), vec![p1]);
+        assert_eq!(prf.lookup_step(&s3).unwrap().2, vec![Coproduct::inject(p1)]);
+        assert_eq!(prf.lookup_step(&s4).unwrap().2, vec![Coproduct::inject(p1)]);
+        assert!(prf.lookup_step(&s5).unwrap().2.is_empty());
+        assert!(matches!(prf.verify_line(&Coproduct::inject(s5)), Err(ProofCheckError::IncorrectDepCount(..))));
+
+        let sub = prf.add_subproof();
+        prf.with_mut_subproof(&sub, |sub| {
+            let sp = sub.add_premise(p("C"));
+            sub.add_step(Justification(p("C"), RuleM::Reit, vec![Coproduct::inject(sp)], vec![]));
+        });
+        let s6 = prf.add_step(Justification(p("C -> C"), RuleM::ImpIntro, vec![], vec![sub]));
+        assert_eq!(prf.remove_subproof_cascade(&sub).affected, vec![s6]);
+        assert!(prf.lookup_step(&s6).unwrap().3.is_empty());
+        assert!(prf.lookup_subproof(&sub).is_none());
+        assert!(matches!(prf.verify_line(&Coproduct::inject(s6)), Err(ProofCheckError::IncorrectSubDepCount(..))));
+    }
+
+    #[test]
+    fn test_remove_subproof_cascade() {
+        use crate::parser::parse_unwrap as p;
+        let mut prf = PooledProof::<HList![Expr]>::new();
+        prf.add_premise(p("A"));
+        let outer = prf.add_subproof();
+        let (op, inner, ip, is1, os1) = prf
+            .with_mut_subproof(&outer, |sub| {
+                let op = sub.add_premise(p("B"));
+                let inner = sub.add_subproof();
+                let (ip, is1) = sub
+                    .with_mut_subproof(&inner, |sub| {
+                        let ip = sub.add_premise(p("C"));
+                        (ip, sub.add_step(Justification(p("C"), RuleM::Reit, vec![Coproduct::inject(ip)], vec![])))
+                    })
+                    .unwrap();
+                let os1 = sub.add_step(Justification(p("C -> C"), RuleM::ImpIntro, vec![], vec![inner]));
+                (op, inner, ip, is1, os1)
+            })
+            .unwrap();
+        // A stale citation of a line inside the inner subproof is also cleaned up
+        let s2 = prf.add_step(Justification(p("C"), RuleM::Reit, vec![Coproduct::inject(is1)], vec![]));
+
+        let removed = prf.remove_subproof_cascade(&inner);
+        assert_eq!(removed.lines, vec![Coproduct::inject(ip), Coproduct::inject(is1)]);
+        assert_eq!(removed.subproofs, vec![inner]);
+        assert_eq!(removed.affected, vec![os1, s2]);
+        assert!(prf.lookup_step(&os1).unwrap().3.is_empty());
+        assert!(prf.lookup_step(&s2).unwrap().2.is_empty());
+        let outer_sub = prf.lookup_subproof(&outer).unwrap();
+        assert_eq!(outer_sub.premises(), vec![op]);
+        assert_eq!(outer_sub.lines(), vec![Coproduct::inject(os1)]);
+
+        let removed = prf.remove_subproof_cascade(&outer);
+        assert_eq!(removed.lines, vec![Coproduct::inject(op), Coproduct::inject(os1)]);
+        assert_eq!(removed.subproofs, vec![outer]);
+        assert!(removed.affected.is_empty());
+        assert_eq!(prf.lines(), vec![Coproduct::inject(s2)]);
+    }
+
+    #[test]
+    fn test_delete_line() {
+        use crate::parser::parse_unwrap as p;
+        use crate::proofs::DeleteError;
+        let mut prf = PooledProof::<HList![Expr]>::new();
+        let p1 = prf.add_premise(p("A"));
+        let p2 = prf.add_premise(p("B"));
+        let sub = prf.add_subproof();
+        let (sp1, sp2, ss1, ss2) = prf
+            .with_mut_subproof(&sub, |sub| {
+                let sp1 = sub.add_premise(p("C"));
+                let sp2 = sub.add_premise(p("D"));
+                let ss1 = sub.add_step(Justification(p("C"), RuleM::Reit, vec![Coproduct::inject(sp1)], vec![]));
+                let ss2 = sub.add_step(Justification(p("D"), RuleM::Reit, vec![Coproduct::inject(sp2)], vec![]));
+                (sp1, sp2, ss1, ss2)
+            })
+            .unwrap();
+        let s1 = prf.add_step(Justification(p("A"), RuleM::Reit, vec![Coproduct::inject(p1)], vec![]));
+        assert!(prf.incomplete_subproofs().is_empty());
+
+        // Happy path: every line has a sibling of the same kind left
+        assert_eq!(prf.delete_line(&Coproduct::inject(p1)), Ok(vec![s1]));
+        assert_eq!(prf.delete_line(&Coproduct::inject(sp2)), Ok(vec![ss2]));
+        assert_eq!(prf.delete_line(&Coproduct::inject(ss2)), Ok(vec![]));
+        // Top-level steps aren't required
+        assert_eq!(prf.delete_line(&Coproduct::inject(s1)), Ok(vec![]));
+
+        // The last premise of the proof, and the last premise and step of a subproof
+        assert_eq!(prf.delete_line(&Coproduct::inject(p2)), Err(DeleteError::LastLineInSubproof));
+        assert_eq!(prf.delete_line(&Coproduct::inject(sp1)), Err(DeleteError::LastLineInSubproof));
+        assert_eq!(prf.delete_line(&Coproduct::inject(ss1)), Err(DeleteError::LastLineInSubproof));
+        assert_eq!(prf.premises(), vec![p2]);
+        assert_eq!(prf.lookup_subproof(&sub).unwrap().premises(), vec![sp1]);
+
+        // Lines that were already deleted
+        assert_eq!(prf.delete_line(&Coproduct::inject(p1)), Err(DeleteError::StaleRef));
+        assert_eq!(prf.delete_line(&Coproduct::inject(s1)), Err(DeleteError::StaleRef));
+        assert!(matches!(prf.delete_enclosing_subproof(&Coproduct::inject(ss2)), Err(DeleteError::StaleRef)));
+
+        // Subproofs can be deleted through any of their lines, but the top-level proof can't
+        assert!(matches!(prf.delete_enclosing_subproof(&Coproduct::inject(p2)), Err(DeleteError::CannotDeleteRoot)));
+        assert_eq!(prf.delete_enclosing_subproof(&Coproduct::inject(ss1)).unwrap().subproofs, vec![sub]);
+        assert!(prf.lookup_subproof(&sub).is_none());
+
+        // Subproofs that didn't come from deleting lines aren't guaranteed to be complete
+        let empty = prf.add_subproof();
+        let premise_only = prf.add_subproof();
+        prf.with_mut_subproof(&premise_only, |sub| sub.add_premise(p("E")));
+        assert_eq!(prf.incomplete_subproofs(), vec![empty, premise_only]);
+    }
+
+    #[test]
+    fn test_iter_lines() {
+        use crate::parser::parse_unwrap as p;
+        use std::collections::HashMap;
+        type P = PooledProof<HList![Expr]>;
+
+        // The walk the UI used to number lines before `iter_lines` existed
+        fn reference_lineinfo(output: &mut HashMap<PjRef<P>, (usize, usize)>, prf: &<P as Proof>::Subproof, line: &mut usize, depth: &mut usize) {
+            for prem in prf.premises() {
+                output.insert(Coproduct::inject(prem), (*line, *depth));
+                *line += 1;
+            }
+            for lineref in prf.lines() {
+                use Coproduct::{Inl, Inr};
+                match lineref {
+                    Inl(r) => {
+                        output.insert(Coproduct::inject(r), (*line, *depth));
+                        *line += 1;
+                    }
+                    Inr(Inl(sr)) => {
+                        *depth += 1;
+                        reference_lineinfo(output, &prf.lookup_subproof(&sr).unwrap(), line, depth);
+                        *depth -= 1;
+                    }
+                    Inr(Inr(void)) => match void {},
+                }
+            }
+        }
+
+        let step = |e| Justification(p(e), RuleM::EmptyRule, vec![], vec![]);
+        let mut prf = P::new();
+        prf.add_premise(p("A"));
+        prf.add_premise(p("B"));
+        let outer = prf.add_subproof();
+        prf.with_mut_subproof(&outer, |sub| {
+            sub.add_premise(p("C"));
+            let inner = sub.add_subproof();
+            sub.with_mut_subproof(&inner, |sub| {
+                sub.add_premise(p("D"));
+                sub.add_step(step("D"));
+            });
+            // Adjacent to the nested subproof
+            let inner2 = sub.add_subproof();
+            sub.with_mut_subproof(&inner2, |sub| {
+                sub.add_premise(p("E"));
+            });
+            sub.add_step(step("C"));
+        });
+        // Adjacent to the outer subproof
+        let sibling = prf.add_subproof();
+        prf.with_mut_subproof(&sibling, |sub| {
+            sub.add_premise(p("F"));
+        });
+        prf.add_step(step("A"));
+
+        let mut expected = HashMap::new();
+        reference_lineinfo(&mut expected, prf.top_level_proof(), &mut 1, &mut 0);
+        let lines = prf.iter_lines().filter_map(|info| Some((info.reference.subset().ok()?, (info.line?, info.depth)))).collect::<HashMap<PjRef<P>, _>>();
+        assert_eq!(lines, expected);
+
+        let numbers = prf.iter_lines().map(|info| info.line).collect::<Vec<_>>();
+        assert_eq!(numbers, vec![Some(1), Some(2), None, Some(3), None, Some(4), Some(5), None, Some(6), Some(7), None, Some(8), Some(9)]);
+        let depths = prf.iter_lines().map(|info| info.depth).collect::<Vec<_>>();
+        assert_eq!(depths, vec![0, 0, 0, 1, 1, 2, 2, 1, 2, 1, 0, 1, 0]);
+        assert_eq!(prf.iter_lines().nth(2).unwrap().reference, Coproduct::inject(outer));
+    }
+
+    #[test]
+    fn test_dependents_of() {
+        use crate::parser::parse_unwrap as p;
+        let mut prf = PooledProof::<HList![Expr]>::new();
+        let p1 = prf.add_premise(p("A"));
+        let p2 = prf.add_premise(p("B"));
+        let s3 = prf.add_step(Justification(p("A & B"), RuleM::AndIntro, vec![Coproduct::inject(p1), Coproduct::inject(p2)], vec![]));
+        let s4 = prf.add_step(Justification(p("A"), RuleM::AndElim, vec![Coproduct::inject(s3)], vec![]));
+        let outer = prf.add_subproof();
+        let (inner, s6, s7) = prf
+            .with_mut_subproof(&outer, |sub| {
+                let sp = sub.add_premise(p("C"));
+                let inner = sub.add_subproof();
+                let s6 = sub.with_mut_subproof(&inner, |sub| {
+                    sub.add_premise(p("D"));
+                    // Cites a premise of the top-level proof from two subproofs deep
+                    sub.add_step(Justification(p("A"), RuleM::Reit, vec![Coproduct::inject(p1)], vec![]))
+                });
+                let s7 = sub.add_step(Justification(p("D -> A"), RuleM::ImpIntro, vec![Coproduct::inject(sp)], vec![inner]));
+                (inner, s6.unwrap(), s7)
+            })
+            .unwrap();
+        let s8 = prf.add_step(Justification(p("C -> (D -> A)"), RuleM::ImpIntro, vec![], vec![outer]));
+        let s9 = prf.add_step(Justification(p("A & B"), RuleM::AndIntro, vec![Coproduct::inject(s4), Coproduct::inject(p2)], vec![outer]));
+
+        assert_eq!(prf.dependents_of(&Coproduct::inject(p1)), vec![s3, s6]);
+        assert_eq!(prf.dependents_of(&Coproduct::inject(p2)), vec![s3, s9]);
+        assert_eq!(prf.dependents_of(&Coproduct::inject(s3)), vec![s4]);
+        assert_eq!(prf.dependents_of(&Coproduct::inject(s4)), vec![s9]);
+        assert!(prf.dependents_of(&Coproduct::inject(s8)).is_empty());
+        assert_eq!(prf.dependents_of_subproof(&outer), vec![s8, s9]);
+        assert_eq!(prf.dependents_of_subproof(&inner), vec![s7]);
+    }
+
+    #[test]
+    fn test_duplicate_subproof() {
+        use crate::parser::parse_unwrap as p;
+        let mut prf = PooledProof::<HList![Expr]>::new();
+        let p1 = prf.add_premise(p("A"));
+        let outer = prf.add_subproof();
+        prf.with_mut_subproof(&outer, |sub| {
+            let sp = sub.add_premise(p("B"));
+            let inner = sub.add_subproof();
+            sub.with_mut_subproof(&inner, |sub| {
+                sub.add_premise(p("C"));
+                // Depends on lines outside the inner subproof, one of them outside the copied subproof
+                sub.add_step(Justification(p("A & B"), RuleM::AndIntro, vec![Coproduct::inject(p1), Coproduct::inject(sp)], vec![]));
+            });
+            sub.add_step(Justification(p("C -> (A & B)"), RuleM::ImpIntro, vec![], vec![inner]));
+        });
+        let copy = prf.duplicate_subproof(&outer, true);
+        assert_eq!(prf.lines(), vec![Coproduct::inject(outer), Coproduct::inject(copy)]);
+
+        let original_sub = prf.lookup_subproof(&outer).unwrap();
+        let copy_sub = prf.lookup_subproof(&copy).unwrap();
+        assert_eq!(copy_sub.premises().iter().map(|r| prf.lookup_premise(r)).collect::<Vec<_>>(), vec![Some(p("B"))]);
+
+        let original_refs = original_sub.contained_justifications(true);
+        let copy_refs = copy_sub.contained_justifications(true);
+        assert_eq!(original_refs.len(), 4);
+        assert_eq!(copy_refs.len(), 4);
+        assert!(original_refs.is_disjoint(&copy_refs));
+        let copy_inner = *copy_sub.lines()[0].get::<SubKey, _>().unwrap();
+        assert_ne!(copy_inner, *original_sub.lines()[0].get::<SubKey, _>().unwrap());
+
+        for r in original_refs.iter().chain(copy_refs.iter()) {
+            assert_eq!(prf.verify_line(r), Ok(()), "{r:?}");
+        }
+
+        // Internal dependencies point at the copies, external ones are kept
+        let copy_premise = copy_sub.premises()[0];
+        let copy_inner_step = *prf.lookup_subproof(&copy_inner).unwrap().lines()[0].get::<JustKey, _>().unwrap();
+        assert_eq!(prf.lookup_step(&copy_inner_step).unwrap().2, vec![Coproduct::inject(p1), Coproduct::inject(copy_premise)]);
+        let copy_step = *copy_sub.lines()[1].get::<JustKey, _>().unwrap();
+        assert_eq!(prf.lookup_step(&copy_step).unwrap().3, vec![copy_inner]);
+    }
+
+    #[test]
+    fn test_extract_subproof() {
+        use crate::parser::parse_unwrap as p;
+        use crate::proofs::ExtractError;
+        type P = PooledProof<HList![Expr]>;
+        let mut prf = P::new();
+        let p1 = prf.add_premise(p("D"));
+        let lemma = prf.add_subproof();
+        prf.with_mut_subproof(&lemma, |sub| {
+            let sp = sub.add_premise(p("A & B"));
+            let s1 = sub.add_step(Justification(p("A"), RuleM::AndElim, vec![Coproduct::inject(sp)], vec![]));
+            sub.add_step(Justification(p("B"), RuleM::AndElim, vec![Coproduct::inject(sp)], vec![]));
+            let inner = sub.add_subproof();
+            sub.with_mut_subproof(&inner, |sub| {
+                sub.add_premise(p("C"));
+                sub.add_step(Justification(p("A"), RuleM::Reit, vec![Coproduct::inject(s1)], vec![]));
+            });
+            sub.add_step(Justification(p("C -> A"), RuleM::ImpIntro, vec![], vec![inner]));
+        });
+        let escaping = prf.add_subproof();
+        prf.with_mut_subproof(&escaping, |sub| {
+            sub.add_premise(p("E"));
+            sub.add_step(Justification(p("D"), RuleM::Reit, vec![Coproduct::inject(p1)], vec![]));
+        });
+
+        let extracted = prf.extract_subproof(&lemma).unwrap();
+        let report = extracted.verify_all();
+        assert!(report.is_fully_verified());
+        assert_eq!(report.lines.len(), 6);
+        assert_eq!(extracted.premises().iter().map(|r| extracted.lookup_premise(r)).collect::<Vec<_>>(), vec![Some(p("A & B"))]);
+        let original = prf.verify_all();
+        let original_lemma_results = original.iter().filter(|(n, _, _)| (2..=7).contains(n)).map(|(_, _, result)| result.is_ok()).collect::<Vec<_>>();
+        assert_eq!(report.iter().map(|(_, _, result)| result.is_ok()).collect::<Vec<_>>(), original_lemma_results);
+
+        let p1_ref: PjRef<P> = Coproduct::inject(p1);
+        assert_eq!(prf.extract_subproof(&escaping).err(), Some(ExtractError::EscapingDependency { lines: vec![Coproduct::inject(p1_ref)] }));
+    }
+
+    #[test]
+    fn test_drop_disallowed_deps() {
+        use crate::parser::parse_unwrap as p;
+        let mut prf = PooledProof::<HList![Expr]>::new();
+        let r1 = prf.add_premise(p("A"));
+        let sub = prf.add_subproof();
+        prf.with_mut_subproof(&sub, |sub| {
+            sub.add_premise(p("B"));
+        });
+        let r2 = prf.add_step(Justification(p("A"), RuleM::Reit, vec![Coproduct::inject(r1)], vec![sub]));
+
+        // Reiteration takes one line and no subproofs
+        prf.with_mut_step(&r2, |j| assert!(j.drop_disallowed_deps())).unwrap();
+        let Justification(_, _, deps, sdeps) = prf.lookup_step(&r2).unwrap();
+        assert_eq!(deps, vec![Coproduct::inject(r1)]);
+        assert!(sdeps.is_empty());
+
+        // Nothing left to drop
+        prf.with_mut_step(&r2, |j| assert!(!j.drop_disallowed_deps())).unwrap();
+
+        // Excluded Middle takes no dependencies at all
+        prf.with_mut_step(&r2, |j| {
+            j.1 = RuleM::ExcludedMiddle;
+            j.3.push(sub);
+            assert!(j.drop_disallowed_deps());
+        })
+        .unwrap();
+        let Justification(_, _, deps, sdeps) = prf.lookup_step(&r2).unwrap();
+        assert!(deps.is_empty());
+        assert!(sdeps.is_empty());
+
+        // The empty rule doesn't constrain its dependencies
+        prf.with_mut_step(&r2, |j| {
+            j.1 = RuleM::EmptyRule;
+            j.2.push(Coproduct::inject(r1));
+            assert!(!j.drop_disallowed_deps());
+        })
+        .unwrap();
+    }
+
+    #[test]
+    fn test_check_goals() {
+        use crate::parser::parse_unwrap as p;
+        use crate::proofs::{GoalStatus, ProofDocument};
+        let mut prf = PooledProof::<HList![Expr]>::new();
+        let r1 = prf.add_premise(p("A & B"));
+        let sub = prf.add_subproof();
+        prf.with_mut_subproof(&sub, |sub| {
+            sub.add_premise(p("C"));
+            sub.add_step(Justification(p("forall x P(x)"), RuleM::Reit, vec![], vec![]));
+        });
+        let r2 = prf.add_step(Justification(p("A"), RuleM::AndElim, vec![Coproduct::inject(r1)], vec![]));
+        let r3 = prf.add_step(Justification(p("A | C"), RuleM::AndElim, vec![Coproduct::inject(r1)], vec![]));
+        let doc = ProofDocument::new(prf, vec![p("A"), p("A | C"), p("forall y P(y)"), p("D")]);
+        assert_eq!(
+            doc.check_goals(),
+            vec![
+                GoalStatus::Reached(Coproduct::inject(r2)),
+                // the last line concludes the goal, but isn't a valid AndElim
+                GoalStatus::ReachedButUnverified(Coproduct::inject(r3)),
+                // only concluded inside a subproof
+                GoalStatus::NotReached,
+                GoalStatus::NotReached,
+            ]
+        );
+
+        // premises count as reached, and bound variable names don't matter
+        let mut doc = doc;
+        doc.proof.with_mut_step(&r3, |j| *j = Justification(p("forall x (A | P(x))"), RuleM::Reit, vec![], vec![])).unwrap();
+        doc.goals = vec![p("A & B"), p("forall y (A | P(y))")];
+        assert_eq!(doc.check_goals(), vec![GoalStatus::Reached(Coproduct::inject(r1)), GoalStatus::ReachedButUnverified(Coproduct::inject(r3))]);
+    }
+
+    #[test]
+    fn test_stable_ids() {
+        use crate::parser::parse_unwrap as p;
+        let mut prf = PooledProof::<HList![Expr]>::new();
+        let r1: PjRef<PooledProof<HList![Expr]>> = Coproduct::inject(prf.add_premise(p("A")));
+        let r2: PjRef<PooledProof<HList![Expr]>> = Coproduct::inject(prf.add_step(Justification(p("A"), RuleM::Reit, vec![r1], vec![])));
+        assert_eq!(prf.stable_id_of(&r1), Some(LineId(0)));
+        assert_eq!(prf.stable_id_of(&r2), Some(LineId(1)));
+
+        // Ids of removed lines aren't handed out again, even though their keys are
+        prf.remove_line(&r2);
+        assert_eq!(prf.stable_id_of(&r2), None);
+        assert_eq!(prf.ref_of_stable_id(LineId(1)), None);
+        let sub = prf.add_subproof();
+        let r3: PjRef<PooledProof<HList![Expr]>> = Coproduct::inject(prf.with_mut_subproof(&sub, |sub| sub.add_premise(p("B"))).unwrap());
+        assert_eq!(prf.stable_id_of(&r3), Some(LineId(2)));
+        assert_eq!(prf.ref_of_stable_id(LineId(2)), Some(r3));
+
+        // Ids can be set, as long as they're not taken, and new ids come after them
+        assert!(!prf.set_stable_id(&r3, LineId(0)));
+        assert!(prf.set_stable_id(&r3, LineId(10)));
+        assert!(!prf.set_stable_id(&r2, LineId(11)));
+        assert_eq!(prf.ref_of_stable_id(LineId(10)), Some(r3));
+        let r4: PjRef<PooledProof<HList![Expr]>> = Coproduct::inject(prf.add_premise(p("C")));
+        assert_eq!(prf.stable_id_of(&r4), Some(LineId(11)));
+    }
+
+    #[test]
+    fn test_stats() {
+        use crate::parser::parse_unwrap as p;
+        /*
+        1 | A
+        2 | B
+        3 | C
+          | ----------
+        4 | | D
+          | | ----------
+        5 | | | E
+          | | | ----------
+        6 | | | A ; Reit [1]
+        7 | | E -> A ; ImpIntro [5..6]
+        8 | D -> (E -> A) ; ImpIntro [4..7]
+        9 | A & C ; AndIntro [1, 3]
+        10| A ; Reit [1]
+        11| B & B ; AndElim [2], which doesn't check
+        */
+        let mut prf = PooledProof::<HList![Expr]>::new();
+        let r1 = prf.add_premise(p("A"));
+        let r2 = prf.add_premise(p("B"));
+        let r3 = prf.add_premise(p("C"));
+        let sub1 = prf.add_subproof();
+        prf.with_mut_subproof(&sub1, |sub1| {
+            sub1.add_premise(p("D"));
+            let sub2 = sub1.add_subproof();
+            sub1.with_mut_subproof(&sub2, |sub2| {
+                sub2.add_premise(p("E"));
+                sub2.add_step(Justification(p("A"), RuleM::Reit, vec![Coproduct::inject(r1)], vec![]));
+            });
+            sub1.add_step(Justification(p("E -> A"), RuleM::ImpIntro, vec![], vec![sub2]));
+        });
+        prf.add_step(Justification(p("D -> (E -> A)"), RuleM::ImpIntro, vec![], vec![sub1]));
+        prf.add_step(Justification(p("A & C"), RuleM::AndIntro, vec![Coproduct::inject(r1), Coproduct::inject(r3)], vec![]));
+        prf.add_step(Justification(p("A"), RuleM::Reit, vec![Coproduct::inject(r1)], vec![]));
+        prf.add_step(Justification(p("B & B"), RuleM::AndElim, vec![Coproduct::inject(r2)], vec![]));
+
+        let stats = prf.stats();
+        assert_eq!(stats.lines, 11);
+        assert_eq!(stats.premises, 3);
+        assert_eq!(stats.subproofs, 2);
+        assert_eq!(stats.max_depth, 2);
+        assert_eq!(stats.rules, vec![(RuleM::Reit, 2), (RuleM::ImpIntro, 2), (RuleM::AndIntro, 1), (RuleM::AndElim, 1)]);
+        assert_eq!(stats.uses_of(RuleM::ImpIntro), 2);
+        assert_eq!(stats.uses_of(RuleM::OrIntro), 0);
+        assert_eq!(stats.unused_premises, vec![r2]);
+    }
+
+    #[test]
+    fn test_stats_unused_premises() {
+        use crate::parser::parse_unwrap as p;
+        /*
+        1 | A
+        2 | B
+        3 | C
+          | ----------
+        4 | A & A ; AndElim [1], which doesn't check
+        5 | A & A ; Reit [4]
+        6 | C ; Reit [2], which doesn't check
+        */
+        let mut prf = PooledProof::<HList![Expr]>::new();
+        let r1 = prf.add_premise(p("A"));
+        let r2 = prf.add_premise(p("B"));
+        let r3 = prf.add_premise(p("C"));
+        let r4 = prf.add_step(Justification(p("A & A"), RuleM::AndElim, vec![Coproduct::inject(r1)], vec![]));
+        prf.add_step(Justification(p("A & A"), RuleM::Reit, vec![Coproduct::inject(r4)], vec![]));
+        prf.add_step(Justification(p("C"), RuleM::Reit, vec![Coproduct::inject(r2)], vec![]));
+
+        // A is only reached through a step that doesn't check, by one that
+        // does, and B is only cited by a step that doesn't check
+        assert_eq!(prf.stats().unused_premises, vec![r2, r3]);
+    }
+
+    #[test]
+    fn test_transitive_deps() {
+        use crate::parser::parse_unwrap as p;
+        use std::collections::BTreeSet;
+        type P = PooledProof<HList![Expr]>;
+        let pj = |r: JustKey| -> PjRef<P> { Coproduct::inject(r) };
+
+        // Diamond: 4 depends on 2 and 3, which both depend on 1
+        let mut prf = P::new();
+        let r1: PjRef<P> = Coproduct::inject(prf.add_premise(p("A")));
+        let r2 = prf.add_step(Justification(p("A"), RuleM::Reit, vec![r1], vec![]));
+        let r3 = prf.add_step(Justification(p("A"), RuleM::Reit, vec![r1], vec![]));
+        let r4 = prf.add_step(Justification(p("A & A"), RuleM::AndIntro, vec![pj(r2), pj(r3)], vec![]));
+        let unrelated = prf.add_step(Justification(p("A"), RuleM::Reit, vec![r1], vec![]));
+        assert_eq!(prf.transitive_deps(&r4), (vec![r1, pj(r2), pj(r3)].into_iter().collect(), BTreeSet::new()));
+        assert_eq!(prf.transitive_deps(&r2), (vec![r1].into_iter().collect(), BTreeSet::new()));
+        assert!(!prf.transitive_deps(&unrelated).0.contains(&pj(r4)));
+
+        // A subproof's lines depend on an outer premise
+        let mut prf = P::new();
+        let r1: PjRef<P> = Coproduct::inject(prf.add_premise(p("P")));
+        let sub = prf.add_subproof();
+        let (r2, r3) = prf
+            .with_mut_subproof(&sub, |sub| {
+                let r2 = sub.add_premise(p("Q"));
+                let r3 = sub.add_step(Justification(p("P"), RuleM::Reit, vec![r1], vec![]));
+                (r2, r3)
+            })
+            .unwrap();
+        let r4 = prf.add_step(Justification(p("Q -> P"), RuleM::ImpIntro, vec![], vec![sub]));
+        assert_eq!(prf.transitive_deps(&r4), (vec![r1, Coproduct::inject(r2), pj(r3)].into_iter().collect(), vec![sub].into_iter().collect()));
+
+        // Dependency cycles in a malformed proof are reported rather than followed forever
+        prf.with_mut_step(&r3, |j| j.2.push(pj(r4))).unwrap();
+        let (lines, subproofs) = prf.transitive_deps(&r4);
+        assert!(lines.contains(&pj(r4)));
+        assert_eq!(subproofs, vec![sub].into_iter().collect());
+        assert!(prf.transitive_dependencies(pj(r4)).contains(&r1));
+    }
+
+    #[test]
+    fn test_minimized() {
+        use crate::parser::parse_unwrap as p;
+        type P = PooledProof<HList![Expr]>;
+        /*
+        1 | A
+        2 | B
+          | ----------
+        3 | | C
+          | | ----------
+        4 | | A ; Reit [1]
+        5 | C -> A ; ImpIntro [3..4]
+        6 | B ; Reit [2]
+        7 | A | B ; OrIntro [1]
+        8 | A ; Reit [1]
+        9 | (C -> A) & A ; AndIntro [5, 1]
+        */
+        let mut prf = P::new();
+        let r1: PjRef<P> = Coproduct::inject(prf.add_premise(p("A")));
+        let r2: PjRef<P> = Coproduct::inject(prf.add_premise(p("B")));
+        let sub = prf.add_subproof();
+        prf.with_mut_subproof(&sub, |sub| {
+            sub.add_premise(p("C"));
+            sub.add_step(Justification(p("A"), RuleM::Reit, vec![r1], vec![]));
+        });
+        let r5: PjRef<P> = Coproduct::inject(prf.add_step(Justification(p("C -> A"), RuleM::ImpIntro, vec![], vec![sub])));
+        prf.add_step(Justification(p("B"), RuleM::Reit, vec![r2], vec![]));
+        let r7: PjRef<P> = Coproduct::inject(prf.add_step(Justification(p("A | B"), RuleM::OrIntro, vec![r1], vec![])));
+        prf.add_step(Justification(p("A"), RuleM::Reit, vec![r1], vec![]));
+        prf.add_step(Justification(p("(C -> A) & A"), RuleM::AndIntro, vec![r5, r1], vec![]));
+        assert!(prf.verify_all().is_fully_verified());
+
+        let exprs = |prf: &P| prf.iter_lines().filter_map(|info| info.reference.subset().ok()).map(|r: PjRef<P>| prf.lookup_expr(&r).unwrap()).collect::<Vec<_>>();
+
+        // The three irrelevant steps are removed, and everything left still checks
+        let minimized = prf.minimized(&[], true);
+        assert_eq!(exprs(&minimized), vec![p("A"), p("B"), p("C"), p("A"), p("C -> A"), p("(C -> A) & A")]);
+        assert!(minimized.verify_all().is_fully_verified());
+        assert_eq!(minimized.stats().subproofs, 1);
+
+        // Without keeping premises, the unused one goes too
+        let minimized = prf.minimized(&[], false);
+        assert_eq!(exprs(&minimized), vec![p("A"), p("C"), p("A"), p("C -> A"), p("(C -> A) & A")]);
+        assert!(minimized.verify_all().is_fully_verified());
+
+        // Keeping a line inside the subproof keeps the subproof and its premise
+        let inner = prf.lookup_subproof(&sub).unwrap().direct_lines()[0];
+        let minimized = prf.minimized(&[Coproduct::inject(inner), r7], false);
+        assert_eq!(exprs(&minimized), vec![p("A"), p("C"), p("A"), p("A | B")]);
+        assert!(minimized.verify_all().is_fully_verified());
+    }
+
+    #[test]
+    fn test_replace_expr() {
+        use crate::parser::parse_unwrap as p;
+        use crate::proofs::ReplaceScope;
+        type P = PooledProof<HList![Expr]>;
+        /*
+        1 | P(c)
+        2 | forall x Q(x, c)
+          | ----------
+        3 | | R(c)
+          | | ----------
+        4 | | P(c) & R(c) ; AndIntro [1, 3]
+        5 | R(c) -> (P(c) & R(c)) ; ImpIntro [3..4]
+        */
+        let mut prf = P::new();
+        let r1 = prf.add_premise(p("P(c)"));
+        let r2 = prf.add_premise(p("forall x Q(x, c)"));
+        let sub = prf.add_subproof();
+        let (r3, r4) = prf
+            .with_mut_subproof(&sub, |sub| {
+                let r3 = sub.add_premise(p("R(c)"));
+                let r4 = sub.add_step(Justification(p("P(c) & R(c)"), RuleM::AndIntro, vec![Coproduct::inject(r1), Coproduct::inject(r3)], vec![]));
+                (r3, r4)
+            })
+            .unwrap();
+        let r5 = prf.add_step(Justification(p("R(c) -> (P(c) & R(c))"), RuleM::ImpIntro, vec![], vec![sub]));
+
+        // Only the subproof is rewritten, including its step's expression
+        assert_eq!(prf.replace_expr(&p("c"), &p("d"), ReplaceScope::Subproof(sub)), 3);
+        assert_eq!(prf.lookup_premise(&r3), Some(p("R(d)")));
+        assert_eq!(prf.lookup_step(&r4).unwrap().0, p("P(c) & R(d)"));
+        assert_eq!(prf.lookup_premise(&r1), Some(p("P(c)")));
+
+        // The rest of the proof, including under a binder
+        assert_eq!(prf.replace_expr(&p("c"), &p("d"), ReplaceScope::WholeProof), 6);
+        assert_eq!(prf.lookup_premise(&r2), Some(p("forall x Q(x, d)")));
+        assert_eq!(prf.lookup_step(&r5).unwrap().0, p("R(d) -> (P(d) & R(d))"));
+        assert!(prf.verify_all().is_fully_verified());
+
+        // Nothing left to replace
+        assert_eq!(prf.replace_expr(&p("c"), &p("d"), ReplaceScope::WholeProof), 0);
+
+        // A replacement mentioning a quantified variable doesn't get captured
+        assert_eq!(prf.replace_expr(&p("d"), &p("x"), ReplaceScope::WholeProof), 8);
+        assert_eq!(prf.lookup_premise(&r2), Some(p("forall x0 Q(x0, x)")));
+        assert!(prf.verify_all().is_fully_verified());
+    }
+
+    #[test]
+    fn test_try_with_mut_stale_refs() {
+        use crate::parser::parse_unwrap as p;
+        use crate::proofs::InvalidRef;
+        use crate::proofs::InvalidRefReason;
+        use crate::proofs::LineNames;
+        type P = PooledProof<HList![Expr]>;
+        let mut prf = P::new();
+        let r1 = prf.add_premise(p("A"));
+        let r2 = prf.add_premise(p("B"));
+        let s1 = prf.add_subproof();
+        let s2 = prf.add_subproof();
+        let r3 = prf.add_step(Justification(p("A"), RuleM::Reit, vec![Coproduct::inject(r1)], vec![]));
+        let r4 = prf.add_step(Justification(p("B"), RuleM::Reit, vec![Coproduct::inject(r2)], vec![]));
+
+        assert_eq!(prf.try_with_mut_premise(&r1, |e| e.clone()), Ok(p("A")));
+        assert_eq!(prf.try_with_mut_step(&r3, |j| j.0.clone()), Ok(p("A")));
+        assert_eq!(prf.try_with_mut_subproof(&s1, |sub| sub.premises().len()), Ok(0));
+
+        prf.remove_line(&Coproduct::inject(r1));
+        prf.remove_line(&Coproduct::inject(r3));
+        prf.remove_subproof(&s1);
+        assert_eq!(prf.try_with_mut_premise(&r1, |e| *e = p("C")), Err(InvalidRef { reference: Coproduct::inject(r1), reason: InvalidRefReason::Removed }));
+        assert_eq!(prf.try_with_mut_step(&r3, |j| j.0 = p("C")), Err(InvalidRef { reference: Coproduct::inject(r3), reason: InvalidRefReason::Removed }));
+        assert_eq!(prf.try_with_mut_subproof(&s1, |_| ()), Err(InvalidRef { reference: Coproduct::inject(s1), reason: InvalidRefReason::Removed }));
+        let names = LineNames::new(&prf);
+        assert_eq!(names.line_or_subproof(&Coproduct::inject(r4)), "line 2");
+        assert_eq!(prf.try_with_mut_step(&r3, |_| ()).unwrap_err().describe(|r| names.line_or_subproof(r)), "Can't change a missing line, which has been removed.");
+
+        // The last key of each pool is past the end once it's removed
+        prf.remove_line(&Coproduct::inject(r2));
+        prf.remove_line(&Coproduct::inject(r4));
+        prf.remove_subproof(&s2);
+        assert_eq!(prf.try_with_mut_premise(&r2, |_| ()).unwrap_err().reason, InvalidRefReason::OutOfRange);
+        assert_eq!(prf.try_with_mut_step(&r4, |_| ()).unwrap_err().reason, InvalidRefReason::OutOfRange);
+        assert_eq!(prf.try_with_mut_subproof(&s2, |_| ()).unwrap_err().reason, InvalidRefReason::OutOfRange);
+        assert_eq!(prf.premises(), vec![]);
+        assert_eq!(prf.lines(), vec![]);
+
+        // A key from another proof is refused, even if this proof has a line with the same key
+        let mut other = P::new();
+        let foreign = other.add_premise(p("D"));
+        let r5 = prf.add_premise(p("E"));
+        assert_eq!(foreign, r5);
+        assert_eq!(prf.try_with_mut_premise(&foreign, |e| *e = p("D")), Err(InvalidRef { reference: Coproduct::inject(foreign), reason: InvalidRefReason::OtherProof }));
+        assert_eq!(prf.lookup_premise(&r5), Some(p("E")));
+        assert_eq!(prf.try_with_mut_premise(&r5, |e| e.clone()), Ok(p("E")));
+    }
+
+    #[test]
+    fn test_compact() {
+        use crate::parser::parse_unwrap as p;
+        use crate::proofs::pj_to_pjs;
+        type P = PooledProof<HList![Expr]>;
+        let mut prf = P::new();
+        let r1 = prf.add_premise(p("A"));
+        let r2 = prf.add_premise(p("B"));
+        let r3 = prf.add_premise(p("C"));
+        let sub = prf.add_subproof();
+        let inner = prf
+            .with_mut_subproof(&sub, |sub| {
+                let q = sub.add_premise(p("D"));
+                sub.add_step(Justification(p("D"), RuleM::Reit, vec![Coproduct::inject(q)], vec![]))
+            })
+            .unwrap();
+        let r5 = prf.add_step(Justification(p("D -> D"), RuleM::ImpIntro, vec![], vec![sub]));
+        let r6 = prf.add_step(Justification(p("A"), RuleM::Reit, vec![Coproduct::inject(r1)], vec![]));
+        let r7 = prf.add_step(Justification(p("C"), RuleM::Reit, vec![Coproduct::inject(r3)], vec![]));
+        let r8 = prf.add_step(Justification(p("(D -> D) & C"), RuleM::AndIntro, vec![Coproduct::inject(r5), Coproduct::inject(r7)], vec![]));
+        let before: Vec<PjRef<P>> = vec![Coproduct::inject(r1), Coproduct::inject(r3), Coproduct::inject(inner), Coproduct::inject(r5), Coproduct::inject(r7), Coproduct::inject(r8)];
+        let exprs: Vec<Expr> = before.iter().map(|r| prf.lookup_expr(r).unwrap()).collect();
+        let ids: Vec<Option<LineId>> = before.iter().map(|r| prf.stable_id_of(r)).collect();
+
+        prf.remove_line(&Coproduct::inject(r2));
+        prf.remove_line(&Coproduct::inject(r6));
+        assert_eq!(prf.memory_stats(), MemoryStats { live_premises: 3, tombstoned_premises: 1, live_steps: 4, tombstoned_steps: 1, live_subproofs: 1, tombstoned_subproofs: 0 });
+
+        let remap = prf.compact();
+        assert_eq!(prf.memory_stats().tombstones(), 0);
+        let after: Vec<PjRef<P>> = before.iter().map(|r| remap.line(r).unwrap()).collect();
+        assert_eq!(after.iter().map(|r| prf.lookup_expr(r).unwrap()).collect::<Vec<_>>(), exprs);
+        assert_eq!(after.iter().map(|r| prf.stable_id_of(r)).collect::<Vec<_>>(), ids);
+        assert_eq!(remap.line(&Coproduct::inject(r2)), None);
+        assert_eq!(prf.lookup_step(&remap.steps[&r5]).unwrap().3, vec![remap.subproof(&sub).unwrap()]);
+        assert_eq!(prf.parent_of_line(&pj_to_pjs::<P>(after[2])), remap.subproof(&sub));
+        assert!(prf.verify_all().is_fully_verified());
+
+        // New lines don't collide with the renumbered ones
+        let r9 = prf.add_premise(p("E"));
+        assert!(!after.contains(&Coproduct::inject(r9)));
+    }
+
+    #[test]
+    fn test_subproof_conclusion() {
+        use crate::parser::parse_unwrap as p;
+        use crate::proofs::builder::ProofBuilder;
+        use crate::proofs::pj_to_pjs;
+        type P = PooledProof<HList![Expr]>;
+        /*
+        1 | A
+          | ----------
+        2 | | B
+          | | ----------
+        3 | | A ; Reit [1]
+        4 | | | C
+          | | | ----------
+        5 | | | C ; Reit [4]
+        6 | B -> A ; ImpIntro [2..5]
+        7 | | D
+          | | ----------
+        */
+        let (prf, lines) = ProofBuilder::new()
+            .premise("A")
+            .subproof(|b| b.premise("B").step("A", RuleM::Reit, crate::deps![1]).subproof(|b| b.premise("C").step("C", RuleM::Reit, crate::deps![4])))
+            .step("B -> A", RuleM::ImpIntro, crate::deps![; 2..=5])
+            .subproof(|b| b.premise("D"))
+            .build_with_lines::<P>()
+            .unwrap();
+        let sub = |n: usize| prf.lookup_subproof(&prf.parent_of_line(&pj_to_pjs::<P>(lines[n - 1])).unwrap()).unwrap();
+
+        // A trailing nested subproof is skipped
+        assert_eq!(sub(2).conclusion(), Some(p("A")));
+        assert_eq!(sub(2).conclusion_ref().map(Coproduct::inject), Some(lines[2]));
+        assert_eq!(sub(4).conclusion(), Some(p("C")));
+        assert!(prf.verify_line(&lines[5]).is_ok());
+        // A subproof with only premises has no conclusion
+        assert_eq!(sub(7).conclusion(), None);
+        assert_eq!(sub(7).conclusion_ref(), None);
+        assert_eq!(prf.top_level_proof().conclusion(), Some(p("B -> A")));
+    }
+
     #[test]
     fn prettyprint_pool() {
         let prf: PooledProof<HList![Expr]> = crate::proofs::proof_tests::demo_proof_1();
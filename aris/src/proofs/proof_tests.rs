@@ -1,13 +1,16 @@
 #![deny(unused_variables, dead_code)]
 
+use crate::deps;
 use crate::expr::Expr;
+use crate::proofs::builder::ProofBuilder;
 use crate::proofs::pooledproof::PooledProof;
 use crate::proofs::Justification;
 use crate::proofs::PjRef;
 use crate::proofs::Proof;
+use crate::rules::CheckContext;
+use crate::rules::CheckProfile;
 use crate::rules::RuleM;
 
-use std::collections::HashSet;
 use std::fmt::Debug;
 use std::fmt::Display;
 
@@ -22,7 +25,7 @@ where
     Coproduct::inject(to_insert)
 }
 
-fn run_test<P: Proof + Display + Debug, F: FnOnce() -> (P, Vec<PjRef<P>>, Vec<PjRef<P>>)>(f: F)
+fn run_test<P: Proof + Display + Debug, F: FnOnce() -> (P, Vec<PjRef<P>>, Vec<PjRef<P>>)>(f: F, ctx: &CheckContext)
 where
     PjRef<P>: Debug,
     P::SubproofReference: Debug,
@@ -31,12 +34,12 @@ where
     println!("{prf}");
     println!("{prf:?}");
     for (i, ok) in oks.iter().enumerate() {
-        if let Err(e) = prf.verify_line(ok) {
+        if let Err(e) = prf.verify_line_with(ok, ctx) {
             panic!("run_test: unexpected error on line {i}: {e:?}");
         }
     }
     for (i, err) in errs.iter().enumerate() {
-        if let Err(e) = prf.verify_line(err) {
+        if let Err(e) = prf.verify_line_with(err, ctx) {
             println!("Error message for {i} was {e:?}");
         } else {
             panic!("{} ({:?}, {:?}) should have failed, but didn't", i, err, prf.lookup_pj(&err.clone()));
@@ -46,13 +49,16 @@ where
 
 macro_rules! generate_tests {
     ($proofrepr:ty, $modprefix:ident; $( $generic_test:ident ),+,) => {
+        generate_tests! { $proofrepr, $modprefix, CheckContext::default(); $( $generic_test ),+, }
+    };
+    ($proofrepr:ty, $modprefix:ident, $ctx:expr; $( $generic_test:ident ),+,) => {
         #[cfg(test)]
         mod $modprefix {
             use super::*;
             $(
                 #[test]
                 fn $generic_test() {
-                    super::run_test::<$proofrepr, _>(super::$generic_test);
+                    super::run_test::<$proofrepr, _>(super::$generic_test, &$ctx);
                 }
             )+
         }
@@ -62,7 +68,7 @@ macro_rules! generate_tests {
 macro_rules! enumerate_subproofless_tests {
     ($x:ty, $y:ident) => {
         generate_tests! { $x, $y;
-            test_andelim, test_contelim, test_orintro, test_reit, test_andintro,
+            test_andelim, test_contelim, test_tautintro, test_orintro, test_reit, test_andintro,
             test_contradictionintro, test_notelim, test_impelim, test_commutation,
             test_association, test_demorgan, test_idempotence, test_doublenegation,
             test_distribution, test_complement, test_identity, test_annihilation,
@@ -81,6 +87,7 @@ macro_rules! enumerate_subproofful_tests {
             test_forallelim,
             test_biconelim,
             test_biconintro,
+            test_bicon_chain_mixing,
             test_impintro,
             test_notintro,
             test_orelim,
@@ -92,8 +99,18 @@ macro_rules! enumerate_subproofful_tests {
     };
 }
 
+macro_rules! enumerate_strict_tests {
+    ($x:ty, $y:ident) => {
+        generate_tests! { $x, $y, CheckContext { profile: CheckProfile::Strict };
+            test_andelim_strict,
+            test_bicon_chain_mixing_strict,
+        }
+    };
+}
+
 enumerate_subproofless_tests! { PooledProof<HList![Expr]>, test_subproofless_rules_on_pooledproof }
 enumerate_subproofful_tests! { PooledProof<HList![Expr]>, test_subproofful_rules_on_pooledproof }
+enumerate_strict_tests! { PooledProof<HList![Expr]>, test_strict_rules_on_pooledproof }
 
 pub fn demo_proof_1<P: Proof>() -> P
 where
@@ -127,1028 +144,987 @@ where
 }
 
 pub fn test_andelim<P: Proof>() -> (P, Vec<PjRef<P>>, Vec<PjRef<P>>) {
-    use self::coproduct_inject as i;
-    use crate::parser::parse_unwrap as p;
-    let mut prf = P::new();
-    let r1 = prf.add_premise(p("A & B & C & D"));
-    let r2 = prf.add_premise(p("E | F"));
-    let r3 = prf.add_step(Justification(p("A"), RuleM::AndElim, vec![i(r1.clone())], vec![]));
-    let r4 = prf.add_step(Justification(p("E"), RuleM::AndElim, vec![i(r1.clone())], vec![]));
-    let r5 = prf.add_step(Justification(p("A"), RuleM::AndElim, vec![i(r1.clone()), i(r1)], vec![]));
-    let r6 = prf.add_step(Justification(p("A"), RuleM::AndElim, vec![i(r2)], vec![]));
-    (prf, vec![i(r3)], vec![i(r4), i(r5), i(r6)])
+    let (prf, lines) = ProofBuilder::new()
+        .premise("A & B & C & D")
+        .premise("E | F")
+        .step("A", RuleM::AndElim, deps![1])
+        .step("E", RuleM::AndElim, deps![1])
+        .step("A", RuleM::AndElim, deps![1, 2])
+        .step("A", RuleM::AndElim, deps![2])
+        .step("C", RuleM::AndElim, deps![1])
+        .step("A & C", RuleM::AndElim, deps![1])
+        .step("D & B & A", RuleM::AndElim, deps![1])
+        .step("A & E", RuleM::AndElim, deps![1])
+        .step("A & A", RuleM::AndElim, deps![1])
+        .build_with_lines()
+        .unwrap();
+    let l = |n: usize| lines[n - 1].clone();
+    (prf, vec![l(3), l(7), l(8), l(9)], vec![l(4), l(5), l(6), l(10), l(11)])
+}
+
+pub fn test_andelim_strict<P: Proof>() -> (P, Vec<PjRef<P>>, Vec<PjRef<P>>) {
+    let (prf, lines) = ProofBuilder::new()
+        .premise("A & B & C & D")
+        .step("B", RuleM::AndElim, deps![1])
+        .step("B & C", RuleM::AndElim, deps![1])
+        .step("C & B", RuleM::AndElim, deps![1])
+        .step("A & C", RuleM::AndElim, deps![1])
+        .step("B & E", RuleM::AndElim, deps![1])
+        .build_with_lines()
+        .unwrap();
+    let l = |n: usize| lines[n - 1].clone();
+    (prf, vec![l(2), l(3)], vec![l(4), l(5), l(6)])
 }
 
 pub fn test_contelim<P: Proof>() -> (P, Vec<PjRef<P>>, Vec<PjRef<P>>) {
-    use self::coproduct_inject as i;
-    use crate::parser::parse_unwrap as p;
-    let mut prf = P::new();
-    let r1 = prf.add_premise(p("_|_"));
-    let r2 = prf.add_premise(p("A & B"));
-    let r3 = prf.add_step(Justification(p("forall x x & ~ x"), RuleM::ContradictionElim, vec![i(r1)], vec![]));
-    let r4 = prf.add_step(Justification(p("Q"), RuleM::ContradictionElim, vec![i(r2)], vec![]));
-    (prf, vec![i(r3)], vec![i(r4)])
+    let (prf, lines) = ProofBuilder::new()
+        .premise("_|_")
+        .premise("A & B")
+        .premise("P & ~P")
+        .step("forall x x & ~ x", RuleM::ContradictionElim, deps![1])
+        .step("Q", RuleM::ContradictionElim, deps![2])
+        .step("Q", RuleM::ContradictionElim, deps![3])
+        .step("Q", RuleM::ContradictionElim, deps![1, 3])
+        .build_with_lines()
+        .unwrap();
+    let l = |n: usize| lines[n - 1].clone();
+    (prf, vec![l(4)], vec![l(5), l(6), l(7)])
+}
+
+pub fn test_tautintro<P: Proof>() -> (P, Vec<PjRef<P>>, Vec<PjRef<P>>) {
+    let (prf, lines) = ProofBuilder::new()
+        .premise("A")
+        .step("^|^", RuleM::TautIntro, deps![])
+        .step("A | ~A", RuleM::TautIntro, deps![])
+        .step("^|^", RuleM::TautIntro, deps![1])
+        .step("_|_", RuleM::ContradictionElim, deps![2])
+        .build_with_lines()
+        .unwrap();
+    let l = |n: usize| lines[n - 1].clone();
+    (prf, vec![l(2)], vec![l(3), l(4), l(5)])
 }
 
 pub fn test_orintro<P: Proof>() -> (P, Vec<PjRef<P>>, Vec<PjRef<P>>) {
-    use self::coproduct_inject as i;
-    use crate::parser::parse_unwrap as p;
-    let mut prf = P::new();
-    let r1 = prf.add_premise(p("A"));
-    let r2 = prf.add_step(Justification(p("A | B | C"), RuleM::OrIntro, vec![i(r1.clone())], vec![]));
-    let r3 = prf.add_step(Justification(p("P | Q"), RuleM::OrIntro, vec![i(r1.clone())], vec![]));
-    let r4 = prf.add_step(Justification(p("P & Q"), RuleM::OrIntro, vec![i(r1)], vec![]));
-    (prf, vec![i(r2)], vec![i(r3), i(r4)])
+    let (prf, lines) = ProofBuilder::new()
+        .premise("A")
+        .step("A | B | C", RuleM::OrIntro, deps![1])
+        .step("P | Q", RuleM::OrIntro, deps![1])
+        .step("P & Q", RuleM::OrIntro, deps![1])
+        .build_with_lines()
+        .unwrap();
+    let l = |n: usize| lines[n - 1].clone();
+    (prf, vec![l(2)], vec![l(3), l(4)])
 }
 
 pub fn test_reit<P: Proof>() -> (P, Vec<PjRef<P>>, Vec<PjRef<P>>) {
-    use self::coproduct_inject as i;
-    use crate::parser::parse_unwrap as p;
-    let mut prf = P::new();
-    let r1 = prf.add_premise(p("A"));
-    let r2 = prf.add_step(Justification(p("A"), RuleM::Reit, vec![i(r1.clone())], vec![]));
-    let r3 = prf.add_step(Justification(p("B"), RuleM::Reit, vec![i(r1)], vec![]));
-    (prf, vec![i(r2)], vec![i(r3)])
+    let (prf, lines) = ProofBuilder::new()
+        .premise("A")
+        .step("A", RuleM::Reit, deps![1])
+        .step("B", RuleM::Reit, deps![1])
+        .build_with_lines()
+        .unwrap();
+    let l = |n: usize| lines[n - 1].clone();
+    (prf, vec![l(2)], vec![l(3)])
 }
 
 pub fn test_andintro<P: Proof>() -> (P, Vec<PjRef<P>>, Vec<PjRef<P>>) {
-    use self::coproduct_inject as i;
-    use crate::parser::parse_unwrap as p;
-    let mut prf = P::new();
-    let r1 = prf.add_premise(p("A"));
-    let r2 = prf.add_premise(p("B"));
-    let r3 = prf.add_premise(p("C"));
-    let r4 = prf.add_step(Justification(p("A & B"), RuleM::AndIntro, vec![i(r1.clone()), i(r2.clone())], vec![]));
-    let r5 = prf.add_step(Justification(p("A & B"), RuleM::AndIntro, vec![i(r1.clone()), i(r2), i(r3)], vec![]));
-    let r6 = prf.add_step(Justification(p("A & B"), RuleM::AndIntro, vec![i(r1.clone())], vec![]));
-    let r7 = prf.add_step(Justification(p("A & A"), RuleM::AndIntro, vec![i(r1)], vec![]));
-    (prf, vec![i(r4), i(r7)], vec![i(r5), i(r6)])
+    let (prf, lines) = ProofBuilder::new()
+        .premise("A")
+        .premise("B")
+        .premise("C")
+        .step("A & B", RuleM::AndIntro, deps![1, 2])
+        .step("A & B", RuleM::AndIntro, deps![1, 2, 3])
+        .step("A & B", RuleM::AndIntro, deps![1])
+        .step("A & A", RuleM::AndIntro, deps![1])
+        .build_with_lines()
+        .unwrap();
+    let l = |n: usize| lines[n - 1].clone();
+    (prf, vec![l(4), l(7)], vec![l(5), l(6)])
 }
 
 pub fn test_contradictionintro<P: Proof>() -> (P, Vec<PjRef<P>>, Vec<PjRef<P>>) {
-    use self::coproduct_inject as i;
-    use crate::parser::parse_unwrap as p;
-    let mut prf = P::new();
-    let r1 = prf.add_premise(p("A"));
-    let r2 = prf.add_premise(p("~A"));
-    let r3 = prf.add_premise(p("~~A"));
-    let r4 = prf.add_premise(p("B"));
-    let r5 = prf.add_step(Justification(p("_|_"), RuleM::ContradictionIntro, vec![i(r1.clone()), i(r2.clone())], vec![]));
-    let r6 = prf.add_step(Justification(p("_|_"), RuleM::ContradictionIntro, vec![i(r2.clone()), i(r3.clone())], vec![]));
-    let r7 = prf.add_step(Justification(p("_|_"), RuleM::ContradictionIntro, vec![i(r1.clone()), i(r3)], vec![]));
-    let r8 = prf.add_step(Justification(p("_|_"), RuleM::ContradictionIntro, vec![i(r1.clone()), i(r4)], vec![]));
-    let r9 = prf.add_step(Justification(p("Q(E,D)"), RuleM::ContradictionIntro, vec![i(r1.clone()), i(r2.clone())], vec![]));
-    let r10 = prf.add_step(Justification(p("_|_"), RuleM::ContradictionIntro, vec![i(r2), i(r1)], vec![]));
-    (prf, vec![i(r5), i(r6), i(r10)], vec![i(r7), i(r8), i(r9)])
+    let (prf, lines) = ProofBuilder::new()
+        .premise("A")
+        .premise("~A")
+        .premise("~~A")
+        .premise("B")
+        .step("_|_", RuleM::ContradictionIntro, deps![1, 2])
+        .step("_|_", RuleM::ContradictionIntro, deps![2, 3])
+        .step("_|_", RuleM::ContradictionIntro, deps![1, 3])
+        .step("_|_", RuleM::ContradictionIntro, deps![1, 4])
+        .step("Q(E,D)", RuleM::ContradictionIntro, deps![1, 2])
+        .step("_|_", RuleM::ContradictionIntro, deps![2, 1])
+        .build_with_lines()
+        .unwrap();
+    let l = |n: usize| lines[n - 1].clone();
+    (prf, vec![l(5), l(6), l(10)], vec![l(7), l(8), l(9)])
 }
 
 pub fn test_notelim<P: Proof>() -> (P, Vec<PjRef<P>>, Vec<PjRef<P>>) {
-    use self::coproduct_inject as i;
-    use crate::parser::parse_unwrap as p;
-    let mut prf = P::new();
-    let r1 = prf.add_premise(p("~~A"));
-    let r2 = prf.add_premise(p("~~(A & B)"));
-    let r3 = prf.add_premise(p("~A"));
-    let r4 = prf.add_premise(p("A"));
-    let r5 = prf.add_step(Justification(p("A"), RuleM::NotElim, vec![i(r1)], vec![])); // 5
-    let r6 = prf.add_step(Justification(p("A & B"), RuleM::NotElim, vec![i(r2.clone())], vec![]));
-    let r7 = prf.add_step(Justification(p("A"), RuleM::NotElim, vec![i(r3)], vec![]));
-    let r8 = prf.add_step(Justification(p("A"), RuleM::NotElim, vec![i(r4)], vec![]));
-    let r9 = prf.add_step(Justification(p("B"), RuleM::NotElim, vec![i(r2)], vec![]));
-    (prf, vec![i(r5), i(r6)], vec![i(r7), i(r8), i(r9)])
+    let (prf, lines) = ProofBuilder::new()
+        .premise("~~A")
+        .premise("~~(A & B)")
+        .premise("~A")
+        .premise("A")
+        .step("A", RuleM::NotElim, deps![1])
+        .step("A & B", RuleM::NotElim, deps![2])
+        .step("A", RuleM::NotElim, deps![3])
+        .step("A", RuleM::NotElim, deps![4])
+        .step("B", RuleM::NotElim, deps![2])
+        .build_with_lines()
+        .unwrap();
+    let l = |n: usize| lines[n - 1].clone();
+    (prf, vec![l(5), l(6)], vec![l(7), l(8), l(9)])
 }
 
 pub fn test_impelim<P: Proof>() -> (P, Vec<PjRef<P>>, Vec<PjRef<P>>) {
-    use self::coproduct_inject as i;
-    use crate::parser::parse_unwrap as p;
-    let mut prf = P::new();
-    let r1 = prf.add_premise(p("P"));
-    let r2 = prf.add_premise(p("P -> Q"));
-    let r3 = prf.add_premise(p("Q"));
-    let r4 = prf.add_premise(p("A"));
-    let r5 = prf.add_premise(p("A -> A"));
-    let r6 = prf.add_step(Justification(p("Q"), RuleM::ImpElim, vec![i(r1.clone()), i(r2.clone())], vec![]));
-    let r7 = prf.add_step(Justification(p("Q"), RuleM::ImpElim, vec![i(r2.clone()), i(r1.clone())], vec![]));
-    let r8 = prf.add_step(Justification(p("B"), RuleM::ImpElim, vec![i(r1.clone()), i(r2.clone())], vec![]));
-    let r9 = prf.add_step(Justification(p("P"), RuleM::ImpElim, vec![i(r3.clone()), i(r2.clone())], vec![]));
-    let r10 = prf.add_step(Justification(p("P"), RuleM::ImpElim, vec![i(r2.clone()), i(r3.clone())], vec![]));
-    let r11 = prf.add_step(Justification(p("B"), RuleM::ImpElim, vec![i(r3), i(r4.clone())], vec![]));
-    let r12 = prf.add_step(Justification(p("Q"), RuleM::ImpElim, vec![i(r2.clone()), i(r4.clone())], vec![]));
-    let r13 = prf.add_step(Justification(p("A"), RuleM::ImpElim, vec![i(r4), i(r5)], vec![]));
-    let r14 = prf.add_step(Justification(p("Q"), RuleM::ImpElim, vec![i(r2), i(r1)], vec![]));
-    (prf, vec![i(r6), i(r7), i(r13), i(r14)], vec![i(r8), i(r9), i(r10), i(r11), i(r12)])
+    let (prf, lines) = ProofBuilder::new()
+        .premise("P")
+        .premise("P -> Q")
+        .premise("Q")
+        .premise("A")
+        .premise("A -> A")
+        .step("Q", RuleM::ImpElim, deps![1, 2])
+        .step("Q", RuleM::ImpElim, deps![2, 1])
+        .step("B", RuleM::ImpElim, deps![1, 2])
+        .step("P", RuleM::ImpElim, deps![3, 2])
+        .step("P", RuleM::ImpElim, deps![2, 3])
+        .step("B", RuleM::ImpElim, deps![3, 4])
+        .step("Q", RuleM::ImpElim, deps![2, 4])
+        .step("A", RuleM::ImpElim, deps![4, 5])
+        .step("Q", RuleM::ImpElim, deps![2, 1])
+        .build_with_lines()
+        .unwrap();
+    let l = |n: usize| lines[n - 1].clone();
+    (prf, vec![l(6), l(7), l(13), l(14)], vec![l(8), l(9), l(10), l(11), l(12)])
 }
 
 pub fn test_biconelim<P: Proof>() -> (P, Vec<PjRef<P>>, Vec<PjRef<P>>) {
-    use self::coproduct_inject as i;
-    use crate::parser::parse_unwrap as p;
-    let mut prf = P::new();
-    let r1 = prf.add_premise(p("A <-> B <-> C"));
-    let r2 = prf.add_premise(p("A"));
-    let r3 = prf.add_step(Justification(p("B <-> C"), RuleM::BiconditionalElim, vec![i(r1.clone()), i(r2.clone())], vec![]));
-    let r4 = prf.add_step(Justification(p("C <-> B"), RuleM::BiconditionalElim, vec![i(r1.clone()), i(r2.clone())], vec![]));
-    let r5 = prf.add_step(Justification(p("D <-> B"), RuleM::BiconditionalElim, vec![i(r1.clone()), i(r2.clone())], vec![]));
-    let r8 = prf.add_subproof();
-    let (_r6, r7) = prf
-        .with_mut_subproof(&r8, |sub| {
-            let r6 = sub.add_premise(p("D"));
-            let r7 = sub.add_step(Justification(p("A <-> B"), RuleM::BiconditionalElim, vec![i(r1.clone()), i(r6.clone())], vec![]));
-            (r6, r7)
-        })
+    let (prf, lines) = ProofBuilder::new()
+        .premise("A <-> B <-> C")
+        .premise("A")
+        .premise("A <-> B")
+        .premise("A <-> B <-> C <-> D")
+        .step("B <-> C", RuleM::BiconditionalElim, deps![1, 2])
+        .step("C <-> B", RuleM::BiconditionalElim, deps![1, 2])
+        .step("D <-> B", RuleM::BiconditionalElim, deps![1, 2])
+        .subproof(|b| b.premise("D").step("A <-> B", RuleM::BiconditionalElim, deps![1, 8]))
+        .step("B", RuleM::BiconditionalElim, deps![1, 2])
+        .step("B", RuleM::BiconditionalElim, deps![3, 2])
+        .step("A <-> C <-> D", RuleM::BiconditionalElim, deps![10, 4])
+        .step("C", RuleM::BiconditionalElim, deps![1, 3])
+        .step("B <-> C", RuleM::BiconditionalElim, deps![2, 1])
+        .build_with_lines()
         .unwrap();
-    let r9 = prf.add_premise(p("A <-> B"));
-    let r10 = prf.add_step(Justification(p("B"), RuleM::BiconditionalElim, vec![i(r1.clone()), i(r2.clone())], vec![]));
-    let r11 = prf.add_step(Justification(p("B"), RuleM::BiconditionalElim, vec![i(r9.clone()), i(r2.clone())], vec![]));
-    let r12 = prf.add_premise(p("A <-> B <-> C <-> D"));
-    let r13 = prf.add_step(Justification(p("A <-> C <-> D"), RuleM::BiconditionalElim, vec![i(r10.clone()), i(r12)], vec![]));
-    let r14 = prf.add_step(Justification(p("C"), RuleM::BiconditionalElim, vec![i(r1.clone()), i(r9)], vec![]));
-    let r15 = prf.add_step(Justification(p("B <-> C"), RuleM::BiconditionalElim, vec![i(r2), i(r1.clone())], vec![]));
+    let l = |n: usize| lines[n - 1].clone();
     static BICON_COMMUTATIVITY: bool = false;
     if BICON_COMMUTATIVITY {
-        (prf, vec![i(r3), i(r4), i(r11), i(r13), i(r15)], vec![i(r5), i(r7), i(r10)])
+        (prf, vec![l(5), l(6), l(11), l(12), l(14)], vec![l(7), l(9), l(10)])
     } else {
-        (prf, vec![i(r3), i(r11), i(r13), i(r14), i(r15)], vec![i(r4), i(r5), i(r7), i(r10)])
+        (prf, vec![l(5), l(11), l(12), l(13), l(14)], vec![l(6), l(7), l(9), l(10)])
     }
 }
 
-pub fn test_impintro<P: Proof + Debug>() -> (P, Vec<PjRef<P>>, Vec<PjRef<P>>)
-where
-    P::Subproof: Debug,
-{
-    use self::coproduct_inject as i;
-    use crate::parser::parse_unwrap as p;
-    let mut prf = P::new();
-    let r1 = prf.add_premise(p("A"));
-    let r2 = prf.add_premise(p("B"));
-    let r5 = prf.add_subproof();
-    let r4 = prf
-        .with_mut_subproof(&r5, |sub1| {
-            let _r3 = sub1.add_premise(p("A"));
-            let r4 = sub1.add_step(Justification(p("B"), RuleM::Reit, vec![i(r2.clone())], vec![]));
-            println!("{sub1:?}");
-            r4
-        })
+pub fn test_impintro<P: Proof>() -> (P, Vec<PjRef<P>>, Vec<PjRef<P>>) {
+    let (prf, lines) = ProofBuilder::new()
+        .premise("A")
+        .premise("B")
+        .subproof(|b| b.premise("A").step("B", RuleM::Reit, deps![2]))
+        .subproof(|b| b.premise("A").step("A", RuleM::Reit, deps![1]))
+        .step("A -> B", RuleM::ImpIntro, deps![; 3..=4])
+        .step("A -> A", RuleM::ImpIntro, deps![; 5..=6])
+        .step("B -> A", RuleM::ImpIntro, deps![; 3..=4])
+        .subproof(|b| b.premise("A").premise("B").step("B", RuleM::Reit, deps![]))
+        .step("A -> B", RuleM::ImpIntro, deps![; 10..=12])
+        .build_with_lines()
         .unwrap();
-    let r8 = prf.add_subproof();
-    let r7 = prf
-        .with_mut_subproof(&r8, |sub2| {
-            let _r6 = sub2.add_premise(p("A"));
-            let r7 = sub2.add_step(Justification(p("A"), RuleM::Reit, vec![i(r1.clone())], vec![]));
-            println!("{sub2:?}");
-            r7
-        })
-        .unwrap();
-    let r9 = prf.add_step(Justification(p("A -> B"), RuleM::ImpIntro, vec![], vec![r5.clone()]));
-    let r10 = prf.add_step(Justification(p("A -> A"), RuleM::ImpIntro, vec![], vec![r8.clone()]));
-    let r11 = prf.add_step(Justification(p("B -> A"), RuleM::ImpIntro, vec![], vec![r5.clone()]));
-    (prf, vec![i(r4), i(r7), i(r9), i(r10)], vec![i(r11)])
+    let l = |n: usize| lines[n - 1].clone();
+    (prf, vec![l(4), l(6), l(7), l(8)], vec![l(9), l(13)])
 }
 
 pub fn test_notintro<P: Proof>() -> (P, Vec<PjRef<P>>, Vec<PjRef<P>>) {
-    use self::coproduct_inject as i;
-    use crate::parser::parse_unwrap as p;
-    let mut prf = P::new();
-    let r1 = prf.add_premise(p("A -> _|_"));
-    let r4 = prf.add_subproof();
-    prf.with_mut_subproof(&r4, |sub1| {
-        let r2 = sub1.add_premise(p("A"));
-        let _r3 = sub1.add_step(Justification(p("_|_"), RuleM::ImpElim, vec![i(r1.clone()), i(r2)], vec![]));
-    });
-    let r5 = prf.add_step(Justification(p("~A"), RuleM::NotIntro, vec![], vec![r4.clone()]));
-    let r6 = prf.add_step(Justification(p("~B"), RuleM::NotIntro, vec![], vec![r4.clone()]));
-    (prf, vec![i(r5)], vec![i(r6)])
+    let (prf, lines) = ProofBuilder::new()
+        .premise("A -> _|_")
+        .subproof(|b| b.premise("A").step("_|_", RuleM::ImpElim, deps![1, 2]))
+        .step("~A", RuleM::NotIntro, deps![; 2..=3])
+        .step("~B", RuleM::NotIntro, deps![; 2..=3])
+        // the contradiction has to be the subproof's last step
+        .subproof(|b| b.premise("A").step("_|_", RuleM::ImpElim, deps![1, 6]).step("A", RuleM::Reit, deps![6]))
+        .step("~A", RuleM::NotIntro, deps![; 6..=8])
+        // so does the subproof's one premise
+        .subproof(|b| b.premise("A").premise("B").step("_|_", RuleM::ImpElim, deps![1, 10]))
+        .step("~A", RuleM::NotIntro, deps![; 10..=12])
+        .build_with_lines()
+        .unwrap();
+    let l = |n: usize| lines[n - 1].clone();
+    (prf, vec![l(4), l(7), l(8), l(12)], vec![l(5), l(9), l(13)])
 }
 
 pub fn test_orelim<P: Proof>() -> (P, Vec<PjRef<P>>, Vec<PjRef<P>>) {
-    use self::coproduct_inject as i;
-    use crate::parser::parse_unwrap as p;
-    let mut prf = P::new();
-    let r1 = prf.add_premise(p("A | B"));
-    let r4 = prf.add_subproof();
-    prf.with_mut_subproof(&r4, |sub1| {
-        let _r2 = sub1.add_premise(p("A"));
-        let _r3 = sub1.add_step(Justification(p("C"), RuleM::Reit, vec![], vec![]));
-    });
-    let r7 = prf.add_subproof();
-    prf.with_mut_subproof(&r7, |sub2| {
-        let _r5 = sub2.add_premise(p("B"));
-        let _r6 = sub2.add_step(Justification(p("C"), RuleM::Reit, vec![], vec![]));
-    });
-    let r10 = prf.add_subproof();
-    prf.with_mut_subproof(&r10, |sub3| {
-        let _r8 = sub3.add_premise(p("B"));
-        let _r9 = sub3.add_step(Justification(p("D"), RuleM::Reit, vec![], vec![]));
-    });
-    let r11 = prf.add_step(Justification(p("C"), RuleM::OrElim, vec![i(r1.clone())], vec![r4.clone(), r7.clone()]));
-    let r12 = prf.add_step(Justification(p("D"), RuleM::OrElim, vec![i(r1.clone())], vec![r4.clone(), r7.clone()]));
-    let r13 = prf.add_step(Justification(p("C"), RuleM::OrElim, vec![i(r1)], vec![r4.clone(), r10.clone()]));
-    (prf, vec![i(r11)], vec![i(r12), i(r13)])
+    let (prf, lines) = ProofBuilder::new()
+        .premise("A | B")
+        .subproof(|b| b.premise("A").step("C", RuleM::Reit, deps![]))
+        .subproof(|b| b.premise("B").step("C", RuleM::Reit, deps![]))
+        .subproof(|b| b.premise("B").step("D", RuleM::Reit, deps![]))
+        .step("C", RuleM::OrElim, deps![1; 2..=3, 4..=5])
+        .step("D", RuleM::OrElim, deps![1; 2..=3, 4..=5])
+        .step("C", RuleM::OrElim, deps![1; 2..=3, 6..=7])
+        .build_with_lines()
+        .unwrap();
+    let l = |n: usize| lines[n - 1].clone();
+    (prf, vec![l(8)], vec![l(9), l(10)])
 }
 
-pub fn test_biconintro<P: Proof + Debug>() -> (P, Vec<PjRef<P>>, Vec<PjRef<P>>)
-where
-    P::Subproof: Debug,
-{
-    use self::coproduct_inject as i;
-    use crate::parser::parse_unwrap as p;
-    let mut prf = P::new();
-    let r1 = prf.add_premise(p("P -> Q"));
-    let r2 = prf.add_premise(p("Q -> R"));
-    let r3 = prf.add_premise(p("Q -> P"));
-    let r4 = prf.add_premise(p("R -> Q"));
-    let r5 = prf.add_premise(p("R -> P"));
-    let r6 = prf.add_premise(p("A -> A"));
-    let r7 = prf.add_step(Justification(p("A <-> A"), RuleM::BiconditionalIntro, vec![i(r6)], vec![]));
-    let r8 = prf.add_step(Justification(p("P <-> Q <-> R"), RuleM::BiconditionalIntro, vec![i(r1.clone()), i(r2.clone()), i(r3.clone()), i(r4.clone())], vec![]));
-    let r9 = prf.add_step(Justification(p("P <-> Q <-> R"), RuleM::BiconditionalIntro, vec![i(r1.clone()), i(r2.clone()), i(r5.clone())], vec![]));
-    let r10 = prf.add_subproof();
-    prf.with_mut_subproof(&r10, |sub1| {
-        sub1.add_premise(p("B"));
-    });
-    let r11 = prf.add_step(Justification(p("B <-> B"), RuleM::BiconditionalIntro, vec![], vec![r10.clone()]));
-    let r12 = prf.add_step(Justification(p("P <-> Q <-> R <-> S"), RuleM::BiconditionalIntro, vec![i(r1.clone()), i(r2.clone()), i(r3.clone()), i(r4)], vec![]));
-    let r13 = prf.add_step(Justification(p("P <-> Q <-> R <-> S"), RuleM::BiconditionalIntro, vec![i(r1), i(r2), i(r5)], vec![]));
-    let r14 = prf.add_subproof();
-    prf.with_mut_subproof(&r14, |sub2| {
-        sub2.add_premise(p("A"));
-        sub2.add_step(Justification(p("B"), RuleM::Reit, vec![], vec![]));
-        sub2.add_step(Justification(p("C"), RuleM::Reit, vec![], vec![]));
-    });
-    let r15 = prf.add_subproof();
-    prf.with_mut_subproof(&r15, |sub2| {
-        sub2.add_premise(p("B"));
-        sub2.add_step(Justification(p("A"), RuleM::Reit, vec![], vec![]));
-        sub2.add_step(Justification(p("C"), RuleM::Reit, vec![], vec![]));
-    });
-    let r16 = prf.add_step(Justification(p("A <-> B"), RuleM::BiconditionalIntro, vec![], vec![r14.clone(), r15.clone()]));
-    let r17 = prf.add_step(Justification(p("A <-> C"), RuleM::BiconditionalIntro, vec![], vec![r14.clone(), r15.clone()]));
-    let r18 = prf.add_subproof();
-    prf.with_mut_subproof(&r18, |sub2| {
-        sub2.add_premise(p("P"));
-        sub2.add_step(Justification(p("Q"), RuleM::Reit, vec![], vec![]));
-    });
-    let r19 = prf.add_step(Justification(p("P <-> Q"), RuleM::BiconditionalIntro, vec![i(r3)], vec![r18.clone()]));
-    (prf, vec![i(r7), i(r11), i(r16), i(r19)], vec![i(r8), i(r9), i(r12), i(r13), i(r17)])
+pub fn test_bicon_chain_mixing<P: Proof>() -> (P, Vec<PjRef<P>>, Vec<PjRef<P>>) {
+    let (prf, lines) = ProofBuilder::new()
+        .premise("(A <-> B) <-> C")
+        .premise("A")
+        .premise("A <-> B")
+        .premise("A <-> B <-> C")
+        .premise("(A <-> B <-> E) -> D")
+        .premise("D -> ((A <-> B) <-> E)")
+        .premise("~~((A <-> B) <-> C)")
+        .step("B <-> C", RuleM::BiconditionalElim, deps![1, 2])
+        .step("C", RuleM::BiconditionalElim, deps![1, 3])
+        .step("(A <-> B) <-> C", RuleM::BiconditionalElim, deps![4, 2])
+        .step("D <-> E", RuleM::BiconditionalElim, deps![1, 2])
+        .step("B <-> C", RuleM::BiconditionalElim, deps![4, 2])
+        .step("D <-> (A <-> B <-> E)", RuleM::BiconditionalIntro, deps![5, 6])
+        // (A <-> B) <-> C holds when A is true and B and C are false, so it must not equate A and B
+        .step("A <-> B", RuleM::BiconditionalIntro, deps![1])
+        .step("A <-> B <-> C", RuleM::DoubleNegation, deps![7])
+        .build_with_lines()
+        .unwrap();
+    let l = |n: usize| lines[n - 1].clone();
+    (prf, vec![l(8), l(9), l(12), l(13), l(15)], vec![l(10), l(11), l(14)])
 }
 
-pub fn test_equivintro<P: Proof + Debug>() -> (P, Vec<PjRef<P>>, Vec<PjRef<P>>)
-where
-    P::Subproof: Debug,
-{
-    use self::coproduct_inject as i;
-    use crate::parser::parse_unwrap as p;
-    let mut prf = P::new();
-    let r1 = prf.add_premise(p("P -> Q"));
-    let r2 = prf.add_premise(p("Q -> R"));
-    let r3 = prf.add_premise(p("Q -> P"));
-    let r4 = prf.add_premise(p("R -> Q"));
-    let r5 = prf.add_premise(p("R -> P"));
-    let r6 = prf.add_premise(p("A -> A"));
-    let r7 = prf.add_step(Justification(p("A === A === A === A === A"), RuleM::EquivalenceIntro, vec![i(r6)], vec![]));
-    let r8 = prf.add_step(Justification(p("P === Q === R"), RuleM::EquivalenceIntro, vec![i(r1.clone()), i(r2.clone()), i(r3.clone()), i(r4.clone())], vec![]));
-    let r9 = prf.add_step(Justification(p("P === Q === R"), RuleM::EquivalenceIntro, vec![i(r1.clone()), i(r2.clone()), i(r5.clone())], vec![]));
-    let r10 = prf.add_subproof();
-    prf.with_mut_subproof(&r10, |sub1| {
-        sub1.add_premise(p("B"));
-    });
-    let r11 = prf.add_step(Justification(p("B === B === B"), RuleM::EquivalenceIntro, vec![], vec![r10.clone()]));
-    let r12 = prf.add_step(Justification(p("P === Q === R === S"), RuleM::EquivalenceIntro, vec![i(r1.clone()), i(r2.clone()), i(r3.clone()), i(r4)], vec![]));
-    let r13 = prf.add_step(Justification(p("P === Q === R === S"), RuleM::EquivalenceIntro, vec![i(r1), i(r2), i(r5)], vec![]));
-    let r14 = prf.add_subproof();
-    prf.with_mut_subproof(&r14, |sub2| {
-        sub2.add_premise(p("A"));
-        sub2.add_step(Justification(p("B"), RuleM::Reit, vec![], vec![]));
-        sub2.add_step(Justification(p("C"), RuleM::Reit, vec![], vec![]));
-    });
-    let r15 = prf.add_subproof();
-    prf.with_mut_subproof(&r15, |sub2| {
-        sub2.add_premise(p("B"));
-        sub2.add_step(Justification(p("A"), RuleM::Reit, vec![], vec![]));
-        sub2.add_step(Justification(p("C"), RuleM::Reit, vec![], vec![]));
-    });
-    let r16 = prf.add_step(Justification(p("A === B"), RuleM::EquivalenceIntro, vec![], vec![r14.clone(), r15.clone()]));
-    let r17 = prf.add_step(Justification(p("A === C"), RuleM::EquivalenceIntro, vec![], vec![r14.clone(), r15.clone()]));
-    let r18 = prf.add_subproof();
-    prf.with_mut_subproof(&r18, |sub2| {
-        sub2.add_premise(p("P"));
-        sub2.add_step(Justification(p("Q"), RuleM::Reit, vec![], vec![]));
-    });
-    let r19 = prf.add_step(Justification(p("P === Q"), RuleM::EquivalenceIntro, vec![i(r3)], vec![r18.clone()]));
-    (prf, vec![i(r7), i(r8), i(r9), i(r11), i(r16), i(r19)], vec![i(r12), i(r13), i(r17)])
+pub fn test_bicon_chain_mixing_strict<P: Proof>() -> (P, Vec<PjRef<P>>, Vec<PjRef<P>>) {
+    let (prf, lines) = ProofBuilder::new()
+        .premise("(A <-> B) <-> C")
+        .premise("A <-> B <-> C")
+        .premise("C")
+        .premise("((A <-> B) <-> C) <-> D")
+        .premise("A")
+        .step("A <-> B", RuleM::BiconditionalElim, deps![1, 3])
+        .step("A <-> B", RuleM::BiconditionalElim, deps![2, 3])
+        .step("B <-> C <-> D", RuleM::BiconditionalElim, deps![4, 5])
+        .build_with_lines()
+        .unwrap();
+    let l = |n: usize| lines[n - 1].clone();
+    (prf, vec![l(6), l(7)], vec![l(8)])
 }
 
-pub fn test_equivelim<P: Proof>() -> (P, Vec<PjRef<P>>, Vec<PjRef<P>>) {
-    use self::coproduct_inject as i;
-    use crate::parser::parse_unwrap as p;
-    let mut prf = P::new();
-    let r1 = prf.add_premise(p("A === B === C"));
-    let r2 = prf.add_premise(p("A"));
-    let r3 = prf.add_step(Justification(p("B"), RuleM::EquivalenceElim, vec![i(r1.clone()), i(r2.clone())], vec![]));
-    let r4 = prf.add_step(Justification(p("C"), RuleM::EquivalenceElim, vec![i(r1.clone()), i(r2.clone())], vec![]));
-    let r5 = prf.add_step(Justification(p("A"), RuleM::EquivalenceElim, vec![i(r1.clone()), i(r4.clone())], vec![]));
-    let r6 = prf.add_step(Justification(p("D"), RuleM::EquivalenceElim, vec![i(r1.clone()), i(r4.clone())], vec![]));
-    let r7 = prf.add_step(Justification(p("A"), RuleM::EquivalenceElim, vec![i(r1.clone()), i(r6.clone())], vec![]));
-    let r8 = prf.add_step(Justification(p("B"), RuleM::EquivalenceElim, vec![i(r2), i(r1)], vec![]));
-    (prf, vec![i(r3), i(r4), i(r5), i(r8)], vec![i(r6), i(r7)])
+pub fn test_biconintro<P: Proof>() -> (P, Vec<PjRef<P>>, Vec<PjRef<P>>) {
+    let (prf, lines) = ProofBuilder::new()
+        .premise("P -> Q")
+        .premise("Q -> R")
+        .premise("Q -> P")
+        .premise("R -> Q")
+        .premise("R -> P")
+        .premise("A -> A")
+        .step("A <-> A", RuleM::BiconditionalIntro, deps![6])
+        .step("P <-> Q <-> R", RuleM::BiconditionalIntro, deps![1, 2, 3, 4])
+        .step("P <-> Q <-> R", RuleM::BiconditionalIntro, deps![1, 2, 5])
+        .subproof(|b| b.premise("B"))
+        .step("B <-> B", RuleM::BiconditionalIntro, deps![; 10..=10])
+        .step("P <-> Q <-> R <-> S", RuleM::BiconditionalIntro, deps![1, 2, 3, 4])
+        .step("P <-> Q <-> R <-> S", RuleM::BiconditionalIntro, deps![1, 2, 5])
+        .subproof(|b| b.premise("A").step("C", RuleM::Reit, deps![]).step("B", RuleM::Reit, deps![]))
+        .subproof(|b| b.premise("B").step("C", RuleM::Reit, deps![]).step("A", RuleM::Reit, deps![]))
+        .step("A <-> B", RuleM::BiconditionalIntro, deps![; 14..=16, 17..=19])
+        .step("A <-> C", RuleM::BiconditionalIntro, deps![; 14..=16, 17..=19])
+        .subproof(|b| b.premise("P").step("Q", RuleM::Reit, deps![]))
+        .step("P <-> Q", RuleM::BiconditionalIntro, deps![3; 22..=23])
+        // B and A are in the subproofs, but aren't what they conclude
+        .subproof(|b| b.premise("A").step("B", RuleM::Reit, deps![]).step("C", RuleM::Reit, deps![]))
+        .subproof(|b| b.premise("B").step("A", RuleM::Reit, deps![]).step("C", RuleM::Reit, deps![]))
+        .step("A <-> B", RuleM::BiconditionalIntro, deps![; 25..=27, 28..=30])
+        .build_with_lines()
+        .unwrap();
+    let l = |n: usize| lines[n - 1].clone();
+    (prf, vec![l(7), l(11), l(20), l(24)], vec![l(8), l(9), l(12), l(13), l(21), l(31)])
 }
 
-pub fn test_forallelim<P: Proof + Debug>() -> (P, Vec<PjRef<P>>, Vec<PjRef<P>>)
-where
-    P::Subproof: Debug,
-{
-    use self::coproduct_inject as i;
-    use crate::parser::parse_unwrap as p;
-    let mut prf = P::new();
-    let r1 = prf.add_premise(p("forall x p(x)"));
-    let r2 = prf.add_step(Justification(p("p(a)"), RuleM::ForallElim, vec![i(r1.clone())], vec![]));
-    let r3 = prf.add_step(Justification(p("q(x)"), RuleM::ForallElim, vec![i(r1.clone())], vec![]));
-    let r4 = prf.add_step(Justification(p("p(A & B & C & D)"), RuleM::ForallElim, vec![i(r1)], vec![]));
-    (prf, vec![i(r2), i(r4)], vec![i(r3)])
+pub fn test_equivintro<P: Proof>() -> (P, Vec<PjRef<P>>, Vec<PjRef<P>>) {
+    let (prf, lines) = ProofBuilder::new()
+        .premise("P -> Q")
+        .premise("Q -> R")
+        .premise("Q -> P")
+        .premise("R -> Q")
+        .premise("R -> P")
+        .premise("A -> A")
+        .step("A === A === A === A === A", RuleM::EquivalenceIntro, deps![6])
+        .step("P === Q === R", RuleM::EquivalenceIntro, deps![1, 2, 3, 4])
+        .step("P === Q === R", RuleM::EquivalenceIntro, deps![1, 2, 5])
+        .subproof(|b| b.premise("B"))
+        .step("B === B === B", RuleM::EquivalenceIntro, deps![; 10..=10])
+        .step("P === Q === R === S", RuleM::EquivalenceIntro, deps![1, 2, 3, 4])
+        .step("P === Q === R === S", RuleM::EquivalenceIntro, deps![1, 2, 5])
+        .subproof(|b| b.premise("A").step("C", RuleM::Reit, deps![]).step("B", RuleM::Reit, deps![]))
+        .subproof(|b| b.premise("B").step("C", RuleM::Reit, deps![]).step("A", RuleM::Reit, deps![]))
+        .step("A === B", RuleM::EquivalenceIntro, deps![; 14..=16, 17..=19])
+        .step("A === C", RuleM::EquivalenceIntro, deps![; 14..=16, 17..=19])
+        .subproof(|b| b.premise("P").step("Q", RuleM::Reit, deps![]))
+        .step("P === Q", RuleM::EquivalenceIntro, deps![3; 22..=23])
+        // B and A are in the subproofs, but aren't what they conclude
+        .subproof(|b| b.premise("A").step("B", RuleM::Reit, deps![]).step("C", RuleM::Reit, deps![]))
+        .subproof(|b| b.premise("B").step("A", RuleM::Reit, deps![]).step("C", RuleM::Reit, deps![]))
+        .step("A === B", RuleM::EquivalenceIntro, deps![; 25..=27, 28..=30])
+        .build_with_lines()
+        .unwrap();
+    let l = |n: usize| lines[n - 1].clone();
+    (prf, vec![l(7), l(8), l(9), l(11), l(20), l(24)], vec![l(12), l(13), l(21), l(31)])
 }
 
-pub fn test_forallintro<P: Proof + Debug>() -> (P, Vec<PjRef<P>>, Vec<PjRef<P>>)
-where
-    P::Subproof: Debug,
-    PjRef<P>: Debug,
-    P::SubproofReference: Debug,
-{
-    use self::coproduct_inject as i;
-    use crate::parser::parse_unwrap as p;
-    let mut prf = P::new();
-    let r1 = prf.add_premise(p("forall x p(x)"));
-    let r2 = prf.add_premise(p("forall x q(x)"));
-    let r3 = prf.add_premise(p("r(c)"));
-    let r4 = prf.add_subproof();
-    let (r5, r6, r7) = prf
-        .with_mut_subproof(&r4, |sub| {
-            let r5 = sub.add_step(Justification(p("p(a)"), RuleM::ForallElim, vec![i(r1.clone())], vec![]));
-            let r6 = sub.add_step(Justification(p("q(a)"), RuleM::ForallElim, vec![i(r2.clone())], vec![]));
-            let r7 = sub.add_step(Justification(p("p(a) & q(a)"), RuleM::AndIntro, vec![i(r5.clone()), i(r6.clone())], vec![]));
-            (r5, r6, r7)
-        })
-        .unwrap();
-    let r8 = prf.add_step(Justification(p("forall y p(y) & q(y)"), RuleM::ForallIntro, vec![], vec![r4.clone()]));
-    let r9 = prf.add_step(Justification(p("forall y p(a) & q(y)"), RuleM::ForallIntro, vec![], vec![r4.clone()]));
-    let r10 = prf.add_subproof();
-    let r11 = prf
-        .with_mut_subproof(&r10, |sub| {
-            let r11 = sub.add_step(Justification(p("r(c)"), RuleM::Reit, vec![i(r3.clone())], vec![]));
-            println!("contained {:?}", sub.contained_justifications(true));
-            println!("reachable {:?}", sub.transitive_dependencies(i(r11.clone())));
-            println!("reachable-contained {:?}", sub.transitive_dependencies(i(r11.clone())).difference(&sub.contained_justifications(true)).collect::<HashSet<_>>());
-            r11
-        })
-        .unwrap();
-    let r12 = prf.add_step(Justification(p("forall y r(y)"), RuleM::ForallIntro, vec![], vec![r10.clone()]));
-    let r13 = prf.add_subproof();
-    let (r17, r18, r19) = prf
-        .with_mut_subproof(&r13, |sub1| {
-            let r14 = sub1.add_subproof();
-            let (r17, r18) = sub1
-                .with_mut_subproof(&r14, |sub2| {
-                    let r15 = sub2.add_subproof();
-                    let r17 = sub2.with_mut_subproof(&r15, |sub3| sub3.add_step(Justification(p("s(a, b)"), RuleM::Reit, vec![], vec![]))).unwrap();
-                    let r18 = sub2.add_step(Justification(p("forall y s(a, y)"), RuleM::ForallIntro, vec![], vec![r15]));
-                    (r17, r18)
-                })
-                .unwrap();
-            let r19 = sub1.add_step(Justification(p("forall x forall y s(x, y)"), RuleM::ForallIntro, vec![], vec![r14]));
-            (r17, r18, r19)
-        })
+pub fn test_equivelim<P: Proof>() -> (P, Vec<PjRef<P>>, Vec<PjRef<P>>) {
+    let (prf, lines) = ProofBuilder::new()
+        .premise("A === B === C")
+        .premise("A")
+        .step("B", RuleM::EquivalenceElim, deps![1, 2])
+        .step("C", RuleM::EquivalenceElim, deps![1, 2])
+        .step("A", RuleM::EquivalenceElim, deps![1, 4])
+        .step("D", RuleM::EquivalenceElim, deps![1, 4])
+        .step("A", RuleM::EquivalenceElim, deps![1, 6])
+        .step("B", RuleM::EquivalenceElim, deps![2, 1])
+        .build_with_lines()
         .unwrap();
+    let l = |n: usize| lines[n - 1].clone();
+    (prf, vec![l(3), l(4), l(5), l(8)], vec![l(6), l(7)])
+}
 
-    let r20 = prf.add_subproof();
-    let r22 = prf
-        .with_mut_subproof(&r20, |sub| {
-            let r21 = sub.add_premise(p("a"));
+pub fn test_forallelim<P: Proof>() -> (P, Vec<PjRef<P>>, Vec<PjRef<P>>) {
+    let (prf, lines) = ProofBuilder::new()
+        .premise("forall x p(x)")
+        .premise("forall x forall y r(x, y)")
+        .premise("forall x forall y forall z (r(x, y) -> r(y, z))")
+        .premise("forall x forall y (r(x, y) & s(x))")
+        .step("p(a)", RuleM::ForallElim, deps![1])
+        .step("q(x)", RuleM::ForallElim, deps![1])
+        .step("p(A & B & C & D)", RuleM::ForallElim, deps![1])
+        .step("r(a, b)", RuleM::ForallElim, deps![2])
+        .step("forall y r(a, y)", RuleM::ForallElim, deps![2])
+        .step("r(c, c)", RuleM::ForallElim, deps![2])
+        .step("r(a, b) -> r(b, c)", RuleM::ForallElim, deps![3])
+        .step("r(f(a), a) -> r(a, f(a))", RuleM::ForallElim, deps![3])
+        .step("forall z (r(a, b) -> r(b, z))", RuleM::ForallElim, deps![3])
+        .step("r(a, b) -> r(c, d)", RuleM::ForallElim, deps![3])
+        .step("forall y r(y, y)", RuleM::ForallElim, deps![2])
+        .step("r(a, b) & s(b)", RuleM::ForallElim, deps![4])
+        .step("r(b, a) -> r(b, c)", RuleM::ForallElim, deps![3])
+        .build_with_lines()
+        .unwrap();
+    let l = |n: usize| lines[n - 1].clone();
+    (prf, vec![l(5), l(7), l(8), l(9), l(10), l(11), l(12), l(13)], vec![l(6), l(14), l(15), l(16), l(17)])
+}
 
-            sub.add_step(Justification(p("a"), RuleM::Reit, vec![i(r21)], vec![]))
+pub fn test_forallintro<P: Proof>() -> (P, Vec<PjRef<P>>, Vec<PjRef<P>>) {
+    let (prf, lines) = ProofBuilder::new()
+        .premise("forall x p(x)")
+        .premise("forall x q(x)")
+        .premise("r(c)")
+        .subproof(|b| {
+            b.step("p(a)", RuleM::ForallElim, deps![1])
+                .step("q(a)", RuleM::ForallElim, deps![2])
+                .step("p(a) & q(a)", RuleM::AndIntro, deps![4, 5])
         })
+        .step("forall y p(y) & q(y)", RuleM::ForallIntro, deps![; 4..=6])
+        .step("forall y p(a) & q(y)", RuleM::ForallIntro, deps![; 4..=6])
+        .subproof(|b| b.step("r(c)", RuleM::Reit, deps![3]))
+        .step("forall y r(y)", RuleM::ForallIntro, deps![; 9..=9])
+        .subproof(|b| {
+            b.subproof(|b| {
+                b.subproof(|b| b.step("s(a, b)", RuleM::Reit, deps![]))
+                    .step("forall y s(a, y)", RuleM::ForallIntro, deps![; 11..=11])
+            })
+                .step("forall x forall y s(x, y)", RuleM::ForallIntro, deps![; 11..=12])
+        })
+        .subproof(|b| b.premise("a").step("a", RuleM::Reit, deps![14]))
+        .step("forall x x", RuleM::ForallIntro, deps![; 14..=15])
+        // p(a) is in the subproof, but isn't its last line
+        .subproof(|b| b.step("p(a)", RuleM::ForallElim, deps![1]).step("q(a)", RuleM::ForallElim, deps![2]))
+        .step("forall x p(x)", RuleM::ForallIntro, deps![; 17..=18])
+        .step("forall x q(x)", RuleM::ForallIntro, deps![; 17..=18])
+        .build_with_lines()
         .unwrap();
-    let r23 = prf.add_step(Justification(p("forall x x"), RuleM::ForallIntro, vec![], vec![r20]));
-    (prf, vec![i(r5), i(r6), i(r7), i(r8), i(r11), i(r18), i(r19), i(r22)], vec![i(r9), i(r12), i(r17), i(r23)])
+    let l = |n: usize| lines[n - 1].clone();
+    (prf, vec![l(4), l(5), l(6), l(7), l(9), l(12), l(13), l(15), l(20)], vec![l(8), l(10), l(11), l(16), l(19)])
 }
 
-pub fn test_existsintro<P: Proof + Debug>() -> (P, Vec<PjRef<P>>, Vec<PjRef<P>>)
-where
-    P::Subproof: Debug,
-    PjRef<P>: Debug,
-    P::SubproofReference: Debug,
-{
-    use self::coproduct_inject as i;
-    use crate::parser::parse_unwrap as p;
-    let mut prf = P::new();
-
-    let r1 = prf.add_premise(p("p(a)"));
-    let r2 = prf.add_step(Justification(p("p(b) & p(b)"), RuleM::Reit, vec![], vec![]));
-    let r3 = prf.add_step(Justification(p("exists x p(x)"), RuleM::ExistsIntro, vec![i(r1.clone())], vec![]));
-    let r4 = prf.add_step(Justification(p("exists x p(a)"), RuleM::ExistsIntro, vec![i(r1.clone())], vec![]));
-    let r5 = prf.add_step(Justification(p("exists x p(b)"), RuleM::ExistsIntro, vec![i(r1)], vec![]));
-    let r6 = prf.add_step(Justification(p("exists x p(x) & p(x)"), RuleM::ExistsIntro, vec![i(r2.clone())], vec![]));
-    let r7 = prf.add_step(Justification(p("exists x p(b) & p(x)"), RuleM::ExistsIntro, vec![i(r2.clone())], vec![]));
-    let r8 = prf.add_step(Justification(p("exists x p(x) & p(b)"), RuleM::ExistsIntro, vec![i(r2.clone())], vec![]));
-    let r9 = prf.add_step(Justification(p("exists x p(b) & p(b)"), RuleM::ExistsIntro, vec![i(r2.clone())], vec![]));
-    let r10 = prf.add_step(Justification(p("exists x p(y) & p(b)"), RuleM::ExistsIntro, vec![i(r2.clone())], vec![]));
-    let r11 = prf.add_step(Justification(p("exists x p(a) & p(b)"), RuleM::ExistsIntro, vec![i(r2.clone())], vec![]));
-    let r12 = prf.add_step(Justification(p("exists x p(y) & p(x)"), RuleM::ExistsIntro, vec![i(r2)], vec![]));
-
-    (prf, vec![i(r3), i(r4), i(r6), i(r7), i(r8), i(r9)], vec![i(r5), i(r10), i(r11), i(r12)])
+pub fn test_existsintro<P: Proof>() -> (P, Vec<PjRef<P>>, Vec<PjRef<P>>) {
+    let (prf, lines) = ProofBuilder::new()
+        .premise("p(a)")
+        .premise("q(c, c)")
+        .step("p(b) & p(b)", RuleM::Reit, deps![])
+        .step("exists x p(x)", RuleM::ExistsIntro, deps![1])
+        .step("exists x p(a)", RuleM::ExistsIntro, deps![1])
+        .step("exists x p(b)", RuleM::ExistsIntro, deps![1])
+        .step("exists x p(x) & p(x)", RuleM::ExistsIntro, deps![3])
+        .step("exists x p(b) & p(x)", RuleM::ExistsIntro, deps![3])
+        .step("exists x p(x) & p(b)", RuleM::ExistsIntro, deps![3])
+        .step("exists x p(b) & p(b)", RuleM::ExistsIntro, deps![3])
+        .step("exists x p(y) & p(b)", RuleM::ExistsIntro, deps![3])
+        .step("exists x p(a) & p(b)", RuleM::ExistsIntro, deps![3])
+        .step("exists x p(y) & p(x)", RuleM::ExistsIntro, deps![3])
+        .step("exists x q(x, c)", RuleM::ExistsIntro, deps![2])
+        .step("exists x q(c, x)", RuleM::ExistsIntro, deps![2])
+        .step("exists x q(x, x)", RuleM::ExistsIntro, deps![2])
+        .step("exists x q(x, d)", RuleM::ExistsIntro, deps![2])
+        .step("exists x forall c q(x, c)", RuleM::ExistsIntro, deps![2])
+        .build_with_lines()
+        .unwrap();
+    let l = |n: usize| lines[n - 1].clone();
+    (prf, vec![l(4), l(5), l(7), l(8), l(9), l(10), l(14), l(15), l(16)], vec![l(6), l(11), l(12), l(13), l(17), l(18)])
 }
-pub fn test_existselim<P: Proof + Debug>() -> (P, Vec<PjRef<P>>, Vec<PjRef<P>>)
-where
-    P::Subproof: Debug,
-    PjRef<P>: Debug,
-    P::SubproofReference: Debug,
-{
-    use self::coproduct_inject as i;
-    use crate::parser::parse_unwrap as p;
-    let mut prf = P::new();
-    let r1 = prf.add_premise(p("exists x p(x)"));
-    let r2 = prf.add_premise(p("p(a) -> q(a)"));
-    let r3 = prf.add_premise(p("forall b (p(b) -> r(b))"));
-    let r4 = prf.add_subproof();
-    let (r6, r7, r8, r9, r10) = prf
-        .with_mut_subproof(&r4, |sub| {
-            let r5 = sub.add_premise(p("p(a)"));
-            let r6 = sub.add_step(Justification(p("q(a)"), RuleM::ImpElim, vec![i(r2.clone()), i(r5.clone())], vec![]));
-            let r7 = sub.add_step(Justification(p("p(a) -> r(a)"), RuleM::ForallElim, vec![i(r3.clone())], vec![]));
-            let r8 = sub.add_step(Justification(p("r(a)"), RuleM::ImpElim, vec![i(r7.clone()), i(r5)], vec![]));
-            let r9 = sub.add_step(Justification(p("exists x q(x)"), RuleM::ExistsIntro, vec![i(r6.clone())], vec![]));
-            let r10 = sub.add_step(Justification(p("exists x r(x)"), RuleM::ExistsIntro, vec![i(r8.clone())], vec![]));
-            (r6, r7, r8, r9, r10)
+pub fn test_existselim<P: Proof>() -> (P, Vec<PjRef<P>>, Vec<PjRef<P>>) {
+    let (prf, lines) = ProofBuilder::new()
+        .premise("exists x p(x)")
+        .premise("p(a) -> q(a)")
+        .premise("forall b (p(b) -> r(b))")
+        .premise("forall y man(y) → mortal(y)")
+        .premise("exists x man(x)")
+        .premise("forall c forall d p(c) -> s(d)")
+        .subproof(|b| {
+            b.premise("p(a)")
+                .step("q(a)", RuleM::ImpElim, deps![2, 7])
+                .step("p(a) -> r(a)", RuleM::ForallElim, deps![3])
+                .step("r(a)", RuleM::ImpElim, deps![9, 7])
+                .step("exists x q(x)", RuleM::ExistsIntro, deps![8])
+                .step("exists x r(x)", RuleM::ExistsIntro, deps![10])
         })
-        .unwrap();
-    let r11 = prf.add_step(Justification(p("exists x q(x)"), RuleM::ExistsElim, vec![i(r1.clone())], vec![r4.clone()]));
-    let r12 = prf.add_step(Justification(p("exists x r(x)"), RuleM::ExistsElim, vec![i(r1.clone())], vec![r4.clone()]));
-    let r13 = prf.add_step(Justification(p("r(a)"), RuleM::ExistsElim, vec![i(r1.clone())], vec![r4.clone()]));
-
-    let s1 = prf.add_premise(p("forall y man(y) → mortal(y)"));
-    let s2 = prf.add_premise(p("exists x man(x)"));
-    let s3 = prf.add_subproof();
-    let (s5, s6, s7) = prf
-        .with_mut_subproof(&s3, |sub| {
-            let s4 = sub.add_premise(p("man(socrates)"));
-            let s5 = sub.add_step(Justification(p("man(socrates) → mortal(socrates)"), RuleM::ForallElim, vec![i(s1.clone())], vec![]));
-            let s6 = sub.add_step(Justification(p("mortal(socrates)"), RuleM::ImpElim, vec![i(s4), i(s5.clone())], vec![]));
-            let s7 = sub.add_step(Justification(p("exists foo mortal(foo)"), RuleM::ExistsIntro, vec![i(s6.clone())], vec![]));
-            (s5, s6, s7)
+        .step("exists x q(x)", RuleM::ExistsElim, deps![1; 7..=12])
+        .step("exists x r(x)", RuleM::ExistsElim, deps![1; 7..=12])
+        .step("r(a)", RuleM::ExistsElim, deps![1; 7..=12])
+        .subproof(|b| {
+            b.premise("man(socrates)")
+                .step("man(socrates) → mortal(socrates)", RuleM::ForallElim, deps![4])
+                .step("mortal(socrates)", RuleM::ImpElim, deps![16, 17])
+                .step("exists foo mortal(foo)", RuleM::ExistsIntro, deps![18])
         })
-        .unwrap();
-    let s8 = prf.add_step(Justification(p("exists foo mortal(foo)"), RuleM::ExistsElim, vec![i(s2)], vec![s3.clone()]));
-
-    let t1 = prf.add_step(Justification(p("p(a) -> r(a)"), RuleM::ForallElim, vec![i(r3.clone())], vec![]));
-    let t2 = prf.add_subproof();
-    let (t4, t5) = prf
-        .with_mut_subproof(&t2, |sub| {
-            let t3 = sub.add_premise(p("p(a)"));
-            let t4 = sub.add_step(Justification(p("r(a)"), RuleM::ImpElim, vec![i(t1.clone()), i(t3)], vec![]));
-            let t5 = sub.add_step(Justification(p("exists x r(x)"), RuleM::ExistsIntro, vec![i(t4.clone())], vec![]));
-            (t4, t5)
+        .step("exists foo mortal(foo)", RuleM::ExistsElim, deps![5; 16..=19])
+        .step("p(a) -> r(a)", RuleM::ForallElim, deps![3])
+        .subproof(|b| {
+            b.premise("p(a)")
+                .step("r(a)", RuleM::ImpElim, deps![21, 22])
+                .step("exists x r(x)", RuleM::ExistsIntro, deps![23])
         })
-        .unwrap();
-    let t6 = prf.add_step(Justification(p("exists x r(x)"), RuleM::ExistsElim, vec![i(r1.clone())], vec![t2.clone()]));
-
-    let u1 = prf.add_subproof();
-    let u2 = prf.add_premise(p("forall c forall d p(c) -> s(d)"));
-    let (u4, u5, u6) = prf
-        .with_mut_subproof(&u1, |sub| {
-            let u3 = sub.add_premise(p("p(a)"));
-            let u4 = sub.add_step(Justification(p("forall d p(a) -> s(d)"), RuleM::ForallElim, vec![i(u2.clone())], vec![]));
-            let u5 = sub.add_step(Justification(p("p(a) -> s(foo)"), RuleM::ForallElim, vec![i(u4.clone())], vec![])); // TODO: generalized forall?
-            let u6 = sub.add_step(Justification(p("s(foo)"), RuleM::ImpElim, vec![i(u3), i(u5.clone())], vec![]));
-            (u4, u5, u6)
+        .step("exists x r(x)", RuleM::ExistsElim, deps![1; 22..=24])
+        .subproof(|b| {
+            b.premise("p(a)")
+                .step("forall d p(a) -> s(d)", RuleM::ForallElim, deps![6])
+                .step("p(a) -> s(foo)", RuleM::ForallElim, deps![27]) // TODO: generalized forall?
+                .step("s(foo)", RuleM::ImpElim, deps![26, 28])
         })
+        .step("s(foo)", RuleM::ExistsElim, deps![1; 26..=29])
+        .build_with_lines()
         .unwrap();
-    let u7 = prf.add_step(Justification(p("s(foo)"), RuleM::ExistsElim, vec![i(r1)], vec![u1.clone()]));
-
-    (prf, vec![i(r6), i(r7), i(r8), i(r9), i(r10), i(r12), i(s5), i(s6), i(s7), i(s8), i(t1), i(t4), i(t5), i(u4), i(u5), i(u6), i(u7)], vec![i(r11), i(r13), i(t6)])
+    let l = |n: usize| lines[n - 1].clone();
+    (prf, vec![l(8), l(9), l(10), l(11), l(12), l(14), l(17), l(18), l(19), l(20), l(21), l(23), l(24), l(27), l(28), l(29), l(30)], vec![l(13), l(15), l(25)])
 }
 
 pub fn test_commutation<P: Proof>() -> (P, Vec<PjRef<P>>, Vec<PjRef<P>>) {
-    use self::coproduct_inject as i;
-    use crate::parser::parse_unwrap as p;
-    let mut prf = P::new();
-    let r1 = prf.add_premise(p("(A & B & C) | (P & Q & R & S)"));
-    let r2 = prf.add_premise(p("(a <-> b <-> c <-> d) === (bar -> quux)"));
-    let r3 = prf.add_step(Justification(p("(Q & R & S & P) | (C & A & B)"), RuleM::Commutation, vec![i(r1.clone())], vec![]));
-    let r4 = prf.add_step(Justification(p("(A & B & C) | (P & Q & R & S)"), RuleM::Commutation, vec![i(r1.clone())], vec![]));
-    let r5 = prf.add_step(Justification(p("(A & B & C) & (P & Q & R & S)"), RuleM::Commutation, vec![i(r1)], vec![]));
-    let r6 = prf.add_step(Justification(p("(a <-> b <-> c <-> d) === (bar -> quux)"), RuleM::Commutation, vec![i(r2.clone())], vec![]));
-    let r7 = prf.add_step(Justification(p("(d <-> a <-> b <-> c) === (bar -> quux)"), RuleM::Commutation, vec![i(r2.clone())], vec![]));
-    let r8 = prf.add_step(Justification(p("(bar -> quux) === (d <-> a <-> b <-> c)"), RuleM::Commutation, vec![i(r2.clone())], vec![]));
-    let r9 = prf.add_step(Justification(p("(a <-> b <-> c <-> d) === (quux -> bar)"), RuleM::Commutation, vec![i(r2)], vec![]));
-    (prf, vec![i(r3), i(r4), i(r6), i(r7), i(r8)], vec![i(r5), i(r9)])
+    let (prf, lines) = ProofBuilder::new()
+        .premise("(A & B & C) | (P & Q & R & S)")
+        .premise("(a <-> b <-> c <-> d) === (bar -> quux)")
+        .step("(Q & R & S & P) | (C & A & B)", RuleM::Commutation, deps![1])
+        .step("(A & B & C) | (P & Q & R & S)", RuleM::Commutation, deps![1])
+        .step("(A & B & C) & (P & Q & R & S)", RuleM::Commutation, deps![1])
+        .step("(a <-> b <-> c <-> d) === (bar -> quux)", RuleM::Commutation, deps![2])
+        .step("(d <-> a <-> b <-> c) === (bar -> quux)", RuleM::Commutation, deps![2])
+        .step("(bar -> quux) === (d <-> a <-> b <-> c)", RuleM::Commutation, deps![2])
+        .step("(a <-> b <-> c <-> d) === (quux -> bar)", RuleM::Commutation, deps![2])
+        .build_with_lines()
+        .unwrap();
+    let l = |n: usize| lines[n - 1].clone();
+    (prf, vec![l(3), l(4), l(6), l(7), l(8)], vec![l(5), l(9)])
 }
 
 pub fn test_association<P: Proof>() -> (P, Vec<PjRef<P>>, Vec<PjRef<P>>) {
-    use self::coproduct_inject as i;
-    use crate::parser::parse_unwrap as p;
-    let mut prf = P::new();
-    let r1 = prf.add_premise(p("(A & B & C) | (P & Q & R & S) | (U <-> V <-> W)"));
-    let r2 = prf.add_step(Justification(p("(A & (B & C)) | ((((P & Q) & (R & S)) | ((U <-> V) <-> W)))"), RuleM::Association, vec![i(r1.clone())], vec![]));
-    let r3 = prf.add_step(Justification(p("(A & B & C) | (P & Q & R & S) | (U | V | W)"), RuleM::Association, vec![i(r1)], vec![]));
-    (prf, vec![i(r2)], vec![i(r3)])
+    let (prf, lines) = ProofBuilder::new()
+        .premise("(A & B & C) | (P & Q & R & S) | (U <-> V <-> W)")
+        .step("(A & (B & C)) | ((((P & Q) & (R & S)) | ((U <-> V) <-> W)))", RuleM::Association, deps![1])
+        .step("(A & B & C) | (P & Q & R & S) | (U | V | W)", RuleM::Association, deps![1])
+        .build_with_lines()
+        .unwrap();
+    let l = |n: usize| lines[n - 1].clone();
+    (prf, vec![l(2)], vec![l(3)])
 }
 
 pub fn test_demorgan<P: Proof>() -> (P, Vec<PjRef<P>>, Vec<PjRef<P>>) {
-    use self::coproduct_inject as i;
-    use crate::parser::parse_unwrap as p;
-    let mut prf = P::new();
-
-    let r1 = prf.add_premise(p("~(A & B)"));
-    let r2 = prf.add_premise(p("~(A | B)"));
-    let r3 = prf.add_premise(p("~(A | B | C)"));
-    let r4 = prf.add_premise(p("~~(A | B)"));
-    let r5 = prf.add_premise(p("~(~(A & B) | ~(C | D))"));
-    let r6 = prf.add_premise(p("~~~~~~~~~~~~~~~~(A & B)"));
-    let r7 = prf.add_step(Justification(p("~A | ~B"), RuleM::DeMorgan, vec![i(r1.clone())], vec![]));
-    let r8 = prf.add_step(Justification(p("~(A | B)"), RuleM::DeMorgan, vec![i(r1.clone())], vec![]));
-    let r9 = prf.add_step(Justification(p("~(~A & ~B)"), RuleM::DeMorgan, vec![i(r1.clone())], vec![]));
-    let r10 = prf.add_step(Justification(p("~(~A | ~B)"), RuleM::DeMorgan, vec![i(r1)], vec![]));
-    let r11 = prf.add_step(Justification(p("~A & ~B"), RuleM::DeMorgan, vec![i(r2.clone())], vec![]));
-    let r12 = prf.add_step(Justification(p("~(A & B)"), RuleM::DeMorgan, vec![i(r2.clone())], vec![]));
-    let r13 = prf.add_step(Justification(p("~(~A | ~B)"), RuleM::DeMorgan, vec![i(r2.clone())], vec![]));
-    let r14 = prf.add_step(Justification(p("~(~A & ~B)"), RuleM::DeMorgan, vec![i(r2)], vec![]));
-    let r15 = prf.add_step(Justification(p("~A & ~B & ~C"), RuleM::DeMorgan, vec![i(r3.clone())], vec![]));
-    let r16 = prf.add_step(Justification(p("~(A & B & C)"), RuleM::DeMorgan, vec![i(r3.clone())], vec![]));
-    let r17 = prf.add_step(Justification(p("~A | ~B | ~C"), RuleM::DeMorgan, vec![i(r3)], vec![]));
-    let r18 = prf.add_step(Justification(p("~~A | ~~B"), RuleM::DeMorgan, vec![i(r4.clone())], vec![]));
-    let r19 = prf.add_step(Justification(p("~(~A & ~B)"), RuleM::DeMorgan, vec![i(r4)], vec![]));
-    let r20 = prf.add_step(Justification(p("~((~A | ~B) | ~(C | D))"), RuleM::DeMorgan, vec![i(r5.clone())], vec![]));
-    let r21 = prf.add_step(Justification(p("~(~(A & B) | (~C & ~D))"), RuleM::DeMorgan, vec![i(r5.clone())], vec![]));
-    let r22 = prf.add_step(Justification(p("~((~A | ~B) | (~C & ~D))"), RuleM::DeMorgan, vec![i(r5.clone())], vec![]));
-    let r23 = prf.add_step(Justification(p("~(~A | ~B) & ~(~C & ~D)"), RuleM::DeMorgan, vec![i(r5.clone())], vec![]));
-    let r24 = prf.add_step(Justification(p("(~~A & ~~B) & (~~C | ~~D)"), RuleM::DeMorgan, vec![i(r5.clone())], vec![]));
-    let r25 = prf.add_step(Justification(p("(~~(A & B) & ~~(C | D))"), RuleM::DeMorgan, vec![i(r5.clone())], vec![]));
-    let r26 = prf.add_step(Justification(p("~~((A & B) & (C | D))"), RuleM::DeMorgan, vec![i(r5.clone())], vec![]));
-    let r27 = prf.add_step(Justification(p("~((A | B) | (C & D))"), RuleM::DeMorgan, vec![i(r5.clone())], vec![]));
-    let r28 = prf.add_step(Justification(p("~~((A & B) | (C | D))"), RuleM::DeMorgan, vec![i(r5)], vec![]));
-    let r29 = prf.add_step(Justification(p("~~~~~~~~~~~~~~~~A & ~~~~~~~~~~~~~~~~B"), RuleM::DeMorgan, vec![i(r6.clone())], vec![]));
-    let r30 = prf.add_step(Justification(p("~~~~~~~~~~~~~~~~A | ~~~~~~~~~~~~~~~~B"), RuleM::DeMorgan, vec![i(r6)], vec![]));
-
-    (prf, vec![i(r7), i(r11), i(r15), i(r18), i(r19), i(r20), i(r21), i(r22), i(r23), i(r24), i(r25), i(r26), i(r29)], vec![i(r8), i(r9), i(r10), i(r12), i(r13), i(r14), i(r16), i(r17), i(r27), i(r28), i(r30)])
+    let (prf, lines) = ProofBuilder::new()
+        .premise("~(A & B)")
+        .premise("~(A | B)")
+        .premise("~(A | B | C)")
+        .premise("~~(A | B)")
+        .premise("~(~(A & B) | ~(C | D))")
+        .premise("~~~~~~~~~~~~~~~~(A & B)")
+        .step("~A | ~B", RuleM::DeMorgan, deps![1])
+        .step("~(A | B)", RuleM::DeMorgan, deps![1])
+        .step("~(~A & ~B)", RuleM::DeMorgan, deps![1])
+        .step("~(~A | ~B)", RuleM::DeMorgan, deps![1])
+        .step("~A & ~B", RuleM::DeMorgan, deps![2])
+        .step("~(A & B)", RuleM::DeMorgan, deps![2])
+        .step("~(~A | ~B)", RuleM::DeMorgan, deps![2])
+        .step("~(~A & ~B)", RuleM::DeMorgan, deps![2])
+        .step("~A & ~B & ~C", RuleM::DeMorgan, deps![3])
+        .step("~(A & B & C)", RuleM::DeMorgan, deps![3])
+        .step("~A | ~B | ~C", RuleM::DeMorgan, deps![3])
+        .step("~~A | ~~B", RuleM::DeMorgan, deps![4])
+        .step("~(~A & ~B)", RuleM::DeMorgan, deps![4])
+        .step("~((~A | ~B) | ~(C | D))", RuleM::DeMorgan, deps![5])
+        .step("~(~(A & B) | (~C & ~D))", RuleM::DeMorgan, deps![5])
+        .step("~((~A | ~B) | (~C & ~D))", RuleM::DeMorgan, deps![5])
+        .step("~(~A | ~B) & ~(~C & ~D)", RuleM::DeMorgan, deps![5])
+        .step("(~~A & ~~B) & (~~C | ~~D)", RuleM::DeMorgan, deps![5])
+        .step("(~~(A & B) & ~~(C | D))", RuleM::DeMorgan, deps![5])
+        .step("~~((A & B) & (C | D))", RuleM::DeMorgan, deps![5])
+        .step("~((A | B) | (C & D))", RuleM::DeMorgan, deps![5])
+        .step("~~((A & B) | (C | D))", RuleM::DeMorgan, deps![5])
+        .step("~~~~~~~~~~~~~~~~A & ~~~~~~~~~~~~~~~~B", RuleM::DeMorgan, deps![6])
+        .step("~~~~~~~~~~~~~~~~A | ~~~~~~~~~~~~~~~~B", RuleM::DeMorgan, deps![6])
+        .build_with_lines()
+        .unwrap();
+    let l = |n: usize| lines[n - 1].clone();
+    (prf, vec![l(7), l(11), l(15), l(18), l(19), l(20), l(21), l(22), l(23), l(24), l(25), l(26), l(29)], vec![l(8), l(9), l(10), l(12), l(13), l(14), l(16), l(17), l(27), l(28), l(30)])
 }
 
 pub fn test_idempotence<P: Proof>() -> (P, Vec<PjRef<P>>, Vec<PjRef<P>>) {
-    use self::coproduct_inject as i;
-    use crate::parser::parse_unwrap as p;
-    let mut prf = P::new();
-
-    let r1 = prf.add_premise(p("A & A"));
-    let r2 = prf.add_premise(p("A | A"));
-    let r3 = prf.add_premise(p("A & A & A & A & A"));
-    let r4 = prf.add_premise(p("(A | A) & (A | A)"));
-    let r5 = prf.add_premise(p("(A | A) & (B | B)"));
-    let r6 = prf.add_premise(p("(A | (A | A)) & ((B & B) | B)"));
-    let r7 = prf.add_premise(p("A & A & B"));
-
-    let r8 = prf.add_step(Justification(p("A"), RuleM::Idempotence, vec![i(r1)], vec![]));
-    let r9 = prf.add_step(Justification(p("A"), RuleM::Idempotence, vec![i(r2)], vec![]));
-    let r10 = prf.add_step(Justification(p("A"), RuleM::Idempotence, vec![i(r3)], vec![]));
-    let r11 = prf.add_step(Justification(p("A"), RuleM::Idempotence, vec![i(r4)], vec![]));
-    let r12 = prf.add_step(Justification(p("A & B"), RuleM::Idempotence, vec![i(r5.clone())], vec![]));
-    let r13 = prf.add_step(Justification(p("A"), RuleM::Idempotence, vec![i(r5.clone())], vec![]));
-    let r14 = prf.add_step(Justification(p("B"), RuleM::Idempotence, vec![i(r5.clone())], vec![]));
-    let r15 = prf.add_step(Justification(p("A | B"), RuleM::Idempotence, vec![i(r5)], vec![]));
-    let r16 = prf.add_step(Justification(p("A & B"), RuleM::Idempotence, vec![i(r6.clone())], vec![]));
-    let r17 = prf.add_step(Justification(p("(A | A) & B"), RuleM::Idempotence, vec![i(r6.clone())], vec![]));
-    let r18 = prf.add_step(Justification(p("A & (B | B)"), RuleM::Idempotence, vec![i(r6.clone())], vec![]));
-    let r19 = prf.add_step(Justification(p("A"), RuleM::Idempotence, vec![i(r6)], vec![]));
-    let r20 = prf.add_step(Justification(p("A"), RuleM::Idempotence, vec![i(r7.clone())], vec![]));
-    let r21 = prf.add_step(Justification(p("B"), RuleM::Idempotence, vec![i(r7.clone())], vec![]));
-    //TODO: Should we make this valid? Currently it is invalid as all args must be equal
-    let r22 = prf.add_step(Justification(p("A & B"), RuleM::Idempotence, vec![i(r7)], vec![]));
-
-    (prf, vec![i(r8), i(r9), i(r10), i(r11), i(r12), i(r16), i(r17), i(r18)], vec![i(r13), i(r14), i(r15), i(r19), i(r20), i(r21), i(r22)])
+    let (prf, lines) = ProofBuilder::new()
+        .premise("A & A")
+        .premise("A | A")
+        .premise("A & A & A & A & A")
+        .premise("(A | A) & (A | A)")
+        .premise("(A | A) & (B | B)")
+        .premise("(A | (A | A)) & ((B & B) | B)")
+        .premise("A & A & B")
+        .step("A", RuleM::Idempotence, deps![1])
+        .step("A", RuleM::Idempotence, deps![2])
+        .step("A", RuleM::Idempotence, deps![3])
+        .step("A", RuleM::Idempotence, deps![4])
+        .step("A & B", RuleM::Idempotence, deps![5])
+        .step("A", RuleM::Idempotence, deps![5])
+        .step("B", RuleM::Idempotence, deps![5])
+        .step("A | B", RuleM::Idempotence, deps![5])
+        .step("A & B", RuleM::Idempotence, deps![6])
+        .step("(A | A) & B", RuleM::Idempotence, deps![6])
+        .step("A & (B | B)", RuleM::Idempotence, deps![6])
+        .step("A", RuleM::Idempotence, deps![6])
+        .step("A", RuleM::Idempotence, deps![7])
+        .step("B", RuleM::Idempotence, deps![7])
+        //TODO: Should we make this valid? Currently it is invalid as all args must be equal
+        .step("A & B", RuleM::Idempotence, deps![7])
+        .build_with_lines()
+        .unwrap();
+    let l = |n: usize| lines[n - 1].clone();
+    (prf, vec![l(8), l(9), l(10), l(11), l(12), l(16), l(17), l(18)], vec![l(13), l(14), l(15), l(19), l(20), l(21), l(22)])
 }
 
 pub fn test_doublenegation<P: Proof>() -> (P, Vec<PjRef<P>>, Vec<PjRef<P>>) {
-    use self::coproduct_inject as i;
-    use crate::parser::parse_unwrap as p;
-    let mut prf = P::new();
-
-    let r1 = prf.add_premise(p("~~A & A"));
-    let r2 = prf.add_premise(p("P & Q & ~~~~(~~R | S)"));
-    let r3 = prf.add_premise(p("~P -> Q"));
-
-    let r4 = prf.add_step(Justification(p("A & A"), RuleM::DoubleNegation, vec![i(r1.clone())], vec![]));
-    let r5 = prf.add_step(Justification(p("A & ~~~~A"), RuleM::DoubleNegation, vec![i(r1.clone())], vec![]));
-    let r6 = prf.add_step(Justification(p("~~P & Q & ~~~~(R | ~~~~S)"), RuleM::DoubleNegation, vec![i(r2.clone())], vec![]));
-    let r7 = prf.add_step(Justification(p("~~P & Q & (R | ~~~~S)"), RuleM::DoubleNegation, vec![i(r2.clone())], vec![]));
-    let r8 = prf.add_step(Justification(p("P & Q & (R | S)"), RuleM::DoubleNegation, vec![i(r2)], vec![]));
-    let r9 = prf.add_step(Justification(p("~~~P -> ~~~~Q"), RuleM::DoubleNegation, vec![i(r3.clone())], vec![]));
-
-    let r10 = prf.add_step(Justification(p("~A & A"), RuleM::DoubleNegation, vec![i(r1)], vec![]));
-    let r11 = prf.add_step(Justification(p("~~~~P -> ~~~Q"), RuleM::DoubleNegation, vec![i(r3)], vec![]));
-
-    (prf, vec![i(r4), i(r5), i(r6), i(r7), i(r8), i(r9)], vec![i(r10), i(r11)])
+    let (prf, lines) = ProofBuilder::new()
+        .premise("~~A & A")
+        .premise("P & Q & ~~~~(~~R | S)")
+        .premise("~P -> Q")
+        .premise("forall x (P(x) -> ~~~Q(x))")
+        .step("A & A", RuleM::DoubleNegation, deps![1])
+        .step("A & ~~~~A", RuleM::DoubleNegation, deps![1])
+        .step("~~P & Q & ~~~~(R | ~~~~S)", RuleM::DoubleNegation, deps![2])
+        .step("~~P & Q & (R | ~~~~S)", RuleM::DoubleNegation, deps![2])
+        .step("P & Q & (R | S)", RuleM::DoubleNegation, deps![2])
+        .step("~~~P -> ~~~~Q", RuleM::DoubleNegation, deps![3])
+        .step("~A & A", RuleM::DoubleNegation, deps![1])
+        .step("~~~~P -> ~~~Q", RuleM::DoubleNegation, deps![3])
+        .step("forall x (~~P(x) -> ~Q(x))", RuleM::DoubleNegation, deps![4])
+        .step("~~forall x ~~(P(x) -> ~~~~~Q(x))", RuleM::DoubleNegation, deps![4])
+        .step("~P -> Q", RuleM::DoubleNegation, deps![3])
+        .step("forall x (P(x) -> Q(x))", RuleM::DoubleNegation, deps![4])
+        .step("forall x (~P(x) -> ~Q(x))", RuleM::DoubleNegation, deps![4])
+        .build_with_lines()
+        .unwrap();
+    let l = |n: usize| lines[n - 1].clone();
+    (prf, vec![l(5), l(6), l(7), l(8), l(9), l(10), l(13), l(14), l(15)], vec![l(11), l(12), l(16), l(17)])
 }
 
 pub fn test_distribution<P: Proof>() -> (P, Vec<PjRef<P>>, Vec<PjRef<P>>) {
-    use self::coproduct_inject as i;
-    use crate::parser::parse_unwrap as p;
-    let mut prf = P::new();
-
-    let p1 = prf.add_premise(p("A & (B | C)"));
-    let p2 = prf.add_premise(p("(B & A) | (C & A)"));
-
-    let r1 = prf.add_step(Justification(p("(A & B) | (A & C)"), RuleM::Distribution, vec![i(p1)], vec![]));
-    let r2 = prf.add_step(Justification(p("A & (B | C)"), RuleM::Distribution, vec![i(p2.clone())], vec![]));
-    let r3 = prf.add_step(Justification(p("(B | C) & A"), RuleM::Distribution, vec![i(p2.clone())], vec![]));
-
-    let r4 = prf.add_step(Justification(p("A | (B & C)"), RuleM::Distribution, vec![i(p2)], vec![]));
-
-    (prf, vec![i(r1), i(r2), i(r3)], vec![i(r4)])
+    let (prf, lines) = ProofBuilder::new()
+        .premise("A & (B | C)")
+        .premise("(B & A) | (C & A)")
+        .step("(A & B) | (A & C)", RuleM::Distribution, deps![1])
+        .step("A & (B | C)", RuleM::Distribution, deps![2])
+        .step("(B | C) & A", RuleM::Distribution, deps![2])
+        .step("A | (B & C)", RuleM::Distribution, deps![2])
+        .build_with_lines()
+        .unwrap();
+    let l = |n: usize| lines[n - 1].clone();
+    (prf, vec![l(3), l(4), l(5)], vec![l(6)])
 }
 
 pub fn test_complement<P: Proof>() -> (P, Vec<PjRef<P>>, Vec<PjRef<P>>) {
-    use self::coproduct_inject as i;
-    use crate::parser::parse_unwrap as p;
-    let mut prf = P::new();
-
-    let r1 = prf.add_premise(p("A & ~A"));
-    let r2 = prf.add_premise(p("~A & A"));
-    let r3 = prf.add_premise(p("A | ~A"));
-    let r4 = prf.add_premise(p("~A | A"));
-    let r5 = prf.add_premise(p("~(forall A A) | (forall B B)"));
-    let r6 = prf.add_premise(p("~(forall A A) & (forall B B)"));
-    let r19 = prf.add_premise(p("(A -> A) & (B <-> B) & (C <-> ~C) & (~D <-> D)"));
-    let r7 = prf.add_step(Justification(p("_|_"), RuleM::Complement, vec![i(r1.clone())], vec![]));
-    let r8 = prf.add_step(Justification(p("^|^"), RuleM::Complement, vec![i(r1)], vec![]));
-    let r9 = prf.add_step(Justification(p("_|_"), RuleM::Complement, vec![i(r2.clone())], vec![]));
-    let r10 = prf.add_step(Justification(p("^|^"), RuleM::Complement, vec![i(r2)], vec![]));
-    let r11 = prf.add_step(Justification(p("_|_"), RuleM::Complement, vec![i(r3.clone())], vec![]));
-    let r12 = prf.add_step(Justification(p("^|^"), RuleM::Complement, vec![i(r3)], vec![]));
-    let r13 = prf.add_step(Justification(p("_|_"), RuleM::Complement, vec![i(r4.clone())], vec![]));
-    let r14 = prf.add_step(Justification(p("^|^"), RuleM::Complement, vec![i(r4)], vec![]));
-    let r15 = prf.add_step(Justification(p("_|_"), RuleM::Complement, vec![i(r5.clone())], vec![]));
-    let r16 = prf.add_step(Justification(p("^|^"), RuleM::Complement, vec![i(r5)], vec![]));
-    let r17 = prf.add_step(Justification(p("_|_"), RuleM::Complement, vec![i(r6.clone())], vec![]));
-    let r18 = prf.add_step(Justification(p("^|^"), RuleM::Complement, vec![i(r6)], vec![]));
-
-    let r20 = prf.add_step(Justification(p("^|^ & ^|^ & _|_ & _|_"), RuleM::CondComplement, vec![i(r19.clone())], vec![]));
-    let r21 = prf.add_step(Justification(p("^|^ & (B <-> B) & (C <-> ~C) & (~D <-> D)"), RuleM::CondComplement, vec![i(r19.clone())], vec![]));
-    let r22 = prf.add_step(Justification(p("(A -> A) & ^|^ & (C <-> ~C) & (~D <-> D)"), RuleM::CondComplement, vec![i(r19.clone())], vec![]));
-    let r23 = prf.add_step(Justification(p("(A -> A) & (B <-> B) & _|_ & (~D <-> D)"), RuleM::CondComplement, vec![i(r19.clone())], vec![]));
-    let r24 = prf.add_step(Justification(p("(A -> A) & (B <-> B) & (C <-> ~C) & _|_"), RuleM::CondComplement, vec![i(r19.clone())], vec![]));
-    let r25 = prf.add_step(Justification(p("_|_ & _|_ & ^|^ & ^|^"), RuleM::CondComplement, vec![i(r19)], vec![]));
-
-    (prf, vec![i(r7), i(r9), i(r12), i(r14), i(r16), i(r17), i(r20), i(r21), i(r22), i(r23), i(r24)], vec![i(r8), i(r10), i(r11), i(r13), i(r15), i(r18), i(r25)])
+    let (prf, lines) = ProofBuilder::new()
+        .premise("A & ~A")
+        .premise("~A & A")
+        .premise("A | ~A")
+        .premise("~A | A")
+        .premise("~(forall A A) | (forall B B)")
+        .premise("~(forall A A) & (forall B B)")
+        .premise("(A -> A) & (B <-> B) & (C <-> ~C) & (~D <-> D)")
+        .step("_|_", RuleM::Complement, deps![1])
+        .step("^|^", RuleM::Complement, deps![1])
+        .step("_|_", RuleM::Complement, deps![2])
+        .step("^|^", RuleM::Complement, deps![2])
+        .step("_|_", RuleM::Complement, deps![3])
+        .step("^|^", RuleM::Complement, deps![3])
+        .step("_|_", RuleM::Complement, deps![4])
+        .step("^|^", RuleM::Complement, deps![4])
+        .step("_|_", RuleM::Complement, deps![5])
+        .step("^|^", RuleM::Complement, deps![5])
+        .step("_|_", RuleM::Complement, deps![6])
+        .step("^|^", RuleM::Complement, deps![6])
+        .step("^|^ & ^|^ & _|_ & _|_", RuleM::CondComplement, deps![7])
+        .step("^|^ & (B <-> B) & (C <-> ~C) & (~D <-> D)", RuleM::CondComplement, deps![7])
+        .step("(A -> A) & ^|^ & (C <-> ~C) & (~D <-> D)", RuleM::CondComplement, deps![7])
+        .step("(A -> A) & (B <-> B) & _|_ & (~D <-> D)", RuleM::CondComplement, deps![7])
+        .step("(A -> A) & (B <-> B) & (C <-> ~C) & _|_", RuleM::CondComplement, deps![7])
+        .step("_|_ & _|_ & ^|^ & ^|^", RuleM::CondComplement, deps![7])
+        .build_with_lines()
+        .unwrap();
+    let l = |n: usize| lines[n - 1].clone();
+    (prf, vec![l(8), l(10), l(13), l(15), l(17), l(18), l(20), l(21), l(22), l(23), l(24)], vec![l(9), l(11), l(12), l(14), l(16), l(19), l(25)])
 }
 
 pub fn test_identity<P: Proof>() -> (P, Vec<PjRef<P>>, Vec<PjRef<P>>) {
-    use self::coproduct_inject as i;
-    use crate::parser::parse_unwrap as p;
-    let mut prf = P::new();
-
-    let r1 = prf.add_premise(p("A & ^|^"));
-    let r2 = prf.add_premise(p("^|^ & A"));
-    let r3 = prf.add_premise(p("A | _|_"));
-    let r4 = prf.add_premise(p("_|_ | A"));
-    let r13 = prf.add_premise(p("(A -> _|_) & (^|^ -> B)"));
-    let r17 = prf.add_premise(p("(A <-> _|_) & (^|^ <-> B)"));
-
-    let r5 = prf.add_step(Justification(p("A"), RuleM::Identity, vec![i(r1.clone())], vec![]));
-    let r6 = prf.add_step(Justification(p("^|^"), RuleM::Identity, vec![i(r1)], vec![]));
-    let r7 = prf.add_step(Justification(p("A"), RuleM::Identity, vec![i(r2.clone())], vec![]));
-    let r8 = prf.add_step(Justification(p("^|^"), RuleM::Identity, vec![i(r2)], vec![]));
-    let r9 = prf.add_step(Justification(p("A"), RuleM::Identity, vec![i(r3.clone())], vec![]));
-    let r10 = prf.add_step(Justification(p("_|_"), RuleM::Identity, vec![i(r3)], vec![]));
-    let r11 = prf.add_step(Justification(p("A"), RuleM::Identity, vec![i(r4.clone())], vec![]));
-    let r12 = prf.add_step(Justification(p("_|_"), RuleM::Identity, vec![i(r4)], vec![]));
-
-    let r14 = prf.add_step(Justification(p("~A & B"), RuleM::CondIdentity, vec![i(r13.clone())], vec![]));
-    let r15 = prf.add_step(Justification(p("A & B"), RuleM::CondIdentity, vec![i(r13.clone())], vec![]));
-    let r16 = prf.add_step(Justification(p("~A & ~B"), RuleM::CondIdentity, vec![i(r13)], vec![]));
-    let r18 = prf.add_step(Justification(p("~A & B"), RuleM::CondIdentity, vec![i(r17.clone())], vec![]));
-    let r19 = prf.add_step(Justification(p("A & B"), RuleM::CondIdentity, vec![i(r17)], vec![]));
-
-    (prf, vec![i(r5), i(r7), i(r9), i(r11), i(r14), i(r18)], vec![i(r6), i(r8), i(r10), i(r12), i(r15), i(r16), i(r19)])
+    let (prf, lines) = ProofBuilder::new()
+        .premise("A & ^|^")
+        .premise("^|^ & A")
+        .premise("A | _|_")
+        .premise("_|_ | A")
+        .premise("(A -> _|_) & (^|^ -> B)")
+        .premise("(A <-> _|_) & (^|^ <-> B)")
+        .step("A", RuleM::Identity, deps![1])
+        .step("^|^", RuleM::Identity, deps![1])
+        .step("A", RuleM::Identity, deps![2])
+        .step("^|^", RuleM::Identity, deps![2])
+        .step("A", RuleM::Identity, deps![3])
+        .step("_|_", RuleM::Identity, deps![3])
+        .step("A", RuleM::Identity, deps![4])
+        .step("_|_", RuleM::Identity, deps![4])
+        .step("~A & B", RuleM::CondIdentity, deps![5])
+        .step("A & B", RuleM::CondIdentity, deps![5])
+        .step("~A & ~B", RuleM::CondIdentity, deps![5])
+        .step("~A & B", RuleM::CondIdentity, deps![6])
+        .step("A & B", RuleM::CondIdentity, deps![6])
+        .build_with_lines()
+        .unwrap();
+    let l = |n: usize| lines[n - 1].clone();
+    (prf, vec![l(7), l(9), l(11), l(13), l(15), l(18)], vec![l(8), l(10), l(12), l(14), l(16), l(17), l(19)])
 }
 
 pub fn test_annihilation<P: Proof>() -> (P, Vec<PjRef<P>>, Vec<PjRef<P>>) {
-    use self::coproduct_inject as i;
-    use crate::parser::parse_unwrap as p;
-    let mut prf = P::new();
-
-    let r1 = prf.add_premise(p("A & _|_"));
-    let r2 = prf.add_premise(p("_|_ & A"));
-    let r3 = prf.add_premise(p("A | ^|^"));
-    let r4 = prf.add_premise(p("^|^ | A"));
-    let r13 = prf.add_premise(p("(A -> ^|^) & (_|_ -> B)"));
-    let r15 = prf.add_premise(p("(A -> _|_)"));
-    let r5 = prf.add_step(Justification(p("_|_"), RuleM::Annihilation, vec![i(r1.clone())], vec![]));
-    let r6 = prf.add_step(Justification(p("A"), RuleM::Annihilation, vec![i(r1)], vec![]));
-    let r7 = prf.add_step(Justification(p("_|_"), RuleM::Annihilation, vec![i(r2.clone())], vec![]));
-    let r8 = prf.add_step(Justification(p("A"), RuleM::Annihilation, vec![i(r2)], vec![]));
-    let r9 = prf.add_step(Justification(p("^|^"), RuleM::Annihilation, vec![i(r3.clone())], vec![]));
-    let r10 = prf.add_step(Justification(p("A"), RuleM::Annihilation, vec![i(r3)], vec![]));
-    let r11 = prf.add_step(Justification(p("^|^"), RuleM::Annihilation, vec![i(r4.clone())], vec![]));
-    let r12 = prf.add_step(Justification(p("A"), RuleM::Annihilation, vec![i(r4)], vec![]));
-
-    let r14 = prf.add_step(Justification(p("^|^ & ^|^"), RuleM::CondAnnihilation, vec![i(r13)], vec![]));
-    let r16 = prf.add_step(Justification(p("^|^"), RuleM::CondAnnihilation, vec![i(r15)], vec![]));
-
-    (prf, vec![i(r5), i(r7), i(r9), i(r11), i(r14)], vec![i(r6), i(r8), i(r10), i(r12), i(r16)])
+    let (prf, lines) = ProofBuilder::new()
+        .premise("A & _|_")
+        .premise("_|_ & A")
+        .premise("A | ^|^")
+        .premise("^|^ | A")
+        .premise("(A -> ^|^) & (_|_ -> B)")
+        .premise("(A -> _|_)")
+        .step("_|_", RuleM::Annihilation, deps![1])
+        .step("A", RuleM::Annihilation, deps![1])
+        .step("_|_", RuleM::Annihilation, deps![2])
+        .step("A", RuleM::Annihilation, deps![2])
+        .step("^|^", RuleM::Annihilation, deps![3])
+        .step("A", RuleM::Annihilation, deps![3])
+        .step("^|^", RuleM::Annihilation, deps![4])
+        .step("A", RuleM::Annihilation, deps![4])
+        .step("^|^ & ^|^", RuleM::CondAnnihilation, deps![5])
+        .step("^|^", RuleM::CondAnnihilation, deps![6])
+        .build_with_lines()
+        .unwrap();
+    let l = |n: usize| lines[n - 1].clone();
+    (prf, vec![l(7), l(9), l(11), l(13), l(15)], vec![l(8), l(10), l(12), l(14), l(16)])
 }
 
 pub fn test_inverse<P: Proof>() -> (P, Vec<PjRef<P>>, Vec<PjRef<P>>) {
-    use self::coproduct_inject as i;
-    use crate::parser::parse_unwrap as p;
-    let mut prf = P::new();
-
-    let r1 = prf.add_premise(p("~_|_"));
-    let r2 = prf.add_premise(p("~^|^"));
-    let r3 = prf.add_premise(p("~~_|_"));
-    let r4 = prf.add_premise(p("~~^|^"));
-    let r5 = prf.add_step(Justification(p("_|_"), RuleM::Inverse, vec![i(r1.clone())], vec![]));
-    let r6 = prf.add_step(Justification(p("^|^"), RuleM::Inverse, vec![i(r1)], vec![]));
-    let r7 = prf.add_step(Justification(p("^|^"), RuleM::Inverse, vec![i(r2.clone())], vec![]));
-    let r8 = prf.add_step(Justification(p("_|_"), RuleM::Inverse, vec![i(r2)], vec![]));
-    let r9 = prf.add_step(Justification(p("_|_"), RuleM::Inverse, vec![i(r3.clone())], vec![]));
-    let r10 = prf.add_step(Justification(p("^|^"), RuleM::Inverse, vec![i(r3)], vec![]));
-    let r11 = prf.add_step(Justification(p("^|^"), RuleM::Inverse, vec![i(r4.clone())], vec![]));
-    let r12 = prf.add_step(Justification(p("_|_"), RuleM::Inverse, vec![i(r4)], vec![]));
-
-    (prf, vec![i(r6), i(r8), i(r9), i(r11)], vec![i(r5), i(r7), i(r10), i(r12)])
+    let (prf, lines) = ProofBuilder::new()
+        .premise("~_|_")
+        .premise("~^|^")
+        .premise("~~_|_")
+        .premise("~~^|^")
+        .step("_|_", RuleM::Inverse, deps![1])
+        .step("^|^", RuleM::Inverse, deps![1])
+        .step("^|^", RuleM::Inverse, deps![2])
+        .step("_|_", RuleM::Inverse, deps![2])
+        .step("_|_", RuleM::Inverse, deps![3])
+        .step("^|^", RuleM::Inverse, deps![3])
+        .step("^|^", RuleM::Inverse, deps![4])
+        .step("_|_", RuleM::Inverse, deps![4])
+        .build_with_lines()
+        .unwrap();
+    let l = |n: usize| lines[n - 1].clone();
+    (prf, vec![l(6), l(8), l(9), l(11)], vec![l(5), l(7), l(10), l(12)])
 }
 
 pub fn test_absorption<P: Proof>() -> (P, Vec<PjRef<P>>, Vec<PjRef<P>>) {
-    use self::coproduct_inject as i;
-    use crate::parser::parse_unwrap as p;
-    let mut prf = P::new();
-
-    let r1 = prf.add_premise(p("A & (A | B)"));
-    let r2 = prf.add_premise(p("A & (B | A)"));
-
-    let r3 = prf.add_premise(p("A | (A & B)"));
-    let r4 = prf.add_premise(p("A | (B & A)"));
-
-    let r5 = prf.add_premise(p("(A & B) | A"));
-    let r6 = prf.add_premise(p("(B & A) | A"));
-
-    let r7 = prf.add_premise(p("(A | B) & A"));
-    let r8 = prf.add_premise(p("(B | A) & A"));
-
-    let r9 = prf.add_premise(p("((A | B) & A) & (((A | B) & A) | C)"));
-
-    let r10 = prf.add_step(Justification(p("A"), RuleM::Absorption, vec![i(r1)], vec![]));
-    let r11 = prf.add_step(Justification(p("A"), RuleM::Absorption, vec![i(r2)], vec![]));
-    let r12 = prf.add_step(Justification(p("A"), RuleM::Absorption, vec![i(r3)], vec![]));
-    let r13 = prf.add_step(Justification(p("A"), RuleM::Absorption, vec![i(r4)], vec![]));
-    let r14 = prf.add_step(Justification(p("A"), RuleM::Absorption, vec![i(r5)], vec![]));
-    let r15 = prf.add_step(Justification(p("A"), RuleM::Absorption, vec![i(r6)], vec![]));
-    let r16 = prf.add_step(Justification(p("A"), RuleM::Absorption, vec![i(r7)], vec![]));
-    let r17 = prf.add_step(Justification(p("A"), RuleM::Absorption, vec![i(r8)], vec![]));
-    let r18 = prf.add_step(Justification(p("A"), RuleM::Absorption, vec![i(r9)], vec![]));
-
-    (prf, vec![i(r10), i(r11), i(r12), i(r13), i(r14), i(r15), i(r16), i(r17), i(r18)], vec![])
+    let (prf, lines) = ProofBuilder::new()
+        .premise("A & (A | B)")
+        .premise("A & (B | A)")
+        .premise("A | (A & B)")
+        .premise("A | (B & A)")
+        .premise("(A & B) | A")
+        .premise("(B & A) | A")
+        .premise("(A | B) & A")
+        .premise("(B | A) & A")
+        .premise("((A | B) & A) & (((A | B) & A) | C)")
+        .step("A", RuleM::Absorption, deps![1])
+        .step("A", RuleM::Absorption, deps![2])
+        .step("A", RuleM::Absorption, deps![3])
+        .step("A", RuleM::Absorption, deps![4])
+        .step("A", RuleM::Absorption, deps![5])
+        .step("A", RuleM::Absorption, deps![6])
+        .step("A", RuleM::Absorption, deps![7])
+        .step("A", RuleM::Absorption, deps![8])
+        .step("A", RuleM::Absorption, deps![9])
+        .build_with_lines()
+        .unwrap();
+    let l = |n: usize| lines[n - 1].clone();
+    (prf, vec![l(10), l(11), l(12), l(13), l(14), l(15), l(16), l(17), l(18)], vec![])
 }
 
 pub fn test_reduction<P: Proof>() -> (P, Vec<PjRef<P>>, Vec<PjRef<P>>) {
-    use self::coproduct_inject as i;
-    use crate::parser::parse_unwrap as p;
-    let mut prf = P::new();
-
-    let p1 = prf.add_premise(p("A & (~A | B)"));
-    let p2 = prf.add_premise(p("(~~A | B) & ~A"));
-    let p3 = prf.add_premise(p("(B & ~A) | A"));
-    let p4 = prf.add_premise(p("~B | (A & ~~B)"));
-    let p5 = prf.add_premise(p("(forall A (A & (~A | B))) | (~(forall A (A & (~A | B))) & C)"));
-    let p6 = prf.add_premise(p("B & (C | (~C & ~A))"));
-    let p7 = prf.add_premise(p("A | (~A & (~~A | B))"));
-    let p8 = prf.add_premise(p("D | (~A & (~~A | B))"));
-
-    let r1 = prf.add_step(Justification(p("A & B"), RuleM::Reduction, vec![i(p1.clone())], vec![]));
-    let r2 = prf.add_step(Justification(p("~A & B"), RuleM::Reduction, vec![i(p2.clone())], vec![]));
-    let r3 = prf.add_step(Justification(p("A | B"), RuleM::Reduction, vec![i(p3.clone())], vec![]));
-    let r4 = prf.add_step(Justification(p("~B | A"), RuleM::Reduction, vec![i(p4.clone())], vec![]));
-    let r5 = prf.add_step(Justification(p("(forall A (A & B)) | C"), RuleM::Reduction, vec![i(p5)], vec![]));
-
-    let r6 = prf.add_step(Justification(p("A"), RuleM::Reduction, vec![i(p1)], vec![]));
-    let r7 = prf.add_step(Justification(p("A | B"), RuleM::Reduction, vec![i(p2)], vec![]));
-    let r8 = prf.add_step(Justification(p("B"), RuleM::Reduction, vec![i(p3)], vec![]));
-    let r9 = prf.add_step(Justification(p("B & A"), RuleM::Reduction, vec![i(p4)], vec![]));
-
-    let r10 = prf.add_step(Justification(p("B & (C | ~A)"), RuleM::Reduction, vec![i(p6.clone())], vec![]));
-    let r11 = prf.add_step(Justification(p("B & (C & ~A)"), RuleM::Reduction, vec![i(p6)], vec![]));
-    let r12 = prf.add_step(Justification(p("A | (~A & B)"), RuleM::Reduction, vec![i(p7)], vec![]));
-    let r13 = prf.add_step(Justification(p("D | (~A & B)"), RuleM::Reduction, vec![i(p8)], vec![]));
-
-    (prf, vec![i(r1), i(r2), i(r3), i(r4), i(r5), i(r10), i(r12), i(r13)], vec![i(r6), i(r7), i(r8), i(r9), i(r11)])
+    let (prf, lines) = ProofBuilder::new()
+        .premise("A & (~A | B)")
+        .premise("(~~A | B) & ~A")
+        .premise("(B & ~A) | A")
+        .premise("~B | (A & ~~B)")
+        .premise("(forall A (A & (~A | B))) | (~(forall A (A & (~A | B))) & C)")
+        .premise("B & (C | (~C & ~A))")
+        .premise("A | (~A & (~~A | B))")
+        .premise("D | (~A & (~~A | B))")
+        .step("A & B", RuleM::Reduction, deps![1])
+        .step("~A & B", RuleM::Reduction, deps![2])
+        .step("A | B", RuleM::Reduction, deps![3])
+        .step("~B | A", RuleM::Reduction, deps![4])
+        .step("(forall A (A & B)) | C", RuleM::Reduction, deps![5])
+        .step("A", RuleM::Reduction, deps![1])
+        .step("A | B", RuleM::Reduction, deps![2])
+        .step("B", RuleM::Reduction, deps![3])
+        .step("B & A", RuleM::Reduction, deps![4])
+        .step("B & (C | ~A)", RuleM::Reduction, deps![6])
+        .step("B & (C & ~A)", RuleM::Reduction, deps![6])
+        .step("A | (~A & B)", RuleM::Reduction, deps![7])
+        .step("D | (~A & B)", RuleM::Reduction, deps![8])
+        .build_with_lines()
+        .unwrap();
+    let l = |n: usize| lines[n - 1].clone();
+    (prf, vec![l(9), l(10), l(11), l(12), l(13), l(18), l(20), l(21)], vec![l(14), l(15), l(16), l(17), l(19)])
 }
 
 pub fn test_adjacency<P: Proof>() -> (P, Vec<PjRef<P>>, Vec<PjRef<P>>) {
-    use self::coproduct_inject as i;
-    use crate::parser::parse_unwrap as p;
-    let mut prf = P::new();
-
-    let p1 = prf.add_premise(p("(A & B) | (A & ~B)"));
-    let p2 = prf.add_premise(p("(A | B) & (A | ~B)"));
-
-    let r1 = prf.add_step(Justification(p("A"), RuleM::Adjacency, vec![i(p1.clone())], vec![]));
-    let r2 = prf.add_step(Justification(p("A"), RuleM::Adjacency, vec![i(p2.clone())], vec![]));
-    let r3 = prf.add_step(Justification(p("(B | A) & (A | ~B)"), RuleM::Adjacency, vec![i(p1.clone())], vec![]));
-    let r4 = prf.add_step(Justification(p("(~B & A) | (A & B)"), RuleM::Adjacency, vec![i(p2.clone())], vec![]));
-
-    let r5 = prf.add_step(Justification(p("B"), RuleM::Adjacency, vec![i(p1)], vec![]));
-    let r6 = prf.add_step(Justification(p("B"), RuleM::Adjacency, vec![i(p2)], vec![]));
-
-    (prf, vec![i(r1), i(r2), i(r3), i(r4)], vec![i(r5), i(r6)])
+    let (prf, lines) = ProofBuilder::new()
+        .premise("(A & B) | (A & ~B)")
+        .premise("(A | B) & (A | ~B)")
+        .step("A", RuleM::Adjacency, deps![1])
+        .step("A", RuleM::Adjacency, deps![2])
+        .step("(B | A) & (A | ~B)", RuleM::Adjacency, deps![1])
+        .step("(~B & A) | (A & B)", RuleM::Adjacency, deps![2])
+        .step("B", RuleM::Adjacency, deps![1])
+        .step("B", RuleM::Adjacency, deps![2])
+        .build_with_lines()
+        .unwrap();
+    let l = |n: usize| lines[n - 1].clone();
+    (prf, vec![l(3), l(4), l(5), l(6)], vec![l(7), l(8)])
 }
 
 pub fn test_resolution<P: Proof>() -> (P, Vec<PjRef<P>>, Vec<PjRef<P>>) {
-    use self::coproduct_inject as i;
-    use crate::parser::parse_unwrap as p;
-    let mut prf = P::new();
-    let p1 = prf.add_premise(p("a1 | a2 | c"));
-    let p2 = prf.add_premise(p("b1 | b2 | ~c"));
-    let p3 = prf.add_premise(p("~c"));
-    let p4 = prf.add_premise(p("c"));
-    let p5 = prf.add_premise(p("a1 & a2 & c"));
-    let p6 = prf.add_premise(p("(a1 & a2) | c"));
-
-    let r1 = prf.add_step(Justification(p("a1 | a2 | b1 | b2"), RuleM::Resolution, vec![i(p1.clone()), i(p2.clone())], vec![]));
-    let r2 = prf.add_step(Justification(p("a1 | a2"), RuleM::Resolution, vec![i(p1.clone()), i(p3.clone())], vec![]));
-    let r3 = prf.add_step(Justification(p("_|_"), RuleM::Resolution, vec![i(p3.clone()), i(p4)], vec![]));
-    let r4 = prf.add_step(Justification(p("a1 & a2"), RuleM::Resolution, vec![i(p3.clone()), i(p6)], vec![]));
-
-    let r5 = prf.add_step(Justification(p("a1 | a2 | c | b1 | b2"), RuleM::Resolution, vec![i(p1.clone()), i(p2.clone())], vec![]));
-    let r6 = prf.add_step(Justification(p("a1 | a2 | b1"), RuleM::Resolution, vec![i(p1), i(p2)], vec![]));
-    let r7 = prf.add_step(Justification(p("a1 | a2"), RuleM::Resolution, vec![i(p5.clone()), i(p3.clone())], vec![]));
-    let r8 = prf.add_step(Justification(p("a1 | a2"), RuleM::Resolution, vec![i(p3), i(p5.clone())], vec![]));
-    let r9 = prf.add_step(Justification(p("a1 | a2"), RuleM::Resolution, vec![i(p5.clone()), i(p5)], vec![]));
-
-    (prf, vec![i(r1), i(r2), i(r3), i(r4)], vec![i(r5), i(r6), i(r7), i(r8), i(r9)])
+    let (prf, lines) = ProofBuilder::new()
+        .premise("a1 | a2 | c")
+        .premise("b1 | b2 | ~c")
+        .premise("~c")
+        .premise("c")
+        .premise("a1 & a2 & c")
+        .premise("(a1 & a2) | c")
+        .step("a1 | a2 | b1 | b2", RuleM::Resolution, deps![1, 2])
+        .step("a1 | a2", RuleM::Resolution, deps![1, 3])
+        .step("_|_", RuleM::Resolution, deps![3, 4])
+        .step("a1 & a2", RuleM::Resolution, deps![3, 6])
+        .step("a1 | a2 | c | b1 | b2", RuleM::Resolution, deps![1, 2])
+        .step("a1 | a2 | b1", RuleM::Resolution, deps![1, 2])
+        .step("a1 | a2", RuleM::Resolution, deps![5, 3])
+        .step("a1 | a2", RuleM::Resolution, deps![3, 5])
+        .step("a1 | a2", RuleM::Resolution, deps![5, 5])
+        .build_with_lines()
+        .unwrap();
+    let l = |n: usize| lines[n - 1].clone();
+    (prf, vec![l(7), l(8), l(9), l(10)], vec![l(11), l(12), l(13), l(14), l(15)])
 }
 
 pub fn test_tautcon<P: Proof>() -> (P, Vec<PjRef<P>>, Vec<PjRef<P>>) {
-    use self::coproduct_inject as i;
-    use crate::parser::parse_unwrap as p;
-    let mut prf = P::new();
-    let p1 = prf.add_premise(p("_|_"));
-    let p2 = prf.add_premise(p("^|^"));
-    let p3 = prf.add_premise(p("A"));
-    let p4 = prf.add_premise(p("~~A"));
-
-    let r1 = prf.add_step(Justification(p("_|_"), RuleM::TautologicalConsequence, vec![i(p1.clone())], vec![]));
-    let r2 = prf.add_step(Justification(p("^|^"), RuleM::TautologicalConsequence, vec![i(p1.clone())], vec![]));
-    let r3 = prf.add_step(Justification(p("A"), RuleM::TautologicalConsequence, vec![i(p1.clone())], vec![]));
-    let r4 = prf.add_step(Justification(p("~~~((A & ~B) | ~C)"), RuleM::TautologicalConsequence, vec![i(p1.clone())], vec![]));
-
-    let r5 = prf.add_step(Justification(p("_|_"), RuleM::TautologicalConsequence, vec![i(p2.clone())], vec![]));
-    let r6 = prf.add_step(Justification(p("^|^"), RuleM::TautologicalConsequence, vec![i(p2.clone())], vec![]));
-    let r7 = prf.add_step(Justification(p("A"), RuleM::TautologicalConsequence, vec![i(p2.clone())], vec![]));
-    let r8 = prf.add_step(Justification(p("~~~((A & ~B) | ~C)"), RuleM::TautologicalConsequence, vec![i(p2)], vec![]));
-
-    let r9 = prf.add_step(Justification(p("A"), RuleM::TautologicalConsequence, vec![i(p3.clone())], vec![]));
-    let r10 = prf.add_step(Justification(p("B"), RuleM::TautologicalConsequence, vec![i(p3.clone())], vec![]));
-    let r11 = prf.add_step(Justification(p("A | B"), RuleM::TautologicalConsequence, vec![i(p3.clone())], vec![]));
-    let r12 = prf.add_step(Justification(p("A & B"), RuleM::TautologicalConsequence, vec![i(p3)], vec![]));
-
-    let r13 = prf.add_step(Justification(p("A"), RuleM::TautologicalConsequence, vec![i(p4.clone())], vec![]));
-    let r14 = prf.add_step(Justification(p("B"), RuleM::TautologicalConsequence, vec![i(p4.clone())], vec![]));
-    let r15 = prf.add_step(Justification(p("A | B"), RuleM::TautologicalConsequence, vec![i(p4.clone())], vec![]));
-    let r16 = prf.add_step(Justification(p("A & B"), RuleM::TautologicalConsequence, vec![i(p4.clone())], vec![]));
-
-    let r17 = prf.add_step(Justification(p("B"), RuleM::TautologicalConsequence, vec![i(p1), i(p4)], vec![]));
-
-    (prf, vec![i(r1), i(r2), i(r3), i(r4), i(r6), i(r9), i(r11), i(r13), i(r15), i(r17)], vec![i(r5), i(r7), i(r8), i(r10), i(r12), i(r14), i(r16)])
+    let (prf, lines) = ProofBuilder::new()
+        .premise("_|_")
+        .premise("^|^")
+        .premise("A")
+        .premise("~~A")
+        .step("_|_", RuleM::TautologicalConsequence, deps![1])
+        .step("^|^", RuleM::TautologicalConsequence, deps![1])
+        .step("A", RuleM::TautologicalConsequence, deps![1])
+        .step("~~~((A & ~B) | ~C)", RuleM::TautologicalConsequence, deps![1])
+        .step("_|_", RuleM::TautologicalConsequence, deps![2])
+        .step("^|^", RuleM::TautologicalConsequence, deps![2])
+        .step("A", RuleM::TautologicalConsequence, deps![2])
+        .step("~~~((A & ~B) | ~C)", RuleM::TautologicalConsequence, deps![2])
+        .step("A", RuleM::TautologicalConsequence, deps![3])
+        .step("B", RuleM::TautologicalConsequence, deps![3])
+        .step("A | B", RuleM::TautologicalConsequence, deps![3])
+        .step("A & B", RuleM::TautologicalConsequence, deps![3])
+        .step("A", RuleM::TautologicalConsequence, deps![4])
+        .step("B", RuleM::TautologicalConsequence, deps![4])
+        .step("A | B", RuleM::TautologicalConsequence, deps![4])
+        .step("A & B", RuleM::TautologicalConsequence, deps![4])
+        .step("B", RuleM::TautologicalConsequence, deps![1, 4])
+        .build_with_lines()
+        .unwrap();
+    let l = |n: usize| lines[n - 1].clone();
+    (prf, vec![l(5), l(6), l(7), l(8), l(10), l(13), l(15), l(17), l(19), l(21)], vec![l(9), l(11), l(12), l(14), l(16), l(18), l(20)])
 }
 
 pub fn test_empty_rule<P: Proof>() -> (P, Vec<PjRef<P>>, Vec<PjRef<P>>) {
-    use self::coproduct_inject as i;
-    use crate::parser::parse_unwrap as p;
-    let mut prf = P::new();
-    let p1 = prf.add_premise(p("A"));
-    let r1 = prf.add_step(Justification(p("A"), RuleM::EmptyRule, vec![i(p1)], vec![]));
-
-    (prf, vec![], vec![i(r1)])
+    let (prf, lines) = ProofBuilder::new()
+        .premise("A")
+        .step("A", RuleM::EmptyRule, deps![1])
+        .build_with_lines()
+        .unwrap();
+    let l = |n: usize| lines[n - 1].clone();
+    (prf, vec![], vec![l(2)])
 }
 
 pub fn test_modus_tollens<P: Proof>() -> (P, Vec<PjRef<P>>, Vec<PjRef<P>>) {
-    use self::coproduct_inject as i;
-    use crate::parser::parse_unwrap as p;
-    let mut prf = P::new();
-    let r1 = prf.add_premise(p("~Q"));
-    let r2 = prf.add_premise(p("P -> Q"));
-    let r3 = prf.add_premise(p("P"));
-    let r4 = prf.add_premise(p("~A"));
-    let r5 = prf.add_premise(p("A -> A"));
-    let r6 = prf.add_step(Justification(p("~P"), RuleM::ModusTollens, vec![i(r1.clone()), i(r2.clone())], vec![]));
-    let r7 = prf.add_step(Justification(p("~P"), RuleM::ModusTollens, vec![i(r2.clone()), i(r1.clone())], vec![]));
-    let r8 = prf.add_step(Justification(p("~B"), RuleM::ModusTollens, vec![i(r1.clone()), i(r2.clone())], vec![]));
-    let r9 = prf.add_step(Justification(p("P"), RuleM::ModusTollens, vec![i(r3.clone()), i(r2.clone())], vec![]));
-    let r10 = prf.add_step(Justification(p("P"), RuleM::ModusTollens, vec![i(r2.clone()), i(r3.clone())], vec![]));
-    let r11 = prf.add_step(Justification(p("B"), RuleM::ModusTollens, vec![i(r3), i(r4.clone())], vec![]));
-    let r12 = prf.add_step(Justification(p("Q"), RuleM::ModusTollens, vec![i(r2.clone()), i(r4.clone())], vec![]));
-    let r13 = prf.add_step(Justification(p("~A"), RuleM::ModusTollens, vec![i(r4), i(r5)], vec![]));
-    let r14 = prf.add_step(Justification(p("~P"), RuleM::ModusTollens, vec![i(r2), i(r1)], vec![]));
-    (prf, vec![i(r6), i(r7), i(r13), i(r14)], vec![i(r8), i(r9), i(r10), i(r11), i(r12)])
+    let (prf, lines) = ProofBuilder::new()
+        .premise("~Q")
+        .premise("P -> Q")
+        .premise("P")
+        .premise("~A")
+        .premise("A -> A")
+        .step("~P", RuleM::ModusTollens, deps![1, 2])
+        .step("~P", RuleM::ModusTollens, deps![2, 1])
+        .step("~B", RuleM::ModusTollens, deps![1, 2])
+        .step("P", RuleM::ModusTollens, deps![3, 2])
+        .step("P", RuleM::ModusTollens, deps![2, 3])
+        .step("B", RuleM::ModusTollens, deps![3, 4])
+        .step("Q", RuleM::ModusTollens, deps![2, 4])
+        .step("~A", RuleM::ModusTollens, deps![4, 5])
+        .step("~P", RuleM::ModusTollens, deps![2, 1])
+        .build_with_lines()
+        .unwrap();
+    let l = |n: usize| lines[n - 1].clone();
+    (prf, vec![l(6), l(7), l(13), l(14)], vec![l(8), l(9), l(10), l(11), l(12)])
 }
 
 pub fn test_hypothetical_syllogism<P: Proof>() -> (P, Vec<PjRef<P>>, Vec<PjRef<P>>) {
-    use self::coproduct_inject as i;
-    use crate::parser::parse_unwrap as p;
-    let mut prf = P::new();
-    let r1 = prf.add_premise(p("P -> Q"));
-    let r2 = prf.add_premise(p("Q -> R"));
-    let r3 = prf.add_premise(p("R -> S"));
-    let r4 = prf.add_premise(p("S -> T"));
-    let r5 = prf.add_premise(p("P"));
-    let r6 = prf.add_step(Justification(p("P -> R"), RuleM::HypotheticalSyllogism, vec![i(r1.clone()), i(r2.clone())], vec![]));
-    let r7 = prf.add_step(Justification(p("P -> R"), RuleM::HypotheticalSyllogism, vec![i(r2.clone()), i(r1.clone())], vec![]));
-    let r8 = prf.add_step(Justification(p("P"), RuleM::HypotheticalSyllogism, vec![i(r1), i(r2.clone())], vec![]));
-    let r9 = prf.add_step(Justification(p("P"), RuleM::HypotheticalSyllogism, vec![i(r3.clone()), i(r2.clone())], vec![]));
-    let r10 = prf.add_step(Justification(p("P"), RuleM::HypotheticalSyllogism, vec![i(r2.clone()), i(r5)], vec![]));
-    let r11 = prf.add_step(Justification(p("B"), RuleM::HypotheticalSyllogism, vec![i(r3.clone()), i(r4.clone())], vec![]));
-    let r12 = prf.add_step(Justification(p("R -> P"), RuleM::HypotheticalSyllogism, vec![i(r2), i(r4.clone())], vec![]));
-    let r13 = prf.add_step(Justification(p("R -> T"), RuleM::HypotheticalSyllogism, vec![i(r3), i(r4)], vec![]));
-    let r14 = prf.add_step(Justification(p("P -> T"), RuleM::HypotheticalSyllogism, vec![i(r6.clone()), i(r13.clone())], vec![]));
-    (prf, vec![i(r6), i(r7), i(r13), i(r14)], vec![i(r8), i(r9), i(r10), i(r11), i(r12)])
+    let (prf, lines) = ProofBuilder::new()
+        .premise("P -> Q")
+        .premise("Q -> R")
+        .premise("R -> S")
+        .premise("S -> T")
+        .premise("P")
+        .step("P -> R", RuleM::HypotheticalSyllogism, deps![1, 2])
+        .step("P -> R", RuleM::HypotheticalSyllogism, deps![2, 1])
+        .step("P", RuleM::HypotheticalSyllogism, deps![1, 2])
+        .step("P", RuleM::HypotheticalSyllogism, deps![3, 2])
+        .step("P", RuleM::HypotheticalSyllogism, deps![2, 5])
+        .step("B", RuleM::HypotheticalSyllogism, deps![3, 4])
+        .step("R -> P", RuleM::HypotheticalSyllogism, deps![2, 4])
+        .step("R -> T", RuleM::HypotheticalSyllogism, deps![3, 4])
+        .step("P -> T", RuleM::HypotheticalSyllogism, deps![6, 13])
+        .build_with_lines()
+        .unwrap();
+    let l = |n: usize| lines[n - 1].clone();
+    (prf, vec![l(6), l(7), l(13), l(14)], vec![l(8), l(9), l(10), l(11), l(12)])
 }
 
 pub fn test_disjunctive_syllogism<P: Proof>() -> (P, Vec<PjRef<P>>, Vec<PjRef<P>>) {
-    use self::coproduct_inject as i;
-    use crate::parser::parse_unwrap as p;
-    let mut prf = P::new();
-    let r1 = prf.add_premise(p("~Q"));
-    let r2 = prf.add_premise(p("P ∨ Q"));
-    let r3 = prf.add_premise(p("P"));
-    let r4 = prf.add_step(Justification(p("P"), RuleM::DisjunctiveSyllogism, vec![i(r1.clone()), i(r2.clone())], vec![]));
-    let r5 = prf.add_step(Justification(p("P"), RuleM::DisjunctiveSyllogism, vec![i(r2.clone()), i(r1.clone())], vec![]));
-    let r6 = prf.add_step(Justification(p("~Q"), RuleM::DisjunctiveSyllogism, vec![i(r3.clone()), i(r2.clone())], vec![]));
-    let r7 = prf.add_step(Justification(p("~Q"), RuleM::DisjunctiveSyllogism, vec![i(r2.clone()), i(r3)], vec![]));
-    let r8 = prf.add_step(Justification(p("Q"), RuleM::DisjunctiveSyllogism, vec![i(r1.clone()), i(r2.clone())], vec![]));
-    let r9 = prf.add_step(Justification(p("Q"), RuleM::DisjunctiveSyllogism, vec![i(r2), i(r1)], vec![]));
-    (prf, vec![i(r4), i(r5)], vec![i(r6), i(r7), i(r8), i(r9)])
+    let (prf, lines) = ProofBuilder::new()
+        .premise("~Q")
+        .premise("P ∨ Q")
+        .premise("P")
+        .step("P", RuleM::DisjunctiveSyllogism, deps![1, 2])
+        .step("P", RuleM::DisjunctiveSyllogism, deps![2, 1])
+        .step("~Q", RuleM::DisjunctiveSyllogism, deps![3, 2])
+        .step("~Q", RuleM::DisjunctiveSyllogism, deps![2, 3])
+        .step("Q", RuleM::DisjunctiveSyllogism, deps![1, 2])
+        .step("Q", RuleM::DisjunctiveSyllogism, deps![2, 1])
+        .build_with_lines()
+        .unwrap();
+    let l = |n: usize| lines[n - 1].clone();
+    (prf, vec![l(4), l(5)], vec![l(6), l(7), l(8), l(9)])
 }
 
 pub fn test_constructive_dilemma<P: Proof>() -> (P, Vec<PjRef<P>>, Vec<PjRef<P>>) {
-    use self::coproduct_inject as i;
-    use crate::parser::parse_unwrap as p;
-    let mut prf = P::new();
-    let r1 = prf.add_premise(p("P -> Q"));
-    let r2 = prf.add_premise(p("R -> S"));
-    let r3 = prf.add_premise(p("S -> T"));
-    let r4 = prf.add_premise(p("P | R"));
-    let r5 = prf.add_premise(p("R | S"));
-    let r6 = prf.add_step(Justification(p("Q | S"), RuleM::ConstructiveDilemma, vec![i(r1.clone()), i(r2.clone()), i(r4.clone())], vec![]));
-    let r7 = prf.add_step(Justification(p("Q | S"), RuleM::ConstructiveDilemma, vec![i(r2.clone()), i(r4.clone()), i(r1)], vec![]));
-    let r8 = prf.add_step(Justification(p("S | T"), RuleM::ConstructiveDilemma, vec![i(r3.clone()), i(r2.clone()), i(r4.clone())], vec![]));
-    let r9 = prf.add_step(Justification(p("P"), RuleM::ConstructiveDilemma, vec![i(r3.clone()), i(r2.clone())], vec![]));
-    let r10 = prf.add_step(Justification(p("P"), RuleM::ConstructiveDilemma, vec![i(r2.clone()), i(r5)], vec![]));
-    let r11 = prf.add_step(Justification(p("B"), RuleM::ConstructiveDilemma, vec![i(r3.clone()), i(r4.clone())], vec![]));
-    let r12 = prf.add_step(Justification(p("R -> P"), RuleM::ConstructiveDilemma, vec![i(r2), i(r4.clone())], vec![]));
-    let r13 = prf.add_step(Justification(p("R -> T"), RuleM::ConstructiveDilemma, vec![i(r3), i(r4)], vec![]));
-    (prf, vec![i(r6), i(r7)], vec![i(r8), i(r9), i(r10), i(r11), i(r12), i(r13)])
+    let (prf, lines) = ProofBuilder::new()
+        .premise("P -> Q")
+        .premise("R -> S")
+        .premise("S -> T")
+        .premise("P | R")
+        .premise("R | S")
+        .step("Q | S", RuleM::ConstructiveDilemma, deps![1, 2, 4])
+        .step("Q | S", RuleM::ConstructiveDilemma, deps![2, 4, 1])
+        .step("S | T", RuleM::ConstructiveDilemma, deps![3, 2, 4])
+        .step("P", RuleM::ConstructiveDilemma, deps![3, 2])
+        .step("P", RuleM::ConstructiveDilemma, deps![2, 5])
+        .step("B", RuleM::ConstructiveDilemma, deps![3, 4])
+        .step("R -> P", RuleM::ConstructiveDilemma, deps![2, 4])
+        .step("R -> T", RuleM::ConstructiveDilemma, deps![3, 4])
+        .build_with_lines()
+        .unwrap();
+    let l = |n: usize| lines[n - 1].clone();
+    (prf, vec![l(6), l(7)], vec![l(8), l(9), l(10), l(11), l(12), l(13)])
 }
 
 pub fn test_excluded_middle<P: Proof>() -> (P, Vec<PjRef<P>>, Vec<PjRef<P>>) {
-    use self::coproduct_inject as i;
-    use crate::parser::parse_unwrap as p;
-    let mut prf = P::new();
-    let r1 = prf.add_step(Justification(p("A | ~A"), RuleM::ExcludedMiddle, vec![], vec![]));
-    let r2 = prf.add_step(Justification(p("A | ~A"), RuleM::ExcludedMiddle, vec![i(r1.clone())], vec![]));
-    let r3 = prf.add_step(Justification(p("A & ~A"), RuleM::ExcludedMiddle, vec![], vec![]));
-    let r4 = prf.add_step(Justification(p("_|_ | ~_|_"), RuleM::ExcludedMiddle, vec![], vec![]));
-    let r5 = prf.add_step(Justification(p("^|^ | ~^|^"), RuleM::ExcludedMiddle, vec![], vec![]));
-    let r6 = prf.add_step(Justification(p("^|^ | ~_|_"), RuleM::ExcludedMiddle, vec![], vec![]));
-    let r7 = prf.add_step(Justification(p("P"), RuleM::ExcludedMiddle, vec![], vec![]));
-    let r8 = prf.add_step(Justification(p("B"), RuleM::ExcludedMiddle, vec![], vec![]));
-    let r9 = prf.add_step(Justification(p("R -> P"), RuleM::ExcludedMiddle, vec![], vec![]));
-    let r10 = prf.add_step(Justification(p("R -> T"), RuleM::ExcludedMiddle, vec![], vec![]));
-    let r11 = prf.add_step(Justification(p("(A & B & C & forall P P) | ~(A & B & C & forall P P)"), RuleM::ExcludedMiddle, vec![], vec![]));
-    (prf, vec![i(r1), i(r4), i(r5), i(r11)], vec![i(r2), i(r3), i(r6), i(r7), i(r8), i(r9), i(r10)])
+    let (prf, lines) = ProofBuilder::new()
+        .step("A | ~A", RuleM::ExcludedMiddle, deps![])
+        .step("A | ~A", RuleM::ExcludedMiddle, deps![1])
+        .step("A & ~A", RuleM::ExcludedMiddle, deps![])
+        .step("_|_ | ~_|_", RuleM::ExcludedMiddle, deps![])
+        .step("^|^ | ~^|^", RuleM::ExcludedMiddle, deps![])
+        .step("^|^ | ~_|_", RuleM::ExcludedMiddle, deps![])
+        .step("P", RuleM::ExcludedMiddle, deps![])
+        .step("B", RuleM::ExcludedMiddle, deps![])
+        .step("R -> P", RuleM::ExcludedMiddle, deps![])
+        .step("R -> T", RuleM::ExcludedMiddle, deps![])
+        .step("(A & B & C & forall P P) | ~(A & B & C & forall P P)", RuleM::ExcludedMiddle, deps![])
+        .build_with_lines()
+        .unwrap();
+    let l = |n: usize| lines[n - 1].clone();
+    (prf, vec![l(1), l(4), l(5), l(11)], vec![l(2), l(3), l(6), l(7), l(8), l(9), l(10)])
 }
 
 pub fn test_weak_induction<P: Proof>() -> (P, Vec<PjRef<P>>, Vec<PjRef<P>>) {
-    use self::coproduct_inject as i;
-    use crate::parser::parse_unwrap as p;
-    let mut prf = P::new();
-    let r1 = prf.add_premise(p("~LessThan(0,0)"));
-    let r2 = prf.add_premise(p("forall x ~LessThan(x,x) -> ~LessThan(s(x),s(x))"));
-    let r3 = prf.add_premise(p("Equals(0,0)"));
-    let r4 = prf.add_premise(p("forall 0 Equals(0,0) -> Equals(s(0),s(0))"));
-    let r5 = prf.add_step(Justification(p("forall x ~LessThan(x,x)"), RuleM::WeakInduction, vec![i(r1.clone()), i(r2.clone())], vec![]));
-    let r6 = prf.add_step(Justification(p("forall x ~LessThan(x,x)"), RuleM::WeakInduction, vec![i(r2.clone()), i(r1.clone())], vec![]));
-    let r7 = prf.add_step(Justification(p("forall n ~LessThan(n,n)"), RuleM::WeakInduction, vec![i(r1.clone()), i(r2.clone())], vec![]));
-    let r8 = prf.add_step(Justification(p("forall x Equals(x,x)"), RuleM::WeakInduction, vec![i(r3.clone()), i(r4.clone())], vec![]));
-    let r9 = prf.add_step(Justification(p("forall x Equals(x,0)"), RuleM::WeakInduction, vec![i(r3.clone()), i(r4.clone())], vec![]));
-    (prf, vec![i(r1), i(r2), i(r3), i(r4), i(r5), i(r6), i(r7), i(r8)], vec![i(r9)])
+    let (prf, lines) = ProofBuilder::new()
+        .premise("~LessThan(0,0)")
+        .premise("forall x ~LessThan(x,x) -> ~LessThan(s(x),s(x))")
+        .premise("Equals(0,0)")
+        .premise("forall 0 Equals(0,0) -> Equals(s(0),s(0))")
+        .step("forall x ~LessThan(x,x)", RuleM::WeakInduction, deps![1, 2])
+        .step("forall x ~LessThan(x,x)", RuleM::WeakInduction, deps![2, 1])
+        .step("forall n ~LessThan(n,n)", RuleM::WeakInduction, deps![1, 2])
+        .step("forall x Equals(x,x)", RuleM::WeakInduction, deps![3, 4])
+        .step("forall x Equals(x,0)", RuleM::WeakInduction, deps![3, 4])
+        .build_with_lines()
+        .unwrap();
+    let l = |n: usize| lines[n - 1].clone();
+    (prf, vec![l(1), l(2), l(3), l(4), l(5), l(6), l(7), l(8)], vec![l(9)])
 }
 
 pub fn test_strong_induction<P: Proof>() -> (P, Vec<PjRef<P>>, Vec<PjRef<P>>) {
-    use self::coproduct_inject as i;
-    use crate::parser::parse_unwrap as p;
-    let mut prf = P::new();
-    let r1 = prf.add_premise(p("forall n (forall x LessThan(x, n) -> P(x)) -> P(n)"));
-    let r2 = prf.add_premise(p("forall n (forall x LessThan(x, n) -> P(x,n)) -> P(n,n)"));
-    let r3 = prf.add_step(Justification(p("forall x P(x)"), RuleM::StrongInduction, vec![i(r1.clone())], vec![]));
-    let r4 = prf.add_step(Justification(p("forall n P(n)"), RuleM::StrongInduction, vec![i(r1.clone())], vec![]));
-    let r5 = prf.add_step(Justification(p("forall n P(n)"), RuleM::StrongInduction, vec![i(r2.clone())], vec![]));
-    let r6 = prf.add_step(Justification(p("forall x P(x,n)"), RuleM::StrongInduction, vec![i(r1.clone())], vec![]));
-    let r7 = prf.add_step(Justification(p("forall x P(x,n)"), RuleM::StrongInduction, vec![i(r2.clone())], vec![]));
-    let r8 = prf.add_step(Justification(p("forall a P(a)"), RuleM::StrongInduction, vec![i(r1.clone())], vec![]));
-    (prf, vec![i(r1), i(r2), i(r3), i(r4), i(r8)], vec![i(r5), i(r6), i(r7)])
+    let (prf, lines) = ProofBuilder::new()
+        .premise("forall n (forall x LessThan(x, n) -> P(x)) -> P(n)")
+        .premise("forall n (forall x LessThan(x, n) -> P(x,n)) -> P(n,n)")
+        .step("forall x P(x)", RuleM::StrongInduction, deps![1])
+        .step("forall n P(n)", RuleM::StrongInduction, deps![1])
+        .step("forall n P(n)", RuleM::StrongInduction, deps![2])
+        .step("forall x P(x,n)", RuleM::StrongInduction, deps![1])
+        .step("forall x P(x,n)", RuleM::StrongInduction, deps![2])
+        .step("forall a P(a)", RuleM::StrongInduction, deps![1])
+        .build_with_lines()
+        .unwrap();
+    let l = |n: usize| lines[n - 1].clone();
+    (prf, vec![l(1), l(2), l(3), l(4), l(8)], vec![l(5), l(6), l(7)])
 }
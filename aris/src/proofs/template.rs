@@ -0,0 +1,117 @@
+/*!
+Proof skeletons for new proofs and assignments.
+
+A template has each premise of a sequent as a premise line, followed by a single step for the goal, justified by the placeholder `EmptyRule` so that it doesn't verify until it's filled in.
+
+# Example
+```
+#[macro_use] extern crate frunk_core;
+use aris::expr::Expr;
+use aris::parser::parse_unwrap as p;
+use aris::proofs::pooledproof::PooledProof;
+use aris::proofs::template::from_sequent;
+use aris::proofs::Proof;
+
+let template = from_sequent::<PooledProof<HList![Expr]>>(&[p("A"), p("A -> B")], &p("B")).unwrap();
+assert_eq!(template.proof.premises().len(), 2);
+assert_eq!(template.proof.lines().len(), 1);
+assert_eq!(template.goals, vec![p("B")]);
+assert!(template.warnings.is_empty());
+```
+*/
+
+use super::Justification;
+use super::Proof;
+use crate::expr::Expr;
+use crate::rules::RuleM;
+
+/// A new proof, with the goals it should be checked against
+#[derive(Clone, Debug)]
+pub struct Template<P> {
+    pub proof: P,
+    pub goals: Vec<Expr>,
+    /// Problems with the sequent that were worked around
+    pub warnings: Vec<TemplateWarning>,
+}
+
+/// Reasons a sequent can't be made into a template
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TemplateError {
+    /// The goal is blank
+    EmptyGoal,
+}
+
+/// Problems with a sequent that don't prevent making a template from it
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TemplateWarning {
+    /// `premises[index]` is the same as `premises[first]`, so it was left out
+    DuplicatePremise { index: usize, first: usize },
+}
+
+impl std::fmt::Display for TemplateError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            TemplateError::EmptyGoal => write!(f, "The goal is empty."),
+        }
+    }
+}
+
+impl std::fmt::Display for TemplateWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            TemplateWarning::DuplicatePremise { index, first } => write!(f, "Premise {} is the same as premise {}, so it was left out.", index + 1, first + 1),
+        }
+    }
+}
+
+/// Create a proof of `premises |- goal`, with the premises in order and
+/// identical premises only included once
+pub fn from_sequent<P: Proof>(premises: &[Expr], goal: &Expr) -> Result<Template<P>, TemplateError> {
+    if matches!(goal, Expr::Var { name } if name.trim().is_empty()) {
+        return Err(TemplateError::EmptyGoal);
+    }
+    let mut proof = P::new();
+    let mut warnings = vec![];
+    for (index, premise) in premises.iter().enumerate() {
+        match premises[..index].iter().position(|e| e == premise) {
+            Some(first) => warnings.push(TemplateWarning::DuplicatePremise { index, first }),
+            None => {
+                proof.add_premise(premise.clone());
+            }
+        }
+    }
+    proof.add_step(Justification(goal.clone(), RuleM::EmptyRule, vec![], vec![]));
+    Ok(Template { proof, goals: vec![goal.clone()], warnings })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::parser::parse_unwrap as p;
+    use crate::proofs::pooledproof::PooledProof;
+
+    use frunk_core::HList;
+
+    type P = PooledProof<HList![Expr]>;
+
+    #[test]
+    fn test_from_sequent() {
+        // a theorem, with no premises
+        let template = from_sequent::<P>(&[], &p("A | ~A")).unwrap();
+        assert!(template.proof.premises().is_empty());
+        let steps = template.proof.direct_lines();
+        assert_eq!(steps.len(), 1);
+        assert_eq!(template.proof.lookup_step(&steps[0]), Some(Justification(p("A | ~A"), RuleM::EmptyRule, vec![], vec![])));
+        assert_eq!(template.goals, vec![p("A | ~A")]);
+        assert!(template.warnings.is_empty());
+        assert!(template.proof.verify_line(&frunk_core::coproduct::Coproduct::inject(steps[0])).is_err());
+
+        let template = from_sequent::<P>(&[p("A"), p("B"), p("A"), p("B"), p("C")], &p("A & B & C")).unwrap();
+        let premises = template.proof.premises().iter().filter_map(|r| template.proof.lookup_premise(r)).collect::<Vec<_>>();
+        assert_eq!(premises, vec![p("A"), p("B"), p("C")]);
+        assert_eq!(template.warnings, vec![TemplateWarning::DuplicatePremise { index: 2, first: 0 }, TemplateWarning::DuplicatePremise { index: 3, first: 1 }]);
+
+        assert_eq!(from_sequent::<P>(&[p("A")], &Expr::var("")).unwrap_err(), TemplateError::EmptyGoal);
+    }
+}
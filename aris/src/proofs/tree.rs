@@ -0,0 +1,309 @@
+/*!
+A plain recursive representation of a proof, for debugging, golden tests, and serializers that shouldn't depend on how a proof type stores its lines.
+
+Dependencies in a `TreeProof` are display line numbers, numbered the same way as `Proof::iter_lines`: each subproof's premises, then its lines, with nested subproofs numbered in place.
+A subproof is cited by the range of line numbers it spans.
+`TreeProof::from_proof` lists each step's dependencies in increasing order, so that it doesn't depend on how the proof orders its references.
+
+# Example
+```
+#[macro_use] extern crate frunk_core;
+use aris::expr::Expr;
+use aris::parser::parse_unwrap as p;
+use aris::proofs::pooledproof::PooledProof;
+use aris::proofs::tree::{TreeLine, TreeProof};
+use aris::rules::RuleM;
+
+let tree = TreeProof {
+    premises: vec![p("A"), p("B")],
+    lines: vec![TreeLine::Step { expr: p("A & B"), rule: RuleM::AndIntro, deps: vec![1, 2], sdeps: vec![] }],
+};
+let prf = PooledProof::<HList![Expr]>::try_from(&tree).unwrap();
+assert_eq!(TreeProof::from(&prf), tree);
+```
+*/
+
+use super::pooledproof::PooledProof;
+use super::Citation;
+use super::Justification;
+use super::PjRef;
+use super::Proof;
+use crate::expr::Expr;
+use crate::rules::Rule;
+use crate::rules::RuleM;
+
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::hash::Hash;
+use std::ops::RangeInclusive;
+
+use frunk_core::coproduct::Coproduct;
+use frunk_core::hlist::HCons;
+use frunk_core::Coprod;
+
+/// A proof, or the contents of a subproof, as a plain recursive structure
+#[derive(Clone, Debug, PartialEq, Hash)]
+pub struct TreeProof {
+    pub premises: Vec<Expr>,
+    pub lines: Vec<TreeLine>,
+}
+
+/// A line of a `TreeProof`
+#[derive(Clone, Debug, PartialEq, Hash)]
+pub enum TreeLine {
+    /// A step, citing lines and subproofs by display line number
+    Step { expr: Expr, rule: Rule, deps: Vec<usize>, sdeps: Vec<RangeInclusive<usize>> },
+    /// A nested subproof
+    Subproof(TreeProof),
+}
+
+/// Reasons a `TreeProof` can't be converted into a proof
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TreeError {
+    /// The step on line `cited_by` cites line `line`, which doesn't exist
+    NoSuchLine { line: usize, cited_by: usize },
+    /// The step on line `cited_by` cites the subproof spanning `lines`, but no subproof spans them
+    NoSuchSubproof { lines: RangeInclusive<usize>, cited_by: usize },
+}
+
+impl std::fmt::Display for TreeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            TreeError::NoSuchLine { line, cited_by } => write!(f, "Line {cited_by} cites line {line}, which doesn't exist."),
+            TreeError::NoSuchSubproof { lines, cited_by } => write!(f, "Line {} cites lines {}-{}, which aren't a subproof.", cited_by, lines.start(), lines.end()),
+        }
+    }
+}
+
+impl TreeProof {
+    /// Copy a proof into a `TreeProof`. A step citing an empty subproof loses
+    /// that citation, since the subproof spans no lines.
+    pub fn from_proof<P: Proof>(prf: &P) -> TreeProof {
+        fn aux<P: Proof>(top: &P, sub: &P::Subproof, line_numbers: &HashMap<PjRef<P>, usize>) -> TreeProof {
+            use frunk_core::coproduct::Coproduct::{Inl, Inr};
+            let premises = sub.premises().iter().filter_map(|pr| top.lookup_premise(pr)).collect();
+            let lines = sub
+                .lines()
+                .into_iter()
+                .filter_map(|line| match line {
+                    Inl(jr) => top.lookup_step(&jr).map(|just| {
+                        let citations = just.citations(top, |r| line_numbers.get(r).copied());
+                        let deps = citations.iter().filter_map(|citation| if let Citation::Line { line, .. } = citation { Some(*line) } else { None }).collect();
+                        let sdeps = citations.iter().filter_map(|citation| if let Citation::Lines { span, .. } = citation { Some(span.clone()) } else { None }).collect();
+                        let Justification(expr, rule, _, _) = just;
+                        TreeLine::Step { expr, rule, deps, sdeps }
+                    }),
+                    Inr(Inl(sr)) => top.lookup_subproof(&sr).map(|sub| TreeLine::Subproof(aux(top, &sub, line_numbers))),
+                    Inr(Inr(void)) => match void {},
+                })
+                .collect();
+            TreeProof { premises, lines }
+        }
+        let line_numbers: HashMap<PjRef<P>, usize> = prf.iter_lines().filter_map(|info| Some((info.reference.subset().ok()?, info.line?))).collect();
+        aux(prf, prf.top_level_proof(), &line_numbers)
+    }
+
+    /// Build a new proof from a `TreeProof`
+    pub fn to_proof<P: Proof>(&self) -> Result<P, TreeError> {
+        let mut prf = P::new();
+        let mut built = Built { lines: vec![], subproofs: HashMap::new(), steps: vec![] };
+        built.add(&mut prf, self);
+        let Built { lines, subproofs, steps } = built;
+        for (jr, cited_by, deps, sdeps) in steps {
+            let deps = deps.iter().map(|line| line.checked_sub(1).and_then(|i| lines.get(i)).cloned().ok_or(TreeError::NoSuchLine { line: *line, cited_by })).collect::<Result<Vec<_>, _>>()?;
+            let sdeps = sdeps.iter().map(|span| subproofs.get(span).cloned().ok_or_else(|| TreeError::NoSuchSubproof { lines: span.clone(), cited_by })).collect::<Result<Vec<_>, _>>()?;
+            prf.with_mut_step(&jr, |Justification(_, _, d, s)| {
+                *d = deps;
+                *s = sdeps;
+            });
+        }
+        Ok(prf)
+    }
+
+    /// The proof written out a line at a time, in display order, which
+    /// doesn't depend on how the proof stores its lines. Each line is
+    /// `<depth> premise <formula>`, `<depth> subproof` before a subproof's
+    /// lines, or `<depth> step <formula> ; <rule> ; <deps> ; <sdeps>`. Depth
+    /// is 0 outside of any subproof, formulas are written as canonical
+    /// strings, rules by their serialized names, and dependencies as
+    /// comma-separated line numbers, with subproofs as `<first>-<last>`.
+    pub fn canonical_string(&self) -> String {
+        fn aux(tree: &TreeProof, depth: usize, out: &mut String) {
+            for premise in &tree.premises {
+                let _ = writeln!(out, "{depth} premise {}", premise.to_canonical_string());
+            }
+            for line in &tree.lines {
+                match line {
+                    TreeLine::Step { expr, rule, deps, sdeps } => {
+                        let deps = deps.iter().map(|dep| format!("{dep}")).collect::<Vec<_>>().join(",");
+                        let sdeps = sdeps.iter().map(|sdep| format!("{}-{}", sdep.start(), sdep.end())).collect::<Vec<_>>().join(",");
+                        let _ = writeln!(out, "{depth} step {} ; {} ; {deps} ; {sdeps}", expr.to_canonical_string(), RuleM::to_serialized_name(*rule));
+                    }
+                    TreeLine::Subproof(sub) => {
+                        let _ = writeln!(out, "{depth} subproof");
+                        aux(sub, depth + 1, out);
+                    }
+                }
+            }
+        }
+        let mut out = String::new();
+        aux(self, 0, &mut out);
+        out
+    }
+}
+
+/// What's been added to a proof while building it from a `TreeProof`, so
+/// that dependencies can be filled in once every line exists
+struct Built<'a, R, J, S> {
+    /// References of the lines added so far, by line number - 1
+    lines: Vec<Coprod!(R, J)>,
+    /// Subproofs that span at least one line, by their span
+    subproofs: HashMap<RangeInclusive<usize>, S>,
+    /// Steps added so far, with their line number and dependencies
+    steps: Vec<(J, usize, &'a [usize], &'a [RangeInclusive<usize>])>,
+}
+
+impl<'a, R, J: Clone, S: Clone + Eq + Hash> Built<'a, R, J, S> {
+    fn add<P: Proof<PremiseReference = R, JustificationReference = J, SubproofReference = S>>(&mut self, dst: &mut P, tree: &'a TreeProof) {
+        for e in tree.premises.iter() {
+            self.lines.push(Coproduct::inject(dst.add_premise(e.clone())));
+        }
+        for line in tree.lines.iter() {
+            match line {
+                TreeLine::Step { expr, rule, deps, sdeps } => {
+                    let jr = dst.add_step(Justification(expr.clone(), *rule, vec![], vec![]));
+                    self.lines.push(Coproduct::inject(jr.clone()));
+                    self.steps.push((jr, self.lines.len(), deps, sdeps));
+                }
+                TreeLine::Subproof(sub) => {
+                    let sr = dst.add_subproof();
+                    let first = self.lines.len() + 1;
+                    dst.with_mut_subproof(&sr, |dst| self.add(dst, sub));
+                    if self.lines.len() >= first {
+                        self.subproofs.insert(first..=self.lines.len(), sr);
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl<Tail: Default + Clone> From<&PooledProof<HCons<Expr, Tail>>> for TreeProof {
+    fn from(prf: &PooledProof<HCons<Expr, Tail>>) -> TreeProof {
+        TreeProof::from_proof(prf)
+    }
+}
+
+impl<Tail: Default + Clone> TryFrom<&TreeProof> for PooledProof<HCons<Expr, Tail>> {
+    type Error = TreeError;
+    fn try_from(tree: &TreeProof) -> Result<Self, TreeError> {
+        tree.to_proof()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::parser::parse_unwrap as p;
+    use crate::rules::RuleM;
+
+    use frunk_core::HList;
+
+    type P = PooledProof<HList![Expr]>;
+
+    /*
+    1 | P -> Q
+      | ----------
+    2 | | ~Q
+      | | ----------
+    3 | | | P
+      | | | ----------
+    4 | | | Q ; ImpElim [1, 3]
+    5 | | | _|_ ; ContradictionIntro [2, 4]
+    6 | | ~P ; NotIntro [3..5]
+    7 | ~Q -> ~P ; ImpIntro [2..6]
+    */
+    fn fixture() -> TreeProof {
+        let step = |expr, rule, deps: Vec<usize>, sdeps: Vec<RangeInclusive<usize>>| TreeLine::Step { expr: p(expr), rule, deps, sdeps };
+        TreeProof {
+            premises: vec![p("P -> Q")],
+            lines: vec![
+                TreeLine::Subproof(TreeProof {
+                    premises: vec![p("~Q")],
+                    lines: vec![
+                        TreeLine::Subproof(TreeProof { premises: vec![p("P")], lines: vec![step("Q", RuleM::ImpElim, vec![1, 3], vec![]), step("_|_", RuleM::ContradictionIntro, vec![2, 4], vec![])] }),
+                        step("~P", RuleM::NotIntro, vec![], vec![3..=5]),
+                    ],
+                }),
+                step("~Q -> ~P", RuleM::ImpIntro, vec![], vec![2..=6]),
+            ],
+        }
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let tree = fixture();
+        let prf = P::try_from(&tree).unwrap();
+        assert!(prf.verify_all().is_fully_verified());
+        assert_eq!(TreeProof::from(&prf), tree);
+
+        // The dependencies point at the lines with those numbers
+        let lines = prf.iter_lines().filter_map(|info| Some((info.line?, info.reference.subset().ok()?))).collect::<HashMap<usize, PjRef<P>>>();
+        let Justification(_, _, deps, _) = prf.lookup_step(&Coproduct::uninject(lines[&5].clone()).unwrap()).unwrap();
+        assert_eq!(deps, vec![lines[&2].clone(), lines[&4].clone()]);
+        let outer: <P as Proof>::SubproofReference = Coproduct::uninject(prf.lines()[0]).unwrap();
+        assert_eq!(prf.lookup_step(&prf.direct_lines()[0]).unwrap().3, vec![outer]);
+
+        // And back again, from a proof built by the existing constructors
+        let prf: P = crate::proofs::proof_tests::demo_proof_1();
+        let tree = TreeProof::from(&prf);
+        assert_eq!(TreeProof::from(&P::try_from(&tree).unwrap()), tree);
+    }
+
+    #[test]
+    fn test_canonical_string() {
+        let expected = "0 premise (P → Q)\n0 subproof\n1 premise ¬Q\n1 subproof\n2 premise P\n2 step Q ; MODUS_PONENS ; 1,3 ; \n2 step ⊥ ; CONTRADICTION ; 2,4 ; \n1 step ¬P ; PROOF_BY_CONTRADICTION ;  ; 3-5\n0 step (¬Q → ¬P) ; CONDITIONAL_PROOF ;  ; 2-6\n";
+        assert_eq!(fixture().canonical_string(), expected);
+    }
+
+    #[test]
+    fn test_bad_citations() {
+        let mut tree = fixture();
+        if let TreeLine::Step { deps, .. } = &mut tree.lines[1] {
+            deps.push(8);
+        }
+        assert_eq!(P::try_from(&tree), Err(TreeError::NoSuchLine { line: 8, cited_by: 7 }));
+
+        let mut tree = fixture();
+        if let TreeLine::Step { sdeps, .. } = &mut tree.lines[1] {
+            sdeps[0] = 2..=5;
+        }
+        assert_eq!(P::try_from(&tree), Err(TreeError::NoSuchSubproof { lines: 2..=5, cited_by: 7 }));
+    }
+
+    #[test]
+    fn test_citations() {
+        use frunk_core::coproduct::Coproduct;
+        let mut prf = P::new();
+        let r1: PjRef<P> = Coproduct::inject(prf.add_premise(p("A")));
+        let r2: PjRef<P> = Coproduct::inject(prf.add_premise(p("C")));
+        let sub = prf.add_subproof();
+        prf.with_mut_subproof(&sub, |sub| sub.add_premise(p("B"))).unwrap();
+        let empty = prf.add_subproof();
+        let step = prf.add_step(Justification(p("A"), RuleM::Reit, vec![r2, r1], vec![empty, sub]));
+        let just = prf.lookup_step(&step).unwrap();
+
+        let line_numbers: HashMap<PjRef<P>, usize> = prf.iter_lines().filter_map(|info| Some((info.reference.subset().ok()?, info.line?))).collect();
+        let citations = just.citations(&prf, |r| line_numbers.get(r).copied());
+        assert_eq!(citations, vec![Citation::Line { dep: r1, line: 1 }, Citation::Line { dep: r2, line: 2 }, Citation::Lines { sdep: sub, span: 3..=3 }, Citation::EmptySubproof(empty)]);
+        assert_eq!(citations.iter().map(|citation| citation.to_string()).collect::<Vec<_>>(), vec!["1", "2", "3-3", "?"]);
+
+        // A line that isn't numbered, as when it was just deleted, goes last
+        let citations = just.citations(&prf, |r| if *r == r1 { None } else { line_numbers.get(r).copied() });
+        assert_eq!(citations[..2], [Citation::Line { dep: r2, line: 2 }, Citation::MissingLine(r1)]);
+
+        // An empty subproof isn't written by the exporters
+        assert_eq!(TreeProof::from_proof(&prf).lines.last(), Some(&TreeLine::Step { expr: p("A"), rule: RuleM::Reit, deps: vec![1, 2], sdeps: vec![3..=3] }));
+    }
+}
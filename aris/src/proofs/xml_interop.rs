@@ -1,38 +1,552 @@
 use crate::expr::Expr;
+use crate::proofs::ExtractError;
 use crate::proofs::Justification;
+use crate::proofs::LineId;
+use crate::proofs::LineNames;
 use crate::proofs::PjRef;
 use crate::proofs::Proof;
+use crate::proofs::tree::TreeProof;
 use crate::rules::RuleM;
 
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
 use std::collections::HashMap;
 use std::io::{Read, Write};
 
 use frunk_core::coproduct::Coproduct;
 use xml::reader::EventReader;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct ProofMetaData {
     pub author: Option<String>, // TODO: it seems like the java SaveManager might treat this as a Vec<String>
     pub hash: Option<String>,
+    pub title: Option<String>,
+    /// When the proof was first saved, and last saved, as ISO 8601 date-times
+    /// such as `2023-04-05T06:07:08Z`
+    pub created: Option<String>,
+    pub modified: Option<String>,
+    /// An identifier for the assignment the proof is for, which is part of
+    /// its seal
+    pub assignment: Option<String>,
+    /// A code proving that the locked lines, goals, and assignment are as the
+    /// instructor gave them, from `export::submission::seal`
+    pub seal: Option<String>,
     pub goals: Vec<Expr>,
+    /// What was typed for lines whose formula was written differently, or
+    /// didn't parse, by stable id. These are saved as lines' `raw`
+    /// attributes, and aren't part of the `submission_hash`.
+    pub raw_inputs: HashMap<LineId, String>,
+    /// Stable ids of the lines that can't be edited or deleted, such as the
+    /// premises of an assigned problem. These are saved as lines' `locked`
+    /// attributes.
+    pub locked: BTreeSet<LineId>,
+    /// Notes on lines, by stable id, which are saved as `<comment>` elements
+    /// of the lines and aren't part of the `submission_hash`
+    pub comments: BTreeMap<LineId, String>,
+    /// Verification results to save with the proof, as `<annotation>`
+    /// elements of its lines
+    pub annotations: Option<Annotations>,
 }
 
-pub fn proof_from_xml<P: Proof, R: Read>(r: R) -> Result<(P, ProofMetaData), String> {
-    let mut er = EventReader::new(r);
+impl ProofMetaData {
+    /// Record that the proof is being saved at `now`, an ISO 8601 date-time,
+    /// such as one from `iso8601`
+    pub fn mark_modified(&mut self, now: String) {
+        self.created.get_or_insert_with(|| now.clone());
+        self.modified = Some(now);
+    }
+}
+
+/// Write a time as an ISO 8601 date-time in UTC, to the second, for
+/// `ProofMetaData::mark_modified` in native code
+pub fn iso8601(time: std::time::SystemTime) -> String {
+    let seconds = time.duration_since(std::time::UNIX_EPOCH).map_or(0, |since| since.as_secs());
+    let (days, seconds) = (seconds / 86400, seconds % 86400);
+    // The proleptic Gregorian calendar repeats every 400 years, which are
+    // 146097 days, and its years are taken to start in March, so that leap
+    // days are at the end of them
+    let days = days + 719468;
+    let (era, day_of_era) = (days / 146097, days % 146097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_from_march = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_from_march + 2) / 5 + 1;
+    let month = if month_from_march < 10 { month_from_march + 3 } else { month_from_march - 9 };
+    let year = era * 400 + year_of_era + u64::from(month <= 2);
+    format!("{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}Z", seconds / 3600, seconds / 60 % 60, seconds % 60)
+}
+
+/// Each line's verification result, for sending a graded proof back to a
+/// student. They're advisory: loading a proof doesn't check them against the
+/// proof, and they're only saved or exported while the proof is the one they
+/// were computed for, so that re-saving a proof without editing it keeps them.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Annotations {
+    /// Each line's result, by stable id: `Ok` if it verified, or the message
+    /// of the error it failed with
+    pub lines: HashMap<LineId, Result<(), String>>,
+    /// The `Proof::content_hash` of the proof they're for
+    pub content_hash: u64,
+}
+
+impl Annotations {
+    /// The results of `Proof::verify_all`, with lines named in error messages
+    /// by their line numbers
+    pub fn from_verification<P: Proof>(prf: &P) -> Annotations {
+        let names = LineNames::new(prf);
+        let lines = prf.verify_all().iter().filter_map(|(_, r, result)| Some((prf.stable_id_of(r)?, result.as_ref().map(|_| ()).map_err(|err| err.describe(|r| names.line(r), |s| names.subproof(s)))))).collect();
+        Annotations { lines, content_hash: prf.content_hash() }
+    }
+
+    /// Whether these are the results for `prf`, rather than for a version of
+    /// it from before an edit
+    pub fn is_for<P: Proof>(&self, prf: &P) -> bool {
+        self.content_hash == prf.content_hash()
+    }
+
+    /// Each line's result by display line number, which is empty if these
+    /// aren't the results for `prf`
+    pub fn by_line_number<P: Proof>(&self, prf: &P) -> HashMap<usize, Result<(), String>> {
+        if !self.is_for(prf) {
+            return HashMap::new();
+        }
+        prf.iter_lines().filter_map(|info| Some((info.line?, self.lines.get(&prf.stable_id_of(&info.reference.subset().ok()?)?)?.clone()))).collect()
+    }
+}
+
+/// Whether a loaded proof's `<hash>` matches its proof, author, and goals, as
+/// computed by `submission_hash`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ImportedHashStatus {
+    /// The file is as it was saved
+    Matches,
+    /// The file was edited after it was saved, or was saved by a version of
+    /// Aris that computed the hash differently
+    Mismatch,
+    /// The file has no hash
+    Absent,
+}
+
+/// A reason a proof can't be loaded from XML, and where in the file it is
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct XmlImportError {
+    pub kind: XmlImportErrorKind,
+    /// The 1-based line and column of the start of the element the error is
+    /// about, or of the malformed XML
+    pub line: u64,
+    pub column: u64,
+    /// The elements that the error is in, below the root element, each
+    /// numbered by its position among its siblings of the same name, as in
+    /// `proof[2] > step[5]`. It's empty for an error in the root element.
+    pub path: String,
+}
+
+impl std::fmt::Display for XmlImportError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let XmlImportError { kind, line, column, path } = self;
+        match &**path {
+            "" => write!(f, "Line {line}, column {column}: {kind}"),
+            path => write!(f, "Line {line}, column {column}, in {path}: {kind}"),
+        }
+    }
+}
+
+/// Reasons a proof can't be loaded from XML
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum XmlImportErrorKind {
+    /// The document isn't well-formed XML
+    Syntax { message: String },
+    /// An element that isn't part of the format
+    UnknownElement { element: String },
+    /// An attribute that isn't part of the format
+    UnknownAttribute { element: String, attribute: String },
+    /// An element is missing an attribute it needs
+    MissingAttribute { element: String, attribute: String },
+    /// An attribute's value is malformed, e.g. a stable id that isn't a number
+    InvalidAttribute { element: String, attribute: String, value: String },
+    /// A subproof step doesn't say which proof element has its contents
+    MissingSubproofId { line_id: String },
+    /// The step whose line id is `line_id` uses a rule that doesn't exist
+    UnknownRule { name: String, line_id: String },
+    /// The formula of the line whose line id is `line_id`, or of a goal if it's
+    /// `None`, doesn't parse
+    UnparseableFormula { text: String, line_id: Option<String> },
+    /// Two lines have the same line id
+    DuplicateLine { line_id: String },
+    /// Two lines have the same stable id
+    DuplicateStableId { id: usize },
+    /// The step whose line id is `cited_by` cites `id`, which isn't the line id
+    /// of any line or subproof
+    DanglingReference { id: String, cited_by: String },
+    /// A proof element whose id isn't the subproof of any step
+    OrphanSubproof { proof_id: String },
+    /// The file is in a newer major version of the format than `supported`
+    UnsupportedVersion { found: String, supported: String },
+}
+
+impl std::fmt::Display for XmlImportErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        use XmlImportErrorKind::*;
+        match self {
+            Syntax { message } => write!(f, "Invalid XML: {message}"),
+            UnknownElement { element } => write!(f, "Unknown element <{element}>."),
+            UnknownAttribute { element, attribute } => write!(f, "Unknown attribute {attribute:?} on <{element}>."),
+            MissingAttribute { element, attribute } => write!(f, "<{element}> is missing the attribute {attribute:?}."),
+            InvalidAttribute { element, attribute, value } => write!(f, "Invalid value {value:?} for the attribute {attribute:?} on <{element}>."),
+            MissingSubproofId { line_id } => write!(f, "The subproof at line id {line_id} doesn't name its proof."),
+            UnknownRule { name, line_id } => write!(f, "Unknown rule {name:?} at line id {line_id}."),
+            UnparseableFormula { text, line_id: Some(line_id) } => write!(f, "Failed to parse {text:?} at line id {line_id}."),
+            UnparseableFormula { text, line_id: None } => write!(f, "Failed to parse the goal {text:?}."),
+            DuplicateLine { line_id } => write!(f, "More than one line has the line id {line_id}."),
+            DuplicateStableId { id } => write!(f, "More than one line has the stable id {id}."),
+            DanglingReference { id, cited_by } => write!(f, "The step at line id {cited_by} cites line id {id}, which doesn't exist."),
+            OrphanSubproof { proof_id } => write!(f, "Proof {proof_id} is not the subproof of any step."),
+            UnsupportedVersion { found, supported } => write!(f, "The file is in version {found} of the format, which is newer than this version of Aris supports ({supported})."),
+        }
+    }
+}
+
+/// Elements of the format, which may appear anywhere
+const ELEMENTS: &[&str] = &["bram", "program", "version", "metadata", "author", "hash", "title", "created", "modified", "assignment", "seal", "proof", "assumption", "step", "sen", "raw", "rule", "premise", "goal", "annotation", "comment"];
+
+/// The version of the format that `xml_from_proof_and_metadata` writes, as the
+/// `version` attribute of `<bram>`.
+///
+/// Files in an older version, or without a version, are read with the
+/// accommodations of `proof_from_xml_compat`. Files in a newer minor version
+/// are read as far as this version of the format goes, ignoring elements and
+/// attributes it doesn't have with a warning, so that additions to the format
+/// only need a new minor version. Files in a newer major version aren't read.
+pub const FORMAT_VERSION: (u64, u64) = (1, 6);
 
-    let mut metadata = ProofMetaData { author: None, hash: None, goals: vec![] };
+/// The elements of `ELEMENTS` that only have other elements in them, rather
+/// than text
+const CONTAINER_ELEMENTS: &[&str] = &["bram", "metadata", "proof", "assumption", "step", "goal"];
 
-    let mut element_stack = vec![];
-    let mut attribute_stack = vec![];
+fn known_attributes(element: &str) -> &'static [&'static str] {
+    match element {
+        "bram" => &["version", "nextid"],
+        "proof" => &["id"],
+        "assumption" | "step" => &["linenum", "stableid", "raw", "locked"],
+        "annotation" => &["verified"],
+        _ => &[],
+    }
+}
+
+/// Accommodations `proof_from_xml_compat` made for a file saved by an older
+/// or newer version of Aris, or for a file Aris wouldn't have saved
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CompatWarning {
+    /// The file isn't UTF-8, so it was read as Windows-1252
+    TranscodedFromWindows1252,
+    /// The step whose line id is `line_id` uses `from`, an older name for the
+    /// rule `to`
+    RenamedRule { from: String, to: String, line_id: String },
+    /// The formula of the line whose line id is `line_id`, or of a goal if it's
+    /// `None`, applies predicates to no arguments, as in `P()`, which were read
+    /// as variables
+    NullaryApplication { line_id: Option<String> },
+    /// A goal is in `<parent>` rather than in a proof
+    GoalOutsideProof { parent: String },
+    /// An element that isn't part of this version of the format, which was
+    /// ignored along with its contents. `line`, `column`, and `path` are where
+    /// it is, as in `XmlImportError`.
+    UnknownElement { element: String, line: u64, column: u64, path: String },
+    /// An attribute that isn't part of this version of the format, which was
+    /// ignored. `line`, `column`, and `path` are where its element is.
+    UnknownAttribute { element: String, attribute: String, line: u64, column: u64, path: String },
+    /// Text directly inside an element that only has other elements in it,
+    /// which was ignored. `line`, `column`, and `path` are where the text is,
+    /// and the path of the element it's in.
+    SkippedContent { text: String, line: u64, column: u64, path: String },
+    /// The subproof started by the step whose line id is `line_id` doesn't
+    /// have both a premise and a step, which deleting lines never leaves
+    IncompleteSubproof { line_id: String },
+}
+
+impl CompatWarning {
+    /// Whether the warning is for something in the file that was ignored,
+    /// rather than read in a different way
+    pub fn is_ignored_content(&self) -> bool {
+        matches!(self, CompatWarning::UnknownElement { .. } | CompatWarning::UnknownAttribute { .. } | CompatWarning::SkippedContent { .. })
+    }
+}
+
+/// Summarize the warnings for ignored content, by how many of each kind of
+/// thing was ignored, as in "2 elements from a newer version were ignored",
+/// for telling the user about them before they're listed
+pub fn summarize_ignored_content(warnings: &[CompatWarning]) -> Vec<String> {
+    let count = |f: fn(&CompatWarning) -> bool| warnings.iter().filter(|w| f(w)).count();
+    let kinds = [
+        (count(|w| matches!(w, CompatWarning::UnknownElement { .. })), "element", "elements", " from a newer version"),
+        (count(|w| matches!(w, CompatWarning::UnknownAttribute { .. })), "attribute", "attributes", " from a newer version"),
+        (count(|w| matches!(w, CompatWarning::SkippedContent { .. })), "piece of text", "pieces of text", " in an unexpected place"),
+    ];
+    kinds
+        .iter()
+        .filter(|(n, ..)| *n > 0)
+        .map(|(n, one, many, source)| match n {
+            1 => format!("1 {one}{source} was ignored"),
+            n => format!("{n} {many}{source} were ignored"),
+        })
+        .collect()
+}
+
+/// Describe where something is in a file, as `XmlImportError` does
+fn describe_position(line: u64, column: u64, path: &str) -> String {
+    match path {
+        "" => format!("line {line}, column {column}"),
+        path => format!("line {line}, column {column}, in {path}"),
+    }
+}
+
+impl std::fmt::Display for CompatWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        use CompatWarning::*;
+        match self {
+            TranscodedFromWindows1252 => write!(f, "The file was read as Windows-1252, since it isn't UTF-8."),
+            RenamedRule { from, to, line_id } => write!(f, "The rule {from:?} at line id {line_id} was read as {to:?}."),
+            NullaryApplication { line_id: Some(line_id) } => write!(f, "Predicates with no arguments at line id {line_id} were read as variables."),
+            NullaryApplication { line_id: None } => write!(f, "Predicates with no arguments in a goal were read as variables."),
+            GoalOutsideProof { parent } => write!(f, "A goal in <{parent}> was read as a goal of the proof."),
+            UnknownElement { element, line, column, path } => write!(f, "The unknown element <{element}> at {} was ignored.", describe_position(*line, *column, path)),
+            UnknownAttribute { element, attribute, line, column, path } => write!(f, "The unknown attribute {attribute:?} on <{element}> at {} was ignored.", describe_position(*line, *column, path)),
+            SkippedContent { text, line, column, path } => write!(f, "The text {text:?} at {} was ignored.", describe_position(*line, *column, path)),
+            IncompleteSubproof { line_id } => write!(f, "The subproof at line id {line_id} is missing a premise or a step."),
+        }
+    }
+}
+
+/// Older names of rules, as written by the Java version of Aris, and the
+/// serialized names they stand for
+const RULE_ALIASES: &[(&str, &str)] = &[
+    ("REITERATE", "REITERATION"),
+    ("DOUBLE_NEGATION", "DOUBLENEGATION"),
+    ("DEMORGAN", "DE_MORGAN"),
+    ("TAUTOLOGY", "TAUTOLOGY_INTRO"),
+    ("CONTRADICTION_ELIMINATION", "PRINCIPLE_OF_EXPLOSION"),
+    ("BICONDITIONAL_INTRODUCTION", "BICONDITIONAL_INTRO"),
+    ("BICONDITIONAL_ELIMINATION", "BICONDITIONAL_ELIM"),
+    ("BIIMPLICATION", "BI_IMPLICATION"),
+];
+
+/// Characters of Windows-1252 that differ from Latin-1, for bytes 0x80 to 0x9F.
+/// The bytes it leaves undefined are read as the Latin-1 control characters.
+const WINDOWS_1252_HIGH: [char; 32] = [
+    '\u{20AC}', '\u{81}', '\u{201A}', '\u{192}', '\u{201E}', '\u{2026}', '\u{2020}', '\u{2021}', '\u{2C6}', '\u{2030}', '\u{160}', '\u{2039}', '\u{152}', '\u{8D}', '\u{17D}', '\u{8F}',
+    '\u{90}', '\u{2018}', '\u{2019}', '\u{201C}', '\u{201D}', '\u{2022}', '\u{2013}', '\u{2014}', '\u{2DC}', '\u{2122}', '\u{161}', '\u{203A}', '\u{153}', '\u{9D}', '\u{17E}', '\u{178}',
+];
+
+fn decode_windows_1252(bytes: &[u8]) -> String {
+    bytes.iter().map(|&b| if (0x80..0xA0).contains(&b) { WINDOWS_1252_HIGH[usize::from(b - 0x80)] } else { char::from(b) }).collect()
+}
+
+/// Load a proof and its metadata, and check them against the hash they were
+/// saved with. See `proof_from_xml_compat` for how files saved by other
+/// versions of Aris are read, along with warnings for the parts of the file
+/// that were ignored, and `proof_from_xml_lenient` for reading as much of a
+/// damaged file as possible.
+pub fn proof_from_xml<P: Proof, R: Read>(r: R) -> Result<(P, ProofMetaData, ImportedHashStatus), XmlImportError> {
+    proof_from_xml_compat(r).map(|(prf, metadata, status, _)| (prf, metadata, status))
+}
+
+/// Like `proof_from_xml`, also returning a warning for each accommodation made
+/// for a file saved by another version of Aris, so that the user can be told
+/// that the file was converted.
+///
+/// Files without a `FORMAT_VERSION`, such as those saved by the Java version of
+/// Aris, can have older rule names, predicates applied to no arguments, and
+/// goals outside of the proof. Files in a newer minor version can have
+/// elements, attributes, and text that are ignored, each with a warning saying
+/// where it is. Text that isn't UTF-8 is read as Windows-1252.
+pub fn proof_from_xml_compat<P: Proof, R: Read>(r: R) -> Result<(P, ProofMetaData, ImportedHashStatus, Vec<CompatWarning>), XmlImportError> {
+    match proof_from_xml_lenient(r) {
+        (Some(loaded), errors) if errors.is_empty() => Ok(loaded),
+        (_, errors) => Err(errors.into_iter().next().expect("a file that failed to load has an error")),
+    }
+}
+
+/// Like `proof_from_xml_compat`, reading past the problems in a file that only
+/// affect part of the proof, and returning every problem found, in the order
+/// of the file, so that the user can open what can be read and be shown all
+/// of what can't.
+///
+/// Steps with unknown rules are read with `RuleM::EmptyRule`. Lines whose
+/// formulas don't parse are read as blank, with their text as their
+/// `ProofMetaData::raw_inputs`. Goals that don't parse, and citations of line
+/// ids that don't exist, are left out. A problem that the rest of the file
+/// can't be read past, such as malformed XML, is the last error, and there's
+/// no proof.
+pub fn proof_from_xml_lenient<P: Proof, R: Read>(r: R) -> (Option<(P, ProofMetaData, ImportedHashStatus, Vec<CompatWarning>)>, Vec<XmlImportError>) {
+    let mut warnings = vec![];
+    let mut errors = vec![];
+    let result = read_text(r, &mut warnings).and_then(|text| {
+        let mut er = EventReader::new(text.as_bytes());
+        let loaded = import(&mut er, None, "", &mut warnings, &mut errors)?;
+        read_to_end(&mut er)?;
+        Ok(loaded)
+    });
+    match result {
+        Ok((prf, metadata, status)) => (Some((prf, metadata, status, warnings)), errors),
+        Err(err) => {
+            errors.push(err);
+            (None, errors)
+        }
+    }
+}
+
+/// Load every proof of a workbook saved by `workbook_to_xml`, in order. A file
+/// with a single proof is loaded as a workbook of one proof.
+pub fn workbook_from_xml<P: Proof, R: Read>(r: R) -> Result<Vec<(P, ProofMetaData, ImportedHashStatus)>, XmlImportError> {
+    let mut warnings = vec![];
+    let mut errors = vec![];
+    let text = read_text(r, &mut warnings)?;
+    let proofs = import_workbook(&mut EventReader::new(text.as_bytes()), &mut warnings, &mut errors);
+    match errors.into_iter().next() {
+        Some(err) => Err(err),
+        None => proofs,
+    }
+}
+
+/// Load the proofs of a workbook, or the proof of a single-proof file, in the
+/// manner of `import`
+fn import_workbook<P: Proof, R: Read>(er: &mut EventReader<R>, warnings: &mut Vec<CompatWarning>, errors: &mut Vec<XmlImportError>) -> Result<Vec<(P, ProofMetaData, ImportedHashStatus)>, XmlImportError> {
+    use xml::common::Position;
+    use xml::reader::XmlEvent::*;
+    use XmlImportErrorKind::*;
+    let mut proofs = vec![];
+    let mut in_workbook = false;
+    loop {
+        let event = er.next();
+        let position = er.position();
+        let at = |kind| XmlImportError { kind, line: position.row + 1, column: position.column + 1, path: String::new() };
+        match event {
+            Ok(StartElement { name, attributes, .. }) if name.local_name == "workbook" && !in_workbook => {
+                if let Some(version) = attributes.iter().find(|x| x.name.local_name == "version") {
+                    let (major, _) = parse_format_version(&version.value).ok_or_else(|| at(InvalidAttribute { element: "workbook".into(), attribute: "version".into(), value: version.value.clone() }))?;
+                    if major > FORMAT_VERSION.0 {
+                        return Err(at(UnsupportedVersion { found: version.value.clone(), supported: format!("{}.{}", FORMAT_VERSION.0, FORMAT_VERSION.1) }));
+                    }
+                }
+                in_workbook = true;
+            }
+            // Each proof of a workbook is a `<bram>` element, as it would be
+            // saved on its own
+            Ok(event @ StartElement { .. }) if !in_workbook || matches!(&event, StartElement { name, .. } if name.local_name == "bram") => {
+                let path = if in_workbook { format!("bram[{}]", proofs.len() + 1) } else { String::new() };
+                proofs.push(import(er, Some(event), &path, warnings, errors)?);
+                if !in_workbook {
+                    read_to_end(er)?;
+                    return Ok(proofs);
+                }
+            }
+            Ok(StartElement { name, .. }) => return Err(at(UnknownElement { element: name.local_name })),
+            Ok(EndElement { .. }) => {
+                read_to_end(er)?;
+                return Ok(proofs);
+            }
+            Ok(EndDocument) => return Ok(proofs),
+            Ok(_) => (),
+            Err(e) => return Err(XmlImportError { kind: Syntax { message: e.msg().into() }, line: e.position().row + 1, column: e.position().column + 1, path: String::new() }),
+        }
+    }
+}
+
+/// Read a file's text, which is read as Windows-1252 if it isn't UTF-8
+fn read_text<R: Read>(mut r: R, warnings: &mut Vec<CompatWarning>) -> Result<String, XmlImportError> {
+    let mut bytes = vec![];
+    r.read_to_end(&mut bytes).map_err(|err| XmlImportError { kind: XmlImportErrorKind::Syntax { message: err.to_string() }, line: 1, column: 1, path: String::new() })?;
+    Ok(match String::from_utf8(bytes) {
+        Ok(text) => text,
+        Err(err) => {
+            warnings.push(CompatWarning::TranscodedFromWindows1252);
+            let text = decode_windows_1252(err.as_bytes());
+            // The declaration may name the encoding the file was meant to have
+            match (text.starts_with("<?xml"), text.find("?>")) {
+                (true, Some(end)) => format!("<?xml version=\"1.0\" encoding=\"UTF-8\"{}", &text[end..]),
+                _ => text,
+            }
+        }
+    })
+}
+
+/// Read the rest of a file after its root element, which may only have
+/// comments and whitespace
+fn read_to_end<R: Read>(er: &mut EventReader<R>) -> Result<(), XmlImportError> {
+    use xml::common::Position;
+    loop {
+        match er.next() {
+            Ok(xml::reader::XmlEvent::EndDocument) => return Ok(()),
+            Ok(_) => (),
+            Err(e) => return Err(XmlImportError { kind: XmlImportErrorKind::Syntax { message: e.msg().into() }, line: e.position().row + 1, column: e.position().column + 1, path: String::new() }),
+        }
+    }
+}
+
+/// Parse a `version` attribute, which is a major version and optionally a minor
+/// one, as in `1.0`
+fn parse_format_version(version: &str) -> Option<(u64, u64)> {
+    let (major, minor) = version.split_once('.').unwrap_or((version, "0"));
+    Some((major.parse().ok()?, minor.parse().ok()?))
+}
+
+/// An element that `import` is inside of
+struct OpenElement {
+    name: String,
+    line: u64,
+    column: u64,
+    /// Its `XmlImportError::path`
+    path: String,
+    /// How many children it has had so far with each name
+    children: HashMap<String, usize>,
+}
+
+/// Load a proof from the next element of `er`, or from `root` if it's the
+/// start of the element, which has already been read. `root_path` is the
+/// `XmlImportError::path` of the element. A warning is added to `warnings` for
+/// each accommodation made, and an error to `errors` for each problem that the
+/// rest of the file can be read past.
+fn import<P: Proof, R: Read>(er: &mut EventReader<R>, mut root: Option<xml::reader::XmlEvent>, root_path: &str, warnings: &mut Vec<CompatWarning>, errors: &mut Vec<XmlImportError>) -> Result<(P, ProofMetaData, ImportedHashStatus), XmlImportError> {
+    use XmlImportErrorKind::*;
+    // Whether the file is from before the format had versions, which is
+    // decided by its root element
+    let mut legacy = true;
+    // How many unknown elements the current element is inside of
+    let mut ignored_depth = 0;
+
+    let mut metadata = ProofMetaData::default();
+
+    let mut element_stack: Vec<OpenElement> = vec![];
     let mut contents = String::new();
+    // Where the element being started or ended is
+    let (mut at_line, mut at_column, mut at_path) = (1, 1, String::new());
+    macro_rules! at {
+        ($kind:expr) => {
+            XmlImportError { kind: $kind, line: at_line, column: at_column, path: at_path.clone() }
+        };
+    }
+    let blank = || Expr::Var { name: "__xml_interop_blank_line".into() };
 
     macro_rules! parse {
-        ($x:expr) => {{
+        ($x:expr, $line_id:expr) => {{
             let s: &str = $x;
             match crate::parser::parse(&s) {
-                Some(e) => e,
-                None if s == "" => Expr::Var { name: "__xml_interop_blank_line".into() },
-                None => return Err(format!("Failed to parse {:?}, element stack {:?}", s, element_stack)),
+                Some(e) if legacy => {
+                    let normalized = e.clone().transform(&|e| match e {
+                        Expr::Apply { func, args } if args.is_empty() => (*func, true),
+                        e => (e, false),
+                    });
+                    if normalized != e {
+                        warnings.push(CompatWarning::NullaryApplication { line_id: $line_id });
+                    }
+                    Some(normalized)
+                }
+                Some(e) => Some(e),
+                None if s == "" => Some(blank()),
+                None => {
+                    errors.push(at!(UnparseableFormula { text: s.into(), line_id: $line_id }));
+                    None
+                }
             }
         }};
     }
@@ -41,78 +555,209 @@ pub fn proof_from_xml<P: Proof, R: Read>(r: R) -> Result<(P, ProofMetaData), Str
     let mut lines_to_subs = HashMap::new();
     let mut line_refs: HashMap<_, PjRef<P>> = HashMap::new();
     let mut last_linenum = "".into();
+    let mut last_stable_id = None;
+    let mut last_raw_input = None;
+    let mut last_locked = false;
+    let mut last_comment = None;
+    let mut last_verified = false;
+    let mut last_annotation = None;
+    let mut annotations = HashMap::new();
     let mut proof = P::new();
     let mut current_proof_id = "0".into();
     let mut last_raw = "".into();
 
     let mut last_rule = "".into();
     let mut seen_premises = vec![];
+    // Steps, with their line ids, the line ids they cite, and where they are,
+    // which are resolved once every line has been added
+    let mut steps = vec![];
+    // A line whose formula doesn't parse is read as blank, keeping what was
+    // written as if it had been typed into it
+    macro_rules! parse_line {
+        () => {
+            match parse!(&last_raw, Some(last_linenum.clone())) {
+                Some(e) => e,
+                None => {
+                    last_raw_input.get_or_insert_with(|| last_raw.clone());
+                    blank()
+                }
+            }
+        };
+    }
 
     loop {
+        use xml::common::Position;
         use xml::reader::XmlEvent::*;
-        match er.next() {
+        match root.take().map(Ok).unwrap_or_else(|| er.next()) {
             //ref e if { println!("{:?}", e); false } => (),
             Ok(StartElement { name, attributes, namespace: _ }) => {
                 let element = name.local_name;
-                element_stack.push(element.clone());
-                attribute_stack.push(attributes.clone());
-                contents = String::new();
+                let position = er.position();
+                (at_line, at_column) = (position.row + 1, position.column + 1);
+                at_path = match element_stack.last_mut() {
+                    Some(parent) => {
+                        let count = parent.children.entry(element.clone()).or_insert(0);
+                        *count += 1;
+                        match &*parent.path {
+                            "" => format!("{element}[{count}]"),
+                            path => format!("{path} > {element}[{count}]"),
+                        }
+                    }
+                    None => root_path.into(),
+                };
+                if element_stack.is_empty() && element == "bram" {
+                    if let Some(version) = attributes.iter().find(|x| x.name.local_name == "version") {
+                        let (major, _) = parse_format_version(&version.value).ok_or_else(|| at!(InvalidAttribute { element: element.clone(), attribute: "version".into(), value: version.value.clone() }))?;
+                        if major > FORMAT_VERSION.0 {
+                            return Err(at!(UnsupportedVersion { found: version.value.clone(), supported: format!("{}.{}", FORMAT_VERSION.0, FORMAT_VERSION.1) }));
+                        }
+                        legacy = major < FORMAT_VERSION.0;
+                    }
+                    // Lines that were removed before the proof was saved had
+                    // ids up to this, which new lines mustn't be given
+                    if let Some(next) = attributes.iter().find(|x| x.name.local_name == "nextid") {
+                        let next = next.value.parse().map_err(|_| at!(InvalidAttribute { element: element.clone(), attribute: "nextid".into(), value: next.value.clone() }))?;
+                        proof.reserve_stable_ids(LineId(next));
+                    }
+                }
+                if ignored_depth > 0 || !ELEMENTS.contains(&&*element) {
+                    if legacy {
+                        return Err(at!(UnknownElement { element }));
+                    }
+                    if ignored_depth == 0 {
+                        warnings.push(CompatWarning::UnknownElement { element, line: at_line, column: at_column, path: at_path.clone() });
+                    }
+                    ignored_depth += 1;
+                    continue;
+                }
+                for attribute in attributes.iter().filter(|x| !known_attributes(&element).contains(&&*x.name.local_name)) {
+                    if legacy {
+                        return Err(at!(UnknownAttribute { element, attribute: attribute.name.local_name.clone() }));
+                    }
+                    warnings.push(CompatWarning::UnknownAttribute { element: element.clone(), attribute: attribute.name.local_name.clone(), line: at_line, column: at_column, path: at_path.clone() });
+                }
+                let attribute = |name: &str| attributes.iter().find(|x| x.name.local_name == name).map(|x| x.value.clone()).ok_or_else(|| at!(MissingAttribute { element: element.clone(), attribute: name.into() }));
                 match &*element {
                     "proof" => {
-                        let id = attributes.iter().find(|x| x.name.local_name == "id").expect("proof element has no id attribute");
-                        current_proof_id = id.value.clone();
+                        current_proof_id = attribute("id")?;
                     }
-                    "assumption" => {
-                        let linenum = attributes.iter().find(|x| x.name.local_name == "linenum").expect("assumption element has no linenum attribute");
-                        last_linenum = linenum.value.clone();
+                    "assumption" | "step" => {
+                        last_linenum = attribute("linenum")?;
+                        last_stable_id = match attributes.iter().find(|x| x.name.local_name == "stableid") {
+                            Some(id) => Some(LineId(id.value.parse().map_err(|_| at!(InvalidAttribute { element: element.clone(), attribute: "stableid".into(), value: id.value.clone() }))?)),
+                            None => None,
+                        };
+                        last_raw_input = attributes.iter().find(|x| x.name.local_name == "raw").map(|x| x.value.clone());
+                        last_locked = match attributes.iter().find(|x| x.name.local_name == "locked") {
+                            Some(locked) => locked.value.parse().map_err(|_| at!(InvalidAttribute { element: element.clone(), attribute: "locked".into(), value: locked.value.clone() }))?,
+                            None => false,
+                        };
+                        last_annotation = None;
+                        last_comment = None;
+                        if element == "step" {
+                            last_rule = "".into();
+                            seen_premises = vec![];
+                        }
                     }
-                    "step" => {
-                        let linenum = attributes.iter().find(|x| x.name.local_name == "linenum").expect("step element has no linenum attribute");
-                        last_linenum = linenum.value.clone();
-                        last_rule = "".into();
-                        seen_premises = vec![];
+                    "annotation" => {
+                        let verified = attribute("verified")?;
+                        last_verified = verified.parse().map_err(|_| at!(InvalidAttribute { element: element.clone(), attribute: "verified".into(), value: verified }))?;
                     }
                     _ => (),
                 }
+                element_stack.push(OpenElement { name: element, line: at_line, column: at_column, path: at_path.clone(), children: HashMap::new() });
+                contents = String::new();
             }
             Ok(Characters(data)) => {
-                contents += &data;
+                match element_stack.last() {
+                    _ if ignored_depth > 0 => {}
+                    // Legacy files are read as they always were
+                    Some(parent) if !legacy && CONTAINER_ELEMENTS.contains(&&*parent.name) => {
+                        let position = er.position();
+                        warnings.push(CompatWarning::SkippedContent { text: data.trim().into(), line: position.row + 1, column: position.column + 1, path: parent.path.clone() });
+                    }
+                    _ => contents += &data,
+                }
+            }
+            Ok(EndElement { .. }) if ignored_depth > 0 => {
+                ignored_depth -= 1;
             }
             Ok(EndElement { name }) => {
                 //println!("end {:?} {:?}", element_stack, contents);
-                let element = element_stack.pop().unwrap();
+                let OpenElement { name: element, line, column, path, .. } = element_stack.pop().unwrap();
                 assert_eq!(name.local_name, element);
-                let _attributes = attribute_stack.pop().unwrap();
-                //println!("{:?} {:?}", element, attributes);
+                (at_line, at_column, at_path) = (line, column, path);
                 macro_rules! on_current_proof {
                     ($n:ident, $x:expr) => {
                         match &*current_proof_id {
                             "0" => {
                                 let $n = &mut proof;
-                                let _ = $x;
+                                $x
                             }
                             r => {
-                                let key = subproofs[r].clone();
-                                proof.with_mut_subproof(&key, |sub| {
-                                    let $n = sub;
-                                    $x
-                                });
+                                let orphan = || at!(OrphanSubproof { proof_id: r.into() });
+                                let key = subproofs.get(r).cloned().ok_or_else(orphan)?;
+                                proof
+                                    .with_mut_subproof(&key, |sub| {
+                                        let $n = sub;
+                                        $x
+                                    })
+                                    .ok_or_else(orphan)?
                             }
                         }
                     };
                 }
+                macro_rules! add_line {
+                    ($r:expr) => {{
+                        let r: PjRef<P> = $r;
+                        if line_refs.contains_key(&last_linenum) {
+                            return Err(at!(DuplicateLine { line_id: last_linenum.clone() }));
+                        }
+                        if let Some(id) = last_stable_id {
+                            if !proof.set_stable_id(&r, id) {
+                                return Err(at!(DuplicateStableId { id: id.0 }));
+                            }
+                        }
+                        if let (Some(input), Some(id)) = (last_raw_input.take(), proof.stable_id_of(&r)) {
+                            metadata.raw_inputs.insert(id, input);
+                        }
+                        if let (true, Some(id)) = (std::mem::take(&mut last_locked), proof.stable_id_of(&r)) {
+                            metadata.locked.insert(id);
+                        }
+                        if let (Some(comment), Some(id)) = (last_comment.take(), proof.stable_id_of(&r)) {
+                            metadata.comments.insert(id, comment);
+                        }
+                        if let (Some(annotation), Some(id)) = (last_annotation.take(), proof.stable_id_of(&r)) {
+                            annotations.insert(id, annotation);
+                        }
+                        line_refs.insert(last_linenum.clone(), r);
+                    }};
+                }
                 match &*element {
                     "author" => metadata.author = Some(contents.clone()),
                     "hash" => metadata.hash = Some(contents.clone()),
+                    "title" => metadata.title = Some(contents.clone()),
+                    "created" => metadata.created = Some(contents.clone()),
+                    "modified" => metadata.modified = Some(contents.clone()),
+                    "assignment" => metadata.assignment = Some(contents.clone()),
+                    "seal" => metadata.seal = Some(contents.clone()),
                     "raw" => {
                         last_raw = contents.clone();
                     }
                     "assumption" => {
-                        on_current_proof! { proof, { let p = proof.add_premise(parse!(&last_raw)); line_refs.insert(last_linenum.clone(), Coproduct::inject(p)).ok_or(format!("Multiple assumptions with line number {last_linenum}")) } }
+                        let e = parse_line!();
+                        let p = on_current_proof!(proof, proof.add_premise(e));
+                        add_line!(Coproduct::inject(p));
                     }
                     "rule" => {
                         last_rule = contents.clone();
                     }
+                    "comment" => {
+                        last_comment = Some(contents.clone());
+                    }
+                    "annotation" => {
+                        last_annotation = Some(if last_verified { Ok(()) } else { Err(contents.clone()) });
+                    }
                     "premise" => {
                         seen_premises.push(contents.clone());
                     }
@@ -121,43 +766,130 @@ pub fn proof_from_xml<P: Proof, R: Read>(r: R) -> Result<(P, ProofMetaData), Str
                         match &*last_rule {
                             "" => {}
                             "SUBPROOF" => {
-                                on_current_proof! { proof, { let p = proof.add_subproof(); subproofs.insert(seen_premises[0].clone(), p.clone()); lines_to_subs.insert(last_linenum.clone(), p) } }
+                                let proof_id = seen_premises.first().cloned().ok_or_else(|| at!(MissingSubproofId { line_id: last_linenum.clone() }))?;
+                                if lines_to_subs.contains_key(&last_linenum) {
+                                    return Err(at!(DuplicateLine { line_id: last_linenum.clone() }));
+                                }
+                                let p = on_current_proof!(proof, proof.add_subproof());
+                                subproofs.insert(proof_id, p.clone());
+                                lines_to_subs.insert(last_linenum.clone(), p);
                             }
                             rulename => {
-                                let rule = RuleM::from_serialized_name(rulename).unwrap_or(RuleM::Reit); // TODO: explicit RuleM::NoSelectionMade?
-                                                                                                         //println!("{:?}", rule);
-                                let deps = seen_premises.iter().filter_map(|x| line_refs.get(x)).cloned().collect::<Vec<_>>();
-                                let sdeps = seen_premises.iter().filter_map(|x| lines_to_subs.get(x)).cloned().collect::<Vec<_>>();
-                                //println!("{:?} {:?}", line_refs, subproofs);
-                                //println!("{:?} {:?}", deps, sdeps);
-                                let just = Justification(parse!(&last_raw), rule, deps, sdeps);
-                                //println!("{:?}", just);
-                                on_current_proof! { proof, { let p = proof.add_step(just); line_refs.insert(last_linenum.clone(), Coproduct::inject(p)); } }
+                                let alias = || {
+                                    let (_, to) = RULE_ALIASES.iter().find(|(from, _)| legacy && *from == rulename)?;
+                                    warnings.push(CompatWarning::RenamedRule { from: rulename.into(), to: (*to).into(), line_id: last_linenum.clone() });
+                                    RuleM::from_serialized_name(to)
+                                };
+                                let rule = match RuleM::from_serialized_name(rulename).or_else(alias) {
+                                    Some(rule) => rule,
+                                    None => {
+                                        errors.push(at!(UnknownRule { name: rulename.into(), line_id: last_linenum.clone() }));
+                                        RuleM::EmptyRule
+                                    }
+                                };
+                                let e = parse_line!();
+                                let p = on_current_proof!(proof, proof.add_step(Justification(e, rule, vec![], vec![])));
+                                add_line!(Coproduct::inject(p.clone()));
+                                steps.push((p, last_linenum.clone(), std::mem::take(&mut seen_premises), (at_line, at_column, at_path.clone())));
                             }
                         }
                     }
                     "goal" => {
+                        if let Some(parent) = element_stack.last().filter(|_| legacy) {
+                            if parent.name != "proof" {
+                                warnings.push(CompatWarning::GoalOutsideProof { parent: parent.name.clone() });
+                            }
+                        }
                         if !last_raw.is_empty() {
-                            metadata.goals.push(parse!(&last_raw));
+                            if let Some(goal) = parse!(&last_raw, None) {
+                                metadata.goals.push(goal);
+                            }
                         }
                     }
                     _ => (),
                 }
+                if element_stack.is_empty() {
+                    break;
+                }
             }
             Ok(Whitespace(_)) => (),
             Ok(EndDocument) => break,
             Ok(_) => (),
             Err(e) => {
-                return Err(format!("Error parsing xml document: {e:?}"));
+                let position = e.position();
+                let path = element_stack.last().map_or_else(|| root_path.into(), |parent| parent.path.clone());
+                return Err(XmlImportError { kind: Syntax { message: e.msg().into() }, line: position.row + 1, column: position.column + 1, path });
+            }
+        }
+    }
+    // A line id can be both a subproof's and its first premise's, in which
+    // case a step inside the subproof cites the premise, and any other step
+    // cites the subproof
+    for (p, cited_by, premises, (line, column, path)) in steps {
+        let mut deps = vec![];
+        let mut sdeps = vec![];
+        for id in premises {
+            match (line_refs.get(&id), lines_to_subs.get(&id)) {
+                (None, None) => errors.push(XmlImportError { kind: DanglingReference { id, cited_by: cited_by.clone() }, line, column, path: path.clone() }),
+                (Some(dep), None) => deps.push(dep.clone()),
+                (None, Some(sdep)) => sdeps.push(sdep.clone()),
+                (Some(dep), Some(sdep)) => {
+                    if proof.lookup_subproof(sdep).is_some_and(|sub| sub.contained_justifications(false).contains(&Coproduct::inject(p.clone()))) {
+                        deps.push(dep.clone());
+                    } else {
+                        sdeps.push(sdep.clone());
+                    }
+                }
             }
         }
+        proof.with_mut_step(&p, |j| {
+            j.2 = deps;
+            j.3 = sdeps;
+        });
     }
-    Ok((proof, metadata))
+    for sr in proof.incomplete_subproofs() {
+        if let Some((line_id, _)) = lines_to_subs.iter().find(|(_, sub)| **sub == sr) {
+            warnings.push(CompatWarning::IncompleteSubproof { line_id: line_id.clone() });
+        }
+    }
+    if !annotations.is_empty() {
+        metadata.annotations = Some(Annotations { lines: annotations, content_hash: proof.content_hash() });
+    }
+    let status = match &metadata.hash {
+        None => ImportedHashStatus::Absent,
+        Some(hash) if *hash == submission_hash(&proof, metadata.author.as_deref(), &metadata.goals) => ImportedHashStatus::Matches,
+        Some(_) => ImportedHashStatus::Mismatch,
+    };
+    Ok((proof, metadata, status))
 }
 
 pub fn xml_from_proof_and_metadata<P: Proof, W: Write>(prf: &P, meta: &ProofMetaData, out: W) -> xml::writer::Result<()> {
+    use xml::writer::{EmitterConfig, EventWriter, XmlEvent::StartDocument};
+    let mut ew = EventWriter::new_with_config(out, EmitterConfig::new().perform_indent(true));
+    ew.write(StartDocument { version: xml::common::XmlVersion::Version10, encoding: Some("UTF-8"), standalone: Some(false) })?;
+    write_bram(prf, meta, &mut ew)
+}
+
+/// Save several proofs to one file, as a `<workbook>` of the `<bram>` elements
+/// that `xml_from_proof_and_metadata` would save each of them as, for
+/// assignments with several problems
+pub fn workbook_to_xml<P: Proof, W: Write>(proofs: &[(ProofMetaData, &P)], out: W) -> xml::writer::Result<()> {
+    use xml::writer::{EmitterConfig, EventWriter, XmlEvent};
+    let mut ew = EventWriter::new_with_config(out, EmitterConfig::new().perform_indent(true));
+    ew.write(XmlEvent::StartDocument { version: xml::common::XmlVersion::Version10, encoding: Some("UTF-8"), standalone: Some(false) })?;
+    let version = format!("{}.{}", FORMAT_VERSION.0, FORMAT_VERSION.1);
+    ew.write(XmlEvent::start_element("workbook").attr("version", &version))?;
+    for (meta, prf) in proofs {
+        write_bram(*prf, meta, &mut ew)?;
+    }
+    ew.write(XmlEvent::end_element().name("workbook"))?;
+    Ok(())
+}
+
+/// Write a proof and its metadata as a `<bram>` element
+fn write_bram<P: Proof, W: Write>(prf: &P, meta: &ProofMetaData, ew: &mut xml::writer::EventWriter<W>) -> xml::writer::Result<()> {
     use xml::writer::{
-        EmitterConfig, EventWriter,
+        EventWriter,
         XmlEvent::{self, *},
     };
     fn leaf_tag<W: Write>(ew: &mut EventWriter<W>, name: &str, val: &str) -> xml::writer::Result<()> {
@@ -166,27 +898,34 @@ pub fn xml_from_proof_and_metadata<P: Proof, W: Write>(prf: &P, meta: &ProofMeta
         ew.write(XmlEvent::end_element().name(name))?;
         Ok(())
     }
-    let mut ew = EventWriter::new_with_config(out, EmitterConfig::new().perform_indent(true));
-    ew.write(StartDocument { version: xml::common::XmlVersion::Version10, encoding: Some("UTF-8"), standalone: Some(false) })?;
-    ew.write(XmlEvent::start_element("bram"))?;
-    leaf_tag(&mut ew, "program", "Aris")?;
-    leaf_tag(&mut ew, "version", "0.1.0")?; // TODO: autodetect from crate metadata?
+    let version = format!("{}.{}", FORMAT_VERSION.0, FORMAT_VERSION.1);
+    let next_id = format!("{}", prf.next_stable_id().0);
+    ew.write(XmlEvent::start_element("bram").attr("version", &version).attr("nextid", &next_id))?;
+    leaf_tag(ew, "program", "Aris")?;
+    leaf_tag(ew, "version", "0.1.0")?; // TODO: autodetect from crate metadata?
 
     ew.write(XmlEvent::start_element("metadata"))?;
     if let Some(author) = &meta.author {
-        leaf_tag(&mut ew, "author", author)?;
+        leaf_tag(ew, "author", author)?;
     }
     if let Some(hash) = &meta.hash {
-        leaf_tag(&mut ew, "hash", hash)?;
+        leaf_tag(ew, "hash", hash)?;
+    }
+    for (name, value) in [("title", &meta.title), ("created", &meta.created), ("modified", &meta.modified), ("assignment", &meta.assignment), ("seal", &meta.seal)] {
+        if let Some(value) = value {
+            leaf_tag(ew, name, value)?;
+        }
     }
     ew.write(XmlEvent::end_element().name("metadata"))?;
 
+    // Line ids and proof ids are numbered in display order, so that saving the
+    // same proof always gives the same file
     struct SerializationState<P: Proof> {
-        queue: Vec<(usize, P::SubproofReference)>,
+        subproofs: Vec<P::SubproofReference>,
         linenum: usize,
-        sproofid: usize,
         deps_map: HashMap<PjRef<P>, usize>,
         sdeps_map: HashMap<P::SubproofReference, usize>,
+        proof_ids: HashMap<P::SubproofReference, usize>,
     }
     fn allocate_identifiers<P: Proof>(prf: &P::Subproof, state: &mut SerializationState<P>) {
         for prem in prf.premises() {
@@ -202,6 +941,8 @@ pub fn xml_from_proof_and_metadata<P: Proof, W: Write>(prf: &P, meta: &ProofMeta
                 }
                 Inr(Inl(sr)) => {
                     state.sdeps_map.insert(sr.clone(), state.linenum);
+                    state.subproofs.push(sr.clone());
+                    state.proof_ids.insert(sr.clone(), state.subproofs.len());
                     // the java version seems to require that the linenum of a subproof aliases its first premise, so don't increment linenum here
                     let sub = prf.lookup_subproof(&sr).unwrap();
                     allocate_identifiers(&sub, state);
@@ -211,13 +952,54 @@ pub fn xml_from_proof_and_metadata<P: Proof, W: Write>(prf: &P, meta: &ProofMeta
         }
     }
 
-    fn aux<P: Proof, W: Write>(prf: &P::Subproof, proofid: usize, state: &mut SerializationState<P>, ew: &mut EventWriter<W>) -> xml::writer::Result<()> {
+    fn comment<W: Write>(ew: &mut EventWriter<W>, meta: &ProofMetaData, id: Option<LineId>) -> xml::writer::Result<()> {
+        match id.and_then(|id| meta.comments.get(&id)) {
+            Some(comment) => leaf_tag(ew, "comment", comment),
+            None => Ok(()),
+        }
+    }
+
+    fn annotation<W: Write>(ew: &mut EventWriter<W>, annotation: Option<&Result<(), String>>) -> xml::writer::Result<()> {
+        match annotation {
+            Some(Ok(())) => {
+                ew.write(XmlEvent::start_element("annotation").attr("verified", "true"))?;
+                ew.write(XmlEvent::end_element().name("annotation"))?;
+            }
+            Some(Err(message)) => {
+                ew.write(XmlEvent::start_element("annotation").attr("verified", "false"))?;
+                ew.write(Characters(message))?;
+                ew.write(XmlEvent::end_element().name("annotation"))?;
+            }
+            None => (),
+        }
+        Ok(())
+    }
+
+    fn aux<P: Proof, W: Write>(prf: &P::Subproof, proofid: usize, meta: &ProofMetaData, goals: &[Expr], state: &SerializationState<P>, annotations: Option<&HashMap<LineId, Result<(), String>>>, ew: &mut EventWriter<W>) -> xml::writer::Result<()> {
         ew.write(XmlEvent::start_element("proof").attr("id", &format!("{proofid}")))?;
+        fn start_line<'a, P: Proof>(prf: &P, meta: &'a ProofMetaData, name: &'a str, r: PjRef<P>, linenum: &'a str, stable_id: &'a mut String) -> xml::writer::events::StartElementBuilder<'a> {
+            let start = XmlEvent::start_element(name).attr("linenum", linenum);
+            match prf.stable_id_of(&r) {
+                Some(id) => {
+                    *stable_id = format!("{}", id.0);
+                    let start = start.attr("stableid", stable_id.as_str());
+                    let start = if meta.locked.contains(&id) { start.attr("locked", "true") } else { start };
+                    match meta.raw_inputs.get(&id) {
+                        Some(input) => start.attr("raw", input),
+                        None => start,
+                    }
+                }
+                None => start,
+            }
+        }
         for prem in prf.premises() {
-            ew.write(XmlEvent::start_element("assumption").attr("linenum", &format!("{}", state.deps_map[&Coproduct::inject(prem.clone())])))?;
+            let (linenum, mut stable_id) = (format!("{}", state.deps_map[&Coproduct::inject(prem.clone())]), String::new());
+            ew.write(start_line(prf, meta, "assumption", Coproduct::inject(prem.clone()), &linenum, &mut stable_id))?;
             if let Some(expr) = prf.lookup_premise(&prem) {
-                leaf_tag(ew, "raw", &format!("{expr}"))?;
+                leaf_tag(ew, "raw", &expr.to_canonical_string())?;
             }
+            comment(ew, meta, prf.stable_id_of(&Coproduct::inject(prem.clone())))?;
+            annotation(ew, annotations.zip(prf.stable_id_of(&Coproduct::inject(prem.clone()))).and_then(|(annotations, id)| annotations.get(&id)))?;
             ew.write(XmlEvent::end_element())?;
         }
         for step in prf.lines() {
@@ -225,37 +1007,43 @@ pub fn xml_from_proof_and_metadata<P: Proof, W: Write>(prf: &P, meta: &ProofMeta
             match step {
                 Inl(jr) => {
                     let just = prf.lookup_step(&jr).unwrap();
-                    ew.write(XmlEvent::start_element("step").attr("linenum", &format!("{}", state.deps_map[&Coproduct::inject(jr.clone())])))?;
-                    leaf_tag(ew, "raw", &format!("{}", just.0))?;
+                    let (linenum, mut stable_id) = (format!("{}", state.deps_map[&Coproduct::inject(jr.clone())]), String::new());
+                    ew.write(start_line(prf, meta, "step", Coproduct::inject(jr.clone()), &linenum, &mut stable_id))?;
+                    leaf_tag(ew, "raw", &just.0.to_canonical_string())?;
                     leaf_tag(ew, "rule", RuleM::to_serialized_name(just.1))?;
-                    for dep in just.2 {
-                        leaf_tag(ew, "premise", &format!("{}", state.deps_map[&dep]))?;
-                    }
-                    for sdep in just.3 {
-                        leaf_tag(ew, "premise", &format!("{}", state.sdeps_map[&sdep]))?;
+                    let mut premises = just.2.iter().map(|dep| state.deps_map[dep]).chain(just.3.iter().map(|sdep| state.sdeps_map[sdep])).collect::<Vec<_>>();
+                    premises.sort();
+                    for premise in premises {
+                        leaf_tag(ew, "premise", &format!("{premise}"))?;
                     }
+                    comment(ew, meta, prf.stable_id_of(&Coproduct::inject(jr.clone())))?;
+                    annotation(ew, annotations.zip(prf.stable_id_of(&Coproduct::inject(jr.clone()))).and_then(|(annotations, id)| annotations.get(&id)))?;
                     ew.write(XmlEvent::end_element().name("step"))?;
                 }
                 Inr(Inl(sr)) => {
                     ew.write(XmlEvent::start_element("step").attr("linenum", &format!("{}", state.sdeps_map[&sr])))?;
                     leaf_tag(ew, "rule", "SUBPROOF")?;
-                    leaf_tag(ew, "premise", &format!("{}", state.sproofid))?;
+                    leaf_tag(ew, "premise", &format!("{}", state.proof_ids[&sr]))?;
                     ew.write(XmlEvent::end_element().name("step"))?;
-                    state.queue.push((state.sproofid, sr.clone()));
-                    state.sproofid += 1;
                 }
                 Inr(Inr(void)) => match void {},
             }
         }
+        for goal in goals {
+            ew.write(XmlEvent::start_element("goal"))?;
+            leaf_tag(ew, "raw", &goal.to_canonical_string())?;
+            ew.write(XmlEvent::end_element().name("goal"))?;
+        }
         ew.write(XmlEvent::end_element().name("proof"))?;
         Ok(())
     }
-    let mut state = SerializationState::<P> { queue: vec![], sproofid: 1, linenum: 0, deps_map: HashMap::new(), sdeps_map: HashMap::new() };
+    let mut state = SerializationState::<P> { subproofs: vec![], linenum: 0, deps_map: HashMap::new(), sdeps_map: HashMap::new(), proof_ids: HashMap::new() };
     allocate_identifiers(prf.top_level_proof(), &mut state);
-    aux(prf.top_level_proof(), 0, &mut state, &mut ew)?;
-    while let Some((id, sr)) = state.queue.pop() {
-        if let Some(sub) = prf.lookup_subproof(&sr) {
-            aux(&sub, id, &mut state, &mut ew)?;
+    let annotations = meta.annotations.as_ref().filter(|annotations| annotations.is_for(prf)).map(|annotations| &annotations.lines);
+    aux(prf.top_level_proof(), 0, meta, &meta.goals, &state, annotations, ew)?;
+    for sr in state.subproofs.iter() {
+        if let Some(sub) = prf.lookup_subproof(sr) {
+            aux(&sub, state.proof_ids[sr], meta, &[], &state, annotations, ew)?;
         }
     }
     ew.write(XmlEvent::end_element().name("bram"))?;
@@ -263,21 +1051,72 @@ pub fn xml_from_proof_and_metadata<P: Proof, W: Write>(prf: &P, meta: &ProofMeta
     Ok(())
 }
 
-pub fn xml_from_proof_and_metadata_with_hash<P: Proof, W: Write>(prf: &P, meta: &ProofMetaData, out: W) -> xml::writer::Result<()> {
+/// The hash saved with a proof: the SHA-256, in base64, of a rendering of the
+/// proof, its author, and its goals.
+///
+/// The rendering has an `author` field if there's an author, a `goal` field
+/// for each goal, and then the proof's `TreeProof::canonical_string`. Fields
+/// are written with their lengths, as in the seal, so an author can't pass for
+/// a goal. Since none of this depends on how the proof stores its lines,
+/// reloading a proof and saving it again keeps its hash.
+pub fn submission_hash<P: Proof>(prf: &P, author: Option<&str>, goals: &[Expr]) -> String {
+    use crate::export::submission::write_field;
     use base64::Engine;
     use sha2::Digest;
+    let mut rendering = String::new();
+    if let Some(author) = author {
+        write_field(&mut rendering, "author", author);
+    }
+    for goal in goals {
+        write_field(&mut rendering, "goal", &goal.to_canonical_string());
+    }
+    rendering.push_str(&TreeProof::from_proof(prf).canonical_string());
+    let hash = sha2::Sha256::digest(rendering.as_bytes());
+    base64::engine::general_purpose::STANDARD.encode(&hash[..])
+}
+
+/// Like `xml_from_proof_and_metadata`, replacing `meta.hash` with the proof's
+/// `submission_hash`
+pub fn xml_from_proof_and_metadata_with_hash<P: Proof, W: Write>(prf: &P, meta: &ProofMetaData, out: W) -> xml::writer::Result<()> {
     let mut meta = meta.clone();
-    meta.hash = None;
-    let mut payload = vec![];
-    xml_from_proof_and_metadata(prf, &meta, &mut payload)?;
-    let mut ctx = sha2::Sha256::new();
-    ctx.update(&payload[..]);
-    ctx.update(b"\n");
-    if let Some(author) = &meta.author {
-        ctx.update(author);
+    meta.hash = Some(submission_hash(prf, meta.author.as_deref(), &meta.goals));
+    xml_from_proof_and_metadata(prf, &meta, out)
+}
+
+/// Reasons `xml_from_subproof` can't write a subproof
+#[derive(Debug)]
+pub enum SubproofExportError<R, S> {
+    /// The subproof can't be made into a proof of its own, such as because it
+    /// cites lines outside of it, which are listed
+    Extract(ExtractError<R, S>),
+    Write(xml::writer::Error),
+}
+
+impl<R: std::fmt::Debug, S: std::fmt::Debug> std::fmt::Display for SubproofExportError<R, S> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            SubproofExportError::Extract(err) => write!(f, "{err}"),
+            SubproofExportError::Write(err) => write!(f, "{err}"),
+        }
     }
-    let hash = ctx.finalize();
-    meta.hash = Some(base64::engine::general_purpose::STANDARD.encode(&hash[..]));
+}
+
+/// Write a subproof as a proof of its own, whose premises are the subproof's
+/// premises, such as to share a lemma. Like `Proof::extract_subproof`, this
+/// fails if the subproof cites lines outside of it, which need to be made its
+/// premises first. What `meta` has by stable id, and its hash and seal, are
+/// for the whole proof, so they're left out.
+pub fn xml_from_subproof<P: Proof, W: Write>(prf: &P, sr: &P::SubproofReference, meta: &ProofMetaData, out: W) -> Result<(), SubproofExportError<PjRef<P>, P::SubproofReference>> {
+    let lemma = prf.extract_subproof(sr).map_err(SubproofExportError::Extract)?;
+    let meta = ProofMetaData { hash: None, seal: None, raw_inputs: HashMap::new(), locked: BTreeSet::new(), comments: BTreeMap::new(), annotations: None, ..meta.clone() };
+    xml_from_proof_and_metadata(&lemma, &meta, out).map_err(SubproofExportError::Write)
+}
+
+/// Like `xml_from_proof_and_metadata`, replacing `meta.annotations` with the
+/// results of checking the proof now
+pub fn xml_from_proof_and_metadata_with_verification<P: Proof, W: Write>(prf: &P, meta: &ProofMetaData, out: W) -> xml::writer::Result<()> {
+    let mut meta = meta.clone();
+    meta.annotations = Some(Annotations::from_verification(prf));
     xml_from_proof_and_metadata(prf, &meta, out)
 }
 
@@ -293,12 +1132,47 @@ mod tests {
     fn test_xml() {
         let data = &include_bytes!("../../../example-proofs/propositional_logic_arguments_for_proofs_ii_problem_10.bram")[..];
         type P = PooledProof<HList![Expr]>;
-        let (prf, metadata) = proof_from_xml::<P, _>(data).unwrap();
+        let (prf, metadata, _) = proof_from_xml::<P, _>(data).unwrap();
         println!("{:?} {:?}\n{}", metadata.author, metadata.hash, prf);
         let mut reserialized = vec![];
         xml_from_proof_and_metadata_with_hash(&prf, &metadata, &mut reserialized).unwrap();
-        let expected = "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"no\"?>\n<bram>\n  <program>Aris</program>\n  <version>0.1.0</version>\n  <metadata>\n    <author>UNKNOWN</author>\n    <hash>Rtp8+ksWhNiBbCNy6rbBgppRL+5GZyJdBdyBSk4FRpk=</hash>\n  </metadata>\n  <proof id=\"0\">\n    <assumption linenum=\"0\">\n      <raw>(¬A ∨ B)</raw>\n    </assumption>\n    <assumption linenum=\"1\">\n      <raw>(A ∨ C)</raw>\n    </assumption>\n    <assumption linenum=\"2\">\n      <raw>(¬D → ¬C)</raw>\n    </assumption>\n    <step linenum=\"3\">\n      <rule>SUBPROOF</rule>\n      <premise>1</premise>\n    </step>\n    <step linenum=\"10\">\n      <rule>SUBPROOF</rule>\n      <premise>2</premise>\n    </step>\n    <step linenum=\"17\">\n      <raw>(B ∨ D)</raw>\n      <rule>DISJUNCTIVE_SYLLOGISM</rule>\n      <premise>1</premise>\n      <premise>10</premise>\n      <premise>3</premise>\n    </step>\n  </proof>\n  <proof id=\"2\">\n    <assumption linenum=\"10\">\n      <raw>C</raw>\n    </assumption>\n    <step linenum=\"11\">\n      <rule>SUBPROOF</rule>\n      <premise>3</premise>\n    </step>\n    <step linenum=\"14\">\n      <raw>¬¬D</raw>\n      <rule>PROOF_BY_CONTRADICTION</rule>\n      <premise>11</premise>\n    </step>\n    <step linenum=\"15\">\n      <raw>D</raw>\n      <rule>DOUBLENEGATION</rule>\n      <premise>14</premise>\n    </step>\n    <step linenum=\"16\">\n      <raw>(B ∨ D)</raw>\n      <rule>ADDITION</rule>\n      <premise>15</premise>\n    </step>\n  </proof>\n  <proof id=\"3\">\n    <assumption linenum=\"11\">\n      <raw>¬D</raw>\n    </assumption>\n    <step linenum=\"12\">\n      <raw>¬C</raw>\n      <rule>MODUS_PONENS</rule>\n      <premise>2</premise>\n      <premise>11</premise>\n    </step>\n    <step linenum=\"13\">\n      <raw>⊥</raw>\n      <rule>CONTRADICTION</rule>\n      <premise>10</premise>\n      <premise>12</premise>\n    </step>\n  </proof>\n  <proof id=\"1\">\n    <assumption linenum=\"3\">\n      <raw>A</raw>\n    </assumption>\n    <step linenum=\"4\">\n      <rule>SUBPROOF</rule>\n      <premise>4</premise>\n    </step>\n    <step linenum=\"7\">\n      <rule>SUBPROOF</rule>\n      <premise>5</premise>\n    </step>\n    <step linenum=\"9\">\n      <raw>(B ∨ D)</raw>\n      <rule>DISJUNCTIVE_SYLLOGISM</rule>\n      <premise>0</premise>\n      <premise>4</premise>\n      <premise>7</premise>\n    </step>\n  </proof>\n  <proof id=\"5\">\n    <assumption linenum=\"7\">\n      <raw>B</raw>\n    </assumption>\n    <step linenum=\"8\">\n      <raw>(B ∨ D)</raw>\n      <rule>ADDITION</rule>\n      <premise>7</premise>\n    </step>\n  </proof>\n  <proof id=\"4\">\n    <assumption linenum=\"4\">\n      <raw>¬A</raw>\n    </assumption>\n    <step linenum=\"5\">\n      <raw>⊥</raw>\n      <rule>CONTRADICTION</rule>\n      <premise>3</premise>\n      <premise>4</premise>\n    </step>\n    <step linenum=\"6\">\n      <raw>(B ∨ D)</raw>\n      <rule>PRINCIPLE_OF_EXPLOSION</rule>\n      <premise>5</premise>\n    </step>\n  </proof>\n</bram>";
+        let expected = "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"no\"?>\n<bram version=\"1.6\" nextid=\"18\">\n  <program>Aris</program>\n  <version>0.1.0</version>\n  <metadata>\n    <author>UNKNOWN</author>\n    <hash>tRfSz/MYuSaaDVWU9CGqdcmDzNpfkwZte5/OdLp5hfA=</hash>\n  </metadata>\n  <proof id=\"0\">\n    <assumption linenum=\"0\" stableid=\"0\">\n      <raw>(¬A ∨ B)</raw>\n    </assumption>\n    <assumption linenum=\"1\" stableid=\"1\">\n      <raw>(A ∨ C)</raw>\n    </assumption>\n    <assumption linenum=\"2\" stableid=\"2\">\n      <raw>(¬D → ¬C)</raw>\n    </assumption>\n    <step linenum=\"3\">\n      <rule>SUBPROOF</rule>\n      <premise>1</premise>\n    </step>\n    <step linenum=\"10\">\n      <rule>SUBPROOF</rule>\n      <premise>4</premise>\n    </step>\n    <step linenum=\"17\" stableid=\"3\">\n      <raw>(B ∨ D)</raw>\n      <rule>DISJUNCTIVE_SYLLOGISM</rule>\n      <premise>1</premise>\n      <premise>3</premise>\n      <premise>10</premise>\n    </step>\n    <goal>\n      <raw>(B ∨ D)</raw>\n    </goal>\n  </proof>\n  <proof id=\"1\">\n    <assumption linenum=\"3\" stableid=\"4\">\n      <raw>A</raw>\n    </assumption>\n    <step linenum=\"4\">\n      <rule>SUBPROOF</rule>\n      <premise>2</premise>\n    </step>\n    <step linenum=\"7\">\n      <rule>SUBPROOF</rule>\n      <premise>3</premise>\n    </step>\n    <step linenum=\"9\" stableid=\"5\">\n      <raw>(B ∨ D)</raw>\n      <rule>DISJUNCTIVE_SYLLOGISM</rule>\n      <premise>0</premise>\n      <premise>4</premise>\n      <premise>7</premise>\n    </step>\n  </proof>\n  <proof id=\"2\">\n    <assumption linenum=\"4\" stableid=\"6\">\n      <raw>¬A</raw>\n    </assumption>\n    <step linenum=\"5\" stableid=\"7\">\n      <raw>⊥</raw>\n      <rule>CONTRADICTION</rule>\n      <premise>3</premise>\n      <premise>4</premise>\n    </step>\n    <step linenum=\"6\" stableid=\"8\">\n      <raw>(B ∨ D)</raw>\n      <rule>PRINCIPLE_OF_EXPLOSION</rule>\n      <premise>5</premise>\n    </step>\n  </proof>\n  <proof id=\"3\">\n    <assumption linenum=\"7\" stableid=\"9\">\n      <raw>B</raw>\n    </assumption>\n    <step linenum=\"8\" stableid=\"10\">\n      <raw>(B ∨ D)</raw>\n      <rule>ADDITION</rule>\n      <premise>7</premise>\n    </step>\n  </proof>\n  <proof id=\"4\">\n    <assumption linenum=\"10\" stableid=\"11\">\n      <raw>C</raw>\n    </assumption>\n    <step linenum=\"11\">\n      <rule>SUBPROOF</rule>\n      <premise>5</premise>\n    </step>\n    <step linenum=\"14\" stableid=\"12\">\n      <raw>¬¬D</raw>\n      <rule>PROOF_BY_CONTRADICTION</rule>\n      <premise>11</premise>\n    </step>\n    <step linenum=\"15\" stableid=\"13\">\n      <raw>D</raw>\n      <rule>DOUBLENEGATION</rule>\n      <premise>14</premise>\n    </step>\n    <step linenum=\"16\" stableid=\"14\">\n      <raw>(B ∨ D)</raw>\n      <rule>ADDITION</rule>\n      <premise>15</premise>\n    </step>\n  </proof>\n  <proof id=\"5\">\n    <assumption linenum=\"11\" stableid=\"15\">\n      <raw>¬D</raw>\n    </assumption>\n    <step linenum=\"12\" stableid=\"16\">\n      <raw>¬C</raw>\n      <rule>MODUS_PONENS</rule>\n      <premise>2</premise>\n      <premise>11</premise>\n    </step>\n    <step linenum=\"13\" stableid=\"17\">\n      <raw>⊥</raw>\n      <rule>CONTRADICTION</rule>\n      <premise>10</premise>\n      <premise>12</premise>\n    </step>\n  </proof>\n</bram>";
         assert_eq!(expected, String::from_utf8_lossy(&reserialized));
+        let (_, reparsed, _) = proof_from_xml::<P, _>(&reserialized[..]).unwrap();
+        assert_eq!(reparsed.goals, vec![crate::parser::parse_unwrap("B | D")]);
+    }
+
+    #[test]
+    fn test_deterministic_output() {
+        use crate::parser::parse_unwrap as p;
+        let data = &include_bytes!("../../../example-proofs/resolution_example.bram")[..];
+        type P = PooledProof<HList![Expr]>;
+        let (mut prf, metadata, _) = proof_from_xml::<P, _>(data).unwrap();
+        let save = |prf: &P| {
+            let mut xml = vec![];
+            xml_from_proof_and_metadata_with_hash(prf, &metadata, &mut xml).unwrap();
+            String::from_utf8(xml).unwrap()
+        };
+        let xml = save(&prf);
+        assert_eq!(save(&prf), xml);
+
+        // Removing a line leaves a gap in the pools, which compacting closes.
+        // Only the next stable id is different, since the line's can't be reused.
+        let extra = prf.add_premise(p("Z"));
+        prf.remove_line(&Coproduct::inject(extra));
+        let removed = xml.replace(r#"nextid="16""#, r#"nextid="17""#);
+        assert_ne!(removed, xml);
+        assert_eq!(save(&prf), removed);
+        prf.compact();
+        assert_eq!(save(&prf), removed);
+
+        // Saving what was loaded gives the same file
+        let (reloaded, _, status) = proof_from_xml::<P, _>(xml.as_bytes()).unwrap();
+        assert_eq!(status, ImportedHashStatus::Matches);
+        assert_eq!(save(&reloaded), xml);
+
+        // The original cites lines out of order, and numbers its proofs differently
+        assert_eq!(xml, include_str!("../../../example-proofs/golden/resolution_example.bram").trim_end());
     }
 
     #[test]
@@ -335,7 +1209,7 @@ mod tests {
         </bram>
         "#;
         type P = PooledProof<HList![Expr]>;
-        let (prf, metadata) = proof_from_xml::<P, _>(&xml[..]).unwrap();
+        let (prf, metadata, _) = proof_from_xml::<P, _>(&xml[..]).unwrap();
         println!("{:?} {:?}\n{}", metadata.author, metadata.hash, prf);
         let lines = prf.lines();
         let sub = prf.lookup_subproof(&lines[0].get::<<P as Proof>::SubproofReference, _>().unwrap().clone()).unwrap();
@@ -353,11 +1227,918 @@ mod tests {
         assert_eq!(s2.len(), 1);
     }
 
+    #[test]
+    fn test_duplicate_deps() {
+        /*
+        1 | A
+        2 | B
+          | -----
+        3 | A & B ; AndIntro [2, 1, 2]
+        */
+        let xml = br#"
+        <bram>
+            <proof id="0">
+                <assumption linenum="0">
+                    <raw>A</raw>
+                </assumption>
+                <assumption linenum="1">
+                    <raw>B</raw>
+                </assumption>
+                <step linenum="2">
+                    <raw>A &amp; B</raw>
+                    <rule>CONJUNCTION</rule>
+                    <premise>1</premise>
+                    <premise>0</premise>
+                    <premise>1</premise>
+                </step>
+            </proof>
+        </bram>
+        "#;
+        type P = PooledProof<HList![Expr]>;
+        let (prf, _, _) = proof_from_xml::<P, _>(&xml[..]).unwrap();
+        let premises = prf.premises();
+        let step = prf.lookup_step(&prf.direct_lines()[0]).unwrap();
+        assert_eq!(step.2, vec![Coproduct::inject(premises[0]), Coproduct::inject(premises[1])]);
+        assert!(prf.verify_all().is_fully_verified());
+
+        // Edits are normalized too, and the display order follows line numbers even after a move
+        let jr = prf.direct_lines()[0];
+        let mut prf = prf;
+        prf.with_mut_step(&jr, |j| j.2 = vec![Coproduct::inject(premises[1]), Coproduct::inject(premises[1]), Coproduct::inject(premises[0])]);
+        assert_eq!(prf.lookup_step(&jr).unwrap().2, vec![Coproduct::inject(premises[0]), Coproduct::inject(premises[1])]);
+        prf.move_premise(&premises[1], crate::proofs::MoveDirection::Up).unwrap();
+        let step = prf.lookup_step(&jr).unwrap();
+        assert_eq!(step.2, vec![Coproduct::inject(premises[0]), Coproduct::inject(premises[1])]);
+        assert_eq!(step.deps_in_display_order(&prf), vec![Coproduct::inject(premises[1]), Coproduct::inject(premises[0])]);
+    }
+
+    #[test]
+    fn test_verify_all_resolution_example() {
+        let data = &include_bytes!("../../../example-proofs/resolution_example.bram")[..];
+        type P = PooledProof<HList![Expr]>;
+        let (mut prf, _, _) = proof_from_xml::<P, _>(data).unwrap();
+
+        let report = prf.verify_all();
+        assert_eq!(report.lines.len(), 16);
+        assert_eq!(report.iter().map(|(n, _, _)| n).collect::<Vec<_>>(), (1..=16).collect::<Vec<_>>());
+        for (_, r, result) in report.iter() {
+            assert!(result.is_ok(), "{r:?}: {result:?}");
+        }
+        assert_eq!(report.error_count(), 0);
+        assert!(report.structural.is_empty());
+        assert!(report.is_fully_verified());
+
+        // Break line 9 by citing a line in a sibling subproof, and line 13 by changing its rule
+        let line = |n: usize| *report.lines[n - 1].1.get::<<P as Proof>::JustificationReference, _>().unwrap();
+        let (line9, line11, line13) = (line(9), line(11), line(13));
+        prf.with_mut_step(&line9, |j| j.2 = vec![Coproduct::inject(line11)]).unwrap();
+        prf.with_mut_step(&line13, |j| j.1 = RuleM::Reit).unwrap();
+        let report = prf.verify_all();
+        assert_eq!(report.errors().map(|(n, r, _)| (n, *r)).collect::<Vec<_>>(), vec![(9, Coproduct::inject(line9)), (13, Coproduct::inject(line13))]);
+        let line11_ref: PjRef<P> = Coproduct::inject(line11);
+        assert!(report.structural.contains(&(Coproduct::inject(line11_ref), Coproduct::inject(line9))));
+        assert!(report.get(&Coproduct::inject(line11)).unwrap().is_ok());
+    }
+
+    #[test]
+    fn test_validate() {
+        use crate::proofs::StructuralError;
+        type P = PooledProof<HList![Expr]>;
+        let line_refs = |prf: &P| prf.iter_lines().filter_map(|info| info.reference.subset().ok()).collect::<Vec<PjRef<P>>>();
+
+        let data = &include_bytes!("../../../example-proofs/propositional_logic_arguments_for_proofs_ii_problem_10.bram")[..];
+        let (prf, _, _) = proof_from_xml::<P, _>(data).unwrap();
+        assert_eq!(prf.validate(), vec![]);
+
+        // The case analyses cite subproofs whose line ids are also their premises'
+        let data = &include_bytes!("../../../example-proofs/resolution_example.bram")[..];
+        let (prf, _, _) = proof_from_xml::<P, _>(data).unwrap();
+        assert_eq!(prf.validate(), vec![]);
+
+        /*
+        1 | | A
+          | -----
+        2 | | A ; REIT [1]
+        3 | | B
+          | -----
+        4 | | A ; REIT [2], which is inside the previous subproof
+        */
+        let xml = br#"
+        <bram>
+            <proof id="0">
+                <step linenum="1"><rule>SUBPROOF</rule><premise>1</premise></step>
+                <step linenum="3"><rule>SUBPROOF</rule><premise>2</premise></step>
+            </proof>
+            <proof id="1">
+                <assumption linenum="1"><raw>A</raw></assumption>
+                <step linenum="2"><raw>A</raw><rule>REITERATION</rule><premise>1</premise></step>
+            </proof>
+            <proof id="2">
+                <assumption linenum="3"><raw>B</raw></assumption>
+                <step linenum="4"><raw>A</raw><rule>REITERATION</rule><premise>2</premise></step>
+            </proof>
+        </bram>
+        "#;
+        let (prf, _, _) = proof_from_xml::<P, _>(&xml[..]).unwrap();
+        let lines = line_refs(&prf);
+        assert_eq!(prf.validate(), vec![StructuralError::OutOfScope { dep: Coproduct::inject(lines[1]), of: lines[3] }]);
+
+        /*
+        1 | A
+          | -----
+        2 | | B
+          | -----
+        3 | | A ; REIT [4], which comes after it
+        4 | A ; REIT [1]
+        */
+        let xml = br#"
+        <bram>
+            <proof id="0">
+                <assumption linenum="0"><raw>A</raw></assumption>
+                <step linenum="1"><rule>SUBPROOF</rule><premise>1</premise></step>
+                <step linenum="3"><raw>A</raw><rule>REITERATION</rule><premise>0</premise></step>
+            </proof>
+            <proof id="1">
+                <assumption linenum="1"><raw>B</raw></assumption>
+                <step linenum="2"><raw>A</raw><rule>REITERATION</rule><premise>3</premise></step>
+            </proof>
+        </bram>
+        "#;
+        let (prf, _, _) = proof_from_xml::<P, _>(&xml[..]).unwrap();
+        let lines = line_refs(&prf);
+        assert_eq!(prf.validate(), vec![StructuralError::ForwardReference { dep: Coproduct::inject(lines[3]), of: lines[2] }]);
+
+        // A step citing itself
+        let xml = br#"
+        <bram>
+            <proof id="0">
+                <assumption linenum="0"><raw>A</raw></assumption>
+                <step linenum="1"><raw>A</raw><rule>REITERATION</rule><premise>0</premise></step>
+            </proof>
+        </bram>
+        "#;
+        let (mut prf, _, _) = proof_from_xml::<P, _>(&xml[..]).unwrap();
+        let lines = line_refs(&prf);
+        let step = *lines[1].get::<<P as Proof>::JustificationReference, _>().unwrap();
+        prf.with_mut_step(&step, |j| j.2.push(lines[1])).unwrap();
+        assert_eq!(prf.validate(), vec![StructuralError::ForwardReference { dep: Coproduct::inject(lines[1]), of: lines[1] }]);
+
+        // Contents for a proof id that no step opens
+        let xml = br#"
+        <bram>
+            <proof id="0">
+                <assumption linenum="0"><raw>A</raw></assumption>
+            </proof>
+            <proof id="7">
+                <assumption linenum="1"><raw>B</raw></assumption>
+            </proof>
+        </bram>
+        "#;
+        assert_eq!(proof_from_xml::<P, _>(&xml[..]).unwrap_err().kind, XmlImportErrorKind::OrphanSubproof { proof_id: "7".into() });
+    }
+
+    #[test]
+    fn test_stable_ids_roundtrip() {
+        use crate::parser::parse_unwrap as p;
+        let data = &include_bytes!("../../../example-proofs/resolution_example.bram")[..];
+        type P = PooledProof<HList![Expr]>;
+        let (mut prf, metadata, _) = proof_from_xml::<P, _>(data).unwrap();
+        let ids = |prf: &P| prf.iter_lines().filter_map(|info| info.reference.subset().ok()).map(|r: PjRef<P>| (prf.stable_id_of(&r).unwrap(), prf.lookup_expr(&r).unwrap())).collect::<BTreeMap<_, _>>();
+
+        // Put a new line first, so that stable ids no longer follow the line order
+        let first = prf.premises()[0];
+        prf.add_premise_relative(p("Z"), &first, false);
+        let before = ids(&prf);
+        assert_eq!(before.len(), 17);
+
+        let mut xml = vec![];
+        xml_from_proof_and_metadata(&prf, &metadata, &mut xml).unwrap();
+        let (mut reloaded, _, _) = proof_from_xml::<P, _>(&xml[..]).unwrap();
+        assert_eq!(ids(&reloaded), before);
+
+        let r = reloaded.add_premise(p("Y"));
+        let id = reloaded.stable_id_of(&Coproduct::inject(r)).unwrap();
+        assert!(!before.contains_key(&id));
+        assert_eq!(reloaded.ref_of_stable_id(id), Some(Coproduct::inject(r)));
+    }
+
+    #[test]
+    fn test_next_stable_id_roundtrip() {
+        use crate::parser::parse_unwrap as p;
+        type P = PooledProof<HList![Expr]>;
+        let mut prf = P::new();
+        prf.add_premise(p("A"));
+        let removed = prf.add_premise(p("B"));
+        let removed_id = prf.stable_id_of(&Coproduct::inject(removed)).unwrap();
+        prf.remove_line(&Coproduct::inject(removed));
+
+        let mut xml = vec![];
+        xml_from_proof_and_metadata(&prf, &ProofMetaData::default(), &mut xml).unwrap();
+        let (mut reloaded, _, _) = proof_from_xml::<P, _>(&xml[..]).unwrap();
+        assert_eq!(reloaded.next_stable_id(), prf.next_stable_id());
+
+        // The removed line's id isn't handed out again
+        let r = reloaded.add_premise(p("C"));
+        assert_ne!(reloaded.stable_id_of(&Coproduct::inject(r)), Some(removed_id));
+    }
+
+    #[test]
+    fn test_duplicate_stable_ids() {
+        let xml = br#"
+        <bram>
+            <proof id="0">
+                <assumption linenum="0" stableid="3"><raw>A</raw></assumption>
+                <assumption linenum="1" stableid="3"><raw>B</raw></assumption>
+            </proof>
+        </bram>
+        "#;
+        assert_eq!(proof_from_xml::<PooledProof<HList![Expr]>, _>(&xml[..]).unwrap_err().kind, XmlImportErrorKind::DuplicateStableId { id: 3 });
+    }
+
+    #[test]
+    fn test_goals_roundtrip() {
+        use crate::parser::parse_unwrap as p;
+        type P = PooledProof<HList![Expr]>;
+        let mut prf = P::new();
+        prf.add_premise(p("A"));
+        for goals in [vec![], vec![p("A")], vec![p("∀x (P(x) → Q(x))"), p("¬A ∨ (B ∧ ⊥)"), p("exists y forall z R(y, z)")]] {
+            let metadata = ProofMetaData { goals: goals.clone(), ..Default::default() };
+            let mut xml = vec![];
+            xml_from_proof_and_metadata_with_hash(&prf, &metadata, &mut xml).unwrap();
+            let (_, reloaded, _) = proof_from_xml::<P, _>(&xml[..]).unwrap();
+            assert_eq!(reloaded.goals, goals);
+        }
+    }
+
+    #[test]
+    fn test_title_and_timestamps() {
+        use crate::parser::parse_unwrap as p;
+        use std::time::{Duration, UNIX_EPOCH};
+        type P = PooledProof<HList![Expr]>;
+        let mut prf = P::new();
+        prf.add_premise(p("A"));
+        let mut metadata = ProofMetaData { title: Some("Problem 3 <b>".into()), ..Default::default() };
+        metadata.mark_modified("2023-04-05T06:07:08Z".into());
+        metadata.mark_modified("2023-04-06T00:00:00Z".into());
+        assert_eq!((metadata.created.as_deref(), metadata.modified.as_deref()), (Some("2023-04-05T06:07:08Z"), Some("2023-04-06T00:00:00Z")));
+        let mut xml = vec![];
+        xml_from_proof_and_metadata_with_hash(&prf, &metadata, &mut xml).unwrap();
+        let (_, reloaded, status) = proof_from_xml::<P, _>(&xml[..]).unwrap();
+        assert_eq!((reloaded.title, reloaded.created, reloaded.modified), (metadata.title, metadata.created, metadata.modified));
+        assert_eq!(status, ImportedHashStatus::Matches);
+
+        // Files saved before they existed don't have them
+        let data = &include_bytes!("../../../example-proofs/resolution_example.bram")[..];
+        let (_, metadata, _) = proof_from_xml::<P, _>(data).unwrap();
+        assert_eq!((metadata.title, metadata.created, metadata.modified), (None, None, None));
+
+        assert_eq!(iso8601(UNIX_EPOCH), "1970-01-01T00:00:00Z");
+        assert_eq!(iso8601(UNIX_EPOCH + Duration::from_secs(951782400)), "2000-02-29T00:00:00Z");
+        assert_eq!(iso8601(UNIX_EPOCH + Duration::from_secs(1680674828)), "2023-04-05T06:07:08Z");
+        assert_eq!(iso8601(UNIX_EPOCH + Duration::from_secs(4102444799)), "2099-12-31T23:59:59Z");
+    }
+
+    #[test]
+    fn test_raw_inputs_roundtrip() {
+        use crate::parser::parse_unwrap as p;
+        type P = PooledProof<HList![Expr]>;
+        let mut prf = P::new();
+        let r1 = prf.add_premise(p("P"));
+        let r2 = prf.add_premise(p("Q"));
+        let id = prf.stable_id_of(&Coproduct::inject(r1)).unwrap();
+        let mut metadata = ProofMetaData::default();
+        metadata.raw_inputs.insert(id, "P -> (".into());
+        let mut xml = vec![];
+        xml_from_proof_and_metadata_with_hash(&prf, &metadata, &mut xml).unwrap();
+        let (reloaded, reloaded_metadata, status) = proof_from_xml::<P, _>(&xml[..]).unwrap();
+        assert_eq!(reloaded_metadata.raw_inputs, metadata.raw_inputs);
+        assert_eq!(reloaded.ref_of_stable_id(id), Some(Coproduct::inject(reloaded.premises()[0])));
+        assert_eq!(status, ImportedHashStatus::Matches);
+        assert!(prf.stable_id_of(&Coproduct::inject(r2)).is_some_and(|id| !reloaded_metadata.raw_inputs.contains_key(&id)));
+    }
+
+    #[test]
+    fn test_locked_lines() {
+        use crate::parser::parse_unwrap as p;
+        use crate::proofs::{EditError, LineNames, ProofDocument};
+        type P = PooledProof<HList![Expr]>;
+        let data = &include_bytes!("../../../example-proofs/locked_premises.bram")[..];
+        let (prf, metadata, _) = proof_from_xml::<P, _>(data).unwrap();
+        let premises = prf.premises();
+        let step = prf.lines()[0].get::<<P as Proof>::JustificationReference, _>().cloned().unwrap();
+        assert_eq!(metadata.locked.len(), 2);
+        let mut doc = ProofDocument { proof: prf, goals: metadata.goals.clone(), locked: metadata.locked.clone(), comments: metadata.comments.clone() };
+        assert!(premises.iter().all(|pr| doc.is_locked(&Coproduct::inject(*pr))));
+        assert!(!doc.is_locked(&Coproduct::inject(step)));
+
+        // Locked premises can't be changed or removed
+        assert_eq!(doc.with_mut_premise(&premises[0], |e| *e = p("R")), Err(EditError::LockedLine(Coproduct::inject(premises[0]))));
+        assert_eq!(doc.remove_line(&Coproduct::inject(premises[1])), Err(EditError::LockedLine(Coproduct::inject(premises[1]))));
+        assert_eq!(doc.proof.lookup_premise(&premises[0]), Some(p("P -> Q")));
+        assert_eq!(doc.proof.premises().len(), 2);
+
+        // Other lines can
+        assert_eq!(doc.with_mut_step(&step, |j| j.0 = p("Q & Q")), Ok(()));
+        assert_eq!(doc.proof.lookup_step(&step).map(|j| j.0), Some(p("Q & Q")));
+
+        // The lock survives saving and loading
+        let mut xml = vec![];
+        xml_from_proof_and_metadata(&doc.proof, &metadata, &mut xml).unwrap();
+        assert_eq!(String::from_utf8(xml.clone()).unwrap().matches(r#"locked="true""#).count(), 2);
+        let (reloaded, reloaded_metadata, _) = proof_from_xml::<P, _>(&xml[..]).unwrap();
+        assert_eq!(reloaded_metadata.locked, metadata.locked);
+        assert!(reloaded.premises().iter().all(|pr| reloaded.stable_id_of(&Coproduct::inject(*pr)).is_some_and(|id| reloaded_metadata.locked.contains(&id))));
+
+        // A subproof with a locked line in it can't be deleted, even from one of its other lines
+        let sub = doc.proof.add_subproof();
+        let (sp, ss) = doc
+            .proof
+            .with_mut_subproof(&sub, |sub| {
+                let sp = sub.add_premise(p("P"));
+                (sp, sub.add_step(Justification(p("P"), RuleM::Reit, vec![Coproduct::inject(sp)], vec![])))
+            })
+            .unwrap();
+        assert!(doc.lock(&Coproduct::inject(sp)));
+        let err = doc.delete_enclosing_subproof(&Coproduct::inject(ss)).err();
+        assert_eq!(err, Some(EditError::LockedLine(Coproduct::inject(sp))));
+        assert_eq!(doc.remove_subproof(&sub).err(), err);
+        assert_eq!(doc.proof.lookup_step(&ss).map(|j| j.0), Some(p("P")));
+        let names = LineNames::new(&doc.proof);
+        assert_eq!(err.unwrap().describe(|r| names.line_or_subproof(r)), "Can't change line 4, which is locked.");
+    }
+
+    #[test]
+    fn test_comments() {
+        use crate::parser::parse_unwrap as p;
+        use crate::proofs::{MoveDirection, ProofDocument};
+        type P = PooledProof<HList![Expr]>;
+        let mut prf = P::new();
+        let r1 = prf.add_premise(p("A"));
+        let r2 = prf.add_premise(p("B"));
+        let r3 = prf.add_premise(p("C"));
+        let step = prf.add_step(Justification(p("A & B"), RuleM::AndIntro, vec![Coproduct::inject(r1), Coproduct::inject(r2)], vec![]));
+        let mut doc = ProofDocument::new(prf, vec![]);
+        let special = "<b>\"A\" & 'B'</b> ]]>";
+        let unicode = "∀x — 証明 👍";
+        assert!(doc.set_comment(&Coproduct::inject(r2), Some(special.into())));
+        assert!(doc.set_comment(&Coproduct::inject(r3), Some("scratch".into())));
+        assert!(doc.set_comment(&Coproduct::inject(step), Some(unicode.into())));
+        assert_eq!(doc.comment(&Coproduct::inject(r2)), Some(special));
+        assert_eq!(doc.comment(&Coproduct::inject(r1)), None);
+
+        // Removing a line removes its comment, and the rest stay with their
+        // lines as they're moved and the proof is compacted
+        doc.remove_line(&Coproduct::inject(r3)).unwrap();
+        assert_eq!(doc.comments.len(), 2);
+        doc.proof.move_premise(&r2, MoveDirection::Up).unwrap();
+        doc.proof.compact();
+        let by_formula = |prf: &P, comments: &BTreeMap<LineId, String>| comments.iter().map(|(id, comment)| (prf.ref_of_stable_id(*id).and_then(|r| prf.lookup_expr(&r)).unwrap(), comment.clone())).collect::<HashMap<_, _>>();
+        let expected = HashMap::from([(p("B"), special.to_string()), (p("A & B"), unicode.to_string())]);
+        assert_eq!(by_formula(&doc.proof, &doc.comments), expected);
+
+        // They survive saving and loading, escaped in the XML
+        let metadata = ProofMetaData { comments: doc.comments.clone(), ..Default::default() };
+        let mut xml = vec![];
+        xml_from_proof_and_metadata(&doc.proof, &metadata, &mut xml).unwrap();
+        let text = String::from_utf8(xml.clone()).unwrap();
+        assert!(text.contains("<comment>&lt;b") && !text.contains("<b>"));
+        assert!(text.contains(&format!("<comment>{unicode}</comment>")));
+        let (reloaded, reloaded_metadata, _) = proof_from_xml::<P, _>(&xml[..]).unwrap();
+        assert_eq!(by_formula(&reloaded, &reloaded_metadata.comments), expected);
+
+        // Deleting a line drops its comment
+        let mut doc = ProofDocument { proof: reloaded, goals: vec![], locked: BTreeSet::new(), comments: reloaded_metadata.comments };
+        let step = doc.proof.lines()[0].get::<<P as Proof>::JustificationReference, _>().cloned().unwrap();
+        doc.delete_line(&Coproduct::inject(step)).unwrap();
+        assert_eq!(by_formula(&doc.proof, &doc.comments), HashMap::from([(p("B"), special.to_string())]));
+    }
+
+    #[test]
+    fn test_subproof_export() {
+        use crate::parser::parse_unwrap as p;
+        use crate::proofs::ExtractError;
+        use frunk_core::Coprod;
+        type P = PooledProof<HList![Expr]>;
+        let mut prf = P::new();
+        let p1 = prf.add_premise(p("D"));
+        let lemma = prf.add_subproof();
+        prf.with_mut_subproof(&lemma, |sub| {
+            let sp = sub.add_premise(p("A & B"));
+            let s1 = sub.add_step(Justification(p("A"), RuleM::AndElim, vec![Coproduct::inject(sp)], vec![]));
+            let s2 = sub.add_step(Justification(p("B"), RuleM::AndElim, vec![Coproduct::inject(sp)], vec![]));
+            sub.add_step(Justification(p("B & A"), RuleM::AndIntro, vec![Coproduct::inject(s2), Coproduct::inject(s1)], vec![]));
+        });
+        let s1 = prf.add_step(Justification(p("D"), RuleM::Reit, vec![Coproduct::inject(p1)], vec![]));
+        let escaping = prf.add_subproof();
+        prf.with_mut_subproof(&escaping, |sub| {
+            sub.add_premise(p("E"));
+            sub.add_step(Justification(p("D"), RuleM::Reit, vec![Coproduct::inject(s1)], vec![]));
+            sub.add_step(Justification(p("D"), RuleM::Reit, vec![Coproduct::inject(p1)], vec![]));
+        });
+        let metadata = ProofMetaData { author: Some("student".into()), hash: Some("stale".into()), title: Some("Lemma".into()), goals: vec![p("B & A")], ..Default::default() };
+
+        // A self-contained subproof loads as a proof of its own that checks
+        let mut xml = vec![];
+        xml_from_subproof(&prf, &lemma, &metadata, &mut xml).unwrap();
+        let (reloaded, reloaded_metadata, status) = proof_from_xml::<P, _>(&xml[..]).unwrap();
+        assert!(reloaded.verify_all().is_fully_verified());
+        assert_eq!(reloaded.premises().iter().map(|r| reloaded.lookup_premise(r)).collect::<Vec<_>>(), vec![Some(p("A & B"))]);
+        assert_eq!(reloaded.lines().len(), 3);
+        assert_eq!((reloaded_metadata.title, reloaded_metadata.goals), (Some("Lemma".into()), vec![p("B & A")]));
+        assert_eq!(status, ImportedHashStatus::Absent);
+
+        // One that cites outer lines lists them, in the order they're cited
+        let outer = |r: PjRef<P>| -> Coprod!(PjRef<P>, <P as Proof>::SubproofReference) { Coproduct::inject(r) };
+        match xml_from_subproof(&prf, &escaping, &metadata, &mut Vec::<u8>::new()) {
+            Err(SubproofExportError::Extract(ExtractError::EscapingDependency { lines })) => assert_eq!(lines, vec![outer(Coproduct::inject(s1)), outer(Coproduct::inject(p1))]),
+            other => panic!("expected an escaping dependency, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_canonical_formulas() {
+        use crate::parser::parse;
+        use std::path::{Path, PathBuf};
+        type P = PooledProof<HList![Expr]>;
+        fn bram_files(dir: &Path, out: &mut Vec<PathBuf>) {
+            for entry in std::fs::read_dir(dir).unwrap() {
+                let path = entry.unwrap().path();
+                if path.is_dir() {
+                    bram_files(&path, out);
+                } else if path.extension().is_some_and(|ext| ext == "bram") {
+                    out.push(path);
+                }
+            }
+        }
+        let mut files = vec![];
+        bram_files(&Path::new(env!("CARGO_MANIFEST_DIR")).join("../example-proofs"), &mut files);
+        let mut checked = 0;
+        for file in files {
+            // The damaged fixtures don't load, which `test_lenient_import` covers
+            let (prf, metadata, _, _) = match proof_from_xml_compat::<P, _>(std::fs::File::open(&file).unwrap()) {
+                Ok(loaded) => loaded,
+                Err(_) => continue,
+            };
+            let exprs = prf.iter_lines().filter_map(|info| info.reference.subset().ok()).filter_map(|r: PjRef<P>| prf.lookup_expr(&r)).chain(metadata.goals);
+            for expr in exprs {
+                // Writing what was read gives text that reads back as itself
+                let written = parse(&expr.to_canonical_string()).unwrap_or_else(|| panic!("{} from {} doesn't parse", expr.to_canonical_string(), file.display())).to_canonical_string();
+                assert_eq!(parse(&written).map(|e| e.to_canonical_string()), Some(written.clone()), "in {}", file.display());
+                checked += 1;
+            }
+        }
+        assert!(checked > 0);
+    }
+
+    #[test]
+    fn test_annotations() {
+        use crate::parser::parse_unwrap as p;
+        type P = PooledProof<HList![Expr]>;
+        let mut prf = P::new();
+        let r1 = prf.add_premise(p("A & B"));
+        let good = prf.add_step(Justification(p("A"), RuleM::AndElim, vec![Coproduct::inject(r1)], vec![]));
+        let bad = prf.add_step(Justification(p("C"), RuleM::AndElim, vec![Coproduct::inject(r1)], vec![]));
+        let id = |prf: &P, r: PjRef<P>| prf.stable_id_of(&r).unwrap();
+        let metadata = ProofMetaData::default();
+        let mut xml = vec![];
+        xml_from_proof_and_metadata_with_verification(&prf, &metadata, &mut xml).unwrap();
+        let text = String::from_utf8(xml.clone()).unwrap();
+        let message = format!("{}", prf.verify_line(&Coproduct::inject(bad)).unwrap_err());
+        assert!(text.contains(r#"<annotation verified="true" />"#));
+        assert!(text.contains(r#"<annotation verified="false">"#));
+
+        // Annotations don't change the proof, and are kept when it's saved again without edits
+        let (mut reloaded, reloaded_metadata, _) = proof_from_xml::<P, _>(&xml[..]).unwrap();
+        assert_eq!(TreeProof::from_proof(&reloaded), TreeProof::from_proof(&prf));
+        let annotations = reloaded_metadata.annotations.clone().unwrap();
+        assert_eq!(annotations.lines[&id(&prf, Coproduct::inject(good))], Ok(()));
+        assert_eq!(annotations.lines[&id(&prf, Coproduct::inject(bad))], Err(message));
+        assert!(!annotations.lines.contains_key(&id(&prf, Coproduct::inject(r1))));
+        assert_eq!(annotations.by_line_number(&reloaded).len(), 2);
+        let mut resaved = vec![];
+        xml_from_proof_and_metadata(&reloaded, &reloaded_metadata, &mut resaved).unwrap();
+        assert_eq!(resaved, xml);
+
+        // After an edit, they're no longer for the proof, so they're left out
+        reloaded.add_premise(p("D"));
+        assert!(annotations.by_line_number(&reloaded).is_empty());
+        let mut edited = vec![];
+        xml_from_proof_and_metadata(&reloaded, &reloaded_metadata, &mut edited).unwrap();
+        assert!(!String::from_utf8(edited).unwrap().contains("<annotation"));
+
+        // Messages are escaped
+        let mut metadata = metadata;
+        metadata.annotations = Some(Annotations { lines: [(id(&prf, Coproduct::inject(bad)), Err("expected A < B & C".into()))].into_iter().collect(), content_hash: prf.content_hash() });
+        let mut xml = vec![];
+        xml_from_proof_and_metadata(&prf, &metadata, &mut xml).unwrap();
+        assert!(String::from_utf8(xml.clone()).unwrap().contains("expected A &lt; B &amp; C"));
+        assert_eq!(proof_from_xml::<P, _>(&xml[..]).unwrap().1.annotations, metadata.annotations);
+
+        // Messages name lines by their line numbers
+        let mut prf = P::new();
+        let r1 = prf.add_premise(p("A"));
+        let r2 = prf.add_step(Justification(p("A"), RuleM::Reit, vec![], vec![]));
+        let r3 = prf.add_step(Justification(p("A"), RuleM::Reit, vec![Coproduct::inject(r1)], vec![]));
+        prf.with_mut_step(&r2, |j| j.2 = vec![Coproduct::inject(r3)]);
+        let annotations = Annotations::from_verification(&prf);
+        assert_eq!(annotations.lines[&id(&prf, Coproduct::inject(r2))], Err("The dependency (line 3) comes after the step that uses it.".into()));
+    }
+
+    #[test]
+    fn test_submission_hash() {
+        use crate::parser::parse_unwrap as p;
+        type P = PooledProof<HList![Expr]>;
+        let save = |prf: &P, metadata: &ProofMetaData| {
+            let mut xml = vec![];
+            xml_from_proof_and_metadata_with_hash(prf, metadata, &mut xml).unwrap();
+            String::from_utf8(xml).unwrap()
+        };
+        let status = |xml: &str| proof_from_xml::<P, _>(xml.as_bytes()).unwrap().2;
+
+        let data = &include_bytes!("../../../example-proofs/resolution_example.bram")[..];
+        let (prf, metadata, _) = proof_from_xml::<P, _>(data).unwrap();
+        let xml = save(&prf, &metadata);
+        assert_eq!(status(&xml), ImportedHashStatus::Matches);
+
+        let mut prf = P::new();
+        prf.add_premise(p("A"));
+        prf.add_premise(p("B"));
+        let metadata = ProofMetaData { author: Some("student".into()), goals: vec![p("A ∧ B")], ..Default::default() };
+        let xml = save(&prf, &metadata);
+        assert_eq!(status(&xml), ImportedHashStatus::Matches);
+
+        // Editing a formula, the author, or a goal by hand
+        assert!(xml.contains("<raw>B</raw>"));
+        assert_eq!(status(&xml.replace("<raw>B</raw>", "<raw>C</raw>")), ImportedHashStatus::Mismatch);
+        assert_eq!(status(&xml.replace("<author>student</author>", "<author>someone else</author>")), ImportedHashStatus::Mismatch);
+        assert_eq!(status(&xml.replace("<raw>(A ∧ B)</raw>", "<raw>(B ∧ A)</raw>")), ImportedHashStatus::Mismatch);
+
+        // An author can't pass for a goal
+        assert_ne!(submission_hash(&prf, Some("student\ngoal (A ∧ B)"), &[]), submission_hash(&prf, Some("student"), &[p("A ∧ B")]));
+
+        // A file saved before hashes were written
+        let legacy = r#"
+        <bram>
+            <metadata><author>student</author></metadata>
+            <proof id="0">
+                <assumption linenum="0"><raw>A</raw></assumption>
+                <step linenum="1"><raw>A</raw><rule>REITERATION</rule><premise>0</premise></step>
+            </proof>
+        </bram>
+        "#;
+        assert_eq!(status(legacy), ImportedHashStatus::Absent);
+    }
+
+    #[test]
+    fn test_import_errors() {
+        use XmlImportErrorKind::*;
+        type P = PooledProof<HList![Expr]>;
+        let load = |xml: &[u8]| proof_from_xml::<P, _>(xml).unwrap_err().kind;
+
+        // Cut off partway through
+        let data = &include_bytes!("../../../example-proofs/resolution_example.bram")[..];
+        assert!(matches!(load(&data[..data.len() / 2]), Syntax { .. }));
+
+        let xml = br#"
+        <bram>
+            <proof id="0">
+                <assumption linenum="0"><raw>A</raw></assumption>
+                <step linenum="1"><raw>A</raw><rule>BOGUS_RULE</rule><premise>0</premise></step>
+            </proof>
+        </bram>
+        "#;
+        assert_eq!(load(xml), UnknownRule { name: "BOGUS_RULE".into(), line_id: "1".into() });
+
+        let xml = br#"
+        <bram>
+            <proof id="0">
+                <assumption linenum="0"><raw>A</raw></assumption>
+                <step linenum="1"><raw>A</raw><rule>REITERATION</rule><premise>9</premise></step>
+            </proof>
+        </bram>
+        "#;
+        assert_eq!(load(xml), DanglingReference { id: "9".into(), cited_by: "1".into() });
+
+        let xml = br#"
+        <bram>
+            <proof id="0">
+                <assumption linenum="0"><raw>A &amp;</raw></assumption>
+            </proof>
+        </bram>
+        "#;
+        assert_eq!(load(xml), UnparseableFormula { text: "A &".into(), line_id: Some("0".into()) });
+
+        let xml = br#"
+        <bram>
+            <proof id="0">
+                <assumption><raw>A</raw></assumption>
+                <sticker>A</sticker>
+            </proof>
+        </bram>
+        "#;
+        assert_eq!(load(xml), MissingAttribute { element: "assumption".into(), attribute: "linenum".into() });
+        let xml = br#"<bram><proof id="0"><sticker>A</sticker></proof></bram>"#;
+        assert_eq!(load(xml), UnknownElement { element: "sticker".into() });
+        let xml = br#"<bram><proof id="0" color="red"></proof></bram>"#;
+        assert_eq!(load(xml), UnknownAttribute { element: "proof".into(), attribute: "color".into() });
+    }
+
+    #[test]
+    fn test_lenient_import() {
+        use XmlImportErrorKind::*;
+        type P = PooledProof<HList![Expr]>;
+        let data = &include_bytes!("../../../example-proofs/damaged/two_bad_formulas.bram")[..];
+
+        // Both formulas are reported, where they are in the file
+        let (loaded, errors) = proof_from_xml_lenient::<P, _>(data);
+        let expected = vec![
+            XmlImportError { kind: UnparseableFormula { text: "P ->".into(), line_id: Some("4".into()) }, line: 11, column: 5, path: "proof[1] > step[2]".into() },
+            XmlImportError { kind: UnparseableFormula { text: "(Q".into(), line_id: Some("3".into()) }, line: 21, column: 5, path: "proof[2] > step[1]".into() },
+        ];
+        assert_eq!(errors, expected);
+        assert_eq!(errors[1].to_string(), "Line 21, column 5, in proof[2] > step[1]: Failed to parse \"(Q\" at line id 3.");
+
+        // The rest of the proof is read, with the bad formulas kept as what was typed into blank lines
+        let (prf, metadata, _, _) = loaded.unwrap();
+        assert_eq!(prf.iter_lines().filter(|info| info.line.is_some()).count(), 4);
+        let mut inputs = metadata.raw_inputs.values().cloned().collect::<Vec<_>>();
+        inputs.sort();
+        assert_eq!(inputs, vec!["(Q".to_string(), "P ->".to_string()]);
+
+        // Reading strictly stops at the first
+        assert_eq!(proof_from_xml::<P, _>(data).unwrap_err(), expected[0]);
+
+        // Unknown rules and citations of missing lines are read past too
+        let xml = br#"<bram version="1.1"><proof id="0">
+            <assumption linenum="0"><raw>A</raw></assumption>
+            <step linenum="1"><raw>A</raw><rule>BOGUS_RULE</rule><premise>0</premise><premise>9</premise></step>
+        </proof></bram>"#;
+        let (loaded, errors) = proof_from_xml_lenient::<P, _>(&xml[..]);
+        assert_eq!(errors.iter().map(|err| (err.kind.clone(), err.line)).collect::<Vec<_>>(), vec![(UnknownRule { name: "BOGUS_RULE".into(), line_id: "1".into() }, 3), (DanglingReference { id: "9".into(), cited_by: "1".into() }, 3)]);
+        let (prf, _, _, _) = loaded.unwrap();
+        let step = prf.lookup_step(&prf.lines()[0].uninject().unwrap()).unwrap();
+        assert_eq!((step.1, step.2.len()), (RuleM::EmptyRule, 1));
+
+        // Malformed XML leaves no proof
+        let (loaded, errors) = proof_from_xml_lenient::<P, _>(&data[..data.len() / 2]);
+        assert!(loaded.is_none());
+        assert!(matches!(errors.last().map(|err| &err.kind), Some(Syntax { .. })));
+    }
+
+    #[test]
+    fn test_workbook_roundtrip() {
+        use crate::parser::parse_unwrap as p;
+        type P = PooledProof<HList![Expr]>;
+        let data = &include_bytes!("../../../example-proofs/resolution_example.bram")[..];
+        let (resolution, resolution_metadata, resolution_status) = proof_from_xml::<P, _>(data).unwrap();
+        let mut empty = P::new();
+        empty.add_subproof();
+        let mut goal_only = P::new();
+        goal_only.add_premise(p("A"));
+        let metadata = |author: &str, goals| ProofMetaData { author: Some(author.into()), goals, ..Default::default() };
+        let workbook = vec![(resolution_metadata, &resolution), (metadata("problem 2", vec![]), &empty), (metadata("problem 3", vec![p("A | B")]), &goal_only)];
+
+        let mut xml = vec![];
+        workbook_to_xml(&workbook, &mut xml).unwrap();
+        let loaded = workbook_from_xml::<P, _>(&xml[..]).unwrap();
+        assert_eq!(loaded.len(), 3);
+        for ((prf, metadata, _), (expected_metadata, expected)) in loaded.iter().zip(workbook.iter()) {
+            assert_eq!(TreeProof::from_proof(prf), TreeProof::from_proof(*expected));
+            assert_eq!((&metadata.author, &metadata.goals), (&expected_metadata.author, &expected_metadata.goals));
+        }
+        assert_eq!(loaded.iter().map(|(_, _, status)| *status).collect::<Vec<_>>(), vec![resolution_status, ImportedHashStatus::Absent, ImportedHashStatus::Absent]);
+
+        // Errors say which proof of the workbook they're in
+        let text = String::from_utf8(xml).unwrap().replace("<raw>(A ∨ B)</raw>", "<raw>(A ∨</raw>");
+        let err = workbook_from_xml::<P, _>(text.as_bytes()).unwrap_err();
+        assert_eq!(err.kind, XmlImportErrorKind::UnparseableFormula { text: "(A ∨".into(), line_id: None });
+        assert_eq!(err.path, "bram[3] > proof[1] > goal[1]");
+    }
+
+    #[test]
+    fn test_workbook_single_proof() {
+        type P = PooledProof<HList![Expr]>;
+        let data = &include_bytes!("../../../example-proofs/legacy/java_nullary_predicates.bram")[..];
+        let (prf, metadata, status) = proof_from_xml::<P, _>(data).unwrap();
+        let loaded = workbook_from_xml::<P, _>(data).unwrap();
+        assert_eq!(loaded.len(), 1);
+        assert_eq!(TreeProof::from_proof(&loaded[0].0), TreeProof::from_proof(&prf));
+        assert_eq!((&loaded[0].1.goals, loaded[0].2), (&metadata.goals, status));
+
+        // A single proof can't be read from a workbook of several
+        let mut xml = vec![];
+        workbook_to_xml(&[(metadata.clone(), &prf), (metadata, &prf)], &mut xml).unwrap();
+        assert!(matches!(proof_from_xml::<P, _>(&xml[..]).map_err(|err| err.kind), Err(XmlImportErrorKind::UnknownElement { .. })));
+    }
+
+    #[test]
+    fn test_compat() {
+        use crate::parser::parse_unwrap as p;
+        use CompatWarning::*;
+        type P = PooledProof<HList![Expr]>;
+
+        // Predicates applied to no arguments, an older rule name, and a goal outside of the proof
+        let data = &include_bytes!("../../../example-proofs/legacy/java_nullary_predicates.bram")[..];
+        let (prf, metadata, _, warnings) = proof_from_xml_compat::<P, _>(data).unwrap();
+        let nullary = |id: &str| NullaryApplication { line_id: Some(id.into()) };
+        assert_eq!(
+            warnings,
+            vec![nullary("0"), nullary("1"), RenamedRule { from: "DOUBLE_NEGATION".into(), to: "DOUBLENEGATION".into(), line_id: "2".into() }, nullary("2"), nullary("3"), GoalOutsideProof { parent: "bram".into() }, NullaryApplication { line_id: None }]
+        );
+        let exprs = prf.iter_lines().filter_map(|info| info.reference.subset().ok()).map(|r: PjRef<P>| prf.lookup_expr(&r).unwrap()).collect::<Vec<_>>();
+        assert_eq!(exprs, vec![p("~~P"), p("P -> Q"), p("P"), p("Q")]);
+        assert_eq!(metadata.goals, vec![p("Q")]);
+        assert!(prf.verify_all().is_fully_verified());
+
+        // Text in Windows-1252
+        let data = &include_bytes!("../../../example-proofs/legacy/java_windows_1252.bram")[..];
+        let (prf, metadata, _, warnings) = proof_from_xml_compat::<P, _>(data).unwrap();
+        assert_eq!(warnings, vec![TranscodedFromWindows1252]);
+        assert_eq!(metadata.author.as_deref(), Some("José O’Brien"));
+        assert_eq!(metadata.goals, vec![p("A")]);
+        assert!(prf.verify_all().is_fully_verified());
+
+        // A current file needs no accommodations
+        let data = &include_bytes!("../../../example-proofs/resolution_example.bram")[..];
+        assert_eq!(proof_from_xml_compat::<P, _>(data).unwrap().3, vec![]);
+    }
+
+    #[test]
+    fn test_format_version() {
+        use CompatWarning::*;
+        type P = PooledProof<HList![Expr]>;
+        let load = |xml: &str| proof_from_xml_compat::<P, _>(xml.as_bytes()).map(|(prf, _, _, warnings)| (prf.verify_all().is_fully_verified(), warnings)).map_err(|err| err.kind);
+        let xml = |version: &str| {
+            format!(
+                r#"
+                <bram{version}>
+                    <proof id="0">
+                        <assumption linenum="0"><raw>P()</raw></assumption>
+                        <step linenum="1"><raw>P()</raw><rule>REITERATE</rule><premise>0</premise></step>
+                    </proof>
+                </bram>
+                "#
+            )
+        };
+
+        // Without a version, the file is from the Java version of Aris
+        let renamed = RenamedRule { from: "REITERATE".into(), to: "REITERATION".into(), line_id: "1".into() };
+        let nullary = |id: &str| NullaryApplication { line_id: Some(id.into()) };
+        assert_eq!(load(&xml("")), Ok((true, vec![nullary("0"), renamed, nullary("1")])));
+        assert_eq!(load(&xml(r#" version="0.9""#)).map(|(_, warnings)| warnings.len()), Ok(3));
+
+        // The current version is read as it is
+        assert_eq!(load(&xml(r#" version="1.0""#)), Err(XmlImportErrorKind::UnknownRule { name: "REITERATE".into(), line_id: "1".into() }));
+        let current = xml(r#" version="1.0""#).replace("P()", "P").replace("REITERATE", "REITERATION");
+        assert_eq!(load(&current), Ok((true, vec![])));
+        let mut prf = P::new();
+        prf.add_premise(crate::parser::parse_unwrap("P"));
+        let mut saved = vec![];
+        xml_from_proof_and_metadata(&prf, &ProofMetaData::default(), &mut saved).unwrap();
+        assert!(String::from_utf8(saved).unwrap().contains(r#"<bram version="1.6" nextid="1">"#));
+
+        // A later minor version can add elements and attributes, which are skipped
+        let later = current.replace(r#"version="1.0""#, r#"version="1.7""#).replace(r#"<proof id="0">"#, r#"<proof id="0" color="red"><sticker>A <raw>B</raw></sticker>"#);
+        let (_, warnings) = load(&later).unwrap();
+        assert!(matches!(&warnings[..], [UnknownAttribute { attribute, .. }, UnknownElement { element, .. }] if attribute == "color" && element == "sticker"));
+
+        // A later major version isn't read
+        let future = current.replace(r#"version="1.0""#, r#"version="2.0""#);
+        assert_eq!(load(&future), Err(XmlImportErrorKind::UnsupportedVersion { found: "2.0".into(), supported: "1.6".into() }));
+        assert!(matches!(proof_from_xml::<P, _>(future.as_bytes()).map_err(|err| err.kind), Err(XmlImportErrorKind::UnsupportedVersion { .. })));
+        let malformed = current.replace(r#"version="1.0""#, r#"version="one""#);
+        assert_eq!(load(&malformed), Err(XmlImportErrorKind::InvalidAttribute { element: "bram".into(), attribute: "version".into(), value: "one".into() }));
+    }
+
+    #[test]
+    fn test_incomplete_subproofs() {
+        use CompatWarning::*;
+        type P = PooledProof<HList![Expr]>;
+        let xml = r#"
+        <bram version="1.6">
+            <proof id="0">
+                <assumption linenum="0"><raw>A</raw></assumption>
+                <step linenum="1"><rule>SUBPROOF</rule><premise>1</premise></step>
+                <step linenum="3"><rule>SUBPROOF</rule><premise>2</premise></step>
+                <step linenum="4"><rule>SUBPROOF</rule><premise>3</premise></step>
+            </proof>
+            <proof id="1">
+                <assumption linenum="1"><raw>B</raw></assumption>
+                <step linenum="2"><raw>B</raw><rule>REITERATION</rule><premise>1</premise></step>
+            </proof>
+            <proof id="2">
+                <assumption linenum="3"><raw>C</raw></assumption>
+            </proof>
+            <proof id="3">
+            </proof>
+        </bram>
+        "#;
+        let (prf, _, _, warnings) = proof_from_xml_compat::<P, _>(xml.as_bytes()).unwrap();
+        assert_eq!(prf.incomplete_subproofs().len(), 2);
+        assert_eq!(warnings, vec![IncompleteSubproof { line_id: "3".into() }, IncompleteSubproof { line_id: "4".into() }]);
+        assert_eq!(warnings[0].to_string(), "The subproof at line id 3 is missing a premise or a step.");
+    }
+
+    #[test]
+    fn test_ignored_content() {
+        use CompatWarning::*;
+        type P = PooledProof<HList![Expr]>;
+        let data = &include_bytes!("../../../example-proofs/newer_minor_version.bram")[..];
+        let (prf, metadata, _, warnings) = proof_from_xml_compat::<P, _>(data).unwrap();
+        let expected = vec![
+            UnknownElement { element: "difficulty".into(), line: 5, column: 5, path: "metadata[1] > difficulty[1]".into() },
+            UnknownAttribute { element: "assumption".into(), attribute: "color".into(), line: 11, column: 5, path: "proof[1] > assumption[2]".into() },
+            UnknownElement { element: "hint".into(), line: 19, column: 7, path: "proof[1] > step[1] > hint[1]".into() },
+        ];
+        assert_eq!(warnings[..3], expected[..]);
+        assert!(matches!(&warnings[3..], [SkippedContent { text, path, .. }] if text == "Scratch work" && path == "proof[1]"));
+        assert!(warnings.iter().all(CompatWarning::is_ignored_content));
+        assert_eq!(warnings[2].to_string(), "The unknown element <hint> at line 19, column 7, in proof[1] > step[1] > hint[1] was ignored.");
+        assert_eq!(summarize_ignored_content(&warnings), ["2 elements from a newer version were ignored", "1 attribute from a newer version was ignored", "1 piece of text in an unexpected place was ignored"]);
+        assert!(summarize_ignored_content(&[RenamedRule { from: "REITERATE".into(), to: "REITERATION".into(), line_id: "1".into() }]).is_empty());
+
+        // The rest of the proof is read as if they weren't there, including the rule inside the unknown element
+        let (expected, expected_metadata, _) = proof_from_xml::<P, _>(&include_bytes!("../../../example-proofs/locked_premises.bram")[..]).unwrap();
+        assert_eq!(TreeProof::from_proof(&prf), TreeProof::from_proof(&expected));
+        assert_eq!((metadata.title, metadata.goals, metadata.locked), (expected_metadata.title, expected_metadata.goals, expected_metadata.locked));
+    }
+
+    #[test]
+    fn test_content_hash() {
+        use crate::parser::parse_unwrap as p;
+        let data = &include_bytes!("../../../example-proofs/resolution_example.bram")[..];
+        type P = PooledProof<HList![Expr]>;
+        let (mut prf, metadata, _) = proof_from_xml::<P, _>(data).unwrap();
+        let hash = prf.content_hash();
+
+        // Saving and reloading, and compacting away deleted lines, keep the hash
+        let mut xml = vec![];
+        xml_from_proof_and_metadata(&prf, &metadata, &mut xml).unwrap();
+        let (reloaded, _, _) = proof_from_xml::<P, _>(&xml[..]).unwrap();
+        assert_eq!(reloaded.content_hash(), hash);
+        let extra = prf.add_premise(p("Z"));
+        assert_ne!(prf.content_hash(), hash);
+        prf.remove_line(&Coproduct::inject(extra));
+        assert_eq!(prf.content_hash(), hash);
+        prf.compact();
+        assert_eq!(prf.content_hash(), hash);
+
+        // Editing a formula or toggling a dependency changes it
+        let step = prf.iter_lines().filter_map(|info| info.reference.uninject().ok()).find(|jr: &<P as Proof>::JustificationReference| prf.lookup_step(jr).is_some_and(|j| !j.2.is_empty())).unwrap();
+        let dep = prf.with_mut_step(&step, |j| j.2.remove(0)).unwrap();
+        assert_ne!(prf.content_hash(), hash);
+        prf.with_mut_step(&step, |j| j.2.insert(0, dep));
+        assert_eq!(prf.content_hash(), hash);
+        let premise = prf.premises()[0];
+        let old = prf.with_mut_premise(&premise, |e| std::mem::replace(e, p("Z"))).unwrap();
+        assert_ne!(prf.content_hash(), hash);
+        prf.with_mut_premise(&premise, |e| *e = old);
+        assert_eq!(prf.content_hash(), hash);
+
+        // It doesn't depend on the build
+        let mut prf = P::new();
+        prf.add_premise(p("A"));
+        assert_eq!(prf.content_hash(), 0xa56e_a0a9_9e19_734c);
+    }
+
+    #[test]
+    fn test_import_as_subproof() {
+        use crate::parser::parse_unwrap as p;
+        let data = &include_bytes!("../../../example-proofs/resolution_example.bram")[..];
+        type P = PooledProof<HList![Expr]>;
+        let (other, _, _) = proof_from_xml::<P, _>(data).unwrap();
+        let other_report = other.verify_all();
+
+        let mut prf = P::new();
+        let p1 = prf.add_premise(p("A"));
+        let s1 = prf.add_step(Justification(p("A"), RuleM::Reit, vec![Coproduct::inject(p1)], vec![]));
+        let sub = prf.import_as_subproof(&other, &Coproduct::inject(s1), true);
+        assert_eq!(prf.lines(), vec![Coproduct::inject(s1), Coproduct::inject(sub)]);
+        assert_eq!(prf.lookup_subproof(&sub).unwrap().premises().len(), 2);
+
+        // Every copied line checks exactly as it did in the original proof
+        let report = prf.verify_all();
+        assert_eq!(report.lines.len(), 2 + other_report.lines.len());
+        let copied = report.iter().skip(2).map(|(_, _, result)| result.is_ok()).collect::<Vec<_>>();
+        let original = other_report.iter().map(|(_, _, result)| result.is_ok()).collect::<Vec<_>>();
+        assert_eq!(copied, original);
+        assert!(report.get(&Coproduct::inject(s1)).unwrap().is_ok());
+
+        // Rules that discharge one assumption reject it, since it has two
+        let s2 = prf.add_step(Justification(p("(a1 ∨ a2 ∨ c) → (a1 ∨ a2 ∨ b1 ∨ b2)"), RuleM::ImpIntro, vec![], vec![sub]));
+        assert_eq!(prf.verify_line(&Coproduct::inject(s2)), Err(crate::rules::ProofCheckError::IncorrectSubproofPremiseCount(sub, 2)));
+
+        let new_premises = prf.append_premises_from(&other);
+        assert_eq!(new_premises.len(), 2);
+        assert_eq!(prf.premises()[1..], new_premises[..]);
+        assert_eq!(new_premises.iter().map(|r| prf.lookup_premise(r)).collect::<Vec<_>>(), other.premises().iter().map(|r| other.lookup_premise(r)).collect::<Vec<_>>());
+    }
+
     #[test]
     fn test_xml3() {
         let xml = b"<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"no\"?>\n<bram>\n  <program>Aris</program>\n  <version>0.0.187</version>\n  <metadata>\n    <author>UNKNOWN</author>\n    <hash>aCDnd1IQS0y8QoTmgj7xeVpBG9o1A3m6tZWd0HXkwjg=</hash>\n  </metadata>\n  <proof id=\"0\">\n    <assumption linenum=\"0\">\n      <sen>p</sen>\n      <raw>p</raw>\n    </assumption>\n    <step linenum=\"1\">\n      <sen>p</sen>\n      <raw>p</raw>\n      <rule>REITERATION</rule>\n      <premise>0</premise>\n    </step>\n    <goal>\n      <sen/>\n      <raw/>\n    </goal>\n  </proof>\n</bram>\n";
         type P = PooledProof<HList![Expr]>;
-        let (prf, metadata) = proof_from_xml::<P, _>(&xml[..]).unwrap();
+        let (prf, metadata, _) = proof_from_xml::<P, _>(&xml[..]).unwrap();
         println!("{prf}");
         println!("{metadata:?}");
     }
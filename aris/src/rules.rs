@@ -63,6 +63,7 @@ use crate::proofs::PjRef;
 use crate::proofs::Proof;
 use crate::rewrite_rules::RewriteRule;
 
+use std::cell::Cell;
 use std::collections::BTreeSet;
 use std::collections::HashMap;
 use std::collections::HashSet;
@@ -79,8 +80,11 @@ use petgraph::algo::tarjan_scc;
 use petgraph::graphmap::DiGraphMap;
 use strum_macros::*;
 
+/// suggest searches for rules and dependencies that justify a step
+pub mod suggest;
+
 #[allow(missing_docs)]
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum PrepositionalInference {
     Reit,
     AndIntro,
@@ -93,6 +97,7 @@ pub enum PrepositionalInference {
     NotElim,
     ContradictionIntro,
     ContradictionElim,
+    TautologyIntro,
     BiconditionalIntro,
     BiconditionalElim,
     EquivalenceIntro,
@@ -100,7 +105,7 @@ pub enum PrepositionalInference {
 }
 
 #[allow(missing_docs)]
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum PredicateInference {
     ForallIntro,
     ForallElim,
@@ -109,7 +114,7 @@ pub enum PredicateInference {
 }
 
 #[allow(missing_docs)]
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum BooleanEquivalence {
     DeMorgan,
     Association,
@@ -127,7 +132,7 @@ pub enum BooleanEquivalence {
 }
 
 #[allow(missing_docs)]
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum ConditionalEquivalence {
     Complement,
     Identity,
@@ -145,7 +150,7 @@ pub enum ConditionalEquivalence {
 }
 
 #[allow(missing_docs)]
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum RedundantPrepositionalInference {
     ModusTollens,
     HypotheticalSyllogism,
@@ -155,14 +160,14 @@ pub enum RedundantPrepositionalInference {
 }
 
 #[allow(missing_docs)]
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum AutomationRelatedRules {
     Resolution,
     TautologicalConsequence,
 }
 
 #[allow(missing_docs)]
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum QuantifierEquivalence {
     QuantifierNegation,
     NullQuantification,
@@ -174,7 +179,7 @@ pub enum QuantifierEquivalence {
 }
 
 #[allow(missing_docs)]
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum Induction {
     Weak,
     Strong,
@@ -189,12 +194,12 @@ pub enum Induction {
 ///
 /// assert_eq!(EmptyRule.get_classifications().len(), 0);
 /// ```
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub struct EmptyRule;
 
 /// The RuleT instance for SharedChecks does checking that is common to all the rules;
 ///  it should always be the outermost constructor of the Rule type alias.
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub struct SharedChecks<T>(T);
 
 pub type Rule = SharedChecks<Coprod!(PrepositionalInference, PredicateInference, BooleanEquivalence, ConditionalEquivalence, RedundantPrepositionalInference, AutomationRelatedRules, QuantifierEquivalence, Induction, EmptyRule)>;
@@ -256,6 +261,7 @@ pub mod RuleM {
         [NotElim, "DOUBLENEGATION", (SharedChecks(Inl(PrepositionalInference::NotElim)))],
         [ContradictionIntro, "CONTRADICTION", (SharedChecks(Inl(PrepositionalInference::ContradictionIntro)))],
         [ContradictionElim, "PRINCIPLE_OF_EXPLOSION", (SharedChecks(Inl(PrepositionalInference::ContradictionElim)))],
+        [TautIntro, "TAUTOLOGY_INTRO", (SharedChecks(Inl(PrepositionalInference::TautologyIntro)))],
         [BiconditionalIntro, "BICONDITIONAL_INTRO", (SharedChecks(Inl(PrepositionalInference::BiconditionalIntro)))],
         [BiconditionalElim, "BICONDITIONAL_ELIM", (SharedChecks(Inl(PrepositionalInference::BiconditionalElim)))],
         [EquivalenceIntro, "EQUIVALENCE_INTRO", (SharedChecks(Inl(PrepositionalInference::EquivalenceIntro)))],
@@ -352,7 +358,8 @@ pub trait RuleT {
     /// num_subdeps is used by SharedChecks to ensure that the right number of subproof dependencies are provided, None indicates that no checking is done (e.g. for variadic rules)
     fn num_subdeps(&self) -> Option<usize>;
     /// check that expr is a valid conclusion of the rule given the corresponding lists of dependencies and subproof dependencies, returning Ok(()) on success, and an error to display in the GUI on failure
-    fn check<P: Proof>(self, p: &P, expr: Expr, deps: Vec<PjRef<P>>, sdeps: Vec<P::SubproofReference>) -> Result<(), ProofCheckError<PjRef<P>, P::SubproofReference>>;
+    /// ctx carries settings that affect how strictly the rule is applied (see `CheckContext`)
+    fn check<P: Proof>(self, p: &P, expr: Expr, deps: Vec<PjRef<P>>, sdeps: Vec<P::SubproofReference>, ctx: &CheckContext) -> Result<(), ProofCheckError<PjRef<P>, P::SubproofReference>>;
 }
 
 impl<A: RuleT, B: RuleT> RuleT for Coproduct<A, B> {
@@ -380,10 +387,10 @@ impl<A: RuleT, B: RuleT> RuleT for Coproduct<A, B> {
             Inr(x) => x.num_subdeps(),
         }
     }
-    fn check<P: Proof>(self, p: &P, expr: Expr, deps: Vec<PjRef<P>>, sdeps: Vec<P::SubproofReference>) -> Result<(), ProofCheckError<PjRef<P>, P::SubproofReference>> {
+    fn check<P: Proof>(self, p: &P, expr: Expr, deps: Vec<PjRef<P>>, sdeps: Vec<P::SubproofReference>, ctx: &CheckContext) -> Result<(), ProofCheckError<PjRef<P>, P::SubproofReference>> {
         match self {
-            Inl(x) => x.check(p, expr, deps, sdeps),
-            Inr(x) => x.check(p, expr, deps, sdeps),
+            Inl(x) => x.check(p, expr, deps, sdeps, ctx),
+            Inr(x) => x.check(p, expr, deps, sdeps, ctx),
         }
     }
 }
@@ -400,7 +407,7 @@ impl RuleT for frunk_core::coproduct::CNil {
     fn num_subdeps(&self) -> Option<usize> {
         match *self {}
     }
-    fn check<P: Proof>(self, _p: &P, _expr: Expr, _deps: Vec<PjRef<P>>, _sdeps: Vec<P::SubproofReference>) -> Result<(), ProofCheckError<PjRef<P>, P::SubproofReference>> {
+    fn check<P: Proof>(self, _p: &P, _expr: Expr, _deps: Vec<PjRef<P>>, _sdeps: Vec<P::SubproofReference>, _ctx: &CheckContext) -> Result<(), ProofCheckError<PjRef<P>, P::SubproofReference>> {
         match self {}
     }
 }
@@ -418,7 +425,7 @@ impl<T: RuleT> RuleT for SharedChecks<T> {
     fn num_subdeps(&self) -> Option<usize> {
         self.0.num_subdeps()
     }
-    fn check<P: Proof>(self, p: &P, expr: Expr, deps: Vec<PjRef<P>>, sdeps: Vec<P::SubproofReference>) -> Result<(), ProofCheckError<PjRef<P>, P::SubproofReference>> {
+    fn check<P: Proof>(self, p: &P, expr: Expr, deps: Vec<PjRef<P>>, sdeps: Vec<P::SubproofReference>, ctx: &CheckContext) -> Result<(), ProofCheckError<PjRef<P>, P::SubproofReference>> {
         use ProofCheckError::*;
         if let Some(directs) = self.num_deps() {
             if deps.len() != directs {
@@ -431,7 +438,7 @@ impl<T: RuleT> RuleT for SharedChecks<T> {
             }
         }
         // TODO: enforce that each subproof has exactly 1 premise
-        self.0.check(p, expr, deps, sdeps)
+        self.0.check(p, expr, deps, sdeps, ctx)
     }
 }
 
@@ -451,6 +458,31 @@ pub fn do_expressions_contradict<P: Proof>(prem1: &Expr, prem2: &Expr) -> Result
     )
 }
 
+fn contains_bicon_chain(e: &Expr) -> bool {
+    match e {
+        Expr::Contra | Expr::Taut | Expr::Var { .. } => false,
+        Expr::Apply { func, args } => contains_bicon_chain(func) || args.iter().any(contains_bicon_chain),
+        Expr::Not { operand } => contains_bicon_chain(operand),
+        Expr::Impl { left, right } => contains_bicon_chain(left) || contains_bicon_chain(right),
+        Expr::Assoc { op, exprs } => (*op == Op::Bicon && exprs.len() > 2) || exprs.iter().any(contains_bicon_chain),
+        Expr::Quant { body, .. } => contains_bicon_chain(body),
+    }
+}
+
+/// Rules compare biconditionals after `Expr::normalize_bicon_chains`, so `(A ↔ B) ↔ C` and `A ↔ B ↔ C` are interchangeable.
+/// The strict profile instead rejects a step whose expressions write biconditionals both ways.
+pub fn check_bicon_chain_mixing<P: Proof>(ctx: &CheckContext, exprs: &[Expr]) -> Result<(), ProofCheckError<PjRef<P>, P::SubproofReference>> {
+    if ctx.profile == CheckProfile::Lenient {
+        return Ok(());
+    }
+    let nested = exprs.iter().find(|e| (*e).clone().normalize_bicon_chains() != **e);
+    let chained = exprs.iter().find(|e| contains_bicon_chain(e));
+    match (nested, chained) {
+        (Some(nested), Some(chained)) => Err(ProofCheckError::Other(format!("{nested} and {chained} write biconditional chains differently, parenthesize them consistently."))),
+        _ => Ok(()),
+    }
+}
+
 impl RuleT for PrepositionalInference {
     fn get_name(&self) -> String {
         use PrepositionalInference::*;
@@ -466,6 +498,7 @@ impl RuleT for PrepositionalInference {
             NotElim => "¬ Elimination",
             ContradictionIntro => "⊥ Introduction",
             ContradictionElim => "⊥ Elimination",
+            TautologyIntro => "⊤ Introduction",
             BiconditionalIntro => "↔ Introduction",
             BiconditionalElim => "↔ Elimination",
             EquivalenceIntro => "≡ Introduction",
@@ -481,7 +514,7 @@ impl RuleT for PrepositionalInference {
             Reit => {
                 ret.insert(MiscInference);
             }
-            AndIntro | OrIntro | ImpIntro | NotIntro | ContradictionIntro | BiconditionalIntro | EquivalenceIntro => {
+            AndIntro | OrIntro | ImpIntro | NotIntro | ContradictionIntro | TautologyIntro | BiconditionalIntro | EquivalenceIntro => {
                 ret.insert(Introduction);
             }
             AndElim | OrElim | ImpElim | NotElim | ContradictionElim | BiconditionalElim | EquivalenceElim => {
@@ -495,7 +528,7 @@ impl RuleT for PrepositionalInference {
         match self {
             Reit | AndElim | OrIntro | OrElim | NotElim | ContradictionElim => Some(1),
             ContradictionIntro | ImpElim | BiconditionalElim | EquivalenceElim => Some(2),
-            NotIntro | ImpIntro => Some(0),
+            NotIntro | ImpIntro | TautologyIntro => Some(0),
             AndIntro | BiconditionalIntro | EquivalenceIntro => None, // AndIntro can have arbitrarily many conjuncts in one application
         }
     }
@@ -503,13 +536,13 @@ impl RuleT for PrepositionalInference {
         use PrepositionalInference::*;
         match self {
             NotIntro | ImpIntro => Some(1),
-            Reit | AndElim | OrIntro | NotElim | ContradictionElim | ContradictionIntro | ImpElim | AndIntro | BiconditionalElim | EquivalenceElim => Some(0),
+            Reit | AndElim | OrIntro | NotElim | ContradictionElim | TautologyIntro | ContradictionIntro | ImpElim | AndIntro | BiconditionalElim | EquivalenceElim => Some(0),
             OrElim | BiconditionalIntro | EquivalenceIntro => None,
         }
     }
 
     #[allow(clippy::redundant_closure)]
-    fn check<P: Proof>(self, p: &P, conclusion: Expr, deps: Vec<PjRef<P>>, sdeps: Vec<P::SubproofReference>) -> Result<(), ProofCheckError<PjRef<P>, P::SubproofReference>> {
+    fn check<P: Proof>(self, p: &P, conclusion: Expr, deps: Vec<PjRef<P>>, sdeps: Vec<P::SubproofReference>, ctx: &CheckContext) -> Result<(), ProofCheckError<PjRef<P>, P::SubproofReference>> {
         use PrepositionalInference::*;
         use ProofCheckError::*;
 
@@ -545,13 +578,39 @@ impl RuleT for PrepositionalInference {
             AndElim => {
                 let prem = p.lookup_expr_or_die(&deps[0])?;
                 if let Expr::Assoc { op: Op::And, ref exprs } = prem {
-                    for e in exprs.iter() {
-                        if e == &conclusion {
-                            return Ok(());
+                    if exprs.iter().any(|e| e == &conclusion) {
+                        return Ok(());
+                    }
+                    // otherwise the conclusion has to be a conjunction of some sub-multiset of the premise's conjuncts
+                    let conc_exprs = match conclusion {
+                        Expr::Assoc { op: Op::And, ref exprs } => exprs.clone(),
+                        _ => vec![conclusion.clone()],
+                    };
+                    match ctx.profile {
+                        CheckProfile::Lenient => {
+                            let normalize = |e: &Expr| e.clone().sort_commutative_ops();
+                            let mut available = exprs.iter().map(normalize).collect::<Vec<_>>();
+                            for e in conc_exprs.iter() {
+                                match available.iter().position(|x| *x == normalize(e)) {
+                                    Some(i) => {
+                                        available.remove(i);
+                                    }
+                                    None => return Err(DoesNotOccur(e.clone(), prem.clone())),
+                                }
+                            }
+                            Ok(())
+                        }
+                        CheckProfile::Strict => {
+                            if let Some(e) = conc_exprs.iter().find(|e| !exprs.contains(e)) {
+                                return Err(DoesNotOccur(e.clone(), prem.clone()));
+                            }
+                            if exprs.windows(conc_exprs.len()).any(|w| w == &conc_exprs[..]) {
+                                Ok(())
+                            } else {
+                                Err(Other(format!("The conjuncts of {conclusion} must appear next to each other and in the same order as in {prem}.")))
+                            }
                         }
                     }
-                    // TODO: allow `A /\ B /\ C |- C /\ A /\ C`, etc
-                    Err(DoesNotOccur(conclusion, prem.clone()))
                 } else {
                     Err(DepDoesNotExist(Expr::assocplaceholder(Op::And), true))
                 }
@@ -571,8 +630,8 @@ impl RuleT for PrepositionalInference {
                 let prem = p.lookup_expr_or_die(&deps[0])?;
                 if let Expr::Assoc { op: Op::Or, ref exprs } = prem {
                     let sproofs = sdeps.into_iter().map(|r| p.lookup_subproof_or_die(&r)).collect::<Result<Vec<_>, _>>()?;
-                    // if not all the subproofs have lines whose expressions contain the conclusion, return an error
-                    let all_sproofs_have_conclusion = sproofs.iter().all(|sproof| sproof.lines().into_iter().filter_map(|x| x.get::<P::JustificationReference, _>().and_then(|y| p.lookup_step(y)).map(|y| y.0)).any(|c| c == conclusion));
+                    // if not all the subproofs conclude with the conclusion, return an error
+                    let all_sproofs_have_conclusion = sproofs.iter().all(|sproof| sproof.conclusion().as_ref() == Some(&conclusion));
                     if !all_sproofs_have_conclusion {
                         return Err(DepDoesNotExist(conclusion, false));
                     }
@@ -587,14 +646,12 @@ impl RuleT for PrepositionalInference {
             ImpIntro => {
                 let sproof = p.lookup_subproof_or_die(&sdeps[0])?;
                 // TODO: allow generalized premises
-                assert_eq!(sproof.premises().len(), 1);
+                let prem = sole_premise::<P>(&sproof, &sdeps[0])?;
                 if let Expr::Impl { ref left, ref right } = conclusion {
-                    let prem = sproof.premises().into_iter().map(|r| p.lookup_premise_or_die(&r)).collect::<Result<Vec<Expr>, _>>()?;
-                    if **left != prem[0] {
-                        return Err(DoesNotOccur(*left.clone(), prem[0].clone()));
+                    if **left != prem {
+                        return Err(DoesNotOccur(*left.clone(), prem));
                     }
-                    let conc = sproof.lines().into_iter().filter_map(|x| x.get::<P::JustificationReference, _>().cloned()).map(|r| p.lookup_expr_or_die(&Coproduct::inject(r))).collect::<Result<Vec<Expr>, _>>()?;
-                    if !conc.iter().any(|c| c == &**right) {
+                    if sproof.conclusion().as_ref() != Some(&**right) {
                         return Err(DepDoesNotExist(*right.clone(), false));
                     }
                     Ok(())
@@ -634,14 +691,12 @@ impl RuleT for PrepositionalInference {
             NotIntro => {
                 let sproof = p.lookup_subproof_or_die(&sdeps[0])?;
                 // TODO: allow generalized premises
-                assert_eq!(sproof.premises().len(), 1);
+                let prem = sole_premise::<P>(&sproof, &sdeps[0])?;
                 if let Expr::Not { ref operand } = conclusion {
-                    let prem = sproof.premises().into_iter().map(|r| p.lookup_premise_or_die(&r)).collect::<Result<Vec<Expr>, _>>()?;
-                    if **operand != prem[0] {
-                        return Err(DoesNotOccur(*operand.clone(), prem[0].clone()));
+                    if **operand != prem {
+                        return Err(DoesNotOccur(*operand.clone(), prem));
                     }
-                    let conc = sproof.lines().into_iter().filter_map(|x| x.get::<P::JustificationReference, _>().cloned()).map(|r| p.lookup_expr_or_die(&Coproduct::inject(r))).collect::<Result<Vec<Expr>, _>>()?;
-                    if !conc.iter().any(|x| *x == Expr::Contra) {
+                    if sproof.conclusion() != Some(Expr::Contra) {
                         return Err(DepDoesNotExist(Expr::Contra, false));
                     }
                     Ok(())
@@ -675,15 +730,26 @@ impl RuleT for PrepositionalInference {
             }
             ContradictionElim => {
                 let prem = p.lookup_expr_or_die(&deps[0])?;
-                if let Expr::Contra = prem {
+                match prem {
+                    Expr::Contra => Ok(()),
+                    Expr::Assoc { op: Op::And, ref exprs } if exprs.iter().any(|e| exprs.contains(&!e.clone())) => Err(Other(format!("{prem} is contradictory, but ⊥ Elimination needs ⊥ itself as its dependency. Derive ⊥ from it with ⊥ Introduction first."))),
+                    _ => Err(DepOfWrongForm(prem, Expr::Contra)),
+                }
+            }
+            TautologyIntro => {
+                if let Expr::Taut = conclusion {
                     Ok(())
                 } else {
-                    Err(DepOfWrongForm(prem, Expr::Contra))
+                    Err(ConclusionOfWrongForm(Expr::Taut))
                 }
             }
             BiconditionalElim => {
                 let prem1 = p.lookup_expr_or_die(&deps[0])?;
                 let prem2 = p.lookup_expr_or_die(&deps[1])?;
+                check_bicon_chain_mixing::<P>(ctx, &[prem1.clone(), prem2.clone(), conclusion.clone()])?;
+                let prem1 = prem1.normalize_bicon_chains();
+                let prem2 = prem2.normalize_bicon_chains();
+                let conclusion = conclusion.normalize_bicon_chains();
                 either_order(
                     &prem1,
                     &prem2,
@@ -724,7 +790,17 @@ impl RuleT for PrepositionalInference {
                             }
                         }
                         let prems = deps.into_iter().map(|r| p.lookup_expr_or_die(&r)).collect::<Result<Vec<Expr>, _>>()?;
-                        let sproofs = sdeps.into_iter().map(|r| p.lookup_subproof_or_die(&r)).collect::<Result<Vec<_>, _>>()?;
+                        let sproofs = sdeps.iter().map(|r| p.lookup_subproof_or_die(r)).collect::<Result<Vec<_>, _>>()?;
+                        let mut seen = exprs.clone();
+                        seen.extend(prems.iter().cloned());
+                        for sproof in sproofs.iter() {
+                            seen.extend(sproof.premises().iter().filter_map(|r| sproof.lookup_premise(r)));
+                            seen.extend(sproof.conclusion());
+                        }
+                        check_bicon_chain_mixing::<P>(ctx, &seen)?;
+                        // a premise's own chain is read as pairwise equivalences, so flattening `(A ↔ B) ↔ C` into one
+                        // would equate A and B; only the operands (the graph's nodes) are normalized, as in the conclusion
+                        let exprs = exprs.iter().cloned().map(Expr::normalize_bicon_chains).collect::<Vec<_>>();
                         let mut slab = HashMap::new();
                         let mut counter = 0;
                         let next: &mut dyn FnMut() -> _ = &mut || {
@@ -735,6 +811,7 @@ impl RuleT for PrepositionalInference {
                         for prem in prems.iter() {
                             match prem {
                                 Expr::Assoc { op, ref exprs } if &oper == op => {
+                                    let exprs = exprs.iter().cloned().map(Expr::normalize_bicon_chains).collect::<Vec<_>>();
                                     for e1 in exprs.iter() {
                                         for e2 in exprs.iter() {
                                             slab.entry(e1.clone()).or_insert_with(|| next());
@@ -744,22 +821,23 @@ impl RuleT for PrepositionalInference {
                                     }
                                 }
                                 Expr::Impl { ref left, ref right } => {
-                                    slab.entry(*left.clone()).or_insert_with(|| next());
-                                    slab.entry(*right.clone()).or_insert_with(|| next());
-                                    g.add_edge(slab[left], slab[right], ());
+                                    let left = (**left).clone().normalize_bicon_chains();
+                                    let right = (**right).clone().normalize_bicon_chains();
+                                    slab.entry(left.clone()).or_insert_with(|| next());
+                                    slab.entry(right.clone()).or_insert_with(|| next());
+                                    g.add_edge(slab[&left], slab[&right], ());
                                 }
                                 _ => return Err(OneOf(btreeset![DepOfWrongForm(prem.clone(), Expr::assocplaceholder(oper)), DepOfWrongForm(prem.clone(), Expr::impl_place_holder()),])),
                             }
                         }
-                        for sproof in sproofs.iter() {
-                            assert_eq!(sproof.premises().len(), 1);
-                            let prem = sproof.lookup_premise_or_die(&sproof.premises()[0])?;
+                        for (sproof, sr) in sproofs.iter().zip(sdeps.iter()) {
+                            let prem = sole_premise::<P>(sproof, sr)?.normalize_bicon_chains();
                             slab.entry(prem.clone()).or_insert_with(|| next());
-                            for r in sproof.exprs() {
-                                let e = sproof.lookup_expr_or_die(&r)?.clone();
-                                slab.entry(e.clone()).or_insert_with(|| next());
-                                g.add_edge(slab[&prem], slab[&e], ());
-                            }
+                            // only the subproof's last line follows from its premise, not every line in it,
+                            // and a subproof with no steps ends with its premise
+                            let e = sproof.conclusion().map_or_else(|| prem.clone(), Expr::normalize_bicon_chains);
+                            slab.entry(e.clone()).or_insert_with(|| next());
+                            g.add_edge(slab[&prem], slab[&e], ());
                         }
                         let rslab = slab.into_iter().map(|(k, v)| (v, k)).collect::<HashMap<_, _>>();
                         let sccs = tarjan_scc(&g).iter().map(|x| x.iter().map(|i| rslab[i].clone()).collect()).collect::<Vec<HashSet<_>>>();
@@ -851,7 +929,7 @@ impl RuleT for PredicateInference {
             ForallIntro | ExistsElim => Some(1),
         }
     }
-    fn check<P: Proof>(self, p: &P, conclusion: Expr, deps: Vec<PjRef<P>>, sdeps: Vec<P::SubproofReference>) -> Result<(), ProofCheckError<PjRef<P>, P::SubproofReference>> {
+    fn check<P: Proof>(self, p: &P, conclusion: Expr, deps: Vec<PjRef<P>>, sdeps: Vec<P::SubproofReference>, _ctx: &CheckContext) -> Result<(), ProofCheckError<PjRef<P>, P::SubproofReference>> {
         use PredicateInference::*;
         use ProofCheckError::*;
         fn unifies_wrt_var<P: Proof>(e1: &Expr, e2: &Expr, var: &str) -> Result<Expr, ProofCheckError<PjRef<P>, P::SubproofReference>> {
@@ -888,7 +966,8 @@ impl RuleT for PredicateInference {
             ForallIntro => {
                 let sproof = p.lookup_subproof_or_die(&sdeps[0])?;
                 if let Expr::Quant { kind: QuantKind::Forall, name, body } = &conclusion {
-                    for (r, expr) in sproof.exprs().into_iter().map(|r| sproof.lookup_expr_or_die(&r).map(|e| (r, e))).collect::<Result<Vec<_>, _>>()? {
+                    if let (Some(r), Some(expr)) = (sproof.conclusion_ref(), sproof.conclusion()) {
+                        let r = Coproduct::inject(r);
                         if let Ok(Expr::Var { name: constant }) = unifies_wrt_var::<P>(body, &expr, name) {
                             println!("ForallIntro constant {constant:?}");
                             if let Some(dangling) = generalizable_variable_counterexample(&sproof, r.clone(), &constant) {
@@ -906,24 +985,49 @@ impl RuleT for PredicateInference {
                             }
                         }
                     }
-                    Err(Other(format!("Couldn't find a subproof line that unifies with the conclusion ({conclusion}).")))
+                    Err(Other(format!("The subproof's last line doesn't unify with the conclusion ({conclusion}).")))
                 } else {
                     Err(ConclusionOfWrongForm(Expr::quant_placeholder(QuantKind::Forall)))
                 }
             }
             ForallElim => {
                 let prem = p.lookup_expr_or_die(&deps[0])?;
-                if let Expr::Quant { kind: QuantKind::Forall, ref name, ref body } = prem {
-                    unifies_wrt_var::<P>(body, &conclusion, name)?;
-                    Ok(())
-                } else {
-                    Err(DepOfWrongForm(prem, Expr::quant_placeholder(QuantKind::Forall)))
+                // any prefix of the universal quantifiers may be instantiated at once, e.g. `forall x forall y P(x, y)` |- `P(a, b)`
+                let mut prefixes = vec![];
+                let mut vars = vec![];
+                let mut e = &prem;
+                while let Expr::Quant { kind: QuantKind::Forall, name, body } = e {
+                    vars.push(name.clone());
+                    prefixes.push((vars.clone(), &**body));
+                    e = &**body;
+                }
+                if prefixes.is_empty() {
+                    return Err(DepOfWrongForm(prem.clone(), Expr::quant_placeholder(QuantKind::Forall)));
+                }
+                // the quantifiers left over in the conclusion tell which prefix was most likely meant, so report that attempt's error
+                let mut remaining = 0;
+                let mut e = &conclusion;
+                while let Expr::Quant { kind: QuantKind::Forall, body, .. } = e {
+                    remaining += 1;
+                    e = &**body;
                 }
+                let likely = prefixes.len().saturating_sub(remaining).max(1) - 1;
+                let mut error = None;
+                for (i, (vars, body)) in prefixes.iter().enumerate() {
+                    match crate::expr::match_instantiation(body, &conclusion, vars) {
+                        Ok(_) => return Ok(()),
+                        Err(msg) if i == likely => error = Some(msg),
+                        Err(_) => {}
+                    }
+                }
+                Err(Other(error.unwrap_or_else(|| format!("{conclusion} is not an instance of {prem}."))))
             }
             ExistsIntro => {
                 if let Expr::Quant { kind: QuantKind::Exists, ref name, ref body } = conclusion {
                     let prem = p.lookup_expr_or_die(&deps[0])?;
-                    unifies_wrt_var::<P>(body, &prem, name)?;
+                    // the occurrences of `name` in the body pick out which occurrences of some term in the premise are generalized,
+                    // e.g. from `P(c, c)`, any of `exists x P(x, c)`, `exists x P(c, x)` and `exists x P(x, x)` follow
+                    crate::expr::match_instantiation(body, &prem, &[name.clone()]).map_err(Other)?;
                     Ok(())
                 } else {
                     Err(ConclusionOfWrongForm(Expr::quant_placeholder(QuantKind::Exists)))
@@ -939,7 +1043,7 @@ impl RuleT for PredicateInference {
                 4 | psi, ExistElim, 2-3
 
                 - the body of the existential in dep 1 must unify with the premise of the subproof at 2, this infers the skolem constant
-                - the conclusion 4 must occur in some line of the subproof at 2-3 (in this, 3)
+                - the conclusion 4 must be the conclusion of the subproof at 2-3 (in this, 3)
                 - the skolem constant must not occur in the transitive dependencies of the conclusion (generalizable variable conterexample check)
                 - the skolem constant must not escape to the conclusion (freevars check)
                 */
@@ -962,31 +1066,40 @@ impl RuleT for PredicateInference {
                         return Err(DepOfWrongForm(prem, Expr::quant_placeholder(QuantKind::Exists)));
                     }
                 };
-                for (r, expr) in sproof.exprs().into_iter().map(|r| sproof.lookup_expr_or_die(&r).map(|e| (r, e))).collect::<Result<Vec<_>, _>>()? {
-                    if expr == conclusion {
-                        println!("ExistsElim conclusion {conclusion:?} skolemname {skolemname:?}");
-                        if let Some(dangling) = generalizable_variable_counterexample(&sproof, r, &skolemname) {
+                match (sproof.conclusion_ref(), sproof.conclusion()) {
+                    (Some(r), Some(expr)) if expr == conclusion => {
+                        if let Some(dangling) = generalizable_variable_counterexample(&sproof, Coproduct::inject(r), &skolemname) {
                             return Err(Other(format!("The skolem constant {skolemname} occurs in dependency {dangling} that's outside the subproof.")));
                         }
                         if crate::expr::free_vars(&conclusion).contains(&skolemname) {
                             return Err(Other(format!("The skolem constant {skolemname} escapes to the conclusion {conclusion}.")));
                         }
-                        return Ok(());
+                        Ok(())
                     }
+                    _ => Err(Other(format!("The subproof doesn't conclude with the conclusion ({conclusion})."))),
                 }
-                Err(Other(format!("Couldn't find a subproof line equal to the conclusion ({conclusion}).")))
             }
         }
     }
 }
 
+/// The premise of the subproof `sr`, for rules that discharge the one
+/// assumption a subproof makes
+fn sole_premise<P: Proof>(sproof: &P::Subproof, sr: &P::SubproofReference) -> Result<Expr, ProofCheckError<PjRef<P>, P::SubproofReference>> {
+    let premises = sproof.premises();
+    if premises.len() != 1 {
+        return Err(ProofCheckError::IncorrectSubproofPremiseCount(sr.clone(), premises.len()));
+    }
+    sproof.lookup_premise_or_die(&premises[0])
+}
+
 fn check_by_normalize_first_expr<F, P: Proof>(p: &P, deps: Vec<PjRef<P>>, conclusion: Expr, commutative: bool, normalize_fn: F) -> Result<(), ProofCheckError<PjRef<P>, P::SubproofReference>>
 where
     F: Fn(Expr) -> Expr,
 {
     let premise = p.lookup_expr_or_die(&deps[0])?;
-    let mut p = normalize_fn(premise);
-    let mut q = normalize_fn(conclusion);
+    let mut p = normalize_fn(premise).normalize_bicon_chains();
+    let mut q = normalize_fn(conclusion).normalize_bicon_chains();
     if commutative {
         p = p.sort_commutative_ops();
         q = q.sort_commutative_ops();
@@ -1004,8 +1117,9 @@ fn check_by_rewrite_rule_confl<P: Proof>(p: &P, deps: Vec<PjRef<P>>, conclusion:
 
 fn check_by_rewrite_rule_non_confl<P: Proof>(p: &P, deps: Vec<PjRef<P>>, conclusion: Expr, commutative: bool, rule: &RewriteRule) -> Result<(), ProofCheckError<PjRef<P>, P::SubproofReference>> {
     let premise = p.lookup_expr_or_die(&deps[0])?;
-    let premise_set = rule.reduce_set(premise.clone());
-    let conclusion_set = rule.reduce_set(conclusion.clone());
+    let normalize_chains = |set: HashSet<Expr>| set.into_iter().map(Expr::normalize_bicon_chains).collect::<HashSet<_>>();
+    let premise_set = normalize_chains(rule.reduce_set(premise.clone()));
+    let conclusion_set = normalize_chains(rule.reduce_set(conclusion.clone()));
     let (premise_set, conclusion_set) = if commutative {
         let sort_ops = |set: HashSet<Expr>| set.into_iter().map(Expr::sort_commutative_ops).collect();
         (sort_ops(premise_set), sort_ops(conclusion_set))
@@ -1050,14 +1164,14 @@ impl RuleT for BooleanEquivalence {
     fn num_subdeps(&self) -> Option<usize> {
         Some(0)
     }
-    fn check<P: Proof>(self, p: &P, conclusion: Expr, deps: Vec<PjRef<P>>, _sdeps: Vec<P::SubproofReference>) -> Result<(), ProofCheckError<PjRef<P>, P::SubproofReference>> {
+    fn check<P: Proof>(self, p: &P, conclusion: Expr, deps: Vec<PjRef<P>>, _sdeps: Vec<P::SubproofReference>, _ctx: &CheckContext) -> Result<(), ProofCheckError<PjRef<P>, P::SubproofReference>> {
         use BooleanEquivalence::*;
         match self {
             DeMorgan => check_by_normalize_first_expr(p, deps, conclusion, false, |e| e.normalize_demorgans()),
             Association => check_by_normalize_first_expr(p, deps, conclusion, false, |e| e.combine_associative_ops()),
             Commutation => check_by_normalize_first_expr(p, deps, conclusion, false, |e| e.sort_commutative_ops()),
             Idempotence => check_by_normalize_first_expr(p, deps, conclusion, false, |e| e.normalize_idempotence()),
-            DoubleNegation => check_by_rewrite_rule_confl(p, deps, conclusion, false, &equivs::DOUBLE_NEGATION),
+            DoubleNegation => check_by_normalize_first_expr(p, deps, conclusion, false, |e| e.normalize_double_negation()),
             // Distribution and Reduction have outputs containing binops that need commutative sorting
             // because we can't expect people to know the specific order of outputs that our definition
             // of the rules uses
@@ -1102,7 +1216,7 @@ impl RuleT for ConditionalEquivalence {
     fn num_subdeps(&self) -> Option<usize> {
         Some(0)
     }
-    fn check<P: Proof>(self, p: &P, conclusion: Expr, deps: Vec<PjRef<P>>, _sdeps: Vec<P::SubproofReference>) -> Result<(), ProofCheckError<PjRef<P>, P::SubproofReference>> {
+    fn check<P: Proof>(self, p: &P, conclusion: Expr, deps: Vec<PjRef<P>>, _sdeps: Vec<P::SubproofReference>, _ctx: &CheckContext) -> Result<(), ProofCheckError<PjRef<P>, P::SubproofReference>> {
         use ConditionalEquivalence::*;
         match self {
             Complement => check_by_rewrite_rule_confl(p, deps, conclusion, false, &equivs::CONDITIONAL_COMPLEMENT),
@@ -1148,7 +1262,7 @@ impl RuleT for RedundantPrepositionalInference {
     fn num_subdeps(&self) -> Option<usize> {
         Some(0)
     }
-    fn check<P: Proof>(self, proof: &P, conclusion: Expr, deps: Vec<PjRef<P>>, sdeps: Vec<P::SubproofReference>) -> Result<(), ProofCheckError<PjRef<P>, P::SubproofReference>> {
+    fn check<P: Proof>(self, proof: &P, conclusion: Expr, deps: Vec<PjRef<P>>, sdeps: Vec<P::SubproofReference>, _ctx: &CheckContext) -> Result<(), ProofCheckError<PjRef<P>, P::SubproofReference>> {
         use ProofCheckError::*;
         use RedundantPrepositionalInference::*;
 
@@ -1347,7 +1461,7 @@ impl RuleT for AutomationRelatedRules {
             AutomationRelatedRules::Resolution | AutomationRelatedRules::TautologicalConsequence => Some(0),
         }
     }
-    fn check<P: Proof>(self, p: &P, conclusion: Expr, deps: Vec<PjRef<P>>, _sdeps: Vec<P::SubproofReference>) -> Result<(), ProofCheckError<PjRef<P>, P::SubproofReference>> {
+    fn check<P: Proof>(self, p: &P, conclusion: Expr, deps: Vec<PjRef<P>>, _sdeps: Vec<P::SubproofReference>, ctx: &CheckContext) -> Result<(), ProofCheckError<PjRef<P>, P::SubproofReference>> {
         match self {
             AutomationRelatedRules::Resolution => {
                 let prem0 = p.lookup_expr_or_die(&deps[0])?;
@@ -1356,6 +1470,7 @@ impl RuleT for AutomationRelatedRules {
                 premise_disjuncts.extend(prem0.disjuncts());
                 premise_disjuncts.extend(prem1.disjuncts());
                 let conclusion_disjuncts = conclusion.disjuncts().into_iter().collect::<HashSet<_>>();
+                ctx.budget.charge::<PjRef<P>, P::SubproofReference>((premise_disjuncts.len() + conclusion_disjuncts.len()) as u64)?;
                 let mut remainder = premise_disjuncts.difference(&conclusion_disjuncts).cloned().collect::<Vec<Expr>>();
                 //println!("resolution remainder of {:?} and {:?} is {:?}", premise_disjuncts, conclusion_disjuncts, remainder);
                 remainder.sort();
@@ -1375,17 +1490,17 @@ impl RuleT for AutomationRelatedRules {
                 // Closure for making CNF conversion errors
                 let cnf_error = || ProofCheckError::Other("Failed converting to CNF; the propositions for this rule should not use quantifiers, arithmetic, or application.".to_string());
 
-                // Closure to convert expression into CNF and change to result type
-                let into_cnf = |expr: Expr| expr.into_cnf().ok_or_else(cnf_error);
-
                 // Convert the premises to a single expression by AND-ing them together
                 let premises = deps.into_iter().map(|dep| p.lookup_expr_or_die(&dep)).collect::<Result<Vec<Expr>, _>>()?;
                 let premise = Expr::Assoc { op: Op::And, exprs: premises };
 
                 // Create `varisat` formula of `~(P -> Q)`. If this is
-                // unsatisfiable, then we've proven `P -> Q`.
+                // unsatisfiable, then we've proven `P -> Q`. Distributing ORs
+                // can make exponentially many clauses, so each one is charged
+                // for as it's made.
                 let sat = !(Expr::implies(premise, conclusion));
-                let (sat, vars) = into_cnf(sat)?.to_varisat();
+                let cnf = sat.into_nnf().ok_or_else(cnf_error)?.try_into_cnf(&mut |clauses| ctx.budget.charge::<PjRef<P>, P::SubproofReference>(clauses))?;
+                let (sat, vars) = cnf.to_varisat();
                 let mut solver = varisat::Solver::new();
                 solver.add_formula(&sat);
 
@@ -1397,19 +1512,17 @@ impl RuleT for AutomationRelatedRules {
                     Some(model) => {
                         // Satisfiable, so `P -> Q` is false. The counterexample is `model`.
 
-                        // Convert model to human-readable variable assignments
-                        // for an error message
+                        // Convert model to variable assignments for an error
+                        // message
                         let model = model
                             .into_iter()
                             .map(|lit| {
                                 let name = vars.get(&lit.var()).expect("taut con vars map error");
-                                let val = if lit.is_positive() { 'T' } else { 'F' };
-                                format!("{name} = {val}")
+                                (name.to_string(), lit.is_positive())
                             })
-                            .collect::<Vec<String>>()
-                            .join(", ");
+                            .collect::<Vec<(String, bool)>>();
 
-                        Err(ProofCheckError::Other(format!("Not true by tautological consequence; Counterexample: {model}")))
+                        Err(ProofCheckError::Counterexample(model))
                     }
                     None => Ok(()),
                 }
@@ -1441,7 +1554,7 @@ impl RuleT for QuantifierEquivalence {
     fn num_subdeps(&self) -> Option<usize> {
         Some(0)
     }
-    fn check<P: Proof>(self, p: &P, conclusion: Expr, deps: Vec<PjRef<P>>, _sdeps: Vec<P::SubproofReference>) -> Result<(), ProofCheckError<PjRef<P>, P::SubproofReference>> {
+    fn check<P: Proof>(self, p: &P, conclusion: Expr, deps: Vec<PjRef<P>>, _sdeps: Vec<P::SubproofReference>, _ctx: &CheckContext) -> Result<(), ProofCheckError<PjRef<P>, P::SubproofReference>> {
         use QuantifierEquivalence::*;
         match self {
             QuantifierNegation => check_by_normalize_first_expr(p, deps, conclusion, false, Expr::negate_quantifiers),
@@ -1479,7 +1592,7 @@ impl RuleT for Induction {
         Some(0)
     }
 
-    fn check<P: Proof>(self, p: &P, conclusion: Expr, deps: Vec<PjRef<P>>, _sdeps: Vec<P::SubproofReference>) -> Result<(), ProofCheckError<PjRef<P>, P::SubproofReference>> {
+    fn check<P: Proof>(self, p: &P, conclusion: Expr, deps: Vec<PjRef<P>>, _sdeps: Vec<P::SubproofReference>, _ctx: &CheckContext) -> Result<(), ProofCheckError<PjRef<P>, P::SubproofReference>> {
         // Check conclusion
         let (quantified_var, property) = match &conclusion {
             Expr::Quant { kind: QuantKind::Forall, name, body } => (name, &**body),
@@ -1577,7 +1690,7 @@ impl RuleT for EmptyRule {
     fn num_subdeps(&self) -> Option<usize> {
         None
     }
-    fn check<P: Proof>(self, _: &P, _: Expr, _: Vec<PjRef<P>>, _: Vec<P::SubproofReference>) -> Result<(), ProofCheckError<PjRef<P>, P::SubproofReference>> {
+    fn check<P: Proof>(self, _: &P, _: Expr, _: Vec<PjRef<P>>, _: Vec<P::SubproofReference>, _ctx: &CheckContext) -> Result<(), ProofCheckError<PjRef<P>, P::SubproofReference>> {
         Err(ProofCheckError::Other("No rule selected".to_string()))
     }
 }
@@ -1672,8 +1785,62 @@ where
     any_order(deps, check_func, fallthrough_error)
 }
 
+/// How forgiving rule checks are about incidental differences in the shape of a step (e.g. the order of conjuncts)
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub enum CheckProfile {
+    /// Accept steps that are correct up to commutativity and similar bookkeeping
+    #[default]
+    Lenient,
+    /// Require steps to match the textbook form of each rule exactly
+    Strict,
+}
+
+/// A limit on how much work expensive rule checks (e.g. `TautologicalConsequence`) may do, so that checking a step can't hang the UI
+///
+/// Cheap rules never touch it, expensive ones `charge` it for each piece of work (e.g. each clause of a CNF conversion) as they go.
+#[derive(Clone, Debug)]
+pub struct Budget {
+    pub steps: Cell<u64>,
+}
+
+impl Budget {
+    pub fn new(steps: u64) -> Self {
+        Budget { steps: Cell::new(steps) }
+    }
+    /// A budget that's effectively never exhausted
+    pub fn unlimited() -> Self {
+        Budget::new(u64::MAX)
+    }
+    /// Spend `cost` steps, or return `ProofCheckError::BudgetExceeded` (emptying the budget) if fewer than that remain
+    pub fn charge<R, S>(&self, cost: u64) -> Result<(), ProofCheckError<R, S>> {
+        match self.steps.get().checked_sub(cost) {
+            Some(remaining) => {
+                self.steps.set(remaining);
+                Ok(())
+            }
+            None => {
+                self.steps.set(0);
+                Err(ProofCheckError::BudgetExceeded)
+            }
+        }
+    }
+}
+
+impl Default for Budget {
+    fn default() -> Self {
+        Budget::unlimited()
+    }
+}
+
+/// Settings threaded through `RuleT::check` by `Proof::verify_line_with`
+#[derive(Clone, Debug, Default)]
+pub struct CheckContext {
+    pub profile: CheckProfile,
+    pub budget: Budget,
+}
+
 /// Errors that can occur when checking a proof
-#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum ProofCheckError<R, S> {
     /// A line reference lookup failed
     LineDoesNotExist(R),
@@ -1681,10 +1848,16 @@ pub enum ProofCheckError<R, S> {
     SubproofDoesNotExist(S),
     /// The proof is malformed in a way that permits circular references
     ReferencesLaterLine(R, Coproduct<R, Coproduct<S, frunk_core::coproduct::CNil>>),
+    /// The step cites `dep`, which comes after it
+    ForwardReference { dep: Coproduct<R, Coproduct<S, frunk_core::coproduct::CNil>> },
+    /// The step cites itself
+    SelfReference,
     /// The wrong number of line dependencies were provided for a rule
     IncorrectDepCount(Vec<R>, usize),
     /// The wrong number of subproof dependencies were provided for a rule
     IncorrectSubDepCount(Vec<S>, usize),
+    /// The subproof `.0` has `.1` premises, but the rule needs it to have exactly one
+    IncorrectSubproofPremiseCount(S, usize),
     /// A dependency `.0` was of the wrong form, and a placeholder `.1` was expected
     DepOfWrongForm(Expr, Expr),
     /// The conclusion of a rule was different from what was expected
@@ -1695,19 +1868,60 @@ pub enum ProofCheckError<R, S> {
     DepDoesNotExist(Expr, bool),
     /// Multiple errors apply
     OneOf(BTreeSet<ProofCheckError<R, S>>),
+    /// Checking the rule would take more work than the `Budget` allowed
+    BudgetExceeded,
+    /// The conclusion doesn't follow from the dependencies, since this
+    /// assignment of truth values to variables makes the dependencies true and
+    /// the conclusion false
+    Counterexample(Vec<(String, bool)>),
     /// Escape hatch for custom errors
     Other(String),
 }
 
-impl<R: std::fmt::Debug, S: std::fmt::Debug> std::fmt::Display for ProofCheckError<R, S> {
+impl<R, S> ProofCheckError<R, S> {
+    /// The message to show the user, naming lines with `line` and subproofs
+    /// with `subproof`, such as `LineNames::line` and `LineNames::subproof`
+    pub fn describe(&self, line: impl Fn(&R) -> String, subproof: impl Fn(&S) -> String) -> String {
+        self.describe_with(&|r| Some(line(r)), &|s| Some(subproof(s)))
+    }
+
+    /// Like `describe`, leaving out the names of lines and subproofs without one
+    fn describe_with(&self, line: &dyn Fn(&R) -> Option<String>, subproof: &dyn Fn(&S) -> Option<String>) -> String {
+        use ProofCheckError::*;
+        let dep = |dep: &Coproduct<R, Coproduct<S, frunk_core::coproduct::CNil>>| match dep {
+            Inl(r) => line(r),
+            Inr(Inl(s)) => subproof(s),
+            Inr(Inr(void)) => match *void {},
+        };
+        let named = |name: Option<String>| name.map(|name| format!(" ({name})")).unwrap_or_default();
+        match self {
+            // These can't be named, since they aren't in the proof
+            LineDoesNotExist(_) => "The referenced line does not exist.".into(),
+            SubproofDoesNotExist(_) => "The referenced subproof does not exist.".into(),
+            ReferencesLaterLine(r, d) => format!("The dependency{} is not in scope for the step that uses it{}.", named(dep(d)), named(line(r))),
+            ForwardReference { dep: d } => format!("The dependency{} comes after the step that uses it.", named(dep(d))),
+            OneOf(errs) => {
+                let mut message = "One of the following requirements was not met:\n".to_string();
+                for err in errs {
+                    message += &err.describe_with(line, subproof);
+                    message.push('\n');
+                }
+                message
+            }
+            _ => self.to_string(),
+        }
+    }
+}
+
+impl<R, S> std::fmt::Display for ProofCheckError<R, S> {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         use ProofCheckError::*;
         match self {
-            LineDoesNotExist(r) => write!(f, "The referenced line {r:?} does not exist."),
-            SubproofDoesNotExist(s) => write!(f, "The referenced subproof {s:?} does not exist."),
-            ReferencesLaterLine(line, dep) => write!(f, "The dependency {dep:?} is after the step that uses it ({line:?})."),
+            LineDoesNotExist(_) | SubproofDoesNotExist(_) | ReferencesLaterLine(..) | ForwardReference { .. } => write!(f, "{}", self.describe_with(&|_| None, &|_| None)),
+            SelfReference => write!(f, "A step can't cite itself."),
             IncorrectDepCount(deps, n) => write!(f, "Too {} dependencies (expected: {}, provided: {}).", if deps.len() > *n { "many" } else { "few" }, n, deps.len()),
             IncorrectSubDepCount(sdeps, n) => write!(f, "Too {} subproof dependencies (expected: {}, provided: {}).", if sdeps.len() > *n { "many" } else { "few" }, n, sdeps.len()),
+            IncorrectSubproofPremiseCount(_, n) => write!(f, "The subproof has {n} premises, but this rule needs it to have exactly one."),
             DepOfWrongForm(x, y) => write!(f, "A dependency ({x}) is of the wrong form, expected {y}."),
             ConclusionOfWrongForm(kind) => write!(f, "The conclusion is of the wrong form, expected {kind}."),
             DoesNotOccur(x, y) => write!(f, "{x} does not occur in {y}."),
@@ -1720,17 +1934,61 @@ impl<R: std::fmt::Debug, S: std::fmt::Debug> std::fmt::Display for ProofCheckErr
                 }
                 Ok(())
             }
+            BudgetExceeded => write!(f, "This step is too expensive to check; try breaking it into smaller steps."),
+            Counterexample(assignment) => {
+                let assignment = assignment.iter().map(|(name, val)| format!("{name} = {}", if *val { 'T' } else { 'F' })).collect::<Vec<String>>().join(", ");
+                write!(f, "Not true by tautological consequence; Counterexample: {assignment}")
+            }
             Other(msg) => write!(f, "{msg}"),
         }
     }
 }
 
+/// A row of a counterexample as it's displayed, with a variable and its truth
+/// value as `T` or `F`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CounterexampleRow {
+    pub atom: String,
+    pub value: char,
+}
+
+/// The rows to display of a `ProofCheckError::Counterexample`, ordered by
+/// variable name rather than the order the solver found them in
+pub fn counterexample_rows(assignment: &[(String, bool)]) -> Vec<CounterexampleRow> {
+    let mut rows = assignment.iter().map(|(atom, value)| CounterexampleRow { atom: atom.clone(), value: if *value { 'T' } else { 'F' } }).collect::<Vec<_>>();
+    rows.sort_by(|a, b| a.atom.cmp(&b.atom));
+    rows
+}
+
+/// A counterexample on one line, like `P=T, Q=F`, in the order of
+/// `counterexample_rows`
+pub fn counterexample_text(assignment: &[(String, bool)]) -> String {
+    counterexample_rows(assignment).iter().map(|row| format!("{}={}", row.atom, row.value)).collect::<Vec<_>>().join(", ")
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
     use frunk_core::HList;
 
+    #[test]
+    fn test_counterexample_rows() {
+        let assignment = vec![("Q".to_string(), false), ("P".to_string(), true), ("B".to_string(), false)];
+        let rows = counterexample_rows(&assignment);
+        assert_eq!(rows.iter().map(|row| (row.atom.as_str(), row.value)).collect::<Vec<_>>(), vec![("B", 'F'), ("P", 'T'), ("Q", 'F')]);
+        assert_eq!(counterexample_text(&assignment), "B=F, P=T, Q=F");
+
+        // Long names are kept whole
+        let long = "it_is_raining_in_the_city_of_troy_on_a_tuesday".to_string();
+        let assignment = vec![(long.clone(), true), ("a".to_string(), true)];
+        assert_eq!(counterexample_rows(&assignment)[0], CounterexampleRow { atom: "a".into(), value: 'T' });
+        assert_eq!(counterexample_rows(&assignment)[1].atom, long);
+        assert_eq!(counterexample_text(&assignment), format!("a=T, {long}=T"));
+
+        assert_eq!(counterexample_text(&[]), "");
+    }
+
     #[test]
     fn test_either_order() {
         use crate::parser::parse_unwrap as p;
@@ -1771,4 +2029,39 @@ mod tests {
 
         assert!(result.is_ok());
     }
+
+    #[test]
+    fn test_budget() {
+        use crate::parser::parse_unwrap as p;
+        use crate::proofs::Justification;
+
+        type P = crate::proofs::pooledproof::PooledProof<HList![Expr]>;
+
+        let mut prf = P::new();
+        let r1 = prf.add_premise(p("A & B & C & D & E & F & G & H & I & J"));
+        let r2 = prf.add_step(Justification(p("J | K"), RuleM::TautologicalConsequence, vec![Coproduct::inject(r1)], vec![]));
+        let r3 = prf.add_step(Justification(p("A"), RuleM::AndElim, vec![Coproduct::inject(r1)], vec![]));
+        let r4 = prf.add_premise(p("A & B"));
+        let r5 = prf.add_step(Justification(p("B"), RuleM::TautologicalConsequence, vec![Coproduct::inject(r4)], vec![]));
+
+        // Few variables, but distributing the disjunction makes 2^8 clauses
+        let r6 = prf.add_premise(p("(A & B) | (A & C) | (A & D) | (B & C) | (B & D) | (C & D) | (A & E) | (B & E)"));
+        let r7 = prf.add_step(Justification(p("A | B | C"), RuleM::TautologicalConsequence, vec![Coproduct::inject(r6)], vec![]));
+        let r8 = prf.add_premise(p("A | B"));
+        let r9 = prf.add_premise(p("~A | C"));
+        let r10 = prf.add_step(Justification(p("B | C"), RuleM::Resolution, vec![Coproduct::inject(r8), Coproduct::inject(r9)], vec![]));
+
+        let small = || CheckContext { budget: Budget::new(64), ..CheckContext::default() };
+        assert_eq!(prf.verify_line_with(&Coproduct::inject(r2), &small()), Ok(()));
+        assert_eq!(prf.verify_line_with(&Coproduct::inject(r3), &small()), Ok(()));
+        assert_eq!(prf.verify_line_with(&Coproduct::inject(r5), &small()), Ok(()));
+        assert_eq!(prf.verify_line_with(&Coproduct::inject(r7), &small()), Err(ProofCheckError::BudgetExceeded));
+        assert_eq!(prf.verify_line_with(&Coproduct::inject(r10), &small()), Ok(()));
+        let tiny = || CheckContext { budget: Budget::new(4), ..CheckContext::default() };
+        assert_eq!(prf.verify_line_with(&Coproduct::inject(r2), &tiny()), Err(ProofCheckError::BudgetExceeded));
+        assert_eq!(prf.verify_line_with(&Coproduct::inject(r10), &tiny()), Err(ProofCheckError::BudgetExceeded));
+        assert_eq!(prf.verify_line(&Coproduct::inject(r2)), Ok(()));
+        assert_eq!(prf.verify_line(&Coproduct::inject(r7)), Ok(()));
+        assert_eq!(prf.verify_line(&Coproduct::inject(r10)), Ok(()));
+    }
 }
@@ -0,0 +1,150 @@
+/*!
+Searching for a rule and dependencies that justify a step.
+
+Each rule is tried in the order of `RuleM::ALL_RULES`, with every combination of lines and subproofs the step can cite, earliest first. Rules that take any number of dependencies are only tried with up to `MAX_VARIADIC_DEPS` of each kind. Trying a combination costs one step of the `CheckContext`'s budget, on top of what the rule's check costs, and the search stops when the budget runs out.
+
+# Example
+```
+#[macro_use] extern crate frunk_core;
+use frunk_core::coproduct::Coproduct;
+use aris::expr::Expr;
+use aris::parser::parse_unwrap as p;
+use aris::proofs::pooledproof::PooledProof;
+use aris::proofs::{Justification, Proof};
+use aris::rules::suggest::{apply_suggestion, suggest};
+use aris::rules::{CheckContext, RuleM};
+
+let mut prf = PooledProof::<HList![Expr]>::new();
+let r1 = prf.add_premise(p("A -> B"));
+let r2 = prf.add_premise(p("A"));
+let step = prf.add_step(Justification(p("B"), RuleM::EmptyRule, vec![], vec![]));
+let suggestions = suggest(&prf, &step, &CheckContext::default(), 5);
+assert_eq!(suggestions[0].rule, RuleM::ImpElim);
+assert_eq!(suggestions[0].deps, vec![Coproduct::inject(r1), Coproduct::inject(r2)]);
+apply_suggestion(&mut prf, &step, &suggestions[0]);
+assert!(prf.verify_all().is_fully_verified());
+```
+*/
+
+use super::CheckContext;
+use super::Rule;
+use super::RuleM;
+use super::RuleT;
+use crate::proofs::Justification;
+use crate::proofs::PjRef;
+use crate::proofs::Proof;
+
+use frunk_core::coproduct::Coproduct;
+use itertools::Itertools;
+
+/// The most dependencies of each kind tried for rules that take any number
+pub const MAX_VARIADIC_DEPS: usize = 2;
+
+/// A rule and dependencies that justify a step
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Suggestion<R, S> {
+    pub rule: Rule,
+    /// Line dependencies, in display order
+    pub deps: Vec<R>,
+    /// Subproof dependencies, in display order
+    pub sdeps: Vec<S>,
+}
+
+/// Up to `limit` ways to justify the step `step`, at most one for each rule,
+/// using only lines and subproofs it can cite
+pub fn suggest<P: Proof>(prf: &P, step: &P::JustificationReference, ctx: &CheckContext, limit: usize) -> Vec<Suggestion<PjRef<P>, P::SubproofReference>> {
+    use frunk_core::coproduct::Coproduct::{Inl, Inr};
+    let Justification(conclusion, _, _, _) = match prf.lookup_step(step) {
+        Some(just) => just,
+        None => return vec![],
+    };
+    let line: PjRef<P> = Coproduct::inject(step.clone());
+    let mut lines = vec![];
+    let mut subproofs = vec![];
+    for info in prf.iter_lines() {
+        match info.reference {
+            Inl(pr) => lines.push(Coproduct::inject(pr)),
+            Inr(Inl(jr)) => lines.push(Coproduct::inject(jr)),
+            Inr(Inr(Inl(sr))) => subproofs.push(sr),
+            Inr(Inr(Inr(void))) => match void {},
+        }
+    }
+    lines.retain(|dep: &PjRef<P>| prf.can_reference_dep(&line, &Coproduct::inject(dep.clone())));
+    subproofs.retain(|sdep| prf.can_reference_dep(&line, &Coproduct::inject(sdep.clone())));
+    let counts = |n: Option<usize>| n.map(|n| vec![n]).unwrap_or_else(|| (0..=MAX_VARIADIC_DEPS).collect());
+    let mut found = vec![];
+    for rule in RuleM::ALL_RULES.iter().copied().filter(|rule| *rule != RuleM::EmptyRule) {
+        if found.len() >= limit {
+            break;
+        }
+        'rule: for num_deps in counts(rule.num_deps()) {
+            for num_sdeps in counts(rule.num_subdeps()) {
+                for deps in lines.iter().cloned().combinations(num_deps) {
+                    for sdeps in subproofs.iter().cloned().combinations(num_sdeps) {
+                        if ctx.budget.charge::<PjRef<P>, P::SubproofReference>(1).is_err() {
+                            return found;
+                        }
+                        if rule.check(prf, conclusion.clone(), deps.clone(), sdeps.clone(), ctx).is_ok() {
+                            found.push(Suggestion { rule, deps, sdeps });
+                            break 'rule;
+                        }
+                    }
+                }
+            }
+        }
+    }
+    found
+}
+
+/// Justify the step `step` as `suggestion` says, all at once. Returns `None`
+/// if the step doesn't exist.
+pub fn apply_suggestion<P: Proof>(prf: &mut P, step: &P::JustificationReference, suggestion: &Suggestion<PjRef<P>, P::SubproofReference>) -> Option<()> {
+    prf.with_mut_step(step, |Justification(_, rule, deps, sdeps)| {
+        *rule = suggestion.rule;
+        *deps = suggestion.deps.clone();
+        *sdeps = suggestion.sdeps.clone();
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::expr::Expr;
+    use crate::parser::parse_unwrap as p;
+    use crate::proofs::pooledproof::PooledProof;
+    use crate::rules::Budget;
+
+    use frunk_core::HList;
+
+    type P = PooledProof<HList![Expr]>;
+
+    #[test]
+    fn test_suggest() {
+        let mut prf = P::new();
+        let r1: PjRef<P> = Coproduct::inject(prf.add_premise(p("A & B")));
+        let sub = prf.add_subproof();
+        let r3: PjRef<P> = Coproduct::inject(prf.with_mut_subproof(&sub, |sub| sub.add_premise(p("C"))).unwrap());
+        let step = prf.add_step(Justification(p("B"), RuleM::EmptyRule, vec![], vec![]));
+
+        // Every suggestion justifies the step, and none cite the line inside the subproof
+        let suggestions = suggest(&prf, &step, &CheckContext::default(), 5);
+        assert!(!suggestions.is_empty() && suggestions.len() <= 5);
+        assert_eq!(suggestions[0], Suggestion { rule: RuleM::AndElim, deps: vec![r1], sdeps: vec![] });
+        for suggestion in &suggestions {
+            assert!(!suggestion.deps.contains(&r3));
+            let mut applied = prf.clone();
+            apply_suggestion(&mut applied, &step, suggestion).unwrap();
+            assert_eq!(applied.verify_line(&Coproduct::inject(step)), Ok(()), "{suggestion:?}");
+        }
+
+        // The first suggestion is a limit of one
+        assert_eq!(suggest(&prf, &step, &CheckContext::default(), 1), suggestions[..1]);
+
+        // Nothing is found without any budget, or for a step that doesn't follow
+        let ctx = CheckContext { budget: Budget::new(0), ..CheckContext::default() };
+        assert!(suggest(&prf, &step, &ctx, 5).is_empty());
+        let step = prf.add_step(Justification(p("D"), RuleM::EmptyRule, vec![], vec![]));
+        assert!(suggest(&prf, &step, &CheckContext::default(), 5).is_empty());
+    }
+}
@@ -0,0 +1,170 @@
+/*!
+Truth tables of propositional formulas.
+
+A truth table has a row for each assignment of truth values to the atoms of a formula, which are its variables, with the formula's value under it. Formulas with quantifiers, predicates, or arithmetic don't have one, and neither do formulas with more than [`MAX_ATOMS`] atoms, whose tables would be too big to be of use.
+
+# Example
+```
+use aris::parser::parse_unwrap as p;
+use aris::truth_table::truth_table;
+use aris::truth_table::Verdict;
+
+let table = truth_table(&p("A -> B | A")).unwrap();
+assert_eq!(table.atoms, vec!["A", "B"]);
+assert_eq!(table.rows.len(), 4);
+assert_eq!(table.verdict(), Verdict::Tautology);
+```
+*/
+
+use crate::expr::free_vars;
+use crate::expr::Expr;
+use crate::expr::Op;
+
+use std::collections::HashMap;
+use std::fmt;
+
+/// The most atoms a formula can have for its truth table to be made
+pub const MAX_ATOMS: usize = 16;
+
+/// The truth table of a formula
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TruthTable {
+    /// The atoms of the formula, in sorted order
+    pub atoms: Vec<String>,
+    /// A row for each assignment of truth values to the atoms, starting from
+    /// all of them true and counting down to all of them false
+    pub rows: Vec<TruthTableRow>,
+}
+
+/// A row of a truth table
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TruthTableRow {
+    /// The value of each atom, in the order of `TruthTable::atoms`
+    pub values: Vec<bool>,
+    /// The value of the formula
+    pub result: bool,
+}
+
+/// Whether a formula is true in every row of its truth table, some of them,
+/// or none of them
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Verdict {
+    Tautology,
+    Contingent,
+    Contradiction,
+}
+
+impl fmt::Display for Verdict {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Verdict::Tautology => write!(f, "tautology"),
+            Verdict::Contingent => write!(f, "contingent"),
+            Verdict::Contradiction => write!(f, "contradiction"),
+        }
+    }
+}
+
+/// Reasons a formula doesn't have a truth table
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TruthTableError {
+    /// The formula has a quantifier, so its truth depends on a domain
+    Quantifier,
+    /// The formula applies a predicate or function, so its truth depends on
+    /// what the predicate is
+    Application,
+    /// The formula uses an operator that isn't a connective
+    Operator(Op),
+    /// The formula has this many atoms, which is more than `MAX_ATOMS`
+    TooManyAtoms(usize),
+}
+
+impl fmt::Display for TruthTableError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TruthTableError::Quantifier => write!(f, "Formulas with quantifiers don't have truth tables."),
+            TruthTableError::Application => write!(f, "Formulas with predicates or functions don't have truth tables."),
+            TruthTableError::Operator(op) => write!(f, "Formulas with {op} don't have truth tables."),
+            TruthTableError::TooManyAtoms(atoms) => write!(f, "This formula has {atoms} atoms, so its truth table would have too many rows. Truth tables can be made for up to {MAX_ATOMS} atoms."),
+        }
+    }
+}
+
+/// The first part of `expr` that a truth table can't be made for, if there is one
+fn unsupported(expr: &Expr) -> Option<TruthTableError> {
+    match expr {
+        Expr::Contra | Expr::Taut | Expr::Var { .. } => None,
+        Expr::Apply { .. } => Some(TruthTableError::Application),
+        Expr::Not { operand } => unsupported(operand),
+        Expr::Impl { left, right } => unsupported(left).or_else(|| unsupported(right)),
+        Expr::Assoc { op: op @ (Op::Equiv | Op::Add | Op::Mult), .. } => Some(TruthTableError::Operator(*op)),
+        Expr::Assoc { exprs, .. } => exprs.iter().find_map(unsupported),
+        Expr::Quant { .. } => Some(TruthTableError::Quantifier),
+    }
+}
+
+/// Make the truth table of the propositional formula `expr`
+pub fn truth_table(expr: &Expr) -> Result<TruthTable, TruthTableError> {
+    if let Some(err) = unsupported(expr) {
+        return Err(err);
+    }
+    let mut atoms = free_vars(expr).into_iter().collect::<Vec<_>>();
+    atoms.sort();
+    if atoms.len() > MAX_ATOMS {
+        return Err(TruthTableError::TooManyAtoms(atoms.len()));
+    }
+    let rows = (0..1usize << atoms.len())
+        .map(|row| {
+            // The first atom changes slowest, and each is true before it's false
+            let values = (0..atoms.len()).map(|i| row >> (atoms.len() - 1 - i) & 1 == 0).collect::<Vec<_>>();
+            let env = atoms.iter().cloned().zip(values.iter().map(|value| vec![*value])).collect::<HashMap<_, _>>();
+            TruthTableRow { result: expr.eval(&env), values }
+        })
+        .collect();
+    Ok(TruthTable { atoms, rows })
+}
+
+impl TruthTable {
+    /// Whether the formula is true in every row, some of them, or none of them
+    pub fn verdict(&self) -> Verdict {
+        let trues = self.rows.iter().filter(|row| row.result).count();
+        if trues == self.rows.len() {
+            Verdict::Tautology
+        } else if trues == 0 {
+            Verdict::Contradiction
+        } else {
+            Verdict::Contingent
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::parser::parse_unwrap as p;
+
+    #[test]
+    fn test_truth_table() {
+        let table = truth_table(&p("A & ~B")).unwrap();
+        let rows = table.rows.iter().map(|row| (row.values.clone(), row.result)).collect::<Vec<_>>();
+        assert_eq!(rows, vec![(vec![true, true], false), (vec![true, false], true), (vec![false, true], false), (vec![false, false], false)]);
+        assert_eq!(table.verdict(), Verdict::Contingent);
+
+        assert_eq!(truth_table(&p("A | ~A")).unwrap().verdict(), Verdict::Tautology);
+        assert_eq!(truth_table(&p("A <-> ~A")).unwrap().verdict(), Verdict::Contradiction);
+
+        // A formula without atoms has a single row
+        let table = truth_table(&p("_|_ -> ^|^")).unwrap();
+        assert_eq!(table.atoms.len(), 0);
+        assert_eq!(table.rows, vec![TruthTableRow { values: vec![], result: true }]);
+    }
+
+    #[test]
+    fn test_truth_table_errors() {
+        assert_eq!(truth_table(&p("forall x P(x)")), Err(TruthTableError::Quantifier));
+        assert_eq!(truth_table(&p("A -> P(x)")), Err(TruthTableError::Application));
+        assert_eq!(truth_table(&p("A & (B === C)")), Err(TruthTableError::Operator(Op::Equiv)));
+        let many = (0..=MAX_ATOMS).map(|i| format!("A{i}")).collect::<Vec<_>>().join(" & ");
+        assert_eq!(truth_table(&p(&many)), Err(TruthTableError::TooManyAtoms(MAX_ATOMS + 1)));
+    }
+}
@@ -110,6 +110,36 @@ impl<T: PartialEq> ZipperVec<T> {
             (if after { &mut self.suffix_r } else { &mut self.prefix }).push(val);
         }
     }
+    /// Swap `rel` with its neighbor after it (or before it, if `after` is
+    /// false). Returns false if `rel` isn't present or has no such neighbor.
+    pub fn swap_relative(&mut self, rel: &T, after: bool) -> bool {
+        let len = self.len();
+        self.move_cursor(len);
+        let i = match self.prefix.iter().position(|x| x == rel) {
+            Some(i) => i,
+            None => return false,
+        };
+        let j = if after { i + 1 } else { i.wrapping_sub(1) };
+        if j >= len {
+            return false;
+        }
+        self.prefix.swap(i, j);
+        true
+    }
+}
+
+#[test]
+fn test_zippervec_swap_relative() {
+    let mut z = ZipperVec::from_vec(vec![0, 10, 20, 30]);
+    z.move_cursor(1);
+    assert!(z.swap_relative(&10, true));
+    assert_eq!(z.iter().cloned().collect::<Vec<usize>>(), vec![0, 20, 10, 30]);
+    assert!(z.swap_relative(&30, false));
+    assert_eq!(z.iter().cloned().collect::<Vec<usize>>(), vec![0, 20, 30, 10]);
+    assert!(!z.swap_relative(&0, false));
+    assert!(!z.swap_relative(&10, true));
+    assert!(!z.swap_relative(&5, true));
+    assert_eq!(z.iter().cloned().collect::<Vec<usize>>(), vec![0, 20, 30, 10]);
 }
 
 #[test]
@@ -3,8 +3,11 @@
 
 use aris::expr::Expr;
 use aris::proofs::lined_proof::LinedProof;
-use aris::proofs::xml_interop::proof_from_xml;
+use aris::proofs::xml_interop::proof_from_xml_compat;
+use aris::proofs::xml_interop::ImportedHashStatus;
 use aris::proofs::{Justification, PjRef, Proof};
+use aris::rules::Budget;
+use aris::rules::CheckContext;
 use aris::rules::ProofCheckError;
 
 use std::collections::HashSet;
@@ -16,6 +19,9 @@ use std::path::Path;
 use frunk_core::coproduct::Coproduct;
 use frunk_core::HList;
 
+/// Grading runs offline, so expensive rules get far more room than in the web UI
+const CHECK_BUDGET: u64 = 1 << 32;
+
 type ValidateError<P> = (PjRef<P>, ProofCheckError<PjRef<P>, <P as Proof>::SubproofReference>);
 
 fn validate_recursive<P: Proof>(proof: &P, line: PjRef<P>) -> Result<(), ValidateError<P>>
@@ -31,7 +37,8 @@ where
     // otherwise,
     while let Some(r) = q.pop() {
         //println!("q: {:?} {:?}", r, q);
-        proof.verify_line(&r).map_err(|e| (r.clone(), e))?;
+        let ctx = CheckContext { budget: Budget::new(CHECK_BUDGET), ..CheckContext::default() };
+        proof.verify_line_with(&r, &ctx).map_err(|e| (r.clone(), e))?;
 
         let line = proof.lookup_pj(&r);
         //println!("line: {:?}", line);
@@ -79,8 +86,17 @@ fn main() -> Result<(), String> {
 
     type P = aris::proofs::pooledproof::PooledProof<HList![Expr]>;
 
-    let (i_prf, i_meta) = proof_from_xml::<P, _>(&instructor_file).unwrap();
-    let (s_prf, _) = proof_from_xml::<P, _>(&student_file).unwrap();
+    let (i_prf, i_meta, _, i_warnings) = proof_from_xml_compat::<P, _>(&instructor_file).unwrap();
+    let (s_prf, _, s_hash, s_warnings) = proof_from_xml_compat::<P, _>(&student_file).unwrap();
+    // Parts of the files that were ignored could have mattered to the grade
+    for (path, warnings) in [(instructor_path, i_warnings), (student_path, s_warnings)] {
+        for warning in warnings {
+            eprintln!("Warning: {}: {warning}", path.display());
+        }
+    }
+    if s_hash == ImportedHashStatus::Mismatch {
+        eprintln!("Warning: the student assignment was edited after it was saved by Aris");
+    }
 
     let instructor_premises = i_prf.premises();
     let student_premises = s_prf.premises();
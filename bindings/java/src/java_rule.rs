@@ -1,5 +1,6 @@
 use super::*;
 
+use aris::rules::CheckContext;
 use aris::rules::Rule;
 use aris::rules::RuleClassification;
 use aris::rules::RuleM;
@@ -136,7 +137,7 @@ pub extern "system" fn Java_edu_rpi_aris_rules_Rule_verifyClaim(env: JNIEnv, rul
             }
         }
         println!("Rule::verifyClaim deps: {deps:?} {sdeps:?}");
-        if let Err(e) = rule.check(&JavaShallowProof(vec![]), conc, deps, sdeps) {
+        if let Err(e) = rule.check(&JavaShallowProof(vec![]), conc, deps, sdeps, &CheckContext::default()) {
             Ok(env.new_string(format!("{e}"))?.into_inner())
         } else {
             Ok(std::ptr::null_mut())
@@ -0,0 +1,134 @@
+//! Autosaving proofs with unsaved changes to the browser's `localStorage`, so
+//! that they can be restored after the page is closed or crashes
+
+use crate::components::tabbed_container::TabId;
+use crate::util::xml_from_json;
+use crate::util::P;
+
+use aris::proofs::json_interop::proof_to_json;
+use aris::proofs::json_interop::JsonError;
+use aris::proofs::xml_interop::ProofMetaData;
+
+use wasm_bindgen::JsCast;
+use wasm_bindgen::JsValue;
+
+/// The start of the keys of autosaved proofs in `localStorage`
+const KEY_PREFIX: &str = "aris-autosave:";
+
+/// How often proofs with unsaved changes are autosaved, in milliseconds
+pub const AUTOSAVE_INTERVAL_MS: u32 = 30_000;
+
+/// The key to autosave the proof of a tab under. Tab ids start over with each
+/// page load, so the key has the session's id too, and it has the tab's name to
+/// restore it with.
+pub fn autosave_key(session: u64, id: TabId, name: &str) -> String {
+    format!("{KEY_PREFIX}{session}:{}:{name}", id.0)
+}
+
+/// The session, tab id, and tab name of an autosaved proof's key, or `None`
+/// if the key isn't one
+pub fn parse_autosave_key(key: &str) -> Option<(u64, TabId, &str)> {
+    let mut parts = key.strip_prefix(KEY_PREFIX)?.splitn(3, ':');
+    let session = parts.next()?.parse().ok()?;
+    let id = TabId(parts.next()?.parse().ok()?);
+    Some((session, id, parts.next()?))
+}
+
+/// Write a proof as it's autosaved
+pub fn autosave_entry(prf: &P, meta: &ProofMetaData) -> String {
+    proof_to_json(prf, meta)
+}
+
+/// The data to open an autosaved proof with in a `ProofWidget`
+pub fn restore_entry(entry: &str) -> Result<Vec<u8>, JsonError> {
+    xml_from_json(entry)
+}
+
+fn local_storage() -> Result<web_sys::Storage, String> {
+    web_sys::window().and_then(|window| window.local_storage().ok().flatten()).ok_or_else(|| "This browser doesn't allow saving data for the page, so proofs can't be autosaved".into())
+}
+
+/// The autosaved proofs, with their keys and tab names
+pub fn load_entries() -> Vec<(String, String, String)> {
+    let storage = match local_storage() {
+        Ok(storage) => storage,
+        Err(_) => return vec![],
+    };
+    let keys = (0..storage.length().unwrap_or(0)).filter_map(|i| storage.key(i).ok().flatten()).collect::<Vec<String>>();
+    keys.into_iter()
+        .filter_map(|key| {
+            let name = parse_autosave_key(&key)?.2.to_string();
+            let entry = storage.get_item(&key).ok().flatten()?;
+            Some((key, name, entry))
+        })
+        .collect()
+}
+
+/// Store an autosaved proof, describing why it couldn't be if it wasn't, such
+/// as there being no room left
+pub fn store_entry(key: &str, entry: &str) -> Result<(), String> {
+    local_storage()?.set_item(key, entry).map_err(|err: JsValue| {
+        let reason = match err.dyn_ref::<js_sys::Error>() {
+            Some(err) if err.name() == "QuotaExceededError" => "the browser's storage for the page is full".into(),
+            Some(err) => String::from(err.message()),
+            None => "the browser's storage can't be written to".into(),
+        };
+        format!("Couldn't autosave the proof, since {reason}")
+    })
+}
+
+/// Remove an autosaved proof, if it's there
+pub fn remove_entry(key: &str) {
+    if let Ok(storage) = local_storage() {
+        let _ = storage.remove_item(key);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use aris::parser::parse_unwrap as p;
+    use aris::proofs::tree::TreeProof;
+    use aris::proofs::xml_interop::proof_from_xml;
+    use aris::proofs::Justification;
+    use aris::proofs::Proof;
+    use aris::rules::RuleM;
+
+    use frunk_core::coproduct::Coproduct;
+
+    #[test]
+    fn test_autosave_key() {
+        let key = autosave_key(1700000000000, TabId(3), "Untitled proof 1");
+        assert_eq!(parse_autosave_key(&key), Some((1700000000000, TabId(3), "Untitled proof 1")));
+
+        // Tabs with the same name, or the same id in another session, don't share a key
+        assert_ne!(autosave_key(1, TabId(3), "a.bram"), autosave_key(1, TabId(4), "a.bram"));
+        assert_ne!(autosave_key(1, TabId(3), "a.bram"), autosave_key(2, TabId(3), "a.bram"));
+        // and names can have anything in them
+        assert_eq!(parse_autosave_key(&autosave_key(1, TabId(1), "hw:3:2")), Some((1, TabId(1), "hw:3:2")));
+        assert_ne!(autosave_key(1, TabId(1), "2:a"), autosave_key(1, TabId(12), "a"));
+
+        // Other keys aren't mistaken for autosaved proofs
+        assert_eq!(parse_autosave_key("theme"), None);
+        assert_eq!(parse_autosave_key("aris-autosave:x:1:a"), None);
+        assert_eq!(parse_autosave_key("aris-autosave:1:1"), None);
+    }
+
+    #[test]
+    fn test_restore_entry() {
+        let mut prf = P::new();
+        let r1 = prf.add_premise(p("A & B"));
+        let r2 = prf.add_step(Justification(p("A"), RuleM::AndElim, vec![Coproduct::inject(r1)], vec![]));
+        let mut meta = ProofMetaData { title: Some("homework".into()), goals: vec![p("A")], ..Default::default() };
+        meta.comments.insert(prf.stable_id_of(&Coproduct::inject(r2)).unwrap(), "by the first premise".into());
+
+        let data = restore_entry(&autosave_entry(&prf, &meta)).unwrap();
+        let (restored, restored_meta, _) = proof_from_xml::<P, _>(&data[..]).unwrap();
+        assert_eq!(TreeProof::from_proof(&restored), TreeProof::from_proof(&prf));
+        assert_eq!((restored_meta.title, restored_meta.goals), (meta.title, meta.goals));
+        assert_eq!(restored_meta.comments.into_values().collect::<Vec<_>>(), vec!["by the first premise".to_string()]);
+
+        assert!(restore_entry("{").is_err());
+    }
+}
@@ -1,4 +0,0 @@
-pub const VERT: char = '│';
-pub const VERT_RIGHT: char = '├';
-pub const UP_RIGHT: char = '╰';
-pub const HORIZ: char = '─';
@@ -1,45 +1,169 @@
+use crate::autosave;
+use crate::components::nav_bar::open_files;
 use crate::components::nav_bar::NavBarMsg;
 use crate::components::nav_bar::NavBarWidget;
 use crate::components::proof_widget::ProofWidget;
 use crate::components::proof_widget::ProofWidgetMsg;
+use crate::components::tabbed_container::TabContent;
+use crate::components::tabbed_container::TabId;
 use crate::components::tabbed_container::TabbedContainer;
 use crate::components::tabbed_container::TabbedContainerMsg;
 use crate::util::P;
+use aris::proofs::xml_interop::ProofMetaData;
 
 use std::collections::HashMap;
+use std::collections::HashSet;
 
+use gloo::events::EventListener;
+use gloo::events::EventListenerOptions;
+use gloo::timers::callback::Interval;
+use wasm_bindgen::UnwrapThrowExt;
 use yew::html::Scope;
 use yew::prelude::*;
 
+/// The contents of a tab with a proof, which is opened from `data` if there is
+/// any. It tells the app about itself through its tab's id.
+pub fn proof_tab_content(app: &Scope<App>, id: TabId, data: Option<Vec<u8>>, unsaved: bool) -> TabContent {
+    let oncreate = app.callback(move |link| AppMsg::RegisterProof { id, link });
+    let ondirty = app.callback(move |dirty| AppMsg::ProofDirty { id, dirty });
+    let onclose = app.callback(move |()| AppMsg::CloseTab(id));
+    TabContent::new(move |visible| html! { <ProofWidget verbose=true data={ data.clone() } { unsaved } oncreate={ oncreate.clone() } ondirty={ ondirty.clone() } onclose={ onclose.clone() } { visible } /> })
+}
+
 pub struct App {
     tabcontainer_link: Option<Scope<TabbedContainer>>,
-    proofs: HashMap<String, Scope<ProofWidget>>,
+    /// The proof widgets of the tabs that have them, by the tab's id, since
+    /// tabs can have the same name
+    proofs: HashMap<TabId, Scope<ProofWidget>>,
+    next_tab_id: usize,
+    /// The tabs whose proofs have changes that haven't been saved
+    unsaved: HashSet<TabId>,
+    /// Asks for confirmation before leaving the page, which is only registered
+    /// while there are unsaved changes
+    beforeunload: Option<EventListener>,
+    /// When the page was loaded, which tells this page's autosaves apart from
+    /// those of earlier ones
+    session: u64,
+    /// The key each tab's proof was last autosaved under
+    autosave_keys: HashMap<TabId, String>,
+    /// Proofs autosaved by earlier pages, with their keys and tab names, which
+    /// can be restored until they're discarded
+    restorable: Vec<(String, String, String)>,
+    /// Why the last autosave or restore failed, if it did
+    autosave_error: Option<String>,
+    _autosave_interval: Interval,
+    /// Errors to show until they're dismissed, such as files that couldn't be
+    /// opened
+    toasts: Vec<String>,
 }
 
 pub enum AppMsg {
     TabbedContainerInit(Scope<TabbedContainer>),
     NavBarInit(Scope<NavBarWidget>),
+    /// Create a tab, with its contents made from the id it's given
     CreateTab {
         name: String,
-        content: Html,
+        content: Box<dyn FnOnce(TabId) -> TabContent>,
     },
-    RegisterProofName {
-        name: String,
+    RegisterProof {
+        id: TabId,
         link: Scope<ProofWidget>,
     },
+    /// Close a tab, from inside it
+    CloseTab(TabId),
+    TabClosed(TabId),
+    /// Whether the proof of a tab has unsaved changes changed
+    ProofDirty {
+        id: TabId,
+        dirty: bool,
+    },
+    /// The proof of a tab was saved
+    ProofSaved(TabId),
+    /// Autosave the proofs that have unsaved changes
+    Autosave,
+    Autosaved {
+        id: TabId,
+        key: String,
+    },
+    AutosaveFailed(String),
+    DismissAutosaveError,
+    /// Open tabs for the proofs autosaved by earlier pages
+    RestoreSession,
+    /// Remove the proofs autosaved by earlier pages
+    DiscardSession,
+    /// Show an error until it's dismissed
+    ShowError(String),
+    DismissError(usize),
+    /// Open the proofs in files dropped on the page
+    DropFiles(web_sys::FileList),
+    /// Check the whole proof of the current tab
+    CheckCurrentProof,
+    /// Print the proof of the current tab
+    PrintCurrentProof,
+    /// The settings changed, so every proof is shown again
+    SettingsChanged,
     #[allow(clippy::type_complexity)]
-    GetProofFromCurrentTab(Box<dyn FnOnce(String, &P)>),
+    GetProofFromCurrentTab(Box<dyn FnOnce(TabId, String, &P, &ProofMetaData)>),
+}
+
+impl App {
+    /// Create a tab, with its contents made from the id it's given
+    fn create_tab(&mut self, name: String, content: Box<dyn FnOnce(TabId) -> TabContent>) -> TabId {
+        let id = TabId(self.next_tab_id);
+        self.next_tab_id += 1;
+        if let Some(tabcontainer_link) = &self.tabcontainer_link {
+            tabcontainer_link.send_message(TabbedContainerMsg::Create { id, name, content: content(id) });
+        }
+        id
+    }
+
+    /// Remove the autosave of a tab's proof, which is no longer needed once
+    /// it's saved or closed
+    fn remove_autosave(&mut self, id: TabId) {
+        if let Some(key) = self.autosave_keys.remove(&id) {
+            autosave::remove_entry(&key);
+        }
+    }
+
+    /// Record whether a tab has unsaved changes, marking it, and warning
+    /// before leaving the page while any tab does
+    fn set_unsaved(&mut self, id: TabId, unsaved: bool) {
+        if unsaved {
+            self.unsaved.insert(id);
+        } else {
+            self.unsaved.remove(&id);
+        }
+        if let Some(tabcontainer_link) = &self.tabcontainer_link {
+            tabcontainer_link.send_message(TabbedContainerMsg::MarkUnsaved { id, unsaved });
+        }
+        match (self.unsaved.is_empty(), &self.beforeunload) {
+            (false, None) => {
+                let window = web_sys::window().expect_throw("window is undefined");
+                self.beforeunload = Some(EventListener::new_with_options(&window, "beforeunload", EventListenerOptions::enable_prevent_default(), |event| {
+                    event.prevent_default();
+                    // Older browsers only ask for confirmation if `returnValue` is set
+                    let _ = js_sys::Reflect::set(event, &"returnValue".into(), &"".into());
+                }));
+            }
+            // Dropping the listener unregisters it
+            (true, Some(_)) => self.beforeunload = None,
+            _ => {}
+        }
+    }
 }
 
 impl Component for App {
     type Message = AppMsg;
     type Properties = ();
 
-    fn create(_: &Context<Self>) -> Self {
-        Self { tabcontainer_link: None, proofs: HashMap::new() }
+    fn create(ctx: &Context<Self>) -> Self {
+        let link = ctx.link().clone();
+        let autosave_interval = Interval::new(autosave::AUTOSAVE_INTERVAL_MS, move || link.send_message(AppMsg::Autosave));
+        // The example proof's tab is the first
+        Self { tabcontainer_link: None, proofs: HashMap::new(), next_tab_id: 1, unsaved: HashSet::new(), beforeunload: None, session: js_sys::Date::now() as u64, autosave_keys: HashMap::new(), restorable: autosave::load_entries(), autosave_error: None, _autosave_interval: autosave_interval, toasts: vec![] }
     }
 
-    fn update(&mut self, _: &Context<Self>, msg: Self::Message) -> bool {
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
         match msg {
             AppMsg::TabbedContainerInit(tabcontainer_link) => {
                 self.tabcontainer_link = Some(tabcontainer_link);
@@ -51,21 +175,142 @@ impl Component for App {
                 false
             }
             AppMsg::CreateTab { name, content } => {
+                self.create_tab(name, content);
+                true
+            }
+            AppMsg::RegisterProof { id, link } => {
+                self.proofs.insert(id, link);
+                false
+            }
+            AppMsg::CloseTab(id) => {
+                if let Some(tabcontainer_link) = &self.tabcontainer_link {
+                    tabcontainer_link.send_message(TabbedContainerMsg::CloseTabWithId(id));
+                }
+                false
+            }
+            AppMsg::TabClosed(id) => {
+                self.proofs.remove(&id);
+                self.set_unsaved(id, false);
+                self.remove_autosave(id);
+                false
+            }
+            AppMsg::ProofDirty { id, dirty } => {
+                self.set_unsaved(id, dirty);
+                false
+            }
+            AppMsg::ProofSaved(id) => {
+                if let Some(link) = self.proofs.get(&id) {
+                    link.send_message(ProofWidgetMsg::MarkSaved);
+                }
+                self.remove_autosave(id);
+                false
+            }
+            AppMsg::Autosave => {
                 if let Some(tabcontainer_link) = &self.tabcontainer_link {
-                    tabcontainer_link.send_message(TabbedContainerMsg::Create { name, content });
+                    let (unsaved, proofs, session, app) = (self.unsaved.clone(), self.proofs.clone(), self.session, ctx.link().clone());
+                    tabcontainer_link.send_message(TabbedContainerMsg::GetTabs(Box::new(move |tabs| {
+                        for (id, name) in tabs.into_iter().filter(|(id, _)| unsaved.contains(id)) {
+                            if let Some(link) = proofs.get(&id) {
+                                let (key, app) = (autosave::autosave_key(session, id, &name), app.clone());
+                                link.send_message(ProofWidgetMsg::CallOnProof(Box::new(move |prf, metadata| match autosave::store_entry(&key, &autosave::autosave_entry(prf, metadata)) {
+                                    Ok(()) => app.send_message(AppMsg::Autosaved { id, key }),
+                                    Err(err) => app.send_message(AppMsg::AutosaveFailed(err)),
+                                })));
+                            }
+                        }
+                    })));
+                }
+                false
+            }
+            AppMsg::Autosaved { id, key } => {
+                // The key changes when the tab is renamed, or was restored from an earlier page
+                if let Some(old_key) = self.autosave_keys.insert(id, key.clone()) {
+                    if old_key != key {
+                        autosave::remove_entry(&old_key);
+                    }
+                }
+                false
+            }
+            AppMsg::AutosaveFailed(err) => {
+                self.autosave_error = Some(err);
+                true
+            }
+            AppMsg::DismissAutosaveError => {
+                self.autosave_error = None;
+                true
+            }
+            AppMsg::RestoreSession => {
+                for (key, name, entry) in std::mem::take(&mut self.restorable) {
+                    match autosave::restore_entry(&entry) {
+                        Ok(data) => {
+                            let app = ctx.link().clone();
+                            let id = self.create_tab(name, Box::new(move |id| proof_tab_content(&app, id, Some(data), true)));
+                            // Keep the earlier page's autosave until this one replaces it
+                            self.autosave_keys.insert(id, key);
+                            self.set_unsaved(id, true);
+                        }
+                        Err(err) => {
+                            self.autosave_error = Some(format!("Couldn't restore \"{name}\": {err}"));
+                            autosave::remove_entry(&key);
+                        }
+                    }
                 }
                 true
             }
-            AppMsg::RegisterProofName { name, link } => {
-                self.proofs.insert(name, link);
+            AppMsg::ShowError(err) => {
+                self.toasts.push(err);
+                true
+            }
+            AppMsg::DismissError(i) => {
+                if i < self.toasts.len() {
+                    self.toasts.remove(i);
+                }
+                true
+            }
+            AppMsg::DropFiles(file_list) => {
+                open_files(ctx.link(), file_list);
+                false
+            }
+            AppMsg::DiscardSession => {
+                for (key, _, _) in std::mem::take(&mut self.restorable) {
+                    autosave::remove_entry(&key);
+                }
+                true
+            }
+            AppMsg::CheckCurrentProof => {
+                if let Some(tabcontainer_link) = &self.tabcontainer_link {
+                    let proofs = self.proofs.clone();
+                    tabcontainer_link.send_message(TabbedContainerMsg::GetCurrentTab(Box::new(move |id, _| {
+                        if let Some(link) = proofs.get(&id) {
+                            link.send_message(ProofWidgetMsg::CheckProof);
+                        }
+                    })));
+                }
+                false
+            }
+            AppMsg::PrintCurrentProof => {
+                if let Some(tabcontainer_link) = &self.tabcontainer_link {
+                    let proofs = self.proofs.clone();
+                    tabcontainer_link.send_message(TabbedContainerMsg::GetCurrentTab(Box::new(move |id, _| {
+                        if let Some(link) = proofs.get(&id) {
+                            link.send_message(ProofWidgetMsg::Print);
+                        }
+                    })));
+                }
+                false
+            }
+            AppMsg::SettingsChanged => {
+                for link in self.proofs.values() {
+                    link.send_message(ProofWidgetMsg::SettingsChanged);
+                }
                 false
             }
             AppMsg::GetProofFromCurrentTab(f) => {
                 if let Some(tabcontainer_link) = &self.tabcontainer_link {
                     let proofs = self.proofs.clone();
-                    tabcontainer_link.send_message(TabbedContainerMsg::GetCurrent(Box::new(move |_, name| {
-                        if let Some(link) = proofs.get(&*name) {
-                            link.send_message(ProofWidgetMsg::CallOnProof(Box::new(move |prf| f(name, prf))));
+                    tabcontainer_link.send_message(TabbedContainerMsg::GetCurrentTab(Box::new(move |id, name| {
+                        if let Some(link) = proofs.get(&id) {
+                            link.send_message(ProofWidgetMsg::CallOnProof(Box::new(move |prf, metadata| f(id, name, prf, metadata))));
                         }
                     })));
                 }
@@ -79,18 +324,147 @@ impl Component for App {
     }
 
     fn view(&self, ctx: &Context<Self>) -> Html {
-        let resolution_fname: String = "resolution_example.bram".into();
-        let resolution_fname_ = resolution_fname.clone();
+        let resolution_id = TabId(0);
+        let resolution = proof_tab_content(ctx.link(), resolution_id, Some(include_bytes!("../../../example-proofs/resolution_example.bram").to_vec()), false);
         let tabview = html! {
-            <TabbedContainer tab_ids={ vec![resolution_fname, "Parser demo".into()] } oncreate={ ctx.link().callback(AppMsg::TabbedContainerInit) }>
-                <ProofWidget verbose=true data={ Some(include_bytes!("../../../example-proofs/resolution_example.bram").to_vec()) } oncreate={ ctx.link().callback(move |link| AppMsg::RegisterProofName { name: resolution_fname_.clone(), link }) } />
-            </TabbedContainer>
+            <TabbedContainer tabs={ vec![(resolution_id, "resolution_example.bram".into(), resolution)] } oncreate={ ctx.link().callback(AppMsg::TabbedContainerInit) } onclose={ ctx.link().callback(AppMsg::TabClosed) } />
         };
+        // Files can be dropped anywhere on the page, which has to accept
+        // dragging them over it for that
+        let ondragover = Callback::from(|e: DragEvent| e.prevent_default());
+        let ondrop = ctx.link().batch_callback(|e: DragEvent| {
+            e.prevent_default();
+            e.data_transfer().and_then(|data_transfer| data_transfer.files()).map(AppMsg::DropFiles)
+        });
         html! {
-            <div>
+            <div { ondragover } { ondrop }>
                 <NavBarWidget parent={ ctx.link().clone() } oncreate={ ctx.link().callback(AppMsg::NavBarInit) } />
+                { self.render_autosave_notices(ctx) }
+                { self.render_toasts(ctx) }
                 { tabview }
             </div>
         }
     }
 }
+
+impl App {
+    /// Render the offer to restore proofs autosaved by earlier pages, and why
+    /// autosaving failed, if it did
+    fn render_autosave_notices(&self, ctx: &Context<Self>) -> Html {
+        let restore = if self.restorable.is_empty() {
+            html! {}
+        } else {
+            let count = match self.restorable.len() {
+                1 => "1 proof".to_string(),
+                n => format!("{n} proofs"),
+            };
+            html! {
+                <div class="alert alert-info m-2" role="alert">
+                    { format!("Restore previous session? {count} with unsaved changes were autosaved.") }
+                    <button type="button" class="btn btn-sm btn-primary ml-2" onclick={ ctx.link().callback(|_| AppMsg::RestoreSession) }> { "Restore" } </button>
+                    <button type="button" class="btn btn-sm btn-outline-secondary ml-2" onclick={ ctx.link().callback(|_| AppMsg::DiscardSession) }> { "Discard" } </button>
+                </div>
+            }
+        };
+        let error = match &self.autosave_error {
+            Some(err) => html! {
+                <div class="alert alert-warning m-2" role="alert">
+                    <button type="button" class="close" aria-label="Close" onclick={ ctx.link().callback(|_| AppMsg::DismissAutosaveError) }>
+                        <span aria-hidden="true"> { "×" } </span>
+                    </button>
+                    { err }
+                </div>
+            },
+            None => html! {},
+        };
+        html! {
+            <>
+                { restore }
+                { error }
+            </>
+        }
+    }
+
+    /// Render the errors that haven't been dismissed, in the corner of the page
+    fn render_toasts(&self, ctx: &Context<Self>) -> Html {
+        let toasts = self
+            .toasts
+            .iter()
+            .enumerate()
+            .map(|(i, err)| {
+                html! {
+                    <div class="toast show" role="alert" aria-live="assertive" aria-atomic="true">
+                        <div class="toast-header">
+                            <strong class="mr-auto"> { "Error" } </strong>
+                            <button type="button" class="ml-2 mb-1 close" aria-label="Close" onclick={ ctx.link().callback(move |_| AppMsg::DismissError(i)) }>
+                                <span aria-hidden="true"> { "×" } </span>
+                            </button>
+                        </div>
+                        <div class="toast-body"> { err } </div>
+                    </div>
+                }
+            })
+            .collect::<Html>();
+        html! {
+            <div style="position: fixed; top: 1rem; right: 1rem; z-index: 1050">
+                { toasts }
+            </div>
+        }
+    }
+}
+
+#[cfg(all(test, target_arch = "wasm32"))]
+mod wasm_tests {
+    use super::*;
+
+    use aris::expr::Expr;
+    use aris::proofs::Proof;
+
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    use wasm_bindgen_test::wasm_bindgen_test;
+    use wasm_bindgen_test::wasm_bindgen_test_configure;
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    /// The id and name of the current tab, and the first premise of its proof
+    async fn current_proof(app: &Scope<App>) -> Option<(TabId, String, Option<Expr>)> {
+        let found = Rc::new(RefCell::new(None));
+        let sink = found.clone();
+        app.send_message(AppMsg::GetProofFromCurrentTab(Box::new(move |id, name, prf, _| {
+            *sink.borrow_mut() = Some((id, name, prf.lookup_premise(&prf.premises()[0])));
+        })));
+        yew::platform::time::sleep(std::time::Duration::ZERO).await;
+        found.take()
+    }
+
+    #[wasm_bindgen_test]
+    async fn test_same_names() {
+        // The proof widgets set up Bootstrap submenus after rendering, which
+        // aren't on the test page
+        js_sys::eval("window.$ = () => ({ submenupicker() {} })").unwrap_throw();
+        let root = gloo::utils::document().create_element("div").unwrap_throw();
+        gloo::utils::body().append_child(&root).unwrap_throw();
+        let app = yew::Renderer::<App>::with_root(root).render();
+        yew::platform::time::sleep(std::time::Duration::ZERO).await;
+
+        // Open two proofs into tabs with the same name, as from opening a file twice
+        for data in [&include_bytes!("../../../example-proofs/locked_premises.bram")[..], &include_bytes!("../../../example-proofs/propositional_logic_arguments_for_proofs_ii_problem_10.bram")[..]] {
+            let scope = (*app).clone();
+            app.send_message(AppMsg::CreateTab { name: "assignment.bram".into(), content: Box::new(move |id| proof_tab_content(&scope, id, Some(data.to_vec()), false)) });
+            yew::platform::time::sleep(std::time::Duration::ZERO).await;
+        }
+
+        // Each tab's proof is the one that was opened into it, the later one
+        // being in front
+        let (second, name, premise) = current_proof(&app).await.unwrap_throw();
+        assert_eq!((&*name, premise), ("assignment.bram", aris::parser::parse("¬A ∨ B")));
+        let tabcontainer_link = app.get_component().unwrap_throw().tabcontainer_link.clone().unwrap_throw();
+        tabcontainer_link.send_message(TabbedContainerMsg::Switch(1));
+        yew::platform::time::sleep(std::time::Duration::ZERO).await;
+        let (first, name, premise) = current_proof(&app).await.unwrap_throw();
+        assert_eq!((&*name, premise), ("assignment.bram", aris::parser::parse("P -> Q")));
+        assert_ne!(first, second);
+    }
+}
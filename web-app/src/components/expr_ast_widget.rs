@@ -1,13 +1,180 @@
 use crate::components::expr_entry::ExprEntry;
+use crate::components::truth_table_widget::render_truth_table;
 
 use aris::expr::Expr;
+use aris::expr::Op;
+use aris::expr::QuantKind;
+use aris::expr::Symbols;
+
+use std::ops::Range;
 
 use yew::prelude::*;
 
+/// Width of a character cell of the tree's layout, in pixels
+const CELL_PX: usize = 10;
+
+/// Height of a level of the tree, in pixels
+const LEVEL_PX: usize = 50;
+
+/// Height of a node's box, in pixels
+const NODE_PX: usize = 26;
+
+/// A node of an expression's tree, laid out in character cells. Nodes are in
+/// preorder, so the root is first and each node comes before its children.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TreeNode {
+    /// The connective, quantifier, or atom the node is
+    pub label: String,
+    /// How many nodes are above it
+    pub depth: usize,
+    /// Where its subtree starts, in cells from the left of the tree
+    pub left: usize,
+    /// How many cells wide its subtree is
+    pub width: usize,
+    /// The index of the node above it
+    pub parent: Option<usize>,
+}
+
+impl TreeNode {
+    /// Where the middle of the node is, in cells from the left of the tree
+    fn center(&self) -> f64 {
+        self.left as f64 + self.width as f64 / 2.0
+    }
+}
+
+fn op_symbol(op: Op, symbols: Symbols) -> String {
+    match (symbols, op) {
+        (Symbols::Unicode, op) => op.to_string(),
+        (Symbols::Ascii, Op::And) => "&".into(),
+        (Symbols::Ascii, Op::Or) => "|".into(),
+        (Symbols::Ascii, Op::Bicon) => "<->".into(),
+        (Symbols::Ascii, Op::Equiv) => "===".into(),
+        (Symbols::Ascii, Op::Add) => "+".into(),
+        (Symbols::Ascii, Op::Mult) => "*".into(),
+    }
+}
+
+fn quant_symbol(kind: QuantKind, symbols: Symbols) -> String {
+    match (symbols, kind) {
+        (Symbols::Unicode, kind) => kind.to_string(),
+        (Symbols::Ascii, QuantKind::Forall) => "forall".into(),
+        (Symbols::Ascii, QuantKind::Exists) => "exists".into(),
+    }
+}
+
+/// The label of the node for `expr` and the subexpressions below it. A
+/// function application is labelled with the function, above its arguments.
+fn node_parts(expr: &Expr, symbols: Symbols) -> (String, Vec<&Expr>) {
+    match expr {
+        Expr::Contra | Expr::Taut | Expr::Var { .. } => (expr.display_with(symbols), vec![]),
+        Expr::Apply { func, args } => (format!("{}()", func.display_with(symbols)), args.iter().collect()),
+        Expr::Not { operand } => (if symbols == Symbols::Ascii { "~" } else { "¬" }.into(), vec![operand]),
+        Expr::Impl { left, right } => (if symbols == Symbols::Ascii { "->" } else { "→" }.into(), vec![left, right]),
+        Expr::Assoc { op, exprs } => (op_symbol(*op, symbols), exprs.iter().collect()),
+        Expr::Quant { kind, name, body } => (format!("{} {name}", quant_symbol(*kind, symbols)), vec![body]),
+    }
+}
+
+/// Lay out the tree of `expr`, with each node's children side by side below
+/// it. A node's subtree is as wide as its children's together, or as its label
+/// with a cell of space on each side if that's wider, so no two nodes overlap.
+pub fn layout_tree(expr: &Expr, symbols: Symbols) -> Vec<TreeNode> {
+    fn aux(expr: &Expr, symbols: Symbols, depth: usize, left: usize, parent: Option<usize>, out: &mut Vec<TreeNode>) -> usize {
+        let (label, children) = node_parts(expr, symbols);
+        let index = out.len();
+        out.push(TreeNode { label, depth, left, width: 0, parent });
+        let mut children_width = 0;
+        for child in children {
+            children_width += aux(child, symbols, depth + 1, left + children_width, Some(index), out);
+        }
+        let width = children_width.max(out[index].label.chars().count() + 2);
+        // Children narrower than the label are centered under it
+        let shift = (width - children_width) / 2;
+        if shift > 0 {
+            for node in &mut out[index + 1..] {
+                node.left += shift;
+            }
+        }
+        out[index].width = width;
+        width
+    }
+    let mut out = vec![];
+    aux(expr, symbols, 0, 0, None, &mut out);
+    out
+}
+
+/// Format `expr` as `Expr::display_with` does, along with the span of each
+/// node of its tree in the text, in the order of `layout_tree`
+pub fn display_spans(expr: &Expr, symbols: Symbols) -> (String, Vec<Range<usize>>) {
+    fn aux(expr: &Expr, symbols: Symbols, out: &mut String, spans: &mut Vec<Range<usize>>) {
+        let index = spans.len();
+        let start = out.len();
+        spans.push(start..start);
+        let ascii = symbols == Symbols::Ascii;
+        match expr {
+            Expr::Contra | Expr::Taut | Expr::Var { .. } => out.push_str(&expr.display_with(symbols)),
+            Expr::Apply { func, args } => {
+                out.push_str(&func.display_with(symbols));
+                out.push('(');
+                aux_separated(args, ", ", symbols, out, spans);
+                out.push(')');
+            }
+            Expr::Not { operand } => {
+                out.push_str(if ascii { "~" } else { "¬" });
+                aux(operand, symbols, out, spans);
+            }
+            Expr::Impl { left, right } => {
+                out.push('(');
+                aux(left, symbols, out, spans);
+                out.push_str(if ascii { " -> " } else { " → " });
+                aux(right, symbols, out, spans);
+                out.push(')');
+            }
+            Expr::Assoc { op, exprs } => {
+                out.push('(');
+                aux_separated(exprs, &format!(" {} ", op_symbol(*op, symbols)), symbols, out, spans);
+                out.push(')');
+            }
+            Expr::Quant { kind, name, body } => {
+                out.push_str(&format!("({} {name}{}", quant_symbol(*kind, symbols), if ascii { " " } else { ", " }));
+                aux(body, symbols, out, spans);
+                out.push(')');
+            }
+        }
+        spans[index] = start..out.len();
+    }
+    fn aux_separated(exprs: &[Expr], separator: &str, symbols: Symbols, out: &mut String, spans: &mut Vec<Range<usize>>) {
+        for (i, expr) in exprs.iter().enumerate() {
+            if i > 0 {
+                out.push_str(separator);
+            }
+            aux(expr, symbols, out, spans);
+        }
+    }
+    let mut out = String::new();
+    let mut spans = vec![];
+    aux(expr, symbols, &mut out, &mut spans);
+    (out, spans)
+}
+
 pub struct ExprAstWidget {
     current_input: String,
-    last_good_parse: String,
+    /// The last input that parsed, which is shown with the symbols setting
+    last_good_parse: Option<Expr>,
     current_expr: Option<Expr>,
+    /// The node of the tree under the mouse, whose text is highlighted
+    hovered: Option<usize>,
+    /// Whether the expression's debug output is shown under its tree
+    show_debug: bool,
+    /// Whether the expression's truth table is shown under its tree
+    show_truth_table: bool,
+}
+
+pub enum ExprAstWidgetMsg {
+    Input(String),
+    Hover(Option<usize>),
+    ToggleDebug,
+    ToggleTruthTable,
 }
 
 #[derive(Clone, Properties, PartialEq)]
@@ -15,20 +182,71 @@ pub struct ExprAstWidgetProps {
     pub initial_contents: String,
 }
 
+impl ExprAstWidget {
+    /// Render the tree of `expr` as an SVG, which scrolls sideways if it's
+    /// wider than the page
+    fn render_tree(&self, ctx: &Context<Self>, expr: &Expr, symbols: Symbols) -> Html {
+        let nodes = layout_tree(expr, symbols);
+        let depth = nodes.iter().map(|node| node.depth).max().unwrap_or_default();
+        let (width, height) = (nodes[0].width * CELL_PX, depth * LEVEL_PX + NODE_PX + 2);
+        let x = |node: &TreeNode| node.center() * CELL_PX as f64;
+        let y = |node: &TreeNode| (node.depth * LEVEL_PX + NODE_PX / 2 + 1) as f64;
+        let edges = nodes
+            .iter()
+            .filter_map(|node| {
+                let parent = &nodes[node.parent?];
+                Some(html! { <line x1={ x(parent).to_string() } y1={ y(parent).to_string() } x2={ x(node).to_string() } y2={ y(node).to_string() } class="ast-edge" /> })
+            })
+            .collect::<Html>();
+        let boxes = nodes
+            .iter()
+            .enumerate()
+            .map(|(i, node)| {
+                let box_width = (node.label.chars().count() + 1) * CELL_PX;
+                let class = if self.hovered == Some(i) { "ast-node hovered" } else { "ast-node" };
+                let onmouseenter = ctx.link().callback(move |_| ExprAstWidgetMsg::Hover(Some(i)));
+                let onmouseleave = ctx.link().callback(|_| ExprAstWidgetMsg::Hover(None));
+                html! {
+                    <g { class } { onmouseenter } { onmouseleave }>
+                        <rect x={ (x(node) - box_width as f64 / 2.0).to_string() } y={ (y(node) - NODE_PX as f64 / 2.0).to_string() } width={ box_width.to_string() } height={ NODE_PX.to_string() } rx="4" />
+                        <text x={ x(node).to_string() } y={ y(node).to_string() } text-anchor="middle" dominant-baseline="central"> { &node.label } </text>
+                    </g>
+                }
+            })
+            .collect::<Html>();
+        html! {
+            <div class="ast-tree">
+                <svg width={ width.to_string() } height={ height.to_string() }>
+                    { edges }
+                    { boxes }
+                </svg>
+            </div>
+        }
+    }
+}
+
 impl Component for ExprAstWidget {
-    type Message = String;
+    type Message = ExprAstWidgetMsg;
     type Properties = ExprAstWidgetProps;
     fn create(ctx: &Context<Self>) -> Self {
-        let mut ret = Self { current_expr: None, current_input: ctx.props().initial_contents.clone(), last_good_parse: "".into() };
-        Component::update(&mut ret, ctx, ctx.props().initial_contents.clone());
+        let mut ret = Self { current_expr: None, current_input: ctx.props().initial_contents.clone(), last_good_parse: None, hovered: None, show_debug: false, show_truth_table: false };
+        Component::update(&mut ret, ctx, ExprAstWidgetMsg::Input(ctx.props().initial_contents.clone()));
         ret
     }
     fn update(&mut self, _: &Context<Self>, msg: Self::Message) -> bool {
         use aris::parser::parse;
-        self.current_input = msg.clone();
-        self.current_expr = parse(&msg);
-        if let Some(expr) = &self.current_expr {
-            self.last_good_parse = format!("{expr}");
+        match msg {
+            ExprAstWidgetMsg::Input(input) => {
+                self.current_expr = parse(&input);
+                self.current_input = input;
+                if self.current_expr.is_some() {
+                    self.last_good_parse = self.current_expr.clone();
+                }
+                self.hovered = None;
+            }
+            ExprAstWidgetMsg::Hover(node) => self.hovered = node,
+            ExprAstWidgetMsg::ToggleDebug => self.show_debug = !self.show_debug,
+            ExprAstWidgetMsg::ToggleTruthTable => self.show_truth_table = !self.show_truth_table,
         }
         true
     }
@@ -36,18 +254,23 @@ impl Component for ExprAstWidget {
         false
     }
     fn view(&self, ctx: &Context<Self>) -> Html {
-        // Convert expression to debug string
-        let expr_debug = self.current_expr.as_ref().map(|e| format!("{e:#?}"));
+        let symbols = crate::settings::symbols();
 
-        // Convert debug expression to HTML or parse error
-        let expr_debug = match expr_debug {
-            Some(s) => {
-                html! {
-                    <div class="card">
-                        <pre> { s } </pre>
-                    </div>
+        // The last good parse, with the text of the node under the mouse highlighted
+        let last_good_parse = match &self.last_good_parse {
+            Some(expr) => {
+                let (text, spans) = display_spans(expr, symbols);
+                match self.hovered.and_then(|i| spans.get(i)) {
+                    Some(span) => html! { <>{ &text[..span.start] }<mark>{ &text[span.clone()] }</mark>{ &text[span.end..] }</> },
+                    None => html! { { text } },
                 }
             }
+            None => html! {},
+        };
+
+        // Tree of the expression, or the parse error
+        let tree = match &self.current_expr {
+            Some(expr) => self.render_tree(ctx, expr, symbols),
             None => {
                 html! {
                     <div class="alert alert-danger"> { "Parse error" } </div>
@@ -55,17 +278,87 @@ impl Component for ExprAstWidget {
             }
         };
 
+        let expr_debug = match (&self.current_expr, self.show_debug) {
+            (Some(expr), true) => html! {
+                <div class="card mt-2">
+                    <pre> { format!("{expr:#?}") } </pre>
+                </div>
+            },
+            _ => html! {},
+        };
+
+        let truth_table = match (&self.current_expr, self.show_truth_table) {
+            (Some(expr), true) => html! { <div class="mt-2"> { render_truth_table(expr, symbols) } </div> },
+            _ => html! {},
+        };
+
         html! {
             <div class="alert alert-primary m-4">
                 <h2> { "Enter Expression:" } </h2>
                 <ExprEntry
-                    oninput={ ctx.link().callback(|value| value) }
+                    oninput={ ctx.link().callback(ExprAstWidgetMsg::Input) }
                     init_value={ self.current_input.clone() }
                     id=""/>
                 <hr />
-                <h5> { &self.last_good_parse } </h5>
+                <h5> { last_good_parse } </h5>
+                { tree }
+                <button type="button" class="btn btn-sm btn-outline-secondary mt-2 mr-2" onclick={ ctx.link().callback(|_| ExprAstWidgetMsg::ToggleTruthTable) }>
+                    { if self.show_truth_table { "Hide truth table" } else { "Show truth table" } }
+                </button>
+                <button type="button" class="btn btn-sm btn-outline-secondary mt-2" onclick={ ctx.link().callback(|_| ExprAstWidgetMsg::ToggleDebug) }>
+                    { if self.show_debug { "Hide debug output" } else { "Show debug output" } }
+                </button>
+                { truth_table }
                 { expr_debug }
             </div>
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use aris::parser::parse_unwrap as p;
+
+    #[test]
+    fn test_layout_tree() {
+        let nodes = layout_tree(&p("A & B -> ~C"), Symbols::Unicode);
+        let summary = nodes.iter().map(|node| (node.label.as_str(), node.depth, node.left, node.width, node.parent)).collect::<Vec<_>>();
+        // Each leaf is its label with a cell on each side, and each connective
+        // is as wide as its children
+        assert_eq!(summary, vec![("→", 0, 0, 9, None), ("∧", 1, 0, 6, Some(0)), ("A", 2, 0, 3, Some(1)), ("B", 2, 3, 3, Some(1)), ("¬", 1, 6, 3, Some(0)), ("C", 2, 6, 3, Some(4))]);
+        assert_eq!(nodes[0].center(), 4.5);
+
+        // A label wider than its children has them centered under it
+        let nodes = layout_tree(&p("forall x P"), Symbols::Ascii);
+        let summary = nodes.iter().map(|node| (node.label.as_str(), node.left, node.width)).collect::<Vec<_>>();
+        assert_eq!(summary, vec![("forall x", 0, 10), ("P", 3, 3)]);
+
+        // Function applications are labelled with the function
+        let nodes = layout_tree(&p("f(x, y)"), Symbols::Unicode);
+        assert_eq!(nodes.iter().map(|node| node.label.as_str()).collect::<Vec<_>>(), vec!["f()", "x", "y"]);
+        assert_eq!(nodes[0].width, 6);
+    }
+
+    #[test]
+    fn test_display_spans() {
+        for formula in ["A & B -> ~C", "forall A, ((exists B, A -> B) & C & f(x, y | z)) <-> Q <-> R", "~~_|_ | ^|^", "((a + b) * c) === d", "forall x x"] {
+            let expr = p(formula);
+            for symbols in [Symbols::Unicode, Symbols::Ascii] {
+                let (text, spans) = display_spans(&expr, symbols);
+                assert_eq!(text, expr.display_with(symbols));
+                let nodes = layout_tree(&expr, symbols);
+                assert_eq!(spans.len(), nodes.len());
+                // Each node's text is inside its parent's
+                for (node, span) in nodes.iter().zip(&spans) {
+                    if let Some(parent) = node.parent {
+                        assert!(spans[parent].start <= span.start && span.end <= spans[parent].end);
+                    }
+                }
+            }
+        }
+        let (text, spans) = display_spans(&p("A & B -> ~C"), Symbols::Ascii);
+        assert_eq!(spans.iter().map(|span| &text[span.clone()]).collect::<Vec<_>>(), vec!["((A & B) -> ~C)", "(A & B)", "A", "B", "~C", "C"]);
+    }
+}
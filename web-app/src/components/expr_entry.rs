@@ -1,3 +1,6 @@
+use crate::components::nav_bar::expand_macros;
+use crate::symbol_palette;
+
 use yew::prelude::*;
 
 /// A text field for entering expressions
@@ -13,6 +16,12 @@ pub enum ExprEntryMsg {
 
     /// Text field was focused
     OnFocus,
+
+    /// Text field lost focus
+    OnBlur,
+
+    /// A key was pressed in the text field
+    OnKeydown(KeyboardEvent),
 }
 
 /// Properties for `ExprEntry`
@@ -26,6 +35,14 @@ pub struct ExprEntryProps {
     #[prop_or_default]
     pub onfocus: Option<Callback<()>>,
 
+    /// Callback to call when text field loses focus
+    #[prop_or_default]
+    pub onblur: Option<Callback<()>>,
+
+    /// Callback to call when a key is pressed in the text field
+    #[prop_or_default]
+    pub onkeydown: Option<Callback<KeyboardEvent>>,
+
     /// Whether the text field should be focused
     ///
     /// ## Values:
@@ -38,8 +55,17 @@ pub struct ExprEntryProps {
     /// Initial text in text field when it is loaded
     pub init_value: String,
 
+    /// Whether the text field is read-only, such as for a locked line
+    #[prop_or_default]
+    pub disabled: bool,
+
     /// An ID to use for our strings
     pub id: String,
+
+    /// Whether the symbol palette inserts into this text field after it loses
+    /// focus, if no other text field has it, as it does for proof lines
+    #[prop_or_default]
+    pub palette_fallback: bool,
 }
 
 impl Component for ExprEntry {
@@ -60,6 +86,22 @@ impl Component for ExprEntry {
                 }
                 false
             }
+            ExprEntryMsg::OnBlur => {
+                if ctx.props().palette_fallback {
+                    let cursor_pos = self.input_element().selection_start().expect("failed getting selection start").unwrap_or_default() as usize;
+                    symbol_palette::remember_line_input(&ctx.props().id, cursor_pos);
+                }
+                if let Some(onblur) = &ctx.props().onblur {
+                    onblur.emit(())
+                }
+                false
+            }
+            ExprEntryMsg::OnKeydown(key_event) => {
+                if let Some(onkeydown) = &ctx.props().onkeydown {
+                    onkeydown.emit(key_event)
+                }
+                false
+            }
         }
     }
     fn changed(&mut self, _: &Context<Self>, _: &Self::Properties) -> bool {
@@ -74,6 +116,9 @@ impl Component for ExprEntry {
                 class="form-control text-input-custom"
                 oninput={ ctx.link().callback(|_| ExprEntryMsg::OnEdit) }
                 onfocus={ ctx.link().callback(|_| ExprEntryMsg::OnFocus) }
+                onblur={ ctx.link().callback(|_| ExprEntryMsg::OnBlur) }
+                onkeydown={ ctx.link().callback(ExprEntryMsg::OnKeydown) }
+                disabled={ ctx.props().disabled }
                 value={ ctx.props().init_value.clone() } />
         }
     }
@@ -100,38 +145,30 @@ impl ExprEntry {
     }
 
     /// Handle an edit of the expression text field by expanding macros with
-    /// `aris::macros::expand()`. To preserve the cursor position, the strings
-    /// to the left and right of the cursor are expanded separately.
+    /// `aris::macros::expand_at()`, unless that's turned off in the nav bar,
+    /// keeping the cursor after the text it was after.
     fn handle_edit(&self, ctx: &Context<Self>) {
         let input_elem = self.input_element();
-
-        // Get cursor position in text field
-        let cursor_pos = input_elem.selection_start().expect("failed getting selection start").unwrap_or_default() as usize;
-
-        // Get text to the left and right of cursor position
-        //
-        // NOTE: The cursor position is measured in characters, not bytes, so
-        // the `String` must be converted to `Vec<char>`.
-        let value = input_elem.value().chars().collect::<Vec<char>>();
-        let (left, right) = value.split_at(cursor_pos);
-
-        // Convert left and right text back into regular `Strings` and expand
-        // macros
-        let left = left.iter().collect::<String>();
-        let left = aris::macros::expand(&left);
-        let right = right.iter().collect::<String>();
-        let right = aris::macros::expand(&right);
-
-        // Compute new cursor position
-        let cursor_pos = left.chars().count() as u32;
-
-        // Update text field value
-        let value = [left, right].concat();
-        input_elem.set_value(&value);
-
-        // Update cursor position
-        input_elem.set_selection_start(Some(cursor_pos)).expect("failed setting selection start");
-        input_elem.set_selection_end(Some(cursor_pos)).expect("failed setting selection end");
+        let mut value = input_elem.value();
+
+        if expand_macros() {
+            // Get cursor position in text field
+            //
+            // NOTE: The cursor position is measured in characters, not bytes,
+            // which is what `expand_at()` takes.
+            let cursor_pos = input_elem.selection_start().expect("failed getting selection start").unwrap_or_default() as usize;
+            let (expanded, cursor_pos) = aris::macros::expand_at(&value, cursor_pos);
+
+            // Only update the text field if a macro was expanded, since
+            // setting its value moves the cursor
+            if expanded != value {
+                input_elem.set_value(&expanded);
+                let cursor_pos = cursor_pos as u32;
+                input_elem.set_selection_start(Some(cursor_pos)).expect("failed setting selection start");
+                input_elem.set_selection_end(Some(cursor_pos)).expect("failed setting selection end");
+                value = expanded;
+            }
+        }
 
         ctx.props().oninput.emit(value);
     }
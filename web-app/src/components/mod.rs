@@ -2,5 +2,7 @@ pub mod app;
 mod expr_ast_widget;
 mod expr_entry;
 mod nav_bar;
+mod new_proof_dialog;
 mod proof_widget;
 mod tabbed_container;
+mod truth_table_widget;
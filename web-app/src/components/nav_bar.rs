@@ -1,9 +1,20 @@
+use crate::components::app::proof_tab_content;
 use crate::components::app::App;
 use crate::components::app::AppMsg;
 use crate::components::expr_ast_widget::ExprAstWidget;
-use crate::components::proof_widget::ProofWidget;
+use crate::components::new_proof_dialog::NewProofDialog;
+use crate::components::tabbed_container::TabContent;
+use crate::components::tabbed_container::TabId;
+use crate::components::truth_table_widget::TruthTableWidget;
+use crate::settings;
+use crate::symbol_palette;
+use crate::util::xml_from_json;
+use crate::util::P;
+
+use aris::expr::Symbols;
 
 use derivative::Derivative;
+use gloo::events::EventListener;
 use gloo::timers::callback::Timeout;
 use wasm_bindgen::UnwrapThrowExt;
 use wasm_bindgen::{closure::Closure, JsCast, JsValue};
@@ -14,54 +25,110 @@ use yew::prelude::*;
 use yew_octicons::Icon;
 use yew_octicons::IconKind;
 
-pub struct FileOpenHelper {
-    file_open_closure: Closure<dyn FnMut(JsValue)>,
-    filename_tx: std::sync::mpsc::Sender<(String, web_sys::FileReader)>,
+/// The tabs to open for a `.bram` file, with their names and contents, which
+/// are named by the titles of their proofs if they have them. A workbook of
+/// several proofs is opened as a tab for each one, and a proof that can only
+/// be partly read as one tab, which shows what couldn't be.
+fn split_workbook(fname: String, contents: String) -> Result<Vec<(String, Vec<u8>)>, String> {
+    use aris::proofs::xml_interop::{proof_from_xml_lenient, workbook_from_xml, xml_from_proof_and_metadata};
+    match workbook_from_xml::<P, _>(contents.as_bytes()) {
+        Ok(proofs) if proofs.len() > 1 => Ok(proofs
+            .iter()
+            .enumerate()
+            .map(|(i, (prf, metadata, _))| {
+                let mut data = vec![];
+                xml_from_proof_and_metadata(prf, metadata, &mut data).expect("xml_from_proof_and_metadata failed");
+                (metadata.title.clone().unwrap_or_else(|| format!("{fname} ({})", i + 1)), data)
+            })
+            .collect()),
+        Ok(proofs) => Ok(vec![(proofs.into_iter().next().and_then(|(_, metadata, _)| metadata.title).unwrap_or(fname), contents.into_bytes())]),
+        Err(err) => match proof_from_xml_lenient::<P, _>(contents.as_bytes()) {
+            (Some(_), _) => Ok(vec![(fname, contents.into_bytes())]),
+            // The last error is the one the file couldn't be read past
+            (None, errors) => Err(format!("Couldn't open {fname}: {}", errors.last().unwrap_or(&err))),
+        },
+    }
 }
 
-impl FileOpenHelper {
-    fn new(parent: Scope<App>) -> Self {
-        let (filename_tx, filename_rx) = std::sync::mpsc::channel::<(String, web_sys::FileReader)>();
-        let file_open_closure = Closure::wrap(Box::new(move |_| {
-            if let Ok((fname, reader)) = filename_rx.recv() {
-                if let Ok(contents) = reader.result() {
-                    if let Some(contents) = contents.as_string() {
-                        let fname_ = fname.clone();
-                        let oncreate = parent.callback(move |link| AppMsg::RegisterProofName { name: fname_.clone(), link });
-                        parent.send_message(AppMsg::CreateTab { name: fname, content: html! { <ProofWidget verbose=true data={ Some(contents.into_bytes()) } oncreate={ oncreate } /> } });
-                    }
+/// The tabs to open for a file that was read, or why it can't be opened, if it
+/// couldn't be read or isn't a proof. Proofs are `.bram` files, or `.json`
+/// ones, which are converted to open them.
+fn tabs_for_file(fname: String, contents: Option<String>) -> Result<Vec<(String, Vec<u8>)>, String> {
+    let contents = contents.ok_or_else(|| format!("Couldn't read {fname}"))?;
+    let extension = std::path::Path::new(&fname).extension().and_then(|extension| extension.to_str()).map(str::to_ascii_lowercase);
+    match extension.as_deref() {
+        Some("bram") => split_workbook(fname, contents),
+        Some("json") => match xml_from_json(&contents) {
+            Ok(data) => Ok(vec![(fname, data)]),
+            Err(err) => Err(format!("Couldn't open {fname}: {err}")),
+        },
+        _ => Err(format!("Couldn't open {fname}, since it isn't a proof, which is a .bram or .json file")),
+    }
+}
+
+/// Read files, opening each proof in them in a tab of `parent`, and telling it
+/// about any that can't be opened
+pub fn open_files(parent: &Scope<App>, file_list: web_sys::FileList) {
+    for file in (0..file_list.length()).filter_map(|i| file_list.get(i)) {
+        // Each reader has its own callback, so results are matched to their
+        // files however the reads finish
+        let reader = web_sys::FileReader::new().expect("FileReader");
+        let (parent, reader_, fname) = (parent.clone(), reader.clone(), file.name());
+        let onload = Closure::once_into_js(move || match tabs_for_file(fname, reader_.result().ok().and_then(|contents| contents.as_string())) {
+            Ok(tabs) => {
+                for (name, data) in tabs {
+                    let parent_ = parent.clone();
+                    let content = Box::new(move |id| proof_tab_content(&parent_, id, Some(data), false));
+                    parent.send_message(AppMsg::CreateTab { name, content });
                 }
             }
-        }) as Box<dyn FnMut(JsValue)>);
-        Self { file_open_closure, filename_tx }
+            Err(err) => parent.send_message(AppMsg::ShowError(err)),
+        });
+        reader.set_onload(Some(onload.unchecked_ref()));
+        reader.read_as_text(&file).expect("FileReader::read_as_text");
     }
-    fn fileopen(&mut self, file_list: web_sys::FileList) -> bool {
-        if let Some(file) = file_list.get(0) {
-            // MDN (https://developer.mozilla.org/en-US/docs/Web/API/Blob/text) and web-sys (https://docs.rs/web-sys/0.3.36/web_sys/struct.Blob.html#method.text)
-            // both document "Blob.text()" as being a thing, but both chrome and firefox say that "getObject(...).text is not a function"
-            /*let _ = self.filename_tx.send(file.name());
-            file.dyn_into::<web_sys::Blob>().expect("dyn_into::<web_sys::Blob> failed").text().then(&self.file_open_closure);*/
-            let reader = web_sys::FileReader::new().expect("FileReader");
-            reader.set_onload(Some(self.file_open_closure.as_ref().unchecked_ref()));
-            reader.read_as_text(&file).expect("FileReader::read_as_text");
-            let _ = self.filename_tx.send((file.name(), reader));
-        }
-        true
+}
+
+/// The name to download a proof under, which is the name of its tab, as a
+/// `.bram` file
+fn save_name(tab_name: &str) -> String {
+    if tab_name.ends_with(".bram") {
+        tab_name.into()
+    } else {
+        format!("{tab_name}.bram")
     }
 }
 
 pub struct NavBarWidget {
     node_ref: NodeRef,
     next_tab_idx: usize,
-    file_open_helper: FileOpenHelper,
+    /// Listener for the OS switching between light and dark mode
+    _system_theme_listener: Option<EventListener>,
 }
 
 pub enum NavBarMsg {
     FileNew,
+    /// Open a proof made in the new proof dialog, in a tab named `name`, or
+    /// named like a blank proof if that's empty
+    FileNewFromSequent {
+        name: String,
+        data: Vec<u8>,
+    },
     FileOpen(web_sys::FileList),
     FileSave,
+    CheckProof,
+    /// Print the current tab's proof
+    Print,
     NewExprTree,
+    NewTruthTable,
     ToggleTheme,
+    /// The theme followed the OS's
+    SystemThemeChanged,
+    ToggleMacros,
+    /// Switch formulas between being displayed with Unicode and ASCII symbols
+    ToggleSymbols,
+    /// Show or hide the log of what was done to each proof
+    ToggleDebugLog,
     Nop,
 }
 #[derive(Properties, Clone, Derivative)]
@@ -78,33 +145,51 @@ impl Component for NavBarWidget {
 
     fn create(ctx: &Context<Self>) -> Self {
         ctx.props().oncreate.emit(ctx.link().clone());
-        let file_open_helper = FileOpenHelper::new(ctx.props().parent.clone());
-        Self { node_ref: NodeRef::default(), next_tab_idx: 1, file_open_helper }
+        let link = ctx.link().clone();
+        let _system_theme_listener = settings::follow_system_theme(move || link.send_message(NavBarMsg::SystemThemeChanged));
+        Self { node_ref: NodeRef::default(), next_tab_idx: 1, _system_theme_listener }
     }
 
     fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
         match msg {
             NavBarMsg::FileNew => {
                 let fname = format!("Untitled proof {}", self.next_tab_idx);
-                let fname_ = fname.clone();
-                let oncreate = ctx.props().parent.callback(move |link| AppMsg::RegisterProofName { name: fname_.clone(), link });
-                ctx.props().parent.send_message(AppMsg::CreateTab { name: fname, content: html! { <ProofWidget verbose=true data={ None } oncreate={ oncreate } /> } });
+                let parent = ctx.props().parent.clone();
+                let content = Box::new(move |id| proof_tab_content(&parent, id, None, false));
+                ctx.props().parent.send_message(AppMsg::CreateTab { name: fname, content });
                 self.next_tab_idx += 1;
                 false
             }
-            NavBarMsg::FileOpen(file_list) => self.file_open_helper.fileopen(file_list),
+            NavBarMsg::FileNewFromSequent { name, data } => {
+                let fname = if name.is_empty() { format!("Untitled proof {}", self.next_tab_idx) } else { name };
+                let parent = ctx.props().parent.clone();
+                let content = Box::new(move |id| proof_tab_content(&parent, id, Some(data), false));
+                ctx.props().parent.send_message(AppMsg::CreateTab { name: fname, content });
+                self.next_tab_idx += 1;
+                false
+            }
+            NavBarMsg::FileOpen(file_list) => {
+                open_files(&ctx.props().parent, file_list);
+                true
+            }
             NavBarMsg::FileSave => {
                 let node = self.node_ref.get().expect("NavBarWidget::node_ref failed");
-                ctx.props().parent.send_message(AppMsg::GetProofFromCurrentTab(Box::new(move |name, prf| {
+                let parent = ctx.props().parent.clone();
+                ctx.props().parent.send_message(AppMsg::GetProofFromCurrentTab(Box::new(move |id, name, prf, loaded| {
                     use aris::proofs::xml_interop;
                     let mut data = vec![];
-                    let metadata = xml_interop::ProofMetaData { author: Some("ARIS-YEW-UI".into()), hash: None, goals: vec![] };
+                    // Keep the author, title, and goals the proof was opened with, any lines that are
+                    // still being typed, and any annotations, which are dropped if the proof has been
+                    // edited since
+                    let author = loaded.author.clone().or_else(|| Some("ARIS-YEW-UI".into()));
+                    let mut metadata = xml_interop::ProofMetaData { author, hash: None, ..loaded.clone() };
+                    metadata.mark_modified(js_sys::Date::new_0().to_iso_string().into());
                     xml_interop::xml_from_proof_and_metadata_with_hash(prf, &metadata, &mut data).expect("xml_from_proof_and_metadata failed");
                     let window = web_sys::window().expect("web_sys::window failed");
                     let document = window.document().expect("window.document failed");
                     let anchor = document.create_element("a").expect("document.create_element(\"a\") failed");
                     let anchor = anchor.dyn_into::<web_sys::HtmlAnchorElement>().expect("dyn_into::HtmlAnchorElement failed");
-                    anchor.set_download(&name);
+                    anchor.set_download(&save_name(&name));
                     let js_str = JsValue::from_str(&String::from_utf8_lossy(&data));
                     let js_array = js_sys::Array::new_with_length(1);
                     js_array.set(0, js_str);
@@ -117,15 +202,43 @@ impl Component for NavBarWidget {
                         node.remove_child(&anchor).expect("node.remove_child failed");
                     })
                     .forget();
+                    // Only this tab's proof was saved
+                    parent.send_message(AppMsg::ProofSaved(id));
                 })));
                 false
             }
+            NavBarMsg::CheckProof => {
+                ctx.props().parent.send_message(AppMsg::CheckCurrentProof);
+                false
+            }
+            NavBarMsg::Print => {
+                ctx.props().parent.send_message(AppMsg::PrintCurrentProof);
+                false
+            }
             NavBarMsg::NewExprTree => {
                 ctx.props().parent.send_message(AppMsg::CreateTab {
                     name: format!("Expr Tree {}", self.next_tab_idx),
-                    content: html! {
-                        <ExprAstWidget initial_contents="forall A, ((exists B, A -> B) & C & f(x, y | z)) <-> Q <-> R" />
-                    },
+                    content: Box::new(|_: TabId| {
+                        TabContent::new(|_| {
+                            html! {
+                                <ExprAstWidget initial_contents="forall A, ((exists B, A -> B) & C & f(x, y | z)) <-> Q <-> R" />
+                            }
+                        })
+                    }),
+                });
+                self.next_tab_idx += 1;
+                false
+            }
+            NavBarMsg::NewTruthTable => {
+                ctx.props().parent.send_message(AppMsg::CreateTab {
+                    name: format!("Truth Table {}", self.next_tab_idx),
+                    content: Box::new(|_: TabId| {
+                        TabContent::new(|_| {
+                            html! {
+                                <TruthTableWidget initial_contents="(P -> Q) & P -> Q" />
+                            }
+                        })
+                    }),
                 });
                 self.next_tab_idx += 1;
                 false
@@ -133,17 +246,37 @@ impl Component for NavBarWidget {
             NavBarMsg::ToggleTheme => {
                 match theme().as_str() {
                     "light" => {
-                        document_element().set_attribute("theme", "dark").expect("failed setting dark theme");
+                        settings::set_theme("dark");
                         // document.get_element_by_id("rule-img").src = img.src.replace("_dark", "_light");
                     }
                     "dark" => {
-                        document_element().set_attribute("theme", "light").expect("failed setting light theme");
+                        settings::set_theme("light");
                         // document.get_element_by_id("rule-img").src = img.src.replace("_light", "_dark");
                     }
                     theme => unreachable!("unknown theme {}", theme),
                 }
                 true
             }
+            NavBarMsg::SystemThemeChanged => true,
+            NavBarMsg::ToggleMacros => {
+                let value = if expand_macros() { "off" } else { "on" };
+                document_element().set_attribute("macros", value).expect("failed setting macro expansion");
+                true
+            }
+            NavBarMsg::ToggleSymbols => {
+                let symbols = match settings::symbols() {
+                    Symbols::Unicode => Symbols::Ascii,
+                    Symbols::Ascii => Symbols::Unicode,
+                };
+                settings::set_symbols(symbols);
+                ctx.props().parent.send_message(AppMsg::SettingsChanged);
+                true
+            }
+            NavBarMsg::ToggleDebugLog => {
+                settings::set_show_debug_log(!settings::show_debug_log());
+                ctx.props().parent.send_message(AppMsg::SettingsChanged);
+                true
+            }
             NavBarMsg::Nop => false,
         }
     }
@@ -170,9 +303,10 @@ impl Component for NavBarWidget {
                             <label for="file-menu-new-proof" class="dropdown-item">{"New blank proof"}</label>
                             <input id="file-menu-new-proof" style="display:none" type="button" onclick={ ctx.link().callback(|_| NavBarMsg::FileNew) } />
                         </div>
+                        <a class="dropdown-item" href="#" data-toggle="modal" data-target="#new-proof-modal">{"New proof from sequent…"}</a>
                         <div>
                             <label for="file-menu-open-proof" class="dropdown-item">{"Open proof"}</label>
-                            <input id="file-menu-open-proof" style="display:none" type="file" onchange={ handle_open_file } />
+                            <input id="file-menu-open-proof" style="display:none" type="file" accept=".bram,.json" multiple=true onchange={ handle_open_file } />
                         </div>
                         <div>
                             <label for="file-menu-save-proof" class="dropdown-item">{"Save proof"}</label>
@@ -182,6 +316,10 @@ impl Component for NavBarWidget {
                             <label for="file-menu-new-expr-tree" class="dropdown-item">{"New expression tree"}</label>
                             <input id="file-menu-new-expr-tree" style="display:none" type="button" onclick={ ctx.link().callback(|_| NavBarMsg::NewExprTree) } />
                         </div>
+                        <div>
+                            <label for="file-menu-new-truth-table" class="dropdown-item">{"New truth table"}</label>
+                            <input id="file-menu-new-truth-table" style="display:none" type="button" onclick={ ctx.link().callback(|_| NavBarMsg::NewTruthTable) } />
+                        </div>
                     </div>
                 </li>
             </ul>
@@ -195,32 +333,12 @@ impl Component for NavBarWidget {
 
         let logic_symbol_buttons = aris::macros::TABLE
             .iter()
-            .map(|(symbol, _)| symbol)
+            .map(|(symbol, _)| *symbol)
             .map(|symbol| {
-                let onmousedown = Callback::from(|e: MouseEvent| {
-                    if let Some(active_input_element) = document().active_element().and_then(|elem| elem.dyn_into::<HtmlInputElement>().ok()) {
-                        e.prevent_default();
-
-                        // Get cursor position in text field
-                        let cursor_pos = active_input_element.selection_start().unwrap_throw().unwrap_or_default() as usize;
-
-                        // Get text to the left and right of cursor position
-                        //
-                        // NOTE: The cursor position is measured in characters, not bytes, so
-                        // the `String` must be converted to `Vec<char>`.
-                        let value = active_input_element.value().chars().collect::<Vec<char>>();
-                        let (left, right) = value.split_at(cursor_pos);
-
-                        // Insert symbol
-                        let symbol = symbol.chars().collect::<Vec<char>>();
-                        let value = left.iter().chain(symbol.iter()).chain(right).collect::<String>();
-                        active_input_element.set_value(&value);
-                        let cursor_pos = (cursor_pos + symbol.len()) as u32;
-                        active_input_element.set_selection_start(Some(cursor_pos)).unwrap_throw();
-
-                        // Trigger `oninput` callback
-                        active_input_element.dispatch_event(&Event::new("input").unwrap_throw()).unwrap_throw();
-                    }
+                let onmousedown = Callback::from(move |e: MouseEvent| {
+                    // Keep the focus in the text field the symbol goes into
+                    e.prevent_default();
+                    symbol_palette::insert_symbol(symbol);
                 });
                 html! {
                     <button type="button" class="btn btn-secondary" { onmousedown }>
@@ -247,6 +365,29 @@ impl Component for NavBarWidget {
                 </div>
 
                 <ul class="navbar-nav ml-auto">
+                    // Check the current tab's proof
+                    <li class="nav-item">
+                        <a class="nav-link" href="#" onclick={ ctx.link().callback(|e: MouseEvent| { e.prevent_default(); NavBarMsg::CheckProof }) }> { "Check proof" } </a>
+                    </li>
+                    // Print the current tab's proof
+                    <li class="nav-item">
+                        <a class="nav-link" href="#" onclick={ ctx.link().callback(|e: MouseEvent| { e.prevent_default(); NavBarMsg::Print }) }> { "Print…" } </a>
+                    </li>
+                    // Settings menu
+                    <li class="nav-item dropdown">
+                        <a class="nav-link dropdown-toggle" href="#" role="button" id="settingsMenuLink" data-toggle="dropdown" aria-haspopup="true" aria-expanded="false">{"Settings"}</a>
+                        <div class="dropdown-menu dropdown-menu-right" aria-labelledby="settingsMenuLink">
+                            <a class="dropdown-item" href="#" title="Replace ASCII like -> and & with logic symbols while typing" onclick={ ctx.link().callback(|e: MouseEvent| { e.prevent_default(); NavBarMsg::ToggleMacros }) }>
+                                { if expand_macros() { "Symbols: on" } else { "Symbols: off" } }
+                            </a>
+                            <a class="dropdown-item" href="#" title="Show formulas with logic symbols, or with ASCII like -> and &" onclick={ ctx.link().callback(|e: MouseEvent| { e.prevent_default(); NavBarMsg::ToggleSymbols }) }>
+                                { match settings::symbols() { Symbols::Unicode => "Display formulas: Unicode", Symbols::Ascii => "Display formulas: ASCII" } }
+                            </a>
+                            <a class="dropdown-item" href="#" title="Show what was done to each proof below it, for reporting bugs" onclick={ ctx.link().callback(|e: MouseEvent| { e.prevent_default(); NavBarMsg::ToggleDebugLog }) }>
+                                { if settings::show_debug_log() { "Show debug log: on" } else { "Show debug log: off" } }
+                            </a>
+                        </div>
+                    </li>
                     // Theme toggle
                     <li class="nav-item">
                         <a class="nav-link" onclick={ ctx.link().callback(|_| NavBarMsg::ToggleTheme) }>
@@ -267,6 +408,7 @@ impl Component for NavBarWidget {
             <>
                 { navbar }
                 { render_help_modal() }
+                <NewProofDialog oncreate={ ctx.link().callback(|(name, data)| NavBarMsg::FileNewFromSequent { name, data }) } />
             </>
         }
     }
@@ -282,9 +424,15 @@ fn document_element() -> web_sys::Element {
     document().document_element().expect_throw("document.document_element()")
 }
 
-/// Get the name of the current theme, or panic if the theme attribute doesn't exist.
+/// Get the name of the current theme, which is light until one is set
 pub fn theme() -> String {
-    document_element().get_attribute("theme").expect("failed querying theme")
+    document_element().get_attribute("theme").unwrap_or_else(|| "light".into())
+}
+
+/// Whether macros like `->` are replaced with logic symbols while typing, which
+/// is on unless it's turned off in the nav bar
+pub fn expand_macros() -> bool {
+    document_element().get_attribute("macros").as_deref() != Some("off")
 }
 
 fn render_help_modal() -> Html {
@@ -350,3 +498,35 @@ fn render_help_body() -> Html {
         </>
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_save_name() {
+        assert_eq!(save_name("Untitled proof 1"), "Untitled proof 1.bram");
+        assert_eq!(save_name("resolution_example.bram"), "resolution_example.bram");
+        assert_eq!(save_name("notes.txt"), "notes.txt.bram");
+    }
+
+    #[test]
+    fn test_tabs_for_file() {
+        use aris::proofs::json_interop::proof_to_json;
+        use aris::proofs::xml_interop::proof_from_xml;
+        let bram = |name: &str| std::fs::read_to_string(format!("../example-proofs/{name}")).unwrap();
+        let (prf, metadata, _) = proof_from_xml::<P, _>(bram("resolution_example.bram").as_bytes()).unwrap();
+        // Files are opened however many are read at once, and in whatever
+        // order their reads finish
+        let results = vec![("resolution_example.bram", Some(bram("resolution_example.bram"))), ("notes.txt", Some("not a proof".into())), ("exported.JSON", Some(proof_to_json(&prf, &metadata))), ("corrupt.bram", Some("\u{fffd}\u{fffd} garbage".into())), ("two_bad_formulas.bram", Some(bram("damaged/two_bad_formulas.bram"))), ("unreadable.bram", None)];
+        let opened = results.into_iter().map(|(fname, contents)| tabs_for_file(fname.into(), contents).map(|tabs| tabs.len())).collect::<Vec<_>>();
+        assert_eq!(opened[0], Ok(1));
+        assert!(matches!(&opened[1], Err(err) if err.contains("notes.txt") && err.contains("isn't a proof")));
+        assert_eq!(opened[2], Ok(1));
+        // A file that can't be read at all is an error instead of a tab that only shows one,
+        assert!(matches!(&opened[3], Err(err) if err.starts_with("Couldn't open corrupt.bram: ")));
+        // but one that can be partly read is opened, to show what was lost
+        assert_eq!(opened[4], Ok(1));
+        assert_eq!(opened[5], Err("Couldn't read unreadable.bram".into()));
+    }
+}
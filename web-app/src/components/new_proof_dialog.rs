@@ -0,0 +1,244 @@
+use crate::util::P;
+
+use aris::expr::Expr;
+use aris::proofs::template::from_sequent;
+use aris::proofs::xml_interop::xml_from_proof_and_metadata;
+use aris::proofs::xml_interop::ProofMetaData;
+
+use std::fmt;
+
+use web_sys::HtmlInputElement;
+use web_sys::HtmlTextAreaElement;
+use yew::prelude::*;
+
+/// A line typed in the new proof dialog, which is a premise, or the conclusion
+/// if it starts with `⊢` or `|-`
+#[derive(Debug, Clone, PartialEq)]
+pub struct SequentLine {
+    /// 1-based line number in the text area
+    pub number: usize,
+    /// The formula, without the `⊢` of a conclusion
+    pub text: String,
+    pub is_conclusion: bool,
+    /// The formula, if it parses
+    pub expr: Option<Expr>,
+}
+
+/// Why the text of the new proof dialog isn't a sequent a proof can be made for
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SequentError {
+    /// The line with this 1-based number doesn't parse
+    Unparsed(usize),
+    /// No line is a conclusion
+    NoConclusion,
+    /// Lines with these 1-based numbers are all conclusions
+    SeveralConclusions(Vec<usize>),
+}
+
+impl fmt::Display for SequentError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SequentError::Unparsed(number) => write!(f, "Line {number} isn't a formula."),
+            SequentError::NoConclusion => write!(f, "Add a conclusion, on a line starting with ⊢."),
+            SequentError::SeveralConclusions(numbers) => write!(f, "Lines {} are all conclusions, but a proof only has one.", numbers.iter().map(usize::to_string).collect::<Vec<_>>().join(", ")),
+        }
+    }
+}
+
+/// The lines of the new proof dialog's text area, leaving out blank ones
+pub fn parse_sequent_lines(text: &str) -> Vec<SequentLine> {
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| {
+            let line = line.trim();
+            let (text, is_conclusion) = match line.strip_prefix('⊢').or_else(|| line.strip_prefix("|-")) {
+                Some(rest) => (rest.trim(), true),
+                None => (line, false),
+            };
+            SequentLine { number: i + 1, text: text.to_string(), is_conclusion, expr: aris::parser::parse(text) }
+        })
+        .collect()
+}
+
+/// The premises and conclusion of the sequent in `lines`, which is only a
+/// sequent if each line parses and exactly one is a conclusion
+pub fn sequent_of(lines: &[SequentLine]) -> Result<(Vec<Expr>, Expr), SequentError> {
+    if let Some(line) = lines.iter().find(|line| line.expr.is_none()) {
+        return Err(SequentError::Unparsed(line.number));
+    }
+    let conclusions = lines.iter().filter(|line| line.is_conclusion).collect::<Vec<_>>();
+    let conclusion = match &conclusions[..] {
+        [] => return Err(SequentError::NoConclusion),
+        [conclusion] => conclusion.expr.clone().expect("conclusion parses"),
+        _ => return Err(SequentError::SeveralConclusions(conclusions.iter().map(|line| line.number).collect())),
+    };
+    let premises = lines.iter().filter(|line| !line.is_conclusion).filter_map(|line| line.expr.clone()).collect();
+    Ok((premises, conclusion))
+}
+
+/// A proof file of `premises ⊢ conclusion`, with the conclusion as its goal,
+/// titled `title` unless that's blank. Identical premises are only included
+/// once.
+fn new_proof_data(title: &str, premises: &[Expr], conclusion: &Expr) -> Result<Vec<u8>, String> {
+    let template = from_sequent::<P>(premises, conclusion).map_err(|err| err.to_string())?;
+    let title = Some(title.trim().to_string()).filter(|title| !title.is_empty());
+    let metadata = ProofMetaData { title, goals: template.goals, ..Default::default() };
+    let mut data = vec![];
+    xml_from_proof_and_metadata(&template.proof, &metadata, &mut data).map_err(|err| err.to_string())?;
+    Ok(data)
+}
+
+/// A dialog for starting a proof of a sequent typed into it, which is shown as
+/// the Bootstrap modal with the id `new-proof-modal`
+pub struct NewProofDialog {
+    title: String,
+    lines: Vec<SequentLine>,
+    /// Why the last proof couldn't be created, if it couldn't
+    create_error: Option<String>,
+    text_ref: NodeRef,
+}
+
+pub enum NewProofDialogMsg {
+    TitleChanged(String),
+    TextChanged(String),
+    Create,
+}
+
+#[derive(Clone, Properties, PartialEq)]
+pub struct NewProofDialogProps {
+    /// Called with the tab name, which is blank if no title was typed, and the
+    /// contents of the new proof
+    pub oncreate: Callback<(String, Vec<u8>)>,
+}
+
+impl Component for NewProofDialog {
+    type Message = NewProofDialogMsg;
+    type Properties = NewProofDialogProps;
+
+    fn create(_: &Context<Self>) -> Self {
+        Self { title: String::new(), lines: vec![], create_error: None, text_ref: NodeRef::default() }
+    }
+
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            NewProofDialogMsg::TitleChanged(title) => self.title = title,
+            NewProofDialogMsg::TextChanged(text) => {
+                self.lines = parse_sequent_lines(&text);
+                self.create_error = None;
+            }
+            NewProofDialogMsg::Create => {
+                let (premises, conclusion) = match sequent_of(&self.lines) {
+                    Ok(sequent) => sequent,
+                    Err(_) => return false,
+                };
+                match new_proof_data(&self.title, &premises, &conclusion) {
+                    Ok(data) => {
+                        ctx.props().oncreate.emit((self.title.trim().to_string(), data));
+                        // Start over for the next proof
+                        if let Some(text_area) = self.text_ref.cast::<HtmlTextAreaElement>() {
+                            text_area.set_value("");
+                        }
+                        self.title.clear();
+                        self.lines.clear();
+                        self.create_error = None;
+                    }
+                    Err(err) => self.create_error = Some(format!("Couldn't create the proof: {err}")),
+                }
+            }
+        }
+        true
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        let sequent = sequent_of(&self.lines);
+        let symbols = crate::settings::symbols();
+        let statuses = self
+            .lines
+            .iter()
+            .map(|line| {
+                let earlier = self.lines.iter().take_while(|other| other.number < line.number).find(|other| !other.is_conclusion && other.expr.is_some() && other.expr == line.expr);
+                let (class, status) = match (&line.expr, line.is_conclusion, earlier) {
+                    (None, _, _) => ("text-danger", format!("{:?} doesn't parse", line.text)),
+                    (Some(expr), true, _) => ("text-success", format!("conclusion {}", expr.display_with(symbols))),
+                    (Some(expr), false, Some(earlier)) => ("text-muted", format!("premise {}, the same as line {}, so it's left out", expr.display_with(symbols), earlier.number)),
+                    (Some(expr), false, None) => ("text-success", format!("premise {}", expr.display_with(symbols))),
+                };
+                html! { <li class={ class }> { format!("Line {}: {status}", line.number) } </li> }
+            })
+            .collect::<Html>();
+        let problem = match (&sequent, &self.create_error) {
+            (_, Some(err)) => html! { <p class="text-danger mb-0"> { err } </p> },
+            (Err(err), None) if !self.lines.is_empty() => html! { <p class="text-danger mb-0"> { err.to_string() } </p> },
+            _ => html! {},
+        };
+        let oninput_title = ctx.link().callback(|e: InputEvent| NewProofDialogMsg::TitleChanged(e.target_unchecked_into::<HtmlInputElement>().value()));
+        let oninput_text = ctx.link().callback(|e: InputEvent| NewProofDialogMsg::TextChanged(e.target_unchecked_into::<HtmlTextAreaElement>().value()));
+        html! {
+            <div class="modal fade" id="new-proof-modal" tabindex="-1" role="dialog" aria-labelledby="new-proof-modal-label" aria-hidden="true">
+                <div class="modal-dialog" role="document">
+                    <div class="modal-content">
+                        <div class="modal-header">
+                            <h5 class="modal-title" id="new-proof-modal-label"> { "New proof from sequent" } </h5>
+                            <button type="button" class="close" data-dismiss="modal" aria-label="Close">
+                                <span aria-hidden="true"> { '×' } </span>
+                            </button>
+                        </div>
+                        <div class="modal-body">
+                            <div class="form-group">
+                                <label for="new-proof-title"> { "Title" } </label>
+                                <input id="new-proof-title" type="text" class="form-control" placeholder="Untitled proof" value={ self.title.clone() } oninput={ oninput_title } />
+                            </div>
+                            <div class="form-group">
+                                <label for="new-proof-sequent"> { "Premises, one per line, then the conclusion after ⊢" } </label>
+                                <textarea id="new-proof-sequent" ref={ self.text_ref.clone() } class="form-control text-monospace" rows="6" placeholder={ "P → Q\nP\n⊢ Q" } oninput={ oninput_text } />
+                            </div>
+                            <ul class="list-unstyled small"> { statuses } </ul>
+                            { problem }
+                        </div>
+                        <div class="modal-footer">
+                            <button type="button" class="btn btn-secondary" data-dismiss="modal"> { "Cancel" } </button>
+                            <button type="button" class="btn btn-primary" data-dismiss="modal" disabled={ sequent.is_err() } onclick={ ctx.link().callback(|_| NewProofDialogMsg::Create) }> { "Create" } </button>
+                        </div>
+                    </div>
+                </div>
+            </div>
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use aris::parser::parse_unwrap as p;
+    use aris::proofs::xml_interop::proof_from_xml;
+    use aris::proofs::Proof;
+
+    #[test]
+    fn test_sequent_of() {
+        let lines = parse_sequent_lines("P -> Q\n\n  P  \n⊢ Q\n");
+        assert_eq!(lines.iter().map(|line| line.number).collect::<Vec<_>>(), vec![1, 3, 4]);
+        assert_eq!(sequent_of(&lines), Ok((vec![p("P -> Q"), p("P")], p("Q"))));
+
+        // Theorems don't have premises, and the conclusion can be written with |-
+        assert_eq!(sequent_of(&parse_sequent_lines("|- A | ~A")), Ok((vec![], p("A | ~A"))));
+
+        assert_eq!(sequent_of(&parse_sequent_lines("P\nQ & \n⊢ P")), Err(SequentError::Unparsed(2)));
+        assert_eq!(sequent_of(&parse_sequent_lines("P\nQ")), Err(SequentError::NoConclusion));
+        assert_eq!(sequent_of(&parse_sequent_lines("⊢ P\n|- Q")), Err(SequentError::SeveralConclusions(vec![1, 2])));
+        assert_eq!(sequent_of(&[]), Err(SequentError::NoConclusion));
+    }
+
+    #[test]
+    fn test_new_proof_data() {
+        let data = new_proof_data(" modus ponens ", &[p("P -> Q"), p("P"), p("P")], &p("Q")).unwrap();
+        let (prf, metadata, _) = proof_from_xml::<P, _>(&data[..]).unwrap();
+        assert_eq!(prf.premises().len(), 2);
+        assert_eq!(metadata.goals, vec![p("Q")]);
+        assert_eq!(metadata.title.as_deref(), Some("modus ponens"));
+
+        let data = new_proof_data("  ", &[], &p("A | ~A")).unwrap();
+        assert_eq!(proof_from_xml::<P, _>(&data[..]).unwrap().1.title, None);
+    }
+}
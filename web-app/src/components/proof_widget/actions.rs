@@ -3,12 +3,12 @@
 //! This module allows getting the description and keyboard shortcuts for all
 //! valid actions on a given line.
 
-use super::may_remove_line;
 use super::LineActionKind;
 use super::ProofItemKind;
 use super::P;
 
 use aris::proofs::pj_to_pjs;
+use aris::proofs::MoveDirection;
 use aris::proofs::PjRef;
 use aris::proofs::Proof;
 
@@ -34,11 +34,12 @@ pub struct ActionInfo {
 /// ## Parameters:
 ///   * `proof` - the current proof object
 ///   * `line_ref` - reference to the current line
-pub fn valid_actions(proof: &P, line_ref: PjRef<P>) -> impl Iterator<Item = &ActionInfo> {
+///   * `locked` - whether the current line is locked against changes
+pub fn valid_actions(proof: &P, line_ref: PjRef<P>, locked: bool) -> impl Iterator<Item = &ActionInfo> {
     use frunk_core::coproduct::Coproduct::{Inl, Inr};
 
     // Can the current line be deleted?
-    let can_delete_line = may_remove_line(proof, &line_ref);
+    let can_delete_line = !locked && proof.may_delete_line(&line_ref).is_ok();
 
     // Is the current line a premise?
     let is_premise = matches!(line_ref, Inl(_));
@@ -67,18 +68,46 @@ pub fn valid_actions(proof: &P, line_ref: PjRef<P>) -> impl Iterator<Item = &Act
         LineActionKind::Delete { what } => match what {
             ProofItemKind::Premise => is_premise && can_delete_line,
             ProofItemKind::Just => is_just && can_delete_line,
-            ProofItemKind::Subproof => in_subproof,
+            ProofItemKind::Subproof => in_subproof && !locked,
         },
+        // Subproofs only have one assumption, so there's nothing to swap it with
+        LineActionKind::Move { .. } => is_just || (is_premise && !in_subproof),
+        LineActionKind::FillTrivial => is_just && !locked,
         _ => false,
     })
 }
 
+/// The action for a key pressed in a line's text field, if it's one of the
+/// valid actions of the line. Enter inserts a line of the same kind after it,
+/// or a step after a subproof's assumption, Ctrl-Enter inserts a subproof after
+/// a step, and Ctrl-Delete deletes the line.
+pub fn key_action(proof: &P, line_ref: PjRef<P>, locked: bool, key: &str, ctrl: bool) -> Option<LineActionKind> {
+    use frunk_core::coproduct::Coproduct::{Inl, Inr};
+    use ProofItemKind::{Just, Premise, Subproof};
+    let insert = |what, relative_to| LineActionKind::Insert { what, after: true, relative_to };
+    // In order of preference
+    let candidates = match (key, ctrl, line_ref) {
+        ("Enter", false, Inl(_)) => vec![insert(Premise, Premise), insert(Just, Premise)],
+        ("Enter", false, Inr(Inl(_))) => vec![insert(Just, Just)],
+        ("Enter", true, Inr(Inl(_))) => vec![insert(Subproof, Just)],
+        ("Delete", true, Inl(_)) => vec![LineActionKind::Delete { what: Premise }],
+        ("Delete", true, Inr(Inl(_))) => vec![LineActionKind::Delete { what: Just }],
+        _ => vec![],
+    };
+    candidates.into_iter().find(|candidate| valid_actions(proof, line_ref, locked).any(|action_info| action_info.line_action_kind == *candidate))
+}
+
 /// Array of all actions
-static ACTIONS: [ActionInfo; 15] = [
+static ACTIONS: [ActionInfo; 18] = [
     // Delete actions
     ActionInfo { keyboard_shortcut: Some('d'), description: "Delete premise", line_action_kind: LineActionKind::Delete { what: ProofItemKind::Premise } },
     ActionInfo { keyboard_shortcut: Some('d'), description: "Delete step", line_action_kind: LineActionKind::Delete { what: ProofItemKind::Just } },
     ActionInfo { keyboard_shortcut: None, description: "Delete subproof", line_action_kind: LineActionKind::Delete { what: ProofItemKind::Subproof } },
+    // Move actions
+    ActionInfo { keyboard_shortcut: None, description: "Move line up", line_action_kind: LineActionKind::Move { direction: MoveDirection::Up } },
+    ActionInfo { keyboard_shortcut: None, description: "Move line down", line_action_kind: LineActionKind::Move { direction: MoveDirection::Down } },
+    // Justification actions
+    ActionInfo { keyboard_shortcut: None, description: "Fill in trivial justification", line_action_kind: LineActionKind::FillTrivial },
     // Insert actions
     // Subproof-relative insert actions
     ActionInfo { keyboard_shortcut: None, description: "Insert step before this subproof", line_action_kind: LineActionKind::Insert { what: ProofItemKind::Just, after: false, relative_to: ProofItemKind::Subproof } },
@@ -96,3 +125,37 @@ static ACTIONS: [ActionInfo; 15] = [
     ActionInfo { keyboard_shortcut: Some('p'), description: "Insert subproof after this step", line_action_kind: LineActionKind::Insert { what: ProofItemKind::Subproof, after: true, relative_to: ProofItemKind::Just } },
     ActionInfo { keyboard_shortcut: Some('r'), description: "Insert premise before this step", line_action_kind: LineActionKind::Insert { what: ProofItemKind::Premise, after: false, relative_to: ProofItemKind::Just } },
 ];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use aris::expr::Expr;
+    use aris::proofs::Justification;
+    use aris::rules::RuleM;
+    use frunk_core::coproduct::Coproduct;
+
+    fn descriptions(proof: &P, line_ref: PjRef<P>, locked: bool) -> Vec<&'static str> {
+        valid_actions(proof, line_ref, locked).map(|action_info| action_info.description).collect()
+    }
+
+    #[test]
+    fn test_valid_actions() {
+        let mut prf = P::new();
+        let r1: PjRef<P> = Coproduct::inject(prf.add_premise(Expr::var("A")));
+        let r2: PjRef<P> = Coproduct::inject(prf.add_step(Justification(Expr::var("A"), RuleM::Reit, vec![r1], vec![])));
+        let sr = prf.add_subproof();
+        let r3: PjRef<P> = Coproduct::inject(prf.with_mut_subproof(&sr, |sub| sub.add_premise(Expr::var("B"))).unwrap());
+
+        // The only premise can't be deleted
+        assert_eq!(descriptions(&prf, r1, false), vec!["Move line up", "Move line down", "Insert premise before this premise", "Insert premise after this premise", "Insert step after this premise"]);
+
+        // Steps can be deleted and justified unless they're locked
+        assert_eq!(descriptions(&prf, r2, false), vec!["Delete step", "Move line up", "Move line down", "Fill in trivial justification", "Insert step before this step", "Insert step after this step", "Insert subproof before this step", "Insert subproof after this step", "Insert premise before this step"]);
+        assert_eq!(descriptions(&prf, r2, true), vec!["Move line up", "Move line down", "Insert step before this step", "Insert step after this step", "Insert subproof before this step", "Insert subproof after this step", "Insert premise before this step"]);
+
+        // A subproof's assumption can't be moved or have another assumption
+        // added, but its subproof can be deleted
+        assert_eq!(descriptions(&prf, r3, false), vec!["Delete subproof", "Insert step before this subproof", "Insert step after this subproof", "Insert subproof before this subproof", "Insert subproof after this subproof", "Insert step after this premise"]);
+    }
+}
@@ -0,0 +1,179 @@
+//! Confirmation before deleting a subproof
+//!
+//! Deleting a subproof deletes every line in it, so unless it's only its
+//! assumption, what will be removed is shown in a dialog first.
+
+use super::P;
+
+use aris::proofs::pj_to_pjs;
+use aris::proofs::PjRef;
+use aris::proofs::Proof;
+
+use frunk_core::coproduct::Coproduct;
+use web_sys::HtmlInputElement;
+use yew::prelude::*;
+
+/// What deleting the subproof around a line removes
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SubproofDeletion {
+    /// How many lines are in the subproof, including its nested subproofs'
+    pub lines: usize,
+    /// How many subproofs are nested in it, at any depth
+    pub subproofs: usize,
+    /// Line numbers of the steps that cite the subproof, in order
+    pub cited_by: Vec<usize>,
+}
+
+/// What deleting the subproof directly containing `line_ref` removes, with
+/// lines numbered by `line_number`, or `None` if the line isn't in a subproof
+pub fn subproof_deletion(prf: &P, line_ref: &PjRef<P>, line_number: impl Fn(&PjRef<P>) -> Option<usize>) -> Option<SubproofDeletion> {
+    let sr = prf.parent_of_line(&pj_to_pjs::<P>(*line_ref))?;
+    let sub = prf.lookup_subproof(&sr)?;
+    let (lines, subproofs) = sub.iter_lines().fold((0, 0), |(lines, subproofs), info| match info.line {
+        Some(_) => (lines + 1, subproofs),
+        None => (lines, subproofs + 1),
+    });
+    let mut cited_by = prf.dependents_of_subproof(&sr).into_iter().filter_map(|jr| line_number(&Coproduct::inject(jr))).collect::<Vec<_>>();
+    cited_by.sort_unstable();
+    Some(SubproofDeletion { lines, subproofs, cited_by })
+}
+
+/// Whether to ask before making `deletion`. A subproof that's only its
+/// assumption is deleted right away, as is any once asking was turned off.
+pub fn should_confirm(deletion: &SubproofDeletion, dont_ask: bool) -> bool {
+    !dont_ask && deletion.lines > 1
+}
+
+/// What the dialog says will be removed
+fn describe(deletion: &SubproofDeletion) -> String {
+    let plural = |n: usize, one: &str, many: &str| if n == 1 { format!("1 {one}") } else { format!("{n} {many}") };
+    let mut description = format!("This deletes {}", plural(deletion.lines, "line", "lines"));
+    if deletion.subproofs > 0 {
+        description += &format!(", including {}", plural(deletion.subproofs, "nested subproof", "nested subproofs"));
+    }
+    description += ".";
+    description
+}
+
+/// A dialog asking whether to delete a subproof, showing what would be removed
+pub struct ConfirmDeleteDialog {
+    dont_ask: bool,
+}
+
+pub enum ConfirmDeleteDialogMsg {
+    DontAskChanged(bool),
+}
+
+#[derive(Clone, Properties, PartialEq)]
+pub struct ConfirmDeleteDialogProps {
+    pub deletion: SubproofDeletion,
+    /// Called when deleting is confirmed, with whether to stop asking
+    pub onconfirm: Callback<bool>,
+    pub oncancel: Callback<()>,
+}
+
+impl Component for ConfirmDeleteDialog {
+    type Message = ConfirmDeleteDialogMsg;
+    type Properties = ConfirmDeleteDialogProps;
+
+    fn create(_: &Context<Self>) -> Self {
+        Self { dont_ask: false }
+    }
+
+    fn update(&mut self, _: &Context<Self>, msg: Self::Message) -> bool {
+        match msg {
+            ConfirmDeleteDialogMsg::DontAskChanged(dont_ask) => self.dont_ask = dont_ask,
+        }
+        true
+    }
+
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        let deletion = &ctx.props().deletion;
+        let cited_by = match &deletion.cited_by[..] {
+            [] => html! {},
+            [line] => html! { <p class="mb-0"> { format!("Line {line} cites the subproof, and won't be justified anymore.") } </p> },
+            lines => html! { <p class="mb-0"> { format!("Lines {} cite the subproof, and won't be justified anymore.", lines.iter().map(usize::to_string).collect::<Vec<_>>().join(", ")) } </p> },
+        };
+        let dont_ask = self.dont_ask;
+        let onconfirm = ctx.props().onconfirm.reform(move |_| dont_ask);
+        let oncancel = ctx.props().oncancel.reform(|_| ());
+        let onchange = ctx.link().callback(|e: Event| ConfirmDeleteDialogMsg::DontAskChanged(e.target_unchecked_into::<HtmlInputElement>().checked()));
+        html! {
+            <>
+                <div class="modal d-block" tabindex="-1" role="dialog" aria-labelledby="confirm-delete-label">
+                    <div class="modal-dialog" role="document">
+                        <div class="modal-content">
+                            <div class="modal-header">
+                                <h5 class="modal-title" id="confirm-delete-label"> { "Delete subproof?" } </h5>
+                                <button type="button" class="close" aria-label="Close" onclick={ oncancel.clone() }>
+                                    <span aria-hidden="true"> { '×' } </span>
+                                </button>
+                            </div>
+                            <div class="modal-body">
+                                <p> { describe(deletion) } </p>
+                                { cited_by }
+                                <div class="form-check mt-3">
+                                    <input id="confirm-delete-dont-ask" type="checkbox" class="form-check-input" checked={ self.dont_ask } { onchange } />
+                                    <label class="form-check-label" for="confirm-delete-dont-ask"> { "Don't ask again this session" } </label>
+                                </div>
+                            </div>
+                            <div class="modal-footer">
+                                <button type="button" class="btn btn-secondary" onclick={ oncancel }> { "Cancel" } </button>
+                                <button type="button" class="btn btn-danger" onclick={ onconfirm }> { "Delete" } </button>
+                            </div>
+                        </div>
+                    </div>
+                </div>
+                <div class="modal-backdrop show" />
+            </>
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use aris::expr::Expr;
+    use aris::proofs::Justification;
+    use aris::rules::RuleM;
+
+    #[test]
+    fn test_subproof_deletion() {
+        let mut prf = P::new();
+        let r1: PjRef<P> = Coproduct::inject(prf.add_premise(Expr::var("A")));
+        // A subproof that's only its assumption,
+        let sr1 = prf.add_subproof();
+        let r2: PjRef<P> = Coproduct::inject(prf.with_mut_subproof(&sr1, |sub| sub.add_premise(Expr::var("B"))).unwrap());
+        // and one with a step and a nested subproof in it
+        let sr2 = prf.add_subproof();
+        let r3: PjRef<P> = prf
+            .with_mut_subproof(&sr2, |sub| {
+                let r3 = sub.add_premise(Expr::var("C"));
+                sub.add_step(Justification(Expr::var("A"), RuleM::Reit, vec![r1], vec![]));
+                let sr3 = sub.add_subproof();
+                sub.with_mut_subproof(&sr3, |sub| sub.add_premise(Expr::var("D"))).unwrap();
+                Coproduct::inject(r3)
+            })
+            .unwrap();
+        prf.add_step(Justification(Expr::var("E"), RuleM::ImpIntro, vec![], vec![sr2]));
+        prf.add_step(Justification(Expr::var("E"), RuleM::ImpIntro, vec![], vec![sr2]));
+
+        let mut lines = std::collections::HashMap::new();
+        crate::util::calculate_lineinfo(&mut lines, &prf);
+        let line_number = |r: &PjRef<P>| lines.get(r).map(|(line, _)| *line);
+
+        let single = subproof_deletion(&prf, &r2, line_number).unwrap();
+        assert_eq!(single, SubproofDeletion { lines: 1, subproofs: 0, cited_by: vec![] });
+        assert!(!should_confirm(&single, false));
+
+        let nested = subproof_deletion(&prf, &r3, line_number).unwrap();
+        assert_eq!(nested, SubproofDeletion { lines: 3, subproofs: 1, cited_by: vec![6, 7] });
+        assert!(should_confirm(&nested, false));
+        assert!(!should_confirm(&nested, true));
+        assert_eq!(describe(&nested), "This deletes 3 lines, including 1 nested subproof.");
+
+        // Lines outside of subproofs can't delete one
+        assert_eq!(subproof_deletion(&prf, &r1, line_number), None);
+    }
+}
@@ -1,41 +1,110 @@
 mod actions;
+mod confirm_delete;
+mod static_view;
 
-use crate::box_chars;
 use crate::components::expr_entry::ExprEntry;
+use crate::debug_log::DebugLog;
 use crate::proof_ui_data::ProofUiData;
 use crate::util::calculate_lineinfo;
+use crate::util::lineinfo_after_delete;
+use crate::util::lineinfo_after_insert;
 use crate::util::P;
 use aris::expr::Expr;
+use aris::expr::Symbols;
+use aris::proofs::autocomplete::fill_trivial;
+use aris::proofs::cache::VerificationCache;
+use aris::proofs::history::RecordingProof;
 use aris::proofs::pj_to_pjs;
+use aris::proofs::xml_interop::summarize_ignored_content;
+use aris::proofs::xml_interop::CompatWarning;
+use aris::proofs::xml_interop::ProofMetaData;
+use aris::proofs::Citation;
+use aris::proofs::DeleteError;
+use aris::proofs::EditError;
+use aris::proofs::GoalStatus;
 use aris::proofs::JsRef;
 use aris::proofs::Justification;
+use aris::proofs::LineId;
+use aris::proofs::LineNames;
+use aris::proofs::MoveDirection;
+use aris::proofs::MoveError;
 use aris::proofs::PjRef;
 use aris::proofs::PjsRef;
 use aris::proofs::Proof;
+use aris::proofs::ProofDocument;
+use aris::proofs::StructuralError;
+use aris::rules::counterexample_rows;
+use aris::rules::counterexample_text;
+use aris::rules::suggest::apply_suggestion;
+use aris::rules::suggest::suggest;
+use aris::rules::suggest::Suggestion;
+use aris::rules::Budget;
+use aris::rules::CheckContext;
+use aris::rules::ProofCheckError;
 use aris::rules::Rule;
 use aris::rules::RuleClassification;
 use aris::rules::RuleM;
 use aris::rules::RuleT;
+use confirm_delete::ConfirmDeleteDialog;
+use confirm_delete::SubproofDeletion;
 use gloo::events::EventListener;
 use gloo::events::EventListenerOptions;
+use gloo::timers::callback::Timeout;
 use wasm_bindgen::UnwrapThrowExt;
+use web_sys::ScrollIntoViewOptions;
+use web_sys::ScrollLogicalPosition;
 use yew::html::Scope;
 
+use std::cell::RefCell;
 use std::collections::BTreeSet;
+use std::collections::HashMap;
+use std::collections::HashSet;
 use std::fmt;
 use std::mem;
+use std::ops::RangeInclusive;
 
 use frunk_core::coproduct::Coproduct;
 use frunk_core::Coprod;
 use strum::IntoEnumIterator;
 use yew::prelude::*;
 
-use web_sys::HtmlElement;
-
+use wasm_bindgen::prelude::wasm_bindgen;
 use wasm_bindgen::JsCast;
+use wasm_bindgen::JsValue;
 
 use js_sys::Math::random;
 
+#[wasm_bindgen]
+extern "C" {
+    /// `navigator.clipboard.writeText`, which web-sys only has behind the
+    /// `web_sys_unstable_apis` cfg. It throws where there's no clipboard, such
+    /// as on pages that aren't served securely.
+    #[wasm_bindgen(catch, js_namespace = ["navigator", "clipboard"], js_name = writeText)]
+    fn write_clipboard_text(text: &str) -> Result<js_sys::Promise, JsValue>;
+}
+
+/// Work allowed for checking a single line, small enough that an expensive
+/// rule like Tautological Consequence can't freeze the tab
+const LINE_CHECK_BUDGET: u64 = 1 << 20;
+
+/// Work allowed for searching for rules that justify a step, which tries many
+/// rules and dependencies, so it's cut off sooner than checking a line
+const SUGGEST_BUDGET: u64 = 1 << 18;
+
+/// The most rules suggested for a step at once
+const MAX_SUGGESTIONS: usize = 5;
+
+/// Number of keys left unused by deleted lines after which the proof is
+/// compacted, so that long editing sessions don't keep growing its pools
+const COMPACT_AFTER_TOMBSTONES: usize = 256;
+
+/// How long typing in a line has to pause before its feedback is shown, in
+/// milliseconds, so that it isn't shown as an error while it's incomplete
+const FEEDBACK_DELAY_MS: u32 = 400;
+
+/// How long the lines a clicked citation cites are flashed, in milliseconds
+const FLASH_MS: u32 = 1200;
+
 fn document() -> web_sys::Document {
     web_sys::window().expect_throw("window is undefined").document().expect_throw("document is undefined")
 }
@@ -50,10 +119,36 @@ struct SelectedLine {
     key_listener: EventListener,
 }
 
+/// Data stored for the open context menu of a line
+struct ContextMenu {
+    /// Reference to line in proof
+    line_ref: PjRef<P>,
+
+    /// Where the menu was opened, relative to the window
+    position: (i32, i32),
+
+    /// Handles for listening for clicks elsewhere on the page and for Escape,
+    /// which close the menu
+    #[allow(dead_code)]
+    listeners: [EventListener; 2],
+}
+
+/// Data stored for the line whose error's details are shown
+struct ErrorPopover {
+    /// Reference to line in proof
+    line_ref: PjRef<P>,
+
+    /// Handle for listening for clicks elsewhere on the page, which close the
+    /// details
+    #[allow(dead_code)]
+    click_listener: EventListener,
+}
+
 /// Component for editing proofs
 pub struct ProofWidget {
-    /// The proof being edited with this widget
-    prf: P,
+    /// The proof being edited with this widget, which records edits so they
+    /// can be undone
+    prf: RecordingProof<P>,
 
     /// UI-specific data associated with the proof, such as intermediate text in
     /// lines that might have parse errors
@@ -64,13 +159,95 @@ pub struct ProofWidget {
 
     /// Error message, for if there was an error parsing the proof XML. If this
     /// exists, it is displayed instead of the proof.
-    open_error: Option<String>,
+    open_error: Option<Vec<OpenProblem>>,
+
+    /// How the proof XML was converted from another version of Aris, if it was,
+    /// and what in it was ignored, which is displayed above the proof until
+    /// it's dismissed
+    open_warnings: Vec<CompatWarning>,
 
-    preblob: String,
+    /// Parts of the proof XML that couldn't be read, if the rest of it could,
+    /// which are listed above the proof
+    open_problems: Vec<String>,
+
+    /// The last messages the widget handled, shown below the proof if the
+    /// debug log is turned on
+    debug_log: DebugLog,
 
     id: String,
+
+    /// Warnings about the last action's effect on lines, such as dependencies
+    /// that were removed because a step's new rule can't use them. Each is
+    /// shown in its line's feedback cell until the next edit.
+    line_warnings: HashMap<PjRef<P>, String>,
+
+    /// Results of checking each line, so that rendering only re-checks the
+    /// lines affected by the last edit. It's filled in while rendering, which
+    /// only has shared access to the widget.
+    verification_cache: RefCell<VerificationCache<P>>,
+
+    /// Metadata the proof was opened with, such as its goals, which are
+    /// written back out when it's saved
+    metadata: ProofMetaData,
+
+    /// The line that was typed in by the last edit, if it was one, so that
+    /// typing in a line is undone all at once
+    last_edited: Option<PjRef<P>>,
+
+    /// Whether the proof has been edited since it was opened or last saved
+    dirty: bool,
+
+    /// What's typed in the search box of the open rule dropdown
+    rule_filter: String,
+
+    /// The position of the highlighted rule among the rules matching
+    /// `rule_filter`, which Enter selects
+    rule_highlight: usize,
+
+    /// The line whose error's details are shown, if any
+    error_popover: Option<ErrorPopover>,
+
+    /// Lines being edited, whose feedback isn't shown until editing pauses
+    pending_feedback: PendingFeedback<Timeout>,
+
+    /// The context menu of a line, if one is open
+    context_menu: Option<ContextMenu>,
+
+    /// The status of each of the proof's goals, found again after each update
+    goals: Vec<GoalEntry>,
+
+    /// The result of the last check of the whole proof, if it's been checked
+    check_summary: Option<CheckSummary>,
+
+    /// The rules and dependencies found to justify a step, which are offered
+    /// until the proof is edited or another line is selected
+    suggestions: Option<(PjRef<P>, Vec<Suggestion<PjRef<P>, <P as Proof>::SubproofReference>>)>,
+
+    /// The lines cited by the last citation clicked, which are flashed until
+    /// the timeout ends
+    flashed: Option<(RangeInclusive<usize>, Timeout)>,
+
+    /// A line to scroll to once the next render has put its row on the page
+    scroll_to: Option<PjRef<P>>,
+
+    /// A subproof deletion waiting to be confirmed, by a line in the subproof
+    pending_delete: Option<(PjRef<P>, SubproofDeletion)>,
+
+    /// Whether to delete subproofs without asking, which lasts until the page
+    /// is closed
+    dont_ask_delete_subproof: bool,
+
+    /// Whether the proof is rendered read-only to be printed, until the print
+    /// dialog is closed
+    printing: bool,
 }
 
+/// The most edits that can be undone
+const UNDO_LIMIT: usize = 100;
+
+/// The most messages kept in the debug log by default
+const DEBUG_LOG_LIMIT: usize = 200;
+
 /// A kind of proof structure item
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum ProofItemKind {
@@ -82,13 +259,20 @@ pub enum ProofItemKind {
     Subproof,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum LineActionKind {
     Insert { what: ProofItemKind, after: bool, relative_to: ProofItemKind },
     Delete { what: ProofItemKind },
+    Move { direction: MoveDirection },
     SetRule { rule: Rule },
+    /// Justify a step from an identical earlier line or a conjunction containing it
+    FillTrivial,
     Select,
-    ToggleDependency { dep: Coprod![PjRef<P>, <P as Proof>::SubproofReference] },
+    /// Set whether a line or subproof is a dependency of the step
+    SetDependency { to: bool, dep: Coprod![PjRef<P>, <P as Proof>::SubproofReference] },
+    /// Set the rule and all of the dependencies of the step at once, as
+    /// suggested for it
+    ApplySuggestion { rule: Rule, deps: Vec<PjRef<P>>, sdeps: Vec<<P as Proof>::SubproofReference> },
 }
 
 /// Message for `ProofWidget`
@@ -97,9 +281,86 @@ pub enum ProofWidgetMsg {
     Nop,
     LineChanged(PjRef<P>, String),
     LineAction(LineActionKind, PjRef<P>),
-    CallOnProof(Box<dyn FnOnce(&P)>),
+    CallOnProof(Box<dyn FnOnce(&P, &ProofMetaData)>),
+    /// Undo the last edit
+    Undo,
+    /// Redo the last edit that was undone
+    Redo,
+    /// Hide the notice of how the proof XML was converted
+    DismissOpenWarnings,
     /// Process keypress, handling any keyboard shortcuts
     Keypress(web_sys::KeyboardEvent),
+    /// Process a key pressed in a line's text field
+    LineKeydown(PjRef<P>, web_sys::KeyboardEvent),
+    /// The proof was saved, so it has no unsaved changes
+    MarkSaved,
+    /// Open the file that couldn't be opened as a proof with each of its lines
+    /// as a premise
+    RetryAsPremises,
+    /// Close the widget's tab
+    CloseTab,
+    /// Clear the rule dropdown's search box, since the dropdown was opened
+    ResetRuleFilter,
+    /// Filter the rules in the rule dropdown by what's typed in its search box
+    RuleFilterChanged(String),
+    /// Process a key pressed in the rule dropdown's search box of a step
+    RuleFilterKeydown(PjRef<P>, web_sys::KeyboardEvent),
+    /// Show or hide the details of a line's error
+    ToggleErrorDetails(PjRef<P>),
+    /// Hide the details of the line's error that are shown
+    CloseErrorDetails,
+    /// Show the feedback of a line, since editing it paused or it lost focus
+    ShowFeedback(PjRef<P>),
+    /// Open the context menu of a line where it was right-clicked, closing any
+    /// other one
+    OpenContextMenu(PjRef<P>, MouseEvent),
+    /// Close the open context menu
+    CloseContextMenu,
+    /// Check every line of the proof, showing a summary of what's wrong
+    CheckProof,
+    /// Print the proof, rendered read-only
+    Print,
+    /// Show the editable proof again after printing it
+    PrintDone,
+    /// Search for rules and dependencies that justify a step
+    Suggest(PjRef<P>),
+    /// Show the proof again, since the settings of the app changed
+    SettingsChanged,
+    /// Flash the lines with these numbers, since a citation of them was
+    /// clicked, and scroll to the first
+    Flash(RangeInclusive<usize>),
+    /// Select a line and scroll to it
+    GoToLine(PjRef<P>),
+    /// Stop flashing the lines cited by the last citation clicked
+    FlashDone,
+    /// Delete the subproof around a line, asking first if it's more than its
+    /// assumption
+    DeleteSubproof(PjRef<P>),
+    /// Make the subproof deletion that was asked about, and stop asking if
+    /// `dont_ask_again`
+    ConfirmDeleteSubproof {
+        dont_ask_again: bool,
+    },
+    CancelDeleteSubproof,
+}
+
+impl ProofWidgetMsg {
+    /// Whether handling the message replaces the proof, so that every line has
+    /// to be renumbered. Line actions that insert, delete, or move lines
+    /// renumber them as they go, and other messages don't change the line
+    /// numbers at all.
+    fn renumbers_lines(&self) -> bool {
+        matches!(self, ProofWidgetMsg::Undo | ProofWidgetMsg::Redo | ProofWidgetMsg::RetryAsPremises)
+    }
+
+    /// Whether the message is an edit of the proof, which can be undone
+    fn edits_proof(&self) -> bool {
+        match self {
+            ProofWidgetMsg::LineAction(LineActionKind::Select, _) => false,
+            ProofWidgetMsg::LineChanged(..) | ProofWidgetMsg::LineAction(..) => true,
+            _ => false,
+        }
+    }
 }
 
 impl fmt::Debug for ProofWidgetMsg {
@@ -110,16 +371,62 @@ impl fmt::Debug for ProofWidgetMsg {
             LineChanged(r, s) => f.debug_tuple("LineChanged").field(&r).field(&s).finish(),
             LineAction(lak, r) => f.debug_tuple("LineAction").field(&lak).field(&r).finish(),
             CallOnProof(_) => f.debug_struct("CallOnProof").finish(),
+            Undo => f.debug_struct("Undo").finish(),
+            Redo => f.debug_struct("Redo").finish(),
+            DismissOpenWarnings => f.debug_struct("DismissOpenWarnings").finish(),
             Keypress(key_event) => f.debug_tuple("Keypress").field(&key_event).finish(),
+            LineKeydown(r, key_event) => f.debug_tuple("LineKeydown").field(&r).field(&key_event).finish(),
+            MarkSaved => f.debug_struct("MarkSaved").finish(),
+            RetryAsPremises => f.debug_struct("RetryAsPremises").finish(),
+            CloseTab => f.debug_struct("CloseTab").finish(),
+            ResetRuleFilter => f.debug_struct("ResetRuleFilter").finish(),
+            RuleFilterChanged(filter) => f.debug_tuple("RuleFilterChanged").field(&filter).finish(),
+            RuleFilterKeydown(r, key_event) => f.debug_tuple("RuleFilterKeydown").field(&r).field(&key_event).finish(),
+            ToggleErrorDetails(r) => f.debug_tuple("ToggleErrorDetails").field(&r).finish(),
+            CloseErrorDetails => f.debug_struct("CloseErrorDetails").finish(),
+            ShowFeedback(r) => f.debug_tuple("ShowFeedback").field(&r).finish(),
+            OpenContextMenu(r, mouse_event) => f.debug_tuple("OpenContextMenu").field(&r).field(&mouse_event).finish(),
+            CloseContextMenu => f.debug_struct("CloseContextMenu").finish(),
+            CheckProof => f.debug_struct("CheckProof").finish(),
+            Print => f.debug_struct("Print").finish(),
+            PrintDone => f.debug_struct("PrintDone").finish(),
+            Suggest(r) => f.debug_tuple("Suggest").field(&r).finish(),
+            SettingsChanged => f.debug_struct("SettingsChanged").finish(),
+            Flash(lines) => f.debug_tuple("Flash").field(&lines).finish(),
+            FlashDone => f.debug_struct("FlashDone").finish(),
+            GoToLine(r) => f.debug_tuple("GoToLine").field(&r).finish(),
+            DeleteSubproof(r) => f.debug_tuple("DeleteSubproof").field(&r).finish(),
+            ConfirmDeleteSubproof { dont_ask_again } => f.debug_struct("ConfirmDeleteSubproof").field("dont_ask_again", &dont_ask_again).finish(),
+            CancelDeleteSubproof => f.debug_struct("CancelDeleteSubproof").finish(),
         }
     }
 }
 
 #[derive(Clone, Properties, PartialEq)]
 pub struct ProofWidgetProps {
+    /// Whether the widget can show a debug log, if it's turned on in the
+    /// settings
     pub verbose: bool,
+    /// The most messages kept in the debug log
+    #[prop_or(DEBUG_LOG_LIMIT)]
+    pub debug_log_limit: usize,
     pub data: Option<Vec<u8>>,
     pub oncreate: Callback<Scope<ProofWidget>>,
+    /// Callback to call with whether the proof has unsaved changes, whenever
+    /// that changes
+    #[prop_or_default]
+    pub ondirty: Option<Callback<bool>>,
+    /// Whether the proof starts out with unsaved changes, as when it's
+    /// restored from an autosave
+    #[prop_or_default]
+    pub unsaved: bool,
+    /// Callback to call to close the widget's tab
+    #[prop_or_default]
+    pub onclose: Option<Callback<()>>,
+    /// Whether the widget's tab is the current one. A hidden widget keeps its
+    /// proof, but doesn't render or check it until it's shown again.
+    #[prop_or(true)]
+    pub visible: bool,
 }
 
 impl ProofWidget {
@@ -128,34 +435,98 @@ impl ProofWidget {
             Some(line) => line.to_string(),
             None => "".to_string(),
         };
-        if let Some(selected_line) = &self.selected_line {
-            use Coproduct::{Inl, Inr};
-            if let Inr(Inl(_)) = selected_line.line_ref {
-                let line_ref = selected_line.line_ref;
-                let toggle_dep = ctx.link().callback(move |_| ProofWidgetMsg::LineAction(LineActionKind::ToggleDependency { dep: proofref }, line_ref));
-                if self.prf.can_reference_dep(&line_ref, &proofref) {
-                    return html! {
-                        <button
-                            type="button"
-                            class="btn btn-secondary"
-                            onclick={ toggle_dep }>
-
-                            { line }
-                        </button>
-                    };
-                }
-            }
+        let used_by = self.render_used_by(proofref);
+        if let Some((choice, is_dep, onclick)) = self.dep_offer(ctx, proofref) {
+            let title = match &choice {
+                DepChoice::NeedsRule => "Select a rule first".to_string(),
+                DepChoice::Full(max) => format!("This rule takes only {max} {}", if *max == 1 { "dependency" } else { "dependencies" }),
+                _ => used_by,
+            };
+            let class = if is_dep { "btn btn-primary" } else { "btn btn-secondary" };
+            return html! {
+                <button
+                    type="button"
+                    {class}
+                    title={ title }
+                    disabled={ matches!(choice, DepChoice::Full(_)) }
+                    {onclick}>
+
+                    { line }
+                </button>
+            };
         }
         html! {
             <button
                 type="button"
                 class="btn"
+                title={ used_by }
                 disabled=true>
 
                 { line }
             </button>
         }
     }
+    /// How a line or subproof is offered as a dependency of the selected step,
+    /// with whether it's one already and the callback toggling it, or `None`
+    /// if the step can't depend on it
+    fn dep_offer(&self, ctx: &Context<Self>, proofref: Coprod!(PjRef<P>, <P as Proof>::SubproofReference)) -> Option<(DepChoice, bool, Callback<MouseEvent>)> {
+        use Coproduct::{Inl, Inr};
+        let (lak, line_ref) = dep_click_action(&self.prf, self.selected_ref(), proofref)?;
+        let is_dep = matches!(lak, LineActionKind::SetDependency { to: false, .. });
+        // Only offer the kinds of dependencies the step's rule can use, as
+        // many as it takes
+        let Justification(_, rule, deps, sdeps) = match line_ref {
+            Inr(Inl(jr)) => self.prf.lookup_step(&jr)?,
+            _ => return None,
+        };
+        let choice = match proofref {
+            Inl(_) => dep_choice(rule, rule.num_deps(), is_dep, deps.len()),
+            Inr(Inl(_)) => dep_choice(rule, rule.num_subdeps(), is_dep, sdeps.len()),
+            Inr(Inr(void)) => match void {},
+        };
+        if choice == DepChoice::Hidden {
+            return None;
+        }
+        // The number's cell or row toggles it too, so the click stops here
+        let onclick = ctx.link().callback(move |e: MouseEvent| {
+            e.stop_propagation();
+            ProofWidgetMsg::LineAction(lak.clone(), line_ref)
+        });
+        Some((choice, is_dep, onclick))
+    }
+    /// The class of the cell with a line's or subproof's number, and the
+    /// callback for clicking it to toggle it as a dependency of the selected
+    /// step. It's highlighted if it's a dependency, and dimmed if the step
+    /// can't depend on it.
+    fn dep_cell(&self, ctx: &Context<Self>, proofref: Coprod!(PjRef<P>, <P as Proof>::SubproofReference)) -> (&'static str, Option<Callback<MouseEvent>>) {
+        use Coproduct::{Inl, Inr};
+        match self.dep_offer(ctx, proofref) {
+            Some((DepChoice::Full(_), _, _)) => ("", None),
+            Some((_, true, onclick)) => ("table-primary dep-clickable", Some(onclick)),
+            Some((_, false, onclick)) => ("dep-clickable", Some(onclick)),
+            None => match self.selected_ref() {
+                Some(line_ref @ Inr(Inl(_))) if !self.prf.can_reference_dep(&line_ref, &proofref) => ("dep-out-of-scope", None),
+                _ => ("", None),
+            },
+        }
+    }
+    /// Describe which lines cite a line or subproof, for its line number's
+    /// tooltip
+    fn render_used_by(&self, proofref: Coprod!(PjRef<P>, <P as Proof>::SubproofReference)) -> String {
+        use Coproduct::{Inl, Inr};
+        let dependents = match proofref {
+            Inl(lr) => self.prf.dependents_of(&lr),
+            Inr(Inl(sr)) => self.prf.dependents_of_subproof(&sr),
+            Inr(Inr(void)) => match void {},
+        };
+        let mut line_nums = dependents.into_iter().filter_map(|jr| self.pud.ref_to_line_depth.get(&Coproduct::inject(jr))).map(|(line, _)| *line).collect::<Vec<_>>();
+        line_nums.sort_unstable();
+        match line_nums.len() {
+            0 => String::new(),
+            1 => format!("This line is used by line {}", line_nums[0]),
+            _ => format!("This line is used by lines {}", line_nums.iter().map(|line| line.to_string()).collect::<Vec<_>>().join(", ")),
+        }
+    }
     /// Create a drop-down menu allowing the user to select the rule used in a
     /// justification line. This uses the [Bootstrap-submenu][lib] library.
     ///
@@ -165,44 +536,69 @@ impl ProofWidget {
     ///
     /// [lib]: https://github.com/vsn4ik/bootstrap-submenu
     fn render_rules_menu(&self, ctx: &Context<Self>, jref: <P as Proof>::JustificationReference, cur_rule_name: &str) -> Html {
-        // Create menu items for rule classes
-        let menu = RuleClassification::iter()
-            .map(|rule_class| {
-                // Create menu items for rules in class
-                let rules = rule_class
-                    .rules()
+        // Create a section for each rule class with rules matching the search
+        // box, with a header over its rules
+        let sections = filter_rule_menu(&rule_menu_sections(), &self.rule_filter);
+        let mut rule_index = 0;
+        let menu = sections
+            .into_iter()
+            .enumerate()
+            .map(|(i, (rule_class, rules))| {
+                let rules = rules
+                    .into_iter()
                     .map(|rule| {
+                        let class = if rule_index == self.rule_highlight { "dropdown-item active" } else { "dropdown-item" };
+                        rule_index += 1;
                         let pjref = Coproduct::inject(jref);
                         // Create menu item for rule
                         //tooltip portion addapted from:
                         // * https://stackoverflow.com/questions/31483302/how-to-display-an-image-inside-bootstrap-tooltip
                         // * https://getbootstrap.com/docs/4.1/components/tooltips/
                         html! {
-                            <button class="dropdown-item" type="button" data-toggle="tooltip" data-placement="right" title={format!("<img id='rule-img' src='proofImages_light/{}.png'/>", rule.get_name())} onclick={ ctx.link().callback(move |_| ProofWidgetMsg::LineAction(LineActionKind::SetRule { rule }, pjref)) }>
+                            <button {class} type="button" data-toggle="tooltip" data-placement="right" title={format!("<img id='rule-img' src='proofImages_light/{}.png'/>", rule.get_name())} onclick={ ctx.link().callback(move |_| ProofWidgetMsg::LineAction(LineActionKind::SetRule { rule }, pjref)) }>
                             { rule.get_name() }
                             </button>
                         }
                     })
                     .collect::<Vec<yew::virtual_dom::VNode>>();
                 let rules = yew::virtual_dom::VList::with_children(rules, None);
-                // Create sub-menu for rule class
+                let divider = if i > 0 {
+                    html! { <div class="dropdown-divider"></div> }
+                } else {
+                    html! {}
+                };
                 html! {
-                    <div class="dropdown dropright dropdown-submenu">
-                        <button class="dropdown-item dropdown-toggle" type="button" data-toggle="dropdown"> { rule_class } </button>
-                        <div class="dropdown-menu"> { rules } </div>
-                    </div>
+                    <>
+                        { divider }
+                        <h6 class="dropdown-header">{ rule_class }</h6>
+                        { rules }
+                    </>
                 }
             })
             .collect::<Vec<yew::virtual_dom::VNode>>();
         let menu = yew::virtual_dom::VList::with_children(menu, None);
+        let no_matches = if rule_index == 0 {
+            html! { <span class="dropdown-item-text text-muted">{ "No rules match" }</span> }
+        } else {
+            html! {}
+        };
+
+        let pjref = Coproduct::inject(jref);
+        let oninput = ctx.link().callback(|e: InputEvent| ProofWidgetMsg::RuleFilterChanged(e.target_unchecked_into::<web_sys::HtmlInputElement>().value()));
+        let onkeydown = ctx.link().callback(move |e: KeyboardEvent| ProofWidgetMsg::RuleFilterKeydown(pjref, e));
 
         // Create top-level menu button
         html! {
             <div class="dropright">
-                <button class="btn btn-primary dropdown-toggle" type="button" data-toggle="dropdown" data-submenu="">
+                <button class="btn btn-primary dropdown-toggle" type="button" data-toggle="dropdown" data-submenu="" onclick={ ctx.link().callback(|_| ProofWidgetMsg::ResetRuleFilter) }>
                     { cur_rule_name }
                 </button>
-                <div class="dropdown-menu">
+                <div class="dropdown-menu" style="max-height: 75vh; overflow-y: auto">
+                    // Pinned above the rules as they scroll
+                    <div class="px-2 pb-2 bg-white" style="position: sticky; top: 0; z-index: 1">
+                        <input type="text" class="form-control form-control-sm" placeholder="Search rules" value={ self.rule_filter.clone() } {oninput} {onkeydown} />
+                    </div>
+                    { no_matches }
                     { menu }
                 </div>
             </div>
@@ -211,42 +607,19 @@ impl ProofWidget {
     fn render_justification_widget(&self, ctx: &Context<Self>, jref: <P as Proof>::JustificationReference) -> Html {
         let just = self.prf.lookup_justification_or_die(&jref).expect("proofref should exist in self.prf");
 
-        // Iterator over line dependency badges, for rendering list of
-        // dependencies
-        let dep_badges = just.2.iter().map(|dep| {
-            let (dep_line, _) = self.pud.ref_to_line_depth[dep];
-            html! {
-                <span class="badge badge-dark m-1"> { dep_line } </span>
-            }
-        });
-
-        // Iterator over subproof dependency badges, for rendering list of
+        // Node containing a badge for each dependency, for rendering list of
         // dependencies
-        let sdep_badges = just.3.iter().filter_map(|sdep| self.prf.lookup_subproof(sdep)).map(|sub| {
-            let (mut lo, mut hi) = (usize::MAX, usize::MIN);
-            for line in sub.premises().into_iter().map(Coproduct::inject).chain(sub.direct_lines().into_iter().map(Coproduct::inject)) {
-                if let Some((i, _)) = self.pud.ref_to_line_depth.get(&line) {
-                    lo = std::cmp::min(lo, *i);
-                    hi = std::cmp::max(hi, *i);
-                }
-            }
-            let sdep_line = format!("{lo}-{hi}");
-            html! {
-                <span class="badge badge-secondary m-1"> { sdep_line } </span>
-            }
-        });
-
-        // Node containing all dependency badges, for rendering list of
-        // dependencies
-        let all_dep_badges = dep_badges.chain(sdep_badges).collect::<Html>();
+        let all_dep_badges = just.citations(&self.prf, |r| self.pud.ref_to_line_depth.get(r).map(|(line, _)| *line)).into_iter().map(|citation| self.render_citation(ctx, citation)).collect::<Html>();
 
         let cur_rule_name = just.1.get_name();
         let rule_selector = self.render_rules_menu(ctx, jref, &cur_rule_name);
+        let suggestions = self.render_suggestions(ctx, Coproduct::inject(jref));
         html! {
             <>
                 <td>
                     // Drop-down menu for selecting rules
                     { rule_selector }
+                    { suggestions }
                 </td>
                 <td>
                     // Dependency list
@@ -257,7 +630,79 @@ impl ProofWidget {
             </>
         }
     }
-    fn render_line_feedback(&self, proofref: PjRef<P>, is_subproof: bool) -> Html {
+    /// Render a dependency of a step as a badge, which goes to the lines it
+    /// cites when clicked and shows what they are when hovered over
+    fn render_citation(&self, ctx: &Context<Self>, citation: Citation<PjRef<P>, <P as Proof>::SubproofReference>) -> Html {
+        let label = citation.to_string();
+        let (class, title, lines) = match &citation {
+            Citation::Line { dep, line } => ("badge badge-dark m-1 citation", self.prf.lookup_expr(dep).map(|expr| expr.display_with(crate::settings::symbols())).unwrap_or_default(), Some(*line..=*line)),
+            Citation::Lines { sdep, span } => ("badge badge-secondary m-1 citation", self.describe_subproof(sdep), Some(span.clone())),
+            Citation::MissingLine(_) => ("badge badge-danger m-1", "This line was deleted".to_string(), None),
+            Citation::MissingSubproof(_) => ("badge badge-danger m-1", "This subproof was deleted".to_string(), None),
+            Citation::EmptySubproof(_) => ("badge badge-danger m-1", "This subproof has no lines".to_string(), None),
+        };
+        match lines {
+            Some(lines) => {
+                let onclick = ctx.link().callback(move |e: MouseEvent| {
+                    e.prevent_default();
+                    ProofWidgetMsg::Flash(lines.clone())
+                });
+                html! { <a href="#" { class } { title } { onclick }> { label } </a> }
+            }
+            None => html! { <span { class } { title }> { label } </span> },
+        }
+    }
+
+    /// What the subproof `sdep` assumes and what its last line is, for the
+    /// tooltip of a step citing it
+    fn describe_subproof(&self, sdep: &<P as Proof>::SubproofReference) -> String {
+        let symbols = crate::settings::symbols();
+        let sub = match self.prf.lookup_subproof(sdep) {
+            Some(sub) => sub,
+            None => return String::new(),
+        };
+        let assumption = sub.premises().first().and_then(|pr| self.prf.lookup_premise(pr)).map(|expr| expr.display_with(symbols));
+        let last = sub.lines().into_iter().rev().find_map(|line| if let Coproduct::Inl(jr) = line { self.prf.lookup_expr(&Coproduct::inject(jr)) } else { None }).map(|expr| expr.display_with(symbols));
+        match (assumption, last) {
+            (Some(assumption), Some(last)) => format!("Assumes {assumption}, concludes {last}"),
+            (Some(assumption), None) => format!("Assumes {assumption}"),
+            _ => String::new(),
+        }
+    }
+
+    /// Render the button for suggesting rules for the step `proofref`, if it's
+    /// selected and its formula parses, and what was suggested, if anything
+    fn render_suggestions(&self, ctx: &Context<Self>, proofref: PjRef<P>) -> Html {
+        let selected = self.selected_ref() == Some(proofref);
+        let parses = self.pud.ref_to_input.get(&proofref).and_then(|input| aris::parser::parse(input)).is_some();
+        if !selected || !parses {
+            return html! {};
+        }
+        let list = match &self.suggestions {
+            Some((r, suggestions)) if *r == proofref && suggestions.is_empty() => html! {
+                <div class="small text-muted"> { "No rule was found that justifies this step" } </div>
+            },
+            Some((r, suggestions)) if *r == proofref => suggestions
+                .iter()
+                .map(|suggestion| {
+                    let Suggestion { rule, deps, sdeps } = suggestion.clone();
+                    let onclick = ctx.link().callback(move |_| ProofWidgetMsg::LineAction(LineActionKind::ApplySuggestion { rule, deps: deps.clone(), sdeps: sdeps.clone() }, proofref));
+                    html! {
+                        <button type="button" class="list-group-item list-group-item-action py-1 small" { onclick }> { describe_suggestion(&self.prf, &self.pud, suggestion) } </button>
+                    }
+                })
+                .collect::<Html>(),
+            _ => html! {},
+        };
+        html! {
+            <div class="mt-1">
+                <button type="button" class="btn btn-sm btn-outline-secondary" title="Find rules and dependencies that justify this step" onclick={ ctx.link().callback(move |_| ProofWidgetMsg::Suggest(proofref)) }> { "Suggest" } </button>
+                <div class="list-group mt-1"> { list } </div>
+            </div>
+        }
+    }
+
+    fn render_line_feedback(&self, ctx: &Context<Self>, proofref: PjRef<P>, is_subproof: bool) -> Html {
         use aris::parser::parse;
         let raw_line = match self.pud.ref_to_input.get(&proofref).and_then(|x| if !x.is_empty() { Some(x) } else { None }) {
             None => {
@@ -265,10 +710,17 @@ impl ProofWidget {
             }
             Some(x) => x,
         };
-        match parse(raw_line).map(|_| self.prf.verify_line(&proofref)) {
+        // A line that's being typed in is incomplete, so it isn't checked until
+        // editing pauses
+        if self.pending_feedback.is_pending(&proofref) {
+            return html! { <span class="alert small-alert text-muted s1">{ "…" }</span> };
+        }
+        let check_ctx = CheckContext { budget: Budget::new(LINE_CHECK_BUDGET), ..CheckContext::default() };
+        match parse(raw_line).map(|_| self.verification_cache.borrow_mut().get_or_verify_with(&self.prf, &proofref, &check_ctx)) {
             None => {
                 html! { <span class="alert alert-warning small-alert s1">{ "Parse error" }</span> }
             }
+            Some(Ok(())) if self.line_warnings.contains_key(&proofref) => self.render_line_warning(proofref),
             Some(Ok(())) => match proofref {
                 Coproduct::Inl(_) => html! {
                     <span class="alert alert-success small-alert s2">
@@ -280,63 +732,121 @@ impl ProofWidget {
                 }
             },
             Some(Err(err)) => {
+                let warning = self.render_line_warning(proofref);
+                let is_open = self.error_popover.as_ref().map(|popover| popover.line_ref == proofref).unwrap_or(false);
+                let details = if is_open {
+                    render_error_details(&error_details(&err, &self.prf, &self.pud))
+                } else {
+                    html! {}
+                };
+                // Clicks on the badge and the details don't reach the page,
+                // which would close them
+                let toggle_details = ctx.link().callback(move |e: MouseEvent| {
+                    e.stop_propagation();
+                    ProofWidgetMsg::ToggleErrorDetails(proofref)
+                });
                 html! {
-                    <button type="button" class="btn btn-danger s1" data-toggle="popover" data-content={ err.to_string() }>
-                        { "Error" }
-                    </button>
+                    <>
+                        <span class="error-popover-anchor" onclick={ Callback::from(|e: MouseEvent| e.stop_propagation()) }>
+                            <button type="button" class="btn btn-danger s1" aria-expanded={ is_open.to_string() } onclick={ toggle_details }>
+                                { "Error" }
+                            </button>
+                            { details }
+                        </span>
+                        { warning }
+                    </>
+                }
+            }
+        }
+    }
+    /// Render the items of a menu of the valid actions of a line, which is
+    /// both its dropdown and its context menu
+    fn render_action_items(&self, ctx: &Context<Self>, proofref: PjRef<P>) -> Html {
+        actions::valid_actions(&self.prf, proofref, self.is_locked(&proofref))
+            .map(|action_info| {
+                let lak = action_info.line_action_kind.clone();
+
+                // Callback triggering line action
+                let onclick = ctx.link().callback(move |_| line_action_message(lak.clone(), proofref));
+
+                // Badge showing keyboard shortcut of action, if any
+                let keyboard_shortcut = match action_info.keyboard_shortcut {
+                    Some(key) => {
+                        html! {
+                            <span>
+                                <kbd>
+                                    <kbd> { "Ctrl" } </kbd>
+                                    { '-' }
+                                    <kbd> { key.to_uppercase() } </kbd>
+                                </kbd>
+                            </span>
+                        }
+                    }
+                    None => html!(),
+                };
+
+                // Item in line actions menu
+                html! {
+                    <a class="dropdown-item" href="#" onclick={ onclick }>
+                        { action_info.description }
+                        { ' ' }
+                        { keyboard_shortcut }
+                    </a>
+                }
+            })
+            .collect::<Html>()
+    }
+    /// Render the open context menu of a line, if there is one, where the line
+    /// was right-clicked
+    fn render_context_menu(&self, ctx: &Context<Self>) -> Html {
+        match &self.context_menu {
+            // The line might have been deleted by an action in another menu
+            Some(menu) if self.prf.lookup_pj(&menu.line_ref).is_some() => {
+                let (x, y) = menu.position;
+                html! {
+                    <div class="dropdown-menu show" role="menu" style={ format!("position: fixed; left: {x}px; top: {y}px") }>
+                        { self.render_action_items(ctx, menu.line_ref) }
+                    </div>
                 }
             }
+            _ => html! {},
+        }
+    }
+    /// Render the dialog asking whether to delete a subproof, if one is
+    /// waiting to be deleted
+    fn render_confirm_delete(&self, ctx: &Context<Self>) -> Html {
+        match &self.pending_delete {
+            Some((_, deletion)) => {
+                let onconfirm = ctx.link().callback(|dont_ask_again| ProofWidgetMsg::ConfirmDeleteSubproof { dont_ask_again });
+                let oncancel = ctx.link().callback(|()| ProofWidgetMsg::CancelDeleteSubproof);
+                html! { <ConfirmDeleteDialog deletion={ deletion.clone() } { onconfirm } { oncancel } /> }
+            }
+            None => html! {},
+        }
+    }
+    fn render_line_warning(&self, proofref: PjRef<P>) -> Html {
+        match self.line_warnings.get(&proofref) {
+            Some(warning) => html! {
+                <span class="alert alert-warning small-alert s1">
+                    { warning }
+                </span>
+            },
+            None => html!(),
         }
     }
-    fn render_proof_line(&self, ctx: &Context<Self>, line: usize, depth: usize, proofref: PjRef<P>, edge_decoration: &str) -> Html {
+    fn render_proof_line(&self, ctx: &Context<Self>, line: usize, depth: usize, proofref: PjRef<P>, last_premise: bool, highlight: &HighlightSet) -> Html {
         use Coproduct::{Inl, Inr};
         let line_num_dep_checkbox = self.render_line_num_dep_checkbox(ctx, Some(line), Coproduct::inject(proofref));
-        let mut indentation = yew::virtual_dom::VList::new();
-        for _ in 0..depth {
-            //indentation.add_child(html! { <span style="background-color:black">{"-"}</span>});
-            //indentation.add_child(html! { <span style="color:white">{"-"}</span>});
-            indentation.add_child(html! { <span class="indent"> { box_chars::VERT } </span>});
-        }
-        indentation.add_child(html! { <span class="indent">{edge_decoration}</span>});
+        let (dep_cell_class, dep_cell_onclick) = self.dep_cell(ctx, Coproduct::inject(proofref));
         let handle_input = ctx.link().callback(move |value: String| ProofWidgetMsg::LineChanged(proofref, value));
         let select_line = ctx.link().callback(move |()| ProofWidgetMsg::LineAction(LineActionKind::Select, proofref));
+        let handle_keydown = ctx.link().callback(move |key_event| ProofWidgetMsg::LineKeydown(proofref, key_event));
+        let show_feedback = ctx.link().callback(move |()| ProofWidgetMsg::ShowFeedback(proofref));
+        let open_context_menu = ctx.link().callback(move |mouse_event| ProofWidgetMsg::OpenContextMenu(proofref, mouse_event));
 
         // Menu for selecting a line action
         let action_selector = {
-            // List of menu items
-            let options = actions::valid_actions(&self.prf, proofref)
-                .map(|action_info| {
-                    let lak = action_info.line_action_kind.clone();
-
-                    // Callback triggering line action
-                    let onclick = ctx.link().callback(move |_| ProofWidgetMsg::LineAction(lak.clone(), proofref));
-
-                    // Badge showing keyboard shortcut of action, if any
-                    let keyboard_shortcut = match action_info.keyboard_shortcut {
-                        Some(key) => {
-                            html! {
-                                <span>
-                                    <kbd>
-                                        <kbd> { "Ctrl" } </kbd>
-                                        { '-' }
-                                        <kbd> { key.to_uppercase() } </kbd>
-                                    </kbd>
-                                </span>
-                            }
-                        }
-                        None => html!(),
-                    };
-
-                    // Item in line actions menu
-                    html! {
-                        <a class="dropdown-item" href="#" onclick={ onclick }>
-                            { action_info.description }
-                            { ' ' }
-                            { keyboard_shortcut }
-                        </a>
-                    }
-                })
-                .collect::<Vec<Html>>();
+            let options = self.render_action_items(ctx, proofref);
 
             // Menu for selecting a line action
             html! {
@@ -358,23 +868,20 @@ impl ProofWidget {
             }
         };
         let init_value = self.pud.ref_to_input.get(&proofref).cloned().unwrap_or_default();
+        let locked = self.is_locked(&proofref);
         let in_subproof = depth > 0;
-        let rule_feedback = self.render_line_feedback(proofref, in_subproof);
-        let is_selected_line = self.selected_line.as_ref().map(|line| line.line_ref == proofref).unwrap_or(false);
-        let is_dep_line = match self.selected_line {
-            Some(SelectedLine { line_ref: Inr(Inl(selected_line)), .. }) => match self.prf.lookup_justification_or_die(&selected_line) {
-                Ok(Justification(_, _, line_deps, _)) => line_deps.contains(&proofref),
-                Err(_) => false,
-            },
-            _ => false,
-        };
+        let rule_feedback = self.render_line_feedback(ctx, proofref, in_subproof);
+        let is_selected_line = self.selected_ref() == Some(proofref);
         let class = if is_selected_line {
             "proof-line table-info"
-        } else if is_dep_line {
+        } else if highlight.contains(&proofref) {
             "proof-line table-secondary"
         } else {
             "proof-line"
         };
+        // The lines cited by a citation that was just clicked
+        let flashed = self.flashed.as_ref().map(|(lines, _)| lines.contains(&line)).unwrap_or(false);
+        let class = if flashed { format!("{class} flashed") } else { class.to_string() };
         let feedback_and_just_widgets = match proofref {
             Inl(_) => {
                 // Premise
@@ -398,17 +905,29 @@ impl ProofWidget {
             Inr(Inr(void)) => match void {},
         };
         let id_num = format!("{}{}{}", self.id, &"line-number-", &line.to_string());
+        // Rows are keyed by their lines, so that editing one doesn't replace
+        // the rows after it and lose the page's scroll position
+        let row_id = self.row_id(&proofref);
+        let key = row_id.clone().unwrap_or_else(|| format!("line-{line}"));
         html! {
-            <tr class={ class }>
-                <td> { line_num_dep_checkbox } </td>
-                <td>
-                    { indentation }
-                    <ExprEntry
-                        oninput={ handle_input }
-                        onfocus={ select_line }
-                        focus={ is_selected_line }
-                        init_value={ init_value }
-                        id={ id_num }/>
+            <tr id={ row_id } key={ key } class={ class } oncontextmenu={ open_context_menu }>
+                <td class={ dep_cell_class } onclick={ dep_cell_onclick }> { line_num_dep_checkbox } </td>
+                <td class="scope-cell">
+                    { render_scope_bars(depth, last_premise, html! {
+                        <>
+                            <ExprEntry
+                                oninput={ handle_input }
+                                onfocus={ select_line }
+                                onkeydown={ handle_keydown }
+                                onblur={ show_feedback }
+                                focus={ is_selected_line }
+                                init_value={ init_value }
+                                disabled={ locked }
+                                id={ id_num }
+                                palette_fallback=true/>
+                            { self.render_line_comment(&proofref) }
+                        </>
+                    }) }
                 </td>
                 { feedback_and_just_widgets }
                 <td>{ action_selector }</td>
@@ -416,44 +935,53 @@ impl ProofWidget {
         }
     }
 
-    fn render_proof(&self, ctx: &Context<Self>, prf: &<P as Proof>::Subproof, sref: Option<<P as Proof>::SubproofReference>, line: &mut usize, depth: &mut usize) -> Html {
+    fn render_proof(&self, ctx: &Context<Self>, prf: &<P as Proof>::Subproof, sref: Option<<P as Proof>::SubproofReference>, line: &mut usize, depth: &mut usize, highlight: &HighlightSet) -> Html {
         // output has a bool tag to prune subproof spacers with, because VNode's PartialEq doesn't do the right thing
         let mut output: Vec<(Html, bool)> = Vec::new();
-        for prem in prf.premises().iter() {
-            let edge_decoration = { box_chars::VERT }.to_string();
-            output.push((self.render_proof_line(ctx, *line, *depth, Coproduct::inject(*prem), &edge_decoration), false));
+        let premises = prf.premises();
+        for (i, prem) in premises.iter().enumerate() {
+            // The last premise has the bar under the premises
+            let last_premise = i == premises.len() - 1;
+            output.push((self.render_proof_line(ctx, *line, *depth, Coproduct::inject(*prem), last_premise, highlight), false));
             *line += 1;
         }
         let dep_checkbox = match sref {
             Some(sr) => self.render_line_num_dep_checkbox(ctx, None, Coproduct::inject(sr)),
             None => yew::virtual_dom::VNode::from(yew::virtual_dom::VList::new()),
         };
+        // Clicking anywhere in the spacer row of a subproof toggles it as a
+        // dependency
+        let (dep_cell_class, dep_cell_onclick) = match sref {
+            Some(sr) => self.dep_cell(ctx, Coproduct::inject(sr)),
+            None => ("", None),
+        };
         let mut spacer = yew::virtual_dom::VList::new();
-        spacer.add_child(html! { <td>{ dep_checkbox }</td> });
-        //spacer.add_child(html! { <td style="background-color:black"></td> });
-        let mut spacer_lines = String::new();
-        for _ in 0..*depth {
-            spacer_lines.push(box_chars::VERT);
-        }
-        spacer_lines += &format!("{}{}", box_chars::VERT_RIGHT, box_chars::HORIZ.to_string().repeat(4));
-        spacer.add_child(html! { <td> <span class="indent"> {spacer_lines} </span> </td> });
+        spacer.add_child(html! { <td class={ dep_cell_class }>{ dep_checkbox }</td> });
+        // The bars continue through the spacer, so they're unbroken between
+        // the premises and the rest of the lines
+        spacer.add_child(html! { <td class="scope-cell">{ render_scope_bars(*depth, false, html! { <div class="scope-spacer"></div> }) }</td> });
 
-        let spacer = html! { <tr> { spacer } </tr> };
+        // Keys for the spacer and the subproof's rows, which the first premise's
+        // row identifies, since it stays in the subproof for as long as it exists
+        let key = match premises.first().and_then(|pr| self.row_id(&Coproduct::inject(*pr))) {
+            Some(row_id) => format!("subproof-{row_id}"),
+            None => format!("subproof-line-{line}"),
+        };
+        let spacer = html! { <tr key={ format!("spacer-{key}") } onclick={ dep_cell_onclick }> { spacer } </tr> };
 
         output.push((spacer, false));
         let prf_lines = prf.lines();
-        for (i, lineref) in prf_lines.iter().enumerate() {
+        for lineref in prf_lines.iter() {
             use Coproduct::{Inl, Inr};
-            let edge_decoration = if i == prf_lines.len() - 1 { box_chars::UP_RIGHT } else { box_chars::VERT }.to_string();
             match lineref {
                 Inl(r) => {
-                    output.push((self.render_proof_line(ctx, *line, *depth, Coproduct::inject(*r), &edge_decoration), false));
+                    output.push((self.render_proof_line(ctx, *line, *depth, Coproduct::inject(*r), false, highlight), false));
                     *line += 1;
                 }
                 Inr(Inl(sr)) => {
                     *depth += 1;
                     //output.push(row_spacer.clone());
-                    output.push((self.render_proof(ctx, &prf.lookup_subproof(sr).unwrap(), Some(*sr), line, depth), false));
+                    output.push((self.render_proof(ctx, &prf.lookup_subproof(sr).unwrap(), Some(*sr), line, depth, highlight), false));
                     //output.push(row_spacer.clone());
                     *depth -= 1;
                 }
@@ -473,17 +1001,226 @@ impl ProofWidget {
             }
         }
         let output: Vec<Html> = output.into_iter().map(|(x, _)| x).collect();
-        let output = yew::virtual_dom::VList::with_children(output, None);
+        let output = yew::virtual_dom::VList::with_children(output, Some(key.into()));
         if *depth == 0 {
-            html! { <table>{ output }</table> }
+            html! { <table class="proof-table">{ output }</table> }
         } else {
             yew::virtual_dom::VNode::from(output)
         }
     }
 
+    /// The id of the row of the line `r`, if it has a stable id
+    fn row_id(&self, r: &PjRef<P>) -> Option<String> {
+        self.prf.stable_id_of(r).map(|line_id| row_dom_id(&self.id, line_id))
+    }
+
+    /// Whether the line is locked by the file it was loaded from, so that it
+    /// can't be changed or deleted
+    fn is_locked(&self, r: &PjRef<P>) -> bool {
+        self.prf.stable_id_of(r).map(|id| self.metadata.locked.contains(&id)).unwrap_or(false)
+    }
+
+    /// The first locked line, in proof order, that deleting `what` at the line
+    /// `r` would remove: `r` itself, or any line of the subproof containing it
+    fn locked_line_deleted_by(&self, what: ProofItemKind, r: &PjRef<P>) -> Option<PjRef<P>> {
+        match what {
+            ProofItemKind::Premise | ProofItemKind::Just => Some(*r).filter(|r| self.is_locked(r)),
+            ProofItemKind::Subproof => {
+                let contained = self.prf.parent_of_line(&pj_to_pjs::<P>(*r)).and_then(|sr| self.prf.lookup_subproof(&sr))?.contained_justifications(true);
+                self.prf.iter_lines().filter_map(|info| info.reference.subset().ok()).find(|line: &PjRef<P>| contained.contains(line) && self.is_locked(line))
+            }
+        }
+    }
+
+    /// Set whether the proof has unsaved changes, telling the parent if that
+    /// changed
+    fn set_dirty(&mut self, ctx: &Context<Self>, dirty: bool) {
+        if self.dirty != dirty {
+            self.dirty = dirty;
+            if let Some(ondirty) = &ctx.props().ondirty {
+                ondirty.emit(dirty);
+            }
+        }
+    }
+
+    /// Undo or redo an edit with `step`, following the lines it put back or
+    /// moved. Returns false if there was nothing to undo or redo.
+    fn step_history(&mut self, ctx: &Context<Self>, step: impl FnOnce(&mut RecordingProof<P>) -> Option<HashMap<PjsRef<P>, PjsRef<P>>>) -> bool {
+        let renames = match undo_or_redo(&mut self.prf, &mut self.pud, crate::settings::symbols(), step) {
+            Some(renames) => renames,
+            None => return false,
+        };
+        let selected = self.selected_line.as_ref().map(|line| renamed(&renames, line.line_ref)).filter(|r| self.prf.lookup_expr(r).is_some());
+        match selected {
+            Some(line_ref) => self.select_line(ctx, line_ref),
+            None => self.selected_line = None,
+        }
+        self.line_warnings.clear();
+        self.last_edited = None;
+        self.verification_cache.get_mut().clear();
+        self.set_dirty(ctx, true);
+        self.mark_check_stale();
+        true
+    }
+
+    /// Ask for the proof to be checked again, since it was edited
+    fn mark_check_stale(&mut self) {
+        if let Some(summary) = &mut self.check_summary {
+            summary.stale = true;
+        }
+    }
+
+    /// Render the proof's goals, each with whether it's been reached. Clicking
+    /// a reached goal selects and scrolls to the line reaching it. Nothing is
+    /// rendered for proofs without goals.
+    fn render_goal_panel(&self, ctx: &Context<Self>) -> Html {
+        if self.goals.is_empty() {
+            return html! {};
+        }
+        let line_of = |r: &PjRef<P>| self.pud.ref_to_line_depth.get(r).map(|(line, _)| *line).unwrap_or_default();
+        let goals = self
+            .goals
+            .iter()
+            .map(|entry| {
+                let (class, status, title, onclick) = match &entry.status {
+                    GoalStatus::Reached(r) => ("badge badge-success goal-reached", format!("Reached on line {}", line_of(r)), "Go to this line".to_string(), Some(self.go_to_line(ctx, *r))),
+                    GoalStatus::ReachedButUnverified(r) => ("badge badge-warning", format!("Line {} doesn't check", line_of(r)), "Fix the line concluding this goal to reach it".to_string(), None),
+                    GoalStatus::NotReached if entry.in_subproof => ("badge badge-danger", "Not reached".to_string(), "Goals are only reached by lines outside of any subproof".to_string(), None),
+                    GoalStatus::NotReached => ("badge badge-danger", "Not reached".to_string(), "No line concludes this goal yet".to_string(), None),
+                };
+                let hint = if entry.in_subproof {
+                    html! { <small class="text-muted ml-2"> { "Only reached inside a subproof" } </small> }
+                } else {
+                    html! {}
+                };
+                html! {
+                    <li class="list-group-item">
+                        <span class="mr-2"> { entry.goal.display_with(crate::settings::symbols()) } </span>
+                        <span class={ class } title={ title } onclick={ onclick }> { status } </span>
+                        { hint }
+                    </li>
+                }
+            })
+            .collect::<Html>();
+        html! {
+            <div class="card goal-panel m-4">
+                <div class="card-header"> { "Goals" } </div>
+                <ul class="list-group list-group-flush">
+                    { goals }
+                </ul>
+            </div>
+        }
+    }
+
+    /// A callback that selects a line and scrolls to it
+    fn go_to_line(&self, ctx: &Context<Self>, r: PjRef<P>) -> Callback<MouseEvent> {
+        ctx.link().callback(move |_: MouseEvent| ProofWidgetMsg::GoToLine(r))
+    }
+
+    /// Render buttons for undoing and redoing edits, and for checking the
+    /// whole proof
+    fn render_undo_toolbar(&self, ctx: &Context<Self>) -> Html {
+        html! {
+            <div class="btn-toolbar m-2" role="toolbar">
+                <div class="btn-group btn-group-sm mr-2" role="group" aria-label="Undo and redo">
+                    <button type="button" class="btn btn-outline-secondary" title="Undo (Ctrl-Z)" disabled={ !self.prf.can_undo() } onclick={ ctx.link().callback(|_| ProofWidgetMsg::Undo) }> { "Undo" } </button>
+                    <button type="button" class="btn btn-outline-secondary" title="Redo (Ctrl-Shift-Z)" disabled={ !self.prf.can_redo() } onclick={ ctx.link().callback(|_| ProofWidgetMsg::Redo) }> { "Redo" } </button>
+                </div>
+                <button type="button" class="btn btn-sm btn-outline-primary" title="Check every line of the proof" onclick={ ctx.link().callback(|_| ProofWidgetMsg::CheckProof) }> { "Check proof" } </button>
+            </div>
+        }
+    }
+
+    /// Render the result of the last check of the whole proof, if it's been
+    /// checked, or a prompt to check it again if it's been edited since
+    fn render_check_summary(&self, ctx: &Context<Self>) -> Html {
+        let summary = match &self.check_summary {
+            Some(summary) => summary,
+            None => return html! {},
+        };
+        if summary.stale {
+            return html! {
+                <div class="alert alert-secondary m-4" role="status">
+                    { "The proof was edited since it was checked. " }
+                    <a href="#" class="alert-link" onclick={ ctx.link().callback(|e: MouseEvent| { e.prevent_default(); ProofWidgetMsg::CheckProof }) }> { "Re-check" } </a>
+                </div>
+            };
+        }
+        if summary.problems.is_empty() {
+            return html! {
+                <div class="alert alert-success m-4" role="status"> { format!("All {} lines verified ✓", summary.lines) } </div>
+            };
+        }
+        let heading = match summary.problems.len() {
+            1 => "1 problem found".to_string(),
+            n => format!("{n} problems found"),
+        };
+        let problems = summary
+            .problems
+            .iter()
+            .map(|(line, r, headline)| {
+                html! {
+                    <li>
+                        <a href="#" class="alert-link" onclick={ self.go_to_line(ctx, *r).reform(|e: MouseEvent| { e.prevent_default(); e }) }> { format!("Line {line}") } </a>
+                        { format!(": {headline}") }
+                    </li>
+                }
+            })
+            .collect::<Html>();
+        html! {
+            <div class="alert alert-danger m-4" role="status">
+                <h5 class="alert-heading"> { heading } </h5>
+                <ul class="mb-0"> { problems } </ul>
+            </div>
+        }
+    }
+
+    /// Render the comment on a line, if it has one, to go after its formula
+    fn render_line_comment(&self, r: &PjRef<P>) -> Html {
+        match self.prf.stable_id_of(r).and_then(|id| self.metadata.comments.get(&id)) {
+            Some(comment) => html! { <small class="text-muted ml-2"> { comment } </small> },
+            None => html! {},
+        }
+    }
+
+    /// Flag steps whose dependencies were removed along with a deleted line or
+    /// subproof
+    fn warn_cited_line_deleted(&mut self, affected: Vec<<P as Proof>::JustificationReference>) {
+        for jr in affected {
+            self.line_warnings.insert(Coproduct::inject(jr), "A cited line was deleted".to_string());
+        }
+    }
+
+    /// Compact the proof if enough lines have been deleted, remapping
+    /// everything that holds references into it
+    fn compact_if_sparse(&mut self) {
+        if self.prf.memory_stats().tombstones() < COMPACT_AFTER_TOMBSTONES {
+            return;
+        }
+        // Compacting renames every line, which the history can't follow
+        let remap = self.prf.edit_unrecorded(|prf| prf.compact());
+        self.pud.remap_lines(|r| remap.line(r));
+        self.line_warnings = mem::take(&mut self.line_warnings).into_iter().filter_map(|(r, warning)| Some((remap.line(&r)?, warning))).collect();
+        self.selected_line = self.selected_line.take().and_then(|SelectedLine { line_ref, key_listener }| Some(SelectedLine { line_ref: remap.line(&line_ref)?, key_listener }));
+        self.error_popover = self.error_popover.take().and_then(|ErrorPopover { line_ref, click_listener }| Some(ErrorPopover { line_ref: remap.line(&line_ref)?, click_listener }));
+        self.context_menu = self.context_menu.take().and_then(|ContextMenu { line_ref, position, listeners }| Some(ContextMenu { line_ref: remap.line(&line_ref)?, position, listeners }));
+        self.scroll_to = self.scroll_to.and_then(|r| remap.line(&r));
+        self.pending_delete = self.pending_delete.take().and_then(|(r, deletion)| Some((remap.line(&r)?, deletion)));
+        // The history was forgotten, so further typing starts a new undo
+        // group. The feedback timers and suggestions hold old references, so
+        // feedback is shown now and suggestions are found again.
+        self.last_edited = None;
+        self.pending_feedback = PendingFeedback::default();
+        self.suggestions = None;
+        self.verification_cache.borrow_mut().clear();
+    }
+
     /// Select the line referenced in `line_ref`. Also, set up a listener for
     /// line action keyboard shortcuts
     fn select_line(&mut self, ctx: &Context<Self>, line_ref: PjRef<P>) {
+        if self.suggestions.as_ref().map(|(r, _)| *r != line_ref).unwrap_or(false) {
+            self.suggestions = None;
+        }
         let callback = ctx.link().callback(ProofWidgetMsg::Keypress);
 
         let key_listener = EventListener::new_with_options(&document(), "keydown", EventListenerOptions::enable_prevent_default(), move |event: &Event| {
@@ -494,6 +1231,71 @@ impl ProofWidget {
         self.selected_line = Some(SelectedLine { line_ref, key_listener });
     }
 
+    /// The selected line, unless it's no longer in the proof, which rendering
+    /// treats as nothing being selected
+    fn selected_ref(&self) -> Option<PjRef<P>> {
+        self.selected_line.as_ref().map(|selected_line| selected_line.line_ref).filter(|line_ref| self.prf.lookup_expr(line_ref).is_some())
+    }
+
+    /// The line before or after a line in display order, if there is one
+    fn adjacent_line(&self, line_ref: PjRef<P>, direction: MoveDirection) -> Option<PjRef<P>> {
+        let lines = self.prf.iter_lines().filter_map(|info| info.reference.subset().ok()).collect::<Vec<PjRef<P>>>();
+        let index = lines.iter().position(|line| *line == line_ref)?;
+        match direction {
+            MoveDirection::Up => index.checked_sub(1).map(|index| lines[index]),
+            MoveDirection::Down => lines.get(index + 1).copied(),
+        }
+    }
+
+    /// Convert a key pressed in a line's text field into a `ProofWidgetMsg`,
+    /// for editing a proof without leaving the keyboard. The arrow keys select
+    /// the line before or after it, which focuses its text field, and the keys
+    /// of `actions::key_action` act on it.
+    fn process_line_key(&self, line_ref: PjRef<P>, key_event: web_sys::KeyboardEvent) -> ProofWidgetMsg {
+        let direction = match key_event.key().as_str() {
+            _ if key_event.ctrl_key() => None,
+            "ArrowUp" => Some(MoveDirection::Up),
+            "ArrowDown" => Some(MoveDirection::Down),
+            _ => None,
+        };
+        if let Some(direction) = direction {
+            key_event.prevent_default();
+            // There's nothing to wrap around to past the first and last lines
+            return match self.adjacent_line(line_ref, direction) {
+                Some(adjacent) => ProofWidgetMsg::LineAction(LineActionKind::Select, adjacent),
+                None => ProofWidgetMsg::Nop,
+            };
+        }
+        match actions::key_action(&self.prf, line_ref, self.is_locked(&line_ref), &key_event.key(), key_event.ctrl_key()) {
+            Some(lak) => {
+                key_event.prevent_default();
+                ProofWidgetMsg::LineAction(lak, line_ref)
+            }
+            None => ProofWidgetMsg::Nop,
+        }
+    }
+
+    /// Process a key pressed in the rule dropdown's search box, where the up
+    /// and down arrows move the highlight through the matching rules and Enter
+    /// selects the highlighted one
+    fn process_rule_filter_key(&mut self, line_ref: PjRef<P>, key_event: web_sys::KeyboardEvent) -> ProofWidgetMsg {
+        let matching = filter_rule_menu(&rule_menu_sections(), &self.rule_filter).into_iter().flat_map(|(_, rules)| rules).collect::<Vec<Rule>>();
+        match key_event.key().as_str() {
+            "ArrowDown" => self.rule_highlight = (self.rule_highlight + 1).min(matching.len().saturating_sub(1)),
+            "ArrowUp" => self.rule_highlight = self.rule_highlight.saturating_sub(1),
+            "Enter" => {
+                key_event.prevent_default();
+                return match matching.get(self.rule_highlight) {
+                    Some(&rule) => ProofWidgetMsg::LineAction(LineActionKind::SetRule { rule }, line_ref),
+                    None => ProofWidgetMsg::Nop,
+                };
+            }
+            _ => return ProofWidgetMsg::Nop,
+        }
+        key_event.prevent_default();
+        ProofWidgetMsg::Nop
+    }
+
     /// Convert a keyboard shortcut into a `ProofWidgetMsg` that performs the
     /// action.
     ///
@@ -501,40 +1303,15 @@ impl ProofWidget {
     /// such as <kbd>Ctrl-A</kbd> and <kbd>Ctrl-P</kbd>.
     fn process_key_shortcut(&self, key_event: web_sys::KeyboardEvent) -> ProofWidgetMsg {
         // Get the selected line, or do nothing if there is none
-        let selected_line = match &self.selected_line {
-            Some(selected_line) => selected_line.line_ref,
+        let selected_line = match self.selected_ref() {
+            Some(selected_line) => selected_line,
             None => return ProofWidgetMsg::Nop,
         };
 
         // All keyboard shortcuts have the control key held. Do nothing if the
-        // control key isn't pressed.
+        // control key isn't pressed. Keys without it that act on lines, like
+        // the arrow keys, are handled by `process_line_key`.
         if !key_event.ctrl_key() {
-            // Change focus on ArrowDown or ArrowUp
-            if key_event.key() == "ArrowDown" || key_event.key() == "ArrowUp" {
-                // Get our current id to find the others.
-                let focused_elem_id = match document().active_element() {
-                    Some(focused_elem_id) => focused_elem_id.id(),
-                    None => return ProofWidgetMsg::Nop,
-                };
-                let up_down = match key_event.key().as_str() {
-                    "ArrowDown" => 1,
-                    "ArrowUp" => -1,
-                    _ => return ProofWidgetMsg::Nop,
-                };
-                let signature = format!("{}{}", self.id, "line-number-");
-                let length = signature.chars().count();
-                // Verify that our selected element is the one we will work with.
-                if focused_elem_id.chars().count() < length {
-                    return ProofWidgetMsg::Nop;
-                }
-                let num = focused_elem_id[length..].parse::<i32>().unwrap() + up_down;
-                //let new_id = "#line-number-".to_owned() + &num.to_string();
-                let _focused_input = match document().get_element_by_id(&format!("{}{}", signature, &num.to_string())) {
-                    Some(_focused_input) => _focused_input.unchecked_into::<HtmlElement>().focus(),
-                    None => return ProofWidgetMsg::Nop,
-                };
-            }
-
             return ProofWidgetMsg::Nop;
         }
 
@@ -543,51 +1320,688 @@ impl ProofWidget {
         key_event.prevent_default();
 
         // Look up the triggered action
-        let action = actions::valid_actions(&self.prf, selected_line).find(|action_info| action_info.keyboard_shortcut == key_event.key().chars().next());
+        let action = actions::valid_actions(&self.prf, selected_line, self.is_locked(&selected_line)).find(|action_info| action_info.keyboard_shortcut == key_event.key().chars().next());
 
         if let Some(action) = action {
             // Return action message
             let lak = action.line_action_kind.clone();
-            ProofWidgetMsg::LineAction(lak, selected_line)
+            line_action_message(lak, selected_line)
         } else {
             ProofWidgetMsg::Nop
         }
     }
 }
 
-/// Is the user allowed to remove the line at `line_ref`?
-fn may_remove_line<P: Proof>(prf: &P, line_ref: &PjRef<P>) -> bool {
-    use Coproduct::Inl;
-
-    let is_premise = matches!(prf.lookup_pj(line_ref), Some(Inl(_)));
-
-    let in_subproof = prf.parent_of_line(&pj_to_pjs::<P>(line_ref.clone())).is_some();
+/// The message for choosing a line action, which for deleting a subproof asks
+/// for confirmation first
+fn line_action_message(kind: LineActionKind, line_ref: PjRef<P>) -> ProofWidgetMsg {
+    match kind {
+        LineActionKind::Delete { what: ProofItemKind::Subproof } => ProofWidgetMsg::DeleteSubproof(line_ref),
+        kind => ProofWidgetMsg::LineAction(kind, line_ref),
+    }
+}
 
-    if is_premise {
-        if in_subproof {
-            // Subproof premises can't be removed
-            false
-        } else {
-            // Can't remove the last top-level premise
-            prf.premises().len() > 1
-        }
+/// Convert <kbd>Ctrl-Z</kbd> into undo and <kbd>Ctrl-Shift-Z</kbd> into redo,
+/// overriding the text fields' own undo
+fn undo_shortcut(key_event: web_sys::KeyboardEvent) -> ProofWidgetMsg {
+    if !key_event.ctrl_key() || !key_event.key().eq_ignore_ascii_case("z") {
+        return ProofWidgetMsg::Nop;
+    }
+    key_event.prevent_default();
+    if key_event.shift_key() {
+        ProofWidgetMsg::Redo
     } else {
-        // Steps can always be removed
-        true
+        ProofWidgetMsg::Undo
     }
 }
 
 /// Render an alert for an error opening the proof
-fn render_open_error(error: &str) -> Html {
-    html! {
-        <div class="alert alert-danger m-4" role="alert">
-            <h4 class="alert-heading"> { "Error opening proof" } </h4>
-            <hr />
-            <p> { error } </p>
-        </div>
+/// The sections of the rule dropdown, in the order the rule classes are
+/// declared in, with each rule under the first class it's in. Rules without a
+/// class, like the empty rule, aren't in the dropdown.
+fn rule_menu_sections() -> Vec<(RuleClassification, Vec<Rule>)> {
+    let mut listed = vec![];
+    let mut sections = vec![];
+    for rule_class in RuleClassification::iter() {
+        let rules = rule_class.rules().filter(|rule| !listed.contains(rule)).collect::<Vec<Rule>>();
+        listed.extend(rules.iter().cloned());
+        if !rules.is_empty() {
+            sections.push((rule_class, rules));
+        }
+    }
+    sections
+}
+
+/// The sections of the rule dropdown with only the rules matching what's typed
+/// in its search box, ignoring case. A rule matches by its name, the name it's
+/// saved with, or the name of any of its classes. Sections with no matching
+/// rules are left out.
+fn filter_rule_menu(sections: &[(RuleClassification, Vec<Rule>)], filter: &str) -> Vec<(RuleClassification, Vec<Rule>)> {
+    let filter = filter.trim().to_lowercase();
+    let matches = |rule: &Rule| {
+        let mut names = vec![rule.get_name(), RuleM::to_serialized_name(*rule).to_string()];
+        names.extend(rule.get_classifications().into_iter().map(|rule_class| rule_class.to_string()));
+        names.iter().any(|name| name.to_lowercase().contains(&filter))
+    };
+    sections.iter().map(|(rule_class, rules)| (*rule_class, rules.iter().cloned().filter(matches).collect::<Vec<Rule>>())).filter(|(_, rules)| !rules.is_empty()).collect()
+}
+
+/// Lines whose feedback is held back while they're being edited, with the
+/// handles of the timers that show it once editing pauses. Replacing or
+/// removing a line's handle drops it, which cancels its timer.
+struct PendingFeedback<H> {
+    timers: HashMap<PjRef<P>, H>,
+}
+
+impl<H> Default for PendingFeedback<H> {
+    fn default() -> Self {
+        PendingFeedback { timers: HashMap::new() }
+    }
+}
+
+impl<H> PendingFeedback<H> {
+    /// Hold back a line's feedback until `timer` fires, instead of the timer
+    /// it was waiting on
+    fn edited(&mut self, r: PjRef<P>, timer: H) {
+        self.timers.insert(r, timer);
+    }
+
+    /// Stop holding back a line's feedback, returning whether it was
+    fn release(&mut self, r: &PjRef<P>) -> bool {
+        self.timers.remove(r).is_some()
+    }
+
+    fn is_pending(&self, r: &PjRef<P>) -> bool {
+        self.timers.contains_key(r)
+    }
+}
+
+/// The classes of the wrappers around a line's contents, outermost first, with
+/// one for each (sub)proof the line is in. Each wrapper draws the bar along the
+/// left of its (sub)proof, and the innermost one of a (sub)proof's last premise
+/// draws the bar under its premises.
+fn scope_classes(depth: usize, last_premise: bool) -> Vec<String> {
+    (0..=depth)
+        .map(|level| match level == depth && last_premise {
+            true => format!("scope depth-{level} scope-premises-end"),
+            false => format!("scope depth-{level}"),
+        })
+        .collect()
+}
+
+/// Wrap a line's contents in the bars of the (sub)proofs it's in
+fn render_scope_bars(depth: usize, last_premise: bool, contents: Html) -> Html {
+    scope_classes(depth, last_premise).into_iter().rev().fold(contents, |inner, class| html! { <div {class}>{ inner }</div> })
+}
+
+/// A line's error as it's shown in its details
+#[derive(Debug, PartialEq, Eq)]
+struct ErrorDetails {
+    headline: String,
+    /// The dependencies the error is about, with their line numbers
+    deps: Vec<String>,
+    /// The assignment of truth values to variables that shows the step is
+    /// wrong, if there's one
+    counterexample: Vec<(String, bool)>,
+}
+
+/// The result of checking the whole proof, as it's shown above it
+#[derive(Debug, PartialEq, Eq)]
+struct CheckSummary {
+    /// How many lines were checked
+    lines: usize,
+    /// The line number and reference of each problem found, with what's wrong,
+    /// ordered by line number
+    problems: Vec<(usize, PjRef<P>, String)>,
+    /// Whether the proof was edited since it was checked
+    stale: bool,
+}
+
+impl CheckSummary {
+    /// Check every line of `prf`, including whether each step only cites
+    /// lines and subproofs in scope for it
+    fn new(prf: &P, pud: &ProofUiData<P>) -> Self {
+        let report = prf.verify_all();
+        let mut problems = report
+            .errors()
+            .map(|(line, r, err)| {
+                let details = error_details(err, prf, pud);
+                let headline = match &details.deps[..] {
+                    [] => details.headline,
+                    deps => format!("{} ({})", details.headline, deps.join(", ")),
+                };
+                (line, *r, headline)
+            })
+            .collect::<Vec<_>>();
+        // Steps are checked for what they cite before their rules are, so
+        // this only adds steps whose own errors don't already say so
+        for (dep, step) in &report.structural {
+            if problems.iter().any(|(_, r, _)| r == step) {
+                continue;
+            }
+            let line = pud.ref_to_line_depth.get(step).map(|(line, _)| *line).unwrap_or_default();
+            problems.push((line, *step, format!("Cites {}, which isn't in scope for it", describe_dep(prf, pud, *dep))));
+        }
+        problems.sort_by_key(|(line, _, _)| *line);
+        CheckSummary { lines: report.lines.len(), problems, stale: false }
+    }
+}
+
+/// Describe a suggested justification by its rule and the line numbers of its
+/// dependencies, like "→ Elim using lines 2, 4"
+fn describe_suggestion(prf: &P, pud: &ProofUiData<P>, suggestion: &Suggestion<PjRef<P>, <P as Proof>::SubproofReference>) -> String {
+    let line_of = |r: &PjRef<P>| pud.ref_to_line_depth.get(r).map(|(line, _)| *line);
+    let deps = suggestion.deps.iter().filter_map(line_of).map(|line| line.to_string());
+    let sdeps = suggestion.sdeps.iter().filter_map(|sr| prf.lookup_subproof(sr)?.line_span(line_of)).map(|span| format!("{}-{}", span.start(), span.end()));
+    let cited = deps.chain(sdeps).collect::<Vec<_>>();
+    match &cited[..] {
+        [] => format!("{} with no dependencies", suggestion.rule.get_name()),
+        [line] if suggestion.sdeps.is_empty() => format!("{} using line {line}", suggestion.rule.get_name()),
+        lines => format!("{} using lines {}", suggestion.rule.get_name(), lines.join(", ")),
+    }
+}
+
+/// Describe a dependency of a step by its line numbers
+fn describe_dep(prf: &P, pud: &ProofUiData<P>, dep: Coprod!(PjRef<P>, <P as Proof>::SubproofReference)) -> String {
+    use Coproduct::{Inl, Inr};
+    match dep {
+        Inl(lr) => match pud.ref_to_line_depth.get(&lr) {
+            Some((line, _)) => format!("line {line}"),
+            None => "a line that no longer exists".into(),
+        },
+        Inr(Inl(sr)) => match prf.lookup_subproof(&sr).and_then(|sub| sub.line_span(|r| pud.ref_to_line_depth.get(r).map(|(line, _)| *line))) {
+            Some(span) => format!("the subproof on lines {}-{}", span.start(), span.end()),
+            None => "a subproof that no longer exists".into(),
+        },
+        Inr(Inr(void)) => match void {},
+    }
+}
+
+/// The details to show of a line's error, with the dependencies it's about
+/// described by their line numbers instead of how they're stored
+fn error_details(err: &ProofCheckError<PjRef<P>, <P as Proof>::SubproofReference>, prf: &P, pud: &ProofUiData<P>) -> ErrorDetails {
+    use ProofCheckError::*;
+    let (headline, deps, counterexample) = match err {
+        LineDoesNotExist(r) => ("A dependency doesn't exist.".to_string(), vec![Coproduct::inject(*r)], vec![]),
+        SubproofDoesNotExist(s) => ("A subproof dependency doesn't exist.".to_string(), vec![Coproduct::inject(*s)], vec![]),
+        ReferencesLaterLine(_, dep) => ("The dependency is not in scope for this step.".to_string(), vec![*dep], vec![]),
+        ForwardReference { dep } => ("The dependency comes after this step.".to_string(), vec![*dep], vec![]),
+        IncorrectDepCount(deps, _) => (err.to_string(), deps.iter().map(|dep| Coproduct::inject(*dep)).collect(), vec![]),
+        IncorrectSubDepCount(sdeps, _) => (err.to_string(), sdeps.iter().map(|sdep| Coproduct::inject(*sdep)).collect(), vec![]),
+        IncorrectSubproofPremiseCount(sdep, _) => (err.to_string(), vec![Coproduct::inject(*sdep)], vec![]),
+        Counterexample(assignment) => ("The conclusion doesn't follow from the dependencies by tautological consequence.".to_string(), vec![], assignment.clone()),
+        _ => (err.to_string(), vec![], vec![]),
+    };
+    let deps = deps.into_iter().map(|dep| describe_dep(prf, pud, dep)).collect();
+    ErrorDetails { headline, deps, counterexample }
+}
+
+/// Render the details of a line's error below its badge
+fn render_error_details(details: &ErrorDetails) -> Html {
+    let deps = if details.deps.is_empty() {
+        html! {}
+    } else {
+        html! { <p class="mb-1">{ format!("Dependency: {}", details.deps.join(", ")) }</p> }
+    };
+    let counterexample = if details.counterexample.is_empty() {
+        html! {}
+    } else {
+        let rows = counterexample_rows(&details.counterexample)
+            .into_iter()
+            .map(|row| {
+                html! {
+                    <tr>
+                        <td class="text-break">{ row.atom }</td>
+                        <td>{ row.value }</td>
+                    </tr>
+                }
+            })
+            .collect::<Html>();
+        let text = counterexample_text(&details.counterexample);
+        let copy = Callback::from(move |_: MouseEvent| {
+            // Nothing is lost if copying is refused
+            let _ = write_clipboard_text(&text);
+        });
+        html! {
+            <>
+                <p class="mb-1">{ "Dependencies true, conclusion false under this assignment:" }</p>
+                <table class="table table-sm table-bordered mb-1">
+                    <thead>
+                        <tr>
+                            <th>{ "Variable" }</th>
+                            <th>{ "Value" }</th>
+                        </tr>
+                    </thead>
+                    <tbody>{ rows }</tbody>
+                </table>
+                <button type="button" class="btn btn-sm btn-outline-secondary" onclick={ copy }>{ "Copy as text" }</button>
+            </>
+        }
+    };
+    html! {
+        <div class="card shadow error-popover" role="dialog">
+            <div class="card-body p-2">
+                <p class="mb-1"><strong>{ &details.headline }</strong></p>
+                { deps }
+                { counterexample }
+            </div>
+        </div>
+    }
+}
+
+/// The lines to tint as dependencies of the selected step, found once for each
+/// render instead of for each line
+#[derive(Debug, Default, PartialEq)]
+struct HighlightSet {
+    /// The step's line dependencies, and every line in its subproof
+    /// dependencies
+    lines: HashSet<PjRef<P>>,
+}
+
+impl HighlightSet {
+    fn new(prf: &P, selected: Option<PjRef<P>>) -> Self {
+        use Coproduct::{Inl, Inr};
+        let mut lines = HashSet::new();
+        if let Some(Justification(_, _, deps, sdeps)) = selected.and_then(|selected| match selected {
+            Inr(Inl(jr)) => prf.lookup_step(&jr),
+            _ => None,
+        }) {
+            lines.extend(deps);
+            for sr in sdeps {
+                if let Some(sub) = prf.lookup_subproof(&sr) {
+                    lines.extend(sub.contained_justifications(true));
+                }
+            }
+        }
+        HighlightSet { lines }
+    }
+
+    fn contains(&self, r: &PjRef<P>) -> bool {
+        self.lines.contains(r)
+    }
+}
+
+/// A goal of the proof and whether it's been reached, for the goal panel
+#[derive(Debug, Clone, PartialEq)]
+struct GoalEntry {
+    goal: Expr,
+    status: GoalStatus<PjRef<P>>,
+    /// Whether a line inside a subproof concludes the goal, which doesn't
+    /// count as reaching it
+    in_subproof: bool,
+}
+
+/// The status of each of `goals` in `prf`, in order, including duplicates
+fn goal_entries(prf: &P, goals: &[Expr]) -> Vec<GoalEntry> {
+    if goals.is_empty() {
+        return vec![];
+    }
+    let statuses = ProofDocument::new(prf.clone(), goals.to_vec()).check_goals();
+    let nested = prf.iter_lines().filter(|info| info.depth > 0).filter_map(|info| info.reference.subset::<PjRef<P>, _>().ok()).filter_map(|r| prf.lookup_expr(&r)).map(Expr::replacing_bound_vars).collect::<Vec<_>>();
+    goals
+        .iter()
+        .zip(statuses)
+        .map(|(goal, status)| {
+            let in_subproof = status == GoalStatus::NotReached && nested.contains(&goal.clone().replacing_bound_vars());
+            GoalEntry { goal: goal.clone(), status, in_subproof }
+        })
+        .collect()
+}
+
+/// The line to select after deleting lines while `selected` was selected,
+/// given the lines in display order from before, `lines`, and whether each is
+/// still in the proof. That's `selected` if it's still there, or else the last
+/// line before it that is, or else the first after it, or `None` if there are
+/// no lines left.
+fn selection_after_delete(lines: &[PjRef<P>], selected: PjRef<P>, is_live: impl Fn(&PjRef<P>) -> bool) -> Option<PjRef<P>> {
+    if is_live(&selected) {
+        return Some(selected);
+    }
+    let index = lines.iter().position(|line| *line == selected).unwrap_or(lines.len());
+    lines[..index].iter().rev().chain(&lines[index..]).find(|line| is_live(line)).copied()
+}
+
+/// The id of the row of the line with the stable id `line_id` in the proof
+/// widget with the id `widget_id`, which stays the same however the proof is
+/// edited, so that a line can be scrolled to after it's rendered
+fn row_dom_id(widget_id: &str, line_id: LineId) -> String {
+    format!("{widget_id}line-row-{}", line_id.0)
+}
+
+/// The action for clicking the number of a line or subproof, `dep`, while
+/// `selected` is the selected line, which sets whether it's a dependency of the
+/// selected step to the opposite of whether it is. Clicking does nothing if the
+/// selected line isn't a step, or if `dep` is out of the step's scope.
+fn dep_click_action(prf: &P, selected: Option<PjRef<P>>, dep: Coprod!(PjRef<P>, <P as Proof>::SubproofReference)) -> Option<(LineActionKind, PjRef<P>)> {
+    use Coproduct::{Inl, Inr};
+    let selected = selected?;
+    let Justification(_, _, deps, sdeps) = match selected {
+        Inr(Inl(jr)) => prf.lookup_step(&jr)?,
+        _ => return None,
+    };
+    if !prf.can_reference_dep(&selected, &dep) {
+        return None;
+    }
+    let is_dep = match dep {
+        Inl(lr) => deps.contains(&lr),
+        Inr(Inl(sr)) => sdeps.contains(&sr),
+        Inr(Inr(void)) => match void {},
+    };
+    Some((LineActionKind::SetDependency { to: !is_dep, dep }, selected))
+}
+
+/// How a line or subproof is offered as a dependency of the selected step
+#[derive(Debug, PartialEq, Eq)]
+enum DepChoice {
+    /// The step's rule takes no dependencies of its kind
+    Hidden,
+    /// It can be checked or unchecked
+    Enabled,
+    /// The step has no rule yet, so it can be checked, but the rule decides
+    /// what it should depend on
+    NeedsRule,
+    /// The step already has as many dependencies of its kind as its rule
+    /// takes, so it can't be checked until one is unchecked
+    Full(usize),
+}
+
+/// Decide how a line or subproof is offered as a dependency of a step, from
+/// how many dependencies of its kind the step's rule takes (`None` if any
+/// number), whether it's already one of them, and how many there are
+fn dep_choice(rule: Rule, takes: Option<usize>, checked: bool, num_checked: usize) -> DepChoice {
+    match takes {
+        _ if rule == RuleM::EmptyRule => DepChoice::NeedsRule,
+        Some(0) => DepChoice::Hidden,
+        Some(max) if !checked && num_checked >= max => DepChoice::Full(max),
+        _ => DepChoice::Enabled,
+    }
+}
+
+fn render_open_error(ctx: &Context<ProofWidget>, problems: &[OpenProblem]) -> Html {
+    let rows = problems
+        .iter()
+        .map(|problem| {
+            html! {
+                <tr>
+                    <td class="text-nowrap"> { problem.position.clone().unwrap_or_default() } </td>
+                    <td> { &problem.message } </td>
+                </tr>
+            }
+        })
+        .collect::<Html>();
+    html! {
+        <div class="alert alert-danger m-4" role="alert">
+            <h4 class="alert-heading"> { "Error opening proof" } </h4>
+            <hr />
+            <table class="table table-sm">
+                <tbody> { rows } </tbody>
+            </table>
+            <button type="button" class="btn btn-primary" onclick={ ctx.link().callback(|_| ProofWidgetMsg::RetryAsPremises) }> { "Retry as plain text premises" } </button>
+            <button type="button" class="btn btn-secondary ml-2" onclick={ ctx.link().callback(|_| ProofWidgetMsg::CloseTab) }> { "Close tab" } </button>
+        </div>
+    }
+}
+
+/// Render a dismissible notice that the proof was converted from another
+/// version of Aris, summarizing what in it was ignored
+fn render_open_warnings(ctx: &Context<ProofWidget>, warnings: &[CompatWarning]) -> Html {
+    if warnings.is_empty() {
+        return html! {};
+    }
+    let heading = match &summarize_ignored_content(warnings)[..] {
+        [] => "This file was converted from another version of Aris".into(),
+        summary => format!("{}.", summary.join(", ")),
+    };
+    html! {
+        <div class="alert alert-info alert-dismissible m-4" role="alert">
+            <h4 class="alert-heading"> { heading } </h4>
+            <button type="button" class="close" aria-label="Close" onclick={ ctx.link().callback(|_| ProofWidgetMsg::DismissOpenWarnings) }>
+                <span aria-hidden="true"> { '×' } </span>
+            </button>
+            <hr />
+            <ul class="mb-0">
+                { for warnings.iter().map(|warning| html! { <li> { warning.to_string() } </li> }) }
+            </ul>
+        </div>
     }
 }
 
+/// Render a list of the parts of the file that couldn't be read
+fn render_open_problems(problems: &[String]) -> Html {
+    if problems.is_empty() {
+        return html! {};
+    }
+    html! {
+        <div class="alert alert-warning m-4" role="alert">
+            <h4 class="alert-heading"> { "Some of this file couldn't be read" } </h4>
+            <hr />
+            <ul class="mb-0">
+                { for problems.iter().map(|problem| html! { <li> { problem } </li> }) }
+            </ul>
+        </div>
+    }
+}
+
+/// A proof opened from a file, with what was converted or couldn't be read in
+/// it
+struct OpenedProof {
+    prf: P,
+    pud: ProofUiData<P>,
+    metadata: ProofMetaData,
+    warnings: Vec<CompatWarning>,
+    problems: Vec<String>,
+}
+
+/// A reason a proof couldn't be opened, with where in the file it was, if it
+/// was in one place
+#[derive(Debug, PartialEq)]
+struct OpenProblem {
+    position: Option<String>,
+    message: String,
+}
+
+/// Open a proof saved as XML, which may be partly read, or describe why it
+/// can't be
+fn open_proof(data: &[u8]) -> Result<OpenedProof, Vec<OpenProblem>> {
+    let (result, errors) = aris::proofs::xml_interop::proof_from_xml_lenient::<P, _>(data);
+    match result {
+        Some((prf, metadata, _, warnings)) => {
+            let pud = ProofUiData::from_proof_with_inputs(&prf, &metadata.raw_inputs, crate::settings::symbols());
+            // Citing a line that's out of scope is reported by the rule
+            // checker, but the rest break assumptions the widget makes
+            let structural_errors = prf.validate().into_iter().filter(|err| !matches!(err, StructuralError::OutOfScope { .. })).collect::<Vec<_>>();
+            if structural_errors.is_empty() {
+                Ok(OpenedProof { prf, pud, metadata, warnings, problems: errors.iter().map(|err| err.to_string()).collect() })
+            } else {
+                Err(vec![OpenProblem { position: None, message: describe_structural_errors(&pud, &structural_errors) }])
+            }
+        }
+        None => Err(errors
+            .iter()
+            .map(|err| {
+                let position = match &*err.path {
+                    "" => format!("Line {}, column {}", err.line, err.column),
+                    path => format!("Line {}, column {}, in {path}", err.line, err.column),
+                };
+                OpenProblem { position: Some(position), message: err.kind.to_string() }
+            })
+            .collect()),
+    }
+}
+
+/// A proof with each line of `text` as a premise, for opening a file that
+/// isn't a proof. Lines that aren't formulas are left as they are, to be fixed.
+fn proof_from_text(text: &str) -> (P, ProofUiData<P>) {
+    let lines = text.lines().map(str::trim).filter(|line| !line.is_empty()).collect::<Vec<&str>>();
+    if lines.is_empty() {
+        return new_empty_proof();
+    }
+    let mut prf = P::new();
+    let premises = lines.iter().map(|line| (prf.add_premise(aris::parser::parse(line).unwrap_or_else(new_empty_premise)), line.to_string())).collect::<Vec<_>>();
+    let mut pud = ProofUiData::from_proof(&prf);
+    for (r, line) in premises {
+        pud.ref_to_input.insert(Coproduct::inject(r), line);
+    }
+    (prf, pud)
+}
+
+/// Describe structural errors in a proof by line number, for `render_open_error`
+fn describe_structural_errors(pud: &ProofUiData<P>, errors: &[StructuralError<PjRef<P>, <P as Proof>::SubproofReference>]) -> String {
+    use Coproduct::{Inl, Inr};
+    let line = |r: &PjRef<P>| pud.ref_to_line_depth.get(r).map(|(line, _)| format!("line {line}")).unwrap_or_else(|| "a missing line".into());
+    let describe = |err: &StructuralError<PjRef<P>, <P as Proof>::SubproofReference>| {
+        let (dep, of, problem) = match err {
+            StructuralError::DanglingReference { dep, of } => (dep, of, "which does not exist"),
+            StructuralError::ForwardReference { dep, of } => (dep, of, "which does not come before it"),
+            StructuralError::OutOfScope { dep, of } => (dep, of, "which is not in scope"),
+        };
+        let dep = match dep {
+            Inl(dep) => line(dep),
+            Inr(Inl(_)) => "a subproof".into(),
+            Inr(Inr(void)) => match *void {},
+        };
+        match pud.ref_to_line_depth.get(of) {
+            Some((of, _)) => format!("Line {of} cites {dep}, {problem}."),
+            None => format!("A missing line cites {dep}, {problem}."),
+        }
+    };
+    errors.iter().map(describe).collect::<Vec<_>>().join(" ")
+}
+
+/// Insert a blank line or subproof, as `LineActionKind::Insert` does
+/// relative to `orig_ref`, renumbering the lines in `pud` from it on. Returns
+/// the line to select, or `None` if nothing could be inserted there.
+fn insert_blank_line(prf: &mut RecordingProof<P>, pud: &mut ProofUiData<P>, what: ProofItemKind, after: bool, relative_to: ProofItemKind, orig_ref: PjRef<P>) -> Option<PjRef<P>> {
+    use Coproduct::{Inl, Inr};
+    let to_select;
+    let orig_ref = pj_to_pjs::<P>(orig_ref);
+    let parent = prf.parent_of_line(&orig_ref);
+    let insertion_point: PjsRef<P> = match relative_to {
+        ProofItemKind::Premise | ProofItemKind::Just => orig_ref,
+        ProofItemKind::Subproof => match parent {
+            Some(parent) => Coproduct::inject(parent),
+            None => return None,
+        },
+    };
+    match what {
+        ProofItemKind::Premise => match insertion_point {
+            Inl(pr) => {
+                // Insert premise relative to premise
+                to_select = Inl(prf.add_premise_relative(new_empty_premise(), &pr, after));
+            }
+            Inr(Inl(_)) | Inr(Inr(Inl(_))) => {
+                // Insert premise relative to line or subproof
+                to_select = Inl(prf.add_premise(new_empty_premise()));
+            }
+            Inr(Inr(Inr(void))) => match void {},
+        },
+        ProofItemKind::Just => match insertion_point {
+            Inl(_) => {
+                // Insert justification relative to premise
+
+                // Add justification to enclosing subproof of premise, if it exists
+                let just_ref = parent.and_then(|parent| prf.with_mut_subproof(&parent, |parent| parent.prepend_step(new_empty_step())));
+
+                // If the insertion point is not in a subproof, add justification to the top-level proof
+                match just_ref {
+                    Some(just_ref) => to_select = Coproduct::inject(just_ref),
+                    None => to_select = Coproduct::inject(prf.prepend_step(new_empty_step())),
+                }
+            }
+            Inr(Inl(jr)) => {
+                // Insert justification relative to justification
+                let jsr = Coproduct::inject(jr);
+                to_select = Inr(Inl(prf.add_step_relative(new_empty_step(), &jsr, after)));
+            }
+            Inr(Inr(Inl(sr))) => {
+                // Insert justification relative to subproof
+                let jsr = Coproduct::inject(sr);
+                to_select = Inr(Inl(prf.add_step_relative(new_empty_step(), &jsr, after)));
+            }
+            Inr(Inr(Inr(void))) => match void {},
+        },
+        ProofItemKind::Subproof => {
+            // Convert insertion point from `PjsRef` to `JsRef`,
+            // returning silently on failure
+            let insertion_point: JsRef<P> = match insertion_point.subset() {
+                Ok(insertion_point) => insertion_point,
+                // Insertion point is a premise, return silently
+                Err(_) => return None,
+            };
+            let sr = prf.add_subproof_relative(&insertion_point, after);
+            to_select = prf
+                .with_mut_subproof(&sr, |sub| {
+                    let to_select = Inl(sub.add_premise(new_empty_premise()));
+                    sub.prepend_step(new_empty_step());
+                    to_select
+                })
+                .expect("Subproof doesn't exist after creating it");
+        }
+    }
+    lineinfo_after_insert(&mut pud.ref_to_line_depth, prf);
+    Some(to_select)
+}
+
+/// Delete a line, or with `ProofItemKind::Subproof` the subproof around it, as
+/// `LineActionKind::Delete` does, forgetting the deleted lines in `pud` and
+/// renumbering the ones after them. Returns the steps that cited what was
+/// deleted.
+fn delete_lines(prf: &mut RecordingProof<P>, pud: &mut ProofUiData<P>, what: ProofItemKind, proofref: PjRef<P>) -> Result<Vec<<P as Proof>::JustificationReference>, DeleteError> {
+    match what {
+        ProofItemKind::Premise | ProofItemKind::Just => prf.delete_line(&proofref).map(|affected| {
+            lineinfo_after_delete(&mut pud.ref_to_line_depth, &[proofref]);
+            pud.ref_to_input.remove(&proofref);
+            affected
+        }),
+        ProofItemKind::Subproof => prf.delete_enclosing_subproof(&proofref).map(|removed| {
+            lineinfo_after_delete(&mut pud.ref_to_line_depth, &removed.lines);
+            pud.remove_lines(&removed.lines);
+            removed.affected
+        }),
+    }
+}
+
+/// Move a premise or step past its neighbor, as `LineActionKind::Move` does.
+/// Moving past a subproof shifts every line in it, so all of the lines in
+/// `pud` are renumbered.
+fn move_line_or_premise(prf: &mut RecordingProof<P>, pud: &mut ProofUiData<P>, direction: MoveDirection, proofref: PjRef<P>) -> Result<(), MoveError<PjRef<P>, <P as Proof>::SubproofReference>> {
+    use Coproduct::{Inl, Inr};
+    let result = match proofref {
+        Inl(pr) => prf.move_premise(&pr, direction),
+        Inr(Inl(jr)) => prf.move_line(&jr, direction),
+        Inr(Inr(void)) => match void {},
+    };
+    calculate_lineinfo::<P>(&mut pud.ref_to_line_depth, prf);
+    result
+}
+
+/// The reference of the line `r` after an undo or redo that gave `renames`
+fn renamed(renames: &HashMap<PjsRef<P>, PjsRef<P>>, r: PjRef<P>) -> PjRef<P> {
+    renames.get(&pj_to_pjs::<P>(r)).and_then(|r| r.clone().subset().ok()).unwrap_or(r)
+}
+
+/// Undo or redo an edit of `prf` with `step`, as `ProofWidgetMsg::Undo` and
+/// `Redo` do. Lines the edit didn't change keep what's typed in them in `pud`,
+/// and the ones it changed or put back show their formulas, written with
+/// `symbols`. Returns the new references of the lines and subproofs that were
+/// put back or moved, or `None` if there was nothing to undo or redo.
+fn undo_or_redo(prf: &mut RecordingProof<P>, pud: &mut ProofUiData<P>, symbols: Symbols, step: impl FnOnce(&mut RecordingProof<P>) -> Option<HashMap<PjsRef<P>, PjsRef<P>>>) -> Option<HashMap<PjsRef<P>, PjsRef<P>>> {
+    let exprs_before = prf.iter_lines().filter_map(|info| info.reference.subset().ok()).map(|r: PjRef<P>| (r, prf.lookup_expr(&r))).collect::<Vec<_>>();
+    let renames = step(&mut *prf)?;
+    let exprs_before = exprs_before.into_iter().map(|(r, e)| (renamed(&renames, r), e)).collect::<HashMap<_, _>>();
+    pud.remap_lines(|r| Some(renamed(&renames, *r)).filter(|r| prf.lookup_expr(r).is_some()));
+    let blanks = [new_empty_premise(), new_empty_step().0];
+    let lines = prf.iter_lines().filter_map(|info| info.reference.subset().ok()).collect::<Vec<PjRef<P>>>();
+    for r in lines {
+        let expr = prf.lookup_expr(&r);
+        if exprs_before.get(&r) != Some(&expr) {
+            pud.ref_to_input.insert(r, expr.filter(|e| !blanks.contains(e)).map(|e| e.display_with(symbols)).unwrap_or_default());
+        }
+    }
+    calculate_lineinfo::<P>(&mut pud.ref_to_line_depth, prf);
+    Some(renames)
+}
+
 /// Create a new empty premise, the default premise when creating a new one in
 /// the UI. The `ProofUiData` is supposed to be modified so this appears blank.
 fn new_empty_premise() -> Expr {
@@ -618,19 +2032,17 @@ impl Component for ProofWidget {
     type Properties = ProofWidgetProps;
     fn create(ctx: &Context<Self>) -> Self {
         ctx.props().oncreate.emit(ctx.link().clone());
-        let (prf, pud, error) = match &ctx.props().data {
-            Some(data) => {
-                let result = aris::proofs::xml_interop::proof_from_xml::<P, _>(&data[..]);
-                match result {
-                    Ok((prf, _)) => {
-                        let pud = ProofUiData::from_proof(&prf);
-                        (prf, pud, None)
-                    }
-                    Err(err) => {
-                        let (prf, pud) = new_empty_proof();
-                        (prf, pud, Some(err))
-                    }
-                }
+        let mut metadata = ProofMetaData::default();
+        let mut open_warnings = vec![];
+        let mut open_problems = vec![];
+        let (prf, pud, error) = match ctx.props().data.as_deref().map(open_proof) {
+            Some(Ok(opened)) => {
+                (metadata, open_warnings, open_problems) = (opened.metadata, opened.warnings, opened.problems);
+                (opened.prf, opened.pud, None)
+            }
+            Some(Err(problems)) => {
+                let (prf, pud) = new_empty_proof();
+                (prf, pud, Some(problems))
             }
             None => {
                 let (prf, pud) = new_empty_proof();
@@ -640,146 +2052,142 @@ impl Component for ProofWidget {
 
         let id: String = ((random() * 10000.0) as i32).to_string();
 
-        let mut tmp = Self { prf, pud, selected_line: None, open_error: error, preblob: "".into(), id };
+        let goals = goal_entries(&prf, &metadata.goals);
+        let prf = RecordingProof::from_proof(prf).with_limit(UNDO_LIMIT);
+        let mut tmp = Self { prf, pud, selected_line: None, open_error: error, open_warnings, open_problems, debug_log: DebugLog::new(ctx.props().debug_log_limit, ctx.props().verbose && crate::settings::show_debug_log()), id, line_warnings: HashMap::new(), verification_cache: RefCell::new(VerificationCache::new()), metadata, last_edited: None, dirty: ctx.props().unsaved, rule_filter: String::new(), rule_highlight: 0, error_popover: None, pending_feedback: PendingFeedback::default(), context_menu: None, goals, check_summary: None, suggestions: None, flashed: None, scroll_to: None, pending_delete: None, dont_ask_delete_subproof: false, printing: false };
         Component::update(&mut tmp, ctx, ProofWidgetMsg::Nop);
         tmp
     }
     fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
         let mut ret = false;
-        if ctx.props().verbose {
-            self.preblob += &format!("{msg:?}\n");
-            ret = true;
-        }
+        let renumbers_lines = msg.renumbers_lines();
+        // Formatting every message is slow, so it's only done when they're shown
+        self.debug_log.record(|| format!("{msg:?}"));
+        ret |= self.debug_log.is_enabled();
         use Coproduct::{Inl, Inr};
+        // Line warnings are only shown until the next edit
+        if let ProofWidgetMsg::LineChanged(..) | ProofWidgetMsg::LineAction(..) = msg {
+            self.line_warnings.clear();
+        }
+        // Locked lines can be selected and moved, but not changed or deleted,
+        // including by deleting a subproof they're in
+        let locked = match &msg {
+            ProofWidgetMsg::LineAction(LineActionKind::Delete { what }, r) => self.locked_line_deleted_by(*what, r).map(|locked| (*r, locked)),
+            ProofWidgetMsg::LineChanged(r, _) | ProofWidgetMsg::LineAction(LineActionKind::SetRule { .. } | LineActionKind::FillTrivial | LineActionKind::SetDependency { .. } | LineActionKind::ApplySuggestion { .. }, r) => Some(*r).filter(|r| self.is_locked(r)).map(|r| (r, r)),
+            _ => None,
+        };
+        if let Some((r, locked)) = locked {
+            let names = LineNames::new(&self.prf);
+            self.line_warnings.insert(r, EditError::LockedLine(pj_to_pjs::<P>(locked)).describe(|r| names.line_or_subproof(r)));
+            return true;
+        }
+        // Everything an edit changes in the proof is undone at once. Typing
+        // in a line is one edit until something else is edited.
+        match &msg {
+            ProofWidgetMsg::LineChanged(r, _) if self.last_edited == Some(*r) => self.prf.continue_group(),
+            ProofWidgetMsg::LineChanged(r, _) => {
+                self.prf.start_group();
+                self.last_edited = Some(*r);
+            }
+            msg if msg.edits_proof() => {
+                self.prf.start_group();
+                self.last_edited = None;
+            }
+            _ => {}
+        }
+        if msg.edits_proof() {
+            self.set_dirty(ctx, true);
+            self.mark_check_stale();
+            self.suggestions = None;
+        }
         match msg {
             ProofWidgetMsg::Nop => {}
             ProofWidgetMsg::LineChanged(r, input) => {
                 self.pud.ref_to_input.insert(r, input.clone());
                 if let Some(e) = aris::parser::parse(&input) {
-                    match r {
-                        Inl(pr) => {
-                            self.prf.with_mut_premise(&pr, |x| *x = e);
-                        }
-                        Inr(Inl(jr)) => {
-                            self.prf.with_mut_step(&jr, |x| x.0 = e);
-                        }
+                    let result = match r {
+                        Inl(pr) => self.prf.try_with_mut_premise(&pr, |x| *x = e),
+                        Inr(Inl(jr)) => self.prf.try_with_mut_step(&jr, |x| x.0 = e),
                         Inr(Inr(void)) => match void {},
+                    };
+                    if let Err(err) = result {
+                        let names = LineNames::new(&self.prf);
+                        self.line_warnings.insert(r, err.describe(|r| names.line_or_subproof(r)));
                     }
+                    self.verification_cache.get_mut().invalidate_for_change(&r, &self.prf);
                 }
+                // Hold back the line's feedback until editing pauses
+                let link = ctx.link().clone();
+                self.pending_feedback.edited(r, Timeout::new(FEEDBACK_DELAY_MS, move || link.send_message(ProofWidgetMsg::ShowFeedback(r))));
                 ret = true;
             }
             ProofWidgetMsg::LineAction(LineActionKind::Insert { what, after, relative_to }, orig_ref) => {
-                let to_select;
-                let orig_ref = pj_to_pjs::<P>(orig_ref);
-                let parent = self.prf.parent_of_line(&orig_ref);
-                let insertion_point: PjsRef<P> = match relative_to {
-                    ProofItemKind::Premise | ProofItemKind::Just => orig_ref,
-                    ProofItemKind::Subproof => match parent {
-                        Some(parent) => Coproduct::inject(parent),
-                        None => return ret,
-                    },
+                let to_select = match insert_blank_line(&mut self.prf, &mut self.pud, what, after, relative_to, orig_ref) {
+                    Some(to_select) => to_select,
+                    None => return ret,
                 };
-                match what {
-                    ProofItemKind::Premise => match insertion_point {
-                        Inl(pr) => {
-                            // Insert premise relative to premise
-                            to_select = Inl(self.prf.add_premise_relative(new_empty_premise(), &pr, after));
-                        }
-                        Inr(Inl(_)) | Inr(Inr(Inl(_))) => {
-                            // Insert premise relative to line or subproof
-                            to_select = Inl(self.prf.add_premise(new_empty_premise()));
-                        }
-                        Inr(Inr(Inr(void))) => match void {},
-                    },
-                    ProofItemKind::Just => match insertion_point {
-                        Inl(_) => {
-                            // Insert justification relative to premise
-
-                            // Add justification to enclosing subproof of premise, if it exists
-                            let just_ref = parent.and_then(|parent| self.prf.with_mut_subproof(&parent, |parent| parent.prepend_step(new_empty_step())));
-
-                            // If the insertion point is not in a subproof, add justification to the top-level proof
-                            match just_ref {
-                                Some(just_ref) => to_select = Coproduct::inject(just_ref),
-                                None => to_select = Coproduct::inject(self.prf.prepend_step(new_empty_step())),
-                            }
-                        }
-                        Inr(Inl(jr)) => {
-                            // Insert justification relative to justification
-                            let jsr = Coproduct::inject(jr);
-                            to_select = Inr(Inl(self.prf.add_step_relative(new_empty_step(), &jsr, after)));
-                        }
-                        Inr(Inr(Inl(sr))) => {
-                            // Insert justification relative to subproof
-                            let jsr = Coproduct::inject(sr);
-                            to_select = Inr(Inl(self.prf.add_step_relative(new_empty_step(), &jsr, after)));
-                        }
-                        Inr(Inr(Inr(void))) => match void {},
-                    },
-                    ProofItemKind::Subproof => {
-                        // Convert insertion point from `PjsRef` to `JsRef`,
-                        // returning silently on failure
-                        let insertion_point: JsRef<P> = match insertion_point.subset() {
-                            Ok(insertion_point) => insertion_point,
-                            // Insertion point is a premise, return silently
-                            Err(_) => return ret,
-                        };
-                        let sr = self.prf.add_subproof_relative(&insertion_point, after);
-                        to_select = self
-                            .prf
-                            .with_mut_subproof(&sr, |sub| {
-                                let to_select = Inl(sub.add_premise(new_empty_premise()));
-                                sub.prepend_step(new_empty_step());
-                                to_select
-                            })
-                            .expect("Subproof doesn't exist after creating it");
-                    }
-                }
                 self.select_line(ctx, to_select);
-                self.preblob += &format!("{:?}\n", self.prf.premises());
+                self.scroll_to = Some(to_select);
                 ret = true;
             }
             ProofWidgetMsg::LineAction(LineActionKind::Delete { what }, proofref) => {
-                let parent = self.prf.parent_of_line(&pj_to_pjs::<P>(proofref));
-                match what {
-                    ProofItemKind::Premise | ProofItemKind::Just => {
-                        fn remove_line_if_allowed<P: Proof, Q: Proof<PremiseReference = <P as Proof>::PremiseReference, JustificationReference = <P as Proof>::JustificationReference>>(prf: &mut Q, pud: &mut ProofUiData<P>, proofref: PjRef<Q>) {
-                            if may_remove_line(prf, &proofref) {
-                                pud.ref_to_line_depth.remove(&proofref);
-                                pud.ref_to_input.remove(&proofref);
-                                prf.remove_line(&proofref);
-                            }
-                        }
-                        match parent {
-                            Some(sr) => {
-                                let pud = &mut self.pud;
-                                self.prf.with_mut_subproof(&sr, |sub| {
-                                    remove_line_if_allowed(sub, pud, proofref);
-                                });
-                            }
-                            None => {
-                                remove_line_if_allowed(&mut self.prf, &mut self.pud, proofref);
-                            }
-                        }
+                self.verification_cache.get_mut().invalidate_for_change(&proofref, &self.prf);
+                let lines_before = self.prf.iter_lines().filter_map(|info| info.reference.subset().ok()).collect::<Vec<PjRef<P>>>();
+                match delete_lines(&mut self.prf, &mut self.pud, what, proofref) {
+                    Ok(affected) => self.warn_cited_line_deleted(affected),
+                    Err(err) => {
+                        self.line_warnings.insert(proofref, err.to_string());
                     }
-                    ProofItemKind::Subproof => {
-                        // TODO: recursively clean out the ProofUiData entries for lines inside a subproof before deletion
-                        // shouldn't delete the root subproof
-                        if let Some(sr) = parent {
-                            self.prf.remove_subproof(&sr);
+                }
+                // Comments of deleted lines are kept by stable id, which
+                // undoing the deletion gives back to the lines, and they
+                // aren't saved while their lines are gone
+                let prf = &self.prf;
+                // The selected line could have been deleted, by itself or in
+                // a subproof, in which case the line before it is selected
+                let selected = self.selected_line.as_ref().and_then(|selected_line| selection_after_delete(&lines_before, selected_line.line_ref, |r| prf.lookup_expr(r).is_some()));
+                match selected {
+                    Some(line_ref) => self.select_line(ctx, line_ref),
+                    None => self.selected_line = None,
+                }
+                self.compact_if_sparse();
+                ret = true;
+            }
+            ProofWidgetMsg::LineAction(LineActionKind::Move { direction }, proofref) => {
+                if let Err(err) = move_line_or_premise(&mut self.prf, &mut self.pud, direction, proofref) {
+                    let names = LineNames::new(&self.prf);
+                    self.line_warnings.insert(proofref, err.describe(|r| names.line(r), |s| names.subproof(s)));
+                }
+                self.select_line(ctx, proofref);
+                ret = true;
+            }
+            ProofWidgetMsg::LineAction(LineActionKind::SetRule { rule }, proofref) => {
+                if let Inr(Inl(jr)) = &proofref {
+                    let dropped = self.prf.try_with_mut_step(jr, |j| {
+                        j.1 = rule;
+                        j.drop_disallowed_deps()
+                    });
+                    match dropped {
+                        Ok(true) => {
+                            self.line_warnings.insert(proofref, "Removed dependencies the new rule can't use".to_string());
+                        }
+                        Ok(false) => {}
+                        Err(err) => {
+                            let names = LineNames::new(&self.prf);
+                            self.line_warnings.insert(proofref, err.describe(|r| names.line_or_subproof(r)));
                         }
                     }
+                    self.verification_cache.get_mut().invalidate_for_change(&proofref, &self.prf);
                 }
-                // Deselect current line to prevent it from pointing to a
-                // deleted line. The selected line could be deep inside a
-                // deleted subproof, so it's easier to deselect conservatively
-                // than to figure out if the selected line is deleted.
-                self.selected_line = None;
+                self.select_line(ctx, proofref);
                 ret = true;
             }
-            ProofWidgetMsg::LineAction(LineActionKind::SetRule { rule }, proofref) => {
+            ProofWidgetMsg::LineAction(LineActionKind::FillTrivial, proofref) => {
                 if let Inr(Inl(jr)) = &proofref {
-                    self.prf.with_mut_step(jr, |j| j.1 = rule);
+                    if fill_trivial(&mut self.prf, jr).is_none() {
+                        self.line_warnings.insert(proofref, "No earlier line is this formula or a conjunction containing it, or the step already has a rule".to_string());
+                    }
+                    self.verification_cache.get_mut().invalidate_for_change(&proofref, &self.prf);
                 }
                 self.select_line(ctx, proofref);
                 ret = true;
@@ -788,37 +2196,202 @@ impl Component for ProofWidget {
                 self.select_line(ctx, proofref);
                 ret = true;
             }
-            ProofWidgetMsg::LineAction(LineActionKind::ToggleDependency { dep }, proofref) => {
+            ProofWidgetMsg::LineAction(LineActionKind::ApplySuggestion { rule, deps, sdeps }, proofref) => {
+                if let Inr(Inl(jr)) = &proofref {
+                    if apply_suggestion(&mut self.prf, jr, &Suggestion { rule, deps, sdeps }).is_none() {
+                        self.line_warnings.insert(proofref, "The step no longer exists".to_string());
+                    }
+                    self.verification_cache.get_mut().invalidate_for_change(&proofref, &self.prf);
+                }
+                self.select_line(ctx, proofref);
+                ret = true;
+            }
+            ProofWidgetMsg::Suggest(proofref) => {
                 if let Inr(Inl(jr)) = &proofref {
-                    self.prf.with_mut_step(jr, |j| {
-                        fn toggle_dep_or_sdep<T: Ord>(dep: T, deps: &mut Vec<T>) {
+                    let check_ctx = CheckContext { budget: Budget::new(SUGGEST_BUDGET), ..CheckContext::default() };
+                    self.suggestions = Some((proofref, suggest(&self.prf, jr, &check_ctx, MAX_SUGGESTIONS)));
+                    ret = true;
+                }
+            }
+            ProofWidgetMsg::LineAction(LineActionKind::SetDependency { to, dep }, proofref) => {
+                if let Inr(Inl(jr)) = &proofref {
+                    let result = self.prf.try_with_mut_step(jr, |j| {
+                        fn set_dep_or_sdep<T: Ord>(dep: T, to: bool, deps: &mut Vec<T>) {
                             let mut dep_set: BTreeSet<T> = mem::take(deps).into_iter().collect();
-                            if dep_set.contains(&dep) {
-                                dep_set.remove(&dep);
-                            } else {
+                            if to {
                                 dep_set.insert(dep);
+                            } else {
+                                dep_set.remove(&dep);
                             }
                             deps.extend(dep_set);
                         }
                         match dep {
-                            Inl(lr) => toggle_dep_or_sdep(lr, &mut j.2),
-                            Inr(Inl(sr)) => toggle_dep_or_sdep(sr, &mut j.3),
+                            Inl(lr) => set_dep_or_sdep(lr, to, &mut j.2),
+                            Inr(Inl(sr)) => set_dep_or_sdep(sr, to, &mut j.3),
                             Inr(Inr(void)) => match void {},
                         }
                     });
+                    if let Err(err) = result {
+                        let names = LineNames::new(&self.prf);
+                        self.line_warnings.insert(proofref, err.describe(|r| names.line_or_subproof(r)));
+                    }
+                    self.verification_cache.get_mut().invalidate_for_change(&proofref, &self.prf);
                 }
                 ret = true;
             }
             ProofWidgetMsg::CallOnProof(f) => {
-                f(&self.prf);
+                // Pass along what's typed in lines that haven't been parsed, so it can be saved
+                let metadata = ProofMetaData { raw_inputs: self.pud.raw_inputs(&self.prf), ..self.metadata.clone() };
+                f(&self.prf, &metadata);
+            }
+            ProofWidgetMsg::DismissOpenWarnings => {
+                self.open_warnings.clear();
+                ret = true;
             }
+            ProofWidgetMsg::Undo => ret |= self.step_history(ctx, RecordingProof::undo_renaming),
+            ProofWidgetMsg::Redo => ret |= self.step_history(ctx, RecordingProof::redo_renaming),
+            // Keyboard shortcuts are for the proof that's shown
+            ProofWidgetMsg::Keypress(_) if !ctx.props().visible => {}
             ProofWidgetMsg::Keypress(key_event) => {
                 let msg = self.process_key_shortcut(key_event);
                 ret = Component::update(self, ctx, msg);
             }
+            ProofWidgetMsg::LineKeydown(r, key_event) => {
+                let msg = self.process_line_key(r, key_event);
+                ret = Component::update(self, ctx, msg);
+            }
+            ProofWidgetMsg::MarkSaved => self.set_dirty(ctx, false),
+            ProofWidgetMsg::RetryAsPremises => {
+                let text = String::from_utf8_lossy(ctx.props().data.as_deref().unwrap_or_default()).into_owned();
+                let (prf, pud) = proof_from_text(&text);
+                (self.prf, self.pud) = (RecordingProof::from_proof(prf).with_limit(UNDO_LIMIT), pud);
+                self.open_error = None;
+                self.verification_cache.get_mut().clear();
+                // The proof isn't the file anymore
+                self.set_dirty(ctx, true);
+                ret = true;
+            }
+            ProofWidgetMsg::CloseTab => {
+                if let Some(onclose) = &ctx.props().onclose {
+                    onclose.emit(());
+                }
+            }
+            ProofWidgetMsg::ToggleErrorDetails(line_ref) => {
+                if self.error_popover.as_ref().map(|popover| popover.line_ref == line_ref).unwrap_or(false) {
+                    self.error_popover = None;
+                } else {
+                    let callback = ctx.link().callback(|_| ProofWidgetMsg::CloseErrorDetails);
+                    let click_listener = EventListener::new(&document(), "click", move |_| callback.emit(()));
+                    self.error_popover = Some(ErrorPopover { line_ref, click_listener });
+                }
+                ret = true;
+            }
+            ProofWidgetMsg::OpenContextMenu(line_ref, mouse_event) => {
+                // Text fields keep the browser's menu, for copying and pasting
+                if mouse_event.target().and_then(|target| target.dyn_into::<web_sys::HtmlInputElement>().ok()).is_none() {
+                    mouse_event.prevent_default();
+                    let close = ctx.link().callback(|()| ProofWidgetMsg::CloseContextMenu);
+                    let close_on_escape = close.clone();
+                    let click_listener = EventListener::new(&document(), "click", move |_| close.emit(()));
+                    let key_listener = EventListener::new(&document(), "keydown", move |event: &Event| {
+                        if event.dyn_ref::<web_sys::KeyboardEvent>().map(|key_event| key_event.key() == "Escape").unwrap_or(false) {
+                            close_on_escape.emit(())
+                        }
+                    });
+                    let position = (mouse_event.client_x(), mouse_event.client_y());
+                    self.context_menu = Some(ContextMenu { line_ref, position, listeners: [click_listener, key_listener] });
+                    ret = true;
+                }
+            }
+            ProofWidgetMsg::CloseContextMenu => {
+                ret = self.context_menu.take().is_some();
+            }
+            ProofWidgetMsg::CheckProof => {
+                self.check_summary = Some(CheckSummary::new(&self.prf, &self.pud));
+                ret = true;
+            }
+            ProofWidgetMsg::Print => {
+                // The print dialog is opened once the read-only proof is rendered
+                self.printing = true;
+                ret = true;
+            }
+            ProofWidgetMsg::PrintDone => {
+                self.printing = false;
+                ret = true;
+            }
+            ProofWidgetMsg::Flash(lines) => {
+                self.scroll_to = self.pud.ref_to_line_depth.iter().find(|(_, (line, _))| line == lines.start()).map(|(r, _)| *r);
+                let link = ctx.link().clone();
+                self.flashed = Some((lines, Timeout::new(FLASH_MS, move || link.send_message(ProofWidgetMsg::FlashDone))));
+                ret = true;
+            }
+            ProofWidgetMsg::GoToLine(r) => {
+                self.select_line(ctx, r);
+                self.scroll_to = Some(r);
+                ret = true;
+            }
+            ProofWidgetMsg::DeleteSubproof(proofref) => {
+                let deletion = confirm_delete::subproof_deletion(&self.prf, &proofref, |r| self.pud.ref_to_line_depth.get(r).map(|(line, _)| *line));
+                match deletion {
+                    Some(deletion) if confirm_delete::should_confirm(&deletion, self.dont_ask_delete_subproof) => {
+                        self.pending_delete = Some((proofref, deletion));
+                        ret = true;
+                    }
+                    _ => ctx.link().send_message(ProofWidgetMsg::LineAction(LineActionKind::Delete { what: ProofItemKind::Subproof }, proofref)),
+                }
+            }
+            ProofWidgetMsg::ConfirmDeleteSubproof { dont_ask_again } => {
+                self.dont_ask_delete_subproof |= dont_ask_again;
+                if let Some((proofref, _)) = self.pending_delete.take() {
+                    ctx.link().send_message(ProofWidgetMsg::LineAction(LineActionKind::Delete { what: ProofItemKind::Subproof }, proofref));
+                }
+                ret = true;
+            }
+            ProofWidgetMsg::CancelDeleteSubproof => {
+                self.pending_delete = None;
+                ret = true;
+            }
+            ProofWidgetMsg::FlashDone => {
+                ret = self.flashed.take().is_some();
+            }
+            ProofWidgetMsg::SettingsChanged => {
+                // What's already typed is kept as it was written
+                self.debug_log.set_enabled(ctx.props().verbose && crate::settings::show_debug_log());
+                ret = true;
+            }
+            ProofWidgetMsg::ShowFeedback(r) => {
+                ret = self.pending_feedback.release(&r);
+            }
+            ProofWidgetMsg::CloseErrorDetails => {
+                ret = self.error_popover.take().is_some();
+            }
+            ProofWidgetMsg::ResetRuleFilter => {
+                self.rule_filter.clear();
+                self.rule_highlight = 0;
+                ret = true;
+            }
+            ProofWidgetMsg::RuleFilterChanged(filter) => {
+                self.rule_filter = filter;
+                self.rule_highlight = 0;
+                ret = true;
+            }
+            ProofWidgetMsg::RuleFilterKeydown(r, key_event) => {
+                let highlight = self.rule_highlight;
+                let msg = self.process_rule_filter_key(r, key_event);
+                ret = Component::update(self, ctx, msg) || self.rule_highlight != highlight;
+            }
         }
+        self.prf.end_group();
         if ret {
-            calculate_lineinfo::<P>(&mut self.pud.ref_to_line_depth, self.prf.top_level_proof(), &mut 1, &mut 0);
+            if renumbers_lines {
+                calculate_lineinfo::<P>(&mut self.pud.ref_to_line_depth, &self.prf);
+            }
+            self.goals = goal_entries(&self.prf, &self.metadata.goals);
+            // Nothing should leave a deleted line selected, but if something
+            // does, its keyboard shortcuts would act on the deleted line
+            if self.selected_line.is_some() && self.selected_ref().is_none() {
+                self.selected_line = None;
+            }
         }
         ret
     }
@@ -826,23 +2399,674 @@ impl Component for ProofWidget {
         true
     }
     fn view(&self, ctx: &Context<Self>) -> Html {
+        // Rendering checks every line, so a hidden proof isn't rendered at all
+        if !ctx.props().visible {
+            return html! {};
+        }
+        let symbols = crate::settings::symbols();
         let widget = match &self.open_error {
-            Some(err) => render_open_error(err),
-            None => self.render_proof(ctx, self.prf.top_level_proof(), None, &mut 1, &mut 0),
+            Some(problems) => render_open_error(ctx, problems),
+            None if self.printing => html! {
+                <div class="proof-print">
+                    { static_view::render_goals_static(&self.goals.iter().map(|entry| entry.goal.clone()).collect::<Vec<_>>(), symbols) }
+                    { static_view::render_proof_static(&self.prf, &self.pud, symbols) }
+                </div>
+            },
+            None => html! {
+                <>
+                    { self.render_undo_toolbar(ctx) }
+                    { render_open_warnings(ctx, &self.open_warnings) }
+                    { render_open_problems(&self.open_problems) }
+                    { self.render_check_summary(ctx) }
+                    { self.render_goal_panel(ctx) }
+                    { self.render_proof(ctx, self.prf.top_level_proof(), None, &mut 1, &mut 0, &HighlightSet::new(&self.prf, self.selected_ref())) }
+                    { self.render_context_menu(ctx) }
+                    { self.render_confirm_delete(ctx) }
+                </>
+            },
         };
-        html! {
-            <div>
-                { widget }
-                <div style="display: none">
+        let debug_log = if self.debug_log.is_enabled() {
+            html! {
+                <div class="debug-log">
                     <hr />
-                    <pre> { format!("{}\n{:#?}", self.prf, self.prf) } </pre>
+                    <pre> { self.prf.to_string() } </pre>
                     <hr />
-                    <pre> { self.preblob.clone() } </pre>
+                    <pre> { self.debug_log.text() } </pre>
+                    <hr />
+                    // The start of the file, to report bugs in opening it with
+                    <pre> { ctx.props().data.as_deref().map(|data| String::from_utf8_lossy(&data[..data.len().min(500)]).into_owned()).unwrap_or_default() } </pre>
                 </div>
+            }
+        } else {
+            html! {}
+        };
+        html! {
+            <div onkeydown={ ctx.link().callback(undo_shortcut) }>
+                { widget }
+                { debug_log }
             </div>
         }
     }
-    fn rendered(&mut self, _: &Context<Self>, _: bool) {
-        js_sys::eval("$('[data-submenu]').submenupicker(); $('[data-toggle=popover]').popover()").unwrap_throw();
+    fn rendered(&mut self, ctx: &Context<Self>, _: bool) {
+        js_sys::eval("$('[data-submenu]').submenupicker()").unwrap_throw();
+        // Printing blocks until the print dialog is closed
+        if self.printing {
+            if let Some(window) = web_sys::window() {
+                let _ = window.print();
+            }
+            ctx.link().send_message(ProofWidgetMsg::PrintDone);
+        }
+        // Only scroll as far as it takes to show the row, so that the rest of
+        // the proof stays where it was. A hidden proof scrolls once it's shown.
+        if !ctx.props().visible {
+            return;
+        }
+        if let Some(element) = self.scroll_to.take().and_then(|r| self.row_id(&r)).and_then(|id| document().get_element_by_id(&id)) {
+            let options = ScrollIntoViewOptions::new();
+            options.set_block(ScrollLogicalPosition::Nearest);
+            element.scroll_into_view_with_scroll_into_view_options(&options);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_edits_proof() {
+        let mut prf = P::new();
+        let r: PjRef<P> = Coproduct::inject(prf.add_premise(Expr::var("A")));
+        // Changes to lines make the proof dirty,
+        assert!(ProofWidgetMsg::LineChanged(r, "B".into()).edits_proof());
+        assert!(ProofWidgetMsg::LineAction(LineActionKind::Delete { what: ProofItemKind::Premise }, r).edits_proof());
+        assert!(ProofWidgetMsg::LineAction(LineActionKind::Move { direction: MoveDirection::Down }, r).edits_proof());
+        // but looking at them or saving them doesn't
+        assert!(!ProofWidgetMsg::LineAction(LineActionKind::Select, r).edits_proof());
+        assert!(!ProofWidgetMsg::CallOnProof(Box::new(|_, _| {})).edits_proof());
+        assert!(!ProofWidgetMsg::DismissOpenWarnings.edits_proof());
+        assert!(!ProofWidgetMsg::MarkSaved.edits_proof());
+    }
+
+    #[test]
+    fn test_renumbers_lines() {
+        let mut prf = P::new();
+        let r: PjRef<P> = Coproduct::inject(prf.add_premise(Expr::var("A")));
+        // Replacing the proof renumbers all of the lines,
+        assert!(ProofWidgetMsg::Undo.renumbers_lines());
+        assert!(ProofWidgetMsg::RetryAsPremises.renumbers_lines());
+        // inserting, deleting, and moving lines renumbers them as it goes,
+        assert!(!ProofWidgetMsg::LineAction(LineActionKind::Insert { what: ProofItemKind::Just, after: true, relative_to: ProofItemKind::Premise }, r).renumbers_lines());
+        assert!(!ProofWidgetMsg::LineAction(LineActionKind::Delete { what: ProofItemKind::Premise }, r).renumbers_lines());
+        assert!(!ProofWidgetMsg::LineAction(LineActionKind::Move { direction: MoveDirection::Up }, r).renumbers_lines());
+        // and editing or selecting lines leaves the numbers alone
+        assert!(!ProofWidgetMsg::LineChanged(r, "B".into()).renumbers_lines());
+        assert!(!ProofWidgetMsg::LineAction(LineActionKind::Select, r).renumbers_lines());
+    }
+
+    #[test]
+    fn test_line_actions_keep_line_numbers() {
+        use crate::util::lineinfo_matches;
+        use aris::parser::parse_unwrap as p;
+        /*
+        1 | A
+        2 | B
+          | ----------
+        3 | | C
+          | | ----------
+        4 | | C
+        5 | D
+        */
+        let mut prf = P::new();
+        let r1: PjRef<P> = Coproduct::inject(prf.add_premise(p("A")));
+        let r2: PjRef<P> = Coproduct::inject(prf.add_premise(p("B")));
+        let sr = prf.add_subproof();
+        let (r3, r4): (PjRef<P>, PjRef<P>) = prf.with_mut_subproof(&sr, |sub| (Coproduct::inject(sub.add_premise(p("C"))), Coproduct::inject(sub.add_step(Justification(p("C"), RuleM::Reit, vec![], vec![]))))).unwrap();
+        let r5: PjRef<P> = Coproduct::inject(prf.add_step(Justification(p("D"), RuleM::EmptyRule, vec![], vec![])));
+
+        // Every line action that inserts, deletes, or moves lines, from each
+        // kind of line it applies to
+        let mut actions = vec![];
+        for what in [ProofItemKind::Premise, ProofItemKind::Just, ProofItemKind::Subproof] {
+            for after in [false, true] {
+                actions.push((LineActionKind::Insert { what, after, relative_to: ProofItemKind::Premise }, r1));
+                actions.push((LineActionKind::Insert { what, after, relative_to: ProofItemKind::Premise }, r3));
+                actions.push((LineActionKind::Insert { what, after, relative_to: ProofItemKind::Just }, r5));
+                actions.push((LineActionKind::Insert { what, after, relative_to: ProofItemKind::Just }, r4));
+                actions.push((LineActionKind::Insert { what, after, relative_to: ProofItemKind::Subproof }, r4));
+            }
+        }
+        for r in [r1, r3, r4, r5] {
+            actions.push((LineActionKind::Delete { what: ProofItemKind::Premise }, r));
+            actions.push((LineActionKind::Delete { what: ProofItemKind::Just }, r));
+        }
+        actions.push((LineActionKind::Delete { what: ProofItemKind::Subproof }, r3));
+        for direction in [MoveDirection::Up, MoveDirection::Down] {
+            for r in [r1, r2, r4, r5] {
+                actions.push((LineActionKind::Move { direction }, r));
+            }
+        }
+
+        for (action, r) in actions {
+            let mut prf = RecordingProof::from_proof(prf.clone());
+            let mut pud = ProofUiData::from_proof(&prf);
+            match action.clone() {
+                LineActionKind::Insert { what, after, relative_to } => {
+                    insert_blank_line(&mut prf, &mut pud, what, after, relative_to, r);
+                }
+                LineActionKind::Delete { what } => {
+                    let _ = delete_lines(&mut prf, &mut pud, what, r);
+                }
+                LineActionKind::Move { direction } => {
+                    let _ = move_line_or_premise(&mut prf, &mut pud, direction, r);
+                }
+                _ => unreachable!(),
+            }
+            assert!(lineinfo_matches(&pud.ref_to_line_depth, &prf), "line numbers drifted from the proof after {action:?} on {r:?}");
+        }
+    }
+
+    #[test]
+    fn test_undo_edits() {
+        use crate::util::lineinfo_matches;
+        use aris::parser::parse_unwrap as p;
+        use aris::proofs::tree::TreeProof;
+
+        /// What the widget shows: the proof, and what's typed in each line, in order
+        fn rendered(prf: &P, pud: &ProofUiData<P>) -> (TreeProof, Vec<Option<String>>) {
+            let inputs = prf.iter_lines().filter_map(|info| info.reference.subset().ok()).map(|r: PjRef<P>| pud.ref_to_input.get(&r).cloned()).collect();
+            (TreeProof::from_proof(prf), inputs)
+        }
+        let mut prf = P::new();
+        let premise = prf.add_premise(p("A & B"));
+        prf.add_premise(p("C"));
+        let step: PjRef<P> = Coproduct::inject(prf.add_step(Justification(p("A"), RuleM::AndElim, vec![Coproduct::inject(premise)], vec![])));
+        let mut pud = ProofUiData::from_proof(&prf);
+        let mut prf = RecordingProof::from_proof(prf);
+        let original = rendered(&prf, &pud);
+
+        // Insert a subproof after the step, which takes several changes, as one edit
+        prf.start_group();
+        let inserted = insert_blank_line(&mut prf, &mut pud, ProofItemKind::Subproof, true, ProofItemKind::Just, step).unwrap();
+        prf.end_group();
+
+        // Type into its premise, which is one edit however many changes it
+        // takes, and leave something that doesn't parse in the step
+        prf.start_group();
+        for input in ["B &", "B & A"] {
+            pud.ref_to_input.insert(inserted, input.into());
+            if let Some(e) = aris::parser::parse(input) {
+                prf.with_mut_premise(inserted.get::<<P as Proof>::PremiseReference, _>().unwrap(), |x| *x = e);
+            }
+        }
+        prf.end_group();
+        pud.ref_to_input.insert(step, "A |".into());
+
+        // Delete the first premise
+        prf.start_group();
+        delete_lines(&mut prf, &mut pud, ProofItemKind::Premise, Coproduct::inject(premise)).unwrap();
+        prf.end_group();
+        let edited = rendered(&prf, &pud);
+        assert_ne!(edited, original);
+
+        // Undoing puts the premise back where it was, with its formula, and
+        // keeps what was typed in the lines it didn't change
+        let renames = undo_or_redo(&mut prf, &mut pud, Symbols::default(), RecordingProof::undo_renaming).unwrap();
+        let premise = renamed(&renames, Coproduct::inject(premise));
+        assert_eq!(pud.ref_to_input[&premise], "A ∧ B");
+        assert_eq!(pud.ref_to_input[&inserted], "B & A");
+        assert_eq!(pud.ref_to_input[&step], "A |");
+        assert!(undo_or_redo(&mut prf, &mut pud, Symbols::default(), RecordingProof::undo_renaming).is_some());
+        assert_eq!(pud.ref_to_input[&inserted], "");
+        assert!(undo_or_redo(&mut prf, &mut pud, Symbols::default(), RecordingProof::undo_renaming).is_some());
+        assert!(!prf.can_undo());
+        assert_eq!(TreeProof::from_proof(&prf), original.0);
+        assert!(lineinfo_matches(&pud.ref_to_line_depth, &prf));
+
+        // Redoing everything gets back to the edited proof
+        while undo_or_redo(&mut prf, &mut pud, Symbols::default(), RecordingProof::redo_renaming).is_some() {}
+        assert_eq!(TreeProof::from_proof(&prf), edited.0);
+        assert!(lineinfo_matches(&pud.ref_to_line_depth, &prf));
+    }
+
+    #[test]
+    fn test_open_garbage() {
+        // A file that isn't a proof shows where it couldn't be read, instead of panicking
+        let problems = open_proof(b"\x89PNG\r\n\x1a\n\x00\x00garbage").err().unwrap();
+        assert!(!problems.is_empty());
+        assert!(problems.iter().all(|problem| problem.position.as_deref().map_or(false, |position| position.starts_with("Line 1, column "))));
+
+        // and can be retried as text, with a premise for each of its lines
+        let (prf, pud) = proof_from_text("A & B\n\n  not a formula (\nC -> D\n");
+        let inputs = prf.premises().into_iter().map(|r| pud.ref_to_input[&Coproduct::inject(r)].clone()).collect::<Vec<_>>();
+        assert_eq!(inputs, vec!["A & B", "not a formula (", "C -> D"]);
+        assert_eq!(prf.lookup_premise(&prf.premises()[0]), aris::parser::parse("A & B"));
+
+        let (prf, _) = proof_from_text("");
+        assert_eq!(prf.premises().len(), 1);
+    }
+
+    #[test]
+    fn test_rule_menu_sections() {
+        let sections = rule_menu_sections();
+        // The sections follow the order the rule classes are declared in
+        let classes = sections.iter().map(|(rule_class, _)| *rule_class).collect::<Vec<_>>();
+        assert_eq!(classes, RuleClassification::iter().filter(|rule_class| classes.contains(rule_class)).collect::<Vec<_>>());
+        assert!(sections.iter().all(|(_, rules)| !rules.is_empty()));
+
+        // and every rule with a class is in exactly one of them
+        let listed = sections.iter().flat_map(|(_, rules)| rules.iter().map(|rule| rule.get_name())).collect::<Vec<_>>();
+        for rule in RuleM::ALL_RULES.iter().filter(|rule| !rule.get_classifications().is_empty()) {
+            assert_eq!(listed.iter().filter(|name| **name == rule.get_name()).count(), 1, "{} should be in the rule dropdown once", rule.get_name());
+        }
+        assert!(!listed.contains(&RuleM::EmptyRule.get_name()));
+    }
+
+    #[test]
+    fn test_filter_rule_menu() {
+        let sections = rule_menu_sections();
+        let names = |filter| filter_rule_menu(&sections, filter).into_iter().flat_map(|(_, rules)| rules).map(|rule| rule.get_name()).collect::<Vec<_>>();
+
+        // Nothing typed leaves every rule
+        assert_eq!(filter_rule_menu(&sections, ""), sections);
+        assert_eq!(filter_rule_menu(&sections, "  "), sections);
+
+        // Rules match by name, ignoring case,
+        assert_eq!(names("∧ intro"), vec![RuleM::AndIntro.get_name()]);
+        assert_eq!(names("∧ INTRO"), names("∧ intro"));
+        // by the name they're saved with,
+        assert!(names("conjunction").contains(&RuleM::AndIntro.get_name()));
+        // and by class, keeping only the sections with matches
+        let by_class = filter_rule_menu(&sections, "quantifier equiv");
+        assert_eq!(by_class.iter().map(|(rule_class, _)| *rule_class).collect::<Vec<_>>(), vec![RuleClassification::QuantifierEquivalence]);
+        assert_eq!(by_class[0].1, RuleClassification::QuantifierEquivalence.rules().collect::<Vec<_>>());
+
+        assert!(filter_rule_menu(&sections, "no such rule").is_empty());
+    }
+
+    #[test]
+    fn test_dep_choice() {
+        // Rules that take no dependencies don't offer any
+        assert_eq!(dep_choice(RuleM::ExcludedMiddle, RuleM::ExcludedMiddle.num_deps(), false, 0), DepChoice::Hidden);
+        assert_eq!(dep_choice(RuleM::AndElim, RuleM::AndElim.num_subdeps(), false, 0), DepChoice::Hidden);
+
+        // Rules that take some stop offering more once they have them all,
+        let takes = RuleM::ImpElim.num_deps();
+        assert_eq!(takes, Some(2));
+        assert_eq!(dep_choice(RuleM::ImpElim, takes, false, 0), DepChoice::Enabled);
+        assert_eq!(dep_choice(RuleM::ImpElim, takes, false, 1), DepChoice::Enabled);
+        assert_eq!(dep_choice(RuleM::ImpElim, takes, false, 2), DepChoice::Full(2));
+        // but the ones they have can still be unchecked
+        assert_eq!(dep_choice(RuleM::ImpElim, takes, true, 2), DepChoice::Enabled);
+
+        // Rules that take any number always offer more
+        assert_eq!(dep_choice(RuleM::AndIntro, RuleM::AndIntro.num_deps(), false, 10), DepChoice::Enabled);
+
+        // and steps without a rule yet offer them, but ask for a rule
+        assert_eq!(dep_choice(RuleM::EmptyRule, RuleM::EmptyRule.num_deps(), false, 0), DepChoice::NeedsRule);
+        assert_eq!(dep_choice(RuleM::EmptyRule, RuleM::EmptyRule.num_subdeps(), true, 3), DepChoice::NeedsRule);
+    }
+
+    #[test]
+    fn test_dep_click_action() {
+        let mut prf = P::new();
+        let r1: PjRef<P> = Coproduct::inject(prf.add_premise(Expr::var("A")));
+        let r2: PjRef<P> = Coproduct::inject(prf.add_step(Justification(Expr::var("A"), RuleM::Reit, vec![r1], vec![])));
+        let sr = prf.add_subproof();
+        let r3: PjRef<P> = Coproduct::inject(prf.with_mut_subproof(&sr, |sub| sub.add_premise(Expr::var("B"))).unwrap());
+        let r4: PjRef<P> = Coproduct::inject(prf.add_step(Justification(Expr::var("C"), RuleM::EmptyRule, vec![], vec![])));
+
+        // Clicking a dependency of the selected step removes it, and clicking
+        // another line or subproof adds it
+        assert_eq!(dep_click_action(&prf, Some(r2), Coproduct::inject(r1)), Some((LineActionKind::SetDependency { to: false, dep: Coproduct::inject(r1) }, r2)));
+        assert_eq!(dep_click_action(&prf, Some(r4), Coproduct::inject(r1)), Some((LineActionKind::SetDependency { to: true, dep: Coproduct::inject(r1) }, r4)));
+        assert_eq!(dep_click_action(&prf, Some(r4), Coproduct::inject(sr)), Some((LineActionKind::SetDependency { to: true, dep: Coproduct::inject(sr) }, r4)));
+
+        // Lines out of the step's scope, or after it, don't react
+        assert_eq!(dep_click_action(&prf, Some(r4), Coproduct::inject(r3)), None);
+        assert_eq!(dep_click_action(&prf, Some(r2), Coproduct::inject(r4)), None);
+        // and neither does anything when a premise or nothing is selected
+        assert_eq!(dep_click_action(&prf, Some(r1), Coproduct::inject(r1)), None);
+        assert_eq!(dep_click_action(&prf, None, Coproduct::inject(r1)), None);
+    }
+
+    #[test]
+    fn test_selection_after_delete() {
+        let mut prf = P::new();
+        let r1: PjRef<P> = Coproduct::inject(prf.add_premise(Expr::var("A")));
+        let r2: PjRef<P> = Coproduct::inject(prf.add_premise(Expr::var("B")));
+        let sr = prf.add_subproof();
+        let (r3, r4) = prf.with_mut_subproof(&sr, |sub| (Coproduct::inject(sub.add_premise(Expr::var("C"))), Coproduct::inject(sub.add_step(Justification(Expr::var("C"), RuleM::Reit, vec![], vec![]))))).unwrap();
+        let r5: PjRef<P> = Coproduct::inject(prf.add_step(Justification(Expr::var("D"), RuleM::EmptyRule, vec![], vec![])));
+        let r6: PjRef<P> = Coproduct::inject(prf.add_step(Justification(Expr::var("E"), RuleM::EmptyRule, vec![], vec![])));
+
+        // Delete `r`, or the subproof it's in, while `selected` is selected,
+        // as the widget does
+        let delete = |prf: &mut P, r: PjRef<P>, subproof: bool, selected: PjRef<P>| {
+            let lines = prf.iter_lines().filter_map(|info| info.reference.subset().ok()).collect::<Vec<PjRef<P>>>();
+            if subproof {
+                prf.delete_enclosing_subproof(&r).unwrap();
+            } else {
+                prf.delete_line(&r).unwrap();
+            }
+            selection_after_delete(&lines, selected, |r| prf.lookup_expr(r).is_some())
+        };
+
+        // Deleting another line keeps the selection
+        assert_eq!(delete(&mut prf, r6, false, r1), Some(r1));
+        // Deleting the selected line selects the one before it, even in a subproof
+        assert_eq!(delete(&mut prf, r5, false, r5), Some(r4));
+        // Deleting the subproof the selected line is in selects the line before it
+        assert_eq!(delete(&mut prf, r3, true, r4), Some(r2));
+        // Deleting the first line selects the one after it
+        assert_eq!(delete(&mut prf, r1, false, r1), Some(r2));
+        assert_eq!(prf.iter_lines().count(), 1);
+
+        // There's nothing to select once every line is gone
+        assert_eq!(selection_after_delete(&[r1, r2], r2, |_| false), None);
+    }
+
+    #[test]
+    fn test_row_dom_id() {
+        assert_eq!(row_dom_id("42", LineId(7)), "42line-row-7");
+        // Rows of different lines, or of the same line in different widgets,
+        // have different ids
+        assert_ne!(row_dom_id("42", LineId(7)), row_dom_id("42", LineId(8)));
+        assert_ne!(row_dom_id("1", LineId(27)), row_dom_id("12", LineId(7)));
+    }
+
+    #[test]
+    fn test_highlight_set() {
+        let mut prf = P::new();
+        let r1: PjRef<P> = Coproduct::inject(prf.add_premise(Expr::var("A")));
+        let r2: PjRef<P> = Coproduct::inject(prf.add_premise(Expr::var("B")));
+        let sr = prf.add_subproof();
+        let (r3, r4, r5) = prf
+            .with_mut_subproof(&sr, |sub| {
+                let r3 = sub.add_premise(Expr::var("C"));
+                let r4 = sub.add_step(Justification(Expr::var("A"), RuleM::Reit, vec![r1], vec![]));
+                let sr2 = sub.add_subproof();
+                let r5 = sub.with_mut_subproof(&sr2, |sub2| sub2.add_premise(Expr::var("D"))).unwrap();
+                (Coproduct::inject(r3), Coproduct::inject(r4), Coproduct::inject(r5))
+            })
+            .unwrap();
+        let r6: PjRef<P> = Coproduct::inject(prf.add_step(Justification(Expr::var("E"), RuleM::ImpIntro, vec![r2], vec![sr])));
+
+        // A step's line dependencies and every line of its subproof
+        // dependencies, even in nested subproofs, are tinted
+        let highlight = HighlightSet::new(&prf, Some(r6));
+        for r in [r2, r3, r4, r5] {
+            assert!(highlight.contains(&r));
+        }
+        assert!(!highlight.contains(&r1));
+        assert!(!highlight.contains(&r6));
+
+        let highlight = HighlightSet::new(&prf, Some(r4));
+        assert_eq!(highlight.lines, [r1].into_iter().collect());
+
+        // Premises and nothing being selected don't tint anything
+        assert_eq!(HighlightSet::new(&prf, Some(r1)), HighlightSet::default());
+        assert_eq!(HighlightSet::new(&prf, None), HighlightSet::default());
+    }
+
+    #[test]
+    fn test_describe_suggestion() {
+        let mut prf = P::new();
+        let r1: PjRef<P> = Coproduct::inject(prf.add_premise(Expr::var("A")));
+        let sr = prf.add_subproof();
+        prf.with_mut_subproof(&sr, |sub| sub.add_premise(Expr::var("B"))).unwrap();
+        let r3: PjRef<P> = Coproduct::inject(prf.add_step(Justification(Expr::var("C"), RuleM::EmptyRule, vec![], vec![])));
+        let mut pud = ProofUiData::from_proof(&prf);
+        calculate_lineinfo::<P>(&mut pud.ref_to_line_depth, &prf);
+
+        let describe = |rule, deps, sdeps| describe_suggestion(&prf, &pud, &Suggestion { rule, deps, sdeps });
+        assert_eq!(describe(RuleM::ImpElim, vec![r1, r3], vec![]), format!("{} using lines 1, 3", RuleM::ImpElim.get_name()));
+        assert_eq!(describe(RuleM::ImpIntro, vec![], vec![sr]), format!("{} using lines 2-2", RuleM::ImpIntro.get_name()));
+        assert_eq!(describe(RuleM::Reit, vec![r1], vec![]), format!("{} using line 1", RuleM::Reit.get_name()));
+        assert_eq!(describe(RuleM::TautIntro, vec![], vec![]), format!("{} with no dependencies", RuleM::TautIntro.get_name()));
+    }
+
+    #[test]
+    fn test_check_summary() {
+        let mut prf = P::new();
+        let r1: PjRef<P> = Coproduct::inject(prf.add_premise(Expr::var("A")));
+        prf.add_step(Justification(Expr::var("A"), RuleM::Reit, vec![r1], vec![]));
+        let r3: PjRef<P> = Coproduct::inject(prf.add_step(Justification(Expr::var("B"), RuleM::AndElim, vec![r1], vec![])));
+        let sr = prf.add_subproof();
+        let r4: PjRef<P> = Coproduct::inject(prf.with_mut_subproof(&sr, |sub| sub.add_premise(Expr::var("C"))).unwrap());
+        let r5: PjRef<P> = Coproduct::inject(prf.add_step(Justification(Expr::var("C"), RuleM::Reit, vec![r4], vec![])));
+        let mut pud = ProofUiData::from_proof(&prf);
+        calculate_lineinfo::<P>(&mut pud.ref_to_line_depth, &prf);
+
+        let summary = CheckSummary::new(&prf, &pud);
+        assert_eq!(summary.lines, 5);
+        assert!(!summary.stale);
+        // Citing a line inside a subproof is a problem whatever the rule is
+        let problems = summary.problems.iter().map(|(line, r, _)| (*line, *r)).collect::<Vec<_>>();
+        assert_eq!(problems, vec![(3, r3), (5, r5)]);
+        assert_eq!(summary.problems[1].2, "The dependency is not in scope for this step. (line 4)");
+
+        let mut prf = P::new();
+        prf.add_premise(Expr::var("A"));
+        let pud = ProofUiData::from_proof(&prf);
+        assert_eq!(CheckSummary::new(&prf, &pud), CheckSummary { lines: 1, problems: vec![], stale: false });
+    }
+
+    #[test]
+    fn test_goal_entries() {
+        use aris::parser::parse_unwrap as p;
+        let mut prf = P::new();
+        let r1: PjRef<P> = Coproduct::inject(prf.add_premise(p("A & B")));
+        let sr = prf.add_subproof();
+        prf.with_mut_subproof(&sr, |sub| sub.add_premise(p("C"))).unwrap();
+        let r3: PjRef<P> = Coproduct::inject(prf.add_step(Justification(p("A"), RuleM::AndElim, vec![r1], vec![])));
+        let r4: PjRef<P> = Coproduct::inject(prf.add_step(Justification(p("A | B"), RuleM::AndElim, vec![r1], vec![])));
+
+        let entries = goal_entries(&prf, &[p("A"), p("A | B"), p("C"), p("D"), p("A")]);
+        let statuses = entries.iter().map(|entry| entry.status.clone()).collect::<Vec<_>>();
+        assert_eq!(statuses, vec![GoalStatus::Reached(r3), GoalStatus::ReachedButUnverified(r4), GoalStatus::NotReached, GoalStatus::NotReached, GoalStatus::Reached(r3)]);
+
+        // Only a goal concluded inside a subproof gets the hint
+        let in_subproof = entries.iter().map(|entry| entry.in_subproof).collect::<Vec<_>>();
+        assert_eq!(in_subproof, vec![false, false, true, false, false]);
+
+        // Proofs without goals don't have any entries
+        assert_eq!(goal_entries(&prf, &[]), vec![]);
+    }
+
+    #[test]
+    fn test_error_details() {
+        let mut prf = P::new();
+        let r1: PjRef<P> = Coproduct::inject(prf.add_premise(Expr::var("A")));
+        let r2: PjRef<P> = Coproduct::inject(prf.add_premise(Expr::var("B")));
+        let sr = prf.add_subproof();
+        prf.with_mut_subproof(&sr, |sub| sub.add_premise(Expr::var("C"))).unwrap();
+        let r4: PjRef<P> = Coproduct::inject(prf.add_step(Justification(Expr::var("C"), RuleM::TautologicalConsequence, vec![r1, r2], vec![])));
+        let mut pud = ProofUiData::from_proof(&prf);
+        calculate_lineinfo::<P>(&mut pud.ref_to_line_depth, &prf);
+
+        // Dependencies are described by their line numbers
+        let details = error_details(&ProofCheckError::IncorrectDepCount(vec![r1, r2], 1), &prf, &pud);
+        assert_eq!(details.deps, vec!["line 1", "line 2"]);
+        let details = error_details(&ProofCheckError::ForwardReference { dep: Coproduct::inject(sr) }, &prf, &pud);
+        assert_eq!(details.deps, vec!["the subproof on lines 3-3"]);
+        assert!(!details.headline.contains("Inl"));
+
+        // and counterexamples are kept as assignments, for a table
+        let err = prf.verify_line(&r4).unwrap_err();
+        let details = error_details(&err, &prf, &pud);
+        let mut counterexample = details.counterexample;
+        counterexample.sort();
+        assert_eq!(counterexample, vec![("A".to_string(), true), ("B".to_string(), true), ("C".to_string(), false)]);
+        assert!(details.deps.is_empty());
+
+        let details = error_details(&ProofCheckError::Other("Something else".into()), &prf, &pud);
+        assert_eq!(details, ErrorDetails { headline: "Something else".into(), deps: vec![], counterexample: vec![] });
+    }
+
+    #[test]
+    fn test_pending_feedback() {
+        use std::cell::Cell;
+        use std::rc::Rc;
+
+        /// Counts how many timers were cancelled by being dropped
+        struct Timer(Rc<Cell<usize>>);
+        impl Drop for Timer {
+            fn drop(&mut self) {
+                self.0.set(self.0.get() + 1);
+            }
+        }
+
+        let mut prf = P::new();
+        let r1: PjRef<P> = Coproduct::inject(prf.add_premise(Expr::var("A")));
+        let r2: PjRef<P> = Coproduct::inject(prf.add_premise(Expr::var("B")));
+        let dropped = Rc::new(Cell::new(0));
+        let mut pending = PendingFeedback::default();
+        let mut verified = 0;
+        // Rendering checks a line only if its feedback isn't held back
+        let render = |pending: &PendingFeedback<Timer>, verified: &mut usize| {
+            if !pending.is_pending(&r1) {
+                *verified += 1;
+            }
+        };
+
+        // Typing in a line doesn't check it for each key,
+        for _ in 0.."A & (B".len() {
+            pending.edited(r1, Timer(dropped.clone()));
+            render(&pending, &mut verified);
+        }
+        assert_eq!(verified, 0);
+        // and each key replaces the timer of the key before it
+        assert_eq!(dropped.get(), "A & (B".len() - 1);
+
+        // Moving on to another line leaves the first one waiting on its timer
+        pending.edited(r2, Timer(dropped.clone()));
+        assert!(pending.release(&r2));
+        render(&pending, &mut verified);
+        assert_eq!(verified, 0);
+
+        // until it fires, when the line is checked once
+        assert!(pending.release(&r1));
+        render(&pending, &mut verified);
+        assert_eq!(verified, 1);
+        assert_eq!(dropped.get(), "A & (B".len() + 1);
+        assert!(!pending.release(&r1));
+    }
+
+    #[test]
+    fn test_scope_classes() {
+        // A proof with a subproof in it, which has a subproof in it
+        let mut prf = P::new();
+        prf.add_premise(Expr::var("A"));
+        let r2 = prf.add_premise(Expr::var("B"));
+        let sr1 = prf.add_subproof();
+        let (r3, r4) = prf
+            .with_mut_subproof(&sr1, |sub1| {
+                let r3 = sub1.add_premise(Expr::var("C"));
+                let sr2 = sub1.add_subproof();
+                let r4 = sub1
+                    .with_mut_subproof(&sr2, |sub2| {
+                        let r4 = sub2.add_premise(Expr::var("D"));
+                        sub2.add_step(Justification(Expr::var("D"), RuleM::Reit, vec![], vec![]));
+                        r4
+                    })
+                    .unwrap();
+                (r3, r4)
+            })
+            .unwrap();
+        prf.add_step(Justification(Expr::var("A"), RuleM::Reit, vec![], vec![]));
+
+        // Each line has a wrapper for each (sub)proof it's in, and the last
+        // premise of each (sub)proof has the bar under the premises
+        let last_premises: Vec<PjRef<P>> = vec![Coproduct::inject(r2), Coproduct::inject(r3), Coproduct::inject(r4)];
+        let rows = prf
+            .iter_lines()
+            .filter_map(|info| {
+                let r: PjRef<P> = info.reference.subset().ok()?;
+                let classes = scope_classes(info.depth, last_premises.contains(&r));
+                Some((classes.len(), classes.last().unwrap().ends_with("scope-premises-end")))
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(rows, vec![(1, false), (1, true), (2, true), (3, true), (3, false), (1, false)]);
+
+        assert_eq!(scope_classes(2, false), vec!["scope depth-0", "scope depth-1", "scope depth-2"]);
+    }
+}
+
+#[cfg(all(test, target_arch = "wasm32"))]
+mod wasm_tests {
+    use super::*;
+
+    use std::rc::Rc;
+
+    use wasm_bindgen_test::wasm_bindgen_test;
+    use wasm_bindgen_test::wasm_bindgen_test_configure;
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    #[wasm_bindgen_test]
+    async fn test_row_ids() {
+        // The widget sets up Bootstrap submenus after rendering, which aren't
+        // on the test page
+        js_sys::eval("window.$ = () => ({ submenupicker() {} })").unwrap_throw();
+        let root = document().create_element("div").unwrap_throw();
+        document().body().unwrap_throw().append_child(&root).unwrap_throw();
+        let scope = Rc::new(RefCell::new(None));
+        let oncreate = {
+            let scope = scope.clone();
+            Callback::from(move |link: Scope<ProofWidget>| *scope.borrow_mut() = Some(link))
+        };
+        yew::Renderer::<ProofWidget>::with_root_and_props(root, ProofWidgetProps { verbose: false, debug_log_limit: DEBUG_LOG_LIMIT, data: None, oncreate, ondirty: None, unsaved: false, onclose: None, visible: true }).render();
+        yew::platform::time::sleep(std::time::Duration::ZERO).await;
+
+        let scope = scope.borrow().clone().unwrap_throw();
+        let widget = scope.get_component().unwrap_throw();
+        let premise: PjRef<P> = Coproduct::inject(widget.prf.premises()[0]);
+        let id = row_dom_id(&widget.id, widget.prf.stable_id_of(&premise).unwrap_throw());
+        let row = document().get_element_by_id(&id).unwrap_throw();
+        assert_eq!(row.tag_name(), "TR");
+        assert_eq!(widget.row_id(&premise), Some(id));
+    }
+    #[wasm_bindgen_test]
+    async fn test_switching_tabs_keeps_proofs() {
+        use crate::components::tabbed_container::TabContent;
+        use crate::components::tabbed_container::TabId;
+        use crate::components::tabbed_container::TabbedContainer;
+        use crate::components::tabbed_container::TabbedContainerMsg;
+        use crate::components::tabbed_container::TabbedContainerProps;
+
+        js_sys::eval("window.$ = () => ({ submenupicker() {} })").unwrap_throw();
+        let root = document().create_element("div").unwrap_throw();
+        document().body().unwrap_throw().append_child(&root).unwrap_throw();
+        let widgets = Rc::new(RefCell::new(vec![]));
+        let content = || {
+            let widgets = widgets.clone();
+            let oncreate = Callback::from(move |link: Scope<ProofWidget>| widgets.borrow_mut().push(link));
+            TabContent::new(move |visible| html! { <ProofWidget verbose=false data={ None::<Vec<u8>> } oncreate={ oncreate.clone() } { visible } /> })
+        };
+        let container = Rc::new(RefCell::new(None));
+        let oncreate = {
+            let container = container.clone();
+            Callback::from(move |link: Scope<TabbedContainer>| *container.borrow_mut() = Some(link))
+        };
+        let tabs = vec![(TabId(0), "first".into(), content()), (TabId(1), "second".into(), content())];
+        yew::Renderer::<TabbedContainer>::with_root_and_props(root, TabbedContainerProps { tabs, oncreate, onclose: None }).render();
+        yew::platform::time::sleep(std::time::Duration::ZERO).await;
+
+        // Type in the first tab's proof, then switch away from it and back
+        let first = widgets.borrow()[0].clone();
+        let premise: PjRef<P> = Coproduct::inject(first.get_component().unwrap_throw().prf.premises()[0]);
+        first.send_message(ProofWidgetMsg::LineChanged(premise, "P -> Q".into()));
+        let container = container.borrow().clone().unwrap_throw();
+        for tab in [1, 0] {
+            container.send_message(TabbedContainerMsg::Switch(tab));
+            yew::platform::time::sleep(std::time::Duration::ZERO).await;
+        }
+
+        // The same widgets are still there, with what was typed
+        assert_eq!(widgets.borrow().len(), 2);
+        let widget = first.get_component().unwrap_throw();
+        assert_eq!(widget.prf.lookup_expr(&premise), aris::parser::parse("P -> Q"));
+        assert_eq!(widget.pud.ref_to_input[&premise], "P -> Q");
     }
 }
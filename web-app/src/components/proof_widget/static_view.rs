@@ -0,0 +1,124 @@
+//! Read-only rendering of a proof, for printing
+//!
+//! Formulas, rules, and citations are rendered as plain text, with none of
+//! the inputs and menus of the editable view.
+
+use super::render_scope_bars;
+use super::P;
+use crate::proof_ui_data::ProofUiData;
+
+use aris::expr::Expr;
+use aris::expr::Symbols;
+use aris::proofs::PjRef;
+use aris::proofs::Proof;
+use aris::rules::RuleT;
+
+use frunk_core::coproduct::Coproduct;
+use yew::prelude::*;
+
+/// A line of the proof as it's shown read-only
+#[derive(Debug, PartialEq, Eq)]
+struct StaticRow {
+    line: usize,
+    depth: usize,
+    /// Whether the line is the last premise of its (sub)proof, which draws the
+    /// bar under the premises
+    last_premise: bool,
+    /// What's typed in the line, as it's shown in the editable view
+    formula: String,
+    /// The name of the step's rule, which is blank for premises
+    rule: String,
+    /// The step's citations, separated by commas
+    citations: String,
+}
+
+/// The lines of `prf` as they're shown read-only, in display order
+fn static_rows(prf: &P, pud: &ProofUiData<P>, symbols: Symbols) -> Vec<StaticRow> {
+    let line_number = |r: &PjRef<P>| pud.ref_to_line_depth.get(r).map(|(line, _)| *line);
+    let infos = prf.iter_lines().collect::<Vec<_>>();
+    let is_premise = |i: usize| matches!(infos.get(i).map(|info| &info.reference), Some(Coproduct::Inl(_)));
+    infos
+        .iter()
+        .enumerate()
+        .filter_map(|(i, info)| {
+            let line = info.line?;
+            let r: PjRef<P> = info.reference.subset().ok()?;
+            let formula = pud.ref_to_input.get(&r).cloned().or_else(|| prf.lookup_expr(&r).map(|expr| expr.display_with(symbols))).unwrap_or_default();
+            let (rule, citations) = match r {
+                Coproduct::Inr(Coproduct::Inl(jr)) => match prf.lookup_step(&jr) {
+                    Some(just) => (just.1.get_name(), just.citations(prf, line_number).iter().map(ToString::to_string).collect::<Vec<_>>().join(", ")),
+                    None => (String::new(), String::new()),
+                },
+                _ => (String::new(), String::new()),
+            };
+            Some(StaticRow { line, depth: info.depth, last_premise: is_premise(i) && !is_premise(i + 1), formula, rule, citations })
+        })
+        .collect()
+}
+
+/// Render `prf` read-only, as a table of its lines with their formulas, rules,
+/// and citations as text
+pub fn render_proof_static(prf: &P, pud: &ProofUiData<P>, symbols: Symbols) -> Html {
+    let rows = static_rows(prf, pud, symbols)
+        .into_iter()
+        .map(|row| {
+            html! {
+                <tr>
+                    <td class="text-right"> { row.line } </td>
+                    <td class="scope-cell"> { render_scope_bars(row.depth, row.last_premise, html! { <span class="text-monospace"> { row.formula } </span> }) } </td>
+                    <td> { row.rule } </td>
+                    <td> { row.citations } </td>
+                </tr>
+            }
+        })
+        .collect::<Html>();
+    html! { <table class="proof-table proof-static"> { rows } </table> }
+}
+
+/// Render the goals of a proof read-only, as a list of their formulas
+pub fn render_goals_static(goals: &[Expr], symbols: Symbols) -> Html {
+    if goals.is_empty() {
+        return html! {};
+    }
+    let goals = goals.iter().map(|goal| html! { <li class="text-monospace"> { goal.display_with(symbols) } </li> }).collect::<Html>();
+    html! {
+        <div class="mb-3">
+            <h5> { "Goals" } </h5>
+            <ul> { goals } </ul>
+        </div>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use aris::parser::parse_unwrap as p;
+    use aris::proofs::Justification;
+    use aris::rules::RuleM;
+
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_static_rows() {
+        let mut prf = P::new();
+        let r1: PjRef<P> = Coproduct::inject(prf.add_premise(p("A -> B")));
+        let r2: PjRef<P> = Coproduct::inject(prf.add_premise(p("A")));
+        let sr = prf.add_subproof();
+        prf.with_mut_subproof(&sr, |sub| {
+            sub.add_premise(p("C"));
+            sub.add_step(Justification(p("A"), RuleM::Reit, vec![r2], vec![]));
+        })
+        .unwrap();
+        prf.add_step(Justification(p("B"), RuleM::ImpElim, vec![r1, r2], vec![]));
+        prf.add_step(Justification(p("C -> A"), RuleM::ImpIntro, vec![], vec![sr]));
+        let r6: PjRef<P> = Coproduct::inject(prf.add_step(Justification(p("A"), RuleM::EmptyRule, vec![], vec![])));
+        let mut pud = ProofUiData::from_proof_with_inputs(&prf, &HashMap::new(), Symbols::Ascii);
+        // A line that doesn't parse shows what's typed in it
+        pud.ref_to_input.insert(r6, "A &".into());
+
+        let rows = static_rows(&prf, &pud, Symbols::Ascii);
+        let row = |line, depth, last_premise, formula: &str, rule: &str, citations: &str| StaticRow { line, depth, last_premise, formula: formula.into(), rule: rule.into(), citations: citations.into() };
+        assert_eq!(rows, vec![row(1, 0, false, "(A -> B)", "", ""), row(2, 0, true, "A", "", ""), row(3, 1, true, "C", "", ""), row(4, 1, false, "A", &RuleM::Reit.get_name(), "2"), row(5, 0, false, "B", &RuleM::ImpElim.get_name(), "1, 2"), row(6, 0, false, "(C -> A)", &RuleM::ImpIntro.get_name(), "3-4"), row(7, 0, false, "A &", &RuleM::EmptyRule.get_name(), ""),]);
+    }
+}
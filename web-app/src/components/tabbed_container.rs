@@ -1,21 +1,129 @@
+use std::collections::HashSet;
+use std::fmt;
+use std::rc::Rc;
+
+use web_sys::HtmlInputElement;
 use yew::{html::Scope, prelude::*};
 
+/// Identifies a tab for as long as it's open, whatever it's named. `App`
+/// allocates them, in increasing order.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct TabId(pub usize);
+
+/// The contents of a tab, rendered given whether the tab is the current one.
+/// Every tab stays mounted, so that switching back to one finds it as it was
+/// left, but hidden ones should render as little as they can.
+#[derive(Clone)]
+pub struct TabContent(Rc<dyn Fn(bool) -> Html>);
+
+impl TabContent {
+    pub fn new(render: impl Fn(bool) -> Html + 'static) -> Self {
+        TabContent(Rc::new(render))
+    }
+
+    /// Render the contents, of the current tab if `visible`
+    pub fn render(&self, visible: bool) -> Html {
+        (self.0)(visible)
+    }
+}
+
+impl PartialEq for TabContent {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+}
+
 pub struct TabbedContainer {
-    tabs: Vec<(String, Html)>,
+    tabs: Vec<(TabId, String, TabContent)>,
     current_tab: usize,
+    /// The tab whose name is being edited, if any
+    renaming: Option<usize>,
+    /// Why the last name entered for a tab wasn't used
+    rename_error: Option<RenameError>,
+    rename_input: NodeRef,
+    /// The tabs with changes that haven't been saved, which are marked
+    unsaved: HashSet<TabId>,
 }
 
 pub enum TabbedContainerMsg {
     Switch(usize),
-    Create { name: String, content: Html },
-    GetCurrent(Box<dyn FnOnce(usize, String)>),
+    Create { id: TabId, name: String, content: TabContent },
+    GetCurrentTab(Box<dyn FnOnce(TabId, String)>),
+    GetTabs(Box<dyn FnOnce(Vec<(TabId, String)>)>),
+    CloseTab(usize),
+    CloseTabWithId(TabId),
+    StartRename(usize),
+    CancelRename,
+    RenameTab { idx: usize, name: String },
+    MarkUnsaved { id: TabId, unsaved: bool },
+}
+
+/// Why a tab can't be given a name
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RenameError {
+    Empty,
+    Duplicate(String),
+}
+
+impl fmt::Display for RenameError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RenameError::Empty => write!(f, "A tab's name can't be empty"),
+            RenameError::Duplicate(name) => write!(f, "There's already a tab named \"{name}\""),
+        }
+    }
 }
 
 #[derive(Clone, Properties, PartialEq)]
 pub struct TabbedContainerProps {
-    pub tab_ids: Vec<String>,
-    pub children: Children,
+    /// The ids, names, and contents of the tabs to start with
+    pub tabs: Vec<(TabId, String, TabContent)>,
     pub oncreate: Callback<Scope<TabbedContainer>>,
+    /// Callback to call with the id of a tab after it's closed
+    #[prop_or_default]
+    pub onclose: Option<Callback<TabId>>,
+}
+
+impl TabbedContainer {
+    /// Add a tab in front of the others, and switch to it
+    fn create_tab(&mut self, id: TabId, name: String, content: TabContent) {
+        self.tabs.insert(0, (id, name, content));
+        self.current_tab = 0;
+    }
+
+    /// The id and name of the current tab, if there are any tabs
+    fn current(&self) -> Option<(TabId, String)> {
+        self.tabs.get(self.current_tab).map(|(id, name, _)| (*id, name.clone()))
+    }
+
+    /// Remove a tab, returning its id. The tab before it is selected if it
+    /// was the current one, and the current one stays selected otherwise.
+    fn close_tab(&mut self, idx: usize) -> Option<TabId> {
+        if idx >= self.tabs.len() {
+            return None;
+        }
+        let (id, _, _) = self.tabs.remove(idx);
+        self.unsaved.remove(&id);
+        if idx < self.current_tab || (idx == self.current_tab && idx > 0) {
+            self.current_tab -= 1;
+        }
+        Some(id)
+    }
+
+    /// Give a tab a new name. Names are trimmed, and have to be unique.
+    fn rename_tab(&mut self, idx: usize, name: &str) -> Result<(), RenameError> {
+        let name = name.trim();
+        if name.is_empty() {
+            return Err(RenameError::Empty);
+        }
+        if self.tabs.iter().enumerate().any(|(i, (_, other, _))| i != idx && other == name) {
+            return Err(RenameError::Duplicate(name.into()));
+        }
+        if let Some((_, old, _)) = self.tabs.get_mut(idx) {
+            *old = name.into();
+        }
+        Ok(())
+    }
 }
 
 impl Component for TabbedContainer {
@@ -23,27 +131,73 @@ impl Component for TabbedContainer {
     type Properties = TabbedContainerProps;
 
     fn create(ctx: &Context<Self>) -> Self {
-        let tabs: Vec<(String, Html)> = ctx.props().tab_ids.iter().cloned().zip(ctx.props().children.iter()).collect();
         ctx.props().oncreate.emit(ctx.link().clone());
-        Self { tabs, current_tab: 0 }
+        Self { tabs: ctx.props().tabs.clone(), current_tab: 0, renaming: None, rename_error: None, rename_input: NodeRef::default(), unsaved: HashSet::new() }
     }
 
-    fn update(&mut self, _: &Context<Self>, msg: Self::Message) -> bool {
+    fn update(&mut self, ctx: &Context<Self>, msg: Self::Message) -> bool {
         match msg {
             TabbedContainerMsg::Switch(idx) => {
                 self.current_tab = idx;
                 true
             }
-            TabbedContainerMsg::Create { name, content } => {
-                self.tabs.insert(0, (name, content));
-                // Switch to new tab
-                self.current_tab = 0;
+            TabbedContainerMsg::Create { id, name, content } => {
+                self.create_tab(id, name, content);
                 true
             }
-            TabbedContainerMsg::GetCurrent(f) => {
-                f(self.current_tab, self.tabs[self.current_tab].0.clone());
+            TabbedContainerMsg::GetCurrentTab(f) => {
+                // There's no current tab after the last one is closed
+                if let Some((id, name)) = self.current() {
+                    f(id, name);
+                }
+                false
+            }
+            TabbedContainerMsg::GetTabs(f) => {
+                f(self.tabs.iter().map(|(id, name, _)| (*id, name.clone())).collect());
                 false
             }
+            TabbedContainerMsg::CloseTab(idx) => match self.close_tab(idx) {
+                Some(id) => {
+                    self.renaming = None;
+                    if let Some(onclose) = &ctx.props().onclose {
+                        onclose.emit(id);
+                    }
+                    true
+                }
+                None => false,
+            },
+            TabbedContainerMsg::CloseTabWithId(id) => match self.tabs.iter().position(|(tab_id, _, _)| *tab_id == id) {
+                Some(idx) => Component::update(self, ctx, TabbedContainerMsg::CloseTab(idx)),
+                None => false,
+            },
+            TabbedContainerMsg::StartRename(idx) => {
+                self.renaming = Some(idx);
+                self.rename_error = None;
+                true
+            }
+            TabbedContainerMsg::CancelRename => {
+                self.renaming = None;
+                self.rename_error = None;
+                true
+            }
+            TabbedContainerMsg::RenameTab { idx, name } => {
+                match self.rename_tab(idx, &name) {
+                    Ok(()) => {
+                        self.renaming = None;
+                        self.rename_error = None;
+                    }
+                    // Keep editing the name, so it can be fixed
+                    Err(err) => self.rename_error = Some(err),
+                }
+                true
+            }
+            TabbedContainerMsg::MarkUnsaved { id, unsaved } => {
+                if unsaved {
+                    self.unsaved.insert(id)
+                } else {
+                    self.unsaved.remove(&id)
+                }
+            }
         }
     }
 
@@ -54,28 +208,141 @@ impl Component for TabbedContainer {
     fn view(&self, ctx: &Context<Self>) -> Html {
         let mut tab_links = yew::virtual_dom::VList::new();
         let mut out = yew::virtual_dom::VList::new();
-        for (i, (name, data)) in self.tabs.iter().enumerate() {
+        for (i, (id, name, content)) in self.tabs.iter().enumerate() {
+            let unsaved = self.unsaved.contains(id);
             let onclick = ctx.link().callback(move |_| TabbedContainerMsg::Switch(i));
+            let onclose = ctx.link().batch_callback(move |e: MouseEvent| {
+                // Keep the tab's link from switching to the tab being closed
+                e.stop_propagation();
+                let confirmed = !unsaved || web_sys::window().and_then(|window| window.confirm_with_message("This proof has unsaved changes. Close it anyway?").ok()).unwrap_or(false);
+                confirmed.then_some(TabbedContainerMsg::CloseTab(i))
+            });
             let link_class = if i == self.current_tab { "nav-link active" } else { "nav-link" };
+            let label = if self.renaming == Some(i) {
+                let onkeydown = ctx.link().batch_callback(move |e: KeyboardEvent| match e.key().as_str() {
+                    "Enter" => Some(TabbedContainerMsg::RenameTab { idx: i, name: e.target_unchecked_into::<HtmlInputElement>().value() }),
+                    "Escape" => Some(TabbedContainerMsg::CancelRename),
+                    _ => None,
+                });
+                let onblur = ctx.link().callback(move |e: FocusEvent| TabbedContainerMsg::RenameTab { idx: i, name: e.target_unchecked_into::<HtmlInputElement>().value() });
+                html! {
+                    <input ref={ self.rename_input.clone() } type="text" class="form-control form-control-sm d-inline-block w-auto" value={ name.clone() } { onkeydown } { onblur } />
+                }
+            } else {
+                let ondblclick = ctx.link().callback(move |_| TabbedContainerMsg::StartRename(i));
+                html! { <span title="Double-click to rename" { ondblclick }> { name } </span> }
+            };
+            let unsaved_marker = if unsaved {
+                html! { <span class="ml-1" title="Unsaved changes"> { "●" } </span> }
+            } else {
+                html! {}
+            };
             tab_links.add_child(html! {
                 <li class="nav-item">
                     <a class={ link_class } href="#" onclick={ onclick }>
-                        { name }
+                        { label }
+                        { unsaved_marker }
+                        <span class="close ml-2" aria-label="Close tab" onclick={ onclose }>{ "×" }</span>
                     </a>
                 </li>
             });
-            if i == self.current_tab {
-                out.add_child(html! { <div> { data.clone() } </div> });
-            } else {
-                out.add_child(html! { <div style="display:none"> { data.clone() } </div> });
-            }
+            // Tabs are keyed by id, since new ones go in front, so that each
+            // keeps its contents mounted as it moves
+            let visible = i == self.current_tab;
+            let style = if visible { None } else { Some("display:none") };
+            out.add_child(html! { <div key={ id.0.to_string() } { style }> { content.render(visible) } </div> });
+        }
+
+        if self.tabs.is_empty() {
+            return html! {
+                <div class="text-center text-muted mt-5">
+                    <p> { "No proofs are open. Create a new one or open a file from the File menu." } </p>
+                </div>
+            };
         }
 
+        let rename_error = match &self.rename_error {
+            Some(err) => html! { <div class="alert alert-warning mt-2" role="alert"> { err } </div> },
+            None => html! {},
+        };
+
         html! {
             <div>
                 <ul class="nav nav-pills"> { tab_links } </ul>
+                { rename_error }
                 { out }
             </div>
         }
     }
+
+    fn rendered(&mut self, _: &Context<Self>, _: bool) {
+        if let Some(input) = self.rename_input.cast::<HtmlInputElement>() {
+            if self.rename_error.is_none() {
+                let _ = input.focus();
+                input.select();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn container(names: &[&str], current_tab: usize) -> TabbedContainer {
+        let tabs = names.iter().enumerate().map(|(i, name)| (TabId(i), name.to_string(), TabContent::new(|_| Html::default()))).collect();
+        TabbedContainer { tabs, current_tab, renaming: None, rename_error: None, rename_input: NodeRef::default(), unsaved: HashSet::new() }
+    }
+
+    fn names(container: &TabbedContainer) -> Vec<&str> {
+        container.tabs.iter().map(|(_, name, _)| &**name).collect()
+    }
+
+    #[test]
+    fn test_close_tab() {
+        // Closing the current tab selects the one before it
+        let mut tabs = container(&["a", "b", "c"], 1);
+        assert_eq!(tabs.close_tab(1), Some(TabId(1)));
+        assert_eq!((names(&tabs), tabs.current_tab), (vec!["a", "c"], 0));
+        // or the new first tab, if it was first
+        assert_eq!(tabs.close_tab(0), Some(TabId(0)));
+        assert_eq!((names(&tabs), tabs.current_tab), (vec!["c"], 0));
+
+        // Closing an earlier tab keeps the same tab selected
+        let mut tabs = container(&["a", "b", "c"], 2);
+        assert_eq!(tabs.close_tab(0), Some(TabId(0)));
+        assert_eq!((names(&tabs), tabs.current_tab), (vec!["b", "c"], 1));
+
+        // and so does closing a later one
+        let mut tabs = container(&["a", "b", "c"], 0);
+        assert_eq!(tabs.close_tab(2), Some(TabId(2)));
+        assert_eq!((names(&tabs), tabs.current_tab), (vec!["a", "b"], 0));
+
+        // Closing every tab leaves none, and there's nothing more to close
+        assert_eq!(tabs.close_tab(1), Some(TabId(1)));
+        assert_eq!(tabs.close_tab(0), Some(TabId(0)));
+        assert!(tabs.tabs.is_empty());
+        assert_eq!(tabs.current_tab, 0);
+        assert_eq!(tabs.close_tab(0), None);
+
+        // A closed tab's changes are forgotten with it
+        let mut tabs = container(&["a", "b"], 0);
+        tabs.unsaved.extend([TabId(0), TabId(1)]);
+        tabs.close_tab(1);
+        assert_eq!(tabs.unsaved, HashSet::from([TabId(0)]));
+    }
+
+    #[test]
+    fn test_rename_tab() {
+        let mut tabs = container(&["Untitled proof 1", "Untitled proof 2"], 0);
+        assert_eq!(tabs.rename_tab(1, " homework 3 "), Ok(()));
+        assert_eq!(names(&tabs), vec!["Untitled proof 1", "homework 3"]);
+        assert_eq!(tabs.rename_tab(1, "homework 3"), Ok(()));
+
+        // Names that are empty or are already used are rejected, leaving the tabs alone
+        assert_eq!(tabs.rename_tab(0, "  "), Err(RenameError::Empty));
+        assert_eq!(tabs.rename_tab(0, "homework 3"), Err(RenameError::Duplicate("homework 3".into())));
+        assert_eq!(RenameError::Duplicate("homework 3".into()).to_string(), "There's already a tab named \"homework 3\"");
+        assert_eq!(names(&tabs), vec!["Untitled proof 1", "homework 3"]);
+    }
 }
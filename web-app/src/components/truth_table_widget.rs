@@ -0,0 +1,144 @@
+use crate::components::expr_entry::ExprEntry;
+
+use aris::expr::Expr;
+use aris::expr::Symbols;
+use aris::truth_table::truth_table;
+use aris::truth_table::TruthTable;
+use aris::truth_table::TruthTableRow;
+use aris::truth_table::Verdict;
+
+use yew::prelude::*;
+
+/// The most rows of a truth table that are rendered. Tables of formulas with
+/// more atoms only show their first rows.
+const MAX_RENDERED_ROWS: usize = 256;
+
+/// How a truth value is shown in a table
+fn truth_value(value: bool) -> &'static str {
+    if value {
+        "T"
+    } else {
+        "F"
+    }
+}
+
+/// The cells of a row of a truth table: the atoms' values, then the formula's
+fn format_row(row: &TruthTableRow) -> Vec<&'static str> {
+    row.values.iter().chain([&row.result]).map(|value| truth_value(*value)).collect()
+}
+
+/// What's said about a formula under its truth table
+fn verdict_line(verdict: Verdict) -> &'static str {
+    match verdict {
+        Verdict::Tautology => "This formula is a tautology: it's true in every row.",
+        Verdict::Contingent => "This formula is contingent: it's true in some rows and false in others.",
+        Verdict::Contradiction => "This formula is a contradiction: it's false in every row.",
+    }
+}
+
+/// The note under a table that isn't rendered whole, if it isn't
+fn truncation_note(table: &TruthTable) -> Option<String> {
+    (table.rows.len() > MAX_RENDERED_ROWS).then(|| format!("Showing the first {MAX_RENDERED_ROWS} of {} rows.", table.rows.len()))
+}
+
+/// Render the truth table of `expr`, with the rows where it's false
+/// highlighted, or why it doesn't have one
+pub fn render_truth_table(expr: &Expr, symbols: Symbols) -> Html {
+    let table = match truth_table(expr) {
+        Ok(table) => table,
+        Err(err) => return html! { <div class="alert alert-warning"> { err.to_string() } </div> },
+    };
+    let header = table.atoms.iter().map(|atom| html! { <th> { atom } </th> }).collect::<Html>();
+    let rows = table
+        .rows
+        .iter()
+        .take(MAX_RENDERED_ROWS)
+        .map(|row| {
+            let class = if row.result { "" } else { "table-danger" };
+            let cells = format_row(row).into_iter().map(|cell| html! { <td> { cell } </td> }).collect::<Html>();
+            html! { <tr { class }> { cells } </tr> }
+        })
+        .collect::<Html>();
+    let note = match truncation_note(&table) {
+        Some(note) => html! { <p class="text-muted small"> { note } </p> },
+        None => html! {},
+    };
+    html! {
+        <div class="truth-table">
+            <table class="table table-sm table-bordered text-center w-auto">
+                <thead>
+                    <tr>
+                        { header }
+                        <th class="text-monospace"> { expr.display_with(symbols) } </th>
+                    </tr>
+                </thead>
+                <tbody> { rows } </tbody>
+            </table>
+            { note }
+            <p class="font-weight-bold"> { verdict_line(table.verdict()) } </p>
+        </div>
+    }
+}
+
+/// A tab for the truth table of a formula typed into it
+pub struct TruthTableWidget {
+    current_input: String,
+    current_expr: Option<Expr>,
+}
+
+#[derive(Clone, Properties, PartialEq)]
+pub struct TruthTableWidgetProps {
+    pub initial_contents: String,
+}
+
+impl Component for TruthTableWidget {
+    type Message = String;
+    type Properties = TruthTableWidgetProps;
+    fn create(ctx: &Context<Self>) -> Self {
+        let initial_contents = ctx.props().initial_contents.clone();
+        Self { current_expr: aris::parser::parse(&initial_contents), current_input: initial_contents }
+    }
+    fn update(&mut self, _: &Context<Self>, msg: Self::Message) -> bool {
+        self.current_expr = aris::parser::parse(&msg);
+        self.current_input = msg;
+        true
+    }
+    fn view(&self, ctx: &Context<Self>) -> Html {
+        let table = match &self.current_expr {
+            Some(expr) => render_truth_table(expr, crate::settings::symbols()),
+            None => html! { <div class="alert alert-danger"> { "Parse error" } </div> },
+        };
+        html! {
+            <div class="alert alert-primary m-4">
+                <h2> { "Enter Expression:" } </h2>
+                <ExprEntry
+                    oninput={ ctx.link().callback(|value| value) }
+                    init_value={ self.current_input.clone() }
+                    id=""/>
+                <hr />
+                { table }
+            </div>
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use aris::parser::parse_unwrap as p;
+    use aris::truth_table::MAX_ATOMS;
+
+    #[test]
+    fn test_table_helpers() {
+        let table = truth_table(&p("A -> B")).unwrap();
+        assert_eq!(table.rows.iter().map(format_row).collect::<Vec<_>>(), vec![vec!["T", "T", "T"], vec!["T", "F", "F"], vec!["F", "T", "T"], vec!["F", "F", "T"]]);
+        assert_eq!(verdict_line(table.verdict()), "This formula is contingent: it's true in some rows and false in others.");
+        assert_eq!(truncation_note(&table), None);
+
+        // Tables bigger than what's rendered say so
+        let many = (0..MAX_ATOMS).map(|i| format!("A{i}")).collect::<Vec<_>>().join(" | ");
+        let table = truth_table(&p(&many)).unwrap();
+        assert_eq!(truncation_note(&table), Some(format!("Showing the first {MAX_RENDERED_ROWS} of {} rows.", 1 << MAX_ATOMS)));
+    }
+}
@@ -0,0 +1,94 @@
+use std::collections::VecDeque;
+
+/// The last messages handled by a component, for debugging. Nothing is
+/// recorded, or even formatted, unless the log is being shown.
+pub struct DebugLog {
+    entries: VecDeque<String>,
+    /// The most messages that are kept
+    limit: usize,
+    enabled: bool,
+}
+
+impl DebugLog {
+    pub fn new(limit: usize, enabled: bool) -> Self {
+        DebugLog { entries: VecDeque::new(), limit, enabled }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Start or stop recording messages. What was recorded is forgotten when
+    /// it stops, since it isn't shown anymore.
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+        if !enabled {
+            self.entries = VecDeque::new();
+        }
+    }
+
+    /// Record the message made by `entry`, which is only called if the log is
+    /// enabled. The oldest message is forgotten past `limit`.
+    pub fn record(&mut self, entry: impl FnOnce() -> String) {
+        if !self.enabled || self.limit == 0 {
+            return;
+        }
+        if self.entries.len() == self.limit {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(entry());
+    }
+
+    /// The recorded messages, oldest first, one per line
+    pub fn text(&self) -> String {
+        self.entries.iter().flat_map(|entry| [entry.as_str(), "\n"]).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::cell::Cell;
+
+    #[test]
+    fn test_debug_log_keeps_latest() {
+        let mut log = DebugLog::new(3, true);
+        for i in 0..5 {
+            log.record(|| format!("message {i}"));
+        }
+        assert_eq!(log.text(), "message 2\nmessage 3\nmessage 4\n");
+
+        // Turning the log off forgets it, and turning it back on starts over
+        log.set_enabled(false);
+        assert_eq!(log.text(), "");
+        log.set_enabled(true);
+        log.record(|| "message 5".into());
+        assert_eq!(log.text(), "message 5\n");
+
+        let mut log = DebugLog::new(0, true);
+        log.record(|| "message".into());
+        assert_eq!(log.text(), "");
+    }
+
+    #[test]
+    fn test_disabled_debug_log_formats_nothing() {
+        let formatted = Cell::new(0);
+        let mut log = DebugLog::new(200, false);
+        for _ in 0..10 {
+            log.record(|| {
+                formatted.set(formatted.get() + 1);
+                "message".into()
+            });
+        }
+        assert_eq!(formatted.get(), 0);
+        assert_eq!(log.text(), "");
+
+        log.set_enabled(true);
+        log.record(|| {
+            formatted.set(formatted.get() + 1);
+            "message".into()
+        });
+        assert_eq!(formatted.get(), 1);
+    }
+}
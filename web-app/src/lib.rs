@@ -1,14 +1,19 @@
 #![recursion_limit = "1024"]
 
-mod box_chars;
+mod autosave;
 mod components;
+mod debug_log;
 mod proof_ui_data;
+mod settings;
+mod symbol_palette;
 mod util;
 
 use wasm_bindgen::prelude::*;
 
 #[wasm_bindgen(start)]
 pub fn run_app() -> Result<(), JsValue> {
+    // Before anything is rendered, so that it isn't shown in the wrong theme first
+    settings::load_theme();
     yew::Renderer::<components::app::App>::new().render();
     Ok(())
 }
@@ -1,13 +1,14 @@
 use crate::util::calculate_lineinfo;
 
-use aris::proofs::js_to_pjs;
+use aris::expr::Symbols;
+use aris::proofs::LineId;
 use aris::proofs::PjRef;
 use aris::proofs::Proof;
 
 use std::collections::HashMap;
+use std::mem;
 
-use frunk_core::coproduct::Coproduct;
-
+#[derive(Clone)]
 pub struct ProofUiData<P: Proof> {
     pub ref_to_line_depth: HashMap<PjRef<P>, (usize, usize)>,
     pub ref_to_input: HashMap<PjRef<P>, String>,
@@ -15,34 +16,162 @@ pub struct ProofUiData<P: Proof> {
 
 impl<P: Proof> ProofUiData<P> {
     pub fn from_proof(prf: &P) -> ProofUiData<P> {
+        ProofUiData::from_proof_with_inputs(prf, &HashMap::new(), Symbols::default())
+    }
+
+    /// Like `from_proof`, but with the inputs of the lines in `raw_inputs`,
+    /// by stable id, set to what was typed instead of their formulas, and the
+    /// others written with `symbols`
+    pub fn from_proof_with_inputs(prf: &P, raw_inputs: &HashMap<LineId, String>, symbols: Symbols) -> ProofUiData<P> {
         let mut ref_to_line_depth = HashMap::new();
-        calculate_lineinfo::<P>(&mut ref_to_line_depth, prf.top_level_proof(), &mut 1, &mut 0);
-        ProofUiData { ref_to_line_depth, ref_to_input: initialize_inputs(prf) }
+        calculate_lineinfo::<P>(&mut ref_to_line_depth, prf);
+        let mut ref_to_input = initialize_inputs(prf, symbols);
+        for (id, input) in raw_inputs {
+            if let Some(input_ref) = prf.ref_of_stable_id(*id).and_then(|r| ref_to_input.get_mut(&r)) {
+                *input_ref = input.clone();
+            }
+        }
+        ProofUiData { ref_to_line_depth, ref_to_input }
+    }
+
+    /// The inputs that differ from how their lines' formulas are written with
+    /// either kind of symbols, such as ones that don't parse, by stable id, to
+    /// be saved with the proof
+    pub fn raw_inputs(&self, prf: &P) -> HashMap<LineId, String> {
+        let is_formula = |r: &PjRef<P>, input: &str| prf.lookup_expr(r).map_or(false, |e| [Symbols::Unicode, Symbols::Ascii].into_iter().any(|symbols| e.display_with(symbols) == input));
+        self.ref_to_input.iter().filter(|(r, input)| !is_formula(r, input)).filter_map(|(r, input)| Some((prf.stable_id_of(r)?, input.clone()))).collect()
+    }
+
+    /// Forget all data about lines that were removed from the proof
+    pub fn remove_lines(&mut self, lines: &[PjRef<P>]) {
+        for line in lines {
+            self.ref_to_line_depth.remove(line);
+            self.ref_to_input.remove(line);
+        }
+    }
+
+    /// Replace the reference of every line, such as after the proof is
+    /// compacted, forgetting lines that `f` maps to `None`
+    pub fn remap_lines(&mut self, f: impl Fn(&PjRef<P>) -> Option<PjRef<P>>) {
+        self.ref_to_line_depth = mem::take(&mut self.ref_to_line_depth).into_iter().filter_map(|(r, v)| Some((f(&r)?, v))).collect();
+        self.ref_to_input = mem::take(&mut self.ref_to_input).into_iter().filter_map(|(r, v)| Some((f(&r)?, v))).collect();
     }
 }
 
-fn initialize_inputs<P: Proof>(prf: &P) -> HashMap<PjRef<P>, String> {
-    fn aux<P: Proof>(p: &<P as Proof>::Subproof, out: &mut HashMap<PjRef<P>, String>) {
-        use Coproduct::{Inl, Inr};
-        for line in p.premises().into_iter().map(Coproduct::inject).chain(p.lines().into_iter().map(js_to_pjs::<P>)) {
-            match line {
-                Inl(pr) => {
-                    if let Some(e) = p.lookup_expr(&Coproduct::inject(pr.clone())) {
-                        out.insert(Coproduct::inject(pr.clone()), format!("{e}"));
-                    }
-                }
-                Inr(Inl(jr)) => {
-                    if let Some(e) = p.lookup_expr(&Coproduct::inject(jr.clone())) {
-                        out.insert(Coproduct::inject(jr.clone()), format!("{e}"));
-                    }
-                }
-                Inr(Inr(Inl(sr))) => aux::<P>(&p.lookup_subproof(&sr).unwrap(), out),
-                Inr(Inr(Inr(void))) => match void {},
-            }
+fn initialize_inputs<P: Proof>(prf: &P, symbols: Symbols) -> HashMap<PjRef<P>, String> {
+    prf.iter_lines().filter_map(|info| info.reference.subset().ok()).filter_map(|r: PjRef<P>| Some((r.clone(), prf.lookup_expr(&r)?.display_with(symbols)))).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use aris::expr::Expr;
+    use aris::parser::parse_unwrap as p;
+    use aris::proofs::pooledproof::PooledProof;
+    use aris::proofs::Justification;
+    use aris::rules::RuleM;
+
+    use frunk_core::coproduct::Coproduct;
+    use frunk_core::HList;
+
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_remove_nested_subproof() {
+        type P = PooledProof<HList![Expr]>;
+        let mut prf = P::new();
+        prf.add_premise(p("A"));
+        let outer = prf.add_subproof();
+        let inner = prf
+            .with_mut_subproof(&outer, |sub| {
+                sub.add_premise(p("B"));
+                let inner = sub.add_subproof();
+                sub.with_mut_subproof(&inner, |sub| {
+                    let ip = sub.add_premise(p("C"));
+                    sub.add_step(Justification(p("C"), RuleM::Reit, vec![Coproduct::inject(ip)], vec![]));
+                });
+                sub.add_step(Justification(p("C -> C"), RuleM::ImpIntro, vec![], vec![inner]));
+                inner
+            })
+            .unwrap();
+        let mut pud = ProofUiData::from_proof(&prf);
+
+        let removed = prf.remove_subproof_cascade(&inner);
+        pud.remove_lines(&removed.lines);
+        assert_eq!(removed.lines.len(), 2);
+        for line in removed.lines.iter() {
+            assert!(!pud.ref_to_line_depth.contains_key(line));
+            assert!(!pud.ref_to_input.contains_key(line));
+        }
+        assert_eq!(pud.ref_to_input.len(), 3);
+        for jr in removed.affected {
+            assert!(prf.lookup_step(&jr).unwrap().3.is_empty());
         }
     }
 
-    let mut out = HashMap::new();
-    aux::<P>(prf.top_level_proof(), &mut out);
-    out
+    #[test]
+    fn test_remap_after_compact() {
+        type P = PooledProof<HList![Expr]>;
+        let mut prf = P::new();
+        let premise = prf.add_premise(p("A"));
+        let steps: Vec<_> = (0..1000).map(|_| prf.add_step(Justification(p("A"), RuleM::Reit, vec![Coproduct::inject(premise)], vec![]))).collect();
+        let mut pud = ProofUiData::from_proof(&prf);
+
+        let removed: Vec<PjRef<P>> = steps.iter().enumerate().filter(|(i, _)| i % 10 != 0).map(|(_, jr)| Coproduct::inject(*jr)).collect();
+        for r in removed.iter() {
+            prf.remove_line_cascade(r);
+        }
+        pud.remove_lines(&removed);
+        assert!(prf.memory_stats().tombstones() > 0);
+
+        let remap = prf.compact();
+        pud.remap_lines(|r| remap.line(r));
+        assert_eq!(prf.memory_stats().tombstones(), 0);
+        assert_eq!(prf.memory_stats().live_steps, 100);
+        let fresh = ProofUiData::from_proof(&prf);
+        // Line numbers are recalculated after every edit, so only the references need to match
+        let keys = |pud: &ProofUiData<P>| pud.ref_to_line_depth.keys().cloned().collect::<HashSet<_>>();
+        assert_eq!(keys(&pud), keys(&fresh));
+        assert_eq!(pud.ref_to_input, fresh.ref_to_input);
+        assert!(prf.verify_all().is_fully_verified());
+    }
+
+    #[test]
+    fn test_raw_inputs_roundtrip() {
+        use aris::proofs::xml_interop::{proof_from_xml, xml_from_proof_and_metadata_with_hash, ProofMetaData};
+        type P = PooledProof<HList![Expr]>;
+        let mut prf = P::new();
+        let premise = prf.add_premise(p("P"));
+        let step = prf.add_step(Justification(p("P"), RuleM::Reit, vec![Coproduct::inject(premise)], vec![]));
+        let mut pud = ProofUiData::from_proof(&prf);
+        // A half-typed line, which is still the formula it had before
+        pud.ref_to_input.insert(Coproduct::inject(step), "P -> (".into());
+        assert_eq!(pud.raw_inputs(&prf).len(), 1);
+
+        let metadata = ProofMetaData { raw_inputs: pud.raw_inputs(&prf), ..Default::default() };
+        let mut xml = vec![];
+        xml_from_proof_and_metadata_with_hash(&prf, &metadata, &mut xml).unwrap();
+        let (reloaded, reloaded_metadata, _) = proof_from_xml::<P, _>(&xml[..]).unwrap();
+        let reloaded_pud = ProofUiData::from_proof_with_inputs(&reloaded, &reloaded_metadata.raw_inputs, Symbols::Unicode);
+        let lines = reloaded.iter_lines().filter_map(|info| info.reference.subset().ok()).collect::<Vec<PjRef<P>>>();
+        assert_eq!(lines.iter().map(|r| reloaded_pud.ref_to_input[r].as_str()).collect::<Vec<_>>(), vec!["P", "P -> ("]);
+        assert_eq!(reloaded.lookup_expr(&lines[1]), Some(p("P")));
+    }
+
+    #[test]
+    fn test_ascii_inputs() {
+        type P = PooledProof<HList![Expr]>;
+        let mut prf = P::new();
+        let premise = prf.add_premise(p("A & B -> ~C"));
+        let r: PjRef<P> = Coproduct::inject(premise);
+        let mut pud = ProofUiData::from_proof_with_inputs(&prf, &HashMap::new(), Symbols::Ascii);
+        assert_eq!(pud.ref_to_input[&r], "((A & B) -> ~C)");
+        // Neither way of writing a formula is saved as a raw input
+        assert!(pud.raw_inputs(&prf).is_empty());
+        pud.ref_to_input.insert(r, "((A ∧ B) → ¬C)".into());
+        assert!(pud.raw_inputs(&prf).is_empty());
+        pud.ref_to_input.insert(r, "A & B -> ~C".into());
+        assert_eq!(pud.raw_inputs(&prf).len(), 1);
+    }
 }
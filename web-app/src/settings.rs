@@ -0,0 +1,217 @@
+//! Settings for the whole app, kept in the browser's `localStorage` so that
+//! they last between visits
+
+use aris::expr::Symbols;
+
+use gloo::events::EventListener;
+
+use std::cell::Cell;
+
+/// The key of the symbols setting in `localStorage`
+const SYMBOLS_KEY: &str = "aris-setting:symbols";
+
+/// The key of the debug log setting in `localStorage`
+const DEBUG_LOG_KEY: &str = "aris-setting:debug-log";
+
+/// The key of the theme in `localStorage`, which is only stored once one is
+/// chosen in the nav bar
+const THEME_KEY: &str = "aris-theme";
+
+/// The media query for whether the OS is in dark mode
+const PREFERS_DARK_QUERY: &str = "(prefers-color-scheme: dark)";
+
+thread_local! {
+    /// The symbols setting, once it's been read from `localStorage`
+    static SYMBOLS: Cell<Option<Symbols>> = Cell::new(None);
+    /// The debug log setting, once it's been read from `localStorage`
+    static DEBUG_LOG: Cell<Option<bool>> = Cell::new(None);
+}
+
+/// How a symbols setting is stored
+fn symbols_name(symbols: Symbols) -> &'static str {
+    match symbols {
+        Symbols::Unicode => "unicode",
+        Symbols::Ascii => "ascii",
+    }
+}
+
+/// The symbols setting stored as `name`, which is Unicode unless ASCII was
+/// chosen
+fn parse_symbols(name: Option<&str>) -> Symbols {
+    match name {
+        Some("ascii") => Symbols::Ascii,
+        _ => Symbols::Unicode,
+    }
+}
+
+fn local_storage() -> Option<web_sys::Storage> {
+    web_sys::window().and_then(|window| window.local_storage().ok().flatten())
+}
+
+/// The symbols to display formulas with. What's typed is parsed the same way
+/// whichever they are.
+pub fn symbols() -> Symbols {
+    SYMBOLS.with(|cell| match cell.get() {
+        Some(symbols) => symbols,
+        None => {
+            let stored = local_storage().and_then(|storage| storage.get_item(SYMBOLS_KEY).ok().flatten());
+            let symbols = parse_symbols(stored.as_deref());
+            cell.set(Some(symbols));
+            symbols
+        }
+    })
+}
+
+/// Display formulas with `symbols` from now on, and on later visits
+pub fn set_symbols(symbols: Symbols) {
+    SYMBOLS.with(|cell| cell.set(Some(symbols)));
+    if let Some(storage) = local_storage() {
+        // The setting still applies until the page is closed if it can't be stored
+        let _ = storage.set_item(SYMBOLS_KEY, symbols_name(symbols));
+    }
+}
+
+/// Whether proofs show a log of what was done to them, for reporting bugs.
+/// It's off unless it was turned on.
+pub fn show_debug_log() -> bool {
+    DEBUG_LOG.with(|cell| match cell.get() {
+        Some(show) => show,
+        None => {
+            let stored = local_storage().and_then(|storage| storage.get_item(DEBUG_LOG_KEY).ok().flatten());
+            let show = stored.as_deref() == Some("on");
+            cell.set(Some(show));
+            show
+        }
+    })
+}
+
+/// Show or hide the debug log from now on, and on later visits
+pub fn set_show_debug_log(show: bool) {
+    DEBUG_LOG.with(|cell| cell.set(Some(show)));
+    if let Some(storage) = local_storage() {
+        let _ = storage.set_item(DEBUG_LOG_KEY, if show { "on" } else { "off" });
+    }
+}
+
+/// The theme to start with, given the one stored if one was chosen, and
+/// whether the OS is in dark mode, if that's known. A chosen theme wins over
+/// the OS's.
+fn startup_theme(stored: Option<&str>, prefers_dark: Option<bool>) -> &'static str {
+    match (stored, prefers_dark) {
+        (Some("light"), _) => "light",
+        (Some("dark"), _) => "dark",
+        (_, Some(true)) => "dark",
+        _ => "light",
+    }
+}
+
+fn stored_theme() -> Option<String> {
+    local_storage().and_then(|storage| storage.get_item(THEME_KEY).ok().flatten())
+}
+
+fn prefers_dark_query() -> Option<web_sys::MediaQueryList> {
+    web_sys::window().and_then(|window| window.match_media(PREFERS_DARK_QUERY).ok().flatten())
+}
+
+/// Show the app with `theme`, by setting the `theme` attribute the style
+/// sheets look at
+fn apply_theme(theme: &str) {
+    if let Some(root) = web_sys::window().and_then(|window| window.document()).and_then(|document| document.document_element()) {
+        let _ = root.set_attribute("theme", theme);
+    }
+}
+
+/// Show the app with the theme chosen on an earlier visit, or the OS's if none
+/// was
+pub fn load_theme() {
+    let prefers_dark = prefers_dark_query().map(|query| query.matches());
+    apply_theme(startup_theme(stored_theme().as_deref(), prefers_dark));
+}
+
+/// Show the app with `theme` from now on, and on later visits, instead of
+/// following the OS's
+pub fn set_theme(theme: &str) {
+    apply_theme(theme);
+    if let Some(storage) = local_storage() {
+        let _ = storage.set_item(THEME_KEY, theme);
+    }
+}
+
+/// Switch themes when the OS switches between light and dark mode, unless a
+/// theme was chosen, calling `onchange` after switching. This stops when the
+/// listener is dropped.
+pub fn follow_system_theme(onchange: impl Fn() + 'static) -> Option<EventListener> {
+    let query = prefers_dark_query()?;
+    let target = query.clone();
+    Some(EventListener::new(&target, "change", move |_| {
+        if stored_theme().is_none() {
+            apply_theme(if query.matches() { "dark" } else { "light" });
+            onchange();
+        }
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_symbols_setting() {
+        for symbols in [Symbols::Unicode, Symbols::Ascii] {
+            assert_eq!(parse_symbols(Some(symbols_name(symbols))), symbols);
+        }
+        // Nothing stored, or something stored by another version, is the default
+        assert_eq!(parse_symbols(None), Symbols::Unicode);
+        assert_eq!(parse_symbols(Some("latex")), Symbols::Unicode);
+    }
+}
+
+#[cfg(all(test, target_arch = "wasm32"))]
+mod wasm_tests {
+    use super::*;
+
+    use wasm_bindgen_test::wasm_bindgen_test;
+    use wasm_bindgen_test::wasm_bindgen_test_configure;
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    fn theme_attribute() -> Option<String> {
+        web_sys::window()?.document()?.document_element()?.get_attribute("theme")
+    }
+
+    #[wasm_bindgen_test]
+    fn test_startup_theme() {
+        // A chosen theme wins over the OS's,
+        for prefers_dark in [None, Some(false), Some(true)] {
+            assert_eq!(startup_theme(Some("light"), prefers_dark), "light");
+            assert_eq!(startup_theme(Some("dark"), prefers_dark), "dark");
+        }
+        // which is followed if no theme, or one from another version, was chosen,
+        for stored in [None, Some("solarized")] {
+            assert_eq!(startup_theme(stored, Some(true)), "dark");
+            assert_eq!(startup_theme(stored, Some(false)), "light");
+            // and is light if it isn't known
+            assert_eq!(startup_theme(stored, None), "light");
+        }
+    }
+
+    #[wasm_bindgen_test]
+    fn test_load_theme() {
+        let storage = local_storage().unwrap();
+        let prefers_dark = prefers_dark_query().map(|query| query.matches());
+
+        // A theme chosen earlier is loaded, whatever the OS is in,
+        set_theme("dark");
+        apply_theme("light");
+        load_theme();
+        assert_eq!(theme_attribute().as_deref(), Some("dark"));
+        set_theme("light");
+        load_theme();
+        assert_eq!(theme_attribute().as_deref(), Some("light"));
+
+        // and the OS's otherwise
+        storage.remove_item(THEME_KEY).unwrap();
+        load_theme();
+        assert_eq!(theme_attribute().as_deref(), Some(if prefers_dark == Some(true) { "dark" } else { "light" }));
+    }
+}
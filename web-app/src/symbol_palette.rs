@@ -0,0 +1,232 @@
+//! Inserting logic symbols from the palette in the nav bar
+//!
+//! A symbol goes into the focused text field. Clicking a palette button can
+//! take the focus away from a proof line in some browsers, so if no text
+//! field is focused, it goes into the proof line that was focused last, where
+//! its cursor was.
+
+use std::cell::RefCell;
+
+use wasm_bindgen::JsCast;
+use web_sys::Element;
+use web_sys::Event;
+use web_sys::HtmlInputElement;
+use web_sys::HtmlTextAreaElement;
+
+thread_local! {
+    /// The element ID of the proof line that was focused last, with the
+    /// position of its cursor when it lost focus
+    static LAST_LINE_INPUT: RefCell<Option<(String, usize)>> = RefCell::new(None);
+}
+
+/// Insert `symbol` into `value` at the cursor position `caret`, returning the
+/// new value and the cursor position after the symbol. Positions are measured
+/// in characters, and one past the end is clamped to the end.
+fn insert_at_caret(value: &str, caret: usize, symbol: &str) -> (String, usize) {
+    let caret = caret.min(value.chars().count());
+    let byte_pos = value.char_indices().nth(caret).map_or(value.len(), |(i, _)| i);
+    let mut value = value.to_string();
+    value.insert_str(byte_pos, symbol);
+    (value, caret + symbol.chars().count())
+}
+
+/// Where a palette symbol is inserted
+#[derive(Debug, PartialEq, Eq)]
+enum PaletteTarget<F> {
+    /// The focused text field, at its cursor
+    Focused(F),
+    /// The proof line that was focused last, at the cursor it had
+    LastLine(F, usize),
+}
+
+/// Where to insert a palette symbol, given the focused text field, if any,
+/// and the proof line that was focused last, if it's still there
+fn palette_target<F>(focused: Option<F>, last_line: Option<(F, usize)>) -> Option<PaletteTarget<F>> {
+    match (focused, last_line) {
+        (Some(field), _) => Some(PaletteTarget::Focused(field)),
+        (None, Some((field, caret))) => Some(PaletteTarget::LastLine(field, caret)),
+        (None, None) => None,
+    }
+}
+
+/// A text field that symbols can be inserted into
+enum TextField {
+    Input(HtmlInputElement),
+    TextArea(HtmlTextAreaElement),
+}
+
+impl TextField {
+    /// The text field `elem` is, if it's one that can be typed into. Locked
+    /// lines and inputs like checkboxes aren't.
+    fn from_element(elem: Element) -> Option<Self> {
+        let field = match elem.dyn_into::<HtmlInputElement>() {
+            Ok(input) if matches!(input.type_().as_str(), "text" | "search") => TextField::Input(input),
+            Ok(_) => return None,
+            Err(elem) => TextField::TextArea(elem.dyn_into::<HtmlTextAreaElement>().ok()?),
+        };
+        let editable = match &field {
+            TextField::Input(input) => !input.disabled() && !input.read_only(),
+            TextField::TextArea(textarea) => !textarea.disabled() && !textarea.read_only(),
+        };
+        editable.then_some(field)
+    }
+
+    fn value(&self) -> String {
+        match self {
+            TextField::Input(input) => input.value(),
+            TextField::TextArea(textarea) => textarea.value(),
+        }
+    }
+
+    fn caret(&self) -> usize {
+        let caret = match self {
+            TextField::Input(input) => input.selection_start(),
+            TextField::TextArea(textarea) => textarea.selection_start(),
+        };
+        caret.ok().flatten().unwrap_or_default() as usize
+    }
+
+    fn element(&self) -> &web_sys::HtmlElement {
+        match self {
+            TextField::Input(input) => input,
+            TextField::TextArea(textarea) => textarea,
+        }
+    }
+
+    /// Replace the text, with the cursor at `caret`, and trigger the field's
+    /// `oninput` callback
+    fn set_value(&self, value: &str, caret: usize) {
+        let caret = caret as u32;
+        let set_selection = match self {
+            TextField::Input(input) => {
+                input.set_value(value);
+                input.set_selection_range(caret, caret)
+            }
+            TextField::TextArea(textarea) => {
+                textarea.set_value(value);
+                textarea.set_selection_range(caret, caret)
+            }
+        };
+        set_selection.expect("failed setting selection range");
+        self.element().dispatch_event(&Event::new("input").expect("failed creating input event")).expect("failed dispatching input event");
+    }
+}
+
+/// Remember that the proof line with element ID `id` lost focus with its
+/// cursor at `caret`, so that the palette can insert there
+pub fn remember_line_input(id: &str, caret: usize) {
+    LAST_LINE_INPUT.with(|last| *last.borrow_mut() = Some((id.to_string(), caret)));
+}
+
+/// Insert `symbol` at the cursor of the focused text field, or of the proof
+/// line that was focused last if none is. Returns whether it was inserted.
+pub fn insert_symbol(symbol: &str) -> bool {
+    let document = match web_sys::window().and_then(|window| window.document()) {
+        Some(document) => document,
+        None => return false,
+    };
+    let focused = document.active_element().and_then(TextField::from_element);
+    let last_line = LAST_LINE_INPUT.with(|last| last.borrow().clone()).and_then(|(id, caret)| Some((TextField::from_element(document.get_element_by_id(&id)?)?, caret)));
+    match palette_target(focused, last_line) {
+        Some(PaletteTarget::Focused(field)) => {
+            let (value, caret) = insert_at_caret(&field.value(), field.caret(), symbol);
+            field.set_value(&value, caret);
+        }
+        Some(PaletteTarget::LastLine(field, caret)) => {
+            let _ = field.element().focus();
+            let (value, caret) = insert_at_caret(&field.value(), caret, symbol);
+            field.set_value(&value, caret);
+            // In case focusing it didn't work, and it won't lose focus to remember this
+            remember_line_input(&field.element().id(), caret);
+        }
+        None => return false,
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_insert_at_caret() {
+        assert_eq!(insert_at_caret("", 0, "∧"), ("∧".to_string(), 1));
+        assert_eq!(insert_at_caret("A B", 1, "∧"), ("A∧ B".to_string(), 2));
+        // Positions are in characters, not bytes
+        assert_eq!(insert_at_caret("¬A → B", 2, "∨"), ("¬A∨ → B".to_string(), 3));
+        assert_eq!(insert_at_caret("¬A", 2, "→"), ("¬A→".to_string(), 3));
+        // A remembered cursor past the end of a line that got shorter appends
+        assert_eq!(insert_at_caret("A", 5, "⊥"), ("A⊥".to_string(), 2));
+    }
+
+    #[test]
+    fn test_palette_target() {
+        // The focused text field wins,
+        assert_eq!(palette_target(Some("focused"), Some(("line", 3))), Some(PaletteTarget::Focused("focused")));
+        assert_eq!(palette_target(Some("focused"), None), Some(PaletteTarget::Focused("focused")));
+        // then the last focused line, at its remembered cursor,
+        assert_eq!(palette_target(None, Some(("line", 3))), Some(PaletteTarget::LastLine("line", 3)));
+        // and otherwise there's nowhere to insert
+        assert_eq!(palette_target::<&str>(None, None), None);
+    }
+}
+
+#[cfg(all(test, target_arch = "wasm32"))]
+mod wasm_tests {
+    use super::*;
+
+    use std::cell::Cell;
+    use std::rc::Rc;
+    use wasm_bindgen::closure::Closure;
+    use wasm_bindgen_test::wasm_bindgen_test;
+    use wasm_bindgen_test::wasm_bindgen_test_configure;
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    /// Add a text field with `tag` to the page, counting its input events
+    fn add_field(tag: &str, id: &str, value: &str) -> (Element, Rc<Cell<usize>>) {
+        let document = web_sys::window().unwrap().document().unwrap();
+        let elem = document.create_element(tag).unwrap();
+        elem.set_id(id);
+        document.body().unwrap().append_child(&elem).unwrap();
+        match TextField::from_element(elem.clone()).unwrap() {
+            TextField::Input(input) => input.set_value(value),
+            TextField::TextArea(textarea) => textarea.set_value(value),
+        }
+        let inputs = Rc::new(Cell::new(0));
+        let counter = inputs.clone();
+        let oninput = Closure::<dyn Fn()>::new(move || counter.set(counter.get() + 1));
+        elem.add_event_listener_with_callback("input", oninput.as_ref().unchecked_ref()).unwrap();
+        oninput.forget();
+        (elem, inputs)
+    }
+
+    #[wasm_bindgen_test]
+    fn test_insert_symbol_into_last_line() {
+        let (line, inputs) = add_field("input", "palette-test-line", "A  B");
+        remember_line_input("palette-test-line", 2);
+        if let Some(active) = web_sys::window().unwrap().document().unwrap().active_element() {
+            active.unchecked_into::<web_sys::HtmlElement>().blur().unwrap();
+        }
+
+        assert!(insert_symbol("∧"));
+        let line = line.unchecked_into::<HtmlInputElement>();
+        assert_eq!(line.value(), "A ∧ B");
+        assert_eq!(inputs.get(), 1);
+        line.remove();
+    }
+
+    #[wasm_bindgen_test]
+    fn test_insert_symbol_into_textarea() {
+        let (textarea, inputs) = add_field("textarea", "palette-test-textarea", "P");
+        let textarea = textarea.unchecked_into::<HtmlTextAreaElement>();
+        textarea.focus().unwrap();
+        textarea.set_selection_range(0, 0).unwrap();
+
+        assert!(insert_symbol("¬"));
+        assert_eq!(textarea.value(), "¬P");
+        assert_eq!(textarea.selection_start().unwrap(), Some(1));
+        assert_eq!(inputs.get(), 1);
+        textarea.remove();
+    }
+}
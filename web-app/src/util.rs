@@ -1,34 +1,119 @@
 use aris::expr::Expr;
+use aris::proofs::json_interop::proof_from_json;
+use aris::proofs::json_interop::JsonError;
 use aris::proofs::pooledproof::PooledProof;
+use aris::proofs::xml_interop::xml_from_proof_and_metadata;
 use aris::proofs::PjRef;
 use aris::proofs::Proof;
 
 use std::collections::HashMap;
 
-use frunk_core::coproduct::Coproduct;
 use frunk_core::HList;
 
 // yew doesn't seem to allow Components to be generic over <P: Proof>, so fix a proof type P at the module level
 pub type P = PooledProof<HList![Expr]>;
 
-pub fn calculate_lineinfo<P: Proof>(output: &mut HashMap<PjRef<P>, (usize, usize)>, prf: &<P as Proof>::Subproof, line: &mut usize, depth: &mut usize) {
-    for prem in prf.premises() {
-        output.insert(Coproduct::inject(prem.clone()), (*line, *depth));
-        *line += 1;
+/// Convert a proof saved as JSON to XML, which is what `ProofWidget` opens
+pub fn xml_from_json(json: &str) -> Result<Vec<u8>, JsonError> {
+    let (prf, meta) = proof_from_json::<P>(json)?;
+    let mut data = vec![];
+    xml_from_proof_and_metadata(&prf, &meta, &mut data).expect("writing XML to memory doesn't fail");
+    Ok(data)
+}
+
+/// Record the line number and depth of every line in `prf`, as displayed in the UI
+pub fn calculate_lineinfo<P: Proof>(output: &mut HashMap<PjRef<P>, (usize, usize)>, prf: &P) {
+    for info in prf.iter_lines() {
+        if let (Ok(r), Some(line)) = (info.reference.subset(), info.line) {
+            output.insert(r, (line, info.depth));
+        }
     }
-    for lineref in prf.lines() {
-        use Coproduct::{Inl, Inr};
-        match lineref {
-            Inl(r) => {
-                output.insert(Coproduct::inject(r), (*line, *depth));
-                *line += 1;
-            }
-            Inr(Inl(sr)) => {
-                *depth += 1;
-                calculate_lineinfo::<P>(output, &prf.lookup_subproof(&sr).unwrap(), line, depth);
-                *depth -= 1;
+}
+
+/// Update `output`, as `calculate_lineinfo` records it, after lines were
+/// inserted into `prf`. Only the lines from the first new one on are
+/// renumbered.
+pub fn lineinfo_after_insert<P: Proof>(output: &mut HashMap<PjRef<P>, (usize, usize)>, prf: &P) {
+    let mut renumbering = false;
+    for info in prf.iter_lines() {
+        if let (Ok(r), Some(line)) = (info.reference.subset(), info.line) {
+            renumbering = renumbering || !output.contains_key(&r);
+            if renumbering {
+                output.insert(r, (line, info.depth));
             }
-            Inr(Inr(void)) => match void {},
         }
     }
 }
+
+/// Update `output`, as `calculate_lineinfo` records it, after the lines
+/// `removed` were deleted, forgetting them and moving each line after them up
+/// by how many were before it, without going through the proof
+pub fn lineinfo_after_delete<P: Proof>(output: &mut HashMap<PjRef<P>, (usize, usize)>, removed: &[PjRef<P>]) {
+    let mut gone = removed.iter().filter_map(|r| output.remove(r)).map(|(line, _)| line).collect::<Vec<_>>();
+    if gone.is_empty() {
+        return;
+    }
+    gone.sort_unstable();
+    for (line, _) in output.values_mut() {
+        *line -= gone.partition_point(|gone| gone < line);
+    }
+}
+
+/// Whether `output` is what `calculate_lineinfo` records for `prf`, for
+/// catching incremental updates that drift from it
+#[cfg(test)]
+pub fn lineinfo_matches<P: Proof>(output: &HashMap<PjRef<P>, (usize, usize)>, prf: &P) -> bool {
+    let mut fresh = HashMap::new();
+    calculate_lineinfo(&mut fresh, prf);
+    *output == fresh
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use aris::parser::parse_unwrap as p;
+    use aris::proofs::Justification;
+    use aris::rules::RuleM;
+
+    use frunk_core::coproduct::Coproduct;
+
+    #[test]
+    fn test_incremental_lineinfo() {
+        // A long proof with a subproof in the middle of it
+        let mut prf = P::new();
+        let premise = prf.add_premise(p("A"));
+        let steps = (0..500).map(|_| prf.add_step(Justification(p("A"), RuleM::Reit, vec![Coproduct::inject(premise)], vec![]))).collect::<Vec<_>>();
+        let sub = prf.add_subproof();
+        let (assumption, inner) = prf.with_mut_subproof(&sub, |sub| (sub.add_premise(p("B")), sub.add_step(Justification(p("B"), RuleM::EmptyRule, vec![], vec![])))).unwrap();
+        for _ in 0..500 {
+            prf.add_step(Justification(p("A"), RuleM::Reit, vec![Coproduct::inject(premise)], vec![]));
+        }
+        let mut lineinfo = HashMap::new();
+        calculate_lineinfo(&mut lineinfo, &prf);
+        assert_eq!(lineinfo.len(), 1003);
+
+        // Inserting steps into the middle, and into the subproof, renumbers
+        // the lines after them
+        let jsr = Coproduct::inject(steps[249]);
+        prf.add_step_relative(Justification(p("A"), RuleM::EmptyRule, vec![], vec![]), &jsr, true);
+        prf.with_mut_subproof(&sub, |sub| sub.prepend_step(Justification(p("C"), RuleM::EmptyRule, vec![], vec![])));
+        lineinfo_after_insert(&mut lineinfo, &prf);
+        assert!(lineinfo_matches(&lineinfo, &prf));
+        assert_eq!(lineinfo[&Coproduct::inject(inner)], (505, 1));
+
+        // and so does deleting lines, one at a time or a subproof at once
+        let r: PjRef<P> = Coproduct::inject(steps[10]);
+        prf.remove_line(&r);
+        lineinfo_after_delete(&mut lineinfo, &[r]);
+        assert!(lineinfo_matches(&lineinfo, &prf));
+        let removed = prf.delete_enclosing_subproof(&Coproduct::inject(assumption)).unwrap();
+        lineinfo_after_delete(&mut lineinfo, &removed.lines);
+        assert!(lineinfo_matches(&lineinfo, &prf));
+        assert_eq!(lineinfo.len(), 1000);
+
+        // Deleting a line that isn't numbered changes nothing
+        lineinfo_after_delete(&mut lineinfo, &[r]);
+        assert!(lineinfo_matches(&lineinfo, &prf));
+    }
+}